@@ -1,75 +1,255 @@
-use anyhow::Result;
+mod repl;
+mod session;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
 use lagrange_core::{
-    common::sign::{DefaultSignProvider, SignProvider},
+    common::{
+        sign::DefaultSignProvider,
+        BotContact, FriendMessageEvent, GroupMessageEvent, KeystoreUpdatedEvent, LoginState, MessageEvent,
+        QrCodeInfo, QrLoginState,
+    },
     config::BotConfig,
     protocol::Protocols,
-    BotContext,
+    BotContext, Error,
 };
+use qrcode::{render::unicode::Dense1x2, QrCode};
+use session::KeystoreFile;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{error, info, Level};
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-const BOT_PROTOCOL: Protocols = Protocols::Linux;
-const BOT_VERBOSE: bool = false;
-const AUTO_RECONNECT: bool = true;
-const AUTO_RELOGIN: bool = true;
-const LOG_LEVEL: Level = Level::TRACE;
 const COLORED_LOGS: bool = true;
 
+/// Development runner for lagrange-core
+#[derive(Debug, Parser)]
+#[command(name = "lagrange-runner", version)]
+struct Args {
+    /// Client to log in as: windows, macos, linux, android-phone, android-pad or android-watch;
+    /// defaults to the config file, then linux
+    #[arg(long, env = "LAGRANGE_PROTOCOL")]
+    protocol: Option<Protocols>,
+
+    /// Account to log in; picks the default keystore file
+    #[arg(long, env = "LAGRANGE_UIN")]
+    uin: Option<u64>,
+
+    /// Log in with a password, needs an Android protocol
+    #[arg(long, env = "LAGRANGE_PASSWORD", requires = "uin", conflicts_with = "qr", hide_env_values = true)]
+    password: Option<String>,
+
+    /// Log in by scanning a QR code, even if the keystore holds a session
+    #[arg(long)]
+    qr: bool,
+
+    /// Keystore JSON to resume the session from and save it to [default: keystore-<uin>.json]
+    #[arg(long, env = "LAGRANGE_KEYSTORE")]
+    keystore: Option<PathBuf>,
+
+    /// Lowest level logged unless RUST_LOG is set
+    #[arg(long, env = "LAGRANGE_LOG_LEVEL", default_value_t = Level::INFO)]
+    log_level: Level,
+
+    /// TOML file with a `BotConfig`; flags take precedence over it
+    #[arg(long, env = "LAGRANGE_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// URL of the sign server
+    #[arg(long, env = "LAGRANGE_SIGN_SERVER")]
+    sign_server: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LoginMode {
+    /// Resume the session in the keystore, falling back to a QR code
+    Auto,
+    QrCode,
+    Password { uin: u64, password: String },
+}
+
+/// Everything the runner needs from the command line, besides the log level
+#[derive(Debug)]
+struct RunnerSetup {
+    config: BotConfig,
+    login: LoginMode,
+    keystore_path: PathBuf,
+}
+
+impl Args {
+    /// Apply the flags on top of `config`, which is read from `--config` or the default
+    fn setup(self, mut config: BotConfig) -> Result<RunnerSetup> {
+        if let Some(protocol) = self.protocol {
+            config.protocol = protocol;
+        }
+        if let Some(url) = self.sign_server {
+            config.sign_provider = Some(Arc::new(DefaultSignProvider::with_url(url)));
+        }
+
+        let login = match (self.password, self.qr) {
+            (Some(password), false) => {
+                let uin = self.uin.context("--password needs --uin")?;
+                LoginMode::Password { uin, password }
+            }
+            (None, true) => LoginMode::QrCode,
+            (None, false) => LoginMode::Auto,
+            (Some(_), true) => bail!("--password and --qr cannot be combined"),
+        };
+
+        let keystore_path = self.keystore.unwrap_or_else(|| match self.uin {
+            Some(uin) => PathBuf::from(format!("keystore-{}.json", uin)),
+            None => PathBuf::from("keystore.json"),
+        });
+
+        Ok(RunnerSetup { config, login, keystore_path })
+    }
+}
+
+fn load_config(path: Option<&Path>) -> Result<BotConfig> {
+    let Some(path) = path else {
+        return Ok(BotConfig::default());
+    };
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("Invalid config {}", path.display()))
+}
+
+fn save_keystore(context: &BotContext, file: &KeystoreFile) {
+    let keystore = context.keystore().clone();
+    match file.save(&keystore) {
+        Ok(()) => info!("Keystore saved to {}", file.path().display()),
+        Err(err) => error!("Failed to save keystore: {:#}", err),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    setup_tracing()?;
+    let args = Args::parse();
+    setup_tracing(args.log_level)?;
 
     info!("Starting Lagrange Core Development Runner");
-    info!("Protocol: {:?}", BOT_PROTOCOL);
-    info!("Log Level: {:?}", LOG_LEVEL);
+    let uin = args.uin;
+    let config = load_config(args.config.as_deref())?;
+    let setup = args.setup(config)?;
+    info!("Protocol: {}", setup.config.protocol);
+    info!("Using sign provider: {}", setup.config.get_sign_provider().platform());
 
-    // Initialize sign provider
-    let sign_provider = Arc::new(DefaultSignProvider::new());
-    info!("Using sign provider: {}", sign_provider.platform());
-
-    let config = BotConfig::builder()
-        .protocol(BOT_PROTOCOL)
-        .verbose(BOT_VERBOSE)
-        .auto_reconnect(AUTO_RECONNECT)
-        .auto_re_login(AUTO_RELOGIN)
-        .sign_provider(sign_provider)
-        .build();
+    let keystore_file = KeystoreFile::new(setup.keystore_path);
+    let keystore = keystore_file.load_or_new(uin, setup.config.protocol)?;
 
     info!("Building bot context...");
     let context = BotContext::builder()
-        .config(config)
-        .build();
+        .config(setup.config)
+        .keystore(keystore)
+        .try_build()
+        .context("Failed to build the bot context")?;
+
+    setup_event_handlers(context.clone(), keystore_file.clone());
+
+    context.connect().await.context("Failed to establish initial connection")?;
+    context.start_connection_monitor();
+
+    login(&context, setup.login).await?;
+    save_keystore(&context, &keystore_file);
+    if let Err(err) = context.start_token_refresh() {
+        info!("Session tickets are not refreshed in the background: {}", err);
+    }
+
+    info!("Press Ctrl+C or enter `quit` to shutdown gracefully");
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => match result {
+            Ok(()) => info!("Received shutdown signal, cleaning up..."),
+            Err(err) => error!("Error listening for shutdown signal: {}", err),
+        },
+        () = repl::run(context.clone()) => info!("Quitting, cleaning up..."),
+    }
+    context.shutdown().await;
+    save_keystore(&context, &keystore_file);
 
-    setup_event_handlers(context.clone());
+    info!("Shutdown complete");
 
-    info!("Starting main event loop...");
-    info!("Press Ctrl+C to shutdown gracefully");
+    Ok(())
+}
 
-    context.connect().await.expect("Failed to establish initial connection");
-    context.clone().start_connection_monitor();
+async fn login(context: &Arc<BotContext>, mode: LoginMode) -> Result<()> {
+    match mode {
+        LoginMode::Auto => match context.login_by_token().await {
+            Ok(()) => info!("Session resumed from the keystore"),
+            Err(Error::LoginRequired(reason)) => {
+                info!("{}, logging in with a QR code", reason);
+                login_by_qrcode(context).await?;
+            }
+            Err(err) => {
+                warn!("Failed to resume the session: {}, logging in with a QR code", err);
+                login_by_qrcode(context).await?;
+            }
+        },
+        LoginMode::QrCode => login_by_qrcode(context).await?,
+        LoginMode::Password { uin, password } => {
+            let state = context.login_by_password(uin, &password, |state| async move { prompt(state).await }).await?;
+            if !state.is_success() {
+                bail!("Password login failed: {:?}", state);
+            }
+        }
+    }
+    info!("Logged in as {}", context.bot_uin().unwrap_or_default());
+    Ok(())
+}
 
-    let qrcode = context.fetch_qrcode().await?;
-    info!("QR Code URL: {}", qrcode.len());
+async fn login_by_qrcode(context: &Arc<BotContext>) -> Result<()> {
+    let (qrcode, mut states) = context.login_by_qrcode(true).await?;
+    save_qrcode(&qrcode);
 
-    match tokio::signal::ctrl_c().await {
-        Ok(()) => {
-            info!("Received shutdown signal, cleaning up...");
+    while let Some(state) = states.next().await {
+        match state {
+            QrLoginState::Expired(Some(qrcode)) => save_qrcode(&qrcode),
+            QrLoginState::LoggedIn => return Ok(()),
+            QrLoginState::Failed(state) => bail!("QR code login failed: {:?}", state),
+            state => info!("QR code login: {:?}", state),
         }
-        Err(err) => {
-            error!("Error listening for shutdown signal: {}", err);
+    }
+    bail!("QR code login ended without logging in")
+}
+
+fn save_qrcode(qrcode: &QrCodeInfo) {
+    info!("QR Code URL: {} (expires in {}s)", qrcode.url, qrcode.expires_in.as_secs());
+    match QrCode::new(&qrcode.url) {
+        Ok(code) => println!("{}", code.render::<Dense1x2>().quiet_zone(true).build()),
+        Err(err) => warn!("Failed to render the QR Code in the terminal: {}", err),
+    }
+    if !qrcode.image.is_empty() {
+        match std::fs::write("qrcode.png", &qrcode.image) {
+            Ok(()) => info!("QR Code saved to qrcode.png"),
+            Err(err) => error!("Failed to save QR Code: {}", err),
         }
     }
+}
 
-    info!("Shutdown complete");
+/// Ask on the terminal for what the password login needs, `None` gives up
+async fn prompt(state: LoginState) -> Option<String> {
+    let question = match &state {
+        LoginState::CaptchaRequired { url } => format!("Solve the captcha at {} and enter the ticket", url),
+        LoginState::SmsRequired { phone, message } => format!("{} ({}), enter the SMS code", message, phone),
+        state => {
+            warn!("Password login cannot continue: {:?}", state);
+            return None;
+        }
+    };
+    info!("{}", question);
 
-    Ok(())
+    let line = tokio::task::spawn_blocking(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| line)
+    })
+    .await
+    .ok()?
+    .ok()?;
+    Some(line.trim().to_string()).filter(|answer| !answer.is_empty())
 }
 
-fn setup_tracing() -> Result<()> {
+fn setup_tracing(level: Level) -> Result<()> {
     let env_filter =
         EnvFilter::try_from_default_env()
-            .unwrap_or_else(|_| EnvFilter::new(LOG_LEVEL.as_str()));
+            .unwrap_or_else(|_| EnvFilter::new(level.as_str()));
 
     let fmt_layer = fmt::layer()
         .with_target(true)
@@ -87,6 +267,79 @@ fn setup_tracing() -> Result<()> {
     Ok(())
 }
 
-fn setup_event_handlers(_context: Arc<BotContext>) {
+fn setup_event_handlers(context: Arc<BotContext>, keystore_file: KeystoreFile) {
+    context
+        .on_sync::<GroupMessageEvent, _>(|_, event| {
+            let sender = event.sender();
+            info!(group = event.group_uin, sender = sender.uin, "{}: {}", sender.display_name(), event.chain);
+        })
+        .detach();
+    context
+        .on_sync::<FriendMessageEvent, _>(|_, event| {
+            let sender = event.sender();
+            info!(sender = sender.uin, "{}: {}", sender.display_name(), event.chain);
+        })
+        .detach();
+    context
+        .on_sync::<KeystoreUpdatedEvent, _>(move |context, _| save_keystore(&context, &keystore_file))
+        .detach();
+
     info!("Event handlers ready (add custom handlers as needed)");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str], config: BotConfig) -> Result<RunnerSetup> {
+        let args = Args::try_parse_from(std::iter::once("lagrange-runner").chain(args.iter().copied()))?;
+        args.setup(config)
+    }
+
+    #[test]
+    fn test_args_to_setup() {
+        let setup = parse(&[], BotConfig::default()).unwrap();
+        assert_eq!(setup.config.protocol, Protocols::Linux);
+        assert_eq!(setup.login, LoginMode::Auto);
+        assert_eq!(setup.keystore_path, PathBuf::from("keystore.json"));
+        assert!(setup.config.sign_provider.is_none());
+
+        let setup = parse(
+            &["--protocol", "android-phone", "--uin", "10001", "--password", "secret", "--sign-server", "http://localhost:8080"],
+            BotConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(setup.config.protocol, Protocols::AndroidPhone);
+        assert_eq!(setup.login, LoginMode::Password { uin: 10001, password: "secret".to_string() });
+        assert_eq!(setup.keystore_path, PathBuf::from("keystore-10001.json"));
+        assert!(setup.config.sign_provider.is_some());
+
+        let setup = parse(&["--qr", "--keystore", "bot.json"], BotConfig::default()).unwrap();
+        assert_eq!(setup.login, LoginMode::QrCode);
+        assert_eq!(setup.keystore_path, PathBuf::from("bot.json"));
+    }
+
+    #[test]
+    fn test_flags_override_config_file() {
+        let config: BotConfig = toml::from_str("protocol = \"Windows\"\nauto_re_login = false\n").unwrap();
+
+        let setup = parse(&[], config.clone()).unwrap();
+        assert_eq!(setup.config.protocol, Protocols::Windows);
+        assert!(!setup.config.auto_re_login);
+
+        let setup = parse(&["--protocol", "macos"], config).unwrap();
+        assert_eq!(setup.config.protocol, Protocols::MacOs);
+        assert!(!setup.config.auto_re_login);
+
+        assert!(parse(&["--protocol", "ios"], BotConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_conflicting_logins() {
+        assert!(parse(&["--uin", "10001", "--password", "secret", "--qr"], BotConfig::default()).is_err());
+        assert!(parse(&["--password", "secret"], BotConfig::default()).is_err());
+
+        let args = Args::try_parse_from(["lagrange-runner", "--log-level", "debug"]).unwrap();
+        assert_eq!(args.log_level, Level::DEBUG);
+    }
+}
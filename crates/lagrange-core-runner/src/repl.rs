@@ -0,0 +1,303 @@
+use lagrange_core::{
+    common::{BotContact, BotFriend, BotGroup},
+    message::{MessageChain, MessageReceipt},
+    BotContext, Error,
+};
+use std::fmt::Write as _;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+const HELP: &str = "\
+Commands:
+  friends                  list friends
+  groups                   list groups
+  send <uin> <text>        send a friend message
+  gsend <group> <text>     send a group message
+  recall <group> <seq>     recall a group message
+  status                   show the bot status
+  help                     show this help
+  quit                     log out and exit";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Friends,
+    Groups,
+    Send { uin: u64, text: String },
+    GroupSend { group_uin: u64, text: String },
+    Recall { group_uin: u64, sequence: u32 },
+    Status,
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// Parse one input line, `Ok(None)` for an empty line
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(None);
+        }
+        let (name, rest) = split_word(line);
+
+        let command = match name {
+            "friends" => Command::Friends,
+            "groups" => Command::Groups,
+            "status" => Command::Status,
+            "help" | "?" => Command::Help,
+            "quit" | "exit" => Command::Quit,
+            "send" => {
+                let (uin, text) = target_and_text(rest, "send <uin> <text>")?;
+                Command::Send { uin, text }
+            }
+            "gsend" => {
+                let (group_uin, text) = target_and_text(rest, "gsend <group> <text>")?;
+                Command::GroupSend { group_uin, text }
+            }
+            "recall" => {
+                let (group, sequence) = split_word(rest);
+                match (group.parse(), sequence.parse()) {
+                    (Ok(group_uin), Ok(sequence)) => Command::Recall { group_uin, sequence },
+                    _ => return Err("Usage: recall <group> <seq>".to_string()),
+                }
+            }
+            name => return Err(format!("Unknown command `{}`, try `help`", name)),
+        };
+        Ok(Some(command))
+    }
+}
+
+/// The first word of `text` and the trimmed remainder
+fn split_word(text: &str) -> (&str, &str) {
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+fn target_and_text(rest: &str, usage: &str) -> Result<(u64, String), String> {
+    let (target, text) = split_word(rest);
+    match target.parse() {
+        Ok(target) if !text.is_empty() => Ok((target, text.to_string())),
+        _ => Err(format!("Usage: {}", usage)),
+    }
+}
+
+/// What the commands need from the bot
+pub trait ReplTarget {
+    async fn friends(&self) -> Result<Vec<Arc<BotFriend>>, Error>;
+    async fn groups(&self) -> Result<Vec<Arc<BotGroup>>, Error>;
+    async fn send_friend_message(&self, uin: u64, chain: MessageChain) -> Result<MessageReceipt, Error>;
+    async fn send_group_message(&self, group_uin: u64, chain: MessageChain) -> Result<MessageReceipt, Error>;
+    async fn recall_group_message(&self, group_uin: u64, sequence: u32) -> Result<(), Error>;
+    fn status(&self) -> String;
+}
+
+impl ReplTarget for Arc<BotContext> {
+    async fn friends(&self) -> Result<Vec<Arc<BotFriend>>, Error> {
+        BotContext::friends(self).await
+    }
+
+    async fn groups(&self) -> Result<Vec<Arc<BotGroup>>, Error> {
+        BotContext::groups(self).await
+    }
+
+    async fn send_friend_message(&self, uin: u64, chain: MessageChain) -> Result<MessageReceipt, Error> {
+        BotContext::send_friend_message(self, uin, chain).await
+    }
+
+    async fn send_group_message(&self, group_uin: u64, chain: MessageChain) -> Result<MessageReceipt, Error> {
+        BotContext::send_group_message(self, group_uin, chain).await
+    }
+
+    async fn recall_group_message(&self, group_uin: u64, sequence: u32) -> Result<(), Error> {
+        BotContext::recall_group_message(self, group_uin, sequence).await
+    }
+
+    fn status(&self) -> String {
+        let name = self.identity().map(|identity| identity.display_name()).unwrap_or_default();
+        format!(
+            "uin {} ({}), {}, {} friends and {} groups cached",
+            self.bot_uin().unwrap_or_default(),
+            name,
+            if self.is_online() { "online" } else { "offline" },
+            self.cache.friends().map_or(0, |friends| friends.len()),
+            self.cache.groups().map_or(0, |groups| groups.len()),
+        )
+    }
+}
+
+/// Run `command` and describe the outcome, `None` for [`Command::Quit`]
+pub async fn dispatch<T: ReplTarget>(target: &T, command: Command) -> Option<String> {
+    let output = match command {
+        Command::Friends => target.friends().await.map(|friends| {
+            let mut output = format!("{} friends", friends.len());
+            for friend in friends {
+                let _ = write!(output, "\n  {:>12}  {}", friend.uin, friend.display_name());
+            }
+            output
+        }),
+        Command::Groups => target.groups().await.map(|groups| {
+            let mut output = format!("{} groups", groups.len());
+            for group in groups {
+                let _ = write!(
+                    output,
+                    "\n  {:>12}  {} ({}/{})",
+                    group.group_uin, group.group_name, group.member_count, group.max_member
+                );
+            }
+            output
+        }),
+        Command::Send { uin, text } => target.send_friend_message(uin, MessageChain::text(text)).await.map(sent),
+        Command::GroupSend { group_uin, text } => {
+            target.send_group_message(group_uin, MessageChain::text(text)).await.map(sent)
+        }
+        Command::Recall { group_uin, sequence } => target
+            .recall_group_message(group_uin, sequence)
+            .await
+            .map(|()| format!("Recalled message {} in {}", sequence, group_uin)),
+        Command::Status => Ok(target.status()),
+        Command::Help => Ok(HELP.to_string()),
+        Command::Quit => return None,
+    };
+    Some(output.unwrap_or_else(|err| format!("Error: {}", err)))
+}
+
+fn sent(receipt: MessageReceipt) -> String {
+    format!("Sent message {}, sequence {}", receipt.id, receipt.sequence)
+}
+
+/// Read commands from stdin until `quit`; at the end of the input, never returns
+pub async fn run<T: ReplTarget>(target: T) {
+    println!("Type `help` for the list of commands");
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                tracing::info!("Input closed, press Ctrl+C to shutdown");
+                return std::future::pending().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to read from stdin: {}, press Ctrl+C to shutdown", err);
+                return std::future::pending().await;
+            }
+        };
+        let command = match Command::parse(&line) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(message) => {
+                println!("{}", message);
+                continue;
+            }
+        };
+        match dispatch(&target, command).await {
+            Some(output) => println!("{}", output),
+            None => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lagrange_core::message::{MessageId, MessageTarget};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_parse() {
+        let cases = [
+            ("friends", Command::Friends),
+            ("  groups  ", Command::Groups),
+            ("status", Command::Status),
+            ("help", Command::Help),
+            ("quit", Command::Quit),
+            ("exit", Command::Quit),
+            ("send 10001 hello  there", Command::Send { uin: 10001, text: "hello  there".to_string() }),
+            ("gsend 20002 hi", Command::GroupSend { group_uin: 20002, text: "hi".to_string() }),
+            ("recall 20002 42", Command::Recall { group_uin: 20002, sequence: 42 }),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(Command::parse(line), Ok(Some(expected)), "{line}");
+        }
+        assert_eq!(Command::parse("   "), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Command::parse("send 10001"), Err("Usage: send <uin> <text>".to_string()));
+        assert_eq!(Command::parse("send bob hi"), Err("Usage: send <uin> <text>".to_string()));
+        assert_eq!(Command::parse("gsend"), Err("Usage: gsend <group> <text>".to_string()));
+        assert_eq!(Command::parse("recall 20002"), Err("Usage: recall <group> <seq>".to_string()));
+        assert_eq!(Command::parse("recall 20002 -1"), Err("Usage: recall <group> <seq>".to_string()));
+        assert_eq!(Command::parse("poke 10001"), Err("Unknown command `poke`, try `help`".to_string()));
+    }
+
+    #[derive(Default)]
+    struct MockTarget {
+        sent: Mutex<Vec<(u64, String)>>,
+    }
+
+    impl ReplTarget for MockTarget {
+        async fn friends(&self) -> Result<Vec<Arc<BotFriend>>, Error> {
+            let friend = BotFriend {
+                uin: 10001,
+                uid: "u_synthetic".to_string(),
+                nickname: "Alice".to_string(),
+                age: 0,
+                gender: Default::default(),
+                remarks: String::new(),
+                personal_sign: String::new(),
+                qid: String::new(),
+                category: None,
+            };
+            Ok(vec![Arc::new(friend)])
+        }
+
+        async fn groups(&self) -> Result<Vec<Arc<BotGroup>>, Error> {
+            Err(Error::LoggedOut)
+        }
+
+        async fn send_friend_message(&self, uin: u64, chain: MessageChain) -> Result<MessageReceipt, Error> {
+            self.sent.lock().unwrap().push((uin, chain.to_string()));
+            let (id, target) = (MessageId(1), MessageTarget::Friend(uin));
+            Ok(MessageReceipt { id, target, sequence: 7, client_sequence: 1, random: 2, timestamp: 3 })
+        }
+
+        async fn send_group_message(&self, _: u64, _: MessageChain) -> Result<MessageReceipt, Error> {
+            unreachable!()
+        }
+
+        async fn recall_group_message(&self, group_uin: u64, sequence: u32) -> Result<(), Error> {
+            assert_eq!((group_uin, sequence), (20002, 42));
+            Ok(())
+        }
+
+        fn status(&self) -> String {
+            "online".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let target = MockTarget::default();
+
+        let output = dispatch(&target, Command::Friends).await.unwrap();
+        assert!(output.starts_with("1 friends\n"), "{output}");
+        assert!(output.contains("10001  Alice"), "{output}");
+
+        let output = dispatch(&target, Command::Send { uin: 10001, text: "hello".to_string() }).await;
+        assert_eq!(output.as_deref(), Some("Sent message 1, sequence 7"));
+        assert_eq!(target.sent.lock().unwrap().as_slice(), [(10001, "hello".to_string())]);
+
+        let output = dispatch(&target, Command::Recall { group_uin: 20002, sequence: 42 }).await;
+        assert_eq!(output.as_deref(), Some("Recalled message 42 in 20002"));
+
+        // Errors are reported, the loop goes on
+        let output = dispatch(&target, Command::Groups).await;
+        assert_eq!(output.as_deref(), Some("Error: Logged out"));
+
+        assert_eq!(dispatch(&target, Command::Status).await.as_deref(), Some("online"));
+        assert_eq!(dispatch(&target, Command::Quit).await, None);
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use lagrange_core::keystore::BotKeystore;
+use lagrange_core::protocol::Protocols;
+use std::path::{Path, PathBuf};
+
+/// The keystore JSON the runner resumes its session from
+#[derive(Debug, Clone)]
+pub struct KeystoreFile {
+    path: PathBuf,
+}
+
+impl KeystoreFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The stored keystore, `None` if there is no file yet
+    pub fn load(&self) -> Result<Option<BotKeystore>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).with_context(|| format!("Failed to read {}", self.path.display())),
+        };
+        let keystore = serde_json::from_str(&text).with_context(|| format!("Invalid keystore {}", self.path.display()))?;
+        Ok(Some(keystore))
+    }
+
+    /// The stored keystore for `uin`, or a new device for `protocol` if there is none or it belongs to
+    /// another account
+    pub fn load_or_new(&self, uin: Option<u64>, protocol: Protocols) -> Result<BotKeystore> {
+        match (self.load()?, uin) {
+            (Some(keystore), Some(uin)) if keystore.uin.is_some_and(|stored| stored != uin) => {
+                tracing::warn!(
+                    stored = keystore.uin,
+                    uin,
+                    "{} belongs to another account, starting a new session",
+                    self.path.display()
+                );
+                Ok(BotKeystore::for_protocol(protocol).with_uin(uin))
+            }
+            (Some(keystore), _) => {
+                tracing::info!(uin = keystore.uin, "Loaded keystore from {}", self.path.display());
+                Ok(keystore)
+            }
+            (None, uin) => {
+                tracing::info!("No keystore at {}, starting a new session", self.path.display());
+                let keystore = BotKeystore::for_protocol(protocol);
+                Ok(match uin {
+                    Some(uin) => keystore.with_uin(uin),
+                    None => keystore,
+                })
+            }
+        }
+    }
+
+    /// Write `keystore`, replacing the file only once it is completely written
+    pub fn save(&self, keystore: &BotKeystore) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(keystore)?;
+        let partial = self.path.with_extension("json.partial");
+        std::fs::write(&partial, json).with_context(|| format!("Failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, &self.path).with_context(|| format!("Failed to replace {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_keystore() -> BotKeystore {
+        let mut keystore = BotKeystore::new().with_uin(10001).with_uid("u_synthetic".to_string());
+        keystore.sigs.a2 = vec![0x01; 64];
+        keystore.sigs.d2 = vec![0x02; 64];
+        keystore.sigs.d2_key = vec![0x03; 16];
+        keystore
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = KeystoreFile::new(dir.path().join("sessions").join("keystore-10001.json"));
+        assert!(file.load().unwrap().is_none());
+
+        let keystore = session_keystore();
+        file.save(&keystore).unwrap();
+        let loaded = file.load().unwrap().unwrap();
+        assert_eq!(loaded.uin, Some(10001));
+        assert_eq!(loaded.uid.as_deref(), Some("u_synthetic"));
+        assert_eq!(loaded.guid, keystore.guid);
+        assert_eq!(loaded.sigs.a2, keystore.sigs.a2);
+        assert_eq!(loaded.sigs.d2, keystore.sigs.d2);
+        assert_eq!(loaded.sigs.d2_key, keystore.sigs.d2_key);
+
+        // Saving again replaces the file and leaves no partial file behind
+        file.save(&loaded).unwrap();
+        let names: Vec<_> = std::fs::read_dir(dir.path().join("sessions")).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(names, ["keystore-10001.json"]);
+    }
+
+    #[test]
+    fn test_load_or_new() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = KeystoreFile::new(dir.path().join("keystore.json"));
+
+        let fresh = file.load_or_new(Some(10001), Protocols::Linux).unwrap();
+        assert_eq!(fresh.uin, Some(10001));
+        assert!(fresh.sigs.d2.is_empty());
+        assert!(file.load_or_new(None, Protocols::AndroidPhone).unwrap().validate(Protocols::AndroidPhone).is_ok());
+
+        file.save(&session_keystore()).unwrap();
+        assert_eq!(file.load_or_new(None, Protocols::Linux).unwrap().sigs.d2, vec![0x02; 64]);
+        assert_eq!(file.load_or_new(Some(10001), Protocols::Linux).unwrap().sigs.d2, vec![0x02; 64]);
+
+        // The session of another account is not resumed
+        let other = file.load_or_new(Some(10002), Protocols::Linux).unwrap();
+        assert_eq!(other.uin, Some(10002));
+        assert!(other.sigs.d2.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keystore.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let err = KeystoreFile::new(&path).load().unwrap_err();
+        assert!(err.to_string().contains("Invalid keystore"), "{err}");
+    }
+}
@@ -0,0 +1,808 @@
+//! Events and message chains as JSON, for dashboards and bridges to other languages.
+//!
+//! The types here mirror the events of [`crate::common`] and the [`MessageChain`] with a shape
+//! of their own, so that changes to the Rust types do not leak into the JSON. Within a
+//! [`BRIDGE_VERSION`] fields and event kinds are only ever added; consumers should ignore
+//! what they do not know. Renamed or removed fields bump the version.
+//!
+//! Bytes are base64 encoded (standard alphabet, padded), entities are tagged by `type` and
+//! durations are given in whole seconds.
+
+use crate::common::{
+    EssenceMessageEvent, FriendInfoChangedEvent, FriendInfoField, FriendMessageEvent, FriendRequestEvent,
+    FriendTypingEvent, GrayTipEvent, GroupJoinRequestEvent, GroupMemberDecreaseEvent, GroupMemberDecreaseKind,
+    GroupMemberIncreaseEvent, GroupMemberIncreaseKind, GroupMessageEvent, GroupMuteEvent, GroupReactionEvent,
+    MessageRecallEvent, PokeEvent, TempMessageEvent,
+};
+use crate::keystore::BotKeystore;
+use crate::message::{
+    FileEntity, ImageEntity, MarketFaceEntity, MediaSource, MessageChain, MessageEntity, RecordEntity, ReplyEntity,
+    VideoEntity,
+};
+use crate::protocol::EventMessage;
+use crate::BotContext;
+use base64::Engine;
+use serde::{Serialize, Serializer};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use tokio::sync::broadcast;
+
+/// Version of the JSON shape, sent with every [`Envelope`]
+pub const BRIDGE_VERSION: u32 = 1;
+
+fn base64<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(data))
+}
+
+fn base64_opt<S: Serializer>(data: &Option<&[u8]>, serializer: S) -> Result<S::Ok, S::Error> {
+    match data {
+        Some(data) => base64(data, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// An event with what a consumer needs to route it
+#[derive(Debug, Clone, Serialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    /// Which event this is, like `group_message`; names the shape of `event`
+    pub kind: &'static str,
+    /// The bot that received the event, `None` before its first login
+    pub bot_uin: Option<u64>,
+    pub event: T,
+}
+
+/// A [`MessageChain`] as a JSON array of its entities
+#[derive(Debug, Clone, Serialize)]
+#[serde(transparent)]
+pub struct ChainDto<'a>(pub Vec<EntityDto<'a>>);
+
+impl<'a> From<&'a MessageChain> for ChainDto<'a> {
+    fn from(chain: &'a MessageChain) -> Self {
+        Self(chain.entities().iter().map(EntityDto::from).collect())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntityDto<'a> {
+    Text { text: &'a str },
+    At { uin: u64, display: &'a str },
+    AtAll,
+    Face { id: u32 },
+    Image(ImageDto<'a>),
+    Reply(ReplyDto<'a>),
+    Record(RecordDto<'a>),
+    Video(VideoDto<'a>),
+    MarketFace(MarketFaceDto<'a>),
+    File(FileDto<'a>),
+    Json { payload: &'a str },
+    Xml { payload: &'a str },
+    Forward { res_id: &'a str },
+    Raw {
+        #[serde(serialize_with = "base64")]
+        data: &'a [u8],
+    },
+}
+
+impl<'a> From<&'a MessageEntity> for EntityDto<'a> {
+    fn from(entity: &'a MessageEntity) -> Self {
+        match entity {
+            MessageEntity::Text { text } => Self::Text { text },
+            MessageEntity::At { uin, display } => Self::At { uin: *uin, display },
+            MessageEntity::AtAll => Self::AtAll,
+            MessageEntity::Face { id } => Self::Face { id: *id },
+            MessageEntity::Image(image) => Self::Image(image.into()),
+            MessageEntity::Reply(reply) => Self::Reply(reply.into()),
+            MessageEntity::Record(record) => Self::Record(record.into()),
+            MessageEntity::Video(video) => Self::Video(video.into()),
+            MessageEntity::MarketFace(face) => Self::MarketFace(face.into()),
+            MessageEntity::File(file) => Self::File(file.into()),
+            MessageEntity::Json { payload } => Self::Json { payload },
+            MessageEntity::Xml { payload } => Self::Xml { payload },
+            MessageEntity::Forward { res_id } => Self::Forward { res_id },
+            MessageEntity::Raw(raw) => Self::Raw { data: &raw.data },
+        }
+    }
+}
+
+/// Content of media that is not uploaded yet
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceDto<'a> {
+    Bytes(#[serde(serialize_with = "base64")] &'a [u8]),
+    Path(&'a std::path::Path),
+}
+
+impl<'a> From<&'a MediaSource> for SourceDto<'a> {
+    fn from(source: &'a MediaSource) -> Self {
+        match source {
+            MediaSource::Bytes(data) => Self::Bytes(data),
+            MediaSource::Path(path) => Self::Path(path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageDto<'a> {
+    pub file_name: &'a str,
+    #[serde(serialize_with = "base64")]
+    pub md5: &'a [u8],
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub url: Option<&'a str>,
+    pub file_id: Option<&'a str>,
+    #[serde(serialize_with = "base64_opt")]
+    pub msg_info: Option<&'a [u8]>,
+    pub is_group: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceDto<'a>>,
+}
+
+impl<'a> From<&'a ImageEntity> for ImageDto<'a> {
+    fn from(image: &'a ImageEntity) -> Self {
+        Self {
+            file_name: &image.file_name,
+            md5: &image.md5,
+            size: image.size,
+            width: image.width,
+            height: image.height,
+            url: image.url.as_deref(),
+            file_id: image.file_id.as_deref(),
+            msg_info: image.msg_info.as_deref(),
+            is_group: image.is_group,
+            source: image.source.as_ref().map(SourceDto::from),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplyDto<'a> {
+    pub sequence: u32,
+    pub sender_uin: u64,
+    pub time: u32,
+    pub source: Vec<EntityDto<'a>>,
+    /// [`MessageId`](crate::message::MessageId) of a message of the bot, only set on outgoing quotes
+    pub message_id: Option<u64>,
+}
+
+impl<'a> From<&'a ReplyEntity> for ReplyDto<'a> {
+    fn from(reply: &'a ReplyEntity) -> Self {
+        Self {
+            sequence: reply.sequence,
+            sender_uin: reply.sender_uin,
+            time: reply.time,
+            source: reply.source.iter().map(EntityDto::from).collect(),
+            message_id: reply.message_id.map(|id| id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordDto<'a> {
+    pub file_name: &'a str,
+    #[serde(serialize_with = "base64")]
+    pub md5: &'a [u8],
+    pub size: u32,
+    pub duration: u32,
+    pub file_id: Option<&'a str>,
+    #[serde(serialize_with = "base64_opt")]
+    pub msg_info: Option<&'a [u8]>,
+    pub is_group: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceDto<'a>>,
+}
+
+impl<'a> From<&'a RecordEntity> for RecordDto<'a> {
+    fn from(record: &'a RecordEntity) -> Self {
+        Self {
+            file_name: &record.file_name,
+            md5: &record.md5,
+            size: record.size,
+            duration: record.duration,
+            file_id: record.file_id.as_deref(),
+            msg_info: record.msg_info.as_deref(),
+            is_group: record.is_group,
+            source: record.source.as_ref().map(SourceDto::from),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoDto<'a> {
+    pub file_name: &'a str,
+    #[serde(serialize_with = "base64")]
+    pub md5: &'a [u8],
+    pub size: u32,
+    pub duration: u32,
+    pub width: u32,
+    pub height: u32,
+    pub file_id: Option<&'a str>,
+    #[serde(serialize_with = "base64_opt")]
+    pub msg_info: Option<&'a [u8]>,
+    pub is_group: bool,
+}
+
+impl<'a> From<&'a VideoEntity> for VideoDto<'a> {
+    fn from(video: &'a VideoEntity) -> Self {
+        Self {
+            file_name: &video.file_name,
+            md5: &video.md5,
+            size: video.size,
+            duration: video.duration,
+            width: video.width,
+            height: video.height,
+            file_id: video.file_id.as_deref(),
+            msg_info: video.msg_info.as_deref(),
+            is_group: video.is_group,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketFaceDto<'a> {
+    pub face_id: &'a str,
+    pub tab_id: u32,
+    pub key: &'a str,
+    pub summary: &'a str,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl<'a> From<&'a MarketFaceEntity> for MarketFaceDto<'a> {
+    fn from(face: &'a MarketFaceEntity) -> Self {
+        Self {
+            face_id: &face.face_id,
+            tab_id: face.tab_id,
+            key: &face.key,
+            summary: &face.summary,
+            width: face.width,
+            height: face.height,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDto<'a> {
+    pub file_id: &'a str,
+    pub file_name: &'a str,
+    pub file_size: u64,
+    pub bus_id: u32,
+}
+
+impl<'a> From<&'a FileEntity> for FileDto<'a> {
+    fn from(file: &'a FileEntity) -> Self {
+        Self { file_id: &file.file_id, file_name: &file.file_name, file_size: file.file_size, bus_id: file.bus_id }
+    }
+}
+
+/// A [`FriendMessageEvent`] or [`TempMessageEvent`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivateMessageDto<'a> {
+    pub sender_uin: u64,
+    pub sender_uid: &'a str,
+    pub sender_nickname: &'a str,
+    pub sequence: u32,
+    pub random: u32,
+    pub timestamp: i64,
+    pub chain: ChainDto<'a>,
+}
+
+impl<'a> From<&'a FriendMessageEvent> for PrivateMessageDto<'a> {
+    fn from(event: &'a FriendMessageEvent) -> Self {
+        Self {
+            sender_uin: event.sender_uin,
+            sender_uid: &event.sender_uid,
+            sender_nickname: &event.sender_nickname,
+            sequence: event.sequence,
+            random: event.random,
+            timestamp: event.timestamp,
+            chain: (&event.chain).into(),
+        }
+    }
+}
+
+impl<'a> From<&'a TempMessageEvent> for PrivateMessageDto<'a> {
+    fn from(event: &'a TempMessageEvent) -> Self {
+        Self {
+            sender_uin: event.sender_uin,
+            sender_uid: &event.sender_uid,
+            sender_nickname: &event.sender_nickname,
+            sequence: event.sequence,
+            random: event.random,
+            timestamp: event.timestamp,
+            chain: (&event.chain).into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMessageDto<'a> {
+    pub group_uin: u64,
+    pub group_name: &'a str,
+    pub sender_uin: u64,
+    pub sender_uid: &'a str,
+    pub sender_nickname: &'a str,
+    pub sequence: u32,
+    pub random: u32,
+    pub timestamp: i64,
+    pub chain: ChainDto<'a>,
+}
+
+impl<'a> From<&'a GroupMessageEvent> for GroupMessageDto<'a> {
+    fn from(event: &'a GroupMessageEvent) -> Self {
+        Self {
+            group_uin: event.group_uin,
+            group_name: &event.group_name,
+            sender_uin: event.sender_uin,
+            sender_uid: &event.sender_uid,
+            sender_nickname: &event.sender_nickname,
+            sequence: event.sequence,
+            random: event.random,
+            timestamp: event.timestamp,
+            chain: (&event.chain).into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FriendRequestDto<'a> {
+    pub source_uin: u64,
+    pub message: &'a str,
+    pub token: &'a str,
+}
+
+impl<'a> From<&'a FriendRequestEvent> for FriendRequestDto<'a> {
+    fn from(event: &'a FriendRequestEvent) -> Self {
+        Self { source_uin: event.source_uin, message: &event.message, token: &event.token }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupJoinRequestDto<'a> {
+    pub group_uin: u64,
+    pub target_uin: u64,
+    pub target_uid: &'a str,
+    pub invitor: Option<u64>,
+    pub comment: &'a str,
+    pub sequence: u64,
+}
+
+impl<'a> From<&'a GroupJoinRequestEvent> for GroupJoinRequestDto<'a> {
+    fn from(event: &'a GroupJoinRequestEvent) -> Self {
+        Self {
+            group_uin: event.group_uin,
+            target_uin: event.target_uin,
+            target_uid: &event.target_uid,
+            invitor: event.invitor,
+            comment: &event.comment,
+            sequence: event.sequence,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageRecallDto {
+    pub group: Option<u64>,
+    pub operator: u64,
+    pub author: u64,
+    pub sequence: u32,
+}
+
+impl From<&MessageRecallEvent> for MessageRecallDto {
+    fn from(event: &MessageRecallEvent) -> Self {
+        Self { group: event.group, operator: event.operator, author: event.author, sequence: event.sequence }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FriendTypingDto {
+    pub uin: u64,
+    pub typing: bool,
+}
+
+impl From<&FriendTypingEvent> for FriendTypingDto {
+    fn from(event: &FriendTypingEvent) -> Self {
+        Self { uin: event.uin, typing: event.typing }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PokeDto<'a> {
+    pub group_uin: u64,
+    pub sender: u64,
+    pub receiver: u64,
+    pub action: &'a str,
+    pub suffix: &'a str,
+}
+
+impl<'a> From<&'a PokeEvent> for PokeDto<'a> {
+    fn from(event: &'a PokeEvent) -> Self {
+        Self {
+            group_uin: event.group_uin,
+            sender: event.sender,
+            receiver: event.receiver,
+            action: &event.action,
+            suffix: &event.suffix,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EssenceMessageDto {
+    pub group: u64,
+    pub operator: u64,
+    pub sender: u64,
+    pub sequence: u32,
+    pub is_set: bool,
+}
+
+impl From<&EssenceMessageEvent> for EssenceMessageDto {
+    fn from(event: &EssenceMessageEvent) -> Self {
+        Self {
+            group: event.group,
+            operator: event.operator,
+            sender: event.sender,
+            sequence: event.sequence,
+            is_set: event.is_set,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupReactionDto {
+    pub group: u64,
+    pub sequence: u32,
+    pub operator: u64,
+    pub emoji_id: u32,
+    pub added: bool,
+    pub current_count: u32,
+}
+
+impl From<&GroupReactionEvent> for GroupReactionDto {
+    fn from(event: &GroupReactionEvent) -> Self {
+        Self {
+            group: event.group,
+            sequence: event.sequence,
+            operator: event.operator,
+            emoji_id: event.emoji_id.0,
+            added: event.added,
+            current_count: event.current_count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FriendInfoChangedDto<'a> {
+    pub uin: u64,
+    /// `nickname`, `remark` or `avatar`
+    pub field: &'static str,
+    pub old: Option<&'a str>,
+    pub new: &'a str,
+}
+
+impl<'a> From<&'a FriendInfoChangedEvent> for FriendInfoChangedDto<'a> {
+    fn from(event: &'a FriendInfoChangedEvent) -> Self {
+        let field = match event.field {
+            FriendInfoField::Nickname => "nickname",
+            FriendInfoField::Remark => "remark",
+            FriendInfoField::Avatar => "avatar",
+        };
+        Self { uin: event.uin, field, old: event.old.as_deref(), new: &event.new }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMuteDto {
+    pub group: u64,
+    pub operator: u64,
+    pub target: Option<u64>,
+    /// `0` for an unmute
+    pub duration_secs: u64,
+    pub whole_group: bool,
+}
+
+impl From<&GroupMuteEvent> for GroupMuteDto {
+    fn from(event: &GroupMuteEvent) -> Self {
+        Self {
+            group: event.group,
+            operator: event.operator,
+            target: event.target,
+            duration_secs: event.duration.as_secs(),
+            whole_group: event.whole_group,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMemberIncreaseDto<'a> {
+    pub group: u64,
+    pub member_uin: u64,
+    pub member_uid: &'a str,
+    pub invitor: Option<u64>,
+    /// `joined` or `invited`
+    pub kind: &'static str,
+}
+
+impl<'a> From<&'a GroupMemberIncreaseEvent> for GroupMemberIncreaseDto<'a> {
+    fn from(event: &'a GroupMemberIncreaseEvent) -> Self {
+        let kind = match event.kind {
+            GroupMemberIncreaseKind::Joined => "joined",
+            GroupMemberIncreaseKind::Invited => "invited",
+        };
+        Self {
+            group: event.group,
+            member_uin: event.member_uin,
+            member_uid: &event.member_uid,
+            invitor: event.invitor,
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMemberDecreaseDto<'a> {
+    pub group: u64,
+    pub member_uin: u64,
+    pub member_uid: &'a str,
+    pub operator: Option<u64>,
+    /// `left`, `kicked` or `kicked_self`
+    pub kind: &'static str,
+}
+
+impl<'a> From<&'a GroupMemberDecreaseEvent> for GroupMemberDecreaseDto<'a> {
+    fn from(event: &'a GroupMemberDecreaseEvent) -> Self {
+        let kind = match event.kind {
+            GroupMemberDecreaseKind::Left => "left",
+            GroupMemberDecreaseKind::Kicked => "kicked",
+            GroupMemberDecreaseKind::KickedSelf => "kicked_self",
+        };
+        Self {
+            group: event.group,
+            member_uin: event.member_uin,
+            member_uid: &event.member_uid,
+            operator: event.operator,
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GrayTipDto<'a> {
+    HonorChanged { group_uin: u64, uin: u64, honor: &'a str },
+    TitleGranted { group_uin: u64, uin: u64, title: &'a str },
+    Unknown {
+        group_uin: u64,
+        busi_id: u64,
+        template_id: u64,
+        /// `[name, value]` pairs in the order they were sent
+        params: Vec<(&'a str, &'a str)>,
+        content: &'a str,
+    },
+}
+
+impl<'a> From<&'a GrayTipEvent> for GrayTipDto<'a> {
+    fn from(event: &'a GrayTipEvent) -> Self {
+        match event {
+            GrayTipEvent::HonorChanged { group_uin, uin, honor } => {
+                Self::HonorChanged { group_uin: *group_uin, uin: *uin, honor }
+            }
+            GrayTipEvent::TitleGranted { group_uin, uin, title } => {
+                Self::TitleGranted { group_uin: *group_uin, uin: *uin, title }
+            }
+            GrayTipEvent::Unknown(tip) => Self::Unknown {
+                group_uin: tip.group_uin,
+                busi_id: tip.busi_id,
+                template_id: tip.template_id,
+                params: tip.params.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect(),
+                content: &tip.content,
+            },
+        }
+    }
+}
+
+fn to_value(dto: impl Serialize) -> serde_json::Value {
+    serde_json::to_value(dto).expect("DTOs serialize to JSON")
+}
+
+type EventSerializer = fn(&EventMessage) -> Option<serde_json::Value>;
+
+/// A bridged kind of event: its name, and its DTO as a JSON value if the event is of its type
+macro_rules! bridged {
+    ($kind:literal, $event:ty, $dto:ident) => {
+        ($kind, |event| event.downcast_ref::<$event>().map(|event| to_value($dto::from(event))))
+    };
+}
+
+/// Bridged events, by kind
+const EVENT_KINDS: &[(&str, EventSerializer)] = &[
+    bridged!("friend_message", FriendMessageEvent, PrivateMessageDto),
+    bridged!("group_message", GroupMessageEvent, GroupMessageDto),
+    bridged!("temp_message", TempMessageEvent, PrivateMessageDto),
+    bridged!("friend_request", FriendRequestEvent, FriendRequestDto),
+    bridged!("group_join_request", GroupJoinRequestEvent, GroupJoinRequestDto),
+    bridged!("message_recall", MessageRecallEvent, MessageRecallDto),
+    bridged!("friend_typing", FriendTypingEvent, FriendTypingDto),
+    bridged!("poke", PokeEvent, PokeDto),
+    bridged!("essence_message", EssenceMessageEvent, EssenceMessageDto),
+    bridged!("group_reaction", GroupReactionEvent, GroupReactionDto),
+    bridged!("friend_info_changed", FriendInfoChangedEvent, FriendInfoChangedDto),
+    bridged!("group_mute", GroupMuteEvent, GroupMuteDto),
+    bridged!("group_member_increase", GroupMemberIncreaseEvent, GroupMemberIncreaseDto),
+    bridged!("group_member_decrease", GroupMemberDecreaseEvent, GroupMemberDecreaseDto),
+    bridged!("gray_tip", GrayTipEvent, GrayTipDto),
+];
+
+/// `event` in its [`Envelope`], `None` for events that are not bridged, like connection changes
+pub fn to_json(event: &EventMessage, bot_uin: Option<u64>) -> Option<serde_json::Value> {
+    EVENT_KINDS.iter().find_map(|(kind, serialize)| {
+        Some(to_value(Envelope { version: BRIDGE_VERSION, kind, bot_uin, event: serialize(event)? }))
+    })
+}
+
+/// An event or why there is none, and the receiver to wait for the next one with
+type Received = (Result<EventMessage, broadcast::error::RecvError>, broadcast::Receiver<EventMessage>);
+type Recv = Pin<Box<dyn Future<Output = Received> + Send>>;
+
+async fn recv(mut receiver: broadcast::Receiver<EventMessage>) -> Received {
+    (receiver.recv().await, receiver)
+}
+
+/// Stream of the bridged events of a bot as JSON, see [`BotContext::subscribe_serialized`].
+///
+/// Read it with [`SerializedEvents::next`] or as a [`futures_core::Stream`].
+pub struct SerializedEvents {
+    keystore: Arc<RwLock<BotKeystore>>,
+    recv: Recv,
+}
+
+impl SerializedEvents {
+    /// The next event, or `None` once the context is dropped
+    pub async fn next(&mut self) -> Option<serde_json::Value> {
+        std::future::poll_fn(|cx| futures_core::Stream::poll_next(Pin::new(&mut *self), cx)).await
+    }
+}
+
+impl futures_core::Stream for SerializedEvents {
+    type Item = serde_json::Value;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let (result, receiver) = std::task::ready!(self.recv.as_mut().poll(cx));
+            self.recv = Box::pin(recv(receiver));
+            match result {
+                Ok(event) => {
+                    let bot_uin = self.keystore.read().expect("RwLock poisoned").uin;
+                    if let Some(value) = to_json(&event, bot_uin) {
+                        return Poll::Ready(Some(value));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "Serialized event stream lagged behind, events were dropped");
+                }
+                Err(broadcast::error::RecvError::Closed) => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl BotContext {
+    /// Every bridged event posted from now on, as an [`Envelope`] in JSON
+    pub fn subscribe_serialized(&self) -> SerializedEvents {
+        SerializedEvents { keystore: self.keystore.clone(), recv: Box::pin(recv(self.event.subscribe())) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn group_message() -> GroupMessageEvent {
+        let image = ImageEntity {
+            file_name: "cat.png".to_string(),
+            md5: vec![0xDE, 0xAD, 0xBE, 0xEF],
+            size: 2048,
+            width: 640,
+            height: 480,
+            url: Some("https://multimedia.nt.qq.com.cn/download?fileid=abc".to_string()),
+            file_id: Some("abc".to_string()),
+            msg_info: Some(vec![0x0A, 0x00]),
+            is_group: true,
+            source: None,
+        };
+        GroupMessageEvent {
+            group_uin: 123456,
+            group_name: "group".to_string(),
+            sender_uin: 10001,
+            sender_uid: "u_10001".to_string(),
+            sender_nickname: "member".to_string(),
+            sequence: 88,
+            random: 7,
+            timestamp: 1700000000,
+            chain: MessageChain::from(vec![
+                MessageEntity::Text { text: "look".to_string() },
+                MessageEntity::Image(image),
+                MessageEntity::AtAll,
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_group_message_shape() {
+        let value = to_json(&EventMessage::new(group_message()), Some(10000)).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "version": 1,
+                "kind": "group_message",
+                "bot_uin": 10000,
+                "event": {
+                    "group_uin": 123456,
+                    "group_name": "group",
+                    "sender_uin": 10001,
+                    "sender_uid": "u_10001",
+                    "sender_nickname": "member",
+                    "sequence": 88,
+                    "random": 7,
+                    "timestamp": 1700000000,
+                    "chain": [
+                        { "type": "text", "text": "look" },
+                        {
+                            "type": "image",
+                            "file_name": "cat.png",
+                            "md5": "3q2+7w==",
+                            "size": 2048,
+                            "width": 640,
+                            "height": 480,
+                            "url": "https://multimedia.nt.qq.com.cn/download?fileid=abc",
+                            "file_id": "abc",
+                            "msg_info": "CgA=",
+                            "is_group": true
+                        },
+                        { "type": "at_all" }
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_notice_shapes() {
+        let mute = GroupMuteEvent {
+            group: 1,
+            operator: 2,
+            target: Some(3),
+            duration: Duration::from_secs(600),
+            whole_group: false,
+        };
+        let value = to_json(&EventMessage::new(mute), None).unwrap();
+        assert_eq!(value["kind"], "group_mute");
+        assert_eq!(value["bot_uin"], serde_json::Value::Null);
+        assert_eq!(value["event"]["duration_secs"], 600);
+
+        let tip = GrayTipEvent::HonorChanged { group_uin: 1, uin: 2, honor: "龙王".to_string() };
+        let value = to_json(&EventMessage::new(tip), None).unwrap();
+        assert_eq!(value["event"], json!({ "type": "honor_changed", "group_uin": 1, "uin": 2, "honor": "龙王" }));
+
+        let fatal = crate::common::ConnectionEvent::Fatal { task: "socket".to_string(), reason: "gone".to_string() };
+        assert_eq!(to_json(&EventMessage::new(fatal), None), None);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_serialized() {
+        let context = BotContext::builder().build();
+        let mut events = context.subscribe_serialized();
+
+        context.post(crate::common::ConnectionEvent::Fatal { task: "socket".to_string(), reason: "gone".to_string() });
+        context.post(FriendTypingEvent { uin: 10001, typing: true });
+        let value = events.next().await.unwrap();
+        assert_eq!(value["kind"], "friend_typing");
+        assert_eq!(value["event"], json!({ "uin": 10001, "typing": true }));
+    }
+}
@@ -1,2 +1,14 @@
 ﻿pub mod network;
-mod account;
\ No newline at end of file
+pub mod account;
+mod announcement;
+mod avatar;
+pub mod contact;
+mod cookies;
+mod group;
+mod group_file;
+mod highway;
+mod media;
+mod message;
+mod request;
+mod status;
+mod token;
\ No newline at end of file
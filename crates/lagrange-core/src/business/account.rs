@@ -1,19 +1,1070 @@
-﻿use std::sync::Arc;
+﻿use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use bytes::Bytes;
+use tokio::sync::mpsc;
 use crate::{BotContext, Error};
-use crate::internal::services::login::{TransEmp31EventReq, TransEmpService, TransEmpServiceRequest, TransEmpServiceResponse};
+use crate::common::{BotInfo, BotOfflineEvent, LoginState, OfflineKind, QrCodeInfo, QrLoginState};
+use crate::internal::packets::login::tlv119::SessionKeys;
+use crate::internal::services::login::{
+    LoginCommand, LoginEventReq, LoginEventReqAndroid, LoginEventResp, LoginEventRespAndroid,
+    LoginService, LoginServiceRequest, LoginServiceResponse, LoginStates, QrCodeState,
+    RegisterEventReq, RegisterService, TransEmp12EventReq, TransEmp12EventResp,
+    TransEmp31EventReq, TransEmpService, TransEmpServiceRequest, TransEmpServiceResponse,
+    UnRegisterEventReq, UnRegisterEventResp,
+};
+use crate::utils::binary::{BinaryPacket, Prefix};
+
+/// Interval between two `trans_emp` 0x12 polls while waiting for a QR code to be scanned
+const QRCODE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How long [`BotContext::logout`] waits for the server to acknowledge
+const LOGOUT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Lifetime of a QR code when the server does not tell
+const DEFAULT_QRCODE_LIFETIME: Duration = Duration::from_secs(120);
 
 impl BotContext {
-    pub async fn fetch_qrcode(self: &Arc<Self>) -> Result<String, Error> {
+    /// Fetch a login QR code; poll it with [`BotContext::login_by_qrcode`] instead to log in.
+    pub async fn fetch_qrcode(self: &Arc<Self>) -> Result<QrCodeInfo, Error> {
+        self.request_qrcode(None).await
+    }
+
+    /// Fetch a login QR code and follow it until the bot is logged in.
+    ///
+    /// The returned stream owns the polling and ends after a final state, see
+    /// [`QrLoginState::is_final`]. With `refresh_expired` an expired code is replaced and the
+    /// login goes on, otherwise it ends with [`QrLoginState::Expired`]. Dropping the stream stops
+    /// the polling.
+    pub async fn login_by_qrcode(self: &Arc<Self>, refresh_expired: bool) -> Result<(QrCodeInfo, QrLoginStream), Error> {
+        let info = self.request_qrcode(None).await?;
+        let stream = QrLoginStream::spawn(
+            self.clone(),
+            refresh_expired,
+            |context| async move { context.poll_qrcode().await },
+            |context| async move { context.request_qrcode(None).await },
+            |context| async move { context.wtlogin().await },
+        );
+        Ok((info, stream))
+    }
+
+    /// Fetch the QR code that confirms a login from an unusual device.
+    ///
+    /// `sig` comes from [`LoginState::UnusualDeviceVerify`]. Once the returned URL has been
+    /// shown to the account owner, call [`BotContext::wait_unusual_device_verify`].
+    pub async fn fetch_unusual_device_qrcode(self: &Arc<Self>, sig: &[u8]) -> Result<String, Error> {
+        Ok(self.request_qrcode(Some(sig.to_vec())).await?.url)
+    }
+
+    /// Poll the unusual-device verification QR code until it is confirmed, then resume
+    /// the original login.
+    pub async fn wait_unusual_device_verify(self: &Arc<Self>) -> Result<LoginState, Error> {
+        let mut interval = tokio::time::interval(QRCODE_POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let resp = self.poll_qrcode().await?;
+            match resp.state() {
+                QrCodeState::Confirmed => {
+                    self.apply_qrcode_confirmed(&resp);
+                    break;
+                }
+                QrCodeState::WaitingForScan | QrCodeState::WaitingForConfirm => continue,
+                state => {
+                    return Ok(LoginState::Failed {
+                        code: resp.ret_code,
+                        message: format!("Unusual device verification ended with {:?}", state),
+                        tag146_url: None,
+                    });
+                }
+            }
+        }
+
+        self.wtlogin().await
+    }
+
+    async fn request_qrcode(self: &Arc<Self>, unusual_sig: Option<Vec<u8>>) -> Result<QrCodeInfo, Error> {
+        self.set_logged_out(false);
         let event = TransEmpServiceRequest::TransEmp31Event(TransEmp31EventReq {
-            unusual_sig: None
+            unusual_sig
         });
         let response = self.event.send::<TransEmpService>(event, self.clone()).await?;
 
         match response {
-            TransEmpServiceResponse::TransEmp31Event(resp) => Ok(resp.qr_url),
+            TransEmpServiceResponse::TransEmp31Event(resp) => {
+                let qr_sig = resp.sig.unwrap_or_default();
+                self.keystore_mut().state.qr_sig = Some(qr_sig.clone());
+                Ok(QrCodeInfo {
+                    url: resp.qr_url,
+                    image: Bytes::from(resp.image),
+                    qr_sig,
+                    expires_in: resp
+                        .expiration
+                        .map_or(DEFAULT_QRCODE_LIFETIME, |seconds| Duration::from_secs(seconds as u64)),
+                })
+            }
             _ => Err(Error::ParseError(
                 "Expected TransEmp31Event response but got different variant".to_string()
             ))
         }
     }
-}
\ No newline at end of file
+
+    /// Ask for the state of the current QR code once, via `trans_emp` 0x12
+    async fn poll_qrcode(self: &Arc<Self>) -> Result<TransEmp12EventResp, Error> {
+        let event = TransEmpServiceRequest::TransEmp12Event(TransEmp12EventReq {});
+        match self.event.send::<TransEmpService>(event, self.clone()).await? {
+            TransEmpServiceResponse::TransEmp12Event(resp) => Ok(resp),
+            _ => Err(Error::ParseError(
+                "Expected TransEmp12Event response but got different variant".to_string()
+            )),
+        }
+    }
+
+    /// Store the credentials handed out once a QR code has been confirmed
+    fn apply_qrcode_confirmed(&self, resp: &TransEmp12EventResp) {
+        let mut keystore = self.keystore_mut();
+        if let Some(uin) = resp.uin {
+            keystore.uin = Some(uin);
+        }
+        if let Some(a1) = &resp.tlv_18 {
+            keystore.sigs.a1 = a1.clone();
+        }
+        if let Some(no_pic_sig) = &resp.tlv_19 {
+            keystore.sigs.no_pic_sig = Some(no_pic_sig.clone());
+        }
+        if let Some(tgtgt_key) = &resp.tlv_1e {
+            keystore.sigs.tgtgt_key = tgtgt_key.clone();
+        }
+    }
+
+    /// Perform `wtlogin.login` with the A1 and tgtgt key currently in the keystore.
+    ///
+    /// Android protocols send the password login packet with the A1 in place of the password.
+    pub async fn wtlogin(self: &Arc<Self>) -> Result<LoginState, Error> {
+        self.set_logged_out(false);
+        let state = if self.app_info.is_android() {
+            let resp = self
+                .send_login_android(LoginEventReqAndroid {
+                    cmd: LoginCommand::Tgtgt,
+                    password: String::new(),
+                    ticket: String::new(),
+                    code: String::new(),
+                })
+                .await?;
+            self.resolve_password_login(&resp)
+        } else {
+            let event = LoginServiceRequest::LoginEvent(LoginEventReq {
+                cmd: LoginCommand::Tgtgt,
+                password: String::new(),
+                ticket: String::new(),
+                code: String::new(),
+            });
+            match self.event.send::<LoginService>(event, self.clone()).await? {
+                LoginServiceResponse::LoginEvent(resp) => self.resolve_login(&resp),
+                _ => return Err(Error::ParseError(
+                    "Expected LoginEvent response but got different variant".to_string()
+                )),
+            }
+        };
+
+        if state.is_success() {
+            self.finish_login().await?;
+        }
+        Ok(state)
+    }
+
+    /// Log in as `uin` with `password`, which needs an Android protocol.
+    ///
+    /// `on_state` is called with every state that needs an answer: the ticket of a
+    /// [`LoginState::CaptchaRequired`], or the code of a [`LoginState::SmsRequired`]. Answering
+    /// `None` gives up and returns that state. Any other state ends the login and is returned,
+    /// such as [`LoginState::UnusualDeviceVerify`] when the device must be confirmed by QR code.
+    ///
+    /// Connects first if needed. On success the bot is registered online and its uin, uid and
+    /// nickname are filled in.
+    pub async fn login_by_password<F, Fut>(self: &Arc<Self>, uin: u64, password: &str, on_state: F) -> Result<LoginState, Error>
+    where
+        F: FnMut(LoginState) -> Fut,
+        Fut: Future<Output = Option<String>>,
+    {
+        if !self.app_info.is_android() {
+            return Err(Error::ProtocolError("Password login needs an Android protocol".to_string()));
+        }
+        if !self.socket.is_connected().await {
+            self.connect().await?;
+        }
+        self.set_logged_out(false);
+        self.keystore_mut().uin = Some(uin);
+
+        let state = self
+            .drive_password_login(password, on_state, |request| self.send_login_android(request))
+            .await?;
+
+        if state.is_success() {
+            self.finish_login().await?;
+        }
+        Ok(state)
+    }
+
+    async fn send_login_android(self: &Arc<Self>, request: LoginEventReqAndroid) -> Result<LoginEventRespAndroid, Error> {
+        let event = LoginServiceRequest::LoginEventAndroid(request);
+        match self.event.send::<LoginService>(event, self.clone()).await? {
+            LoginServiceResponse::LoginEventAndroid(resp) => Ok(resp),
+            _ => Err(Error::ParseError(
+                "Expected LoginEventAndroid response but got different variant".to_string()
+            )),
+        }
+    }
+
+    /// Run the password login state machine, with `send` exchanging one `wtlogin.login` packet
+    async fn drive_password_login<F, Fut, S, SF>(&self, password: &str, mut on_state: F, mut send: S) -> Result<LoginState, Error>
+    where
+        F: FnMut(LoginState) -> Fut,
+        Fut: Future<Output = Option<String>>,
+        S: FnMut(LoginEventReqAndroid) -> SF,
+        SF: Future<Output = Result<LoginEventRespAndroid, Error>>,
+    {
+        let request = |cmd, answer: &str| LoginEventReqAndroid {
+            cmd,
+            password: password.to_string(),
+            ticket: if cmd == LoginCommand::Captcha { answer.to_string() } else { String::new() },
+            code: if cmd == LoginCommand::SubmitSMSCode { answer.to_string() } else { String::new() },
+        };
+
+        let mut response = send(request(LoginCommand::Tgtgt, "")).await?;
+        let mut sms_sent = false;
+        loop {
+            let mut state = self.resolve_password_login(&response);
+            let next = match state {
+                LoginState::CaptchaRequired { .. } => LoginCommand::Captcha,
+                LoginState::SmsRequired { .. } => LoginCommand::SubmitSMSCode,
+                _ => return Ok(state),
+            };
+
+            // The code is only sent on request, and a resent code replaces the first one
+            if next == LoginCommand::SubmitSMSCode && !sms_sent {
+                sms_sent = true;
+                let fetched = send(request(LoginCommand::FetchSMSCode, "")).await?;
+                if let failed @ LoginState::Failed { .. } = self.resolve_password_login(&fetched) {
+                    return Ok(failed);
+                }
+                if let (LoginState::SmsRequired { phone, .. }, Some(sent_to)) = (&mut state, fetched.tlvs.get(&TLV_SMS_PHONE)) {
+                    *phone = parse_sms_phone(sent_to).unwrap_or_default();
+                }
+            }
+
+            let Some(answer) = on_state(state.clone()).await else {
+                return Ok(state);
+            };
+            response = send(request(next, &answer)).await?;
+        }
+    }
+
+    fn resolve_password_login(&self, resp: &LoginEventRespAndroid) -> LoginState {
+        // Captcha and SMS answers refer back to these
+        {
+            let mut keystore = self.keystore_mut();
+            for tag in [0x104, 0x174, 0x547] {
+                if let Some(data) = resp.tlvs.get(&tag) {
+                    keystore.state.tlv_cache.insert(tag, data.clone());
+                }
+            }
+        }
+
+        // 0xEF carrying TLV 0x174 asks to confirm the device by QR code rather than by SMS
+        if let Some(unusual) = resp.unusual_device() {
+            tracing::info!(message = %unusual.message, "Unusual device verification required");
+            return LoginState::UnusualDeviceVerify {
+                sig: unusual.sig,
+                message: unusual.message,
+            };
+        }
+
+        if let Some(failed) = failed_state(resp.ret_code, &resp.error) {
+            return failed;
+        }
+
+        let string_tlv = |tag| resp.tlvs.get(&tag).map(|data| String::from_utf8_lossy(data).into_owned());
+        match resp.state() {
+            LoginStates::Success => {
+                self.apply_login_tlvs(&resp.tlvs);
+                LoginState::Success
+            }
+            LoginStates::CaptchaVerify => match string_tlv(TLV_CAPTCHA_URL) {
+                Some(url) => LoginState::CaptchaRequired { url },
+                None => unexpected_state(resp.ret_code),
+            },
+            LoginStates::SmsRequired | LoginStates::DeviceLockViaSmsNewArea => {
+                match resp.tlvs.get(&TLV_SMS_PHONE).and_then(|data| parse_sms_phone(data)) {
+                    Some(phone) => LoginState::SmsRequired {
+                        phone,
+                        message: string_tlv(TLV_SMS_MESSAGE).unwrap_or_default(),
+                    },
+                    None => unexpected_state(resp.ret_code),
+                }
+            }
+            LoginStates::DeviceLock => match string_tlv(TLV_DEVICE_LOCK_URL) {
+                Some(url) => LoginState::DeviceLocked { url },
+                None => unexpected_state(resp.ret_code),
+            },
+            _ => unexpected_state(resp.ret_code),
+        }
+    }
+
+    /// Register the bot online and fetch what the session needs, once the keys are in place
+    async fn finish_login(self: &Arc<Self>) -> Result<(), Error> {
+        self.register_online().await?;
+        self.prepare_session().await;
+        Ok(())
+    }
+
+    /// Register the bot online with the tickets in the keystore
+    pub(crate) async fn register_online(self: &Arc<Self>) -> Result<(), Error> {
+        self.event.send::<RegisterService>(RegisterEventReq {}, self.clone()).await?;
+        self.set_online(true);
+        Ok(())
+    }
+
+    /// Tell the server the bot goes offline on purpose, so the account does not linger online.
+    ///
+    /// Waits up to a few seconds for the server to acknowledge, a missing answer does not fail
+    /// the logout. Afterwards requests fail with [`Error::LoggedOut`] until the next login; the
+    /// tickets stay in the keystore, so [`BotContext::login_by_token`] can resume later.
+    pub async fn logout(self: &Arc<Self>) -> Result<(), Error> {
+        if let Err(e) = self
+            .send_and_wait::<UnRegisterEventReq, UnRegisterEventResp>(UnRegisterEventReq {}, LOGOUT_TIMEOUT)
+            .await
+        {
+            tracing::warn!(error = %e, "Server did not acknowledge the logout");
+        }
+
+        self.set_online(false);
+        self.set_logged_out(true);
+        {
+            let mut keystore = self.keystore_mut();
+            keystore.state.clear_volatile();
+            // The web cookies end with the session
+            keystore.state.cookies.clear();
+        }
+        self.post(BotOfflineEvent {
+            kind: OfflineKind::Logout,
+            title: "Logged out".to_string(),
+            message: String::new(),
+        });
+        Ok(())
+    }
+
+    /// Fetch what the session needs once online
+    pub(crate) async fn prepare_session(self: &Arc<Self>) {
+        // The profile is a convenience, a failure must not fail the login itself
+        if let Err(e) = self.refresh_bot_info().await {
+            tracing::warn!(error = %e, "Failed to fetch the bot profile");
+        }
+        // Uploads fetch the session on demand if this fails
+        if let Err(e) = self.refresh_highway_session().await {
+            tracing::warn!(error = %e, "Failed to fetch the highway session");
+        }
+        if let Err(e) = self.restore_online_status().await {
+            tracing::warn!(error = %e, "Failed to restore the online status");
+        }
+    }
+
+    /// Store the session keys and the identity of the account from a successful login
+    fn apply_login_tlvs(&self, tlvs: &HashMap<u16, Vec<u8>>) {
+        let keys = SessionKeys::from_tlvs(tlvs);
+        let mut keystore = self.keystore_mut();
+        keystore.sigs.apply_session_keys(&keys, chrono::Utc::now().timestamp());
+        if let Some(uid) = keys.uid {
+            keystore.uid = Some(uid);
+        }
+        if let Some(info) = tlvs.get(&TLV_PROFILE).and_then(|data| parse_profile(data)) {
+            keystore.bot_info = Some(info);
+        }
+    }
+
+    fn resolve_login(&self, resp: &LoginEventResp) -> LoginState {
+        if let Some(unusual) = resp.unusual_device() {
+            tracing::info!(message = %unusual.message, "Unusual device verification required");
+            return LoginState::UnusualDeviceVerify {
+                sig: unusual.sig,
+                message: unusual.message,
+            };
+        }
+
+        if let Some(failed) = failed_state(resp.ret_code, &resp.error) {
+            return failed;
+        }
+        if resp.ret_code != 0 {
+            return unexpected_state(resp.ret_code);
+        }
+
+        self.apply_login_tlvs(&resp.tlvs);
+        LoginState::Success
+    }
+}
+
+/// Async stream of the states of a QR code login, see [`BotContext::login_by_qrcode`].
+///
+/// Read it with [`QrLoginStream::next`] or as a [`futures_core::Stream`].
+pub struct QrLoginStream {
+    receiver: mpsc::Receiver<QrLoginState>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl QrLoginStream {
+    /// Drive the login in a task; `poll` asks for the code state, `fetch` replaces an expired
+    /// code and `login` finishes a confirmed one
+    fn spawn<P, PF, F, FF, L, LF>(context: Arc<BotContext>, refresh_expired: bool, poll: P, fetch: F, login: L) -> Self
+    where
+        P: Fn(Arc<BotContext>) -> PF + Send + 'static,
+        PF: Future<Output = Result<TransEmp12EventResp, Error>> + Send,
+        F: Fn(Arc<BotContext>) -> FF + Send + 'static,
+        FF: Future<Output = Result<QrCodeInfo, Error>> + Send,
+        L: FnOnce(Arc<BotContext>) -> LF + Send + 'static,
+        LF: Future<Output = Result<LoginState, Error>> + Send,
+    {
+        let (sender, receiver) = mpsc::channel(4);
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(QRCODE_POLL_INTERVAL);
+            let mut last = None;
+
+            let last_state = loop {
+                interval.tick().await;
+
+                let resp = match poll(context.clone()).await {
+                    Ok(resp) => resp,
+                    Err(e) => break QrLoginState::Failed(error_state(e)),
+                };
+                let state = match resp.state() {
+                    QrCodeState::WaitingForScan => QrLoginState::WaitingScan,
+                    QrCodeState::WaitingForConfirm => QrLoginState::Scanned,
+                    QrCodeState::Confirmed => {
+                        context.apply_qrcode_confirmed(&resp);
+                        if sender.send(QrLoginState::Confirmed).await.is_err() {
+                            return;
+                        }
+                        break match login(context.clone()).await {
+                            Ok(LoginState::Success) => QrLoginState::LoggedIn,
+                            Ok(state) => QrLoginState::Failed(state),
+                            Err(e) => QrLoginState::Failed(error_state(e)),
+                        };
+                    }
+                    QrCodeState::CodeExpired if refresh_expired => match fetch(context.clone()).await {
+                        Ok(info) => QrLoginState::Expired(Some(info)),
+                        Err(e) => break QrLoginState::Failed(error_state(e)),
+                    },
+                    QrCodeState::CodeExpired => break QrLoginState::Expired(None),
+                    state => {
+                        break QrLoginState::Failed(LoginState::Failed {
+                            code: resp.ret_code,
+                            message: format!("QR code login ended with {:?}", state),
+                            tag146_url: None,
+                        })
+                    }
+                };
+
+                // A new code starts over, so its first state is reported again
+                if matches!(state, QrLoginState::Expired(_)) {
+                    last = None;
+                } else if last.as_ref() == Some(&state) {
+                    continue;
+                } else {
+                    last = Some(state.clone());
+                }
+                if sender.send(state).await.is_err() {
+                    return;
+                }
+            };
+
+            let _ = sender.send(last_state).await;
+        });
+
+        Self { receiver, task }
+    }
+
+    /// The next state, or `None` after the final one
+    pub async fn next(&mut self) -> Option<QrLoginState> {
+        self.receiver.recv().await
+    }
+}
+
+impl futures_core::Stream for QrLoginStream {
+    type Item = QrLoginState;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for QrLoginStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// [`LoginState::Failed`] for a login stopped by an error instead of the server
+fn error_state(error: Error) -> LoginState {
+    LoginState::Failed {
+        code: 0,
+        message: error.to_string(),
+        tag146_url: None,
+    }
+}
+
+/// TLV with the url of the slider captcha
+const TLV_CAPTCHA_URL: u16 = 0x192;
+/// TLV with the country code and masked number of the phone an SMS code goes to
+const TLV_SMS_PHONE: u16 = 0x178;
+/// TLV with the prompt shown along an SMS verification
+const TLV_SMS_MESSAGE: u16 = 0x17E;
+/// TLV with the url that lifts a device lock
+const TLV_DEVICE_LOCK_URL: u16 = 0x204;
+/// TLV with the face, age, gender and nickname of the account
+const TLV_PROFILE: u16 = 0x11A;
+
+/// [`LoginState::Failed`] for a login rejected with TLV 0x146, which may link to an explanation
+fn failed_state(ret_code: u8, error: &Option<(String, String)>) -> Option<LoginState> {
+    let (title, message) = error.as_ref()?;
+    let tag146_url = message
+        .find("http")
+        .map(|start| message[start..].split_whitespace().next().unwrap_or_default().to_string());
+
+    Some(LoginState::Failed {
+        code: ret_code,
+        message: format!("{}: {}", title, message),
+        tag146_url,
+    })
+}
+
+fn unexpected_state(ret_code: u8) -> LoginState {
+    LoginState::Failed {
+        code: ret_code,
+        message: format!("Login failed with state {:?}", LoginStates::from(ret_code)),
+        tag146_url: None,
+    }
+}
+
+/// `+<country code> <masked number>` from TLV 0x178
+fn parse_sms_phone(data: &[u8]) -> Option<String> {
+    let mut reader = BinaryPacket::from_slice(data);
+    let country_code = reader.read_string(Prefix::INT16).ok()?;
+    let phone = reader.read_string(Prefix::INT16).ok()?;
+    Some(format!("+{} {}", country_code, phone))
+}
+
+/// Profile from TLV 0x11A: face id (2 bytes), age, gender, then the length prefixed nickname
+fn parse_profile(data: &[u8]) -> Option<BotInfo> {
+    let mut reader = BinaryPacket::from_slice(data);
+    let _face = reader.read::<u16>().ok()?;
+    let age = reader.read::<u8>().ok()?;
+    let gender = reader.read::<u8>().ok()?;
+    let name = reader.read_string(Prefix::INT8).ok()?;
+    Some(BotInfo::new(age, (gender as u32).into(), name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BotConfig;
+    use crate::internal::packets::login::qr_login_ext_info::QrExtInfo;
+    use crate::internal::packets::login::wtlogin::WtLogin;
+    use crate::internal::packets::login::ServiceRegisterResponse;
+    use crate::internal::packets::login::register::{Tlv543, Tlv543Layer1, Tlv543Layer2};
+    use crate::keystore::BotKeystore;
+    use lagrange_proto::ProtoMessage;
+    use crate::testing::{Expectation, MockServer};
+    use crate::utils::crypto::tea;
+    use crate::Protocols;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    fn pack_tlvs(tlvs: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut writer = BinaryPacket::with_capacity(64);
+        writer.write(tlvs.len() as u16);
+        for (tag, data) in tlvs {
+            writer.write(*tag);
+            writer.write_bytes_with_prefix(data, Prefix::INT16);
+        }
+        writer.to_vec()
+    }
+
+    fn login_response(context: &Arc<BotContext>, state: u8, tlvs: &[u8]) -> Bytes {
+        let mut payload = BinaryPacket::with_capacity(tlvs.len() + 3);
+        payload.write(0x09u16);
+        payload.write(state);
+        payload.write_bytes(tlvs);
+
+        let mut keystore = context.keystore.write().unwrap();
+        let packet = WtLogin::new(&mut keystore, context.app_info.inner()).unwrap();
+        Bytes::from(packet.build_test_response(0x810, 0, payload.as_slice()))
+    }
+
+    fn code_2d_response(context: &Arc<BotContext>, command: u16, body: &[u8]) -> Bytes {
+        let mut keystore = context.keystore.write().unwrap();
+        let packet = WtLogin::new(&mut keystore, context.app_info.inner()).unwrap();
+        Bytes::from(packet.build_test_code_2d_response(command, body))
+    }
+
+    const VERIFY_URL: &str = "https://ti.qq.com/safe/verify?k=unusual";
+
+    /// trans_emp 0x31 answer with the verification QR code
+    fn verify_qrcode_response(context: &Arc<BotContext>) -> Bytes {
+        let ext_info = QrExtInfo { qr_url: Some(VERIFY_URL.to_string()), ..Default::default() };
+        let mut body = BinaryPacket::with_capacity(64);
+        body.write(0i16);
+        body.write(context.app_info.app_id() as i32);
+        body.write(0u8);
+        body.write_bytes_with_prefix(b"verify-sig", Prefix::INT16);
+        body.write_bytes(&pack_tlvs(&[(0xD1, &ext_info.encode_to_vec().unwrap())]));
+        code_2d_response(context, 0x31, body.as_slice())
+    }
+
+    /// trans_emp 0x12 answer reporting the code as confirmed, with an A1 of 0x18s
+    fn confirmed_response(context: &Arc<BotContext>, tgtgt_key: &[u8; 16]) -> Bytes {
+        let mut body = BinaryPacket::with_capacity(128);
+        body.write(0i16);
+        body.write(context.app_info.app_id() as i32);
+        body.write(0u8);
+        body.write(123456789u64);
+        body.write(0u8);
+        body.write_bytes(&pack_tlvs(&[(0x18, &[0x18; 32]), (0x19, &[0x19; 32]), (0x1E, tgtgt_key)]));
+        code_2d_response(context, 0x12, body.as_slice())
+    }
+
+    /// Successful login answer, its session keys encrypted with `tgtgt_key`
+    fn logged_in_response(context: &Arc<BotContext>, tgtgt_key: &[u8; 16]) -> Bytes {
+        let inner = pack_tlvs(&[(0x10A, &[0xA2; 48]), (0x143, &[0xD2; 48]), (0x305, &[0xDD; 16])]);
+        let tlvs = pack_tlvs(&[(0x119, &tea::encrypt(&inner, tgtgt_key))]);
+        login_response(context, 0, &tlvs)
+    }
+
+    /// An Android bot that is not logged in yet, its server expecting the keys of `logged_in_response`
+    async fn connect_android() -> (Arc<BotContext>, MockServer) {
+        let keystore = BotKeystore::for_protocol(Protocols::AndroidPhone);
+        let mut logged_in = keystore.clone();
+        logged_in.sigs.d2 = vec![0xD2; 48];
+        logged_in.sigs.d2_key = vec![0xDD; 16];
+        let (transport, server) = MockServer::start(logged_in);
+        let context = BotContext::builder()
+            .config(BotConfig::builder().protocol(Protocols::AndroidPhone).build())
+            .keystore(keystore)
+            .transport(transport)
+            .build();
+        context.connect().await.unwrap();
+        (context, server)
+    }
+
+    /// Answer the registration that follows a login; the rest of the session setup may fail
+    fn expect_session_setup(server: &MockServer) {
+        let registered = ServiceRegisterResponse {
+            message: Some(ServiceRegisterResponse::SUCCESS.to_string()),
+            timestamp: Some(1_700_000_000),
+        };
+        server.expect(
+            Expectation::new("trpc.qq_new_tech.status_svc.StatusService.Register")
+                .respond(registered.encode_to_vec().unwrap()),
+        );
+        server.expect(Expectation::new("OidbSvcTrpcTcp.0xfe1_2").reject(-1, "unavailable"));
+        server.expect(Expectation::new("HttpConn.0x6ff_501").reject(-1, "unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_unusual_device_follow_ups_on_android() {
+        let (context, server) = connect_android().await;
+        let tgtgt_key = [0x1Eu8; 16];
+        server.expect(Expectation::new("wtlogin.trans_emp").respond(verify_qrcode_response(&context)).times(1));
+        server.expect(Expectation::new("wtlogin.trans_emp").respond(confirmed_response(&context, &tgtgt_key)));
+        server.expect(Expectation::new("wtlogin.login").respond(logged_in_response(&context, &tgtgt_key)));
+        expect_session_setup(&server);
+
+        assert_eq!(context.fetch_unusual_device_qrcode(b"unusual-sig").await.unwrap(), VERIFY_URL);
+        assert_eq!(context.wait_unusual_device_verify().await.unwrap(), LoginState::Success);
+        assert!(context.is_online());
+        assert_eq!(context.keystore.read().unwrap().sigs.d2, vec![0xD2; 48]);
+
+        // The resumed login carries the A1 of the confirmation in place of a password
+        let received = server.received();
+        let login = received.iter().find(|packet| packet.command == "wtlogin.login").unwrap();
+        let decrypted = {
+            let mut keystore = context.keystore.write().unwrap();
+            WtLogin::new(&mut keystore, context.app_info.inner())
+                .unwrap()
+                .decrypt_test_request(&login.data)
+        };
+        let mut tlv_106 = vec![0x01, 0x06, 0x00, 0x20];
+        tlv_106.extend_from_slice(&[0x18; 32]);
+        assert!(decrypted.windows(tlv_106.len()).any(|w| w == tlv_106.as_slice()));
+    }
+
+    #[tokio::test]
+    async fn test_unusual_device_detour() {
+        let (context, server) = connect_android().await;
+        let unusual_sig = b"unusual-device-sig".to_vec();
+        let tgtgt_key = [0x1Eu8; 16];
+        let unusual = pack_tlvs(&[(0x174, &unusual_sig), (0x17E, "Please verify".as_bytes())]);
+        server.expect(Expectation::new("wtlogin.login").respond(login_response(&context, 0xEF, &unusual)).times(1));
+        server.expect(Expectation::new("wtlogin.trans_emp").respond(verify_qrcode_response(&context)).times(1));
+        server.expect(Expectation::new("wtlogin.trans_emp").respond(confirmed_response(&context, &tgtgt_key)));
+        server.expect(Expectation::new("wtlogin.login").respond(logged_in_response(&context, &tgtgt_key)));
+        expect_session_setup(&server);
+
+        // 1. The password login is answered with 0xEF and an unusual sig
+        let state = context.login_by_password(123456789, "hunter2", |_| async { None }).await.unwrap();
+        assert_eq!(
+            state,
+            LoginState::UnusualDeviceVerify {
+                sig: unusual_sig.clone(),
+                message: "Please verify".to_string(),
+            }
+        );
+        assert!(!context.is_online());
+
+        // 2. The verification QR code request carries the sig in tlv 0x11
+        assert_eq!(context.fetch_unusual_device_qrcode(&unusual_sig).await.unwrap(), VERIFY_URL);
+        let decrypted = {
+            let request = &server.received()[1];
+            let mut keystore = context.keystore.write().unwrap();
+            WtLogin::new(&mut keystore, context.app_info.inner())
+                .unwrap()
+                .decrypt_test_request(&request.data)
+        };
+        let mut tlv_11 = vec![0x00, 0x11, 0x00, unusual_sig.len() as u8];
+        tlv_11.extend_from_slice(&unusual_sig);
+        assert!(decrypted.windows(tlv_11.len()).any(|w| w == tlv_11.as_slice()));
+
+        // 3. Once the code is confirmed, the login resumes with the new credentials
+        assert_eq!(context.wait_unusual_device_verify().await.unwrap(), LoginState::Success);
+        assert!(context.is_online());
+        let commands: Vec<_> = server.received().into_iter().map(|packet| packet.command).take(4).collect();
+        assert_eq!(commands, ["wtlogin.login", "wtlogin.trans_emp", "wtlogin.trans_emp", "wtlogin.login"]);
+
+        let keystore = context.keystore.read().unwrap();
+        assert_eq!(keystore.uin, Some(123456789));
+        assert_eq!(keystore.sigs.a1, vec![0x18; 32]);
+        assert_eq!(keystore.sigs.no_pic_sig, Some(vec![0x19; 32]));
+        assert_eq!(keystore.sigs.tgtgt_key, tgtgt_key.to_vec());
+        assert_eq!(keystore.sigs.a2, vec![0xA2; 48]);
+        assert_eq!(keystore.sigs.d2, vec![0xD2; 48]);
+        assert_eq!(keystore.sigs.d2_key, vec![0xDD; 16]);
+    }
+
+    fn android_response(ret_code: u8, tlvs: Vec<(u16, Vec<u8>)>) -> LoginEventRespAndroid {
+        LoginEventRespAndroid { ret_code, error: None, tlvs: tlvs.into_iter().collect() }
+    }
+
+    fn success_response() -> LoginEventRespAndroid {
+        let uid = Tlv543 {
+            layer1: Some(Tlv543Layer1 {
+                layer2: Some(Tlv543Layer2 { uid: Some("u_bot".to_string()) }),
+            }),
+        };
+        let mut profile = vec![0x00, 0x01, 25, 1, 3];
+        profile.extend_from_slice(b"Bot");
+
+        android_response(0, vec![
+            (0x10A, vec![0xA2; 48]),
+            (0x143, vec![0xD2; 48]),
+            (0x305, vec![0xDD; 16]),
+            (0x543, uid.encode_to_vec().unwrap()),
+            (TLV_PROFILE, profile),
+        ])
+    }
+
+    /// Run the password login against `script`, answering the states that need it with `answers`.
+    /// Returns the final state, the commands sent and the states answered.
+    async fn run_script(
+        context: &Arc<BotContext>,
+        script: Vec<LoginEventRespAndroid>,
+        answers: Vec<Option<&str>>,
+    ) -> (LoginState, Vec<LoginEventReqAndroid>, Vec<LoginState>) {
+        let script = Mutex::new(VecDeque::from(script));
+        let answers = Mutex::new(VecDeque::from(answers));
+        let sent = Mutex::new(Vec::new());
+        let prompted = Mutex::new(Vec::new());
+
+        let state = context
+            .drive_password_login(
+                "hunter2",
+                |state| {
+                    prompted.lock().unwrap().push(state);
+                    let answer = answers.lock().unwrap().pop_front().unwrap();
+                    async move { answer.map(str::to_string) }
+                },
+                |request| {
+                    sent.lock().unwrap().push(request);
+                    let response = script.lock().unwrap().pop_front().expect("script exhausted");
+                    async move { Ok(response) }
+                },
+            )
+            .await
+            .unwrap();
+
+        (state, sent.into_inner().unwrap(), prompted.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_password_login_success() {
+        let context = BotContext::builder().build();
+        let (state, sent, prompted) = run_script(&context, vec![success_response()], vec![]).await;
+
+        assert_eq!(state, LoginState::Success);
+        assert!(prompted.is_empty());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].cmd, LoginCommand::Tgtgt);
+        assert_eq!(sent[0].password, "hunter2");
+
+        let keystore = context.keystore.read().unwrap();
+        assert_eq!(keystore.sigs.d2_key, vec![0xDD; 16]);
+        assert_eq!(keystore.uid.as_deref(), Some("u_bot"));
+        let info = keystore.bot_info.as_ref().unwrap();
+        assert_eq!((info.name.as_str(), info.age), ("Bot", 25));
+    }
+
+    #[tokio::test]
+    async fn test_password_login_captcha() {
+        let context = BotContext::builder().build();
+        let captcha = android_response(2, vec![
+            (TLV_CAPTCHA_URL, b"https://ssl.captcha.qq.com/template/wireless_mqq_captcha.html?sid=1".to_vec()),
+            (0x104, b"verify-token".to_vec()),
+        ]);
+        let (state, sent, prompted) = run_script(
+            &context,
+            vec![captcha, success_response()],
+            vec![Some("t03ticket")],
+        )
+        .await;
+
+        assert_eq!(state, LoginState::Success);
+        assert_eq!(
+            prompted,
+            vec![LoginState::CaptchaRequired {
+                url: "https://ssl.captcha.qq.com/template/wireless_mqq_captcha.html?sid=1".to_string(),
+            }]
+        );
+        assert_eq!(sent.iter().map(|request| request.cmd).collect::<Vec<_>>(), [LoginCommand::Tgtgt, LoginCommand::Captcha]);
+        assert_eq!(sent[1].ticket, "t03ticket");
+        // The captcha answer refers back to the token of the first response
+        assert_eq!(
+            context.keystore.read().unwrap().state.tlv_cache.get(&0x104),
+            Some(&b"verify-token".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_password_login_sms() {
+        let context = BotContext::builder().build();
+        let mut phone = BinaryPacket::with_capacity(32);
+        phone.write_str("86", Prefix::INT16);
+        phone.write_str("138****0000", Prefix::INT16);
+        let sms = android_response(160, vec![(TLV_SMS_PHONE, phone.to_vec()), (0x174, b"sms-sig".to_vec())]);
+
+        let (state, sent, prompted) = run_script(
+            &context,
+            vec![sms.clone(), sms, success_response()],
+            vec![Some("123456")],
+        )
+        .await;
+
+        assert_eq!(state, LoginState::Success);
+        assert_eq!(
+            prompted,
+            vec![LoginState::SmsRequired { phone: "+86 138****0000".to_string(), message: String::new() }]
+        );
+        assert_eq!(
+            sent.iter().map(|request| request.cmd).collect::<Vec<_>>(),
+            [LoginCommand::Tgtgt, LoginCommand::FetchSMSCode, LoginCommand::SubmitSMSCode]
+        );
+        assert_eq!(sent[2].code, "123456");
+    }
+
+    #[tokio::test]
+    async fn test_password_login_unusual_device() {
+        let context = BotContext::builder().build();
+        let unusual = android_response(0xEF, vec![
+            (0x174, b"unusual-sig".to_vec()),
+            (0x17E, "请使用手机QQ扫码验证".as_bytes().to_vec()),
+        ]);
+        let (state, sent, prompted) = run_script(&context, vec![unusual], vec![]).await;
+
+        assert_eq!(
+            state,
+            LoginState::UnusualDeviceVerify {
+                sig: b"unusual-sig".to_vec(),
+                message: "请使用手机QQ扫码验证".to_string(),
+            }
+        );
+        assert!(prompted.is_empty());
+        assert_eq!(sent.len(), 1);
+        assert_eq!(
+            context.keystore.read().unwrap().state.tlv_cache.get(&0x174),
+            Some(&b"unusual-sig".to_vec())
+        );
+
+        // Without 0x174, the same state asks for an SMS code
+        let mut phone = BinaryPacket::with_capacity(32);
+        phone.write_str("86", Prefix::INT16);
+        phone.write_str("138****0000", Prefix::INT16);
+        let sms = android_response(0xEF, vec![(TLV_SMS_PHONE, phone.to_vec())]);
+        let (state, sent, _) = run_script(&context, vec![sms.clone(), sms], vec![None]).await;
+        assert_eq!(state, LoginState::SmsRequired { phone: "+86 138****0000".to_string(), message: String::new() });
+        assert_eq!(sent.last().unwrap().cmd, LoginCommand::FetchSMSCode);
+    }
+
+    #[tokio::test]
+    async fn test_password_login_gives_up_and_fails() {
+        let context = BotContext::builder().build();
+        let captcha = android_response(2, vec![(TLV_CAPTCHA_URL, b"https://captcha".to_vec())]);
+        let (state, _, _) = run_script(&context, vec![captcha], vec![None]).await;
+        assert_eq!(state, LoginState::CaptchaRequired { url: "https://captcha".to_string() });
+
+        let mut rejected = android_response(1, vec![]);
+        rejected.error = Some((
+            "Login failed".to_string(),
+            "Wrong password, see https://accounts.qq.com/find for help".to_string(),
+        ));
+        let (state, _, _) = run_script(&context, vec![rejected], vec![]).await;
+        assert_eq!(
+            state,
+            LoginState::Failed {
+                code: 1,
+                message: "Login failed: Wrong password, see https://accounts.qq.com/find for help".to_string(),
+                tag146_url: Some("https://accounts.qq.com/find".to_string()),
+            }
+        );
+
+        let locked = android_response(204, vec![(TLV_DEVICE_LOCK_URL, b"https://accounts.qq.com/safe".to_vec())]);
+        let (state, _, _) = run_script(&context, vec![locked], vec![]).await;
+        assert_eq!(state, LoginState::DeviceLocked { url: "https://accounts.qq.com/safe".to_string() });
+    }
+
+    fn new_qrcode() -> QrCodeInfo {
+        QrCodeInfo {
+            url: "https://txz.qq.com/p?k=new".to_string(),
+            image: Bytes::from_static(b"\x89PNG"),
+            qr_sig: b"new-sig".to_vec(),
+            expires_in: DEFAULT_QRCODE_LIFETIME,
+        }
+    }
+
+    /// A QR login answering the polls with the states in `script`, counting the polls in `polls`
+    fn scripted_qrcode_login(
+        context: &Arc<BotContext>,
+        refresh_expired: bool,
+        script: Vec<u8>,
+        polls: Arc<AtomicUsize>,
+    ) -> QrLoginStream {
+        let script = Mutex::new(VecDeque::from(script));
+        QrLoginStream::spawn(
+            context.clone(),
+            refresh_expired,
+            move |_| {
+                polls.fetch_add(1, Ordering::SeqCst);
+                let response = script.lock().unwrap().pop_front().map(|ret_code| TransEmp12EventResp {
+                    ret_code,
+                    uin: (ret_code == 0).then_some(123456789),
+                    retry: None,
+                    tlv_1e: (ret_code == 0).then(|| vec![0x1E; 16]),
+                    tlv_19: None,
+                    tlv_18: (ret_code == 0).then(|| vec![0x18; 32]),
+                });
+                async move { response.ok_or_else(|| Error::network(std::io::ErrorKind::UnexpectedEof, "script exhausted")) }
+            },
+            |_| async { Ok(new_qrcode()) },
+            |_| async { Ok(LoginState::Success) },
+        )
+    }
+
+    async fn collect(mut stream: QrLoginStream) -> Vec<QrLoginState> {
+        let mut states = Vec::new();
+        while let Some(state) = stream.next().await {
+            states.push(state);
+        }
+        states
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_qrcode_login_states() {
+        let context = BotContext::builder().build();
+        let polls = Arc::new(AtomicUsize::new(0));
+        let stream = scripted_qrcode_login(&context, false, vec![48, 48, 53, 53, 0], polls.clone());
+
+        // Repeated poll results are reported once
+        assert_eq!(
+            collect(stream).await,
+            [QrLoginState::WaitingScan, QrLoginState::Scanned, QrLoginState::Confirmed, QrLoginState::LoggedIn]
+        );
+        assert_eq!(polls.load(Ordering::SeqCst), 5);
+
+        let keystore = context.keystore.read().unwrap();
+        assert_eq!(keystore.uin, Some(123456789));
+        assert_eq!(keystore.sigs.tgtgt_key, vec![0x1E; 16]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_qrcode_expired() {
+        let context = BotContext::builder().build();
+        let polls = Arc::new(AtomicUsize::new(0));
+
+        let stream = scripted_qrcode_login(&context, true, vec![48, 17, 48, 54], polls.clone());
+        assert_eq!(
+            collect(stream).await,
+            [
+                QrLoginState::WaitingScan,
+                QrLoginState::Expired(Some(new_qrcode())),
+                QrLoginState::WaitingScan,
+                QrLoginState::Failed(LoginState::Failed {
+                    code: 54,
+                    message: "QR code login ended with Canceled".to_string(),
+                    tag146_url: None,
+                }),
+            ]
+        );
+
+        let stream = scripted_qrcode_login(&context, false, vec![17, 48], polls.clone());
+        assert_eq!(collect(stream).await, [QrLoginState::Expired(None)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_qrcode_drop_stops_polling() {
+        let context = BotContext::builder().build();
+        let polls = Arc::new(AtomicUsize::new(0));
+        let mut stream = scripted_qrcode_login(&context, false, vec![48; 100], polls.clone());
+
+        assert_eq!(stream.next().await, Some(QrLoginState::WaitingScan));
+        drop(stream);
+        let polled = polls.load(Ordering::SeqCst);
+
+        tokio::time::sleep(QRCODE_POLL_INTERVAL * 10).await;
+        assert_eq!(polls.load(Ordering::SeqCst), polled);
+    }
+
+    #[tokio::test]
+    async fn test_logout() {
+        let context = BotContext::builder().build();
+        let mut events = context.event.subscribe_to::<BotOfflineEvent>();
+        {
+            let mut keystore = context.keystore.write().unwrap();
+            keystore.sigs.a2 = vec![0xA2; 64];
+            keystore.state.qr_sig = Some(b"qr-sig".to_vec());
+        }
+        context.set_online(true);
+
+        // Not connected, the unregister request fails and the logout goes on
+        context.logout().await.unwrap();
+        assert!(!context.is_online());
+        assert!(context.is_logged_out());
+        assert_eq!(events.recv().await.unwrap().kind, OfflineKind::Logout);
+
+        let keystore = context.keystore.read().unwrap().clone();
+        assert_eq!(keystore.sigs.a2, vec![0xA2; 64]);
+        assert!(keystore.state.qr_sig.is_none());
+
+        let response = context
+            .send_and_wait::<UnRegisterEventReq, UnRegisterEventResp>(UnRegisterEventReq {}, Duration::from_secs(1))
+            .await;
+        assert!(matches!(response, Err(Error::LoggedOut)));
+    }
+}
@@ -0,0 +1,380 @@
+use crate::common::{Announcement, AnnouncementImage, Cookies};
+use crate::internal::context::HttpRequest;
+use crate::utils::image;
+use crate::{BotContext, Error};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::Arc;
+
+const ANNOUNCE_API: &str = "https://web.qun.qq.com/cgi-bin/announce";
+/// Domain whose pskey authorizes the announcement API
+const ANNOUNCE_DOMAIN: &str = "qun.qq.com";
+const MULTIPART_BOUNDARY: &str = "----LagrangeAnnouncementBoundary";
+
+impl BotContext {
+    /// Announcements of `group_uin`, pinned ones first.
+    pub async fn fetch_group_announcements(self: &Arc<Self>, group_uin: u64) -> Result<Vec<Announcement>, Error> {
+        let cookies = self.fetch_cookies(ANNOUNCE_DOMAIN).await?;
+        let url = format!(
+            "{}/get_t_list?bkn={}&qid={}&ft=23&s=-1&n=20&ni=1&i=1",
+            ANNOUNCE_API, cookies.bkn, group_uin
+        );
+        let list: FeedList = self.send_web(&cookies, HttpRequest::get(url), "fetch announcements").await?;
+
+        Ok(list.inst.into_iter().chain(list.feeds).map(Feed::into_announcement).collect())
+    }
+
+    /// Publish an announcement in `group_uin` and return its id.
+    ///
+    /// `image` is uploaded to the announcement server first; JPEG, PNG and GIF are accepted.
+    pub async fn publish_group_announcement(
+        self: &Arc<Self>,
+        group_uin: u64,
+        text: impl Into<String>,
+        image: Option<&[u8]>,
+        pinned: bool,
+    ) -> Result<String, Error> {
+        let cookies = self.fetch_cookies(ANNOUNCE_DOMAIN).await?;
+        let image = match image {
+            Some(data) => Some(self.upload_announcement_image(&cookies, data).await?),
+            None => None,
+        };
+
+        let request = publish_request(&cookies, group_uin, &text.into(), image.as_ref(), pinned);
+        let published: Published = self.send_web(&cookies, request, "publish announcement").await?;
+        Ok(published.new_fid)
+    }
+
+    /// Delete the announcement `id` of `group_uin`.
+    pub async fn delete_group_announcement(self: &Arc<Self>, group_uin: u64, id: &str) -> Result<(), Error> {
+        let cookies = self.fetch_cookies(ANNOUNCE_DOMAIN).await?;
+        let bkn = cookies.bkn.to_string();
+        let group = group_uin.to_string();
+        let request = HttpRequest::post_form(
+            format!("{}/del_feed?bkn={}", ANNOUNCE_API, bkn),
+            [("fid", id), ("qid", &group), ("bkn", &bkn), ("ft", "23"), ("op", "1")],
+        );
+        let _: Status = self.send_web(&cookies, request, "delete announcement").await?;
+        Ok(())
+    }
+
+    async fn upload_announcement_image(
+        self: &Arc<Self>,
+        cookies: &Cookies,
+        data: &[u8],
+    ) -> Result<AnnouncementImage, Error> {
+        let format = image::probe(data)
+            .ok_or_else(|| Error::BuildError("Unsupported announcement image format".to_string()))?
+            .format;
+
+        let bkn = cookies.bkn.to_string();
+        let mut body = Vec::with_capacity(data.len() + 512);
+        for (name, value) in [("bkn", bkn.as_str()), ("source", "troopNotice"), ("m", "0")] {
+            body.extend(format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                MULTIPART_BOUNDARY, name, value
+            ).into_bytes());
+        }
+        body.extend(format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"pic_up\"; filename=\"image.{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            MULTIPART_BOUNDARY,
+            format.extension()
+        ).into_bytes());
+        body.extend_from_slice(data);
+        body.extend(format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY).into_bytes());
+
+        let request = HttpRequest::post(format!("{}/upload_img", ANNOUNCE_API), body).header(
+            "Content-Type",
+            format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY),
+        );
+        let uploaded: Uploaded = self.send_web(cookies, request, "upload announcement image").await?;
+
+        // The id is a JSON object, HTML-escaped into a string
+        let picture: Picture = serde_json::from_str(&unescape_html(&uploaded.id))
+            .map_err(|e| Error::ParseError(format!("Invalid announcement image id: {}", e)))?;
+        Ok(picture.into_image())
+    }
+
+    /// Send `request` with `cookies` and decode its JSON reply, failing on a non-zero `ec`
+    async fn send_web<T: DeserializeOwned>(
+        &self,
+        cookies: &Cookies,
+        request: HttpRequest,
+        action: &str,
+    ) -> Result<T, Error> {
+        let body = self.http.send(request.header("Cookie", cookies.header())).await?.body;
+
+        let status: Status = serde_json::from_slice(&body)
+            .map_err(|e| Error::ParseError(format!("Failed to {}: {}", action, e)))?;
+        if status.ec != 0 {
+            return Err(Error::ProtocolError(format!(
+                "Failed to {}: {} (ec {})",
+                action, status.em, status.ec
+            )));
+        }
+
+        serde_json::from_slice(&body).map_err(|e| Error::ParseError(format!("Failed to {}: {}", action, e)))
+    }
+}
+
+fn publish_request(
+    cookies: &Cookies,
+    group_uin: u64,
+    text: &str,
+    image: Option<&AnnouncementImage>,
+    pinned: bool,
+) -> HttpRequest {
+    let bkn = cookies.bkn.to_string();
+    let group = group_uin.to_string();
+    let settings = r#"{"is_show_edit_card":0,"tip_window_type":1,"confirm_required":1}"#;
+
+    let mut fields = vec![
+        ("qid", group.clone()),
+        ("bkn", bkn.clone()),
+        ("text", text.to_string()),
+        ("pinned", (pinned as u8).to_string()),
+        ("type", "1".to_string()),
+        ("settings", settings.to_string()),
+    ];
+    if let Some(image) = image {
+        fields.push(("pic", image.id.clone()));
+        fields.push(("imgWidth", image.width.to_string()));
+        fields.push(("imgHeight", image.height.to_string()));
+    }
+
+    HttpRequest::post_form(
+        format!("{}/add_qun_notice?bkn={}", ANNOUNCE_API, bkn),
+        fields.iter().map(|(name, value)| (*name, value.as_str())),
+    )
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#10;", "\n")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[derive(Deserialize)]
+struct Status {
+    ec: i32,
+    #[serde(default)]
+    em: String,
+}
+
+#[derive(Deserialize)]
+struct FeedList {
+    #[serde(default)]
+    feeds: Vec<Feed>,
+    /// Pinned announcements
+    #[serde(default)]
+    inst: Vec<Feed>,
+}
+
+#[derive(Deserialize)]
+struct Feed {
+    fid: String,
+    u: u64,
+    pubt: i64,
+    msg: FeedMessage,
+    #[serde(default)]
+    pinned: u8,
+}
+
+#[derive(Deserialize)]
+struct FeedMessage {
+    text: String,
+    #[serde(default)]
+    pics: Vec<Picture>,
+}
+
+#[derive(Deserialize)]
+struct Picture {
+    id: String,
+    #[serde(default)]
+    w: String,
+    #[serde(default)]
+    h: String,
+}
+
+#[derive(Deserialize)]
+struct Published {
+    new_fid: String,
+}
+
+#[derive(Deserialize)]
+struct Uploaded {
+    id: String,
+}
+
+impl Feed {
+    fn into_announcement(self) -> Announcement {
+        Announcement {
+            id: self.fid,
+            author: self.u,
+            publish_time: self.pubt,
+            content: unescape_html(&self.msg.text),
+            image: self.msg.pics.into_iter().next().map(Picture::into_image),
+            pinned: self.pinned != 0,
+        }
+    }
+}
+
+impl Picture {
+    fn into_image(self) -> AnnouncementImage {
+        AnnouncementImage {
+            width: self.w.parse().unwrap_or_default(),
+            height: self.h.parse().unwrap_or_default(),
+            id: self.id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::CookieSet;
+    use crate::internal::context::{HttpClient, HttpMethod, HttpResponse};
+    use crate::keystore::BotKeystore;
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use std::sync::Mutex;
+
+    /// Replies to each request with the body registered for the first matching url fragment
+    struct MockWeb {
+        replies: Vec<(&'static str, &'static str)>,
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockWeb {
+        async fn send(&self, request: HttpRequest) -> crate::Result<HttpResponse> {
+            let body = self
+                .replies
+                .iter()
+                .find(|(fragment, _)| request.url.contains(fragment))
+                .map(|(_, body)| Bytes::from_static(body.as_bytes()))
+                .ok_or_else(|| Error::network(std::io::ErrorKind::NotFound, format!("unexpected request to {}", request.url)))?;
+            self.requests.lock().unwrap().push(request);
+            Ok(HttpResponse { status: 200, headers: Vec::new(), body })
+        }
+    }
+
+    /// bkn of the skey `@abcdEFGH`
+    const BKN: u32 = 2082277385;
+
+    fn context(replies: Vec<(&'static str, &'static str)>) -> (Arc<BotContext>, Arc<MockWeb>) {
+        let web = Arc::new(MockWeb { replies, requests: Mutex::new(Vec::new()) });
+        let cookies = CookieSet { skey: "@abcdEFGH".to_string(), pskey: "pskey".to_string(), expires_at: i64::MAX };
+        let keystore = BotKeystore::new().with_uin(10001);
+        keystore.state.cookies.set(ANNOUNCE_DOMAIN, cookies);
+        let context = BotContext::builder().keystore(keystore).http_client(web.clone()).build();
+        (context, web)
+    }
+
+    fn form(request: &HttpRequest) -> Vec<(String, String)> {
+        form_urlencoded::parse(request.body.as_deref().unwrap()).into_owned().collect()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_announcements() {
+        // Synthetic reply laid out like the announcement web API
+        let list = r#"{"ec":0,"em":"",
+            "feeds":[{"fid":"feed1","u":10002,"pubt":1700000000,"msg":{"text":"line&#10;&quot;two&quot;"}}],
+            "inst":[{"fid":"feed0","u":10003,"pubt":1690000000,"pinned":1,
+                "msg":{"text":"rules","pics":[{"id":"pic0","w":"640","h":"480"}]}}]}"#;
+        let (context, web) = context(vec![("get_t_list", list)]);
+
+        let announcements = context.fetch_group_announcements(123456).await.unwrap();
+        assert_eq!(
+            announcements,
+            vec![
+                Announcement {
+                    id: "feed0".to_string(),
+                    author: 10003,
+                    publish_time: 1690000000,
+                    content: "rules".to_string(),
+                    image: Some(AnnouncementImage { id: "pic0".to_string(), width: 640, height: 480 }),
+                    pinned: true,
+                },
+                Announcement {
+                    id: "feed1".to_string(),
+                    author: 10002,
+                    publish_time: 1700000000,
+                    content: "line\n\"two\"".to_string(),
+                    image: None,
+                    pinned: false,
+                },
+            ]
+        );
+
+        let requests = web.requests.lock().unwrap();
+        assert_eq!(requests[0].method, HttpMethod::Get);
+        assert!(requests[0].url.contains(&format!("bkn={}&qid=123456", BKN)));
+        assert_eq!(
+            requests[0].header_value("cookie"),
+            Some("uin=o0000010001; skey=@abcdEFGH; p_uin=o0000010001; p_skey=pskey")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_publish_with_image() {
+        const PNG_HEADER: &[u8] = &[
+            0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13, b'I', b'H', b'D', b'R', 0, 0, 0x02, 0x80,
+            0, 0, 0x01, 0xE0,
+        ];
+        let (context, web) = context(vec![
+            ("upload_img", r#"{"ec":0,"id":"{&quot;h&quot;:&quot;480&quot;,&quot;w&quot;:&quot;640&quot;,&quot;id&quot;:&quot;pic1&quot;}"}"#),
+            ("add_qun_notice", r#"{"ec":0,"em":"","new_fid":"feed2"}"#),
+        ]);
+
+        let id = context
+            .publish_group_announcement(123456, "hello & welcome", Some(PNG_HEADER), true)
+            .await
+            .unwrap();
+        assert_eq!(id, "feed2");
+
+        let requests = web.requests.lock().unwrap();
+        let upload = &requests[0];
+        assert!(upload.header_value("content-type").unwrap().starts_with("multipart/form-data; boundary="));
+        let upload_body = upload.body.as_deref().unwrap();
+        assert!(upload_body.windows(PNG_HEADER.len()).any(|window| window == PNG_HEADER));
+        assert!(String::from_utf8_lossy(upload_body).contains("filename=\"image.png\""));
+
+        let bkn = BKN.to_string();
+        assert_eq!(requests[1].url, format!("{}/add_qun_notice?bkn={}", ANNOUNCE_API, bkn));
+        let fields = form(&requests[1]);
+        let field = |name: &str| fields.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        assert_eq!(field("qid"), Some("123456"));
+        assert_eq!(field("bkn"), Some(bkn.as_str()));
+        assert_eq!(field("text"), Some("hello & welcome"));
+        assert_eq!(field("pinned"), Some("1"));
+        assert_eq!(field("pic"), Some("pic1"));
+        assert_eq!((field("imgWidth"), field("imgHeight")), (Some("640"), Some("480")));
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_error() {
+        let (context, web) = context(vec![
+            ("del_feed", r#"{"ec":0,"em":""}"#),
+            ("get_t_list", r#"{"ec":1,"em":"no permission"}"#),
+        ]);
+
+        context.delete_group_announcement(123456, "feed1").await.unwrap();
+        let fields = form(&web.requests.lock().unwrap()[0]);
+        assert!(fields.contains(&("fid".to_string(), "feed1".to_string())));
+        assert!(fields.contains(&("qid".to_string(), "123456".to_string())));
+
+        let error = context.fetch_group_announcements(123456).await.unwrap_err();
+        assert!(error.to_string().contains("no permission"));
+    }
+
+    #[tokio::test]
+    async fn test_needs_login() {
+        let context = BotContext::builder().build();
+        assert!(context.fetch_group_announcements(123456).await.is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::common::{Avatar, AvatarSize};
+use crate::internal::context::{HttpRequest, HttpResponse};
+use crate::{BotContext, Error};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+impl BotContext {
+    /// Avatar of the user `uin`.
+    ///
+    /// Kept in `BotConfig::avatar_cache_dir` if set and only downloaded again once the server
+    /// reports a new ETag.
+    pub async fn download_avatar(self: &Arc<Self>, uin: u64, size: AvatarSize) -> Result<Avatar, Error> {
+        let key = format!("user-{}-{}", uin, size.pixels());
+        self.download_avatar_from(&size.user_url(uin), &key).await
+    }
+
+    /// Avatar of the group `group_uin`, cached like [`BotContext::download_avatar`]
+    pub async fn download_group_avatar(self: &Arc<Self>, group_uin: u64, size: AvatarSize) -> Result<Avatar, Error> {
+        let key = format!("group-{}-{}", group_uin, size.pixels());
+        self.download_avatar_from(&size.group_url(group_uin), &key).await
+    }
+
+    async fn download_avatar_from(&self, url: &str, key: &str) -> Result<Avatar, Error> {
+        let cache = self.config.avatar_cache_dir.as_deref().map(|dir| AvatarCache::new(dir, key));
+        let cached = match &cache {
+            Some(cache) => cache.load().await,
+            None => None,
+        };
+
+        // Redirects are taken apart to tell the placeholder from an avatar of the account
+        let mut request = HttpRequest::get(url).without_redirects();
+        if let Some((etag, _)) = &cached {
+            request = request.header("If-None-Match", etag.as_str());
+        }
+        let response = self.http.send(request).await?;
+
+        match response.status {
+            304 => cached.map(|(_, avatar)| avatar).ok_or_else(|| {
+                Error::ProtocolError(format!("{} is not modified, but there is no cached avatar", url))
+            }),
+            300..=399 => {
+                let location = response
+                    .header_value("Location")
+                    .ok_or_else(|| Error::ProtocolError(format!("{} redirects without a location", url)))?;
+                let location = reqwest::Url::parse(url)
+                    .and_then(|base| base.join(location))
+                    .map_err(|e| Error::ProtocolError(format!("Invalid avatar redirect {}: {}", location, e)))?;
+                // Not cached, the account may set an avatar any time
+                let response = self.http.send(HttpRequest::get(location.as_str())).await?;
+                Ok(avatar_of(response, true))
+            }
+            _ => {
+                let etag = response.header_value("ETag").map(str::to_string);
+                let avatar = avatar_of(response, false);
+                if let (Some(cache), Some(etag)) = (&cache, etag) {
+                    cache.store(&etag, &avatar).await;
+                }
+                Ok(avatar)
+            }
+        }
+    }
+}
+
+fn avatar_of(response: HttpResponse, is_default: bool) -> Avatar {
+    let content_type = response.header_value("Content-Type").unwrap_or(DEFAULT_CONTENT_TYPE).to_string();
+    Avatar { data: response.body, content_type, is_default }
+}
+
+/// What is kept next to a cached avatar
+#[derive(Serialize, Deserialize)]
+struct CachedAvatar {
+    etag: String,
+    content_type: String,
+}
+
+/// The image and the [`CachedAvatar`] of one uin and size in the cache directory
+struct AvatarCache {
+    image: PathBuf,
+    meta: PathBuf,
+}
+
+impl AvatarCache {
+    fn new(dir: &Path, key: &str) -> Self {
+        Self { image: dir.join(format!("{}.img", key)), meta: dir.join(format!("{}.json", key)) }
+    }
+
+    /// The ETag and the avatar, `None` if nothing usable is cached
+    async fn load(&self) -> Option<(String, Avatar)> {
+        let meta = tokio::fs::read(&self.meta).await.ok()?;
+        let meta: CachedAvatar = serde_json::from_slice(&meta).ok()?;
+        let data = tokio::fs::read(&self.image).await.ok()?;
+        let avatar = Avatar { data: data.into(), content_type: meta.content_type, is_default: false };
+        Some((meta.etag, avatar))
+    }
+
+    /// Store `avatar`; a failure only costs a download, so it is logged
+    async fn store(&self, etag: &str, avatar: &Avatar) {
+        let meta = CachedAvatar { etag: etag.to_string(), content_type: avatar.content_type.clone() };
+        let result = async {
+            if let Some(dir) = self.image.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            // The image goes first, so a meta file always has its image
+            tokio::fs::write(&self.image, &avatar.data).await?;
+            tokio::fs::write(&self.meta, serde_json::to_vec(&meta)?).await
+        };
+        if let Err(e) = result.await {
+            tracing::warn!(path = %self.image.display(), error = %e, "Failed to cache avatar");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BotConfig;
+    use crate::internal::context::HttpClient;
+    use async_trait::async_trait;
+    use bytes::Bytes;
+    use std::sync::Mutex;
+
+    const IMAGE: &[u8] = b"\xFF\xD8\xFF\xE0synthetic jpeg";
+    const PLACEHOLDER: &[u8] = b"\x89PNGsynthetic placeholder";
+
+    /// Serves one avatar with the ETag `"v1"` at each size, the placeholder for the user 10002
+    /// and nothing for the user 10003
+    #[derive(Default)]
+    struct MockQlogo {
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockQlogo {
+        async fn send(&self, request: HttpRequest) -> crate::error::Result<HttpResponse> {
+            self.requests.lock().unwrap().push(request.clone());
+            let headers =
+                |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+
+            if request.url.contains("nk=10003") {
+                // Like the reqwest client, which fails on error statuses
+                return Err(Error::network(std::io::ErrorKind::Other, "GET failed: 404 Not Found"));
+            }
+            if request.url.contains("nk=10002") {
+                return Ok(HttpResponse {
+                    status: 302,
+                    headers: headers(&[("Location", "/default/placeholder.png")]),
+                    body: Bytes::new(),
+                });
+            }
+            if request.url.ends_with("/default/placeholder.png") {
+                return Ok(HttpResponse {
+                    status: 200,
+                    headers: headers(&[("Content-Type", "image/png")]),
+                    body: Bytes::from_static(PLACEHOLDER),
+                });
+            }
+            if request.header_value("If-None-Match") == Some("\"v1\"") {
+                return Ok(HttpResponse { status: 304, headers: Vec::new(), body: Bytes::new() });
+            }
+            Ok(HttpResponse {
+                status: 200,
+                headers: headers(&[("Content-Type", "image/jpeg"), ("ETag", "\"v1\"")]),
+                body: Bytes::from_static(IMAGE),
+            })
+        }
+    }
+
+    fn context(config: BotConfig) -> (Arc<BotContext>, Arc<MockQlogo>) {
+        let web = Arc::new(MockQlogo::default());
+        (BotContext::builder().config(config).http_client(web.clone()).build(), web)
+    }
+
+    #[tokio::test]
+    async fn test_download_avatar() {
+        let (context, web) = context(BotConfig::default());
+
+        let avatar = context.download_avatar(10001, AvatarSize::Large).await.unwrap();
+        assert_eq!(avatar.data.as_ref(), IMAGE);
+        assert_eq!((avatar.content_type.as_str(), avatar.is_default), ("image/jpeg", false));
+
+        let group = context.download_group_avatar(123456, AvatarSize::Small).await.unwrap();
+        assert_eq!(group.data.as_ref(), IMAGE);
+
+        let requests = web.requests.lock().unwrap();
+        assert_eq!(requests[0].url, "https://q.qlogo.cn/g?b=qq&nk=10001&s=140");
+        assert!(!requests[0].follow_redirects);
+        assert_eq!(requests[1].url, "https://p.qlogo.cn/gh/123456/123456/40/");
+    }
+
+    #[tokio::test]
+    async fn test_default_avatar_redirect() {
+        let (context, web) = context(BotConfig::default());
+
+        let avatar = context.download_avatar(10002, AvatarSize::Original).await.unwrap();
+        assert!(avatar.is_default);
+        assert_eq!(avatar.data.as_ref(), PLACEHOLDER);
+        assert_eq!(avatar.content_type, "image/png");
+        assert_eq!(web.requests.lock().unwrap()[1].url, "https://q.qlogo.cn/default/placeholder.png");
+    }
+
+    #[tokio::test]
+    async fn test_missing_avatar() {
+        let dir = tempfile::tempdir().unwrap();
+        let (context, _) = context(BotConfig::builder().avatar_cache_dir(dir.path()).build());
+
+        let result = context.download_avatar(10003, AvatarSize::Original).await;
+        assert!(matches!(result, Err(Error::Network(_))));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cache_revalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let (context, web) = context(BotConfig::builder().avatar_cache_dir(dir.path().join("avatars")).build());
+
+        let first = context.download_avatar(10001, AvatarSize::Medium).await.unwrap();
+        let second = context.download_avatar(10001, AvatarSize::Medium).await.unwrap();
+        assert_eq!(first, second);
+
+        let requests = web.requests.lock().unwrap().clone();
+        assert_eq!(requests[0].header_value("If-None-Match"), None);
+        assert_eq!(requests[1].header_value("If-None-Match"), Some("\"v1\""));
+        assert!(dir.path().join("avatars/user-10001-100.img").exists());
+
+        // Placeholders are not cached
+        context.download_avatar(10002, AvatarSize::Medium).await.unwrap();
+        assert!(!dir.path().join("avatars/user-10002-100.img").exists());
+    }
+}
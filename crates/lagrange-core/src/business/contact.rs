@@ -0,0 +1,741 @@
+use crate::common::{
+    BotFriend, BotFriendCategory, BotGroup, BotGroupMember, BotInfo, BotStranger, BotUserInfo, UserId,
+};
+use crate::error::OidbError;
+use crate::internal::services::system::{
+    FetchFriendsEventReq, FetchFriendsEventResp, FetchFriendsService, FetchGroupExtraEventReq,
+    FetchGroupExtraService, FetchGroupsEventReq, FetchGroupsService, FetchMembersEventReq,
+    FetchMembersService, FetchStrangerEventReq, FetchStrangerService, FetchUserInfoEventReq,
+    FetchUserInfoEventResp, FetchUserInfoService, GroupExtra,
+};
+use crate::{BotContext, Error};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+impl BotContext {
+    /// Fetch the whole friend list, following the continuation token until the last page.
+    ///
+    /// The result also refreshes the friend cache, so their uids can be resolved afterwards.
+    pub async fn fetch_friends(self: &Arc<Self>) -> Result<Vec<BotFriend>, Error> {
+        let mut pages = Vec::new();
+        let mut next_uin = None;
+
+        loop {
+            let page = self
+                .event
+                .send::<FetchFriendsService>(FetchFriendsEventReq { next_uin }, self.clone())
+                .await?;
+
+            // Guard against a server echoing the same token forever
+            let done = page.next_uin.is_none() || page.next_uin == next_uin;
+            next_uin = page.next_uin;
+            pages.push(page);
+
+            if done {
+                break;
+            }
+        }
+
+        let friends = merge_friend_pages(pages);
+        self.cache.cache_friends(friends.clone());
+
+        Ok(friends)
+    }
+
+    /// Fetch all joined groups.
+    ///
+    /// Groups whose question, announcement and description all came back empty are looked up
+    /// again through the extended information request, which has them more often.
+    pub async fn fetch_groups(self: &Arc<Self>) -> Result<Vec<BotGroup>, Error> {
+        let mut groups = Vec::new();
+        let mut cookie = None;
+
+        loop {
+            let page = self
+                .event
+                .send::<FetchGroupsService>(FetchGroupsEventReq { cookie: cookie.clone() }, self.clone())
+                .await?;
+            groups.extend(page.groups);
+
+            // Guard against a server echoing the same cookie forever
+            if page.next_cookie.is_none() || page.next_cookie == cookie {
+                break;
+            }
+            cookie = page.next_cookie;
+        }
+
+        let missing: Vec<u64> = groups
+            .iter()
+            .filter(|group| group.description.is_none() && group.question.is_none() && group.announcement.is_none())
+            .map(|group| group.group_uin)
+            .collect();
+        for chunk in missing.chunks(GROUP_EXTRA_BATCH) {
+            let request = FetchGroupExtraEventReq { group_uins: chunk.to_vec() };
+            let extras = self.event.send::<FetchGroupExtraService>(request, self.clone()).await?;
+            apply_group_extra(&mut groups, extras.groups);
+        }
+
+        self.cache.cache_groups(groups.clone());
+
+        Ok(groups)
+    }
+
+    /// Fetch every member of `group_uin`, following the continuation token until the last page.
+    ///
+    /// The result also refreshes the member cache of the group. Use
+    /// [`BotContext::group_member_pages`] to process very large groups page by page instead.
+    pub async fn fetch_group_members(self: &Arc<Self>, group_uin: u64) -> Result<Vec<BotGroupMember>, Error> {
+        let mut pages = self.group_member_pages(group_uin);
+        let mut members = Vec::new();
+        while let Some(page) = pages.next().await {
+            members.extend(page?);
+        }
+
+        self.cache.cache_members(group_uin, members.clone());
+
+        Ok(members)
+    }
+
+    /// Fetch the friend list again, replacing the cached one
+    pub async fn refresh_friends(self: &Arc<Self>) -> Result<(), Error> {
+        self.fetch_friends().await.map(|_| ())
+    }
+
+    /// Fetch the joined groups again, replacing the cached ones
+    pub async fn refresh_groups(self: &Arc<Self>) -> Result<(), Error> {
+        self.fetch_groups().await.map(|_| ())
+    }
+
+    /// A friend from the cache, without touching the network.
+    ///
+    /// `None` if `uin` is not a friend, or the friend list has not been fetched yet or has
+    /// expired, see [`BotContext::friend`].
+    pub fn get_friend(&self, uin: u64) -> Option<Arc<BotFriend>> {
+        self.cache.friend(uin)
+    }
+
+    /// A joined group from the cache, without touching the network, see [`BotContext::get_friend`]
+    pub fn get_group(&self, group_uin: u64) -> Option<Arc<BotGroup>> {
+        self.cache.group(group_uin)
+    }
+
+    /// A group member from the cache, without touching the network, see [`BotContext::get_friend`]
+    pub fn get_group_member(&self, group_uin: u64, uin: u64) -> Option<Arc<BotGroupMember>> {
+        self.cache.member(group_uin, uin)
+    }
+
+    /// A stranger fetched with [`BotContext::fetch_stranger_info`] in the last few minutes, see
+    /// [`STRANGER_TTL`](crate::internal::context::cache::STRANGER_TTL)
+    pub fn get_stranger(&self, uin: u64) -> Option<Arc<BotStranger>> {
+        self.cache.stranger(uin)
+    }
+
+    /// A friend, fetching the friend list first if it is not cached or has expired
+    pub async fn friend(self: &Arc<Self>, uin: u64) -> Result<Option<Arc<BotFriend>>, Error> {
+        if !self.cache.has_friends() {
+            self.refresh_friends().await?;
+        }
+        Ok(self.cache.friend(uin))
+    }
+
+    /// All friends, fetching the friend list first if it is not cached or has expired
+    pub async fn friends(self: &Arc<Self>) -> Result<Vec<Arc<BotFriend>>, Error> {
+        match self.cache.friends() {
+            Some(friends) => Ok(friends),
+            None => Ok(self.fetch_friends().await?.into_iter().map(Arc::new).collect()),
+        }
+    }
+
+    /// A joined group, fetching the groups first if they are not cached or have expired
+    pub async fn group(self: &Arc<Self>, group_uin: u64) -> Result<Option<Arc<BotGroup>>, Error> {
+        if !self.cache.has_groups() {
+            self.refresh_groups().await?;
+        }
+        Ok(self.cache.group(group_uin))
+    }
+
+    /// All joined groups, fetching them first if they are not cached or have expired
+    pub async fn groups(self: &Arc<Self>) -> Result<Vec<Arc<BotGroup>>, Error> {
+        match self.cache.groups() {
+            Some(groups) => Ok(groups),
+            None => Ok(self.fetch_groups().await?.into_iter().map(Arc::new).collect()),
+        }
+    }
+
+    /// A member of `group_uin`, fetching the member list first if it is not cached or has expired
+    pub async fn group_member(self: &Arc<Self>, group_uin: u64, uin: u64) -> Result<Option<Arc<BotGroupMember>>, Error> {
+        if !self.cache.has_members(group_uin) {
+            self.fetch_group_members(group_uin).await?;
+        }
+        Ok(self.cache.member(group_uin, uin))
+    }
+
+    /// All members of `group_uin`, fetching them first if they are not cached or have expired
+    pub async fn group_members(self: &Arc<Self>, group_uin: u64) -> Result<Vec<Arc<BotGroupMember>>, Error> {
+        match self.cache.members(group_uin) {
+            Some(members) => Ok(members),
+            None => Ok(self.fetch_group_members(group_uin).await?.into_iter().map(Arc::new).collect()),
+        }
+    }
+
+    /// Iterate over the member list of `group_uin` one page at a time.
+    ///
+    /// Pages are only requested when [`GroupMemberPages::next`] is awaited, and the member cache
+    /// is left untouched.
+    pub fn group_member_pages(self: &Arc<Self>, group_uin: u64) -> GroupMemberPages {
+        GroupMemberPages {
+            context: self.clone(),
+            group_uin,
+            token: None,
+            done: false,
+        }
+    }
+}
+
+impl BotContext {
+    /// Fetch the profile card of any user, by uin or uid
+    pub async fn fetch_user_info(self: &Arc<Self>, user: impl Into<UserId>) -> Result<BotUserInfo, Error> {
+        let user = user.into();
+        let response = self
+            .event
+            .send::<FetchUserInfoService>(FetchUserInfoEventReq { user: user.clone() }, self.clone())
+            .await?;
+
+        let info = into_user_info(response, &user)?;
+        self.cache.map_uid(info.uin, &info.uid);
+        Ok(info)
+    }
+
+    /// Fetch the basic profile of a user who is neither a friend nor in a group with the bot,
+    /// lighter than [`BotContext::fetch_user_info`].
+    ///
+    /// The profile is cached apart from the friends, see [`BotContext::get_stranger`]. Fails
+    /// with [`Error::UserNotFound`] if there is no such user or the server refuses to tell.
+    pub async fn fetch_stranger_info(self: &Arc<Self>, uin: u64) -> Result<BotStranger, Error> {
+        let request = FetchStrangerEventReq { uin };
+        let stranger = match self.event.send::<FetchStrangerService>(request, self.clone()).await {
+            Ok(response) => response.stranger,
+            Err(Error::Oidb(OidbError::Failed { code, message, .. })) => {
+                tracing::debug!(uin, code, %message, "Stranger lookup refused");
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
+        let stranger = stranger.ok_or_else(|| Error::UserNotFound(uin.to_string()))?;
+        self.cache.cache_stranger(stranger.clone());
+        Ok(stranger)
+    }
+
+    /// The uid of `uin`, looked up on the server if it is not cached.
+    ///
+    /// Fails with [`Error::UserNotFound`] if there is no such user.
+    pub async fn resolve_uid(self: &Arc<Self>, uin: u64) -> Result<String, Error> {
+        if let Some(uid) = self.cached_uid(uin) {
+            return Ok(uid);
+        }
+        Ok(self.fetch_user_info(uin).await?.uid)
+    }
+
+    /// The uin of `uid`, looked up on the server if it is not cached, see [`BotContext::resolve_uid`]
+    pub async fn resolve_uin(self: &Arc<Self>, uid: &str) -> Result<u64, Error> {
+        if let Some(uin) = self.cached_uin(uid) {
+            return Ok(uin);
+        }
+        Ok(self.fetch_user_info(uid).await?.uin)
+    }
+
+    /// The uids of `uins`. Users that are not cached are looked up concurrently, one request
+    /// each, and the whole batch fails if any of them cannot be found.
+    pub async fn resolve_uids(self: &Arc<Self>, uins: &[u64]) -> Result<HashMap<u64, String>, Error> {
+        let mut resolved = HashMap::with_capacity(uins.len());
+        let mut lookups = JoinSet::new();
+        for &uin in uins {
+            match self.cached_uid(uin) {
+                Some(uid) => {
+                    resolved.insert(uin, uid);
+                }
+                None => {
+                    let context = self.clone();
+                    lookups.spawn(async move { context.fetch_user_info(uin).await.map(|info| (uin, info.uid)) });
+                }
+            }
+        }
+
+        while let Some(joined) = lookups.join_next().await {
+            let (uin, uid) = joined.map_err(|e| anyhow::Error::new(e).context("Uid lookup failed"))??;
+            resolved.insert(uin, uid);
+        }
+        Ok(resolved)
+    }
+
+    /// The uins of `uids`, see [`BotContext::resolve_uids`]
+    pub async fn resolve_uins(self: &Arc<Self>, uids: &[String]) -> Result<HashMap<String, u64>, Error> {
+        let mut resolved = HashMap::with_capacity(uids.len());
+        let mut lookups = JoinSet::new();
+        for uid in uids {
+            match self.cached_uin(uid) {
+                Some(uin) => {
+                    resolved.insert(uid.clone(), uin);
+                }
+                None => {
+                    let context = self.clone();
+                    let uid = uid.clone();
+                    lookups.spawn(async move {
+                        let info = context.fetch_user_info(uid.as_str()).await?;
+                        Ok::<_, Error>((uid, info.uin))
+                    });
+                }
+            }
+        }
+
+        while let Some(joined) = lookups.join_next().await {
+            let (uid, uin) = joined.map_err(|e| anyhow::Error::new(e).context("Uin lookup failed"))??;
+            resolved.insert(uid, uin);
+        }
+        Ok(resolved)
+    }
+
+    /// The uid of `uin` from the cache or the keystore, if it is the bot itself
+    fn cached_uid(&self, uin: u64) -> Option<String> {
+        if self.bot_uin() == Some(uin) {
+            if let Some(uid) = self.bot_uid() {
+                return Some(uid);
+            }
+        }
+        self.cache.resolve_uid(uin)
+    }
+
+    /// The uin of `uid` from the cache or the keystore, if it is the bot itself
+    pub(crate) fn cached_uin(&self, uid: &str) -> Option<u64> {
+        if self.bot_uid().as_deref() == Some(uid) {
+            if let Some(uin) = self.bot_uin() {
+                return Some(uin);
+            }
+        }
+        self.cache.resolve_uin(uid)
+    }
+
+    /// Fetch the bot's own profile and store it as the keystore's [`BotInfo`]
+    pub async fn refresh_bot_info(self: &Arc<Self>) -> Result<BotInfo, Error> {
+        let uin = self
+            .bot_uin()
+            .ok_or_else(|| Error::ProtocolError("Bot uin is not known yet".to_string()))?;
+        let info = self.fetch_user_info(uin).await?;
+
+        let bot_info = BotInfo::new(info.age.min(u8::MAX as u32) as u8, info.gender, info.nickname);
+        self.keystore_mut().bot_info = Some(bot_info.clone());
+        Ok(bot_info)
+    }
+}
+
+fn into_user_info(response: FetchUserInfoEventResp, user: &UserId) -> Result<BotUserInfo, Error> {
+    response.info.ok_or_else(|| Error::UserNotFound(user.to_string()))
+}
+
+/// Async iterator over the pages of a group member list, see [`BotContext::group_member_pages`]
+pub struct GroupMemberPages {
+    context: Arc<BotContext>,
+    group_uin: u64,
+    token: Option<String>,
+    done: bool,
+}
+
+impl GroupMemberPages {
+    /// Fetch the next page, or `None` once the last one has been returned.
+    ///
+    /// After an error the iterator ends.
+    pub async fn next(&mut self) -> Option<Result<Vec<BotGroupMember>, Error>> {
+        if self.done {
+            return None;
+        }
+
+        let request = FetchMembersEventReq {
+            group_uin: self.group_uin,
+            token: self.token.clone(),
+        };
+        let page = match self.context.event.send::<FetchMembersService>(request, self.context.clone()).await {
+            Ok(page) => page,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+
+        // Guard against a server echoing the same token forever
+        self.done = page.next_token.is_none() || page.next_token == self.token;
+        self.token = page.next_token;
+        Some(Ok(page.members))
+    }
+}
+
+/// Groups per extended information request
+const GROUP_EXTRA_BATCH: usize = 50;
+
+fn apply_group_extra(groups: &mut [BotGroup], extras: Vec<GroupExtra>) {
+    for extra in extras {
+        if let Some(group) = groups.iter_mut().find(|group| group.group_uin == extra.group_uin) {
+            group.description = group.description.take().or(extra.description);
+            group.question = group.question.take().or(extra.question);
+            group.announcement = group.announcement.take().or(extra.announcement);
+        }
+    }
+}
+
+/// Concatenate the pages and fill in category names that were sent on another page
+fn merge_friend_pages(pages: Vec<FetchFriendsEventResp>) -> Vec<BotFriend> {
+    let categories: HashMap<u32, BotFriendCategory> = pages
+        .iter()
+        .flat_map(|page| page.categories.iter())
+        .map(|category| (category.category_id, category.clone()))
+        .collect();
+
+    pages
+        .into_iter()
+        .flat_map(|page| page.friends)
+        .map(|mut friend| {
+            if let Some(category) = &mut friend.category {
+                if let Some(known) = categories.get(&category.category_id) {
+                    *category = known.clone();
+                }
+            }
+            friend
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::{
+        FriendCategory, FriendLayer1, FriendProperty, FriendPropertyGroup, FriendsResponse,
+        FriendsResponseNext, OidbFriend, OidbSvcTrpcTcpBase, UserInfoBody, UserInfoRequestByUid,
+        UserInfoRequestByUin, UserInfoResponse,
+    };
+    use crate::internal::context::cache::DEFAULT_CONTACT_TTL;
+    use crate::internal::packets::SsoPacket;
+    use crate::keystore::BotKeystore;
+    use crate::protocol::TypedService;
+    use crate::utils::crypto::tea;
+    use bytes::Bytes;
+    use lagrange_proto::ProtoMessage;
+    use std::sync::Mutex;
+
+    fn friend(uin: u32, nickname: &str, category_id: u32) -> OidbFriend {
+        OidbFriend {
+            uid: Some(format!("u_{}", uin)),
+            category_id: Some(category_id),
+            uin: Some(uin),
+            additional: vec![FriendPropertyGroup {
+                property_type: Some(1),
+                layer1: Some(FriendLayer1 {
+                    properties: vec![FriendProperty {
+                        code: 20002,
+                        value: nickname.to_string(),
+                    }],
+                    ..Default::default()
+                }),
+            }],
+        }
+    }
+
+    async fn parse(response: FriendsResponse) -> FetchFriendsEventResp {
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0xfd4,
+            sub_command: 1,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+
+        FetchFriendsService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap()
+    }
+
+    type Requests = Arc<Mutex<Vec<OidbSvcTrpcTcpBase>>>;
+
+    /// A logged in context whose server answers every OIDB request with the body returned by
+    /// `respond`, along with the requests it received.
+    ///
+    /// Requests are D2-encrypted protocol 12 frames; after the service head, the decrypted SSO
+    /// frame carries the sequence behind the length of its head, and the body behind the head.
+    async fn mock_server<F>(respond: F) -> (Arc<BotContext>, Requests)
+    where
+        F: Fn(&OidbSvcTrpcTcpBase) -> Vec<u8> + Send + 'static,
+    {
+        let mut keystore = BotKeystore::default().with_uin(10000);
+        keystore.uid = Some("u_bot".to_string());
+        keystore.sigs.d2 = vec![0xD2; 4];
+        keystore.sigs.d2_key = (0..16).collect();
+        let key: [u8; 16] = keystore.sigs.d2_key[..].try_into().unwrap();
+        let service_head = 4 + 1 + 4 + keystore.sigs.d2.len() + 1 + 4 + "10000".len();
+
+        let context = BotContext::builder().keystore(keystore).build();
+        let requests = Requests::default();
+        let mut outbound = context.socket.attach_test_channel().await;
+        let packet = context.packet.clone();
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Some(frame) = outbound.recv().await {
+                let sso = tea::decrypt(&frame[service_head..], &key).unwrap();
+                let head_length = u32::from_be_bytes(sso[..4].try_into().unwrap()) as usize;
+                let sequence = i32::from_be_bytes(sso[4..8].try_into().unwrap());
+                let request = OidbSvcTrpcTcpBase::decode_from_slice(&sso[head_length + 4..]).unwrap();
+
+                let response = OidbSvcTrpcTcpBase {
+                    command: request.command,
+                    sub_command: request.sub_command,
+                    body: Some(respond(&request)),
+                    ..Default::default()
+                };
+                received.lock().unwrap().push(request);
+                packet.dispatch_packet(SsoPacket::new(
+                    String::new(),
+                    Bytes::from(response.encode_to_vec().unwrap()),
+                    sequence,
+                ));
+            }
+        });
+
+        (context, requests)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_friend_cache_lazy_fill() {
+        let friends = FriendsResponse {
+            friends: vec![friend(10001, "alice", 0), friend(10002, "bob", 0)],
+            ..Default::default()
+        };
+        let (context, requests) = mock_server(move |_| friends.encode_to_vec().unwrap()).await;
+        assert_eq!(context.get_friend(10001), None);
+
+        let alice = context.friend(10001).await.unwrap().unwrap();
+        assert_eq!(alice.nickname, "alice");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // Answered from the cache, including uins that are not friends
+        assert_eq!(context.get_friend(10002).unwrap().nickname, "bob");
+        assert_eq!(context.friend(10003).await.unwrap(), None);
+        assert_eq!(context.friends().await.unwrap().len(), 2);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        tokio::time::advance(DEFAULT_CONTACT_TTL).await;
+        assert_eq!(context.get_friend(10001), None);
+        assert!(context.friend(10001).await.unwrap().is_some());
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    /// Profiles of users 10001 to 10008, whose uid is `u_<uin>`; other users are unknown
+    fn user_profile(request: &OidbSvcTrpcTcpBase) -> Vec<u8> {
+        let body = request.body.as_deref().unwrap();
+        let uin = match request.reserved {
+            Some(1) => {
+                let uid = UserInfoRequestByUid::decode_from_slice(body).unwrap().uid;
+                uid.strip_prefix("u_").unwrap().parse().unwrap()
+            }
+            _ => UserInfoRequestByUin::decode_from_slice(body).unwrap().uin,
+        };
+
+        let body = (10001..=10008).contains(&uin).then(|| UserInfoBody {
+            uid: Some(format!("u_{}", uin)),
+            uin: Some(uin),
+            ..Default::default()
+        });
+        UserInfoResponse { body }.encode_to_vec().unwrap()
+    }
+
+    /// The users a profile request addresses, by uin or uid
+    fn requested_users(requests: &Requests) -> Vec<UserId> {
+        let mut users: Vec<UserId> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| {
+                assert_eq!((request.command, request.sub_command), (0xfe1, 2));
+                let body = request.body.as_deref().unwrap();
+                match request.reserved {
+                    Some(1) => UserId::Uid(UserInfoRequestByUid::decode_from_slice(body).unwrap().uid),
+                    _ => UserId::Uin(UserInfoRequestByUin::decode_from_slice(body).unwrap().uin as u64),
+                }
+            })
+            .collect();
+        users.sort_by_key(|user| user.to_string());
+        users
+    }
+
+    #[tokio::test]
+    async fn test_resolve_uids_batch() {
+        let (context, requests) = mock_server(user_profile).await;
+        context.cache.map_uid(10001, "u_10001");
+
+        let uids = context.resolve_uids(&[10000, 10001, 10002, 10003]).await.unwrap();
+        assert_eq!(uids.len(), 4);
+        assert_eq!(uids[&10000], "u_bot");
+        assert_eq!(uids[&10003], "u_10003");
+        // Only the users missing from the cache were looked up, one request each
+        assert_eq!(requested_users(&requests), [UserId::Uin(10002), UserId::Uin(10003)]);
+
+        let uins = context
+            .resolve_uins(&["u_10002".to_string(), "u_10004".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(uins, HashMap::from([("u_10002".to_string(), 10002), ("u_10004".to_string(), 10004)]));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert_eq!(requested_users(&requests)[2], UserId::Uid("u_10004".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_cache_hits() {
+        let (context, requests) = mock_server(user_profile).await;
+
+        assert_eq!(context.resolve_uid(10005).await.unwrap(), "u_10005");
+        assert_eq!(context.resolve_uid(10005).await.unwrap(), "u_10005");
+        assert_eq!(context.resolve_uin("u_10005").await.unwrap(), 10005);
+        assert_eq!(context.resolve_uin("u_bot").await.unwrap(), 10000);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_unknown_user() {
+        let (context, _) = mock_server(user_profile).await;
+
+        let result = context.resolve_uid(10009).await;
+        assert!(matches!(result, Err(Error::UserNotFound(user)) if user == "10009"));
+
+        let result = context.resolve_uids(&[10001, 10009]).await;
+        assert!(matches!(result, Err(Error::UserNotFound(user)) if user == "10009"));
+        assert_eq!(context.cache.resolve_uid(10009), None);
+    }
+
+    #[tokio::test]
+    async fn test_merge_two_pages() {
+        let first = parse(FriendsResponse {
+            next: Some(FriendsResponseNext { uin: 10003 }),
+            friends: vec![friend(10001, "alice", 0), friend(10002, "bob", 1)],
+            categories: vec![
+                FriendCategory {
+                    category_id: 0,
+                    category_name: Some("My Friends".to_string()),
+                    ..Default::default()
+                },
+                FriendCategory {
+                    category_id: 1,
+                    category_name: Some("Family".to_string()),
+                    sort_id: Some(1),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        })
+        .await;
+        // Later pages omit the categories
+        let second = parse(FriendsResponse {
+            friends: vec![friend(10003, "carol", 1)],
+            ..Default::default()
+        })
+        .await;
+
+        assert_eq!(first.next_uin, Some(10003));
+        assert_eq!(second.next_uin, None);
+
+        let friends = merge_friend_pages(vec![first, second]);
+        let summary: Vec<(u64, &str, &str)> = friends
+            .iter()
+            .map(|friend| {
+                (
+                    friend.uin,
+                    friend.nickname.as_str(),
+                    friend.category.as_ref().unwrap().category_name.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (10001, "alice", "My Friends"),
+                (10002, "bob", "Family"),
+                (10003, "carol", "Family"),
+            ]
+        );
+        assert_eq!(friends[2].uid, "u_10003");
+        assert_eq!(friends[2].category.as_ref().unwrap().sort_id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stranger_info() {
+        use crate::internal::packets::oidb::{OidbStranger, StrangerInfoRequest, StrangerInfoResponse};
+        use crate::testing::{Expectation, MockServer};
+
+        let (transport, server) = MockServer::start(BotKeystore::default());
+        server.expect(Expectation::new("OidbSvcTrpcTcp.0x5eb_22").respond_with(|request| {
+            let request = OidbSvcTrpcTcpBase::decode_from_slice(&request.data).unwrap();
+            let uin = StrangerInfoRequest::decode_from_slice(request.body.as_deref().unwrap()).unwrap().uins[0];
+            let response = match uin {
+                10009 => OidbSvcTrpcTcpBase { error_code: Some(1), error_msg: Some("refused".to_string()), ..request },
+                _ => {
+                    let user = OidbStranger { uin, nickname: Some("bob".to_string()), ..Default::default() };
+                    let body = StrangerInfoResponse { users: vec![user] }.encode_to_vec().unwrap();
+                    OidbSvcTrpcTcpBase { body: Some(body), ..request }
+                }
+            };
+            Bytes::from(response.encode_to_vec().unwrap())
+        }));
+        let context = BotContext::builder().transport(transport).build();
+        context.connect().await.unwrap();
+
+        assert_eq!(context.fetch_stranger_info(10002).await.unwrap().nickname, "bob");
+        assert_eq!(context.get_stranger(10002).unwrap().nickname, "bob");
+        assert_eq!(context.get_friend(10002), None);
+
+        let result = context.fetch_stranger_info(10009).await;
+        assert!(matches!(result, Err(Error::UserNotFound(user)) if user == "10009"));
+        assert!(context.get_stranger(10009).is_none());
+    }
+
+    #[test]
+    fn test_user_not_found() {
+        let result = into_user_info(FetchUserInfoEventResp { info: None }, &UserId::Uin(10001));
+        assert!(matches!(result, Err(Error::UserNotFound(user)) if user == "10001"));
+
+        let info = BotUserInfo {
+            uin: 10001,
+            ..Default::default()
+        };
+        let result = into_user_info(FetchUserInfoEventResp { info: Some(info.clone()) }, &UserId::Uin(10001));
+        assert_eq!(result.unwrap(), info);
+    }
+
+    #[test]
+    fn test_apply_group_extra() {
+        let group = |group_uin: u64| BotGroup {
+            group_uin,
+            group_uid: group_uin.to_string(),
+            group_name: "group".to_string(),
+            member_count: 1,
+            max_member: 200,
+            create_time: 0,
+            description: None,
+            question: None,
+            announcement: None,
+        };
+        let mut groups = vec![group(1), group(2)];
+
+        apply_group_extra(
+            &mut groups,
+            vec![GroupExtra {
+                group_uin: 2,
+                description: Some("second".to_string()),
+                question: None,
+                announcement: Some("hello".to_string()),
+            }],
+        );
+
+        assert_eq!(groups[0], group(1));
+        assert_eq!(groups[1].description.as_deref(), Some("second"));
+        assert_eq!(groups[1].announcement.as_deref(), Some("hello"));
+        assert_eq!(groups[1].question, None);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::common::{CookieJar, CookieSet, Cookies};
+use crate::internal::context::{HttpRequest, HttpResponse};
+use crate::internal::services::system::{
+    FetchClientKeyEventReq, FetchClientKeyService, FetchPsKeysEventReq, FetchPsKeysService,
+};
+use crate::{BotContext, Error};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Lifetime assumed when the server does not tell
+const DEFAULT_LIFETIME: i64 = 24 * 60 * 60;
+/// Cookies are fetched again this long before they expire
+const REFRESH_MARGIN: i64 = 5 * 60;
+
+impl BotContext {
+    /// Web credentials for `domain`, like `qun.qq.com`.
+    ///
+    /// They are kept in the [`CookieJar`] of the session state and reused until shortly before
+    /// they expire.
+    pub async fn fetch_cookies(self: &Arc<Self>, domain: &str) -> Result<Cookies, Error> {
+        let now = chrono::Utc::now().timestamp();
+        let uin = {
+            let keystore = self.keystore();
+            let uin = keystore
+                .uin
+                .ok_or_else(|| Error::ProtocolError("Web cookies need a logged in session".to_string()))?;
+            if let Some(set) = cached_set(&keystore.state.cookies, domain, now) {
+                return Ok(Cookies::new(domain, uin, set));
+            }
+            uin
+        };
+
+        let set = fetch_cookies_with(
+            uin,
+            domain,
+            now,
+            || async {
+                let request = FetchPsKeysEventReq { domains: vec![domain.to_string()] };
+                let mut response = self.event.send::<FetchPsKeysService>(request, self.clone()).await?;
+                response
+                    .ps_keys
+                    .remove(domain)
+                    .ok_or_else(|| Error::ProtocolError(format!("No pskey for {}", domain)))
+            },
+            || async {
+                let response = self.event.send::<FetchClientKeyService>(FetchClientKeyEventReq {}, self.clone()).await?;
+                Ok((response.client_key, response.expiration))
+            },
+            |request| async move { self.http.send(request).await },
+        )
+        .await?;
+
+        self.keystore().state.cookies.set(domain, set.clone());
+        Ok(Cookies::new(domain, uin, set))
+    }
+}
+
+/// Set of `domain` that is still good for a while at `now`
+fn cached_set(jar: &CookieJar, domain: &str, now: i64) -> Option<CookieSet> {
+    jar.get_valid(domain, now + REFRESH_MARGIN)
+}
+
+/// Fetch the pskey of `domain`, then trade a client key for the skey at the `jump` endpoint
+async fn fetch_cookies_with<P, PF, C, CF, S, SF>(
+    uin: u64,
+    domain: &str,
+    now: i64,
+    fetch_pskey: P,
+    fetch_client_key: C,
+    send: S,
+) -> Result<CookieSet, Error>
+where
+    P: FnOnce() -> PF,
+    PF: Future<Output = Result<String, Error>>,
+    C: FnOnce() -> CF,
+    CF: Future<Output = Result<(String, u32), Error>>,
+    S: FnOnce(HttpRequest) -> SF,
+    SF: Future<Output = Result<HttpResponse, Error>>,
+{
+    let pskey = fetch_pskey().await?;
+    let (client_key, lifetime) = fetch_client_key().await?;
+
+    let target = form_urlencoded::byte_serialize(format!("https://{}", domain).as_bytes()).collect::<String>();
+    let url = format!(
+        "https://ssl.ptlogin2.qq.com/jump?ptlang=1033&clientuin={}&clientkey={}&u1={}&keyindex=19",
+        uin, client_key, target
+    );
+    // The skey is set by the redirecting response itself
+    let response = send(HttpRequest::get(url).without_redirects()).await?;
+    let skey = response
+        .header_values("Set-Cookie")
+        .find_map(|cookie| cookie_value(cookie, "skey"))
+        .ok_or_else(|| Error::ProtocolError("Jump response sets no skey".to_string()))?
+        .to_string();
+
+    let lifetime = match lifetime {
+        0 => DEFAULT_LIFETIME,
+        seconds => seconds as i64,
+    };
+    Ok(CookieSet { skey, pskey, expires_at: now + lifetime })
+}
+
+/// Non-empty value of the cookie `name` in a `Set-Cookie` header
+fn cookie_value<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    let (key, value) = header.split(';').next()?.split_once('=')?;
+    (key.trim() == name && !value.is_empty()).then_some(value.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_bkn_known_answers() {
+        assert_eq!(Cookies::bkn_of(""), 5381);
+        assert_eq!(Cookies::bkn_of("a"), 177670);
+        assert_eq!(Cookies::bkn_of("@abcdEFGH"), 2082277385);
+        assert_eq!(Cookies::bkn_of("@Lf9hT2pQz"), 198961785);
+    }
+
+    #[test]
+    fn test_cookie_value() {
+        assert_eq!(cookie_value("skey=@abc; PATH=/; DOMAIN=qq.com;", "skey"), Some("@abc"));
+        assert_eq!(cookie_value("p_skey=xyz; PATH=/", "skey"), None);
+        // Cleared cookies come with an empty value
+        assert_eq!(cookie_value("skey=; PATH=/", "skey"), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_flow() {
+        let sent = Mutex::new(None);
+        let set = fetch_cookies_with(
+            123456,
+            "qun.qq.com",
+            1_700_000_000,
+            || async { Ok("pskey".to_string()) },
+            || async { Ok(("CK".to_string(), 3600)) },
+            |request| {
+                *sent.lock().unwrap() = Some(request);
+                async {
+                    Ok(HttpResponse {
+                        status: 302,
+                        headers: vec![
+                            ("Location".to_string(), "https://qun.qq.com".to_string()),
+                            ("Set-Cookie".to_string(), "pt2gguin=o0000123456; PATH=/".to_string()),
+                            ("set-cookie".to_string(), "skey=@abcdEFGH; PATH=/; DOMAIN=qq.com;".to_string()),
+                        ],
+                        body: Default::default(),
+                    })
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            set,
+            CookieSet { skey: "@abcdEFGH".to_string(), pskey: "pskey".to_string(), expires_at: 1_700_003_600 }
+        );
+        assert_eq!(
+            Cookies::new("qun.qq.com", 123456, set).header(),
+            "uin=o0000123456; skey=@abcdEFGH; p_uin=o0000123456; p_skey=pskey"
+        );
+
+        let request = sent.lock().unwrap().take().unwrap();
+        assert!(!request.follow_redirects);
+        assert!(request.url.contains("clientuin=123456&clientkey=CK&u1=https%3A%2F%2Fqun.qq.com"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_without_skey() {
+        let result = fetch_cookies_with(
+            123456,
+            "qun.qq.com",
+            0,
+            || async { Ok("pskey".to_string()) },
+            || async { Ok(("CK".to_string(), 0)) },
+            |_| async { Ok(HttpResponse::default()) },
+        )
+        .await;
+        assert!(matches!(result, Err(Error::ProtocolError(_))));
+    }
+
+    #[test]
+    fn test_cache_expiry() {
+        let set = CookieSet { skey: "@abc".to_string(), pskey: "pskey".to_string(), expires_at: 10_000 };
+        let jar = CookieJar::new();
+        jar.set("qun.qq.com", set.clone());
+
+        assert_eq!(cached_set(&jar, "qun.qq.com", 1_000), Some(set));
+        assert_eq!(cached_set(&jar, "qzone.qq.com", 1_000), None);
+        // Refreshed ahead of the actual expiry
+        assert_eq!(cached_set(&jar, "qun.qq.com", 10_000 - REFRESH_MARGIN), None);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::common::AtAllRemain;
+use crate::internal::services::system::{
+    FetchAtAllRemainEventReq, FetchAtAllRemainService, KickMemberEventReq, KickMemberService,
+    MuteAllEventReq, MuteAllService, MuteMemberEventReq, MuteMemberService, RemoveEssenceEventReq, RemoveEssenceService, SetAdminEventReq, SetAdminService,
+    SetEssenceEventReq, SetEssenceService, SetGroupNameEventReq, SetGroupNameService,
+    SetMemberCardEventReq, SetMemberCardService,
+};
+use crate::{BotContext, Error};
+use std::sync::Arc;
+
+impl BotContext {
+    /// Mute the member `uin` of `group_uin` for `seconds`; `0` lifts the mute.
+    pub async fn mute_member(self: &Arc<Self>, group_uin: u64, uin: u64, seconds: u32) -> Result<(), Error> {
+        let uid = self.resolve_member_uid(group_uin, uin).await?;
+        let request = MuteMemberEventReq { group_uin, uid, duration: seconds };
+        self.event.send::<MuteMemberService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Mute or unmute everyone in `group_uin` except the owner and admins.
+    pub async fn mute_all(self: &Arc<Self>, group_uin: u64, enable: bool) -> Result<(), Error> {
+        let request = MuteAllEventReq { group_uin, enable };
+        self.event.send::<MuteAllService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Remove the member `uin` from `group_uin`, optionally rejecting their future join requests.
+    pub async fn kick_member(self: &Arc<Self>, group_uin: u64, uin: u64, reject_add_request: bool) -> Result<(), Error> {
+        let uid = self.resolve_member_uid(group_uin, uin).await?;
+        let request = KickMemberEventReq { group_uin, uid, reject_add_request };
+        self.event.send::<KickMemberService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Grant or revoke the admin role of the member `uin`. Only the owner can do this.
+    pub async fn set_admin(self: &Arc<Self>, group_uin: u64, uin: u64, is_admin: bool) -> Result<(), Error> {
+        let uid = self.resolve_member_uid(group_uin, uin).await?;
+        let request = SetAdminEventReq { group_uin, uid, is_admin };
+        self.event.send::<SetAdminService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Set the group card of the member `uin`; an empty card falls back to their nickname.
+    pub async fn set_member_card(self: &Arc<Self>, group_uin: u64, uin: u64, card: impl Into<String>) -> Result<(), Error> {
+        let uid = self.resolve_member_uid(group_uin, uin).await?;
+        let request = SetMemberCardEventReq { group_uin, uid, card: card.into() };
+        self.event.send::<SetMemberCardService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// How many @all messages the bot and `group_uin` have left today, to check before sending
+    /// [`MessageEntity::AtAll`](crate::message::MessageEntity::AtAll).
+    pub async fn get_at_all_remain(self: &Arc<Self>, group_uin: u64) -> Result<AtAllRemain, Error> {
+        let request = FetchAtAllRemainEventReq { group_uin, uin: self.bot_uin().unwrap_or_default() };
+        Ok(self.event.send::<FetchAtAllRemainService>(request, self.clone()).await?.remain)
+    }
+
+    /// Rename `group_uin`.
+    pub async fn set_group_name(self: &Arc<Self>, group_uin: u64, name: impl Into<String>) -> Result<(), Error> {
+        let request = SetGroupNameEventReq { group_uin, name: name.into() };
+        self.event.send::<SetGroupNameService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Add the message `sequence` of `group_uin` to the essence list; `random` comes with the message.
+    pub async fn set_essence_message(self: &Arc<Self>, group_uin: u64, sequence: u32, random: u32) -> Result<(), Error> {
+        let request = SetEssenceEventReq { group_uin, sequence, random };
+        self.event.send::<SetEssenceService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Remove the message `sequence` of `group_uin` from the essence list.
+    pub async fn remove_essence_message(self: &Arc<Self>, group_uin: u64, sequence: u32, random: u32) -> Result<(), Error> {
+        let request = RemoveEssenceEventReq { group_uin, sequence, random };
+        self.event.send::<RemoveEssenceService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Look up the uid of a member, refreshing the member list of the group once on a cache miss
+    async fn resolve_member_uid(self: &Arc<Self>, group_uin: u64, uin: u64) -> Result<String, Error> {
+        if let Some(uid) = self.cache.resolve_uid(uin) {
+            return Ok(uid);
+        }
+
+        self.fetch_group_members(group_uin).await?;
+        self.cache
+            .resolve_uid(uin)
+            .ok_or_else(|| Error::UserNotFound(format!("{} in group {}", uin, group_uin)))
+    }
+}
@@ -0,0 +1,528 @@
+use crate::internal::context::{UploadProgress, UploadStatus};
+use crate::internal::packets::oidb::{
+    FileUploadBusiness, FileUploadClientInfo, FileUploadEntry, FileUploadExt, FileUploadFileEntry,
+    FileUploadFileName, FileUploadHost, FileUploadHosts, FileUploadUrl,
+};
+use crate::common::GroupFsEntry;
+use crate::internal::services::system::{
+    GroupFileDeleteEventReq, GroupFileDeleteService, GroupFileDownloadEventReq, GroupFileDownloadService,
+    GroupFileFeedEventReq, GroupFileFeedService, GroupFileListEventReq, GroupFileListService, GroupFileMoveEventReq,
+    GroupFileMoveService, GroupFileRenameEventReq, GroupFileRenameService, GroupFileSlot, GroupFileUpload,
+    GroupFileUploadEventReq, GroupFileUploadService, GroupFolderCreateEventReq, GroupFolderCreateService,
+    GroupFolderDeleteEventReq, GroupFolderDeleteService, GroupFolderRenameEventReq, GroupFolderRenameService,
+};
+use crate::utils::UploadSource;
+use crate::{BotContext, Error};
+use lagrange_proto::ProtoMessage;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncSeek};
+
+/// Highway command id of file uploads
+const GROUP_FILE_COMMAND_ID: u32 = 71;
+
+/// Bytes read at a time while hashing a file
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Entries requested per page of a folder listing
+const LIST_PAGE_SIZE: u32 = 100;
+
+/// Id of the root folder of a group
+const ROOT_FOLDER_ID: &str = "/";
+
+impl BotContext {
+    /// Upload the file at `path` into the folder `folder_id` of `group_uin`, keeping its name.
+    ///
+    /// See [`BotContext::upload_group_file`].
+    pub async fn upload_group_file_from_path(
+        self: &Arc<Self>,
+        group_uin: u64,
+        path: impl AsRef<Path>,
+        folder_id: &str,
+        progress: Option<UploadProgress>,
+    ) -> Result<String, Error> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| Error::BuildError(format!("No file name in {}", path.display())))?
+            .to_string();
+        let file = tokio::fs::File::open(path).await?;
+        self.upload_group_file(group_uin, file, name, folder_id, progress).await
+    }
+
+    /// Upload the content of `reader` as `name` into the folder `folder_id` of `group_uin` and
+    /// return the id of the new file. The root folder has the id `/`.
+    ///
+    /// The content is read twice, once to hash it and once to upload it, so it is never held in
+    /// memory as a whole. `progress` receives the bytes uploaded so far. An upload interrupted by
+    /// a network error is resumed after the part the server acknowledged. Rejections of the
+    /// server, like exhausted group space, are reported as [`crate::error::GroupFileError`].
+    pub async fn upload_group_file<R>(
+        self: &Arc<Self>,
+        group_uin: u64,
+        reader: R,
+        name: impl Into<String>,
+        folder_id: &str,
+        progress: Option<UploadProgress>,
+    ) -> Result<String, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let uin = self.keystore.read().expect("RwLock poisoned").uin.unwrap_or_default();
+        let folder_id = folder_id.to_string();
+
+        upload_group_file_with(
+            reader,
+            name.into(),
+            uin,
+            group_uin,
+            progress,
+            |file| async move {
+                let request = GroupFileUploadEventReq { group_uin, folder_id, file };
+                let response = self.event.send::<GroupFileUploadService>(request, self.clone()).await?;
+                Ok(response.slot)
+            },
+            |mut reader, file, extend_info, progress| async move {
+                self.highway_upload_resumable(
+                    GROUP_FILE_COMMAND_ID,
+                    &mut reader,
+                    file.size,
+                    file.md5,
+                    &extend_info,
+                    progress,
+                )
+                .await?;
+                Ok(())
+            },
+            |file_id| async move {
+                let request = GroupFileFeedEventReq { group_uin, file_id, random: rand::random() };
+                self.event.send::<GroupFileFeedService>(request, self.clone()).await?;
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    /// Download url of the file `file_id` in `group_uin`
+    pub async fn get_group_file_url(self: &Arc<Self>, group_uin: u64, file_id: &str) -> Result<String, Error> {
+        let request = GroupFileDownloadEventReq { group_uin, file_id: file_id.to_string() };
+        Ok(self.event.send::<GroupFileDownloadService>(request, self.clone()).await?.url)
+    }
+
+    /// Files and folders in the folder `folder_id` of `group_uin`, or in the root folder for `None`.
+    ///
+    /// Large folders are fetched page by page until the server reports the last one.
+    pub async fn list_group_files(
+        self: &Arc<Self>,
+        group_uin: u64,
+        folder_id: Option<&str>,
+    ) -> Result<Vec<GroupFsEntry>, Error> {
+        let folder_id = folder_id.unwrap_or(ROOT_FOLDER_ID);
+        list_group_files_with(|start_index| async move {
+            let request = GroupFileListEventReq {
+                group_uin,
+                folder_id: folder_id.to_string(),
+                start_index,
+                count: LIST_PAGE_SIZE,
+            };
+            let response = self.event.send::<GroupFileListService>(request, self.clone()).await?;
+            Ok((response.entries, response.is_end))
+        })
+        .await
+    }
+
+    /// Create a folder named `name` in the root folder of `group_uin`
+    pub async fn create_group_folder(self: &Arc<Self>, group_uin: u64, name: &str) -> Result<(), Error> {
+        let request = GroupFolderCreateEventReq {
+            group_uin,
+            parent_folder_id: ROOT_FOLDER_ID.to_string(),
+            name: name.to_string(),
+        };
+        self.event.send::<GroupFolderCreateService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Rename the folder `folder_id` of `group_uin` to `new_name`
+    pub async fn rename_group_folder(
+        self: &Arc<Self>,
+        group_uin: u64,
+        folder_id: &str,
+        new_name: &str,
+    ) -> Result<(), Error> {
+        let request = GroupFolderRenameEventReq {
+            group_uin,
+            folder_id: folder_id.to_string(),
+            new_name: new_name.to_string(),
+        };
+        self.event.send::<GroupFolderRenameService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Delete the folder `folder_id` of `group_uin` together with the files in it
+    pub async fn delete_group_folder(self: &Arc<Self>, group_uin: u64, folder_id: &str) -> Result<(), Error> {
+        let request = GroupFolderDeleteEventReq { group_uin, folder_id: folder_id.to_string() };
+        self.event.send::<GroupFolderDeleteService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Rename the file `file_id` in the folder `folder_id` of `group_uin` to `new_name`
+    pub async fn rename_group_file(
+        self: &Arc<Self>,
+        group_uin: u64,
+        file_id: &str,
+        folder_id: &str,
+        new_name: &str,
+    ) -> Result<(), Error> {
+        let request = GroupFileRenameEventReq {
+            group_uin,
+            file_id: file_id.to_string(),
+            folder_id: folder_id.to_string(),
+            new_name: new_name.to_string(),
+        };
+        self.event.send::<GroupFileRenameService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Move the file `file_id` of `group_uin` from the folder `folder_id` to `target_folder_id`
+    pub async fn move_group_file(
+        self: &Arc<Self>,
+        group_uin: u64,
+        file_id: &str,
+        folder_id: &str,
+        target_folder_id: &str,
+    ) -> Result<(), Error> {
+        let request = GroupFileMoveEventReq {
+            group_uin,
+            file_id: file_id.to_string(),
+            folder_id: folder_id.to_string(),
+            target_folder_id: target_folder_id.to_string(),
+        };
+        self.event.send::<GroupFileMoveService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Delete the file `file_id` of `group_uin`
+    pub async fn delete_group_file(self: &Arc<Self>, group_uin: u64, file_id: &str) -> Result<(), Error> {
+        let request = GroupFileDeleteEventReq { group_uin, file_id: file_id.to_string() };
+        self.event.send::<GroupFileDeleteService>(request, self.clone()).await?;
+        Ok(())
+    }
+}
+
+/// Collect a listing from pages fetched by `fetch_page`, which receives the index of the first
+/// entry and returns the entries of the page and whether it is the last one.
+async fn list_group_files_with<P, PFut>(mut fetch_page: P) -> Result<Vec<GroupFsEntry>, Error>
+where
+    P: FnMut(u32) -> PFut,
+    PFut: Future<Output = Result<(Vec<GroupFsEntry>, bool), Error>>,
+{
+    let mut entries = Vec::new();
+    loop {
+        let (page, is_end) = fetch_page(entries.len() as u32).await?;
+        // An empty page ends the listing even if the server does not flag it
+        let done = is_end || page.is_empty();
+        entries.extend(page);
+        if done {
+            return Ok(entries);
+        }
+    }
+}
+
+/// Hash the file, request an upload slot, push the content unless the server has it and post
+/// the file to the group.
+///
+/// The three steps are passed in, so the flow can be tested without a connection.
+#[allow(clippy::too_many_arguments)]
+async fn upload_group_file_with<R, S, SFut, H, HFut, F, FFut>(
+    reader: R,
+    name: String,
+    uin: u64,
+    group_uin: u64,
+    progress: Option<UploadProgress>,
+    request_slot: S,
+    highway_upload: H,
+    post_feed: F,
+) -> Result<String, Error>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    S: FnOnce(GroupFileUpload) -> SFut,
+    SFut: Future<Output = Result<GroupFileSlot, Error>>,
+    H: FnOnce(R, GroupFileUpload, Vec<u8>, Option<UploadProgress>) -> HFut,
+    HFut: Future<Output = Result<(), Error>>,
+    F: FnOnce(String) -> FFut,
+    FFut: Future<Output = Result<(), Error>>,
+{
+    if name.is_empty() {
+        return Err(Error::BuildError("Group files need a name".to_string()));
+    }
+    let source = UploadSource::open(reader, HASH_BUFFER_SIZE).await?;
+    let digest = source.digest();
+    if digest.size == 0 {
+        return Err(Error::BuildError("Cannot upload an empty file".to_string()));
+    }
+    let file = GroupFileUpload { name, size: digest.size, md5: digest.md5, sha1: digest.sha1 };
+
+    let slot = request_slot(file.clone()).await?;
+    if slot.exists {
+        if let Some(progress) = &progress {
+            progress(UploadStatus { resumed: file.size, uploaded: 0, size: file.size });
+        }
+    } else {
+        let extend_info = file_upload_ext(uin, group_uin, &file, &slot)
+            .encode_to_vec()
+            .map_err(|e| Error::BuildError(e.to_string()))?;
+        highway_upload(source.into_reader(), file, extend_info, progress).await?;
+    }
+
+    post_feed(slot.file_id.clone()).await?;
+    Ok(slot.file_id)
+}
+
+fn file_upload_ext(uin: u64, group_uin: u64, file: &GroupFileUpload, slot: &GroupFileSlot) -> FileUploadExt {
+    FileUploadExt {
+        unknown1: 100,
+        unknown2: 1,
+        entry: Some(FileUploadEntry {
+            busi_buff: Some(FileUploadBusiness {
+                bus_id: 0,
+                sender_uin: uin,
+                receiver_uin: group_uin,
+                group_code: group_uin,
+            }),
+            file_entry: Some(FileUploadFileEntry {
+                file_size: file.size,
+                md5: Some(file.md5.to_vec()),
+                check_key: Some(slot.check_key.clone()),
+                md5_s2: Some(file.md5.to_vec()),
+                file_id: slot.file_id.clone(),
+                upload_key: Some(slot.file_key.clone()),
+            }),
+            client_info: Some(FileUploadClientInfo {
+                client_type: 3,
+                app_id: "100".to_string(),
+                terminal_type: 3,
+                client_version: "1.1.1".to_string(),
+                unknown: 4,
+            }),
+            file_name_info: Some(FileUploadFileName { file_name: file.name.clone() }),
+            host: Some(FileUploadHosts {
+                hosts: vec![FileUploadHost {
+                    url: Some(FileUploadUrl { unknown: 1, host: slot.upload_ip.clone() }),
+                    port: slot.upload_port,
+                }],
+            }),
+        }),
+        unknown200: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GroupFileError;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+    use tokio::io::AsyncReadExt;
+
+    const CHUNK_SIZE: usize = 4;
+
+    type Reports = Arc<Mutex<Vec<(u64, u64)>>>;
+
+    fn slot(exists: bool) -> GroupFileSlot {
+        GroupFileSlot {
+            file_id: "/abc-123".to_string(),
+            exists,
+            check_key: vec![3; 4],
+            file_key: vec![4; 4],
+            upload_ip: "10.0.0.1".to_string(),
+            upload_port: 443,
+        }
+    }
+
+    /// Reads the file like the highway does, `CHUNK_SIZE` bytes per acknowledged chunk
+    async fn mock_highway<R: AsyncRead + Unpin>(
+        mut reader: R,
+        file: GroupFileUpload,
+        progress: Option<UploadProgress>,
+    ) -> Vec<Vec<u8>> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        while offset < file.size {
+            let mut chunk = vec![0; (file.size - offset).min(CHUNK_SIZE as u64) as usize];
+            reader.read_exact(&mut chunk).await.unwrap();
+            offset += chunk.len() as u64;
+            chunks.push(chunk);
+            if let Some(progress) = &progress {
+                progress(UploadStatus { resumed: 0, uploaded: offset, size: file.size });
+            }
+        }
+        chunks
+    }
+
+    fn recorder() -> (UploadProgress, Reports) {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let progress: UploadProgress = {
+            let reports = reports.clone();
+            Arc::new(move |status: UploadStatus| reports.lock().unwrap().push((status.acknowledged(), status.size)))
+        };
+        (progress, reports)
+    }
+
+    #[tokio::test]
+    async fn test_full_flow() {
+        let data = b"0123456789".to_vec();
+        let (progress, reports) = recorder();
+        let uploaded = Mutex::new(None);
+        let posted = Mutex::new(None);
+
+        let file_id = upload_group_file_with(
+            Cursor::new(data.clone()),
+            "notes.txt".to_string(),
+            10000,
+            123456,
+            Some(progress),
+            |file| async move {
+                assert_eq!(file.size, 10);
+                assert_eq!(file.md5, md5::compute(b"0123456789").0);
+                Ok(slot(false))
+            },
+            |reader, file, extend_info, progress| async {
+                *uploaded.lock().unwrap() = Some((mock_highway(reader, file, progress).await, extend_info));
+                Ok(())
+            },
+            |file_id| async {
+                *posted.lock().unwrap() = Some(file_id);
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(file_id, "/abc-123");
+        assert_eq!(posted.into_inner().unwrap().as_deref(), Some("/abc-123"));
+
+        // The content is read again from the start after hashing
+        let (chunks, extend_info) = uploaded.into_inner().unwrap().unwrap();
+        assert_eq!(chunks, vec![b"0123".to_vec(), b"4567".to_vec(), b"89".to_vec()]);
+        assert_eq!(*reports.lock().unwrap(), vec![(4, 10), (8, 10), (10, 10)]);
+
+        let entry = FileUploadExt::decode_from_slice(&extend_info).unwrap().entry.unwrap();
+        let file_entry = entry.file_entry.unwrap();
+        assert_eq!(file_entry.file_id, "/abc-123");
+        assert_eq!(file_entry.check_key, Some(vec![3; 4]));
+        assert_eq!(file_entry.upload_key, Some(vec![4; 4]));
+        assert_eq!(entry.busi_buff.unwrap().group_code, 123456);
+        assert_eq!(entry.file_name_info.unwrap().file_name, "notes.txt");
+        assert_eq!(entry.host.unwrap().hosts[0].port, 443);
+    }
+
+    #[tokio::test]
+    async fn test_existing_file_skips_highway() {
+        let (progress, reports) = recorder();
+        let file_id = upload_group_file_with(
+            Cursor::new(b"0123456789".to_vec()),
+            "notes.txt".to_string(),
+            10000,
+            123456,
+            Some(progress),
+            |_| async { Ok(slot(true)) },
+            |_, _, _, _| async { panic!("the server already has the file") },
+            |_| async { Ok(()) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(file_id, "/abc-123");
+        assert_eq!(*reports.lock().unwrap(), vec![(10, 10)]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_slot() {
+        let result = upload_group_file_with(
+            Cursor::new(b"0123456789".to_vec()),
+            "notes.txt".to_string(),
+            10000,
+            123456,
+            None,
+            |_| async {
+                Err(GroupFileError::from_result(GroupFileError::QUOTA_EXCEEDED_CODE, "full").unwrap().into())
+            },
+            |_, _, _, _| async { panic!("no slot was granted") },
+            |_| async { panic!("no slot was granted") },
+        )
+        .await;
+        assert!(matches!(result, Err(Error::GroupFile(GroupFileError::QuotaExceeded { .. }))));
+    }
+
+    fn file_entry(index: u32) -> GroupFsEntry {
+        GroupFsEntry::File(crate::common::GroupFileEntry {
+            file_id: format!("/file-{index}"),
+            name: format!("file-{index}.txt"),
+            size: 1024,
+            bus_id: 102,
+            folder_id: "/".to_string(),
+            uploader_uin: 10000,
+            uploader_name: "uploader".to_string(),
+            upload_time: 1700000000,
+            expire_time: 0,
+            modified_time: 1700000000,
+            download_count: 0,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_pagination() {
+        const TOTAL: u32 = 250;
+        let requested = Mutex::new(Vec::new());
+
+        let entries = list_group_files_with(|start_index| {
+            requested.lock().unwrap().push(start_index);
+            async move {
+                let end = (start_index + LIST_PAGE_SIZE).min(TOTAL);
+                Ok(((start_index..end).map(file_entry).collect(), end == TOTAL))
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(*requested.lock().unwrap(), vec![0, 100, 200]);
+        assert_eq!(entries.len(), TOTAL as usize);
+        assert_eq!(entries[0], file_entry(0));
+        assert_eq!(entries[249], file_entry(249));
+    }
+
+    #[tokio::test]
+    async fn test_list_stops_on_empty_page() {
+        let pages = Mutex::new(0);
+        let entries = list_group_files_with(|start_index| {
+            *pages.lock().unwrap() += 1;
+            async move {
+                // The last page is never flagged
+                let page = if start_index == 0 { (0..LIST_PAGE_SIZE).map(file_entry).collect() } else { Vec::new() };
+                Ok((page, false))
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(entries.len(), LIST_PAGE_SIZE as usize);
+        assert_eq!(*pages.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_empty_file() {
+        let result = upload_group_file_with(
+            Cursor::new(Vec::new()),
+            "empty.txt".to_string(),
+            10000,
+            123456,
+            None,
+            |_| async { panic!("nothing to upload") },
+            |_, _, _, _| async { panic!("nothing to upload") },
+            |_| async { panic!("nothing to upload") },
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BuildError(_))));
+    }
+}
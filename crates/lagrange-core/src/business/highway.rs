@@ -0,0 +1,278 @@
+use crate::internal::context::{HighwaySession, HighwayUploader, UploadProgress};
+use crate::internal::services::system::{FetchHighwaySessionEventReq, FetchHighwaySessionService};
+use crate::{BotContext, Error};
+use std::io::{Cursor, SeekFrom};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncSeek, AsyncSeekExt};
+
+/// Attempts at an upload interrupted by network errors, each one resuming the previous
+const HIGHWAY_UPLOAD_ATTEMPTS: u32 = 3;
+
+impl BotContext {
+    /// Fetch a new highway session and keep it for later uploads
+    pub async fn refresh_highway_session(self: &Arc<Self>) -> Result<HighwaySession, Error> {
+        let response = self
+            .event
+            .send::<FetchHighwaySessionService>(FetchHighwaySessionEventReq {}, self.clone())
+            .await?;
+        let verify_hosts = self.config.highway_verify_hosts || self.config.highway_servers_override().is_none();
+        if response.session.servers.is_empty() && verify_hosts {
+            return Err(Error::ProtocolError("Highway session has no upload server".to_string()));
+        }
+
+        self.highway.set_session(response.session.clone());
+        Ok(response.session)
+    }
+
+    /// Upload `data` to the highway and return the extension of the final response.
+    ///
+    /// `command_id` selects the kind of media, `extend_info` is the upload request it belongs to.
+    /// An upload interrupted by a network error is retried with a new highway session, resuming
+    /// after the acknowledged part.
+    pub async fn highway_upload(
+        self: &Arc<Self>,
+        command_id: u32,
+        data: &[u8],
+        extend_info: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let file_md5 = md5::compute(data).0;
+        let size = data.len() as u64;
+        self.highway_upload_resumable(command_id, &mut Cursor::new(data), size, file_md5, extend_info, None)
+            .await
+    }
+
+    /// Like [`BotContext::highway_upload`], but reads the file from `reader` while uploading and
+    /// reports the acknowledged bytes to `progress`.
+    ///
+    /// The upload is attempted once. Calling this again for the same file after it failed
+    /// resumes after the part the server acknowledged, `reader` has to start at the beginning of
+    /// the file again.
+    pub async fn highway_upload_stream<R>(
+        self: &Arc<Self>,
+        command_id: u32,
+        reader: &mut R,
+        size: u64,
+        file_md5: [u8; 16],
+        extend_info: &[u8],
+        progress: Option<UploadProgress>,
+    ) -> Result<Vec<u8>, Error>
+    where
+        R: AsyncRead + Unpin,
+    {
+        self.highway_uploader()
+            .await?
+            .upload_stream(command_id, reader, size, file_md5, extend_info, progress)
+            .await
+    }
+
+    /// Like [`BotContext::highway_upload_stream`], retrying the upload when it is interrupted by
+    /// a network error
+    pub(crate) async fn highway_upload_resumable<R>(
+        self: &Arc<Self>,
+        command_id: u32,
+        reader: &mut R,
+        size: u64,
+        file_md5: [u8; 16],
+        extend_info: &[u8],
+        progress: Option<UploadProgress>,
+    ) -> Result<Vec<u8>, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let mut attempt = 1;
+        loop {
+            reader.seek(SeekFrom::Start(0)).await?;
+            let result = self
+                .highway_upload_stream(command_id, reader, size, file_md5, extend_info, progress.clone())
+                .await;
+            match result {
+                Err(Error::Network(e)) if attempt < HIGHWAY_UPLOAD_ATTEMPTS => {
+                    tracing::warn!(attempt, error = %e, "Highway upload interrupted, resuming");
+                    // The session may have ended along with the connection
+                    self.refresh_highway_session().await?;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn highway_uploader(self: &Arc<Self>) -> Result<HighwayUploader, Error> {
+        let session = match self.highway.session() {
+            Some(session) => session,
+            None => self.refresh_highway_session().await?,
+        };
+        let servers = match self.config.highway_servers_override() {
+            Some(overrides) => {
+                let servers: Vec<String> = overrides.iter().map(ToString::to_string).collect();
+                if self.config.highway_verify_hosts {
+                    if let Some(server) = servers.iter().find(|server| !session.servers.contains(server)) {
+                        return Err(Error::ProtocolError(format!(
+                            "Highway override {} is not offered by the session",
+                            server
+                        )));
+                    }
+                }
+                tracing::warn!(?servers, offered = ?session.servers, "Uploading to the highway override");
+                servers
+            }
+            None if session.servers.is_empty() => {
+                return Err(Error::ProtocolError("Highway session has no upload server".to_string()));
+            }
+            None => session.servers.clone(),
+        };
+
+        let keystore = self.keystore.read().expect("RwLock poisoned");
+        Ok(HighwayUploader {
+            servers,
+            uin: keystore.uin.unwrap_or_default(),
+            app_id: self.app_info.inner().sub_app_id,
+            login_sig: keystore.sigs.a2.clone(),
+            ticket: session.sig_session,
+            chunk_size: self.config.highway_chunk_size,
+            concurrent: self.config.highway_concurrent,
+            resume: Some(self.highway.resume_store()),
+            socket: self.config.socket.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::BotConfig;
+    use crate::internal::packets::highway::{
+        decode_frame_header, encode_frame, HighwayAddr, HighwayServerList, HighwaySessionResponse,
+        HighwaySessionResponseBody, ReqDataHighwayHead, RespDataHighwayHead, FRAME_HEADER_SIZE,
+    };
+    use crate::keystore::BotKeystore;
+    use crate::testing::{Expectation, MockServer};
+    use crate::BotContext;
+    use bytes::Bytes;
+    use lagrange_proto::{Fixed32, ProtoMessage};
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Highway server acknowledging every chunk, returns its address and the bodies it received
+    async fn mock_highway() -> (SocketAddr, Arc<Mutex<Vec<u8>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let collected = received.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let received = collected.clone();
+                tokio::spawn(async move {
+                    let mut header = [0; FRAME_HEADER_SIZE];
+                    while stream.read_exact(&mut header).await.is_ok() {
+                        let (head_length, body_length) = decode_frame_header(&header).unwrap();
+                        let mut frame = vec![0; head_length + body_length + 1];
+                        stream.read_exact(&mut frame).await.unwrap();
+                        received.lock().unwrap().extend_from_slice(&frame[head_length..head_length + body_length]);
+
+                        let head = ReqDataHighwayHead::decode_from_slice(&frame[..head_length]).unwrap();
+                        let seg = head.seg_head.unwrap();
+                        let is_final = seg.data_offset.unwrap() + body_length as u64 == seg.file_size.unwrap();
+                        let response = RespDataHighwayHead {
+                            error_code: Some(0),
+                            rsp_extend_info: is_final.then(|| b"done".to_vec()),
+                            ..Default::default()
+                        };
+                        let head = response.encode_to_vec().unwrap();
+                        stream.write_all(&encode_frame(&head, &[])).await.unwrap();
+                    }
+                });
+            }
+        });
+        (addr, received)
+    }
+
+    /// A session ticket offering `servers` for uploads
+    fn session_offering(servers: &[SocketAddr]) -> Bytes {
+        let addrs = servers
+            .iter()
+            .map(|server| {
+                let IpAddr::V4(ip) = server.ip() else { panic!("highway addresses are IPv4") };
+                let ip = Fixed32(u32::from_le_bytes(ip.octets()));
+                HighwayAddr { ip_type: 0, ip, port: server.port() as u32, area: None }
+            })
+            .collect();
+        let response = HighwaySessionResponse {
+            body: Some(HighwaySessionResponseBody {
+                sig_session: Some(vec![0x51; 4]),
+                session_key: Some(vec![0x52; 4]),
+                server_lists: vec![HighwayServerList { service_type: 1, addrs }],
+            }),
+        };
+        Bytes::from(response.encode_to_vec().unwrap())
+    }
+
+    async fn connect(config: BotConfig, offered: &[SocketAddr]) -> (Arc<BotContext>, MockServer) {
+        let (transport, server) = MockServer::start(BotKeystore::default());
+        server.expect(Expectation::new("HttpConn.0x6ff_501").respond(session_offering(offered)));
+        let context = BotContext::builder().config(config).transport(transport).build();
+        context.connect().await.unwrap();
+        (context, server)
+    }
+
+    #[tokio::test]
+    async fn test_upload_to_override() {
+        let (highway, received) = mock_highway().await;
+        let config = BotConfig::builder()
+            .highway_override(vec![highway])
+            .highway_verify_hosts(false)
+            .highway_chunk_size(1000)
+            .build();
+        let (context, _server) = connect(config, &[]).await;
+
+        let data: Vec<u8> = (0..2500).map(|i| (i % 251) as u8).collect();
+        let extend_info = context.highway_upload(1004, &data, b"ext").await.unwrap();
+        assert_eq!(extend_info, b"done");
+        assert_eq!(received.lock().unwrap().len(), data.len());
+    }
+
+    #[tokio::test]
+    async fn test_override_keeps_host_verification() {
+        let (highway, _) = mock_highway().await;
+        let config = BotConfig::builder().highway_override(vec![highway]).build();
+        let (context, _server) = connect(config, &[]).await;
+
+        let result = context.highway_upload(1004, b"data", b"").await;
+        assert!(matches!(result, Err(crate::Error::ProtocolError(message)) if message.contains("no upload server")));
+    }
+
+    #[tokio::test]
+    async fn test_override_must_be_offered() {
+        let (highway, received) = mock_highway().await;
+        let other: SocketAddr = "127.0.0.1:9".parse().unwrap();
+
+        let config = BotConfig::builder().highway_override(vec![highway]).build();
+        let (context, _server) = connect(config, &[other]).await;
+        let result = context.highway_upload(1004, b"data", b"").await;
+        assert!(matches!(result, Err(crate::Error::ProtocolError(message)) if message.contains("not offered")));
+
+        // An override the session offers too is used, even when it is not the best server
+        let config = BotConfig::builder().highway_override(vec![highway]).build();
+        let (context, _server) = connect(config, &[other, highway]).await;
+        assert_eq!(context.highway_upload(1004, b"data", b"").await.unwrap(), b"done");
+        assert_eq!(received.lock().unwrap().as_slice(), b"data");
+    }
+
+    #[tokio::test]
+    async fn test_upload_tries_offered_servers_in_order() {
+        let (highway, received) = mock_highway().await;
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+        let (context, _server) = connect(BotConfig::default(), &[closed, highway]).await;
+        assert_eq!(context.highway_upload(1004, b"data", b"").await.unwrap(), b"done");
+        assert_eq!(received.lock().unwrap().as_slice(), b"data");
+    }
+
+    #[test]
+    fn test_parse_socket_addrs() {
+        let servers = crate::config::parse_socket_addrs("127.0.0.1:8080, [::1]:443,").unwrap();
+        assert_eq!(servers, vec!["127.0.0.1:8080".parse().unwrap(), "[::1]:443".parse().unwrap()]);
+        assert!(crate::config::parse_socket_addrs("localhost").is_err());
+    }
+}
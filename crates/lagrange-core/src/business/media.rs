@@ -0,0 +1,697 @@
+use crate::common::MessageEvent;
+use crate::internal::context::{CacheContext, MediaRKey, RKeyKind};
+use crate::internal::packets::oidb::{
+    MsgInfo, NtHighwayDomain, NtHighwayExt, NtHighwayHash, NtHighwayIpv4, NtHighwayNetwork,
+};
+use crate::internal::services::message::{
+    FetchRKeyEventReq, FetchRKeyService, FriendImageUploadEventReq, FriendImageUploadService, FriendRecordDownloadEventReq,
+    FriendRecordDownloadService, FriendRecordUploadEventReq, FriendRecordUploadService,
+    GroupImageUploadEventReq, GroupImageUploadService, GroupRecordDownloadEventReq,
+    GroupRecordDownloadService, GroupRecordUploadEventReq, GroupRecordUploadService, ImageUpload,
+    MediaUploadTicket, RecordUpload, SendTarget,
+};
+use crate::message::{ImageEntity, MediaSource, MessageChain, MessageEntity, RecordEntity};
+use crate::utils::common::to_hex;
+use crate::utils::audio::AudioProbe;
+use crate::utils::image;
+use crate::utils::upload::{FileDigest, UploadSource};
+use crate::{BotContext, Error};
+use bytes::Bytes;
+use lagrange_proto::ProtoMessage;
+use std::future::Future;
+use std::io::Cursor;
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncSeek};
+
+/// Highway command ids of image uploads
+const FRIEND_IMAGE_COMMAND_ID: u32 = 1003;
+const GROUP_IMAGE_COMMAND_ID: u32 = 1004;
+
+/// Highway command ids of record uploads
+const FRIEND_RECORD_COMMAND_ID: u32 = 1007;
+const GROUP_RECORD_COMMAND_ID: u32 = 1008;
+
+/// Host of NT images, their paths carry the file id
+const NT_IMAGE_HOST: &str = "multimedia.nt.qq.com.cn";
+/// Host of images sent by older clients, addressed by md5
+const LEGACY_IMAGE_HOST: &str = "gchat.qpic.cn";
+
+/// Start of an image kept to read its dimensions, which JPEG files may put after large metadata
+const IMAGE_HEAD_LENGTH: usize = 256 * 1024;
+
+impl BotContext {
+    /// Upload a PNG, JPEG or GIF image for the group `group_uin`.
+    ///
+    /// Images the server already has are not uploaded again. The result can only be sent to
+    /// the group it was uploaded for.
+    pub async fn upload_group_image(self: &Arc<Self>, group_uin: u64, data: &[u8]) -> Result<ImageEntity, Error> {
+        self.upload_group_image_from(group_uin, Cursor::new(data)).await
+    }
+
+    async fn upload_group_image_from<R>(self: &Arc<Self>, group_uin: u64, reader: R) -> Result<ImageEntity, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        upload_image_with(
+            reader,
+            true,
+            self.config.highway_chunk_size,
+            |image| async move {
+                let request = GroupImageUploadEventReq { group_uin, image };
+                let response = self.event.send::<GroupImageUploadService>(request, self.clone()).await?;
+                Ok(response.ticket)
+            },
+            |source, extend_info| async move {
+                self.highway_upload_source(GROUP_IMAGE_COMMAND_ID, source, &extend_info).await
+            },
+        )
+        .await
+    }
+
+    /// Upload a PNG, JPEG or GIF image for the friend `uin`, see [`BotContext::upload_group_image`]
+    pub async fn upload_friend_image(self: &Arc<Self>, uin: u64, data: &[u8]) -> Result<ImageEntity, Error> {
+        let uid = self.resolve_uid(uin).await?;
+        self.upload_friend_image_by_uid(uid, Cursor::new(data)).await
+    }
+
+    async fn upload_friend_image_by_uid<R>(self: &Arc<Self>, uid: String, reader: R) -> Result<ImageEntity, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        upload_image_with(
+            reader,
+            false,
+            self.config.highway_chunk_size,
+            |image| async move {
+                let request = FriendImageUploadEventReq { uid, image };
+                let response = self.event.send::<FriendImageUploadService>(request, self.clone()).await?;
+                Ok(response.ticket)
+            },
+            |source, extend_info| async move {
+                self.highway_upload_source(FRIEND_IMAGE_COMMAND_ID, source, &extend_info).await
+            },
+        )
+        .await
+    }
+
+    /// Upload an encoded SILK or AMR voice clip for the group `group_uin`.
+    ///
+    /// Raw audio has to be encoded beforehand, the duration shown by clients is read from the
+    /// frames of the clip.
+    pub async fn upload_group_record(self: &Arc<Self>, group_uin: u64, data: &[u8]) -> Result<RecordEntity, Error> {
+        self.upload_group_record_from(group_uin, Cursor::new(data)).await
+    }
+
+    async fn upload_group_record_from<R>(self: &Arc<Self>, group_uin: u64, reader: R) -> Result<RecordEntity, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        upload_record_with(
+            reader,
+            true,
+            self.config.highway_chunk_size,
+            |record| async move {
+                let request = GroupRecordUploadEventReq { group_uin, record };
+                let response = self.event.send::<GroupRecordUploadService>(request, self.clone()).await?;
+                Ok(response.ticket)
+            },
+            |source, extend_info| async move {
+                self.highway_upload_source(GROUP_RECORD_COMMAND_ID, source, &extend_info).await
+            },
+        )
+        .await
+    }
+
+    /// Upload a voice clip for the friend `uin`, see [`BotContext::upload_group_record`]
+    pub async fn upload_friend_record(self: &Arc<Self>, uin: u64, data: &[u8]) -> Result<RecordEntity, Error> {
+        let uid = self.resolve_uid(uin).await?;
+        self.upload_friend_record_by_uid(uid, Cursor::new(data)).await
+    }
+
+    async fn upload_friend_record_by_uid<R>(self: &Arc<Self>, uid: String, reader: R) -> Result<RecordEntity, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        upload_record_with(
+            reader,
+            false,
+            self.config.highway_chunk_size,
+            |record| async move {
+                let request = FriendRecordUploadEventReq { uid, record };
+                let response = self.event.send::<FriendRecordUploadService>(request, self.clone()).await?;
+                Ok(response.ticket)
+            },
+            |source, extend_info| async move {
+                self.highway_upload_source(FRIEND_RECORD_COMMAND_ID, source, &extend_info).await
+            },
+        )
+        .await
+    }
+
+    /// Download the first voice clip of a received message, `None` if it has none
+    pub async fn download_voice(self: &Arc<Self>, event: &impl MessageEvent) -> Result<Option<Bytes>, Error> {
+        let Some(record) = event.chain().entities().iter().find_map(|entity| match entity {
+            MessageEntity::Record(record) => Some(record),
+            _ => None,
+        }) else {
+            return Ok(None);
+        };
+
+        let url = self.get_voice_url(event, record).await?;
+        Ok(Some(self.http.get(&url).await?))
+    }
+
+    /// Download url of an image of a received message.
+    ///
+    /// NT images are authorized by an rkey, which is fetched once and reused until it expires.
+    pub async fn get_image_url(self: &Arc<Self>, image: &ImageEntity) -> Result<String, Error> {
+        image_url_with(image, |kind| async move {
+            resolve_rkey_with(&self.cache, kind, chrono::Utc::now().timestamp(), || async {
+                Ok(self.event.send::<FetchRKeyService>(FetchRKeyEventReq {}, self.clone()).await?.rkeys)
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Download url of `record`, which `event` carried
+    pub async fn get_voice_url(self: &Arc<Self>, event: &impl MessageEvent, record: &RecordEntity) -> Result<String, Error> {
+        let node = record
+            .msg_info
+            .as_deref()
+            .and_then(|msg_info| MsgInfo::decode_from_slice(msg_info).ok())
+            .and_then(|msg_info| msg_info.msg_info_body.into_iter().next())
+            .and_then(|body| body.index)
+            .ok_or_else(|| Error::ProtocolError("Record carries no file index".to_string()))?;
+
+        let url = match event.group_uin() {
+            Some(group_uin) => {
+                let request = GroupRecordDownloadEventReq { group_uin, node };
+                self.event.send::<GroupRecordDownloadService>(request, self.clone()).await?.url
+            }
+            None => {
+                let request = FriendRecordDownloadEventReq { uid: event.sender_uid().to_string(), node };
+                self.event.send::<FriendRecordDownloadService>(request, self.clone()).await?.url
+            }
+        };
+        Ok(url)
+    }
+
+    /// Upload the images and records of `chain` that still have a [`MediaSource`]
+    pub(crate) async fn upload_pending_media(self: &Arc<Self>, target: &SendTarget, chain: MessageChain) -> Result<MessageChain, Error> {
+        let mut entities = Vec::with_capacity(chain.len());
+        for entity in chain.entities() {
+            let entity = match entity {
+                MessageEntity::Image(ImageEntity { source: Some(source), .. }) => MessageEntity::Image(match source {
+                    MediaSource::Bytes(data) => self.upload_pending_image(target, Cursor::new(data.clone())).await?,
+                    MediaSource::Path(path) => self.upload_pending_image(target, File::open(path).await?).await?,
+                }),
+                MessageEntity::Record(RecordEntity { source: Some(source), .. }) => MessageEntity::Record(match source {
+                    MediaSource::Bytes(data) => self.upload_pending_record(target, Cursor::new(data.clone())).await?,
+                    MediaSource::Path(path) => self.upload_pending_record(target, File::open(path).await?).await?,
+                }),
+                entity => entity.clone(),
+            };
+            entities.push(entity);
+        }
+        Ok(MessageChain::from(entities))
+    }
+
+    async fn upload_pending_image<R>(self: &Arc<Self>, target: &SendTarget, reader: R) -> Result<ImageEntity, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        match target {
+            SendTarget::Group { group_uin } => self.upload_group_image_from(*group_uin, reader).await,
+            SendTarget::Friend { uid, .. } => self.upload_friend_image_by_uid(uid.clone(), reader).await,
+        }
+    }
+
+    async fn upload_pending_record<R>(self: &Arc<Self>, target: &SendTarget, reader: R) -> Result<RecordEntity, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        match target {
+            SendTarget::Group { group_uin } => self.upload_group_record_from(*group_uin, reader).await,
+            SendTarget::Friend { uid, .. } => self.upload_friend_record_by_uid(uid.clone(), reader).await,
+        }
+    }
+
+    /// Push an opened media file through the highway, resuming it after network errors
+    async fn highway_upload_source<R>(
+        self: &Arc<Self>,
+        command_id: u32,
+        mut source: UploadSource<R>,
+        extend_info: &[u8],
+    ) -> Result<Vec<u8>, Error>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let digest = source.digest();
+        self.highway_upload_resumable(command_id, source.reader_mut(), digest.size, digest.md5, extend_info, None)
+            .await
+    }
+}
+
+/// Url of `image`, with the rkey of its kind appended for NT images.
+///
+/// The rkey lookup is passed in, so the assembly can be tested without a connection.
+async fn image_url_with<R, RFut>(image: &ImageEntity, rkey: R) -> Result<String, Error>
+where
+    R: FnOnce(RKeyKind) -> RFut,
+    RFut: Future<Output = Result<MediaRKey, Error>>,
+{
+    if image.msg_info.is_some() {
+        let url = image
+            .url
+            .as_deref()
+            .ok_or_else(|| Error::ProtocolError("Image carries no download path".to_string()))?;
+        let rkey = rkey(RKeyKind::from_is_group(image.is_group)).await?;
+        return Ok(format!("{}{}", url, rkey.param));
+    }
+
+    match image.url.as_deref() {
+        Some(url) if url.starts_with("http") => Ok(url.to_string()),
+        // Legacy elements sent by NT clients point to the NT servers and bring their own rkey
+        Some(url) if url.contains("rkey=") => Ok(format!("https://{}{}", NT_IMAGE_HOST, url)),
+        Some(url) if !url.is_empty() => Ok(format!("http://{}{}", LEGACY_IMAGE_HOST, url)),
+        _ if image.md5.len() == 16 => Ok(format!(
+            "http://{}/gchatpic_new/0/0-0-{}/0",
+            LEGACY_IMAGE_HOST,
+            to_hex(&image.md5).to_uppercase()
+        )),
+        _ => Err(Error::ProtocolError("Image carries neither a path nor an md5".to_string())),
+    }
+}
+
+/// Rkey of `kind` from `cache`, refreshed through `fetch` once it is about to expire at `now`
+async fn resolve_rkey_with<F, FFut>(cache: &CacheContext, kind: RKeyKind, now: i64, fetch: F) -> Result<MediaRKey, Error>
+where
+    F: FnOnce() -> FFut,
+    FFut: Future<Output = Result<Vec<MediaRKey>, Error>>,
+{
+    if let Some(rkey) = cache.get_rkey(kind, now) {
+        return Ok(rkey);
+    }
+
+    cache.cache_rkeys(fetch().await?);
+    cache
+        .get_rkey(kind, now)
+        .ok_or_else(|| Error::ProtocolError(format!("The server issued no valid rkey for {:?} images", kind)))
+}
+
+/// Hash `reader` and request a ticket for it, then push it through the highway unless the
+/// server has it.
+///
+/// Both steps are passed in, so the flow can be tested without a connection.
+async fn upload_image_with<R, T, TFut, H, HFut>(
+    reader: R,
+    is_group: bool,
+    block_size: usize,
+    request_ticket: T,
+    highway_upload: H,
+) -> Result<ImageEntity, Error>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    T: FnOnce(ImageUpload) -> TFut,
+    TFut: Future<Output = Result<MediaUploadTicket, Error>>,
+    H: FnOnce(UploadSource<R>, Vec<u8>) -> HFut,
+    HFut: Future<Output = Result<Vec<u8>, Error>>,
+{
+    let mut head = Vec::new();
+    let source = UploadSource::open_inspecting(reader, block_size, |piece| {
+        let take = IMAGE_HEAD_LENGTH.saturating_sub(head.len()).min(piece.len());
+        head.extend_from_slice(&piece[..take]);
+    })
+    .await?;
+    let info = image::probe(&head)
+        .ok_or_else(|| Error::BuildError("Unsupported image, expected PNG, JPEG or GIF".to_string()))?;
+    let upload = ImageUpload::from_digest(&media_digest(source.digest())?, info);
+    let ticket = request_ticket(upload.clone()).await?;
+    push_unless_present(&ticket, &upload.sha1, block_size, |extend_info| highway_upload(source, extend_info)).await?;
+
+    let mut image = ImageEntity::from_msg_info(&ticket.msg_info);
+    image.file_name = upload.file_name();
+    image.md5 = upload.md5.to_vec();
+    image.size = upload.size;
+    image.width = info.width;
+    image.height = info.height;
+    image.msg_info = Some(
+        ticket
+            .msg_info
+            .encode_to_vec()
+            .map_err(|e| Error::BuildError(e.to_string()))?,
+    );
+    image.is_group = is_group;
+    Ok(image)
+}
+
+/// Voice clip counterpart of [`upload_image_with`]
+async fn upload_record_with<R, T, TFut, H, HFut>(
+    reader: R,
+    is_group: bool,
+    block_size: usize,
+    request_ticket: T,
+    highway_upload: H,
+) -> Result<RecordEntity, Error>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+    T: FnOnce(RecordUpload) -> TFut,
+    TFut: Future<Output = Result<MediaUploadTicket, Error>>,
+    H: FnOnce(UploadSource<R>, Vec<u8>) -> HFut,
+    HFut: Future<Output = Result<Vec<u8>, Error>>,
+{
+    let mut probe = AudioProbe::new();
+    let source = UploadSource::open_inspecting(reader, block_size, |piece| probe.feed(piece)).await?;
+    let info = probe
+        .finish()
+        .ok_or_else(|| Error::BuildError("Unsupported voice clip, expected SILK or AMR".to_string()))?;
+    let upload = RecordUpload::from_digest(&media_digest(source.digest())?, info);
+    let ticket = request_ticket(upload.clone()).await?;
+    push_unless_present(&ticket, &upload.sha1, block_size, |extend_info| highway_upload(source, extend_info)).await?;
+
+    let msg_info = ticket
+        .msg_info
+        .encode_to_vec()
+        .map_err(|e| Error::BuildError(e.to_string()))?;
+    let mut record = RecordEntity::from_encoded_msg_info(msg_info, is_group);
+    record.file_name = upload.file_name();
+    record.md5 = upload.md5.to_vec();
+    record.size = upload.size;
+    record.duration = info.seconds();
+    Ok(record)
+}
+
+/// `digest` if its size fits into rich media requests, which announce it as 32 bits
+fn media_digest(digest: FileDigest) -> Result<FileDigest, Error> {
+    if digest.size > u32::MAX as u64 {
+        return Err(Error::BuildError(format!("Media of {} bytes is too large to upload", digest.size)));
+    }
+    Ok(digest)
+}
+
+/// Push the file through the highway if the ticket carries an upload key, the server omits it
+/// for files it already has
+async fn push_unless_present<H, HFut>(
+    ticket: &MediaUploadTicket,
+    sha1: &[u8; 20],
+    block_size: usize,
+    highway_upload: H,
+) -> Result<(), Error>
+where
+    H: FnOnce(Vec<u8>) -> HFut,
+    HFut: Future<Output = Result<Vec<u8>, Error>>,
+{
+    if let Some(u_key) = &ticket.u_key {
+        let extend_info = highway_extend_info(ticket, u_key, sha1, block_size)
+            .encode_to_vec()
+            .map_err(|e| Error::BuildError(e.to_string()))?;
+        highway_upload(extend_info).await?;
+    }
+    Ok(())
+}
+
+fn highway_extend_info(ticket: &MediaUploadTicket, u_key: &str, sha1: &[u8; 20], block_size: usize) -> NtHighwayExt {
+    let msg_info_body = ticket.msg_info.msg_info_body.clone();
+    let file_uuid = msg_info_body
+        .first()
+        .and_then(|body| body.index.as_ref())
+        .map(|index| index.file_uuid.clone())
+        .unwrap_or_default();
+    let ipv4s = ticket
+        .servers
+        .iter()
+        .filter_map(|server| server.rsplit_once(':'))
+        .map(|(ip, port)| NtHighwayIpv4 {
+            domain: Some(NtHighwayDomain { is_enable: true, ip: ip.to_string() }),
+            port: port.parse().unwrap_or_default(),
+        })
+        .collect();
+
+    NtHighwayExt {
+        file_uuid,
+        u_key: u_key.to_string(),
+        network: Some(NtHighwayNetwork { ipv4s }),
+        msg_info_body,
+        block_size: block_size as u32,
+        hash: Some(NtHighwayHash { file_sha1: vec![sha1.to_vec()] }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::{IndexNode, MsgInfoBody, PictureInfo};
+    use std::sync::Mutex;
+
+    /// A 2x1 PNG, only the header matters for the upload
+    fn png() -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(2u32.to_be_bytes());
+        png.extend(1u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+        png
+    }
+
+    fn ticket(u_key: Option<&str>) -> MediaUploadTicket {
+        MediaUploadTicket {
+            u_key: u_key.map(str::to_string),
+            servers: vec!["10.0.0.1:80".to_string()],
+            msg_info: MsgInfo {
+                msg_info_body: vec![MsgInfoBody {
+                    index: Some(IndexNode { file_uuid: "uuid".to_string(), ..Default::default() }),
+                    picture: Some(PictureInfo {
+                        url_path: "/download?fileid=uuid".to_string(),
+                        domain: "multimedia.nt.qq.com.cn".to_string(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_existing_image_skips_highway() {
+        let data = png();
+        let image = upload_image_with(
+            Cursor::new(&data),
+            true,
+            1024,
+            |upload| async move {
+                assert_eq!((upload.info.width, upload.info.height), (2, 1));
+                Ok(ticket(None))
+            },
+            |_, _| async { panic!("the server already has the image") },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(image.md5, md5::compute(&data).0.to_vec());
+        assert_eq!(image.file_id.as_deref(), Some("uuid"));
+        assert_eq!(image.url.as_deref(), Some("https://multimedia.nt.qq.com.cn/download?fileid=uuid"));
+        assert!(image.is_group);
+        assert_eq!(
+            MsgInfo::decode_from_slice(image.msg_info.as_ref().unwrap()).unwrap(),
+            ticket(None).msg_info
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_image_is_uploaded() {
+        let data = png();
+        let uploaded = Mutex::new(None);
+        let image = upload_image_with(
+            Cursor::new(&data),
+            false,
+            1024,
+            |_| async { Ok(ticket(Some("ukey"))) },
+            |_, extend_info| async {
+                *uploaded.lock().unwrap() = Some(extend_info);
+                Ok(Vec::new())
+            },
+        )
+        .await
+        .unwrap();
+
+        let extend_info = NtHighwayExt::decode_from_slice(&uploaded.into_inner().unwrap().unwrap()).unwrap();
+        assert_eq!(extend_info.file_uuid, "uuid");
+        assert_eq!(extend_info.u_key, "ukey");
+        assert_eq!(extend_info.block_size, 1024);
+        assert_eq!(extend_info.network.unwrap().ipv4s[0].port, 80);
+        {
+            use sha1::Digest;
+            assert_eq!(extend_info.hash.unwrap().file_sha1, vec![sha1::Sha1::digest(&data).to_vec()]);
+        }
+        assert!(!image.is_group);
+        assert_eq!(image.size, data.len() as u32);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_image() {
+        let result = upload_image_with(
+            Cursor::new(b"plain text"),
+            true,
+            1024,
+            |_| async { panic!("nothing to upload") },
+            |_, _| async { panic!("nothing to upload") },
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BuildError(_))));
+    }
+
+    const SILK_FIXTURE: &[u8] = include_bytes!("../../tests/fixtures/voice.silk");
+
+    #[tokio::test]
+    async fn test_existing_record_skips_highway() {
+        let record = upload_record_with(
+            Cursor::new(SILK_FIXTURE),
+            true,
+            1024,
+            |upload| async move {
+                assert_eq!(upload.info.format, crate::utils::audio::AudioFormat::Silk);
+                Ok(ticket(None))
+            },
+            |_, _| async { panic!("the server already has the record") },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(record.md5, md5::compute(SILK_FIXTURE).0.to_vec());
+        assert_eq!(record.file_name, format!("{}.silk", crate::utils::common::to_hex(&record.md5)));
+        assert_eq!(record.size, SILK_FIXTURE.len() as u32);
+        assert_eq!(record.duration, 3);
+        assert_eq!(record.file_id.as_deref(), Some("uuid"));
+        assert!(record.is_group);
+        assert!(record.source.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_new_record_is_uploaded() {
+        let uploaded = Mutex::new(None);
+        let record = upload_record_with(
+            Cursor::new(SILK_FIXTURE),
+            false,
+            4096,
+            |_| async { Ok(ticket(Some("ukey"))) },
+            |_, extend_info| async {
+                *uploaded.lock().unwrap() = Some(extend_info);
+                Ok(Vec::new())
+            },
+        )
+        .await
+        .unwrap();
+
+        let extend_info = NtHighwayExt::decode_from_slice(&uploaded.into_inner().unwrap().unwrap()).unwrap();
+        assert_eq!((extend_info.file_uuid.as_str(), extend_info.block_size), ("uuid", 4096));
+        assert!(!record.is_group);
+        assert_eq!(
+            MsgInfo::decode_from_slice(record.msg_info.as_ref().unwrap()).unwrap(),
+            ticket(Some("ukey")).msg_info
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_record() {
+        let result = upload_record_with(
+            Cursor::new(b"RIFF\0\0\0\0WAVE"),
+            true,
+            1024,
+            |_| async { panic!("nothing to upload") },
+            |_, _| async { panic!("nothing to upload") },
+        )
+        .await;
+        assert!(matches!(result, Err(Error::BuildError(_))));
+    }
+
+    fn rkey(kind: RKeyKind, param: &str, expires_at: i64) -> MediaRKey {
+        MediaRKey { kind, param: param.to_string(), expires_at }
+    }
+
+    #[tokio::test]
+    async fn test_rkey_is_cached_until_expiry() {
+        let cache = CacheContext::default();
+        let fetches = Mutex::new(0);
+        let fetch = |param: &'static str, expires_at| {
+            let fetches = &fetches;
+            move || async move {
+                *fetches.lock().unwrap() += 1;
+                Ok(vec![rkey(RKeyKind::Private, "&rkey=private", expires_at), rkey(RKeyKind::Group, param, expires_at)])
+            }
+        };
+
+        let first = resolve_rkey_with(&cache, RKeyKind::Group, 1000, fetch("&rkey=first", 4600)).await.unwrap();
+        assert_eq!(first.param, "&rkey=first");
+        let cached = resolve_rkey_with(&cache, RKeyKind::Group, 2000, fetch("&rkey=unused", 9000)).await.unwrap();
+        assert_eq!(cached.param, "&rkey=first");
+        let private = resolve_rkey_with(&cache, RKeyKind::Private, 2000, fetch("&rkey=unused", 9000)).await.unwrap();
+        assert_eq!(private.param, "&rkey=private");
+        assert_eq!(*fetches.lock().unwrap(), 1);
+
+        // Close to the expiry the key is fetched again
+        let renewed = resolve_rkey_with(&cache, RKeyKind::Group, 4550, fetch("&rkey=second", 8200)).await.unwrap();
+        assert_eq!(renewed.param, "&rkey=second");
+        assert_eq!(*fetches.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_rkey_from_server() {
+        let cache = CacheContext::default();
+        let result = resolve_rkey_with(&cache, RKeyKind::Group, 5000, || async {
+            Ok(vec![rkey(RKeyKind::Group, "&rkey=stale", 4000)])
+        })
+        .await;
+        assert!(matches!(result, Err(Error::ProtocolError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_nt_image_url() {
+        // Received group image as a synthetic rich media element
+        let msg_info = MsgInfo {
+            msg_info_body: vec![MsgInfoBody {
+                index: Some(IndexNode { file_uuid: "uuid".to_string(), ..Default::default() }),
+                picture: Some(PictureInfo {
+                    url_path: "/download?appid=1407&fileid=uuid".to_string(),
+                    domain: NT_IMAGE_HOST.to_string(),
+                    ext: Some(crate::internal::packets::oidb::PicUrlExtInfo {
+                        original_parameter: "&spec=0".to_string(),
+                        ..Default::default()
+                    }),
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut image = ImageEntity::from_msg_info(&msg_info);
+        image.msg_info = Some(msg_info.encode_to_vec().unwrap());
+        image.is_group = true;
+
+        let url = image_url_with(&image, |kind| async move {
+            assert_eq!(kind, RKeyKind::Group);
+            Ok(rkey(kind, "&rkey=CAQSKAB", i64::MAX))
+        })
+        .await
+        .unwrap();
+        assert_eq!(url, "https://multimedia.nt.qq.com.cn/download?appid=1407&fileid=uuid&spec=0&rkey=CAQSKAB");
+    }
+
+    #[tokio::test]
+    async fn test_legacy_image_urls() {
+        let no_rkey = |_| async { panic!("legacy images need no rkey") };
+        let legacy = |url: Option<&str>, md5: Vec<u8>| ImageEntity { url: url.map(str::to_string), md5, ..Default::default() };
+
+        let url = image_url_with(&legacy(Some("/download?appid=1407&fileid=uuid&rkey=CAQ"), Vec::new()), no_rkey).await;
+        assert_eq!(url.unwrap(), "https://multimedia.nt.qq.com.cn/download?appid=1407&fileid=uuid&rkey=CAQ");
+
+        let url = image_url_with(&legacy(Some("/gchatpic_new/1/0-0-ABCD/0"), Vec::new()), no_rkey).await;
+        assert_eq!(url.unwrap(), "http://gchat.qpic.cn/gchatpic_new/1/0-0-ABCD/0");
+
+        let url = image_url_with(&legacy(None, vec![0xab; 16]), no_rkey).await;
+        assert_eq!(url.unwrap(), format!("http://gchat.qpic.cn/gchatpic_new/0/0-0-{}/0", "AB".repeat(16)));
+
+        let url = image_url_with(&legacy(None, Vec::new()), no_rkey).await;
+        assert!(matches!(url, Err(Error::ProtocolError(_))));
+    }
+}
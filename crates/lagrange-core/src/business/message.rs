@@ -0,0 +1,489 @@
+use crate::common::{EmojiId, MessageScene, PokeTarget};
+use crate::internal::services::message::{
+    DownloadForwardEventReq, DownloadForwardService, FriendRecallEventReq, FriendRecallService,
+    GroupRecallEventReq, GroupRecallService, SendMessageEventReq, SendMessageEventResp,
+    SendMessageService, SendTarget, SetInputStatusEventReq, SetInputStatusService,
+    UploadForwardEventReq, UploadForwardService,
+};
+use crate::internal::services::system::{
+    AddReactionEventReq, AddReactionService, RemoveReactionEventReq, RemoveReactionService, SendPokeEventReq,
+    SendPokeService,
+};
+use crate::message::{
+    MessageChain, MessageEntity, MessageId, MessageNode, MessageReceipt, MessageTarget, ReplyEntity,
+    SendMessageError,
+};
+use crate::{BotContext, Error};
+use std::sync::Arc;
+
+impl BotContext {
+    /// Send `chain` to the friend `uin`.
+    ///
+    /// The friend's uid is looked up on the server if it is not cached.
+    pub async fn send_friend_message(self: &Arc<Self>, uin: u64, chain: MessageChain) -> Result<MessageReceipt, Error> {
+        let uid = self.resolve_uid(uin).await?;
+
+        self.send_message(SendTarget::Friend { uin, uid }, chain).await
+    }
+
+    /// Send `chain` to the group `group_uin`.
+    ///
+    /// The sequence of the returned receipt is the group sequence, used to recall the message.
+    pub async fn send_group_message(self: &Arc<Self>, group_uin: u64, chain: MessageChain) -> Result<MessageReceipt, Error> {
+        self.send_message(SendTarget::Group { group_uin }, chain).await
+    }
+
+    /// Recall a message in `group_uin` by its group sequence, e.g. [`MessageReceipt::sequence`].
+    ///
+    /// Messages of other members can be recalled too if the bot is an admin.
+    pub async fn recall_group_message(self: &Arc<Self>, group_uin: u64, sequence: u32) -> Result<(), Error> {
+        let request = GroupRecallEventReq { group_uin, sequence };
+        self.event.send::<GroupRecallService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// React to the message `sequence` of `group_uin` with `emoji_id`, or take the reaction back
+    /// if `add` is `false`.
+    pub async fn set_group_reaction(
+        self: &Arc<Self>,
+        group_uin: u64,
+        sequence: u32,
+        emoji_id: EmojiId,
+        add: bool,
+    ) -> Result<(), Error> {
+        if add {
+            let request = AddReactionEventReq { group_uin, sequence, emoji_id };
+            self.event.send::<AddReactionService>(request, self.clone()).await?;
+        } else {
+            let request = RemoveReactionEventReq { group_uin, sequence, emoji_id };
+            self.event.send::<RemoveReactionService>(request, self.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Recall a message sent to the friend `uin`.
+    ///
+    /// `sequence`, `random` and `timestamp` are those of the [`MessageReceipt`] of the message.
+    pub async fn recall_friend_message(self: &Arc<Self>, uin: u64, sequence: u32, random: u32, timestamp: i64) -> Result<(), Error> {
+        let uid = self.resolve_uid(uin).await?;
+
+        let request = FriendRecallEventReq {
+            uid,
+            client_sequence: sequence,
+            sequence,
+            random,
+            timestamp: timestamp as u32,
+        };
+        self.event.send::<FriendRecallService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Recall a message the bot sent, by the id of its receipt.
+    ///
+    /// Fails if the receipt is no longer in [`BotContext::messages`].
+    pub async fn recall_message(self: &Arc<Self>, message_id: MessageId) -> Result<(), Error> {
+        let receipt = self.stored_receipt(message_id)?;
+        match receipt.target {
+            MessageTarget::Group(group_uin) => self.recall_group_message(group_uin, receipt.sequence).await,
+            MessageTarget::Friend(uin) => {
+                let request = FriendRecallEventReq {
+                    uid: self.resolve_uid(uin).await?,
+                    client_sequence: receipt.client_sequence,
+                    sequence: receipt.sequence,
+                    random: receipt.random,
+                    timestamp: receipt.timestamp as u32,
+                };
+                self.event.send::<FriendRecallService>(request, self.clone()).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Show the friend `uin` that the bot is typing, or stop showing it.
+    pub async fn set_input_status(self: &Arc<Self>, uin: u64, typing: bool) -> Result<(), Error> {
+        let uid = self.resolve_uid(uin).await?;
+
+        self.event.send::<SetInputStatusService>(SetInputStatusEventReq { uid, typing }, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Poke `uin`, either a friend in the private chat or a member of a group.
+    pub async fn send_poke(self: &Arc<Self>, target: PokeTarget, uin: u64) -> Result<(), Error> {
+        let group_uin = match target {
+            PokeTarget::Friend => None,
+            PokeTarget::Group(group_uin) => Some(group_uin),
+        };
+        self.event.send::<SendPokeService>(SendPokeEventReq { uin, group_uin }, self.clone()).await?;
+        Ok(())
+    }
+
+    /// Upload `nodes` as a forwarded bundle and return its resid.
+    ///
+    /// Send the bundle with [`MessageChainBuilder::forward`](crate::message::MessageChainBuilder::forward)
+    /// to the group `group_uin`, or to a friend if `None`. Bundles nest by putting the resid of
+    /// one into a node of another.
+    pub async fn upload_forward(self: &Arc<Self>, group_uin: Option<u64>, nodes: Vec<MessageNode>) -> Result<String, Error> {
+        let request = UploadForwardEventReq { group_uin, nodes };
+        let response = self.event.send::<UploadForwardService>(request, self.clone()).await?;
+        Ok(response.res_id)
+    }
+
+    /// Download the messages of a forwarded bundle, e.g. from a received
+    /// [`MessageEntity::Forward`](crate::message::MessageEntity::Forward).
+    ///
+    /// Nested bundles are left as forward elements, download them the same way.
+    pub async fn download_forward(self: &Arc<Self>, res_id: impl Into<String>) -> Result<Vec<MessageNode>, Error> {
+        let request = DownloadForwardEventReq { res_id: res_id.into() };
+        let response = self.event.send::<DownloadForwardService>(request, self.clone()).await?;
+        Ok(response.nodes)
+    }
+
+    /// Wait until the messages queued for `target` are sent, see [`SendQueueContext::flush`].
+    ///
+    /// Returns right away if the send queue is disabled.
+    ///
+    /// [`SendQueueContext::flush`]: crate::internal::context::SendQueueContext::flush
+    pub async fn flush_messages(&self, target: MessageTarget) {
+        self.send_queue.flush(target).await
+    }
+
+    /// Send `chain` to the chat of a received message, see [`MessageEvent::reply`]
+    ///
+    /// [`MessageEvent::reply`]: crate::common::MessageEvent::reply
+    pub(crate) async fn send_to_scene(
+        self: &Arc<Self>,
+        scene: MessageScene,
+        sender_uin: u64,
+        sender_uid: String,
+        chain: MessageChain,
+    ) -> Result<MessageReceipt, Error> {
+        let target = match scene {
+            MessageScene::Friend => SendTarget::Friend { uin: sender_uin, uid: sender_uid },
+            MessageScene::Group(group_uin) => SendTarget::Group { group_uin },
+            MessageScene::Temp => {
+                return Err(Error::BuildError("Sending temporary messages is not supported".to_string()));
+            }
+        };
+        self.send_message(target, chain).await
+    }
+
+    /// Send `chain` to `target`, behind the messages queued for it if the send queue is enabled
+    async fn send_message(self: &Arc<Self>, target: SendTarget, chain: MessageChain) -> Result<MessageReceipt, Error> {
+        let message_target = match &target {
+            SendTarget::Friend { uin, .. } => MessageTarget::Friend(*uin),
+            SendTarget::Group { group_uin } => MessageTarget::Group(*group_uin),
+        };
+        // Media are uploaded in the queue too, so a message with an image does not fall behind
+        // the text sent after it
+        self.send_queue
+            .run(message_target, || async {
+                let chain = self.resolve_replies(chain)?;
+                let chain = self.upload_pending_media(&target, chain).await?;
+                let request = SendMessageEventReq {
+                    target,
+                    chain,
+                    client_sequence: self.next_message_sequence(),
+                    random: rand::random::<u32>(),
+                };
+                let (client_sequence, random) = (request.client_sequence, request.random);
+
+                let response = self.event.send::<SendMessageService>(request, self.clone()).await?;
+                let receipt = into_receipt(&response, message_target, client_sequence, random)?;
+                Ok(self.messages.insert(receipt))
+            })
+            .await
+    }
+
+    fn stored_receipt(&self, message_id: MessageId) -> Result<MessageReceipt, Error> {
+        self.messages
+            .get(message_id)
+            .ok_or_else(|| Error::BuildError(format!("Message {} is not in the message store", message_id)))
+    }
+
+    /// Fill in replies to messages of the bot from their receipts
+    fn resolve_replies(&self, chain: MessageChain) -> Result<MessageChain, Error> {
+        if !chain.entities().iter().any(|entity| matches!(entity, MessageEntity::Reply(ReplyEntity { message_id: Some(_), .. }))) {
+            return Ok(chain);
+        }
+
+        let mut entities = Vec::with_capacity(chain.len());
+        for entity in chain.entities() {
+            let entity = match entity {
+                MessageEntity::Reply(reply @ ReplyEntity { message_id: Some(message_id), .. }) => {
+                    let receipt = self.stored_receipt(*message_id)?;
+                    MessageEntity::Reply(ReplyEntity {
+                        sequence: receipt.sequence,
+                        sender_uin: self.bot_uin().unwrap_or_default(),
+                        time: receipt.timestamp as u32,
+                        ..reply.clone()
+                    })
+                }
+                entity => entity.clone(),
+            };
+            entities.push(entity);
+        }
+        Ok(MessageChain::from(entities))
+    }
+}
+
+fn into_receipt(
+    response: &SendMessageEventResp,
+    target: MessageTarget,
+    client_sequence: u32,
+    random: u32,
+) -> Result<MessageReceipt, Error> {
+    if let Some(error) = SendMessageError::from_result(response.result, &response.error_message) {
+        return Err(error.into());
+    }
+
+    Ok(MessageReceipt {
+        id: MessageId::default(),
+        target,
+        sequence: response.sequence,
+        client_sequence,
+        random,
+        timestamp: response.timestamp as i64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::message::{FriendRecallRequest, GroupRecallRequest, PbSendMsg, PbSendMsgResp};
+    use crate::common::{FriendMessageEvent, GroupMessageEvent, MessageEvent, TempMessageEvent};
+    use crate::keystore::BotKeystore;
+    use crate::message::MessageChainBuilder;
+    use crate::protocol::TypedService;
+    use crate::testing::{Expectation, MockServer};
+    use bytes::Bytes;
+    use lagrange_proto::{ProtoDecode, ProtoMessage};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Build the request like the real send path, answer it like the server, and map the result
+    async fn round_trip(context: &Arc<BotContext>, request: SendMessageEventReq, result: u32) -> Result<MessageReceipt, Error> {
+        let service = SendMessageService::default();
+        let body = service.build(&request, context.clone()).await.unwrap();
+        let sent = PbSendMsg::decode(&body).unwrap();
+        let group = sent.routing_head.unwrap().grp.unwrap();
+
+        let response = PbSendMsgResp {
+            result: Some(result),
+            err_msg: Some("server message".to_string()),
+            send_time: Some(1700000000),
+            group_sequence: Some(group.group_code.unwrap() % 1000),
+            ..Default::default()
+        };
+        let parsed = service
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), context.clone())
+            .await
+            .unwrap();
+
+        into_receipt(&parsed, MessageTarget::Group(123456), request.client_sequence, request.random)
+    }
+
+    fn group_request(context: &Arc<BotContext>) -> SendMessageEventReq {
+        SendMessageEventReq {
+            target: SendTarget::Group { group_uin: 123456 },
+            chain: MessageChain::text("hello group"),
+            client_sequence: context.next_message_sequence(),
+            random: 42,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_group_message_receipt() {
+        let context = BotContext::builder().build();
+        let request = group_request(&context);
+        let client_sequence = request.client_sequence;
+
+        let receipt = round_trip(&context, request, 0).await.unwrap();
+        assert_eq!(
+            receipt,
+            MessageReceipt {
+                id: MessageId::default(),
+                target: MessageTarget::Group(123456),
+                sequence: 456,
+                client_sequence,
+                random: 42,
+                timestamp: 1700000000,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_group_message_errors() {
+        let context = BotContext::builder().build();
+
+        let muted = round_trip(&context, group_request(&context), 120).await;
+        assert!(matches!(
+            muted,
+            Err(Error::SendMessage(SendMessageError::Muted { code: 120, .. }))
+        ));
+
+        let slow = round_trip(&context, group_request(&context), 121).await;
+        assert!(matches!(
+            slow,
+            Err(Error::SendMessage(SendMessageError::SlowMode { code: 121, .. }))
+        ));
+
+        let quota = round_trip(&context, group_request(&context), 10024).await;
+        assert!(matches!(
+            quota,
+            Err(Error::SendMessage(SendMessageError::AtAllQuotaExceeded { code: 10024, .. }))
+        ));
+    }
+
+    /// A logged in context whose server accepts every message with the group sequence 1000,
+    /// 1001, ... and every recall
+    async fn mock_server() -> (Arc<BotContext>, MockServer) {
+        let mut keystore = BotKeystore::default().with_uin(10000);
+        keystore.uid = Some("u_bot".to_string());
+        keystore.sigs.d2 = vec![0xD2; 4];
+        keystore.sigs.d2_key = (0..16).collect();
+
+        let (transport, server) = MockServer::start(keystore.clone());
+        let group_sequence = Arc::new(AtomicU32::new(1000));
+        server.expect(Expectation::new("MessageSvc.PbSendMsg").respond_with(move |_| {
+            let response = PbSendMsgResp {
+                result: Some(0),
+                send_time: Some(1700000000),
+                group_sequence: Some(group_sequence.fetch_add(1, Ordering::SeqCst)),
+                ..Default::default()
+            };
+            Bytes::from(response.encode_to_vec().unwrap())
+        }));
+        server.expect(Expectation::new("trpc.msg.msg_svc.MsgService.SsoGroupRecallMsg").respond(Bytes::new()));
+        server.expect(Expectation::new("trpc.msg.msg_svc.MsgService.SsoC2CRecallMsg").respond(Bytes::new()));
+
+        let context = BotContext::builder().keystore(keystore).transport(transport).build();
+        context.cache.map_uid(10001, "u_10001");
+        context.connect().await.unwrap();
+        (context, server)
+    }
+
+    #[tokio::test]
+    async fn test_sent_messages_are_stored() {
+        let (context, _server) = mock_server().await;
+
+        let first = context.send_group_message(123456, MessageChain::text("first")).await.unwrap();
+        let second = context.send_group_message(123456, MessageChain::text("second")).await.unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(first.target, MessageTarget::Group(123456));
+        assert_eq!(context.messages.get(first.id), Some(first));
+        assert_eq!(context.messages.get(second.id).unwrap().sequence, 1001);
+    }
+
+    #[tokio::test]
+    async fn test_reply_and_recall_by_id() {
+        let (context, server) = mock_server().await;
+        let quoted = context.send_group_message(123456, MessageChain::text("first")).await.unwrap();
+
+        let chain = MessageChainBuilder::new().reply_to(quoted.id).text("second").build();
+        context.send_group_message(123456, chain).await.unwrap();
+        let sent = PbSendMsg::decode(&server.received()[1].data).unwrap();
+        let elems = sent.message_body.unwrap().rich_text.unwrap().elems;
+        let src_msg = elems[0].src_msg.clone().unwrap();
+        assert_eq!(src_msg.orig_seqs, [1000]);
+        assert_eq!(src_msg.sender_uin, Some(10000));
+        assert_eq!(src_msg.time, Some(1700000000));
+
+        context.recall_message(quoted.id).await.unwrap();
+        let request = &server.received()[2];
+        assert_eq!(request.command, "trpc.msg.msg_svc.MsgService.SsoGroupRecallMsg");
+        let recall = GroupRecallRequest::decode_from_slice(&request.data).unwrap();
+        assert_eq!((recall.group_uin, recall.info.unwrap().sequence), (123456, 1000));
+    }
+
+    #[tokio::test]
+    async fn test_recall_friend_message_by_id() {
+        let (context, server) = mock_server().await;
+        let receipt = context.send_friend_message(10001, MessageChain::text("hi")).await.unwrap();
+        assert_eq!(receipt.target, MessageTarget::Friend(10001));
+
+        context.recall_message(receipt.id).await.unwrap();
+        let request = &server.received()[1];
+        assert_eq!(request.command, "trpc.msg.msg_svc.MsgService.SsoC2CRecallMsg");
+        let recall = FriendRecallRequest::decode_from_slice(&request.data).unwrap();
+        let info = recall.info.unwrap();
+        assert_eq!(recall.target_uid, "u_10001");
+        assert_eq!((info.client_sequence, info.random), (receipt.client_sequence, receipt.random));
+    }
+
+    #[tokio::test]
+    async fn test_evicted_message_id() {
+        let (context, server) = mock_server().await;
+        let missing = MessageId(42);
+
+        assert!(matches!(context.recall_message(missing).await, Err(Error::BuildError(_))));
+        let chain = MessageChainBuilder::new().reply_to(missing).text("hi").build();
+        assert!(matches!(context.send_group_message(123456, chain).await, Err(Error::BuildError(_))));
+        assert!(server.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reply_to_group_message() {
+        let (context, server) = mock_server().await;
+        let quoted = MessageChainBuilder::new()
+            .reply(7)
+            .text("question")
+            .build();
+        let event = GroupMessageEvent {
+            group_uin: 123456,
+            group_name: "group".to_string(),
+            sender_uin: 10001,
+            sender_uid: "u_10001".to_string(),
+            sender_nickname: "member".to_string(),
+            sequence: 88,
+            random: 1,
+            timestamp: 1690000000,
+            chain: quoted,
+        };
+        assert_eq!(event.scene(), MessageScene::Group(123456));
+        assert_eq!(event.group_uin(), Some(123456));
+
+        let receipt = event.reply_text(&context, "answer").await.unwrap();
+        assert_eq!(receipt.target, MessageTarget::Group(123456));
+        let sent = PbSendMsg::decode(&server.received()[0].data).unwrap();
+        assert_eq!(sent.routing_head.unwrap().grp.unwrap().group_code, Some(123456));
+        let elems = sent.message_body.unwrap().rich_text.unwrap().elems;
+        let src_msg = elems[0].src_msg.clone().unwrap();
+        assert_eq!(src_msg.orig_seqs, [88]);
+        assert_eq!(src_msg.sender_uin, Some(10001));
+        assert_eq!(src_msg.time, Some(1690000000));
+
+        // The quote leaves out what the message itself quoted
+        assert_eq!(event.quote().source, [MessageEntity::Text { text: "question".to_string() }]);
+    }
+
+    #[tokio::test]
+    async fn test_reply_to_private_messages() {
+        let (context, server) = mock_server().await;
+        let event = FriendMessageEvent {
+            sender_uin: 10001,
+            sender_uid: "u_10001".to_string(),
+            sender_nickname: "friend".to_string(),
+            sequence: 5,
+            random: 1,
+            timestamp: 1690000000,
+            chain: MessageChain::text("hi"),
+        };
+        assert_eq!((event.scene(), event.group_uin()), (MessageScene::Friend, None));
+        let receipt = event.reply(&context, MessageChain::text("hello")).await.unwrap();
+        assert_eq!(receipt.target, MessageTarget::Friend(10001));
+        let sent = PbSendMsg::decode(&server.received()[0].data).unwrap();
+        assert!(sent.routing_head.unwrap().c2c.is_some());
+
+        let event = TempMessageEvent {
+            sender_uin: 10002,
+            sender_uid: "u_10002".to_string(),
+            sender_nickname: "stranger".to_string(),
+            sequence: 6,
+            random: 1,
+            timestamp: 1690000000,
+            chain: MessageChain::text("hi"),
+        };
+        assert_eq!(event.scene(), MessageScene::Temp);
+        assert!(matches!(event.reply_text(&context, "hello").await, Err(Error::BuildError(_))));
+        assert_eq!(server.received().len(), 1);
+    }
+}
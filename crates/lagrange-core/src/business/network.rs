@@ -1,98 +1,258 @@
-﻿use crate::{BotContext, Error, internal::services::system::{AliveEventReq, AliveService}};
+﻿use crate::{BotContext, Error, common::BotOfflineEvent, internal::services::{registry, login::offline_event, message::PushMessageEventResp, system::{AliveEventReq, AliveService}}};
+use crate::internal::context::JobHandle;
+use crate::internal::packets::SsoPacket;
+use crate::protocol::EventMessage;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::time;
+use tracing::Instrument;
 
 impl BotContext {
+    /// Name of the job started by [`BotContext::start_heartbeat`]
+    pub const HEARTBEAT_JOB: &'static str = "heartbeat";
+
+    /// Name of the job started by [`BotContext::start_connection_monitor`]
+    pub const CONNECTION_MONITOR_JOB: &'static str = "connection-monitor";
+
     pub async fn connect(self: &Arc<Self>) -> Result<bool, Error> {
-        let result = self.socket.connect(
-            self.config.use_ipv6_network,
-            self.packet.clone()
-        ).await;
+        let result = self.socket.connect(self.packet.clone(), &self.supervisor).await;
 
         if result.is_err() {
-            Err(Error::NetworkError("Failed to connect to server".to_string()))
+            Err(Error::network(std::io::ErrorKind::NotConnected, "Failed to connect to server"))
         } else {
-            self.clone().start_heartbeat();
+            self.start_heartbeat();
+            self.clone().start_push_dispatcher();
             Ok(true)
         }
     }
 
-    /// Start sending heartbeat packets at 5-second intervals
-    pub fn start_heartbeat(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
-        tokio::spawn(async move {
-            let mut interval = time::interval(Duration::from_secs(5));
-            interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+    /// Route server-initiated pushes to the service registered for their command and post
+    /// the parsed result on the event bus.
+    ///
+    /// Only the first call starts a dispatcher, later calls return `None`.
+    pub fn start_push_dispatcher(self: Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        // Shared so that a restarted dispatcher keeps receiving the pushes
+        let push_rx = Arc::new(tokio::sync::Mutex::new(self.packet.take_push_receiver()?));
+        let context = self.clone();
 
-            loop {
-                interval.tick().await;
+        Some(self.supervisor.supervise("push dispatcher", move || context.clone().dispatch_pushes(push_rx.clone())))
+    }
 
-                if !self.socket.is_connected().await {
-                    tracing::debug!("Socket not connected, skipping heartbeat");
-                    continue;
-                }
+    async fn dispatch_pushes(self: Arc<Self>, push_rx: Arc<tokio::sync::Mutex<UnboundedReceiver<SsoPacket>>>) {
+        let mut push_rx = push_rx.lock().await;
+        while let Some(packet) = push_rx.recv().await {
+            let span = tracing::info_span!(
+                "push",
+                command = %packet.command,
+                sequence = packet.sequence,
+                uin = ?self.bot_uin()
+            );
+            self.dispatch_push(packet).instrument(span).await;
+        }
+    }
 
-                // Use new type-safe send API
-                if let Err(e) = self.event.send::<AliveService>(AliveEventReq {}, self.clone()).await {
-                    tracing::warn!(error = %e, "Failed to send heartbeat");
+    async fn dispatch_push(self: &Arc<Self>, packet: SsoPacket) {
+        let Some(service) = registry().get_typed_service_by_command(&packet.command) else {
+            tracing::debug!("No service registered for push");
+            return;
+        };
+
+        let event = service
+            .parse_event(packet.data, self.clone())
+            .await
+            .map(|event| event.with_origin(packet.command, packet.sequence));
+        if let Some(offline) = event.as_ref().ok().and_then(offline_event) {
+            self.handle_offline(offline).await;
+            return;
+        }
+
+        match event {
+            // Message pushes are unwrapped so subscribers see the typed message, request and notice events
+            Ok(event) => {
+                let origin = event.origin().cloned();
+                let with_origin = |unwrapped: EventMessage| match &origin {
+                    Some(origin) => unwrapped.with_origin(origin.command.clone(), origin.sequence),
+                    None => unwrapped,
+                };
+                match event.downcast::<PushMessageEventResp>() {
+                    Ok(push) => {
+                        if let Some(message) = push.message.clone() {
+                            self.post_event(with_origin(message.into_event()));
+                        }
+                        if let Some(request) = push.request.clone() {
+                            self.post_event(with_origin(request.into_event()));
+                        }
+                        if let Some(notice) = push.notice.clone() {
+                            self.post_event(with_origin(notice.into_event()));
+                        }
+                    }
+                    Err(event) => self.post_event(event),
                 }
             }
-        })
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to parse push");
+            }
+        }
     }
 
-    pub fn start_connection_monitor(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
-        tokio::spawn(async move {
-            if !self.config.auto_reconnect {
-                tracing::info!("Auto-reconnect disabled, connection monitor not started");
-                return;
+    /// Cancel the scheduled jobs, log out if the bot is online, then close the connection
+    pub async fn shutdown(self: &Arc<Self>) {
+        self.scheduler.cancel_all();
+        if self.is_online() {
+            if let Err(e) = self.logout().await {
+                tracing::warn!(error = %e, "Failed to log out");
             }
+        }
+        self.socket.disconnect().await;
+    }
 
-            tracing::info!("Starting connection monitor with auto-reconnect enabled");
-            let mut check_interval = time::interval(Duration::from_secs(3));
-            check_interval.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+    /// Post `event`, then drop the connection the server is about to close.
+    ///
+    /// The bot is marked offline first when logging in again is pointless, so the connection
+    /// monitor reconnects without resuming the session.
+    async fn handle_offline(self: &Arc<Self>, event: BotOfflineEvent) {
+        tracing::warn!(kind = ?event.kind, title = %event.title, message = %event.message, "Bot went offline");
 
-            let mut retry_count = 0u32;
-            let max_backoff_secs = 60; // Max 60 seconds between retries
+        if !event.kind.allows_re_login() {
+            self.set_online(false);
+        }
+        self.post(event);
+        self.socket.disconnect().await;
+    }
 
-            loop {
-                check_interval.tick().await;
+    /// Send a heartbeat every 5 seconds while connected, as the `heartbeat` job
+    pub fn start_heartbeat(self: &Arc<Self>) -> JobHandle {
+        self.schedule_always(Self::HEARTBEAT_JOB, Duration::from_secs(5), |context| async move {
+            if !context.socket.is_connected().await {
+                tracing::debug!("Socket not connected, skipping heartbeat");
+                return Ok(());
+            }
+            if context.is_logged_out() {
+                return Ok(());
+            }
+            context.event.send::<AliveService>(AliveEventReq {}, context.clone()).await.map(drop)
+        })
+    }
 
-                if self.socket.is_connected().await {
-                    if retry_count > 0 {
-                        retry_count = 0;
-                        tracing::info!("Connection restored, retry count reset");
-                    }
-                    continue;
-                }
+    /// Check the connection every 3 seconds as the `connection-monitor` job, reconnecting with
+    /// exponential backoff and resuming the session if the bot was online.
+    ///
+    /// Returns `None` if `auto_reconnect` is disabled.
+    pub fn start_connection_monitor(self: &Arc<Self>) -> Option<JobHandle> {
+        if !self.config.auto_reconnect {
+            tracing::info!("Auto-reconnect disabled, connection monitor not started");
+            return None;
+        }
 
-                tracing::warn!(retry_count, "Socket disconnected, attempting to reconnect");
-                let backoff_secs = (1u64 << retry_count.min(6)).min(max_backoff_secs);
+        tracing::info!("Starting connection monitor with auto-reconnect enabled");
+        let monitor = Arc::new(ConnectionMonitor::default());
+        Some(self.schedule_always(Self::CONNECTION_MONITOR_JOB, Duration::from_secs(3), move |context| {
+            let monitor = monitor.clone();
+            async move { context.check_connection(&monitor).await }
+        }))
+    }
 
-                if retry_count > 0 {
-                    tracing::info!(backoff_secs, "Waiting before reconnection attempt");
-                    time::sleep(Duration::from_secs(backoff_secs)).await;
-                }
+    async fn check_connection(self: &Arc<Self>, monitor: &ConnectionMonitor) -> Result<(), Error> {
+        const MAX_BACKOFF_SECS: u64 = 60;
 
-                match self.socket.connect(
-                    self.config.use_ipv6_network,
-                    self.packet.clone()
-                ).await {
-                    Ok(_) => {
-                        tracing::info!("Successfully reconnected to server");
-                        self.clone().start_heartbeat();
-                        retry_count = 0;
+        if self.socket.is_connected().await {
+            if monitor.retry_count.swap(0, Ordering::SeqCst) > 0 {
+                tracing::info!("Connection restored, retry count reset");
+            }
+            // Retried on every check until it succeeds or cannot succeed
+            if monitor.resume.load(Ordering::SeqCst) && self.config.auto_re_login {
+                match self.login_by_token().await {
+                    Ok(()) => {
+                        monitor.resume.store(false, Ordering::SeqCst);
+                        tracing::info!("Session resumed after reconnecting");
                     }
-                    Err(e) => {
-                        retry_count += 1;
-                        tracing::error!(
-                            error = %e,
-                            retry_count,
-                            next_backoff_secs = (1u64 << retry_count.min(6)).min(max_backoff_secs),
-                            "Failed to reconnect"
-                        );
+                    Err(e) if e.requires_relogin() => {
+                        monitor.resume.store(false, Ordering::SeqCst);
+                        tracing::error!(error = %e, "Session cannot be resumed, log in again");
                     }
+                    Err(e) => return Err(e),
                 }
             }
-        })
+            return Ok(());
+        }
+
+        if self.is_online() {
+            monitor.resume.store(true, Ordering::SeqCst);
+            self.set_online(false);
+        }
+        let retry_count = monitor.retry_count.load(Ordering::SeqCst);
+        tracing::warn!(retry_count, "Socket disconnected, attempting to reconnect");
+
+        if retry_count > 0 {
+            let backoff_secs = (1u64 << retry_count.min(6)).min(MAX_BACKOFF_SECS);
+            tracing::info!(backoff_secs, "Waiting before reconnection attempt");
+            time::sleep(Duration::from_secs(backoff_secs)).await;
+        }
+
+        match self.socket.connect(self.packet.clone(), &self.supervisor).await {
+            Ok(_) => {
+                tracing::info!("Successfully reconnected to server");
+                self.start_heartbeat();
+                monitor.retry_count.store(0, Ordering::SeqCst);
+                Ok(())
+            }
+            Err(e) => {
+                let retry_count = monitor.retry_count.fetch_add(1, Ordering::SeqCst) + 1;
+                tracing::debug!(
+                    retry_count,
+                    next_backoff_secs = (1u64 << retry_count.min(6)).min(MAX_BACKOFF_SECS),
+                    "Failed to reconnect"
+                );
+                Err(e)
+            }
+        }
+    }
+}
+
+/// State of the connection monitor kept between its runs
+#[derive(Default)]
+struct ConnectionMonitor {
+    retry_count: AtomicU32,
+    /// Whether the bot was online when the connection dropped
+    resume: AtomicBool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::OfflineKind;
+
+    fn offline(kind: OfflineKind) -> BotOfflineEvent {
+        BotOfflineEvent {
+            kind,
+            title: "下线通知".to_string(),
+            message: "你的帐号在另一台电脑登录".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_login_suppresses_re_login() {
+        let context = BotContext::builder().build();
+        let mut events = context.event.subscribe_to::<BotOfflineEvent>();
+        context.set_online(true);
+
+        context.handle_offline(offline(OfflineKind::KickedByOther)).await;
+        assert_eq!(*events.recv().await.unwrap(), offline(OfflineKind::KickedByOther));
+        // The connection monitor only resumes sessions that were online when the connection dropped
+        assert!(!context.is_online());
+        assert!(!context.socket.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_msf_offline_keeps_re_login() {
+        let context = BotContext::builder().build();
+        let mut events = context.event.subscribe_to::<BotOfflineEvent>();
+        context.set_online(true);
+
+        context.handle_offline(offline(OfflineKind::MsfOffline)).await;
+        assert_eq!(events.recv().await.unwrap().kind, OfflineKind::MsfOffline);
+        assert!(context.is_online());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,203 @@
+use crate::common::{FriendRequestEvent, GroupJoinRequestEvent, GroupRequest, GroupRequestFilter};
+use crate::internal::services::system::{
+    FetchGroupRequestsEventReq, FetchGroupRequestsService, FriendRequestActionEventReq, FriendRequestActionService,
+    GroupRequestActionEventReq, GroupRequestActionService,
+};
+use crate::{BotContext, Error};
+use std::sync::Arc;
+
+impl BotContext {
+    /// Accept a friend request received as a [`FriendRequestEvent`].
+    pub async fn accept_friend_request(self: &Arc<Self>, event: &FriendRequestEvent) -> Result<(), Error> {
+        self.answer_friend_request(event, true).await
+    }
+
+    /// Decline a friend request received as a [`FriendRequestEvent`].
+    pub async fn decline_friend_request(self: &Arc<Self>, event: &FriendRequestEvent) -> Result<(), Error> {
+        self.answer_friend_request(event, false).await
+    }
+
+    /// Let the target of a [`GroupJoinRequestEvent`] into the group.
+    ///
+    /// `reason` is kept for symmetry with [`BotContext::reject_group_request`], the server does
+    /// not show it on approval.
+    pub async fn approve_group_request(self: &Arc<Self>, event: &GroupJoinRequestEvent, reason: impl Into<String>) -> Result<(), Error> {
+        self.answer_group_request(event, true, reason.into()).await
+    }
+
+    /// Turn down a [`GroupJoinRequestEvent`], showing `reason` to the target.
+    pub async fn reject_group_request(self: &Arc<Self>, event: &GroupJoinRequestEvent, reason: impl Into<String>) -> Result<(), Error> {
+        self.answer_group_request(event, false, reason.into()).await
+    }
+
+    /// Fetch the latest `count` join requests and invitations of the groups the bot manages,
+    /// newest first, including those already handled.
+    pub async fn fetch_group_requests(self: &Arc<Self>, count: u32) -> Result<Vec<GroupRequest>, Error> {
+        let request = FetchGroupRequestsEventReq { count };
+        let response = self.event.send::<FetchGroupRequestsService>(request, self.clone()).await?;
+        Ok(response.requests)
+    }
+
+    /// Like [`BotContext::fetch_group_requests`], keeping the requests `filter` matches.
+    ///
+    /// The server picks the latest `count` requests before they are filtered, so fewer may be
+    /// returned even if there are more matching ones.
+    pub async fn fetch_group_requests_filtered(
+        self: &Arc<Self>,
+        count: u32,
+        filter: GroupRequestFilter,
+    ) -> Result<Vec<GroupRequest>, Error> {
+        let mut requests = self.fetch_group_requests(count).await?;
+        requests.retain(|request| filter.matches(request));
+        Ok(requests)
+    }
+
+    /// Approve fetched [`GroupRequest`]s one after the other, returning the outcome of each
+    pub async fn approve_group_requests(
+        self: &Arc<Self>,
+        requests: &[GroupRequest],
+        reason: impl Into<String>,
+    ) -> Vec<Result<(), Error>> {
+        self.answer_group_requests(requests, true, reason.into()).await
+    }
+
+    /// Reject fetched [`GroupRequest`]s one after the other, showing `reason` to the requesters
+    /// and returning the outcome of each
+    pub async fn reject_group_requests(
+        self: &Arc<Self>,
+        requests: &[GroupRequest],
+        reason: impl Into<String>,
+    ) -> Vec<Result<(), Error>> {
+        self.answer_group_requests(requests, false, reason.into()).await
+    }
+
+    async fn answer_friend_request(self: &Arc<Self>, event: &FriendRequestEvent, accept: bool) -> Result<(), Error> {
+        let request = FriendRequestActionEventReq { uid: event.token.clone(), accept };
+        self.event.send::<FriendRequestActionService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    async fn answer_group_request(self: &Arc<Self>, event: &GroupJoinRequestEvent, accept: bool, reason: String) -> Result<(), Error> {
+        let request = GroupRequestActionEventReq {
+            group_uin: event.group_uin,
+            sequence: event.sequence,
+            is_invitation: event.invitor.is_some(),
+            accept,
+            reason,
+        };
+        self.event.send::<GroupRequestActionService>(request, self.clone()).await?;
+        Ok(())
+    }
+
+    async fn answer_group_requests(
+        self: &Arc<Self>,
+        requests: &[GroupRequest],
+        accept: bool,
+        reason: String,
+    ) -> Vec<Result<(), Error>> {
+        let mut results = Vec::with_capacity(requests.len());
+        for request in requests {
+            let request = GroupRequestActionEventReq {
+                group_uin: request.group_uin,
+                sequence: request.sequence,
+                is_invitation: request.is_invitation(),
+                accept,
+                reason: reason.clone(),
+            };
+            let result = self.event.send::<GroupRequestActionService>(request, self.clone()).await;
+            results.push(result.map(drop));
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{GroupRequestState, GroupRequestUser};
+    use crate::internal::packets::oidb::{
+        GroupRequestActionRequest, GroupRequestsResponse, OidbGroupRequest, OidbGroupRequestGroup,
+        OidbGroupRequestUser, OidbSvcTrpcTcpBase,
+    };
+    use crate::keystore::BotKeystore;
+    use crate::testing::{Expectation, MockServer};
+    use bytes::Bytes;
+    use lagrange_proto::ProtoMessage;
+
+    fn oidb(command: u32, sub_command: u32, body: Vec<u8>) -> Bytes {
+        let oidb = OidbSvcTrpcTcpBase { command, sub_command, body: Some(body), ..Default::default() };
+        Bytes::from(oidb.encode_to_vec().unwrap())
+    }
+
+    fn listed(sequence: u64, event_type: u32, state: u32, group_uin: u32, target: &str) -> OidbGroupRequest {
+        let user = |uid: &str| OidbGroupRequestUser { uid: uid.to_string(), name: uid.to_string() };
+        OidbGroupRequest {
+            sequence,
+            event_type,
+            state,
+            group: Some(OidbGroupRequestGroup { group_uin, group_name: String::new() }),
+            target: Some(user(target)),
+            invitor: (event_type == 22).then(|| user("u_invitor")),
+            ..Default::default()
+        }
+    }
+
+    async fn connect() -> (Arc<BotContext>, MockServer) {
+        let response = GroupRequestsResponse {
+            requests: vec![
+                listed(3, 1, 1, 123456, "u_alice"),
+                listed(2, 22, 1, 654321, "u_bob"),
+                listed(1, 1, 2, 123456, "u_carol"),
+            ],
+            latest_sequence: Some(3),
+        };
+        let (transport, server) = MockServer::start(BotKeystore::default());
+        server.expect(
+            Expectation::new("OidbSvcTrpcTcp.0x10c0_1").respond(oidb(0x10c0, 1, response.encode_to_vec().unwrap())),
+        );
+        server.expect(Expectation::new("OidbSvcTrpcTcp.0x10c8_1").respond(oidb(0x10c8, 1, Vec::new())));
+        let context = BotContext::builder().transport(transport).build();
+        context.connect().await.unwrap();
+        (context, server)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_group_requests_filtered() {
+        let (context, _server) = connect().await;
+
+        let all = context.fetch_group_requests(20).await.unwrap();
+        assert_eq!(all.iter().map(|request| request.sequence).collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(all[2].state, GroupRequestState::Approved);
+        assert_eq!(
+            all[1].invitor,
+            Some(GroupRequestUser { uid: "u_invitor".to_string(), nickname: "u_invitor".to_string() })
+        );
+
+        let pending = context.fetch_group_requests_filtered(20, GroupRequestFilter::pending()).await.unwrap();
+        assert_eq!(pending.iter().map(|request| request.sequence).collect::<Vec<_>>(), vec![3, 2]);
+        let invitations = context.fetch_group_requests_filtered(20, GroupRequestFilter::invitations()).await.unwrap();
+        assert_eq!(invitations.iter().map(|request| request.sequence).collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_approve_fetched_requests() {
+        let (context, server) = connect().await;
+
+        let pending = context.fetch_group_requests_filtered(20, GroupRequestFilter::pending()).await.unwrap();
+        let results = context.approve_group_requests(&pending, "").await;
+        assert!(results.iter().all(Result::is_ok));
+
+        let answered: Vec<_> = server
+            .received()
+            .into_iter()
+            .filter(|packet| packet.command == "OidbSvcTrpcTcp.0x10c8_1")
+            .map(|packet| {
+                let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&packet.data).unwrap();
+                let request = GroupRequestActionRequest::decode_from_slice(&oidb.body.unwrap()).unwrap();
+                let body = request.body.unwrap();
+                (request.accept, body.group_uin, body.sequence, body.event_type)
+            })
+            .collect();
+        assert_eq!(answered, vec![(1, 123456, 3, 1), (1, 654321, 2, 22)]);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::common::OnlineStatus;
+use crate::internal::services::login::{SetStatusEventReq, SetStatusService};
+use crate::{BotContext, Error};
+use std::sync::Arc;
+
+impl BotContext {
+    /// Change the status shown to friends.
+    ///
+    /// The status is kept in the keystore once the server accepted it, and set again after each
+    /// login, including the automatic ones. Fails with [`Error::StatusRejected`] if the server
+    /// refuses it.
+    pub async fn set_online_status(self: &Arc<Self>, status: OnlineStatus) -> Result<(), Error> {
+        self.send_online_status(status.clone()).await?;
+        self.keystore_mut().state.online_status = Some(status);
+        Ok(())
+    }
+
+    /// Show the emoji `face_id` along with `text` as status, see [`BotContext::set_online_status`]
+    pub async fn set_custom_status(self: &Arc<Self>, face_id: u32, text: impl Into<String>) -> Result<(), Error> {
+        self.set_online_status(OnlineStatus::Custom { face_id, text: text.into() }).await
+    }
+
+    /// Set the status chosen before the login again, if any
+    pub(crate) async fn restore_online_status(self: &Arc<Self>) -> Result<(), Error> {
+        let status = self.keystore.read().expect("RwLock poisoned").state.online_status.clone();
+        match status {
+            Some(status) => self.send_online_status(status).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn send_online_status(self: &Arc<Self>, status: OnlineStatus) -> Result<(), Error> {
+        self.event.send::<SetStatusService>(SetStatusEventReq { status }, self.clone()).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::login::{ServiceRegisterResponse, SetStatus, SetStatusResponse};
+    use crate::keystore::BotKeystore;
+    use crate::testing::{Expectation, MockServer};
+    use bytes::Bytes;
+    use lagrange_proto::ProtoMessage;
+
+    const SET_STATUS: &str = "trpc.qq_new_tech.status_svc.StatusService.SetStatus";
+
+    fn set_status_response(message: &str) -> Bytes {
+        let response = SetStatusResponse { message: Some(message.to_string()) };
+        Bytes::from(response.encode_to_vec().unwrap())
+    }
+
+    fn sent_statuses(server: &MockServer) -> Vec<(u32, u32)> {
+        server
+            .received()
+            .into_iter()
+            .filter(|packet| packet.command == SET_STATUS)
+            .map(|packet| {
+                let request = SetStatus::decode_from_slice(&packet.data).unwrap();
+                (request.status, request.ext_status)
+            })
+            .collect()
+    }
+
+    async fn connect(keystore: BotKeystore) -> (Arc<BotContext>, MockServer) {
+        let (transport, server) = MockServer::start(keystore.clone());
+        let context = BotContext::builder().keystore(keystore).transport(transport).build();
+        context.connect().await.unwrap();
+        (context, server)
+    }
+
+    #[tokio::test]
+    async fn test_rejected_status_is_not_kept() {
+        let (context, server) = connect(BotKeystore::default()).await;
+        server.expect(Expectation::new(SET_STATUS).respond(set_status_response("status not allowed")));
+
+        let result = context.set_online_status(OnlineStatus::QMe).await;
+        assert!(matches!(result, Err(Error::StatusRejected(message)) if message == "status not allowed"));
+        assert_eq!(context.keystore.read().unwrap().state.online_status, None);
+    }
+
+    #[tokio::test]
+    async fn test_status_restored_after_relogin() {
+        let keystore = BotKeystore {
+            uin: Some(123456789),
+            sigs: crate::keystore::WLoginSigs { a2: vec![0x01; 64], d2: vec![0x02; 64], ..Default::default() },
+            ..BotKeystore::default()
+        };
+        let (context, server) = connect(keystore).await;
+        server.expect(Expectation::new(SET_STATUS).respond(set_status_response(SetStatusResponse::SUCCESS)));
+        let registered = ServiceRegisterResponse {
+            message: Some(ServiceRegisterResponse::SUCCESS.to_string()),
+            timestamp: Some(1_700_000_000),
+        };
+        server.expect(
+            Expectation::new("trpc.qq_new_tech.status_svc.StatusService.Register")
+                .respond(registered.encode_to_vec().unwrap()),
+        );
+        // The rest of the session setup may fail without failing the login
+        server.expect(Expectation::new("OidbSvcTrpcTcp.0xfe1_2").reject(-1, "unavailable"));
+        server.expect(Expectation::new("HttpConn.0x6ff_501").reject(-1, "unavailable"));
+
+        context.set_custom_status(14, "hi").await.unwrap();
+        assert_eq!(
+            context.keystore.read().unwrap().state.online_status,
+            Some(OnlineStatus::Custom { face_id: 14, text: "hi".to_string() })
+        );
+
+        context.login_by_token().await.unwrap();
+        assert_eq!(sent_statuses(&server), vec![(10, 2000), (10, 2000)]);
+    }
+}
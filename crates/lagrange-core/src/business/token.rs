@@ -0,0 +1,470 @@
+use crate::{
+    common::KeystoreUpdatedEvent,
+    internal::context::JobHandle,
+    internal::services::login::{ExchangeEmpCommand, ExchangeEmpEventReq, ExchangeEmpService},
+    BotContext, Error,
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How long before the tickets expire the refresh is attempted
+const REFRESH_MARGIN: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// Refresh interval used when the keystore does not know when the tickets expire
+const FALLBACK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const MIN_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+/// How often the refresh job checks whether the tickets are due
+const CHECK_INTERVAL: Duration = MIN_BACKOFF;
+
+const TOKEN_REFRESH_JOB: &str = "token-refresh";
+
+/// What the refresh job remembers between its runs
+struct RefreshState {
+    /// When to refresh if the keystore does not know when the tickets expire
+    fallback_at: Instant,
+    /// When to retry a failed refresh
+    retry_at: Option<Instant>,
+    backoff: Duration,
+}
+
+impl RefreshState {
+    fn new() -> Self {
+        Self {
+            fallback_at: Instant::now() + FALLBACK_INTERVAL,
+            retry_at: None,
+            backoff: MIN_BACKOFF,
+        }
+    }
+
+    fn is_due(&self, expires_at: Option<i64>) -> bool {
+        match (self.retry_at, expires_at) {
+            (Some(retry_at), _) => Instant::now() >= retry_at,
+            (None, Some(_)) => next_refresh_delay(expires_at, chrono::Utc::now().timestamp()).is_zero(),
+            (None, None) => Instant::now() >= self.fallback_at,
+        }
+    }
+}
+
+impl BotContext {
+    /// Resume the session stored in the keystore, without any interaction.
+    ///
+    /// Registers online with the stored A2/D2 tickets, refreshing them via `exchange_emp` first
+    /// if the server rejects them. Connects first if needed. Fails with
+    /// [`Error::LoginRequired`] if the session cannot be resumed.
+    pub async fn login_by_token(self: &Arc<Self>) -> Result<(), Error> {
+        self.set_logged_out(false);
+        if !self.socket.is_connected().await {
+            self.connect().await?;
+        }
+
+        self.resume_session(
+            |context| async move { context.register_online().await },
+            |context| context.exchange_emp(),
+        )
+        .await?;
+        self.prepare_session().await;
+        Ok(())
+    }
+
+    /// Resume the session in the background after the server rejected the tickets of an online bot.
+    ///
+    /// Does nothing if `auto_re_login` is disabled or a resume is already running. Goes offline
+    /// if the session cannot be resumed.
+    pub(crate) fn schedule_relogin(self: &Arc<Self>) {
+        if !self.config.auto_re_login || !self.is_online() || self.relogin_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let context = self.clone();
+        tokio::spawn(async move {
+            match context.login_by_token().await {
+                Ok(()) => tracing::info!("Session resumed after the tickets were rejected"),
+                Err(e) if e.requires_relogin() => {
+                    context.set_online(false);
+                    tracing::error!(error = %e, "Session cannot be resumed, log in again");
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to resume the session"),
+            }
+            context.relogin_running.store(false, Ordering::SeqCst);
+        });
+    }
+
+    async fn resume_session<R, RF, E, EF>(self: &Arc<Self>, register: R, refresh: E) -> Result<(), Error>
+    where
+        R: Fn(Arc<Self>) -> RF,
+        RF: Future<Output = Result<(), Error>>,
+        E: FnOnce(Arc<Self>) -> EF,
+        EF: Future<Output = Result<HashMap<u16, Vec<u8>>, Error>>,
+    {
+        {
+            let keystore = self.keystore.read().expect("RwLock poisoned");
+            if keystore.uin.is_none() || keystore.sigs.a2.is_empty() || keystore.sigs.d2.is_empty() {
+                return Err(Error::LoginRequired("The keystore holds no session tickets".to_string()));
+            }
+        }
+
+        match register(self.clone()).await {
+            Ok(()) => return Ok(()),
+            Err(e @ Error::Server { .. }) => {
+                tracing::info!(error = %e, "Session tickets rejected, refreshing them");
+            }
+            Err(e) => return Err(e),
+        }
+
+        let tlvs = match refresh(self.clone()).await {
+            Ok(tlvs) => tlvs,
+            Err(e) if e.is_retryable() => return Err(e),
+            Err(e) => return Err(Error::LoginRequired(format!("Refreshing the session tickets failed: {}", e))),
+        };
+        self.apply_refreshed_sigs(&tlvs);
+
+        register(self.clone()).await.map_err(|e| match e {
+            Error::Server { .. } => Error::LoginRequired(format!("Refreshed tickets were rejected: {}", e)),
+            e => e,
+        })
+    }
+
+    /// Start refreshing the A2/D2 tickets in the background via `wtlogin.exchange_emp`.
+    ///
+    /// Should be called once the bot is logged in. Runs as the `token-refresh` job of the
+    /// [`SchedulerContext`](crate::internal::context::SchedulerContext), so it pauses while the
+    /// bot is offline and stops on [`BotContext::shutdown`]. Failed refreshes are retried with
+    /// exponential backoff and never tear down the session. Fails with
+    /// [`Error::ServiceNotFound`] if the protocol of the config has no `exchange_emp`.
+    pub fn start_token_refresh(self: &Arc<Self>) -> Result<JobHandle, Error> {
+        if !self.event.supports::<ExchangeEmpService>() {
+            return Err(Error::ServiceNotFound(format!(
+                "Protocol {} cannot refresh its session tickets",
+                self.config.protocol
+            )));
+        }
+        Ok(self.schedule_token_refresh(|ctx| async move { ctx.exchange_emp().await }))
+    }
+
+    /// Perform a single `wtlogin.exchange_emp` request and return the refreshed TLVs
+    async fn exchange_emp(self: Arc<Self>) -> Result<HashMap<u16, Vec<u8>>, Error> {
+        let request = ExchangeEmpEventReq {
+            cmd: ExchangeEmpCommand::RefreshByA1,
+        };
+        let response = self.event.send::<ExchangeEmpService>(request, self.clone()).await?;
+
+        if !response.is_success() {
+            return Err(Error::ProtocolError(format!(
+                "Exchange EMP failed with state {}",
+                response.state
+            )));
+        }
+
+        Ok(response.tlvs)
+    }
+
+    fn schedule_token_refresh<F, Fut>(self: &Arc<Self>, refresh: F) -> JobHandle
+    where
+        F: Fn(Arc<Self>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<HashMap<u16, Vec<u8>>, Error>> + Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(RefreshState::new()));
+        let refresh = Arc::new(refresh);
+        self.schedule(TOKEN_REFRESH_JOB, CHECK_INTERVAL, move |ctx| {
+            let state = state.clone();
+            let refresh = refresh.clone();
+            async move { ctx.refresh_if_due(&state, refresh.as_ref()).await }
+        })
+    }
+
+    async fn refresh_if_due<F, Fut>(self: Arc<Self>, state: &Mutex<RefreshState>, refresh: &F) -> Result<(), Error>
+    where
+        F: Fn(Arc<Self>) -> Fut,
+        Fut: Future<Output = Result<HashMap<u16, Vec<u8>>, Error>>,
+    {
+        let expires_at = self.keystore.read().expect("RwLock poisoned").sigs.expires_at;
+        if !state.lock().expect("Mutex poisoned").is_due(expires_at) {
+            return Ok(());
+        }
+
+        match refresh(self.clone()).await {
+            Ok(tlvs) => {
+                self.apply_refreshed_sigs(&tlvs);
+                *state.lock().expect("Mutex poisoned") = RefreshState::new();
+                Ok(())
+            }
+            Err(e) => {
+                let mut state = state.lock().expect("Mutex poisoned");
+                state.retry_at = Some(Instant::now() + state.backoff);
+                tracing::debug!(retry_secs = state.backoff.as_secs(), "Token refresh will be retried");
+                state.backoff = (state.backoff * 2).min(MAX_BACKOFF);
+                Err(e)
+            }
+        }
+    }
+
+    fn apply_refreshed_sigs(&self, tlvs: &HashMap<u16, Vec<u8>>) {
+        let event = {
+            let mut keystore = self.keystore_mut();
+            keystore.sigs.apply_tlvs(tlvs, chrono::Utc::now().timestamp());
+            KeystoreUpdatedEvent {
+                uin: keystore.uin,
+                expires_at: keystore.sigs.expires_at,
+            }
+        };
+
+        tracing::info!(expires_at = ?event.expires_at, "Session tickets refreshed");
+        self.post(event);
+    }
+}
+
+fn next_refresh_delay(expires_at: Option<i64>, now: i64) -> Duration {
+    match expires_at {
+        Some(expires_at) => {
+            let remaining = (expires_at - now).max(0) as u64;
+            Duration::from_secs(remaining.saturating_sub(REFRESH_MARGIN.as_secs()))
+        }
+        None => FALLBACK_INTERVAL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tokio::time;
+
+    fn refreshed_tlvs() -> HashMap<u16, Vec<u8>> {
+        let mut lifetimes = Vec::new();
+        lifetimes.extend_from_slice(&2u32.to_be_bytes());
+        for tag in [0x10Au16, 0x143] {
+            lifetimes.extend_from_slice(&tag.to_be_bytes());
+            lifetimes.extend_from_slice(&(2 * 24 * 60 * 60u32).to_be_bytes());
+            lifetimes.extend_from_slice(&0u32.to_be_bytes());
+        }
+
+        HashMap::from([
+            (0x10A, vec![0xA2; 64]),
+            (0x10D, vec![0xAA; 16]),
+            (0x143, vec![0xD2; 64]),
+            (0x305, vec![0xDD; 16]),
+            (0x138, lifetimes),
+        ])
+    }
+
+    #[test]
+    fn test_next_refresh_delay() {
+        let now = 1_700_000_000;
+        assert_eq!(next_refresh_delay(None, now), FALLBACK_INTERVAL);
+        assert_eq!(
+            next_refresh_delay(Some(now + REFRESH_MARGIN.as_secs() as i64 + 60), now),
+            Duration::from_secs(60)
+        );
+        assert_eq!(next_refresh_delay(Some(now - 60), now), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_rotates_keys_and_posts_event() {
+        let context = BotContext::builder().build();
+        context.set_online(true);
+        let mut updates = context.event.subscribe_to::<KeystoreUpdatedEvent>();
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let job = context.schedule_token_refresh(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Ok(refreshed_tlvs()) }
+        });
+
+        // No expiry known yet, so nothing happens before the fallback interval
+        time::sleep(FALLBACK_INTERVAL - Duration::from_secs(1)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let event = updates.recv().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let keystore = context.keystore.read().unwrap();
+        assert_eq!(keystore.sigs.a2, vec![0xA2; 64]);
+        assert_eq!(keystore.sigs.a2_key, vec![0xAA; 16]);
+        assert_eq!(keystore.sigs.d2, vec![0xD2; 64]);
+        assert_eq!(keystore.sigs.d2_key, vec![0xDD; 16]);
+        assert!(keystore.sigs.expires_at.is_some());
+        assert_eq!(event.expires_at, keystore.sigs.expires_at);
+        drop(keystore);
+
+        job.cancel();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_backs_off_on_failure() {
+        let context = BotContext::builder().build();
+        context.set_online(true);
+        context.keystore.write().unwrap().sigs.expires_at = Some(chrono::Utc::now().timestamp());
+        let mut updates = context.event.subscribe_to::<KeystoreUpdatedEvent>();
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let job = context.schedule_token_refresh(move |_| {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 2 {
+                    Err(Error::network(std::io::ErrorKind::TimedOut, "timed out"))
+                } else {
+                    Ok(refreshed_tlvs())
+                }
+            }
+        });
+
+        let started = time::Instant::now();
+        updates.recv().await.unwrap();
+
+        // Two failures: wait MIN_BACKOFF, then twice that before the third attempt
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(started.elapsed(), MIN_BACKOFF * 3);
+        assert!(!job.is_finished());
+        assert_eq!(job.status().last_error, None);
+
+        job.cancel();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown_stops_refresh() {
+        let context = BotContext::builder().build();
+        context.set_online(true);
+        context.keystore.write().unwrap().sigs.expires_at = Some(chrono::Utc::now().timestamp());
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = calls.clone();
+        let job = context.schedule_token_refresh(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Err(Error::network(std::io::ErrorKind::TimedOut, "timed out")) }
+        });
+        time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        context.shutdown().await;
+        time::sleep(MAX_BACKOFF * 4).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(job.is_finished());
+        assert_eq!(context.scheduler.status(TOKEN_REFRESH_JOB), None);
+
+        // Nor does the job keep the context alive
+        let weak = Arc::downgrade(&context);
+        drop(context);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_needs_exchange_emp() {
+        let context = BotContext::builder().build();
+        assert!(matches!(context.start_token_refresh(), Err(Error::ServiceNotFound(_))));
+        assert!(context.scheduler.statuses().is_empty());
+
+        let config = crate::config::BotConfig::builder().protocol(crate::Protocols::AndroidPhone).build();
+        let context = BotContext::builder().config(config).build();
+        let job = context.start_token_refresh().unwrap();
+        assert_eq!(job.name(), TOKEN_REFRESH_JOB);
+        context.shutdown().await;
+    }
+
+    fn stored_session() -> Arc<BotContext> {
+        let context = BotContext::builder().build();
+        {
+            let mut keystore = context.keystore.write().unwrap();
+            keystore.uin = Some(123456789);
+            keystore.sigs.a2 = vec![0x01; 64];
+            keystore.sigs.d2 = vec![0x02; 64];
+        }
+        context
+    }
+
+    fn rejected() -> Error {
+        Error::Server {
+            command: "trpc.qq_new_tech.status_svc.StatusService.Register".to_string(),
+            ret_code: crate::RetCode::SessionExpired,
+            message: "Token expired".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_with_stored_tickets() {
+        let context = stored_session();
+        let registered = AtomicU32::new(0);
+        context
+            .resume_session(
+                |_| {
+                    registered.fetch_add(1, Ordering::SeqCst);
+                    async { Ok(()) }
+                },
+                |_| async { panic!("valid tickets must not be refreshed") },
+            )
+            .await
+            .unwrap();
+        assert_eq!(registered.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resume_refreshes_rejected_tickets() {
+        let context = stored_session();
+        let registered = AtomicU32::new(0);
+        context
+            .resume_session(
+                |context| {
+                    let attempt = registered.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        match attempt {
+                            0 => Err(rejected()),
+                            // The second attempt goes out with the refreshed tickets
+                            _ => {
+                                assert_eq!(context.keystore.read().unwrap().sigs.a2, vec![0xA2; 64]);
+                                Ok(())
+                            }
+                        }
+                    }
+                },
+                |_| async { Ok(refreshed_tlvs()) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(registered.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_needs_full_login() {
+        let context = stored_session();
+        let result = context
+            .resume_session(
+                |_| async { Err(rejected()) },
+                |_| async { Err(Error::ProtocolError("Exchange EMP failed with state 1".to_string())) },
+            )
+            .await;
+        assert!(matches!(result, Err(Error::LoginRequired(_))));
+
+        // Refreshed tickets that are rejected as well
+        let result = stored_session()
+            .resume_session(|_| async { Err(rejected()) }, |_| async { Ok(refreshed_tlvs()) })
+            .await;
+        assert!(matches!(result, Err(Error::LoginRequired(_))));
+
+        // Nothing to resume
+        let result = BotContext::builder()
+            .build()
+            .resume_session(|_| async { Ok(()) }, |_| async { Ok(refreshed_tlvs()) })
+            .await;
+        assert!(matches!(result, Err(Error::LoginRequired(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resume_keeps_network_errors() {
+        let result = stored_session()
+            .resume_session(
+                |_| async { Err(Error::network(std::io::ErrorKind::TimedOut, "timed out")) },
+                |_| async { Ok(refreshed_tlvs()) },
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Network(_))));
+    }
+}
+
@@ -1,9 +1,27 @@
+pub mod announcement;
 pub mod app_info;
 pub mod bot_info;
 pub mod contact;
+pub mod cookies;
+pub mod event;
+pub mod group_file;
+pub mod group_request;
+pub mod login;
+pub mod online_status;
+pub mod reaction;
 pub mod sign;
+pub mod user_info;
 
+pub use announcement::*;
 pub use app_info::*;
 pub use bot_info::*;
 pub use contact::*;
+pub use cookies::{CookieJar, CookieSet, Cookies};
+pub use event::*;
+pub use group_file::{GroupFileEntry, GroupFolderEntry, GroupFsEntry};
+pub use group_request::{GroupRequest, GroupRequestFilter, GroupRequestState, GroupRequestUser};
+pub use login::{LoginState, QrCodeInfo, QrLoginState};
+pub use online_status::OnlineStatus;
+pub use reaction::EmojiId;
 pub use sign::SignProvider;
+pub use user_info::{BotUserInfo, UserId};
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// A group announcement
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Announcement {
+    /// Feed id, used to delete the announcement
+    pub id: String,
+    /// Uin of the member who published it
+    pub author: u64,
+    /// Unix timestamp (seconds)
+    pub publish_time: i64,
+    pub content: String,
+    pub image: Option<AnnouncementImage>,
+    pub pinned: bool,
+}
+
+/// Image attached to an announcement, stored on the announcement web server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnouncementImage {
+    pub id: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AnnouncementImage {
+    /// Download url of the image
+    pub fn url(&self) -> String {
+        format!("https://gdynamic.qpic.cn/gdynamic/{}/628", self.id)
+    }
+}
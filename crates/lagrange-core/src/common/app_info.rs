@@ -1,5 +1,9 @@
 use crate::protocol::Protocols;
+use crate::Error;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u32)]
@@ -39,7 +43,7 @@ pub const fn create_sig_map(flags: &[Sig]) -> MainSigMap {
     map
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct WtLoginSdkInfo {
     pub sdk_build_time: u32,
     pub sdk_version: String,
@@ -114,7 +118,11 @@ pub enum AndroidVariant {
     Watch,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Version constants of a client build.
+///
+/// Serialized with the field names below and `apk_signature_md5` as hex; every field is
+/// required, see [`AppInfo::from_json`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AppInfo {
     pub os: String,
     pub vendor_os: String,
@@ -123,6 +131,7 @@ pub struct AppInfo {
     pub pt_version: String,
     pub sso_version: u32,
     pub package_name: String,
+    #[serde(with = "hex_bytes")]
     pub apk_signature_md5: Vec<u8>,
     pub sdk_info: WtLoginSdkInfo,
     pub app_id: u32,
@@ -131,6 +140,11 @@ pub struct AppInfo {
 }
 
 impl AppInfo {
+    /// Parse an app info from JSON, failing on missing fields rather than defaulting them
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|e| Error::ParseError(format!("Invalid app info: {}", e)))
+    }
+
     pub fn windows() -> Self {
         Self {
             os: "Windows".to_string(),
@@ -220,6 +234,12 @@ pub enum BotAppInfo {
         info: AppInfo,
         variant: AndroidVariant,
     },
+    /// A client build registered with [`AppInfoRegistry::register`]
+    Custom {
+        #[serde(flatten)]
+        info: AppInfo,
+        id: u8,
+    },
 }
 
 impl Default for BotAppInfo {
@@ -229,6 +249,7 @@ impl Default for BotAppInfo {
 }
 
 impl BotAppInfo {
+    /// The built-in app info of `protocol`, or the one registered for a custom protocol
     pub fn from_protocol(protocol: Protocols) -> Self {
         match protocol {
             Protocols::Windows => Self::Windows(AppInfo::windows()),
@@ -246,10 +267,33 @@ impl BotAppInfo {
                 info: AppInfo::android(AndroidVariant::Watch),
                 variant: AndroidVariant::Watch,
             },
+            Protocols::Custom(id) => match AppInfoRegistry::get(protocol) {
+                Some(info) => Self::Custom { info, id },
+                None => {
+                    tracing::warn!(id, "Custom protocol is not registered, using Linux");
+                    Self::Linux(AppInfo::linux())
+                }
+            },
             Protocols::None => Self::Linux(AppInfo::linux()),
         }
     }
 
+    /// `info` as the app info of `protocol`, in place of the built-in one.
+    ///
+    /// Custom protocols are not looked up in the [`AppInfoRegistry`]; [`Protocols::None`]
+    /// counts as Linux like in [`BotAppInfo::from_protocol`].
+    pub fn with_info(protocol: Protocols, info: AppInfo) -> Self {
+        match protocol {
+            Protocols::Windows => Self::Windows(info),
+            Protocols::MacOs => Self::MacOs(info),
+            Protocols::Linux | Protocols::None => Self::Linux(info),
+            Protocols::AndroidPhone => Self::Android { info, variant: AndroidVariant::Phone },
+            Protocols::AndroidPad => Self::Android { info, variant: AndroidVariant::Pad },
+            Protocols::AndroidWatch => Self::Android { info, variant: AndroidVariant::Watch },
+            Protocols::Custom(id) => Self::Custom { info, id },
+        }
+    }
+
     pub fn protocol(&self) -> Protocols {
         match self {
             Self::Windows(_) => Protocols::Windows,
@@ -260,13 +304,19 @@ impl BotAppInfo {
                 AndroidVariant::Pad => Protocols::AndroidPad,
                 AndroidVariant::Watch => Protocols::AndroidWatch,
             },
+            Self::Custom { id, .. } => Protocols::Custom(*id),
         }
     }
 
+    /// Whether the client is an Android build, including custom builds registered on one
+    pub fn is_android(&self) -> bool {
+        self.protocol().is_android()
+    }
+
     pub fn inner(&self) -> &AppInfo {
         match self {
             Self::Windows(info) | Self::Linux(info) | Self::MacOs(info) => info,
-            Self::Android { info, .. } => info,
+            Self::Android { info, .. } | Self::Custom { info, .. } => info,
         }
     }
 
@@ -289,3 +339,111 @@ impl BotAppInfo {
         }
     }
 }
+
+/// App infos by protocol, to follow client updates without a new release of this crate.
+///
+/// Loaded from a JSON object with the keys `windows`, `macos`, `linux`, `android_phone`,
+/// `android_pad` and `android_watch`, each optional. Unknown keys are rejected, see
+/// `BotConfig::app_info_file` to use a table.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppInfoTable {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows: Option<AppInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macos: Option<AppInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linux: Option<AppInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub android_phone: Option<AppInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub android_pad: Option<AppInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub android_watch: Option<AppInfo>,
+}
+
+impl AppInfoTable {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|e| Error::ParseError(format!("Invalid app info table: {}", e)))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// The entry for `protocol`, if the table has one
+    pub fn get(&self, protocol: Protocols) -> Option<&AppInfo> {
+        match protocol {
+            Protocols::Windows => self.windows.as_ref(),
+            Protocols::MacOs => self.macos.as_ref(),
+            Protocols::Linux => self.linux.as_ref(),
+            Protocols::AndroidPhone => self.android_phone.as_ref(),
+            Protocols::AndroidPad => self.android_pad.as_ref(),
+            Protocols::AndroidWatch => self.android_watch.as_ref(),
+            Protocols::None | Protocols::Custom(_) => None,
+        }
+    }
+}
+
+/// Byte fields written as hex strings
+mod hex_bytes {
+    use crate::utils::common::{from_hex, to_hex};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        from_hex(&hex).ok_or_else(|| D::Error::custom(format!("invalid hex string: {}", hex)))
+    }
+}
+
+struct CustomAppInfo {
+    base: Protocols,
+    info: AppInfo,
+}
+
+static CUSTOM_APP_INFOS: OnceLock<RwLock<HashMap<u8, CustomAppInfo>>> = OnceLock::new();
+
+/// Client builds supplied by the application, for versions this crate does not ship
+pub struct AppInfoRegistry;
+
+impl AppInfoRegistry {
+    fn entries() -> &'static RwLock<HashMap<u8, CustomAppInfo>> {
+        CUSTOM_APP_INFOS.get_or_init(Default::default)
+    }
+
+    /// Make `info` available as `protocol`, which must be a [`Protocols::Custom`].
+    ///
+    /// The client is handled like `base`, a built-in protocol, wherever the kind of client
+    /// matters, e.g. for which services it uses. Registering an id again replaces the entry.
+    pub fn register(protocol: Protocols, base: Protocols, info: AppInfo) -> Result<(), Error> {
+        let Protocols::Custom(id) = protocol else {
+            return Err(Error::ProtocolError(format!("{:?} is not a custom protocol", protocol)));
+        };
+        if base.bits() == 0 {
+            return Err(Error::ProtocolError(format!("{:?} cannot be the base of a custom protocol", base)));
+        }
+
+        Self::entries().write().expect("RwLock poisoned").insert(id, CustomAppInfo { base, info });
+        Ok(())
+    }
+
+    /// The app info registered for `protocol`
+    pub fn get(protocol: Protocols) -> Option<AppInfo> {
+        let Protocols::Custom(id) = protocol else {
+            return None;
+        };
+        Self::entries().read().expect("RwLock poisoned").get(&id).map(|entry| entry.info.clone())
+    }
+
+    /// The built-in protocol `protocol` was registered on
+    pub fn base(protocol: Protocols) -> Option<Protocols> {
+        let Protocols::Custom(id) = protocol else {
+            return None;
+        };
+        Self::entries().read().expect("RwLock poisoned").get(&id).map(|entry| entry.base)
+    }
+}
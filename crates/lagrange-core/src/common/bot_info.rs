@@ -1,17 +1,23 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum BotGender {
+    #[default]
     Unset = 0,
     Male = 1,
     Female = 2,
     Unknown = 255,
 }
 
-impl Default for BotGender {
-    fn default() -> Self {
-        Self::Unset
+impl From<u32> for BotGender {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => BotGender::Unset,
+            1 => BotGender::Male,
+            2 => BotGender::Female,
+            _ => BotGender::Unknown,
+        }
     }
 }
 
@@ -1,20 +1,101 @@
 use crate::common::bot_info::BotGender;
 use serde::{Deserialize, Serialize};
 
+/// What a [`BotContact`] is to the bot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContactKind {
+    Friend,
+    Group,
+    GroupMember,
+    Stranger,
+    /// The bot's own account
+    Bot,
+}
+
+/// Anyone or any group the bot deals with, so handlers can treat them alike
 pub trait BotContact {
     fn uin(&self) -> u64;
     fn uid(&self) -> &str;
     fn nickname(&self) -> &str;
+    fn contact_kind(&self) -> ContactKind;
+
+    /// The 640px avatar, for users or for groups depending on [`BotContact::contact_kind`]
+    fn avatar_url(&self) -> String {
+        match self.contact_kind() {
+            ContactKind::Group => group_avatar_url(self.uin()),
+            _ => user_avatar_url(self.uin()),
+        }
+    }
+
+    /// The name to show: the nickname, which prefers the group card for members, or the uin
+    /// if there is none
+    fn display_name(&self) -> String {
+        match self.nickname() {
+            "" => self.uin().to_string(),
+            name => name.to_string(),
+        }
+    }
+}
+
+pub fn user_avatar_url(uin: u64) -> String {
+    AvatarSize::Original.user_url(uin)
+}
+
+pub fn group_avatar_url(group_uin: u64) -> String {
+    AvatarSize::Original.group_url(group_uin)
+}
+
+/// Edge length of the square avatars served by the avatar servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum AvatarSize {
+    /// 40px
+    Small,
+    /// 100px
+    Medium,
+    /// 140px
+    Large,
+    /// 640px, the largest size served
+    #[default]
+    Original,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl AvatarSize {
+    pub fn pixels(&self) -> u32 {
+        match self {
+            Self::Small => 40,
+            Self::Medium => 100,
+            Self::Large => 140,
+            Self::Original => 640,
+        }
+    }
+
+    pub fn user_url(&self, uin: u64) -> String {
+        format!("https://q.qlogo.cn/g?b=qq&nk={}&s={}", uin, self.pixels())
+    }
+
+    pub fn group_url(&self, group_uin: u64) -> String {
+        format!("https://p.qlogo.cn/gh/{0}/{0}/{1}/", group_uin, self.pixels())
+    }
+}
+
+/// A downloaded avatar, see [`BotContext::download_avatar`](crate::BotContext::download_avatar)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Avatar {
+    pub data: bytes::Bytes,
+    /// MIME type reported by the server, like `image/jpeg`
+    pub content_type: String,
+    /// Whether the server redirected to the placeholder shown for accounts without an avatar
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BotFriendCategory {
     pub category_id: u32,
     pub category_name: String,
     pub sort_id: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BotFriend {
     pub uin: u64,
     pub uid: String,
@@ -39,9 +120,22 @@ impl BotContact for BotFriend {
     fn nickname(&self) -> &str {
         &self.nickname
     }
+
+    fn contact_kind(&self) -> ContactKind {
+        ContactKind::Friend
+    }
+
+    /// The remark given to the friend if set, otherwise as for any contact
+    fn display_name(&self) -> String {
+        match self.remarks.as_str() {
+            "" if self.nickname.is_empty() => self.uin.to_string(),
+            "" => self.nickname.clone(),
+            remarks => remarks.to_string(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BotGroup {
     pub group_uin: u64,
     #[serde(default)]
@@ -67,32 +161,81 @@ impl BotContact for BotGroup {
     fn nickname(&self) -> &str {
         &self.group_name
     }
+
+    fn contact_kind(&self) -> ContactKind {
+        ContactKind::Group
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub enum GroupMemberPermission {
+/// How many @all messages may still be sent to a group today
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtAllRemain {
+    /// Whether the bot may mention everyone in the group at all
+    pub can_at_all: bool,
+    /// Left for the whole group
+    pub remain_for_group: u32,
+    /// Left for the bot
+    pub remain_for_self: u32,
+}
+
+/// Role of a member in a group, ordered by rank so `role >= GroupRole::Admin` checks for admins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum GroupRole {
     Member,
     Admin,
     Owner,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl GroupRole {
+    /// Whether the role can manage members, i.e. is an admin or the owner
+    pub fn is_admin(self) -> bool {
+        self >= GroupRole::Admin
+    }
+}
+
+impl From<u32> for GroupRole {
+    /// Map the OIDB permission value; unknown values are treated as a plain member
+    fn from(value: u32) -> Self {
+        match value {
+            1 => GroupRole::Owner,
+            2 => GroupRole::Admin,
+            _ => GroupRole::Member,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BotGroupMember {
     pub uin: u64,
     pub uid: String,
+    /// Account nickname; [`BotContact::nickname`] prefers the group card
     pub nickname: String,
     pub group_uin: u64,
-    pub permission: GroupMemberPermission,
-    pub group_level: u32,
-    pub member_card: Option<String>,
+    pub role: GroupRole,
+    pub level: u32,
+    /// Name set for this group, `None` if the member has not set one
+    pub card: Option<String>,
     pub special_title: Option<String>,
     pub age: u32,
     pub gender: BotGender,
     pub join_time: chrono::DateTime<chrono::Utc>,
     pub last_msg_time: chrono::DateTime<chrono::Utc>,
+    /// Until when the member is muted, in the past if they are not
     pub shut_up_timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl BotGroupMember {
+    /// Whether the member is still muted at `now`
+    pub fn is_muted(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.shut_up_timestamp > now
+    }
+
+    /// How long the member stays muted from `now`, `None` if they are not muted
+    pub fn mute_remaining(&self, now: chrono::DateTime<chrono::Utc>) -> Option<std::time::Duration> {
+        (self.shut_up_timestamp - now).to_std().ok().filter(|remaining| !remaining.is_zero())
+    }
+}
+
 impl BotContact for BotGroupMember {
     fn uin(&self) -> u64 {
         self.uin
@@ -102,18 +245,31 @@ impl BotContact for BotGroupMember {
         &self.uid
     }
 
+    /// The group card if set, otherwise the account nickname
     fn nickname(&self) -> &str {
-        &self.nickname
+        match self.card.as_deref() {
+            Some(card) if !card.is_empty() => card,
+            _ => &self.nickname,
+        }
+    }
+
+    fn contact_kind(&self) -> ContactKind {
+        ContactKind::GroupMember
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Basic profile of a user who is neither a friend nor in a group with the bot, see
+/// [`BotContext::fetch_stranger_info`](crate::BotContext::fetch_stranger_info)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BotStranger {
     pub uin: u64,
+    /// Empty if the server did not tell
     pub uid: String,
     pub nickname: String,
     pub age: u32,
     pub gender: BotGender,
+    pub level: u32,
+    pub personal_sign: String,
 }
 
 impl BotContact for BotStranger {
@@ -128,4 +284,157 @@ impl BotContact for BotStranger {
     fn nickname(&self) -> &str {
         &self.nickname
     }
+
+    fn contact_kind(&self) -> ContactKind {
+        ContactKind::Stranger
+    }
+}
+
+/// The bot's own account, see [`BotContext::identity`](crate::BotContext::identity)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BotIdentity {
+    pub uin: u64,
+    pub uid: String,
+    /// Empty until the profile was fetched with
+    /// [`BotContext::refresh_bot_info`](crate::BotContext::refresh_bot_info)
+    pub nickname: String,
+}
+
+impl BotContact for BotIdentity {
+    fn uin(&self) -> u64 {
+        self.uin
+    }
+
+    fn uid(&self) -> &str {
+        &self.uid
+    }
+
+    fn nickname(&self) -> &str {
+        &self.nickname
+    }
+
+    fn contact_kind(&self) -> ContactKind {
+        ContactKind::Bot
+    }
+}
+
+/// Where a poke is sent, see [`BotContext::send_poke`](crate::BotContext::send_poke)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PokeTarget {
+    /// Poke a friend in the private chat
+    Friend,
+    /// Poke a member in this group
+    Group(u64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    fn member(card: Option<&str>, shut_up_timestamp: i64) -> BotGroupMember {
+        BotGroupMember {
+            uin: 10001,
+            uid: "u_abc".to_string(),
+            nickname: "alice".to_string(),
+            group_uin: 123456,
+            role: GroupRole::Member,
+            level: 1,
+            card: card.map(str::to_string),
+            special_title: None,
+            age: 0,
+            gender: BotGender::Unset,
+            join_time: DateTime::default(),
+            last_msg_time: DateTime::default(),
+            shut_up_timestamp: DateTime::from_timestamp(shut_up_timestamp, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_member_nickname_prefers_card() {
+        assert_eq!(BotContact::nickname(&member(Some("alice (ops)"), 0)), "alice (ops)");
+        assert_eq!(BotContact::nickname(&member(Some(""), 0)), "alice");
+        assert_eq!(BotContact::nickname(&member(None, 0)), "alice");
+        assert_eq!(BotContact::uid(&member(None, 0)), "u_abc");
+    }
+
+    #[test]
+    fn test_member_mute() {
+        let now: DateTime<Utc> = DateTime::from_timestamp(1700000000, 0).unwrap();
+
+        let muted = member(None, 1700000060);
+        assert!(muted.is_muted(now));
+        assert_eq!(muted.mute_remaining(now), Some(std::time::Duration::from_secs(60)));
+
+        // The mute ends exactly at the timestamp
+        let expired = member(None, 1700000000);
+        assert!(!expired.is_muted(now));
+        assert_eq!(expired.mute_remaining(now), None);
+        assert!(!member(None, 0).is_muted(now));
+    }
+
+    #[test]
+    fn test_avatar_urls() {
+        let group = BotGroup {
+            group_uin: 123456,
+            group_uid: String::new(),
+            group_name: "rust".to_string(),
+            member_count: 0,
+            max_member: 0,
+            create_time: 0,
+            description: None,
+            question: None,
+            announcement: None,
+        };
+        assert_eq!(group.contact_kind(), ContactKind::Group);
+        assert_eq!(group.avatar_url(), "https://p.qlogo.cn/gh/123456/123456/640/");
+
+        let contacts: [&dyn BotContact; 2] = [
+            &member(None, 0),
+            &BotIdentity { uin: 10001, uid: "u_abc".to_string(), nickname: String::new() },
+        ];
+        for contact in contacts {
+            assert_eq!(contact.avatar_url(), "https://q.qlogo.cn/g?b=qq&nk=10001&s=640");
+        }
+    }
+
+    #[test]
+    fn test_display_name_fallbacks() {
+        assert_eq!(member(Some("alice (ops)"), 0).display_name(), "alice (ops)");
+        assert_eq!(member(None, 0).display_name(), "alice");
+        let mut unnamed = member(Some(""), 0);
+        unnamed.nickname.clear();
+        assert_eq!(unnamed.display_name(), "10001");
+
+        let mut friend = BotFriend {
+            uin: 10002,
+            uid: "u_def".to_string(),
+            nickname: "bob".to_string(),
+            age: 0,
+            gender: BotGender::Unset,
+            remarks: "Bob from work".to_string(),
+            personal_sign: String::new(),
+            qid: String::new(),
+            category: None,
+        };
+        assert_eq!(friend.display_name(), "Bob from work");
+        friend.remarks.clear();
+        assert_eq!(friend.display_name(), "bob");
+        friend.nickname.clear();
+        assert_eq!(friend.display_name(), "10002");
+
+        let bot = BotIdentity { uin: 10000, uid: "u_bot".to_string(), nickname: String::new() };
+        assert_eq!(bot.contact_kind(), ContactKind::Bot);
+        assert_eq!(bot.display_name(), "10000");
+    }
+
+    #[test]
+    fn test_role_order() {
+        assert!(GroupRole::Owner > GroupRole::Admin);
+        assert!(GroupRole::Admin > GroupRole::Member);
+        assert!(GroupRole::Owner.is_admin());
+        assert!(!GroupRole::Member.is_admin());
+        assert_eq!(GroupRole::from(2), GroupRole::Admin);
+        assert_eq!(GroupRole::from(7), GroupRole::Member);
+    }
 }
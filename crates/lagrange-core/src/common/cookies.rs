@@ -0,0 +1,216 @@
+use crate::utils::Redacted;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+/// Web credentials of one domain, see [`BotContext::fetch_cookies`](crate::BotContext::fetch_cookies).
+/// [`Debug`] shows the keys [`Redacted`].
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookies {
+    pub domain: String,
+    pub skey: String,
+    /// Domain-specific key, sent as `p_skey`
+    pub pskey: String,
+    /// Value of the `uin` and `p_uin` cookies, like `o0000123456`
+    pub uin_cookie: String,
+    /// CSRF token derived from the skey, also known as `g_tk`
+    pub bkn: u32,
+    /// Unix timestamp (seconds) after which the cookies are fetched again
+    pub expires_at: i64,
+}
+
+impl fmt::Debug for Cookies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cookies")
+            .field("domain", &self.domain)
+            .field("skey", &Redacted(&self.skey))
+            .field("pskey", &Redacted(&self.pskey))
+            .field("uin_cookie", &self.uin_cookie)
+            .field("bkn", &self.bkn)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+impl Cookies {
+    /// The cookies of `uin` for `domain` carrying the keys of `set`
+    pub fn new(domain: &str, uin: u64, set: CookieSet) -> Self {
+        Self {
+            domain: domain.to_string(),
+            bkn: Self::bkn_of(&set.skey),
+            skey: set.skey,
+            pskey: set.pskey,
+            uin_cookie: format!("o{:010}", uin),
+            expires_at: set.expires_at,
+        }
+    }
+
+    /// Value of a `Cookie` header carrying these credentials
+    pub fn header(&self) -> String {
+        format!(
+            "uin={uin}; skey={}; p_uin={uin}; p_skey={}",
+            self.skey,
+            self.pskey,
+            uin = self.uin_cookie
+        )
+    }
+
+    /// Whether the cookies are still valid at `now`
+    pub fn is_valid(&self, now: i64) -> bool {
+        now < self.expires_at
+    }
+
+    /// CSRF token the web APIs expect next to `skey`, the well-known djb2 variant
+    pub fn bkn_of(skey: &str) -> u32 {
+        let hash = skey
+            .bytes()
+            .fold(5381u32, |hash, byte| hash.wrapping_add(hash << 5).wrapping_add(byte as u32));
+        hash & 0x7FFF_FFFF
+    }
+}
+
+/// Keys the server handed out for one domain; [`Debug`] shows them [`Redacted`]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CookieSet {
+    pub skey: String,
+    /// Domain-specific key, sent as `p_skey`
+    pub pskey: String,
+    /// Unix timestamp (seconds) after which the keys are no longer accepted
+    pub expires_at: i64,
+}
+
+impl fmt::Debug for CookieSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CookieSet")
+            .field("skey", &Redacted(&self.skey))
+            .field("pskey", &Redacted(&self.pskey))
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+impl CookieSet {
+    /// Whether the keys are still valid at `now`
+    pub fn is_valid(&self, now: i64) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// The [`CookieSet`]s of the session by domain, persisted with the keystore.
+///
+/// Shared through the keystore lock, so it synchronizes its own entries and is updated with a
+/// read guard of the keystore. Serialized as a map from domain to set; keystores written with
+/// full [`Cookies`] entries load as well.
+#[derive(Default)]
+pub struct CookieJar {
+    sets: RwLock<HashMap<String, CookieSet>>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `set` for `domain`, replacing the previous one
+    pub fn set(&self, domain: &str, set: CookieSet) {
+        self.sets.write().expect("RwLock poisoned").insert(domain.to_string(), set);
+    }
+
+    /// The set of `domain` if it is still valid at `now`
+    pub fn get_valid(&self, domain: &str, now: i64) -> Option<CookieSet> {
+        let sets = self.sets.read().expect("RwLock poisoned");
+        sets.get(domain).filter(|set| set.is_valid(now)).cloned()
+    }
+
+    /// Forget the sets of all domains
+    pub fn clear(&self) {
+        self.sets.write().expect("RwLock poisoned").clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.sets.read().expect("RwLock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Clone for CookieJar {
+    fn clone(&self) -> Self {
+        Self { sets: RwLock::new(self.sets.read().expect("RwLock poisoned").clone()) }
+    }
+}
+
+impl fmt::Debug for CookieJar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.sets.read().expect("RwLock poisoned").iter()).finish()
+    }
+}
+
+impl Serialize for CookieJar {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.sets.read().expect("RwLock poisoned").serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CookieJar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self { sets: RwLock::new(HashMap::deserialize(deserializer)?) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(expires_at: i64) -> CookieSet {
+        CookieSet { skey: "@abcdEFGH".to_string(), pskey: "pskey".to_string(), expires_at }
+    }
+
+    #[test]
+    fn test_expiry() {
+        let jar = CookieJar::new();
+        jar.set("qun.qq.com", set(10_000));
+
+        assert_eq!(jar.get_valid("qun.qq.com", 9_999), Some(set(10_000)));
+        assert_eq!(jar.get_valid("qun.qq.com", 10_000), None);
+        assert_eq!(jar.get_valid("qzone.qq.com", 0), None);
+
+        // A refreshed set replaces the expired one
+        jar.set("qun.qq.com", set(20_000));
+        assert_eq!(jar.get_valid("qun.qq.com", 10_000), Some(set(20_000)));
+
+        jar.clear();
+        assert!(jar.is_empty());
+        assert_eq!(jar.get_valid("qun.qq.com", 0), None);
+    }
+
+    #[test]
+    fn test_persistence_round_trip() {
+        let jar = CookieJar::new();
+        jar.set("qun.qq.com", set(10_000));
+        jar.set("qzone.qq.com", set(20_000));
+
+        let json = serde_json::to_string(&jar).unwrap();
+        let loaded: CookieJar = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get_valid("qun.qq.com", 0), Some(set(10_000)));
+        assert_eq!(loaded.get_valid("qzone.qq.com", 0), Some(set(20_000)));
+
+        // Entries written before the jar carried the full cookies
+        let cookies = Cookies::new("qun.qq.com", 10001, set(10_000));
+        let legacy = serde_json::json!({ "qun.qq.com": cookies }).to_string();
+        let loaded: CookieJar = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(loaded.get_valid("qun.qq.com", 0), Some(set(10_000)));
+    }
+
+    #[test]
+    fn test_cookies_from_set() {
+        let cookies = Cookies::new("qun.qq.com", 123456, set(10_000));
+        assert_eq!(cookies.uin_cookie, "o0000123456");
+        assert_eq!(cookies.bkn, 2082277385);
+        assert_eq!(cookies.header(), "uin=o0000123456; skey=@abcdEFGH; p_uin=o0000123456; p_skey=pskey");
+    }
+}
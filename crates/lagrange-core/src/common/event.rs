@@ -0,0 +1,530 @@
+use crate::common::{BotContact, BotGroup, BotGroupMember, ContactKind, EmojiId};
+use crate::message::{MessageChain, MessageEntity, MessageReceipt, ReplyEntity};
+use crate::protocol::ProtocolEvent;
+use crate::{BotContext, Error};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Posted whenever the session signatures in the keystore have been rotated,
+/// so that subscribers can persist the new keystore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeystoreUpdatedEvent {
+    pub uin: Option<u64>,
+    /// Unix timestamp (seconds) at which the new tickets expire, if known
+    pub expires_at: Option<i64>,
+}
+
+impl ProtocolEvent for KeystoreUpdatedEvent {}
+
+/// The server ended the session; the connection is torn down after this is posted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotOfflineEvent {
+    pub kind: OfflineKind,
+    pub title: String,
+    pub message: String,
+}
+
+impl ProtocolEvent for BotOfflineEvent {}
+
+/// Why the session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineKind {
+    /// The account logged in on another device of the same kind
+    KickedByOther,
+    /// The server forced the account offline, e.g. after a password change or a ban
+    ServerKick,
+    /// The server dropped the connection, e.g. for maintenance
+    MsfOffline,
+    /// The bot logged out, see [`BotContext::logout`](crate::BotContext::logout)
+    Logout,
+}
+
+impl OfflineKind {
+    /// Whether logging in again right away can succeed. Re-logging in after a duplicate
+    /// login would only kick the other device in turn.
+    pub fn allows_re_login(self) -> bool {
+        matches!(self, OfflineKind::MsfOffline)
+    }
+}
+
+/// State of the connection machinery itself, as opposed to the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// An internal task kept crashing and was given up; the context no longer processes what
+    /// it receives and should be rebuilt, see
+    /// [`SupervisorContext`](crate::internal::context::SupervisorContext)
+    Fatal { task: String, reason: String },
+}
+
+impl ProtocolEvent for ConnectionEvent {}
+
+/// A message received from a friend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriendMessageEvent {
+    pub sender_uin: u64,
+    pub sender_uid: String,
+    pub sender_nickname: String,
+    pub sequence: u32,
+    pub random: u32,
+    /// Unix timestamp (seconds) at which the message was sent
+    pub timestamp: i64,
+    pub chain: MessageChain,
+}
+
+impl ProtocolEvent for FriendMessageEvent {}
+
+/// A message received in a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMessageEvent {
+    pub group_uin: u64,
+    pub group_name: String,
+    pub sender_uin: u64,
+    pub sender_uid: String,
+    /// Group card of the sender, empty when they have not set one
+    pub sender_nickname: String,
+    pub sequence: u32,
+    pub random: u32,
+    pub timestamp: i64,
+    pub chain: MessageChain,
+}
+
+impl ProtocolEvent for GroupMessageEvent {}
+
+/// A message received from a stranger through a group they share with the bot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TempMessageEvent {
+    pub sender_uin: u64,
+    pub sender_uid: String,
+    pub sender_nickname: String,
+    pub sequence: u32,
+    pub random: u32,
+    pub timestamp: i64,
+    pub chain: MessageChain,
+}
+
+impl ProtocolEvent for TempMessageEvent {}
+
+/// Who sent a message, borrowed from its event, see [`MessageEvent::sender`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageSender<'a> {
+    pub uin: u64,
+    pub uid: &'a str,
+    /// Group card or nickname as sent with the message, may be empty
+    pub nickname: &'a str,
+    pub kind: ContactKind,
+}
+
+impl BotContact for MessageSender<'_> {
+    fn uin(&self) -> u64 {
+        self.uin
+    }
+
+    fn uid(&self) -> &str {
+        self.uid
+    }
+
+    fn nickname(&self) -> &str {
+        self.nickname
+    }
+
+    fn contact_kind(&self) -> ContactKind {
+        self.kind
+    }
+}
+
+/// Chat a received message was sent in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageScene {
+    Friend,
+    Group(u64),
+    /// Private message of a stranger through a shared group
+    Temp,
+}
+
+/// Accessors shared by the received message events, and replies to them
+pub trait MessageEvent {
+    fn chain(&self) -> &MessageChain;
+
+    fn sender_uid(&self) -> &str;
+
+    fn sender(&self) -> MessageSender<'_>;
+
+    fn scene(&self) -> MessageScene;
+
+    /// Sequence of the message in its chat, which replies and recalls refer to
+    fn sequence(&self) -> u32;
+
+    /// Unix timestamp (seconds) at which the message was sent
+    fn timestamp(&self) -> i64;
+
+    /// Group the message was sent in, `None` for private messages
+    fn group_uin(&self) -> Option<u64> {
+        match self.scene() {
+            MessageScene::Group(group_uin) => Some(group_uin),
+            MessageScene::Friend | MessageScene::Temp => None,
+        }
+    }
+
+    /// Reply element quoting this message
+    fn quote(&self) -> ReplyEntity {
+        ReplyEntity {
+            sequence: self.sequence(),
+            sender_uin: self.sender().uin,
+            time: self.timestamp() as u32,
+            // Quotes of quotes are not nested
+            source: self
+                .chain()
+                .entities()
+                .iter()
+                .filter(|entity| !matches!(entity, MessageEntity::Reply(_)))
+                .cloned()
+                .collect(),
+            message_id: None,
+        }
+    }
+
+    /// Send `chain` to the chat of this message, quoting it.
+    ///
+    /// Replies to [`TempMessageEvent`]s fail, sending temporary messages is not supported.
+    fn reply(
+        &self,
+        context: &Arc<BotContext>,
+        chain: MessageChain,
+    ) -> impl Future<Output = Result<MessageReceipt, Error>> + Send {
+        let mut entities = Vec::with_capacity(chain.len() + 1);
+        entities.push(MessageEntity::Reply(self.quote()));
+        entities.extend(chain.entities().iter().cloned());
+        let (context, scene) = (context.clone(), self.scene());
+        let (uin, uid) = (self.sender().uin, self.sender_uid().to_string());
+        async move { context.send_to_scene(scene, uin, uid, MessageChain::from(entities)).await }
+    }
+
+    /// Like [`MessageEvent::reply`], with a chain of just `text`
+    fn reply_text(
+        &self,
+        context: &Arc<BotContext>,
+        text: impl Into<String>,
+    ) -> impl Future<Output = Result<MessageReceipt, Error>> + Send {
+        self.reply(context, MessageChain::text(text))
+    }
+}
+
+impl MessageEvent for FriendMessageEvent {
+    fn chain(&self) -> &MessageChain {
+        &self.chain
+    }
+
+    fn sender_uid(&self) -> &str {
+        &self.sender_uid
+    }
+
+    fn sender(&self) -> MessageSender<'_> {
+        MessageSender {
+            uin: self.sender_uin,
+            uid: &self.sender_uid,
+            nickname: &self.sender_nickname,
+            kind: ContactKind::Friend,
+        }
+    }
+
+    fn scene(&self) -> MessageScene {
+        MessageScene::Friend
+    }
+
+    fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+impl MessageEvent for GroupMessageEvent {
+    fn chain(&self) -> &MessageChain {
+        &self.chain
+    }
+
+    fn sender_uid(&self) -> &str {
+        &self.sender_uid
+    }
+
+    fn sender(&self) -> MessageSender<'_> {
+        MessageSender {
+            uin: self.sender_uin,
+            uid: &self.sender_uid,
+            nickname: &self.sender_nickname,
+            kind: ContactKind::GroupMember,
+        }
+    }
+
+    fn scene(&self) -> MessageScene {
+        MessageScene::Group(self.group_uin)
+    }
+
+    fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+impl GroupMessageEvent {
+    /// The group the message was sent in, from the cache or fetched
+    pub async fn group(&self, context: &Arc<BotContext>) -> Result<Option<Arc<BotGroup>>, Error> {
+        context.group(self.group_uin).await
+    }
+
+    /// The sender as a member of the group, from the cache or fetched
+    pub async fn member(&self, context: &Arc<BotContext>) -> Result<Option<Arc<BotGroupMember>>, Error> {
+        context.group_member(self.group_uin, self.sender_uin).await
+    }
+}
+
+impl MessageEvent for TempMessageEvent {
+    fn chain(&self) -> &MessageChain {
+        &self.chain
+    }
+
+    fn sender_uid(&self) -> &str {
+        &self.sender_uid
+    }
+
+    fn sender(&self) -> MessageSender<'_> {
+        MessageSender {
+            uin: self.sender_uin,
+            uid: &self.sender_uid,
+            nickname: &self.sender_nickname,
+            kind: ContactKind::Stranger,
+        }
+    }
+
+    fn scene(&self) -> MessageScene {
+        MessageScene::Temp
+    }
+
+    fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+/// Someone asked to add the bot as a friend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriendRequestEvent {
+    pub source_uin: u64,
+    /// Verification message entered by the requester
+    pub message: String,
+    /// Identifies the request when answering it, the uid of the requester
+    pub token: String,
+}
+
+impl ProtocolEvent for FriendRequestEvent {}
+
+/// Someone asked to join, or was invited into, a group the bot manages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupJoinRequestEvent {
+    pub group_uin: u64,
+    /// `0` when the uid of the target could not be resolved
+    pub target_uin: u64,
+    pub target_uid: String,
+    /// Member who invited the target, `None` for requests the target sent themselves
+    pub invitor: Option<u64>,
+    /// Verification message entered by the target
+    pub comment: String,
+    /// Identifies the request when answering it
+    pub sequence: u64,
+}
+
+impl ProtocolEvent for GroupJoinRequestEvent {}
+
+/// A friend or group message was recalled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageRecallEvent {
+    /// `None` for friend messages
+    pub group: Option<u64>,
+    /// Who recalled the message, `0` when the uid could not be resolved
+    pub operator: u64,
+    /// Who sent the message, `0` when the uid could not be resolved
+    pub author: u64,
+    pub sequence: u32,
+}
+
+impl ProtocolEvent for MessageRecallEvent {}
+
+/// A friend started or stopped typing in the private chat with the bot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriendTypingEvent {
+    pub uin: u64,
+    pub typing: bool,
+}
+
+impl ProtocolEvent for FriendTypingEvent {}
+
+/// A member poked another member, or the bot, in a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PokeEvent {
+    pub group_uin: u64,
+    pub sender: u64,
+    pub receiver: u64,
+    /// Verb of the poke, "戳了戳" unless the sender customised it
+    pub action: String,
+    /// Text after the receiver, empty unless the sender customised it
+    pub suffix: String,
+}
+
+impl ProtocolEvent for PokeEvent {}
+
+/// A message was added to or removed from the essence list of a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EssenceMessageEvent {
+    pub group: u64,
+    /// Admin who changed the essence list
+    pub operator: u64,
+    /// Who sent the message
+    pub sender: u64,
+    pub sequence: u32,
+    /// `false` when the message was removed from the list
+    pub is_set: bool,
+}
+
+impl ProtocolEvent for EssenceMessageEvent {}
+
+/// A member added or took back an emoji reaction to a group message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupReactionEvent {
+    pub group: u64,
+    /// Sequence of the message reacted to
+    pub sequence: u32,
+    /// Who reacted, `0` when the uid could not be resolved
+    pub operator: u64,
+    pub emoji_id: EmojiId,
+    /// `false` when the reaction was taken back
+    pub added: bool,
+    /// How many members react to the message with this emoji now
+    pub current_count: u32,
+}
+
+impl ProtocolEvent for GroupReactionEvent {}
+
+/// Which part of a friend's profile changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FriendInfoField {
+    Nickname,
+    /// The name the bot gave the friend, changed from one of its other devices
+    Remark,
+    Avatar,
+}
+
+/// A friend changed their nickname or avatar, or the bot changed their remark
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriendInfoChangedEvent {
+    pub uin: u64,
+    pub field: FriendInfoField,
+    /// The cached value before the change, `None` if the friend was not cached or for
+    /// [`FriendInfoField::Avatar`]
+    pub old: Option<String>,
+    /// The new value; the avatar URL for [`FriendInfoField::Avatar`]
+    pub new: String,
+}
+
+impl ProtocolEvent for FriendInfoChangedEvent {}
+
+/// A member or the whole group was muted or unmuted by an admin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMuteEvent {
+    pub group: u64,
+    pub operator: u64,
+    /// Muted member, `None` when the whole group is; `0` when the uid could not be resolved
+    pub target: Option<u64>,
+    /// How long the member is muted, zero when they or the group were unmuted. For the whole
+    /// group, any other value means muted until it is lifted.
+    pub duration: Duration,
+    pub whole_group: bool,
+}
+
+impl GroupMuteEvent {
+    pub fn is_unmute(&self) -> bool {
+        self.duration.is_zero()
+    }
+}
+
+impl ProtocolEvent for GroupMuteEvent {}
+
+/// How a member came to join a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupMemberIncreaseKind {
+    /// Their join request was approved
+    Joined,
+    Invited,
+}
+
+/// A member, possibly the bot, joined a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMemberIncreaseEvent {
+    pub group: u64,
+    /// `0` when the uid could not be resolved
+    pub member_uin: u64,
+    pub member_uid: String,
+    /// Who invited the member, for [`GroupMemberIncreaseKind::Invited`]; `0` when the uid
+    /// could not be resolved
+    pub invitor: Option<u64>,
+    pub kind: GroupMemberIncreaseKind,
+}
+
+impl ProtocolEvent for GroupMemberIncreaseEvent {}
+
+/// How a member came to leave a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupMemberDecreaseKind {
+    Left,
+    /// Another member was removed by an admin
+    Kicked,
+    /// The bot was removed by an admin
+    KickedSelf,
+}
+
+/// A member, possibly the bot, left a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMemberDecreaseEvent {
+    pub group: u64,
+    /// `0` when the uid could not be resolved
+    pub member_uin: u64,
+    pub member_uid: String,
+    /// Admin who removed the member, `None` if they left on their own; `0` when the uid could
+    /// not be resolved
+    pub operator: Option<u64>,
+    pub kind: GroupMemberDecreaseKind,
+}
+
+impl ProtocolEvent for GroupMemberDecreaseEvent {}
+
+/// A gray notice line in a group; pokes have their own [`PokeEvent`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrayTipEvent {
+    /// A member gained a group honor, like "龙王"
+    HonorChanged { group_uin: u64, uin: u64, honor: String },
+    /// The owner granted a member a special title
+    TitleGranted { group_uin: u64, uin: u64, title: String },
+    Unknown(UnknownGrayTip),
+}
+
+impl ProtocolEvent for GrayTipEvent {}
+
+/// Gray tip of a template without a typed event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownGrayTip {
+    pub group_uin: u64,
+    pub busi_id: u64,
+    pub template_id: u64,
+    /// Template parameters in the order they were sent
+    pub params: Vec<(String, String)>,
+    /// Rendered text, if the server sent one
+    pub content: String,
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+/// A file in the file system of a group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupFileEntry {
+    /// Id of the file, used to download, rename, move or delete it
+    pub file_id: String,
+    pub name: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Business id, 102 for permanent files
+    pub bus_id: u32,
+    /// Id of the folder the file is in, `/` for the root
+    pub folder_id: String,
+    pub uploader_uin: u64,
+    pub uploader_name: String,
+    /// Unix timestamp (seconds)
+    pub upload_time: u32,
+    /// Unix timestamp (seconds), 0 for files that do not expire
+    pub expire_time: u32,
+    /// Unix timestamp (seconds)
+    pub modified_time: u32,
+    pub download_count: u32,
+}
+
+/// A folder in the file system of a group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupFolderEntry {
+    pub folder_id: String,
+    /// Id of the folder this one is in, `/` for the root
+    pub parent_folder_id: String,
+    pub name: String,
+    pub creator_uin: u32,
+    pub creator_name: String,
+    /// Unix timestamp (seconds)
+    pub create_time: u32,
+    /// Unix timestamp (seconds)
+    pub modified_time: u32,
+    /// Number of files in the folder
+    pub file_count: u32,
+}
+
+/// An entry of a group folder listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupFsEntry {
+    File(GroupFileEntry),
+    Folder(GroupFolderEntry),
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+/// A join request or invitation listed by
+/// [`BotContext::fetch_group_requests`](crate::BotContext::fetch_group_requests), handled or not
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupRequest {
+    pub group_uin: u64,
+    pub group_name: String,
+    /// Who asked to join, or was invited
+    pub requester: GroupRequestUser,
+    /// Member who invited the requester, `None` for requests the requester sent themselves
+    pub invitor: Option<GroupRequestUser>,
+    /// Admin who handled the request
+    pub operator: Option<GroupRequestUser>,
+    /// Verification message entered by the requester
+    pub comment: String,
+    /// Identifies the request when answering it
+    pub sequence: u64,
+    pub state: GroupRequestState,
+}
+
+impl GroupRequest {
+    pub fn is_invitation(&self) -> bool {
+        self.invitor.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupRequestUser {
+    pub uid: String,
+    pub nickname: String,
+}
+
+/// Whether a [`GroupRequest`] was handled, and how
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupRequestState {
+    Pending,
+    Approved,
+    Rejected,
+    Ignored,
+    /// Any other state, by its code
+    Unknown(u32),
+}
+
+impl GroupRequestState {
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            1 => GroupRequestState::Pending,
+            2 => GroupRequestState::Approved,
+            3 => GroupRequestState::Rejected,
+            4 => GroupRequestState::Ignored,
+            code => GroupRequestState::Unknown(code),
+        }
+    }
+
+    pub fn is_pending(self) -> bool {
+        self == GroupRequestState::Pending
+    }
+}
+
+/// Which [`GroupRequest`]s [`BotContext::fetch_group_requests_filtered`](crate::BotContext::fetch_group_requests_filtered)
+/// keeps; the default keeps all of them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GroupRequestFilter {
+    /// Drop requests that were already handled
+    pub pending_only: bool,
+    /// Drop requests the requester sent themselves
+    pub invitations_only: bool,
+}
+
+impl GroupRequestFilter {
+    pub fn pending() -> Self {
+        Self { pending_only: true, ..Self::default() }
+    }
+
+    pub fn invitations() -> Self {
+        Self { invitations_only: true, ..Self::default() }
+    }
+
+    pub fn matches(&self, request: &GroupRequest) -> bool {
+        (!self.pending_only || request.state.is_pending()) && (!self.invitations_only || request.is_invitation())
+    }
+}
@@ -0,0 +1,73 @@
+use bytes::Bytes;
+use std::time::Duration;
+
+/// Outcome of a login attempt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginState {
+    /// The bot is logged in and the session keys are stored in the keystore
+    Success,
+    /// Solve the slider captcha at `url` and answer with the ticket it hands out
+    CaptchaRequired { url: String },
+    /// An SMS code was sent to `phone`, answer with the code
+    SmsRequired { phone: String, message: String },
+    /// The server flagged this device as unusual. Fetch a verification QR code
+    /// with `sig`, have it scanned by the account owner, then wait for the
+    /// verification to resume the login.
+    UnusualDeviceVerify { sig: Vec<u8>, message: String },
+    /// The device lock has to be lifted by the account owner at `url` before logging in again
+    DeviceLocked { url: String },
+    /// The login was rejected
+    Failed {
+        code: u8,
+        message: String,
+        /// Link to a page explaining or resolving the rejection, if the server sent one
+        tag146_url: Option<String>,
+    },
+}
+
+impl LoginState {
+    pub fn is_success(&self) -> bool {
+        matches!(self, LoginState::Success)
+    }
+
+    /// Whether the login waits for an answer, see [`BotContext::login_by_password`](crate::BotContext::login_by_password)
+    pub fn needs_answer(&self) -> bool {
+        matches!(self, LoginState::CaptchaRequired { .. } | LoginState::SmsRequired { .. })
+    }
+}
+
+/// A login QR code, to be scanned with the mobile client of the account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCodeInfo {
+    pub url: String,
+    /// The code rendered by the server, as PNG
+    pub image: Bytes,
+    /// Identifies the code while polling, also kept in the session state
+    pub qr_sig: Vec<u8>,
+    /// How long the code can be scanned, from when it was fetched
+    pub expires_in: Duration,
+}
+
+/// Progress of a [`BotContext::login_by_qrcode`](crate::BotContext::login_by_qrcode)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QrLoginState {
+    WaitingScan,
+    /// Scanned, waiting for the login to be confirmed on the phone
+    Scanned,
+    /// Confirmed, the login itself follows
+    Confirmed,
+    /// The code expired. Carries the replacement code if expired codes are refreshed,
+    /// otherwise the login ends here.
+    Expired(Option<QrCodeInfo>),
+    LoggedIn,
+    /// The login did not complete, with the outcome of the login after confirmation, or a
+    /// [`LoginState::Failed`] if the code was canceled or polling failed
+    Failed(LoginState),
+}
+
+impl QrLoginState {
+    /// Whether the login ends with this state
+    pub fn is_final(&self) -> bool {
+        matches!(self, QrLoginState::Expired(None) | QrLoginState::LoggedIn | QrLoginState::Failed(_))
+    }
+}
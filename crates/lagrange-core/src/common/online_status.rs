@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// Status shown to friends next to the bot, see
+/// [`BotContext::set_online_status`](crate::BotContext::set_online_status)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OnlineStatus {
+    Online,
+    Away,
+    /// Appears offline while staying logged in
+    Invisible,
+    Busy,
+    /// Invites friends to start a chat
+    QMe,
+    DoNotDisturb,
+    /// An emoji by its face id with a short text
+    Custom { face_id: u32, text: String },
+}
+
+impl OnlineStatus {
+    /// Code of the status, custom statuses are a kind of online
+    pub fn status_code(&self) -> u32 {
+        match self {
+            OnlineStatus::Online | OnlineStatus::Custom { .. } => 10,
+            OnlineStatus::Away => 30,
+            OnlineStatus::Invisible => 40,
+            OnlineStatus::Busy => 50,
+            OnlineStatus::QMe => 60,
+            OnlineStatus::DoNotDisturb => 70,
+        }
+    }
+
+    /// Extended status refining [`OnlineStatus::status_code`], 0 for none
+    pub fn ext_status_code(&self) -> u32 {
+        match self {
+            OnlineStatus::Custom { .. } => 2000,
+            _ => 0,
+        }
+    }
+}
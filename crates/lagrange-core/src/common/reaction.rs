@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Emoji reacting to a group message, see
+/// [`BotContext::set_group_reaction`](crate::BotContext::set_group_reaction).
+///
+/// Small ids are QQ faces, larger ones the code point of a Unicode emoji.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EmojiId(pub u32);
+
+impl EmojiId {
+    pub const THUMBS_UP: EmojiId = EmojiId(76);
+    pub const HEART: EmojiId = EmojiId(66);
+    pub const ROSE: EmojiId = EmojiId(63);
+    pub const APPLAUSE: EmojiId = EmojiId(99);
+    pub const OK: EmojiId = EmojiId(124);
+    /// 🔥
+    pub const FIRE: EmojiId = EmojiId(0x1F525);
+    /// 😂
+    pub const TEARS_OF_JOY: EmojiId = EmojiId(0x1F602);
+    /// 🎉
+    pub const PARTY: EmojiId = EmojiId(0x1F389);
+
+    /// Ids the server sends as codes of more than three digits are Unicode emoji
+    const FIRST_EMOJI: u32 = 1000;
+
+    /// Whether this is a Unicode emoji rather than a QQ face
+    pub fn is_emoji(self) -> bool {
+        self.0 >= Self::FIRST_EMOJI
+    }
+
+    /// Parse the code the server uses for the emoji
+    pub fn from_code(code: &str) -> Option<Self> {
+        code.parse().ok().map(EmojiId)
+    }
+
+    /// Code the server uses for the emoji
+    pub fn code(self) -> String {
+        self.0.to_string()
+    }
+}
+
+impl From<u32> for EmojiId {
+    fn from(id: u32) -> Self {
+        EmojiId(id)
+    }
+}
+
+impl std::fmt::Display for EmojiId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match char::from_u32(self.0).filter(|_| self.is_emoji()) {
+            Some(emoji) => write!(f, "{}", emoji),
+            None => write!(f, "[face {}]", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emoji_ids() {
+        assert!(!EmojiId::THUMBS_UP.is_emoji());
+        assert!(EmojiId::FIRE.is_emoji());
+        assert_eq!(EmojiId::FIRE.code(), "128293");
+        assert_eq!(EmojiId::from_code("128293"), Some(EmojiId::FIRE));
+        assert_eq!(EmojiId::from_code("smile"), None);
+        assert_eq!(EmojiId::FIRE.to_string(), "🔥");
+        assert_eq!(EmojiId::THUMBS_UP.to_string(), "[face 76]");
+    }
+}
@@ -108,14 +108,21 @@ mod default {
     #[derive(Debug)]
     pub struct DefaultSignProvider {
         client: reqwest::Client,
+        url: String,
         whitelist: HashSet<String>,
     }
 
     impl DefaultSignProvider {
         pub fn new() -> Self {
+            Self::with_url(SIGN_API_URL)
+        }
+
+        /// Sign through the server at `url` instead of the built-in one
+        pub fn with_url(url: impl Into<String>) -> Self {
             let whitelist = Self::build_whitelist();
             Self {
                 client: reqwest::Client::new(),
+                url: url.into(),
                 whitelist,
             }
         }
@@ -206,7 +213,7 @@ mod default {
             };
 
             let response = match self.client
-                .post(SIGN_API_URL)
+                .post(&self.url)
                 .json(&request)
                 .send()
                 .await
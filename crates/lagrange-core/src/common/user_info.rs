@@ -0,0 +1,52 @@
+use crate::common::bot_info::BotGender;
+use serde::{Deserialize, Serialize};
+
+/// A user addressed either by uin or by uid
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UserId {
+    Uin(u64),
+    Uid(String),
+}
+
+impl From<u64> for UserId {
+    fn from(uin: u64) -> Self {
+        UserId::Uin(uin)
+    }
+}
+
+impl From<String> for UserId {
+    fn from(uid: String) -> Self {
+        UserId::Uid(uid)
+    }
+}
+
+impl From<&str> for UserId {
+    fn from(uid: &str) -> Self {
+        UserId::Uid(uid.to_string())
+    }
+}
+
+impl std::fmt::Display for UserId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserId::Uin(uin) => write!(f, "{}", uin),
+            UserId::Uid(uid) => f.write_str(uid),
+        }
+    }
+}
+
+/// Profile card of any user
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BotUserInfo {
+    pub uin: u64,
+    pub uid: String,
+    pub nickname: String,
+    pub avatar_url: String,
+    pub personal_sign: String,
+    pub country: String,
+    pub city: String,
+    pub age: u32,
+    pub gender: BotGender,
+    pub qid: String,
+    pub level: u32,
+}
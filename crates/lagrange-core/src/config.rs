@@ -1,26 +1,30 @@
 use crate::{
     common::{sign::BoxedSignProvider, sign::NoOpSignProvider},
+    internal::{context::cache::DEFAULT_CONTACT_TTL, packets::frame::DEFAULT_MAX_FRAME_LENGTH},
+    message::store::{DEFAULT_MESSAGE_STORE_CAPACITY, DEFAULT_MESSAGE_STORE_TTL},
     protocol::Protocols,
 };
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Environment variable consulted when [`BotConfig::highway_override`] is unset, a
+/// comma-separated list of `ip:port`
+pub const HIGHWAY_OVERRIDE_ENV: &str = "LAGRANGE_HIGHWAY_OVERRIDE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum LogLevel {
     Trace,
     Debug,
+    #[default]
     Info,
     Warning,
     Error,
     Critical,
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        Self::Info
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BotConfig {
     pub protocol: Protocols,
@@ -46,16 +50,155 @@ pub struct BotConfig {
     #[serde(default = "default_highway_concurrent")]
     pub highway_concurrent: usize,
 
+    /// Upload to these servers instead of those the highway session offers, to debug against
+    /// a proxy or a mock server, see [`BotConfig::highway_servers_override`]
+    #[serde(default)]
+    pub highway_override: Option<Vec<SocketAddr>>,
+
+    /// Upload to the servers of `highway_override` only if the highway session offers them too,
+    /// and reject sessions offering no upload server. Disable to upload to a proxy or a mock
+    /// server the session does not know about
+    #[serde(default = "default_true")]
+    pub highway_verify_hosts: bool,
+
     #[serde(skip)]
     pub sign_provider: Option<BoxedSignProvider>,
 
+    /// Dump packet bodies at trace level
     #[serde(default)]
     pub verbose: bool,
 
+    /// Also dump the bodies of login and credential commands in full; they are redacted to
+    /// their length and first bytes otherwise
+    #[serde(default)]
+    pub log_sensitive: bool,
+
+    /// Inbound frames announcing a larger length reset the connection
+    #[serde(default = "default_max_frame_length")]
+    pub max_frame_length: usize,
+
+    /// JSON [`AppInfoTable`](crate::common::AppInfoTable) whose entry for `protocol` replaces
+    /// the built-in app info when the context is built
+    #[serde(default)]
+    pub app_info_file: Option<PathBuf>,
+
+    /// Seconds fetched friend, group and member lists are trusted before they are fetched again
+    #[serde(default = "default_contact_cache_ttl_secs")]
+    pub contact_cache_ttl_secs: u64,
+
+    /// Receipts of sent messages kept to recall or quote them by id
+    #[serde(default = "default_message_store_capacity")]
+    pub message_store_capacity: usize,
+
+    /// Seconds receipts of sent messages are kept
+    #[serde(default = "default_message_store_ttl_secs")]
+    pub message_store_ttl_secs: u64,
+
+    /// Dispatch [`FriendInfoChangedEvent`](crate::common::FriendInfoChangedEvent)s and patch the
+    /// cached friends when a friend changes their nickname, remark or avatar
+    #[serde(default = "default_true")]
+    pub sync_friend_info: bool,
+
+    /// Options of the SSO and highway connections
+    #[serde(default)]
+    pub socket: SocketOptions,
+
+    /// Directory downloaded avatars are kept in and revalidated from by their ETag; `None`
+    /// downloads them every time
+    #[serde(default)]
+    pub avatar_cache_dir: Option<PathBuf>,
+
+    /// Serialize and pace outgoing messages per chat, see
+    /// [`SendQueueContext`](crate::internal::context::SendQueueContext); `None` sends right away
+    #[serde(default)]
+    pub send_queue: Option<SendQueueConfig>,
+
     #[serde(default)]
     pub custom: std::collections::HashMap<String, String>,
 }
 
+/// Options applied to every TCP connection of the bot before it connects
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SocketOptions {
+    /// Disable Nagle's algorithm, so small packets such as messages and heartbeats are sent
+    /// right away instead of being batched
+    #[serde(default = "default_true")]
+    pub nodelay: bool,
+
+    /// Probe idle connections; `None` keeps the system default, which usually sends none
+    #[serde(default)]
+    pub keepalive: Option<KeepaliveConfig>,
+
+    /// `SO_SNDBUF` in bytes, `None` keeps the system default
+    #[serde(default)]
+    pub send_buffer: Option<usize>,
+
+    /// `SO_RCVBUF` in bytes, `None` keeps the system default
+    #[serde(default)]
+    pub recv_buffer: Option<usize>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self { nodelay: true, keepalive: None, send_buffer: None, recv_buffer: None }
+    }
+}
+
+/// Limits of the outgoing message queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SendQueueConfig {
+    /// Messages that may wait for one chat before sends fail with
+    /// [`Error::SendQueueFull`](crate::Error::SendQueueFull), including the one being sent
+    #[serde(default = "default_send_queue_capacity")]
+    pub capacity: usize,
+
+    /// Milliseconds between two messages, whatever chat they go to
+    #[serde(default = "default_send_interval_ms")]
+    pub interval_ms: u64,
+}
+
+impl Default for SendQueueConfig {
+    fn default() -> Self {
+        Self { capacity: default_send_queue_capacity(), interval_ms: default_send_interval_ms() }
+    }
+}
+
+impl SendQueueConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+/// TCP keepalive probing of an idle connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeepaliveConfig {
+    /// Seconds the connection has to be idle before the first probe
+    pub idle_secs: u64,
+    /// Seconds between unanswered probes, `None` keeps the system default
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+}
+
+impl KeepaliveConfig {
+    pub fn idle(&self) -> Duration {
+        Duration::from_secs(self.idle_secs)
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval_secs.map(Duration::from_secs)
+    }
+}
+
+/// Parse a comma-separated list of `ip:port`
+pub(crate) fn parse_socket_addrs(value: &str) -> Result<Vec<SocketAddr>, std::net::AddrParseError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
 fn default_true() -> bool {
     true
 }
@@ -68,6 +211,30 @@ fn default_highway_concurrent() -> usize {
     4
 }
 
+fn default_max_frame_length() -> usize {
+    DEFAULT_MAX_FRAME_LENGTH
+}
+
+fn default_contact_cache_ttl_secs() -> u64 {
+    DEFAULT_CONTACT_TTL.as_secs()
+}
+
+fn default_message_store_capacity() -> usize {
+    DEFAULT_MESSAGE_STORE_CAPACITY
+}
+
+fn default_message_store_ttl_secs() -> u64 {
+    DEFAULT_MESSAGE_STORE_TTL.as_secs()
+}
+
+fn default_send_queue_capacity() -> usize {
+    32
+}
+
+fn default_send_interval_ms() -> u64 {
+    500
+}
+
 impl Default for BotConfig {
     fn default() -> Self {
         Self {
@@ -79,8 +246,20 @@ impl Default for BotConfig {
             log_level: LogLevel::Info,
             highway_chunk_size: 1024 * 1024,
             highway_concurrent: 4,
+            highway_override: None,
+            highway_verify_hosts: true,
             sign_provider: None,
             verbose: false,
+            log_sensitive: false,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            app_info_file: None,
+            contact_cache_ttl_secs: DEFAULT_CONTACT_TTL.as_secs(),
+            message_store_capacity: DEFAULT_MESSAGE_STORE_CAPACITY,
+            message_store_ttl_secs: DEFAULT_MESSAGE_STORE_TTL.as_secs(),
+            sync_friend_info: true,
+            socket: SocketOptions::default(),
+            avatar_cache_dir: None,
+            send_queue: None,
             custom: Default::default(),
         }
     }
@@ -96,6 +275,33 @@ impl BotConfig {
             .clone()
             .unwrap_or_else(|| Arc::new(NoOpSignProvider))
     }
+
+    pub fn contact_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.contact_cache_ttl_secs)
+    }
+
+    /// Highway servers replacing those of the session: `highway_override`, or else the
+    /// addresses in [`HIGHWAY_OVERRIDE_ENV`]. `None` if neither lists a server.
+    pub fn highway_servers_override(&self) -> Option<Vec<SocketAddr>> {
+        let servers = match &self.highway_override {
+            Some(servers) => servers.clone(),
+            None => {
+                let value = std::env::var(HIGHWAY_OVERRIDE_ENV).ok()?;
+                match parse_socket_addrs(&value) {
+                    Ok(servers) => servers,
+                    Err(e) => {
+                        tracing::warn!(value, error = %e, "Ignoring invalid {}", HIGHWAY_OVERRIDE_ENV);
+                        return None;
+                    }
+                }
+            }
+        };
+        (!servers.is_empty()).then_some(servers)
+    }
+
+    pub fn message_store_ttl(&self) -> Duration {
+        Duration::from_secs(self.message_store_ttl_secs)
+    }
 }
 
 #[derive(Default)]
@@ -108,8 +314,20 @@ pub struct BotConfigBuilder {
     log_level: Option<LogLevel>,
     highway_chunk_size: Option<usize>,
     highway_concurrent: Option<usize>,
+    highway_override: Option<Vec<SocketAddr>>,
+    highway_verify_hosts: Option<bool>,
     sign_provider: Option<BoxedSignProvider>,
     verbose: Option<bool>,
+    log_sensitive: Option<bool>,
+    max_frame_length: Option<usize>,
+    app_info_file: Option<PathBuf>,
+    contact_cache_ttl: Option<Duration>,
+    message_store_capacity: Option<usize>,
+    message_store_ttl: Option<Duration>,
+    sync_friend_info: Option<bool>,
+    socket: Option<SocketOptions>,
+    avatar_cache_dir: Option<PathBuf>,
+    send_queue: Option<SendQueueConfig>,
 }
 
 impl BotConfigBuilder {
@@ -153,6 +371,16 @@ impl BotConfigBuilder {
         self
     }
 
+    pub fn highway_override(mut self, servers: Vec<SocketAddr>) -> Self {
+        self.highway_override = Some(servers);
+        self
+    }
+
+    pub fn highway_verify_hosts(mut self, enabled: bool) -> Self {
+        self.highway_verify_hosts = Some(enabled);
+        self
+    }
+
     pub fn sign_provider(mut self, provider: BoxedSignProvider) -> Self {
         self.sign_provider = Some(provider);
         self
@@ -163,6 +391,58 @@ impl BotConfigBuilder {
         self
     }
 
+    pub fn log_sensitive(mut self, enabled: bool) -> Self {
+        self.log_sensitive = Some(enabled);
+        self
+    }
+
+    pub fn max_frame_length(mut self, length: usize) -> Self {
+        self.max_frame_length = Some(length);
+        self
+    }
+
+    pub fn app_info_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.app_info_file = Some(path.into());
+        self
+    }
+
+    /// How long fetched contact lists are trusted, rounded down to whole seconds
+    pub fn contact_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.contact_cache_ttl = Some(ttl);
+        self
+    }
+
+    pub fn message_store_capacity(mut self, capacity: usize) -> Self {
+        self.message_store_capacity = Some(capacity);
+        self
+    }
+
+    /// How long receipts of sent messages are kept, rounded down to whole seconds
+    pub fn message_store_ttl(mut self, ttl: Duration) -> Self {
+        self.message_store_ttl = Some(ttl);
+        self
+    }
+
+    pub fn sync_friend_info(mut self, enabled: bool) -> Self {
+        self.sync_friend_info = Some(enabled);
+        self
+    }
+
+    pub fn socket(mut self, options: SocketOptions) -> Self {
+        self.socket = Some(options);
+        self
+    }
+
+    pub fn avatar_cache_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.avatar_cache_dir = Some(path.into());
+        self
+    }
+
+    pub fn send_queue(mut self, config: SendQueueConfig) -> Self {
+        self.send_queue = Some(config);
+        self
+    }
+
     pub fn build(self) -> BotConfig {
         BotConfig {
             protocol: self.protocol.unwrap_or(Protocols::Linux),
@@ -173,8 +453,20 @@ impl BotConfigBuilder {
             log_level: self.log_level.unwrap_or(LogLevel::Info),
             highway_chunk_size: self.highway_chunk_size.unwrap_or(1024 * 1024),
             highway_concurrent: self.highway_concurrent.unwrap_or(4),
+            highway_override: self.highway_override,
+            highway_verify_hosts: self.highway_verify_hosts.unwrap_or(true),
             sign_provider: self.sign_provider,
             verbose: self.verbose.unwrap_or(false),
+            log_sensitive: self.log_sensitive.unwrap_or(false),
+            max_frame_length: self.max_frame_length.unwrap_or(DEFAULT_MAX_FRAME_LENGTH),
+            app_info_file: self.app_info_file,
+            contact_cache_ttl_secs: self.contact_cache_ttl.unwrap_or(DEFAULT_CONTACT_TTL).as_secs(),
+            message_store_capacity: self.message_store_capacity.unwrap_or(DEFAULT_MESSAGE_STORE_CAPACITY),
+            message_store_ttl_secs: self.message_store_ttl.unwrap_or(DEFAULT_MESSAGE_STORE_TTL).as_secs(),
+            sync_friend_info: self.sync_friend_info.unwrap_or(true),
+            socket: self.socket.unwrap_or_default(),
+            avatar_cache_dir: self.avatar_cache_dir,
+            send_queue: self.send_queue,
             custom: Default::default(),
         }
     }
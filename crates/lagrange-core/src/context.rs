@@ -1,18 +1,27 @@
 use crate::{
-    common::BotAppInfo,
+    common::{AppInfoTable, BotAppInfo, BotIdentity},
     config::BotConfig,
-    internal::context::{CacheContext, EventContext, PacketContext, ServiceContext, SocketContext},
+    internal::context::{
+        CacheContext, EventContext, HandlerContext, HandlerGuard, HighwayContext, HttpClient,
+        HttpContext, JobHandle, JobPolicy, PacketContext, ReqwestHttpClient, SchedulerContext,
+        SendQueueContext, ServiceContext, SocketContext, SupervisorContext, TcpTransport, Transport,
+    },
     keystore::BotKeystore,
+    message::MessageStore,
     protocol::{EventMessage, ProtocolEvent},
 };
+use crate::Error;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub struct BotContext {
     pub config: BotConfig,
 
     pub app_info: BotAppInfo,
 
-    pub keystore: std::sync::RwLock<BotKeystore>,
+    /// See [`BotContext::keystore`]
+    pub(crate) keystore: Arc<std::sync::RwLock<BotKeystore>>,
 
     pub cache: Arc<CacheContext>,
 
@@ -24,7 +33,33 @@ pub struct BotContext {
 
     pub event: Arc<EventContext>,
 
+    pub handler: Arc<HandlerContext>,
+
+    pub highway: Arc<HighwayContext>,
+
+    pub http: Arc<HttpContext>,
+
+    pub scheduler: Arc<SchedulerContext>,
+
+    /// Restarts the internal tasks that crash, see [`SupervisorContext`]
+    pub supervisor: Arc<SupervisorContext>,
+
+    /// Receipts of the messages sent by the bot
+    pub messages: Arc<MessageStore>,
+
+    /// Orders and paces outgoing messages per chat, see [`SendQueueContext`]
+    pub send_queue: Arc<SendQueueContext>,
+
     is_online: std::sync::RwLock<bool>,
+
+    /// Set by [`BotContext::logout`] until the next login
+    logged_out: std::sync::RwLock<bool>,
+
+    /// Client-side sequence of outgoing messages
+    message_sequence: std::sync::atomic::AtomicU32,
+
+    /// Set while [`BotContext::schedule_relogin`] resumes the session
+    pub(crate) relogin_running: std::sync::atomic::AtomicBool,
 }
 
 impl BotContext {
@@ -32,6 +67,17 @@ impl BotContext {
         BotContextBuilder::default()
     }
 
+    /// The keystore of the session, e.g. to save it after a login. Drop the guard before
+    /// sending requests, the login flows update the keystore while they run.
+    pub fn keystore(&self) -> std::sync::RwLockReadGuard<'_, BotKeystore> {
+        self.keystore.read().expect("RwLock poisoned")
+    }
+
+    /// Write access for the login flows, which store the tickets handed out by the server
+    pub(crate) fn keystore_mut(&self) -> std::sync::RwLockWriteGuard<'_, BotKeystore> {
+        self.keystore.write().expect("RwLock poisoned")
+    }
+
     pub fn bot_uin(&self) -> Option<u64> {
         self.keystore.read().expect("RwLock poisoned").uin
     }
@@ -40,20 +86,137 @@ impl BotContext {
         self.keystore.read().expect("RwLock poisoned").uid.clone()
     }
 
+    /// The bot's own account as a contact, `None` before its uin and uid are known
+    pub fn identity(&self) -> Option<BotIdentity> {
+        let keystore = self.keystore.read().expect("RwLock poisoned");
+        Some(BotIdentity {
+            uin: keystore.uin?,
+            uid: keystore.uid.clone()?,
+            nickname: keystore.bot_info.as_ref().map(|info| info.name.clone()).unwrap_or_default(),
+        })
+    }
+
     pub fn is_online(&self) -> bool {
         *self.is_online.read().expect("RwLock poisoned")
     }
 
+    /// Whether the bot logged out; requests fail with [`Error::LoggedOut`] until the next login
+    pub fn is_logged_out(&self) -> bool {
+        *self.logged_out.read().expect("RwLock poisoned")
+    }
+
+    pub(crate) fn set_logged_out(&self, logged_out: bool) {
+        *self.logged_out.write().expect("RwLock poisoned") = logged_out;
+    }
+
+    pub(crate) fn next_message_sequence(&self) -> u32 {
+        self.message_sequence.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    }
+
     pub fn set_online(&self, online: bool) {
         *self.is_online.write().expect("RwLock poisoned") = online;
+        self.scheduler.set_online(online);
     }
 
+    /// Post `event` to subscribers, after updating the contact cache from it
     pub fn post_event(&self, event: EventMessage) {
+        self.cache.apply_event(&event, self.bot_uin().unwrap_or_default());
         self.event.post_event(event);
     }
 
     pub fn post<T: ProtocolEvent>(&self, event: T) {
-        self.event.post(event);
+        self.post_event(EventMessage::new(event));
+    }
+
+    /// Run `handler` for every event of type `T` until the returned guard is dropped.
+    ///
+    /// Handlers run in the order they were registered, one at a time, see [`HandlerContext`].
+    /// Must be called within a Tokio runtime.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use lagrange_core::{common::GroupMessageEvent, BotContext};
+    /// # use std::sync::Arc;
+    /// # let context: Arc<BotContext> = todo!();
+    /// let guard = context.on::<GroupMessageEvent, _, _>(|ctx, event| async move {
+    ///     tracing::info!(group = event.group_uin, online = ctx.is_online(), "Group message");
+    /// });
+    /// ```
+    pub fn on<T, F, Fut>(self: &Arc<Self>, handler: F) -> HandlerGuard
+    where
+        T: ProtocolEvent,
+        F: Fn(Arc<BotContext>, Arc<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.handler.register(self, handler)
+    }
+
+    /// Like [`BotContext::on`], for a handler that does not need to await
+    pub fn on_sync<T, F>(self: &Arc<Self>, handler: F) -> HandlerGuard
+    where
+        T: ProtocolEvent,
+        F: Fn(Arc<BotContext>, Arc<T>) + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.handler.register(self, move |context, event: Arc<T>| {
+            let handler = handler.clone();
+            async move { handler(context, event) }
+        })
+    }
+
+    /// Run `job` every `interval` while the bot is online, see [`SchedulerContext`].
+    ///
+    /// The first run is immediate, and so is the first one after the bot comes back online.
+    /// Scheduling another job under `name` replaces this one. Must be called within a Tokio
+    /// runtime.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use lagrange_core::BotContext;
+    /// # use std::{sync::Arc, time::Duration};
+    /// # let context: Arc<BotContext> = todo!();
+    /// let job = context.schedule("refresh-groups", Duration::from_secs(600), |ctx| async move {
+    ///     ctx.cache.invalidate_groups();
+    ///     ctx.groups().await.map(drop)
+    /// });
+    /// tracing::info!(runs = job.status().runs, "Group refresh scheduled");
+    /// ```
+    pub fn schedule<F, Fut>(self: &Arc<Self>, name: impl Into<String>, interval: Duration, job: F) -> JobHandle
+    where
+        F: Fn(Arc<BotContext>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.scheduler.schedule(self, name, interval, JobPolicy::WhileOnline, job)
+    }
+
+    /// Like [`BotContext::schedule`], for a job that also runs while the bot is offline
+    pub fn schedule_always<F, Fut>(self: &Arc<Self>, name: impl Into<String>, interval: Duration, job: F) -> JobHandle
+    where
+        F: Fn(Arc<BotContext>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.scheduler.schedule(self, name, interval, JobPolicy::Always, job)
+    }
+
+    /// Send `request` to the service registered for its type and wait at most `timeout` for the
+    /// response, see [`EventContext::send_and_wait`](crate::internal::context::EventContext::send_and_wait).
+    ///
+    /// # Example
+    /// ```ignore
+    /// let response = context
+    ///     .send_and_wait::<AliveEventReq, AliveEventResp>(AliveEventReq {}, Duration::from_secs(5))
+    ///     .await?;
+    /// ```
+    pub async fn send_and_wait<Req, Resp>(
+        self: &Arc<Self>,
+        request: Req,
+        timeout: Duration,
+    ) -> Result<Resp, Error>
+    where
+        Req: ProtocolEvent,
+        Resp: ProtocolEvent,
+    {
+        self.event.send_and_wait(request, timeout, self.clone()).await
     }
 
     /// Creates a tracing span with bot context (uin, uid, online status)
@@ -80,14 +243,18 @@ pub struct BotContextBuilder {
     config: Option<BotConfig>,
     app_info: Option<BotAppInfo>,
     keystore: Option<BotKeystore>,
+    http_client: Option<Arc<dyn HttpClient>>,
+    transport: Option<Arc<dyn Transport>>,
 }
 
 impl Default for BotContextBuilder {
     fn default() -> Self {
         Self {
             config: Some(BotConfig::default()),
-            app_info: Some(BotAppInfo::default()),
-            keystore: Some(BotKeystore::new()),
+            app_info: None,
+            keystore: None,
+            http_client: None,
+            transport: None,
         }
     }
 }
@@ -102,47 +269,110 @@ impl BotContextBuilder {
         self
     }
 
+    /// Use `app_info` as is, ignoring `BotConfig::app_info_file`
     pub fn app_info(mut self, app_info: BotAppInfo) -> Self {
         self.app_info = Some(app_info);
         self
     }
 
+    /// Resume from `keystore`, e.g. one saved after an earlier login; a new keystore with a
+    /// generated device for the protocol of the config otherwise
     pub fn keystore(mut self, keystore: BotKeystore) -> Self {
         self.keystore = Some(keystore);
         self
     }
 
+    /// Replace the HTTP client used for media downloads and web APIs
+    pub fn http_client(mut self, client: Arc<dyn HttpClient>) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// Replace the connection to the SSO server, e.g. with an in-memory one in tests; TCP to
+    /// the server picked by the config otherwise
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Build the context, for tests and configs that need no validation.
+    ///
+    /// Without a keystore or an app info file in the config nothing can fail; otherwise prefer
+    /// [`BotContextBuilder::try_build`].
+    ///
+    /// # Panics
+    ///
+    /// If [`BotContextBuilder::try_build`] fails, i.e. the keystore does not fit the protocol of
+    /// the config or the app info file of the config cannot be loaded.
     pub fn build(self) -> Arc<BotContext> {
+        self.try_build().expect("Failed to build the bot context")
+    }
+
+    /// Build the context, failing if the app info file of the config cannot be loaded or the
+    /// keystore does not fit the protocol of the config
+    pub fn try_build(self) -> Result<Arc<BotContext>, Error> {
         let config = self.config.expect("Config is required");
-        let app_info = self.app_info.expect("AppInfo is required");
-        let keystore = self.keystore.expect("Keystore is required");
+        let keystore = self.keystore.unwrap_or_else(|| BotKeystore::for_protocol(config.protocol));
+        keystore.validate(config.protocol)?;
+        let app_info = match (self.app_info, &config.app_info_file) {
+            (Some(app_info), _) => app_info,
+            (None, Some(path)) => {
+                let table = AppInfoTable::load(path)?;
+                match table.get(config.protocol) {
+                    Some(info) => BotAppInfo::with_info(config.protocol, info.clone()),
+                    None => {
+                        tracing::warn!(path = %path.display(), protocol = ?config.protocol, "No app info for the protocol in the app info file");
+                        BotAppInfo::from_protocol(config.protocol)
+                    }
+                }
+            }
+            (None, None) => BotAppInfo::from_protocol(config.protocol),
+        };
 
-        let cache = CacheContext::new();
-        let socket = SocketContext::new();
+        let cache = CacheContext::new(config.contact_cache_ttl());
+        let messages = Arc::new(MessageStore::new(config.message_store_capacity, config.message_store_ttl()));
+        let send_queue = SendQueueContext::new(config.send_queue);
+        let transport = self.transport.unwrap_or_else(|| Arc::new(TcpTransport::new(&config)));
+        let socket = SocketContext::new(transport);
 
-        let keystore_arc = Arc::new(std::sync::RwLock::new(keystore.clone()));
+        // Shared with PacketContext so refreshed sigs are used for outgoing packets
+        let keystore_arc = Arc::new(std::sync::RwLock::new(keystore));
         let app_info_arc = Arc::new(app_info.clone());
 
         // PacketContext needs keystore, app_info, and config
-        let packet = PacketContext::new(keystore_arc, app_info_arc, &config);
+        let packet = PacketContext::new(keystore_arc.clone(), app_info_arc, &config);
 
         let service = ServiceContext::new(&config);
 
         // EventContext needs packet, socket, and config
         let config_arc = Arc::new(config.clone());
         let event = EventContext::new(packet.clone(), socket.clone(), config_arc);
+        let supervisor = SupervisorContext::new(event.clone());
 
-        Arc::new(BotContext {
+        Ok(Arc::new(BotContext {
             config,
             app_info,
-            keystore: std::sync::RwLock::new(keystore),
+            keystore: keystore_arc,
             cache,
             packet,
             service,
             socket,
             event,
+            handler: HandlerContext::new(),
+            highway: HighwayContext::new(),
+            http: HttpContext::new(
+                self.http_client
+                    .unwrap_or_else(|| Arc::new(ReqwestHttpClient::default())),
+            ),
+            scheduler: SchedulerContext::new(),
+            supervisor,
+            messages,
+            send_queue,
             is_online: std::sync::RwLock::new(false),
-        })
+            logged_out: std::sync::RwLock::new(false),
+            message_sequence: std::sync::atomic::AtomicU32::new(rand::random::<u16>() as u32),
+            relogin_running: std::sync::atomic::AtomicBool::new(false),
+        }))
     }
 }
 
@@ -11,23 +11,376 @@ pub enum Error {
     #[error("Protocol error: {0}")]
     ProtocolError(String),
 
+    /// The connection failed or was lost
     #[error("Network error: {0}")]
-    NetworkError(String),
+    Network(#[source] std::io::Error),
 
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    #[error("Decode error: {0}")]
+    Decode(#[from] lagrange_proto::DecodeError),
+
     #[error("Build error: {0}")]
     BuildError(String),
 
+    /// Local IO, like reading a media file; connection failures are [`Error::Network`]
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
     #[error("Packet error: {0}")]
     Packet(#[from] crate::utils::binary::PacketError),
 
+    #[error("SSO error: {0}")]
+    Sso(#[from] crate::internal::SsoError),
+
+    /// The server rejected the request at the SSO layer, e.g. for expired tickets
+    #[error("SSO request {command} failed ({ret_code}): {message}")]
+    Server { command: String, ret_code: RetCode, message: String },
+
+    /// No response arrived within the time the caller was willing to wait
+    #[error("SSO request {command} timed out after {after:?}")]
+    Timeout { command: String, after: std::time::Duration },
+
+    /// The service registered for a request answers with another response type than expected
+    #[error("SSO request {command} does not respond with {expected}")]
+    WrongResponseType { command: String, expected: &'static str },
+
+    /// The bot logged out, log in again before sending requests
+    #[error("Logged out")]
+    LoggedOut,
+
+    /// The session cannot be resumed, a QR code or password login is needed
+    #[error("Full login required: {0}")]
+    LoginRequired(String),
+
+    /// The keystore given to the builder does not fit the protocol of the config
+    #[error("Invalid keystore: {0}")]
+    InvalidKeystore(String),
+
+    /// The server refused the online status, with its reason
+    #[error("Online status rejected: {0}")]
+    StatusRejected(String),
+
+    #[error("User not found: {0}")]
+    UserNotFound(String),
+
+    #[error("{0}")]
+    Oidb(#[from] OidbError),
+
+    #[error("Group administration error: {0}")]
+    GroupAdmin(#[from] GroupAdminError),
+
+    #[error("Essence message error: {0}")]
+    Essence(#[from] EssenceError),
+
+    #[error("Group file error: {0}")]
+    GroupFile(#[from] GroupFileError),
+
+    #[error("Send message error: {0}")]
+    SendMessage(#[from] crate::message::SendMessageError),
+
+    /// The chat already has as many messages waiting as the send queue holds
+    #[error("Send queue of {target:?} is full ({capacity} messages)")]
+    SendQueueFull { target: crate::message::MessageTarget, capacity: usize },
+
     #[error("Other error: {0}")]
     Other(#[from] anyhow::Error),
 }
 
+impl Error {
+    /// A connection failure with `message`, for failures without an underlying IO error
+    pub fn network(kind: std::io::ErrorKind, message: impl Into<String>) -> Self {
+        Error::Network(std::io::Error::new(kind, message.into()))
+    }
+
+    /// Whether sending the same request again, possibly after reconnecting, can succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Network(_) | Error::Timeout { .. } => true,
+            Error::SendMessage(e) => matches!(e, crate::message::SendMessageError::SlowMode { .. }),
+            _ => false,
+        }
+    }
+
+    /// Whether the session is gone and requests can only succeed after logging in again
+    pub fn requires_relogin(&self) -> bool {
+        match self {
+            Error::LoggedOut | Error::LoginRequired(_) => true,
+            Error::Server { ret_code, .. } => ret_code.is_token_expired(),
+            _ => false,
+        }
+    }
+}
+
+/// Non-zero ret code of an SSO response, see [`Error::Server`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetCode {
+    /// -10001, the session tickets are no longer accepted
+    SessionExpired,
+
+    /// -10003, the A2/D2 tickets expired and must be refreshed
+    TokenExpired,
+
+    /// -10008, the session was invalidated, e.g. by a login elsewhere
+    SessionInvalidated,
+
+    /// 120, the account is blocked from logging in
+    Blocked,
+
+    /// 210, the request was stopped by risk control
+    RiskControl,
+
+    /// Any other code, the server message usually explains it
+    Unknown(i32),
+}
+
+impl RetCode {
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            -10001 => RetCode::SessionExpired,
+            -10003 => RetCode::TokenExpired,
+            -10008 => RetCode::SessionInvalidated,
+            120 => RetCode::Blocked,
+            210 => RetCode::RiskControl,
+            code => RetCode::Unknown(code),
+        }
+    }
+
+    /// The raw code sent by the server
+    pub fn code(self) -> i32 {
+        match self {
+            RetCode::SessionExpired => -10001,
+            RetCode::TokenExpired => -10003,
+            RetCode::SessionInvalidated => -10008,
+            RetCode::Blocked => 120,
+            RetCode::RiskControl => 210,
+            RetCode::Unknown(code) => code,
+        }
+    }
+
+    /// Whether the session tickets were rejected, logging in with refreshed tickets may help
+    pub fn is_token_expired(self) -> bool {
+        matches!(self, RetCode::SessionExpired | RetCode::TokenExpired | RetCode::SessionInvalidated)
+    }
+}
+
+impl std::fmt::Display for RetCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            RetCode::SessionExpired => "session expired",
+            RetCode::TokenExpired => "token expired",
+            RetCode::SessionInvalidated => "session invalidated",
+            RetCode::Blocked => "account blocked",
+            RetCode::RiskControl => "risk control",
+            RetCode::Unknown(code) => return write!(f, "{}", code),
+        };
+        write!(f, "{} {}", self.code(), description)
+    }
+}
+
+/// Failure of an `OidbSvcTrpcTcp` request, see [`parse_oidb`](crate::internal::packets::oidb::parse_oidb)
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OidbError {
+    #[error("OIDB 0x{command:x}_{sub_command} failed ({code}): {message}")]
+    Failed { command: u32, sub_command: u32, code: u32, message: String },
+
+    #[error("Malformed OIDB response: {0}")]
+    Malformed(String),
+}
+
+impl OidbError {
+    /// Result code and message reported by the server, `None` for malformed responses
+    pub fn result(&self) -> Option<(u32, &str)> {
+        match self {
+            OidbError::Failed { code, message, .. } => Some((*code, message)),
+            OidbError::Malformed(_) => None,
+        }
+    }
+}
+
+/// Non-zero results of the group administration requests
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GroupAdminError {
+    #[error("Permission denied ({code}): {message}")]
+    PermissionDenied { code: u32, message: String },
+
+    #[error("Not a member of the group ({code}): {message}")]
+    NotInGroup { code: u32, message: String },
+
+    #[error("Operation failed ({code}): {message}")]
+    Failed { code: u32, message: String },
+}
+
+impl GroupAdminError {
+    /// Result code when the bot's role is too low for the operation
+    pub const PERMISSION_DENIED_CODE: u32 = 10003;
+
+    /// Result code when the bot or the target is not in the group
+    pub const NOT_IN_GROUP_CODE: u32 = 10004;
+
+    /// Maps the result of an operation; `None` for success
+    pub fn from_result(code: u32, message: &str) -> Option<Self> {
+        let message = message.to_string();
+        match code {
+            0 => None,
+            Self::PERMISSION_DENIED_CODE => Some(GroupAdminError::PermissionDenied { code, message }),
+            Self::NOT_IN_GROUP_CODE => Some(GroupAdminError::NotInGroup { code, message }),
+            code => Some(GroupAdminError::Failed { code, message }),
+        }
+    }
+}
+
+/// Non-zero results of the essence message requests
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EssenceError {
+    #[error("Only admins can change essence messages ({code}): {message}")]
+    NotAdmin { code: u32, message: String },
+
+    #[error("Message is already an essence message ({code}): {message}")]
+    AlreadyEssence { code: u32, message: String },
+
+    #[error("Operation failed ({code}): {message}")]
+    Failed { code: u32, message: String },
+}
+
+impl EssenceError {
+    /// Result code when the bot is neither the owner nor an admin
+    pub const NOT_ADMIN_CODE: u32 = 11001;
+
+    /// Result code when the message already is on the essence list
+    pub const ALREADY_ESSENCE_CODE: u32 = 11007;
+
+    /// Maps the result of an operation; `None` for success
+    pub fn from_result(code: u32, message: &str) -> Option<Self> {
+        let message = message.to_string();
+        match code {
+            0 => None,
+            Self::NOT_ADMIN_CODE => Some(EssenceError::NotAdmin { code, message }),
+            Self::ALREADY_ESSENCE_CODE => Some(EssenceError::AlreadyEssence { code, message }),
+            code => Some(EssenceError::Failed { code, message }),
+        }
+    }
+}
+
+/// Non-zero results of the group file requests
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GroupFileError {
+    #[error("Group file space exhausted ({code}): {message}")]
+    QuotaExceeded { code: i32, message: String },
+
+    #[error("Permission denied ({code}): {message}")]
+    PermissionDenied { code: i32, message: String },
+
+    #[error("File name already taken ({code}): {message}")]
+    NameConflict { code: i32, message: String },
+
+    #[error("Operation failed ({code}): {message}")]
+    Failed { code: i32, message: String },
+}
+
+impl GroupFileError {
+    /// Result code when the group is not allowed to upload, or members may not
+    pub const PERMISSION_DENIED_CODE: i32 = -36;
+
+    /// Result code when the file would exceed the storage space of the group
+    pub const QUOTA_EXCEEDED_CODE: i32 = -134;
+
+    /// Result code when the folder already holds a file of that name
+    pub const NAME_CONFLICT_CODE: i32 = -304;
+
+    /// Maps the result of an operation; `None` for success
+    pub fn from_result(code: i32, message: &str) -> Option<Self> {
+        let message = message.to_string();
+        match code {
+            0 => None,
+            Self::PERMISSION_DENIED_CODE => Some(GroupFileError::PermissionDenied { code, message }),
+            Self::QUOTA_EXCEEDED_CODE => Some(GroupFileError::QuotaExceeded { code, message }),
+            Self::NAME_CONFLICT_CODE => Some(GroupFileError::NameConflict { code, message }),
+            code => Some(GroupFileError::Failed { code, message }),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::SendMessageError;
+    use lagrange_proto::ProtoMessage;
+    use std::error::Error as _;
+    use std::time::Duration;
+
+    fn server(ret_code: i32) -> Error {
+        Error::Server {
+            command: "trpc.qq_new_tech.status_svc.StatusService.Register".to_string(),
+            ret_code: RetCode::from_code(ret_code),
+            message: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_classification() {
+        for ret_code in [-10001, -10003, -10008] {
+            assert!(server(ret_code).requires_relogin(), "{ret_code}");
+            assert!(!server(ret_code).is_retryable(), "{ret_code}");
+        }
+        assert!(!server(-1).requires_relogin());
+        assert!(!server(-1).is_retryable());
+
+        let timeout = Error::Timeout { command: "Heartbeat.Alive".to_string(), after: Duration::from_secs(5) };
+        assert!(timeout.is_retryable());
+        assert!(Error::network(std::io::ErrorKind::ConnectionReset, "reset").is_retryable());
+        assert!(Error::LoggedOut.requires_relogin());
+        assert!(Error::LoginRequired(String::new()).requires_relogin());
+
+        let slow = SendMessageError::SlowMode { code: SendMessageError::SLOW_MODE_CODE, message: String::new() };
+        let risk = SendMessageError::RiskControl { code: 46, message: String::new() };
+        assert!(Error::from(slow).is_retryable());
+        assert!(!Error::from(risk).is_retryable());
+
+        // Local IO failures are not fixed by trying again
+        assert!(!Error::from(std::io::Error::from(std::io::ErrorKind::NotFound)).is_retryable());
+    }
+
+    #[test]
+    fn test_ret_codes() {
+        let table = [
+            (-10001, RetCode::SessionExpired, true),
+            (-10003, RetCode::TokenExpired, true),
+            (-10008, RetCode::SessionInvalidated, true),
+            (120, RetCode::Blocked, false),
+            (210, RetCode::RiskControl, false),
+            (-1, RetCode::Unknown(-1), false),
+        ];
+        for (code, expected, expired) in table {
+            let ret_code = RetCode::from_code(code);
+            assert_eq!(ret_code, expected, "{code}");
+            assert_eq!(ret_code.code(), code);
+            assert_eq!(ret_code.is_token_expired(), expired, "{code}");
+            assert_eq!(server(code).requires_relogin(), expired, "{code}");
+        }
+
+        assert_eq!(RetCode::TokenExpired.to_string(), "-10003 token expired");
+        assert_eq!(RetCode::Unknown(-1).to_string(), "-1");
+    }
+
+    #[test]
+    fn test_source_chain() {
+        let reset = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "peer reset");
+        let error = Error::Network(reset);
+        let source = error.source().unwrap().downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(source.kind(), std::io::ErrorKind::ConnectionReset);
+
+        // Truncated varint
+        let decode = crate::internal::packets::oidb::OidbSvcTrpcTcpBase::decode_from_slice(&[0x08, 0x80]).unwrap_err();
+        let error = Error::from(decode);
+        assert!(matches!(error, Error::Decode(_)));
+        assert!(error.source().unwrap().is::<lagrange_proto::DecodeError>());
+
+        let oidb = OidbError::Failed { command: 0xfe1, sub_command: 2, code: 1, message: "denied".to_string() };
+        let error = Error::from(oidb.clone());
+        assert_eq!(error.source().unwrap().downcast_ref::<OidbError>(), Some(&oidb));
+    }
+}
@@ -1,6 +1,6 @@
 pub mod context;
-mod packets;
+pub(crate) mod packets;
 pub mod services;
 
 // Re-export commonly used packet types
-pub use packets::SsoPacket;
+pub use packets::{sso, sso::SsoError, SsoPacket};
@@ -1,11 +1,28 @@
 pub mod cache;
 pub mod event;
+pub mod handler;
+pub mod highway;
+pub mod http;
 pub mod packet;
+pub mod scheduler;
+pub mod send_queue;
 pub mod service;
 pub mod socket;
+pub mod supervisor;
+pub mod transport;
 
-pub use cache::CacheContext;
+pub use cache::{CacheContext, MediaRKey, RKeyKind};
 pub use event::EventContext;
+pub use handler::{HandlerContext, HandlerGuard};
+pub use highway::{
+    HighwayContext, HighwaySession, HighwayUploader, MemoryResumeStore, UploadProgress, UploadResumeStore,
+    UploadStatus,
+};
+pub use http::{HttpClient, HttpContext, HttpMethod, HttpRequest, HttpResponse, ReqwestHttpClient};
 pub use packet::PacketContext;
+pub use scheduler::{JobHandle, JobPolicy, JobStatus, SchedulerContext};
+pub use send_queue::SendQueueContext;
 pub use service::ServiceContext;
 pub use socket::SocketContext;
+pub use supervisor::{SupervisorContext, SupervisorStats, MAX_TASK_RESTARTS};
+pub use transport::{TcpTransport, Transport};
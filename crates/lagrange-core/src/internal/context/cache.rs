@@ -1,107 +1,724 @@
+use crate::common::{
+    BotFriend, BotGroup, BotGroupMember, BotStranger, FriendInfoChangedEvent, FriendInfoField, FriendMessageEvent,
+    GroupMemberDecreaseEvent, GroupMemberDecreaseKind, GroupMemberIncreaseEvent, GroupMessageEvent, GroupMuteEvent,
+};
+use crate::protocol::EventMessage;
 use dashmap::DashMap;
-use std::sync::Arc;
+use lru::LruCache;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
 
-#[derive(Debug, Clone)]
-pub struct Friend {
-    pub uin: u64,
-    pub uid: String,
-    pub nickname: String,
+/// How long fetched contact lists are trusted by default
+pub const DEFAULT_CONTACT_TTL: Duration = Duration::from_secs(3600);
+
+/// How long stranger profiles are trusted, unless the contact lists expire sooner; strangers
+/// send no updates, so they go stale faster
+pub const STRANGER_TTL: Duration = Duration::from_secs(300);
+
+/// Users whose uid mapping is kept; the least recently used are forgotten first
+const UID_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(16384).unwrap();
+
+/// Both directions of the uin and uid mapping, bounded in size
+struct UidCache {
+    uids: LruCache<u64, String>,
+    uins: LruCache<String, u64>,
+}
+
+impl UidCache {
+    fn new() -> Self {
+        Self {
+            uids: LruCache::new(UID_CACHE_CAPACITY),
+            uins: LruCache::new(UID_CACHE_CAPACITY),
+        }
+    }
+}
+
+/// A contact list as fetched at once, keyed by uin
+struct Snapshot<T> {
+    fetched_at: Instant,
+    entries: HashMap<u64, Arc<T>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Group {
-    pub group_id: u64,
-    pub group_name: String,
+impl<T> Snapshot<T> {
+    fn new(entries: impl IntoIterator<Item = (u64, T)>) -> Self {
+        Self {
+            fetched_at: Instant::now(),
+            entries: entries.into_iter().map(|(uin, entry)| (uin, Arc::new(entry))).collect(),
+        }
+    }
+
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        self.fetched_at.elapsed() < ttl
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct GroupMember {
-    pub uin: u64,
-    pub uid: String,
-    pub nickname: String,
-    pub card: String,
+/// Profile of a stranger as fetched on its own
+struct CachedStranger {
+    fetched_at: Instant,
+    stranger: Arc<BotStranger>,
 }
 
+/// Kind of images an rkey authorizes downloads for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RKeyKind {
+    Private,
+    Group,
+}
+
+impl RKeyKind {
+    pub fn from_is_group(is_group: bool) -> Self {
+        if is_group {
+            RKeyKind::Group
+        } else {
+            RKeyKind::Private
+        }
+    }
+
+    /// Type of the kind in rkey requests
+    pub fn type_id(self) -> u32 {
+        match self {
+            RKeyKind::Private => 10,
+            RKeyKind::Group => 20,
+        }
+    }
+
+    pub fn from_type_id(type_id: u32) -> Option<Self> {
+        match type_id {
+            10 => Some(RKeyKind::Private),
+            20 => Some(RKeyKind::Group),
+            _ => None,
+        }
+    }
+}
+
+/// Download authorization for NT images
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaRKey {
+    pub kind: RKeyKind,
+    /// Query parameter to append to download urls, starting with `&rkey=`
+    pub param: String,
+    /// Unix timestamp (seconds)
+    pub expires_at: i64,
+}
+
+/// Rkeys are replaced this many seconds before they expire, so urls stay valid for a while
+const RKEY_EXPIRY_MARGIN: i64 = 60;
+
+/// Friends, groups and members of the bot, plus the uid mappings and rkeys learned along the way.
+///
+/// Uid mappings are seeded by every contact list and profile fetched and by incoming messages;
+/// [`BotContext::resolve_uid`](crate::BotContext::resolve_uid) asks the server for the rest.
+///
+/// Contact lists expire `ttl` after they were fetched; expired lists read as missing so the
+/// next lookup through [`BotContext`](crate::BotContext) fetches them again. Pushed events keep
+/// them current in between, see [`CacheContext::apply_event`].
 pub struct CacheContext {
-    friends: std::sync::RwLock<Option<Vec<Friend>>>,
+    ttl: Duration,
 
-    groups: std::sync::RwLock<Option<Vec<Group>>>,
+    friends: std::sync::RwLock<Option<Snapshot<BotFriend>>>,
 
-    members: DashMap<u64, Vec<GroupMember>>,
+    groups: std::sync::RwLock<Option<Snapshot<BotGroup>>>,
 
-    uin_to_uid: DashMap<u64, String>,
+    members: DashMap<u64, Snapshot<BotGroupMember>>,
 
-    uid_to_uin: DashMap<String, u64>,
+    /// Kept apart from the friends, so looking someone up does not make them one
+    strangers: DashMap<u64, CachedStranger>,
+
+    uids: Mutex<UidCache>,
+
+    rkeys: DashMap<RKeyKind, MediaRKey>,
 }
 
 impl CacheContext {
-    pub fn new() -> Arc<Self> {
-        Arc::new(Self {
+    pub fn new(ttl: Duration) -> Arc<Self> {
+        Arc::new(Self::with_ttl(ttl))
+    }
+
+    fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl,
             friends: std::sync::RwLock::new(None),
             groups: std::sync::RwLock::new(None),
             members: DashMap::new(),
-            uin_to_uid: DashMap::new(),
-            uid_to_uin: DashMap::new(),
-        })
+            strangers: DashMap::new(),
+            uids: Mutex::new(UidCache::new()),
+            rkeys: DashMap::new(),
+        }
+    }
+
+    /// The friend list, `None` if it was never fetched or has expired
+    pub fn friends(&self) -> Option<Vec<Arc<BotFriend>>> {
+        let friends = self.friends.read().expect("RwLock poisoned");
+        friends
+            .as_ref()
+            .filter(|snapshot| snapshot.is_fresh(self.ttl))
+            .map(|snapshot| snapshot.entries.values().cloned().collect())
     }
 
-    pub fn get_friends(&self) -> Option<Vec<Friend>> {
-        self.friends.read().expect("RwLock poisoned").clone()
+    /// A friend from the cached list. `None` either means the list is not loaded, see
+    /// [`CacheContext::has_friends`], or that `uin` is not a friend.
+    pub fn friend(&self, uin: u64) -> Option<Arc<BotFriend>> {
+        let friends = self.friends.read().expect("RwLock poisoned");
+        friends
+            .as_ref()
+            .filter(|snapshot| snapshot.is_fresh(self.ttl))
+            .and_then(|snapshot| snapshot.entries.get(&uin).cloned())
     }
 
-    pub fn cache_friends(&self, friends: Vec<Friend>) {
+    /// Whether an unexpired friend list is cached
+    pub fn has_friends(&self) -> bool {
+        let friends = self.friends.read().expect("RwLock poisoned");
+        friends.as_ref().is_some_and(|snapshot| snapshot.is_fresh(self.ttl))
+    }
+
+    pub fn cache_friends(&self, friends: Vec<BotFriend>) {
         for friend in &friends {
-            self.uin_to_uid.insert(friend.uin, friend.uid.clone());
-            self.uid_to_uin.insert(friend.uid.clone(), friend.uin);
+            self.map_uid(friend.uin, &friend.uid);
+        }
+        let snapshot = Snapshot::new(friends.into_iter().map(|friend| (friend.uin, friend)));
+        *self.friends.write().expect("RwLock poisoned") = Some(snapshot);
+    }
+
+    /// Add a new friend to the cached list, if one is loaded
+    pub fn add_friend(&self, friend: BotFriend) {
+        self.map_uid(friend.uin, &friend.uid);
+        if let Some(snapshot) = self.friends.write().expect("RwLock poisoned").as_mut() {
+            snapshot.entries.insert(friend.uin, Arc::new(friend));
+        }
+    }
+
+    /// Change a cached friend in place, if they are in the loaded list
+    pub fn update_friend(&self, uin: u64, update: impl FnOnce(&mut BotFriend)) {
+        if let Some(snapshot) = self.friends.write().expect("RwLock poisoned").as_mut() {
+            if let Some(friend) = snapshot.entries.get_mut(&uin) {
+                update(Arc::make_mut(friend));
+            }
+        }
+    }
+
+    pub fn remove_friend(&self, uin: u64) {
+        if let Some(snapshot) = self.friends.write().expect("RwLock poisoned").as_mut() {
+            snapshot.entries.remove(&uin);
         }
-        *self.friends.write().expect("RwLock poisoned") = Some(friends);
     }
 
-    pub fn get_groups(&self) -> Option<Vec<Group>> {
-        self.groups.read().expect("RwLock poisoned").clone()
+    /// Drop the friend list so the next lookup fetches it again
+    pub fn invalidate_friends(&self) {
+        *self.friends.write().expect("RwLock poisoned") = None;
+    }
+
+    /// The joined groups, `None` if they were never fetched or have expired
+    pub fn groups(&self) -> Option<Vec<Arc<BotGroup>>> {
+        let groups = self.groups.read().expect("RwLock poisoned");
+        groups
+            .as_ref()
+            .filter(|snapshot| snapshot.is_fresh(self.ttl))
+            .map(|snapshot| snapshot.entries.values().cloned().collect())
+    }
+
+    /// A joined group from the cached list, see [`CacheContext::friend`] for what `None` means
+    pub fn group(&self, group_uin: u64) -> Option<Arc<BotGroup>> {
+        let groups = self.groups.read().expect("RwLock poisoned");
+        groups
+            .as_ref()
+            .filter(|snapshot| snapshot.is_fresh(self.ttl))
+            .and_then(|snapshot| snapshot.entries.get(&group_uin).cloned())
     }
 
-    pub fn cache_groups(&self, groups: Vec<Group>) {
-        *self.groups.write().expect("RwLock poisoned") = Some(groups);
+    /// Whether an unexpired group list is cached
+    pub fn has_groups(&self) -> bool {
+        let groups = self.groups.read().expect("RwLock poisoned");
+        groups.as_ref().is_some_and(|snapshot| snapshot.is_fresh(self.ttl))
     }
 
-    pub fn get_members(&self, group_id: u64) -> Option<Vec<GroupMember>> {
-        self.members.get(&group_id).map(|v| v.clone())
+    pub fn cache_groups(&self, groups: Vec<BotGroup>) {
+        let snapshot = Snapshot::new(groups.into_iter().map(|group| (group.group_uin, group)));
+        *self.groups.write().expect("RwLock poisoned") = Some(snapshot);
     }
 
-    pub fn cache_members(&self, group_id: u64, members: Vec<GroupMember>) {
+    /// Drop a group the bot is no longer in, along with its members
+    pub fn remove_group(&self, group_uin: u64) {
+        if let Some(snapshot) = self.groups.write().expect("RwLock poisoned").as_mut() {
+            snapshot.entries.remove(&group_uin);
+        }
+        self.invalidate_members(group_uin);
+    }
+
+    /// Drop the group list so the next lookup fetches it again
+    pub fn invalidate_groups(&self) {
+        *self.groups.write().expect("RwLock poisoned") = None;
+    }
+
+    pub fn rename_group(&self, group_uin: u64, name: &str) {
+        if let Some(snapshot) = self.groups.write().expect("RwLock poisoned").as_mut() {
+            if let Some(group) = snapshot.entries.get_mut(&group_uin) {
+                if group.group_name != name {
+                    Arc::make_mut(group).group_name = name.to_string();
+                }
+            }
+        }
+    }
+
+    /// The members of `group_uin`, `None` if they were never fetched or have expired
+    pub fn members(&self, group_uin: u64) -> Option<Vec<Arc<BotGroupMember>>> {
+        self.members
+            .get(&group_uin)
+            .filter(|snapshot| snapshot.is_fresh(self.ttl))
+            .map(|snapshot| snapshot.entries.values().cloned().collect())
+    }
+
+    /// A member from the cached list of `group_uin`, see [`CacheContext::friend`] for what
+    /// `None` means
+    pub fn member(&self, group_uin: u64, uin: u64) -> Option<Arc<BotGroupMember>> {
+        self.members
+            .get(&group_uin)
+            .filter(|snapshot| snapshot.is_fresh(self.ttl))
+            .and_then(|snapshot| snapshot.entries.get(&uin).cloned())
+    }
+
+    /// Whether an unexpired member list of `group_uin` is cached
+    pub fn has_members(&self, group_uin: u64) -> bool {
+        self.members
+            .get(&group_uin)
+            .is_some_and(|snapshot| snapshot.is_fresh(self.ttl))
+    }
+
+    pub fn cache_members(&self, group_uin: u64, members: Vec<BotGroupMember>) {
         for member in &members {
-            self.uin_to_uid.insert(member.uin, member.uid.clone());
-            self.uid_to_uin.insert(member.uid.clone(), member.uin);
+            self.map_uid(member.uin, &member.uid);
         }
-        self.members.insert(group_id, members);
+        let snapshot = Snapshot::new(members.into_iter().map(|member| (member.uin, member)));
+        self.members.insert(group_uin, snapshot);
     }
 
+    /// Add a member who joined to the cached list of their group, if one is loaded
+    pub fn add_member(&self, member: BotGroupMember) {
+        self.map_uid(member.uin, &member.uid);
+        if let Some(mut snapshot) = self.members.get_mut(&member.group_uin) {
+            snapshot.entries.insert(member.uin, Arc::new(member));
+        }
+    }
+
+    pub fn remove_member(&self, group_uin: u64, uin: u64) {
+        if let Some(mut snapshot) = self.members.get_mut(&group_uin) {
+            snapshot.entries.remove(&uin);
+        }
+    }
+
+    /// Set until when a cached member is muted, the Unix epoch when they are not
+    pub fn mute_member(&self, group_uin: u64, uin: u64, until: chrono::DateTime<chrono::Utc>) {
+        if let Some(mut snapshot) = self.members.get_mut(&group_uin) {
+            if let Some(member) = snapshot.entries.get_mut(&uin) {
+                Arc::make_mut(member).shut_up_timestamp = until;
+            }
+        }
+    }
+
+    /// Drop the member list of `group_uin` so the next lookup fetches it again
+    pub fn invalidate_members(&self, group_uin: u64) {
+        self.members.remove(&group_uin);
+    }
+
+    /// Profile of a stranger, `None` if it was never fetched or has expired
+    pub fn stranger(&self, uin: u64) -> Option<Arc<BotStranger>> {
+        let ttl = self.stranger_ttl();
+        self.strangers
+            .get(&uin)
+            .filter(|cached| cached.fetched_at.elapsed() < ttl)
+            .map(|cached| cached.stranger.clone())
+    }
+
+    /// Keep the profile of a stranger for [`STRANGER_TTL`], dropping the expired ones
+    pub fn cache_stranger(&self, stranger: BotStranger) {
+        let ttl = self.stranger_ttl();
+        self.strangers.retain(|_, cached| cached.fetched_at.elapsed() < ttl);
+        self.map_uid(stranger.uin, &stranger.uid);
+        let cached = CachedStranger { fetched_at: Instant::now(), stranger: Arc::new(stranger) };
+        self.strangers.insert(cached.stranger.uin, cached);
+    }
+
+    fn stranger_ttl(&self) -> Duration {
+        self.ttl.min(STRANGER_TTL)
+    }
+
+    /// Keep the contact lists in line with a pushed event.
+    ///
+    /// Messages carry the current group name, and a sender missing from a loaded list means
+    /// the list is outdated: it is dropped rather than patched, since the message alone does
+    /// not describe the new contact. Messages of the bot itself, `bot_uin`, synced from its
+    /// other devices, say nothing about the friend list.
+    ///
+    /// Members who left are removed from their group; one who joined drops the member list for
+    /// the same reason as an unknown sender. When the bot itself joins or leaves a group, the
+    /// group list is updated instead.
+    ///
+    /// Mutes of a member count from the moment they are applied here; friend nicknames and
+    /// remarks are patched in place.
+    pub fn apply_event(&self, event: &EventMessage, bot_uin: u64) {
+        if let Some(message) = event.downcast_ref::<GroupMessageEvent>() {
+            if !message.group_name.is_empty() {
+                self.rename_group(message.group_uin, &message.group_name);
+            }
+            if self.has_members(message.group_uin) && self.member(message.group_uin, message.sender_uin).is_none() {
+                self.invalidate_members(message.group_uin);
+            }
+        } else if let Some(message) = event.downcast_ref::<FriendMessageEvent>() {
+            if message.sender_uin != bot_uin && self.has_friends() && self.friend(message.sender_uin).is_none() {
+                self.invalidate_friends();
+            }        } else if let Some(change) = event.downcast_ref::<GroupMemberIncreaseEvent>() {
+            if change.member_uin == bot_uin {
+                self.invalidate_groups();
+            }
+            self.invalidate_members(change.group);
+        } else if let Some(change) = event.downcast_ref::<GroupMemberDecreaseEvent>() {
+            if change.kind == GroupMemberDecreaseKind::KickedSelf || change.member_uin == bot_uin {
+                self.remove_group(change.group);
+            } else {
+                self.remove_member(change.group, change.member_uin);
+            }
+        } else if let Some(change) = event.downcast_ref::<FriendInfoChangedEvent>() {
+            let new = change.new.clone();
+            match change.field {
+                FriendInfoField::Nickname => self.update_friend(change.uin, |friend| friend.nickname = new),
+                FriendInfoField::Remark => self.update_friend(change.uin, |friend| friend.remarks = new),
+                FriendInfoField::Avatar => {}
+            }
+        } else if let Some(mute) = event.downcast_ref::<GroupMuteEvent>() {
+            if let Some(target) = mute.target.filter(|&target| target != 0) {
+                let until = match chrono::Duration::from_std(mute.duration) {
+                    Ok(duration) if !duration.is_zero() => chrono::Utc::now() + duration,
+                    _ => chrono::DateTime::UNIX_EPOCH,
+                };
+                self.mute_member(mute.group, target, until);
+            }
+        }
+    }
+
+    /// Remember that `uin` and `uid` are the same user; ignored if either is unset
+    pub fn map_uid(&self, uin: u64, uid: &str) {
+        if uin == 0 || uid.is_empty() {
+            return;
+        }
+        let mut uids = self.uids.lock().expect("Mutex poisoned");
+        uids.uids.put(uin, uid.to_string());
+        uids.uins.put(uid.to_string(), uin);
+    }
+
+    /// The cached uid of `uin`, without asking the server
     pub fn resolve_uid(&self, uin: u64) -> Option<String> {
-        self.uin_to_uid.get(&uin).map(|v| v.clone())
+        self.uids.lock().expect("Mutex poisoned").uids.get(&uin).cloned()
     }
 
+    /// The cached uin of `uid`, without asking the server
     pub fn resolve_uin(&self, uid: &str) -> Option<u64> {
-        self.uid_to_uin.get(uid).map(|v| *v)
+        self.uids.lock().expect("Mutex poisoned").uins.get(uid).copied()
+    }
+
+    /// Rkey of `kind` that is still valid at `now` (unix seconds)
+    pub fn get_rkey(&self, kind: RKeyKind, now: i64) -> Option<MediaRKey> {
+        self.rkeys
+            .get(&kind)
+            .filter(|rkey| rkey.expires_at - RKEY_EXPIRY_MARGIN > now)
+            .map(|rkey| rkey.clone())
+    }
+
+    pub fn cache_rkeys(&self, rkeys: Vec<MediaRKey>) {
+        for rkey in rkeys {
+            self.rkeys.insert(rkey.kind, rkey);
+        }
     }
 
     pub fn clear(&self) {
         *self.friends.write().expect("RwLock poisoned") = None;
         *self.groups.write().expect("RwLock poisoned") = None;
         self.members.clear();
-        self.uin_to_uid.clear();
-        self.uid_to_uin.clear();
+        self.strangers.clear();
+        *self.uids.lock().expect("Mutex poisoned") = UidCache::new();
+        self.rkeys.clear();
     }
 }
 
 impl Default for CacheContext {
     fn default() -> Self {
-        Self {
-            friends: std::sync::RwLock::new(None),
-            groups: std::sync::RwLock::new(None),
-            members: DashMap::new(),
-            uin_to_uid: DashMap::new(),
-            uid_to_uin: DashMap::new(),
+        Self::with_ttl(DEFAULT_CONTACT_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{BotGender, GroupMemberIncreaseKind, GroupRole};
+    use crate::message::MessageChain;
+
+    fn friend(uin: u64) -> BotFriend {
+        BotFriend {
+            uin,
+            uid: format!("u_{}", uin),
+            nickname: format!("friend {}", uin),
+            age: 0,
+            gender: BotGender::Unset,
+            remarks: String::new(),
+            personal_sign: String::new(),
+            qid: String::new(),
+            category: None,
         }
     }
+
+    fn group(group_uin: u64, name: &str) -> BotGroup {
+        BotGroup {
+            group_uin,
+            group_uid: String::new(),
+            group_name: name.to_string(),
+            member_count: 2,
+            max_member: 200,
+            create_time: 0,
+            description: None,
+            question: None,
+            announcement: None,
+        }
+    }
+
+    fn member(group_uin: u64, uin: u64) -> BotGroupMember {
+        BotGroupMember {
+            uin,
+            uid: format!("u_{}", uin),
+            nickname: format!("member {}", uin),
+            group_uin,
+            role: GroupRole::Member,
+            level: 0,
+            card: None,
+            special_title: None,
+            age: 0,
+            gender: BotGender::Unset,
+            join_time: Default::default(),
+            last_msg_time: Default::default(),
+            shut_up_timestamp: Default::default(),
+        }
+    }
+
+    fn group_message(group_uin: u64, group_name: &str, sender_uin: u64) -> EventMessage {
+        EventMessage::new(GroupMessageEvent {
+            group_uin,
+            group_name: group_name.to_string(),
+            sender_uin,
+            sender_uid: format!("u_{}", sender_uin),
+            sender_nickname: String::new(),
+            sequence: 1,
+            random: 1,
+            timestamp: 0,
+            chain: MessageChain::new(),
+        })
+    }
+
+    fn friend_message(sender_uin: u64) -> EventMessage {
+        EventMessage::new(FriendMessageEvent {
+            sender_uin,
+            sender_uid: format!("u_{}", sender_uin),
+            sender_nickname: String::new(),
+            sequence: 1,
+            random: 1,
+            timestamp: 0,
+            chain: MessageChain::new(),
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_contact_ttl() {
+        let cache = CacheContext::with_ttl(Duration::from_secs(60));
+        assert_eq!(cache.friends(), None);
+        assert!(!cache.has_friends());
+
+        cache.cache_friends(vec![friend(10001), friend(10002)]);
+        cache.cache_groups(vec![group(123456, "rust")]);
+        cache.cache_members(123456, vec![member(123456, 10001)]);
+        assert_eq!(cache.friend(10001).unwrap().nickname, "friend 10001");
+        assert_eq!(cache.friend(10003), None);
+        assert_eq!(cache.friends().unwrap().len(), 2);
+        assert_eq!(cache.group(123456).unwrap().group_name, "rust");
+        assert!(cache.member(123456, 10001).is_some());
+
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert!(cache.has_friends());
+        cache.cache_members(123456, vec![member(123456, 10001)]);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(!cache.has_friends());
+        assert_eq!(cache.friend(10001), None);
+        assert_eq!(cache.groups(), None);
+        // Fetched later, so it is still fresh
+        assert!(cache.has_members(123456));
+        // The uid mappings outlive the lists
+        assert_eq!(cache.resolve_uin("u_10002"), Some(10002));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stranger_ttl() {
+        let cache = CacheContext::default();
+        let stranger = BotStranger {
+            uin: 10009,
+            uid: "u_10009".to_string(),
+            nickname: "stranger".to_string(),
+            age: 0,
+            gender: BotGender::Unset,
+            level: 1,
+            personal_sign: String::new(),
+        };
+        cache.cache_friends(vec![friend(10001)]);
+        cache.cache_stranger(stranger);
+        assert_eq!(cache.stranger(10009).unwrap().nickname, "stranger");
+        assert_eq!(cache.friend(10009), None);
+        assert_eq!(cache.friends().unwrap().len(), 1);
+
+        tokio::time::advance(STRANGER_TTL).await;
+        assert_eq!(cache.stranger(10009), None);
+        assert!(cache.has_friends());
+        assert_eq!(cache.resolve_uid(10009).as_deref(), Some("u_10009"));
+    }
+
+    #[test]
+    fn test_apply_group_message() {
+        let cache = CacheContext::default();
+        cache.cache_groups(vec![group(123456, "rust")]);
+        cache.cache_members(123456, vec![member(123456, 10001), member(123456, 10002)]);
+
+        let before = cache.group(123456).unwrap();
+        cache.apply_event(&group_message(123456, "rustaceans", 10001), 10000);
+        assert_eq!(cache.group(123456).unwrap().group_name, "rustaceans");
+        assert_eq!(before.group_name, "rust");
+        assert!(cache.has_members(123456));
+
+        // A sender missing from the member list joined after it was fetched
+        cache.apply_event(&group_message(123456, "rustaceans", 10003), 10000);
+        assert!(!cache.has_members(123456));
+        assert!(cache.has_groups());
+    }
+
+    #[test]
+    fn test_apply_friend_message() {
+        let cache = CacheContext::default();
+        cache.cache_friends(vec![friend(10001)]);
+
+        cache.apply_event(&friend_message(10001), 10000);
+        cache.apply_event(&friend_message(10000), 10000);
+        assert!(cache.has_friends());
+
+        cache.apply_event(&friend_message(10002), 10000);
+        assert!(!cache.has_friends());
+    }
+
+    #[test]
+    fn test_apply_member_changes() {
+        let cache = CacheContext::default();
+        cache.cache_groups(vec![group(123456, "rust"), group(654321, "go")]);
+        cache.cache_members(123456, vec![member(123456, 10001), member(123456, 10002)]);
+        cache.cache_members(654321, vec![member(654321, 10001)]);
+
+        let decrease = |group, member_uin, kind| {
+            EventMessage::new(GroupMemberDecreaseEvent {
+                group,
+                member_uin,
+                member_uid: format!("u_{}", member_uin),
+                operator: None,
+                kind,
+            })
+        };
+        cache.apply_event(&decrease(123456, 10002, GroupMemberDecreaseKind::Left), 10000);
+        let uins: Vec<u64> = cache.members(123456).unwrap().iter().map(|member| member.uin).collect();
+        assert_eq!(uins, [10001]);
+        assert!(cache.group(123456).is_some());
+
+        // The bot leaving drops the whole group
+        cache.apply_event(&decrease(654321, 10000, GroupMemberDecreaseKind::KickedSelf), 10000);
+        assert_eq!(cache.group(654321), None);
+        assert_eq!(cache.members(654321), None);
+        assert!(cache.group(123456).is_some());
+
+        let increase = |member_uin| {
+            EventMessage::new(GroupMemberIncreaseEvent {
+                group: 123456,
+                member_uin,
+                member_uid: format!("u_{}", member_uin),
+                invitor: None,
+                kind: GroupMemberIncreaseKind::Joined,
+            })
+        };
+        cache.apply_event(&increase(10003), 10000);
+        assert_eq!(cache.members(123456), None);
+        assert!(cache.has_groups());
+
+        cache.apply_event(&increase(10000), 10000);
+        assert!(!cache.has_groups());
+    }
+
+    #[test]
+    fn test_apply_mute() {
+        let cache = CacheContext::default();
+        cache.cache_members(123456, vec![member(123456, 10001)]);
+        let mute = |duration| {
+            EventMessage::new(GroupMuteEvent {
+                group: 123456,
+                operator: 10009,
+                target: Some(10001),
+                duration: Duration::from_secs(duration),
+                whole_group: false,
+            })
+        };
+
+        cache.apply_event(&mute(600), 10000);
+        let muted = cache.member(123456, 10001).unwrap();
+        assert!(muted.is_muted(chrono::Utc::now()));
+        let remaining = muted.mute_remaining(chrono::Utc::now()).unwrap();
+        assert!(remaining > Duration::from_secs(590) && remaining <= Duration::from_secs(600));
+
+        cache.apply_event(&mute(0), 10000);
+        assert!(!cache.member(123456, 10001).unwrap().is_muted(chrono::Utc::now()));
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        let cache = CacheContext::default();
+        cache.add_member(member(123456, 10001));
+        assert_eq!(cache.members(123456), None);
+        assert_eq!(cache.resolve_uid(10001).as_deref(), Some("u_10001"));
+
+        cache.cache_members(123456, vec![member(123456, 10001)]);
+        cache.add_member(member(123456, 10002));
+        cache.remove_member(123456, 10001);
+        let uins: Vec<u64> = cache.members(123456).unwrap().iter().map(|member| member.uin).collect();
+        assert_eq!(uins, [10002]);
+
+        cache.cache_friends(vec![friend(10001)]);
+        cache.add_friend(friend(10002));
+        cache.remove_friend(10001);
+        assert_eq!(cache.friend(10001), None);
+        assert!(cache.friend(10002).is_some());
+    }
+
+    fn rkey(kind: RKeyKind, param: &str, expires_at: i64) -> MediaRKey {
+        MediaRKey { kind, param: param.to_string(), expires_at }
+    }
+
+    #[test]
+    fn test_rkey_expiry() {
+        let cache = CacheContext::default();
+        assert_eq!(cache.get_rkey(RKeyKind::Group, 1000), None);
+
+        cache.cache_rkeys(vec![
+            rkey(RKeyKind::Private, "&rkey=private", 4600),
+            rkey(RKeyKind::Group, "&rkey=group", 4600),
+        ]);
+        assert_eq!(cache.get_rkey(RKeyKind::Group, 1000).unwrap().param, "&rkey=group");
+        assert_eq!(cache.get_rkey(RKeyKind::Private, 1000).unwrap().param, "&rkey=private");
+
+        // Dropped a minute before the server expires it
+        assert!(cache.get_rkey(RKeyKind::Group, 4539).is_some());
+        assert_eq!(cache.get_rkey(RKeyKind::Group, 4540), None);
+
+        cache.cache_rkeys(vec![rkey(RKeyKind::Group, "&rkey=renewed", 8200)]);
+        assert_eq!(cache.get_rkey(RKeyKind::Group, 4540).unwrap().param, "&rkey=renewed");
+        assert_eq!(cache.get_rkey(RKeyKind::Private, 4540), None);
+    }
 }
@@ -1,8 +1,13 @@
 use crate::protocol::{EventMessage, ProtocolEvent};
 use crate::config::BotConfig;
+use crate::internal::services::TypedServiceEntry;
+use super::packet::ServiceAttribute;
 use super::{PacketContext, SocketContext};
-use std::{any::TypeId, sync::Arc};
+use std::any::{Any, TypeId};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
+use tracing::Instrument;
 
 pub struct EventContext {
     sender: broadcast::Sender<EventMessage>,
@@ -38,10 +43,9 @@ impl EventContext {
         self.sender.subscribe()
     }
 
-    pub fn subscribe_to<T: 'static>(&self) -> TypedEventReceiver<T> {
+    pub fn subscribe_to<T: ProtocolEvent>(&self) -> TypedEventReceiver<T> {
         TypedEventReceiver {
             receiver: self.sender.subscribe(),
-            type_id: TypeId::of::<T>(),
             _phantom: std::marker::PhantomData,
         }
     }
@@ -79,35 +83,135 @@ impl EventContext {
     where
         S: crate::protocol::TypedService,
     {
-        use crate::internal::context::packet::ServiceAttribute;
-        use std::any::TypeId;
+        // 1. Find the typed service entry by request type + protocol
+        let service_entry = self.service_for(TypeId::of::<S::Request>())?;
 
-        let request_type_id = TypeId::of::<S::Request>();
-        let protocol = self.config.protocol as u8;
+        // 2-5. Build, send and parse (type-erased but type-safe)
+        let response = self
+            .send_erased(service_entry, Box::new(request), context)
+            .await?;
 
-        // 1. Find the typed service entry by request type + protocol
-        let registry = crate::internal::services::registry();
-        let service_entry = registry
+        // 6. Unwrap the response as the concrete type
+        // This is guaranteed to succeed because the service entry was created with
+        // matching request/response types
+        response.try_unwrap::<S::Response>().map_err(|response| {
+            crate::Error::ParseError(format!(
+                "Failed to downcast response {} to expected type {}",
+                response,
+                std::any::type_name::<S::Response>()
+            ))
+        })
+    }
+
+    /// Send `request` to the service registered for `Req` and wait at most `timeout` for its
+    /// response.
+    ///
+    /// Unlike [`EventContext::send`] the service is not named, it is looked up by the request
+    /// type. Returns [`Error::WrongResponseType`](crate::Error::WrongResponseType) if that
+    /// service does not answer with `Resp`, and [`Error::Timeout`](crate::Error::Timeout) if
+    /// no response arrived in time; a response arriving later is dropped.
+    pub async fn send_and_wait<Req, Resp>(
+        self: &Arc<Self>,
+        request: Req,
+        timeout: Duration,
+        context: Arc<crate::context::BotContext>,
+    ) -> Result<Resp, crate::Error>
+    where
+        Req: ProtocolEvent,
+        Resp: ProtocolEvent,
+    {
+        let service_entry = self.service_for(TypeId::of::<Req>())?;
+        let wrong_response_type = || crate::Error::WrongResponseType {
+            command: service_entry.command.clone(),
+            expected: std::any::type_name::<Resp>(),
+        };
+        if service_entry.response_type_id != TypeId::of::<Resp>() {
+            return Err(wrong_response_type());
+        }
+
+        let response = tokio::time::timeout(
+            timeout,
+            self.send_erased(service_entry, Box::new(request), context),
+        )
+        .await
+        .map_err(|_| crate::Error::Timeout {
+            command: service_entry.command.clone(),
+            after: timeout,
+        })??;
+
+        response.try_unwrap::<Resp>().map_err(|_| wrong_response_type())
+    }
+
+    /// Whether a service for `S` is registered for the protocol of the config, i.e. whether
+    /// [`EventContext::send`] can reach it rather than fail with `ServiceNotFound`
+    pub fn supports<S>(&self) -> bool
+    where
+        S: crate::protocol::TypedService,
+    {
+        self.service_for(TypeId::of::<S::Request>()).is_ok()
+    }
+
+    fn service_for(
+        &self,
+        request_type_id: TypeId,
+    ) -> Result<&'static Arc<TypedServiceEntry>, crate::Error> {
+        let protocol = self.config.protocol.mask();
+        crate::internal::services::registry()
             .get_typed_service_by_request_and_protocol(request_type_id, protocol)
             .ok_or_else(|| {
                 crate::Error::ServiceNotFound(format!(
                     "No typed service found for request type {:?} with protocol {:?}",
                     request_type_id, self.config.protocol
                 ))
-            })?;
+            })
+    }
+
+    /// Build, sign, send and parse inside a `packet` span, so every line logged on the way
+    /// carries the command, sequence and bot uin, and the span records the latency on close
+    async fn send_erased(
+        &self,
+        service_entry: &TypedServiceEntry,
+        request: Box<dyn Any + Send>,
+        context: Arc<crate::context::BotContext>,
+    ) -> Result<EventMessage, crate::Error> {
+        let span = tracing::info_span!(
+            "packet",
+            command = %service_entry.command,
+            sequence = tracing::field::Empty,
+            uin = ?context.bot_uin(),
+            latency_ms = tracing::field::Empty
+        );
 
-        // 2. Build the outgoing packet (type-erased but type-safe)
-        let request_any = Box::new(request) as Box<dyn std::any::Any + Send>;
-        let bytes = service_entry.build(request_any, context.clone()).await?;
+        let started = Instant::now();
+        let result = self
+            .send_in_span(service_entry, request, context)
+            .instrument(span.clone())
+            .await;
+        span.record("latency_ms", started.elapsed().as_millis() as u64);
+        result
+    }
+
+    async fn send_in_span(
+        &self,
+        service_entry: &TypedServiceEntry,
+        request: Box<dyn Any + Send>,
+        context: Arc<crate::context::BotContext>,
+    ) -> Result<EventMessage, crate::Error> {
+        if context.is_logged_out() {
+            return Err(crate::Error::LoggedOut);
+        }
 
-        // 3. Set up packet attributes
+        // Build the outgoing packet
+        let bytes = service_entry.build(request, context.clone()).await?;
+
+        // Set up packet attributes
         let attributes = Some(
             ServiceAttribute::new()
                 .with_request_type(service_entry.metadata.request_type)
                 .with_encrypt_type(service_entry.metadata.encrypt_type),
         );
 
-        // 4. Send the packet over the network
+        // Send the packet over the network
         let response_packet = self
             .packet
             .send_packet(
@@ -118,40 +222,36 @@ impl EventContext {
             )
             .await?;
 
-        // 5. Parse the response (type-erased but type-safe)
-        let response_any = service_entry.parse(response_packet.data, context).await?;
-
-        // 6. Downcast the response to the concrete type
-        // This is guaranteed safe because the service entry was created with
-        // matching request/response types
-        let response = response_any
-            .downcast::<S::Response>()
-            .map_err(|_| {
-                crate::Error::ParseError(format!(
-                    "Failed to downcast response to expected type {:?}",
-                    TypeId::of::<S::Response>()
-                ))
-            })?;
+        // Rejected before reaching the service, there is no body to parse
+        if response_packet.ret_code != 0 {
+            let ret_code = crate::RetCode::from_code(response_packet.ret_code);
+            if ret_code.is_token_expired() {
+                context.schedule_relogin();
+            }
+            return Err(crate::Error::Server {
+                command: response_packet.command,
+                ret_code,
+                message: response_packet.extra,
+            });
+        }
 
-        Ok(*response)
+        let response = service_entry.parse_event(response_packet.data, context).await?;
+        Ok(response.with_origin(response_packet.command, response_packet.sequence))
     }
 
 }
 
 pub struct TypedEventReceiver<T> {
     receiver: broadcast::Receiver<EventMessage>,
-    type_id: TypeId,
     _phantom: std::marker::PhantomData<T>,
 }
 
-impl<T: 'static> TypedEventReceiver<T> {
+impl<T: ProtocolEvent> TypedEventReceiver<T> {
     pub async fn recv(&mut self) -> Result<Arc<T>, broadcast::error::RecvError> {
         loop {
             let event = self.receiver.recv().await?;
-            if event.type_id() == self.type_id {
-                if let Some(typed) = event.downcast::<T>() {
-                    return Ok(typed);
-                }
+            if let Ok(typed) = event.downcast::<T>() {
+                return Ok(typed);
             }
         }
     }
@@ -159,10 +259,8 @@ impl<T: 'static> TypedEventReceiver<T> {
     pub fn try_recv(&mut self) -> Result<Arc<T>, broadcast::error::TryRecvError> {
         loop {
             let event = self.receiver.try_recv()?;
-            if event.type_id() == self.type_id {
-                if let Some(typed) = event.downcast::<T>() {
-                    return Ok(typed);
-                }
+            if let Ok(typed) = event.downcast::<T>() {
+                return Ok(typed);
             }
         }
     }
@@ -173,7 +271,6 @@ impl<T> Clone for TypedEventReceiver<T> {
     fn clone(&self) -> Self {
         Self {
             receiver: self.receiver.resubscribe(),
-            type_id: self.type_id,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -182,3 +279,80 @@ impl<T> Clone for TypedEventReceiver<T> {
 impl Drop for EventContext {
     fn drop(&mut self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::context::BotContext;
+    use crate::internal::services::system::heartbeat::{AliveEventReq, AliveEventResp};
+    use crate::internal::services::login::TransEmp31EventResp;
+    use crate::keystore::BotKeystore;
+    use crate::testing::{Expectation, MockServer};
+    use crate::{Error, RetCode};
+    use bytes::Bytes;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A context connected to a mock server handling its heartbeats as `expectation` says
+    async fn connect(expectation: Expectation) -> (Arc<BotContext>, MockServer) {
+        let (transport, server) = MockServer::start(BotKeystore::default());
+        server.expect(expectation);
+        let context = BotContext::builder().transport(transport).build();
+        context.connect().await.unwrap();
+        (context, server)
+    }
+
+    #[tokio::test]
+    async fn test_send_and_wait() {
+        let (context, _server) = connect(Expectation::new("Heartbeat.Alive").respond(Bytes::new())).await;
+
+        let response = context
+            .send_and_wait::<AliveEventReq, AliveEventResp>(AliveEventReq {}, Duration::from_secs(5))
+            .await;
+        assert!(response.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_and_wait_timeout() {
+        let (context, _server) = connect(Expectation::new("Heartbeat.Alive")).await;
+
+        let response = context
+            .send_and_wait::<AliveEventReq, AliveEventResp>(AliveEventReq {}, Duration::from_millis(50))
+            .await;
+        assert!(matches!(
+            response,
+            Err(Error::Timeout { command, after }) if command == "Heartbeat.Alive" && after == Duration::from_millis(50)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_request() {
+        let (context, _server) = connect(Expectation::new("Heartbeat.Alive").reject(-10003, "token expired")).await;
+
+        let response = context
+            .send_and_wait::<AliveEventReq, AliveEventResp>(AliveEventReq {}, Duration::from_secs(5))
+            .await;
+        let error = response.unwrap_err();
+        assert!(error.requires_relogin());
+        assert!(matches!(
+            error,
+            Error::Server { ret_code: RetCode::TokenExpired, message, .. } if message == "token expired"
+        ));
+        // Offline bots are logging in themselves, the rejection is left to that login
+        assert!(!context.relogin_running.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_send_and_wait_wrong_response_type() {
+        let (context, _server) = connect(Expectation::new("Heartbeat.Alive").respond(Bytes::new())).await;
+
+        let response = context
+            .send_and_wait::<AliveEventReq, TransEmp31EventResp>(AliveEventReq {}, Duration::from_secs(5))
+            .await;
+        assert!(matches!(
+            response,
+            Err(Error::WrongResponseType { command, expected })
+                if command == "Heartbeat.Alive" && expected.ends_with("TransEmp31EventResp")
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::context::BotContext;
+use crate::protocol::{EventMessage, ProtocolEvent};
+use std::any::TypeId;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use tokio::sync::{broadcast, Mutex as AsyncMutex};
+
+type BoxedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type ErasedHandler = Arc<dyn Fn(Arc<BotContext>, EventMessage) -> BoxedFuture + Send + Sync>;
+
+struct Registration {
+    id: u64,
+    type_id: TypeId,
+    handler: ErasedHandler,
+}
+
+/// Event handlers registered on a [`BotContext`], see [`BotContext::on`].
+///
+/// Handlers run one after another on a dispatcher task, in the order they were registered, and
+/// every event is handled before the next one. A handler that panics is logged, counted in the
+/// [`SupervisorStats`](super::SupervisorStats) and skipped, the others still run. Subscriptions
+/// declared with `#[event_subscribe]` are not dispatched here.
+#[derive(Default)]
+pub struct HandlerContext {
+    handlers: RwLock<Vec<Registration>>,
+    next_id: AtomicU64,
+    dispatcher: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl HandlerContext {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Add a handler for events of type `T`, starting the dispatcher on first use
+    pub(crate) fn register<T, F, Fut>(
+        self: &Arc<Self>,
+        context: &Arc<BotContext>,
+        handler: F,
+    ) -> HandlerGuard
+    where
+        T: ProtocolEvent,
+        F: Fn(Arc<BotContext>, Arc<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handler: ErasedHandler = Arc::new(move |context, event: EventMessage| match event.downcast::<T>() {
+            Ok(event) => Box::pin(handler(context, event)),
+            Err(_) => Box::pin(async {}),
+        });
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.handlers.write().expect("RwLock poisoned").push(Registration {
+            id,
+            type_id: TypeId::of::<T>(),
+            handler,
+        });
+        self.start_dispatcher(context);
+
+        HandlerGuard {
+            handlers: Arc::downgrade(self),
+            id: Some(id),
+        }
+    }
+
+    fn start_dispatcher(self: &Arc<Self>, context: &Arc<BotContext>) {
+        let mut dispatcher = self.dispatcher.lock().expect("Mutex poisoned");
+        if dispatcher.is_none() {
+            // Subscribed here so that events posted right after registering are not missed, and
+            // shared so that a restarted dispatcher carries on where the crashed one stopped
+            let receiver = Arc::new(AsyncMutex::new(context.event.subscribe()));
+            let (handlers, weak_context) = (Arc::downgrade(self), Arc::downgrade(context));
+            *dispatcher = Some(context.supervisor.supervise("event handlers", move || {
+                dispatch(handlers.clone(), weak_context.clone(), receiver.clone())
+            }));
+        }
+    }
+
+    fn handlers_for(&self, type_id: TypeId) -> Vec<ErasedHandler> {
+        self.handlers
+            .read()
+            .expect("RwLock poisoned")
+            .iter()
+            .filter(|registration| registration.type_id == type_id)
+            .map(|registration| registration.handler.clone())
+            .collect()
+    }
+
+    fn remove(&self, id: u64) {
+        self.handlers.write().expect("RwLock poisoned").retain(|registration| registration.id != id);
+    }
+}
+
+impl Drop for HandlerContext {
+    fn drop(&mut self) {
+        if let Some(dispatcher) = self.dispatcher.get_mut().expect("Mutex poisoned").take() {
+            dispatcher.abort();
+        }
+    }
+}
+
+async fn dispatch(
+    handlers: Weak<HandlerContext>,
+    context: Weak<BotContext>,
+    receiver: Arc<AsyncMutex<broadcast::Receiver<EventMessage>>>,
+) {
+    let mut receiver = receiver.lock().await;
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "Event handlers fell behind, events were dropped");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let (Some(handlers), Some(context)) = (handlers.upgrade(), context.upgrade()) else {
+            return;
+        };
+
+        for handler in handlers.handlers_for(event.type_id()) {
+            // Each handler runs as its own task so a panic stays contained
+            if let Err(e) = tokio::spawn(handler(context.clone(), event.clone())).await {
+                if e.is_panic() {
+                    tracing::error!(error = %e, "Event handler panicked");
+                    context.supervisor.record_handler_panic();
+                }
+            }
+        }
+    }
+}
+
+/// Keeps a handler registered; it is removed when the guard is dropped
+#[must_use = "the handler is removed when the guard is dropped"]
+pub struct HandlerGuard {
+    handlers: Weak<HandlerContext>,
+    id: Option<u64>,
+}
+
+impl HandlerGuard {
+    /// Keep the handler for as long as the bot lives
+    pub fn detach(mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for HandlerGuard {
+    fn drop(&mut self) {
+        if let (Some(id), Some(handlers)) = (self.id, self.handlers.upgrade()) {
+            handlers.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ProtocolEvent;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[derive(Debug)]
+    struct TestEvent(u32);
+
+    impl ProtocolEvent for TestEvent {}
+
+    #[derive(Debug)]
+    struct OtherEvent;
+
+    impl ProtocolEvent for OtherEvent {}
+
+    #[tokio::test]
+    async fn test_async_handler() {
+        let context = BotContext::builder().build();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let _guard = context.on::<TestEvent, _, _>(move |_, event| {
+            let sender = sender.clone();
+            async move {
+                tokio::task::yield_now().await;
+                sender.send(event.0).unwrap();
+            }
+        });
+
+        context.post(OtherEvent);
+        context.post(TestEvent(1));
+        context.post(TestEvent(2));
+        assert_eq!(receiver.recv().await, Some(1));
+        assert_eq!(receiver.recv().await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_guard_drop_unsubscribes() {
+        let context = BotContext::builder().build();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let dropped = {
+            let sender = sender.clone();
+            context.on_sync::<TestEvent, _>(move |_, event| sender.send(("dropped", event.0)).unwrap())
+        };
+        let _kept = context.on_sync::<TestEvent, _>(move |_, event| sender.send(("kept", event.0)).unwrap());
+
+        context.post(TestEvent(1));
+        assert_eq!(receiver.recv().await, Some(("dropped", 1)));
+        assert_eq!(receiver.recv().await, Some(("kept", 1)));
+
+        drop(dropped);
+        context.post(TestEvent(2));
+        assert_eq!(receiver.recv().await, Some(("kept", 2)));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_panic_isolation() {
+        let context = BotContext::builder().build();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let _panics = context.on_sync::<TestEvent, _>(|_, event| {
+            if event.0 == 1 {
+                panic!("handler failure");
+            }
+        });
+        let _records = context.on_sync::<TestEvent, _>(move |_, event| sender.send(event.0).unwrap());
+
+        context.post(TestEvent(1));
+        context.post(TestEvent(2));
+        assert_eq!(receiver.recv().await, Some(1));
+        assert_eq!(receiver.recv().await, Some(2));
+        assert_eq!(context.supervisor.stats().handler_panics, 1);
+        assert_eq!(context.supervisor.stats().restarts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_registration_order() {
+        let context = BotContext::builder().build();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let first = sender.clone();
+        // The slow handler still finishes before the next one starts
+        let _slow = context.on::<TestEvent, _, _>(move |_, event| {
+            let sender = first.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                sender.send(("slow", event.0)).unwrap();
+            }
+        });
+        let _fast = context.on_sync::<TestEvent, _>(move |_, event| sender.send(("fast", event.0)).unwrap());
+
+        context.post(TestEvent(1));
+        context.post(TestEvent(2));
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            order.push(receiver.recv().await.unwrap());
+        }
+        assert_eq!(order, [("slow", 1), ("fast", 1), ("slow", 2), ("fast", 2)]);
+    }
+}
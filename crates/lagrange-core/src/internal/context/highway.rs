@@ -0,0 +1,759 @@
+use super::transport::connect_tcp;
+use crate::config::SocketOptions;
+use crate::internal::packets::highway::{
+    decode_frame_header, encode_frame, DataHighwayHead, LoginSigHead, ReqDataHighwayHead,
+    RespDataHighwayHead, SegHead, FRAME_END, FRAME_HEADER_SIZE,
+};
+use bytes::Bytes;
+use lagrange_proto::ProtoMessage;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinSet;
+
+/// Upper bound for the head and body of a highway response
+const MAX_RESPONSE_LENGTH: usize = 1024 * 1024;
+
+/// Called with the [`UploadStatus`] whenever more of the file is acknowledged
+pub type UploadProgress = Arc<dyn Fn(UploadStatus) + Send + Sync>;
+
+/// How much of a file the server has, reported to [`UploadProgress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadStatus {
+    /// Bytes the server already had, acknowledged by an interrupted attempt and skipped now
+    pub resumed: u64,
+    /// Bytes acknowledged by this attempt
+    pub uploaded: u64,
+    pub size: u64,
+}
+
+impl UploadStatus {
+    pub fn acknowledged(&self) -> u64 {
+        self.resumed + self.uploaded
+    }
+}
+
+/// Remembers how far interrupted uploads got, so that retrying them skips the acknowledged
+/// part, see [`HighwayUploader::resume`]. Files are keyed by their md5.
+///
+/// [`MemoryResumeStore`] forgets everything with the process; implement this trait on top of a
+/// file to resume uploads across restarts.
+pub trait UploadResumeStore: Send + Sync + fmt::Debug {
+    /// Offset up to which every byte of the file was acknowledged
+    fn load(&self, file_md5: &[u8; 16]) -> Option<u64>;
+
+    fn save(&self, file_md5: &[u8; 16], offset: u64);
+
+    /// Forget the file once it is uploaded
+    fn remove(&self, file_md5: &[u8; 16]);
+}
+
+/// The [`UploadResumeStore`] used by default
+#[derive(Debug, Default)]
+pub struct MemoryResumeStore {
+    offsets: std::sync::Mutex<HashMap<[u8; 16], u64>>,
+}
+
+impl UploadResumeStore for MemoryResumeStore {
+    fn load(&self, file_md5: &[u8; 16]) -> Option<u64> {
+        self.offsets.lock().expect("Mutex poisoned").get(file_md5).copied()
+    }
+
+    fn save(&self, file_md5: &[u8; 16], offset: u64) {
+        self.offsets.lock().expect("Mutex poisoned").insert(*file_md5, offset);
+    }
+
+    fn remove(&self, file_md5: &[u8; 16]) {
+        self.offsets.lock().expect("Mutex poisoned").remove(file_md5);
+    }
+}
+
+/// Ticket and servers for highway uploads, valid until the next login
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HighwaySession {
+    pub sig_session: Vec<u8>,
+    pub session_key: Vec<u8>,
+    /// `host:port` of the upload servers, best first
+    pub servers: Vec<String>,
+}
+
+/// Holds the highway session fetched after login and the progress of interrupted uploads
+pub struct HighwayContext {
+    session: std::sync::RwLock<Option<HighwaySession>>,
+    resume: std::sync::RwLock<Arc<dyn UploadResumeStore>>,
+}
+
+impl HighwayContext {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            session: std::sync::RwLock::new(None),
+            resume: std::sync::RwLock::new(Arc::new(MemoryResumeStore::default())),
+        })
+    }
+
+    pub fn resume_store(&self) -> Arc<dyn UploadResumeStore> {
+        self.resume.read().expect("RwLock poisoned").clone()
+    }
+
+    /// Keep the progress of uploads in `store` instead of memory
+    pub fn set_resume_store(&self, store: Arc<dyn UploadResumeStore>) {
+        *self.resume.write().expect("RwLock poisoned") = store;
+    }
+
+    pub fn session(&self) -> Option<HighwaySession> {
+        self.session.read().expect("RwLock poisoned").clone()
+    }
+
+    pub fn set_session(&self, session: HighwaySession) {
+        *self.session.write().expect("RwLock poisoned") = Some(session);
+    }
+
+    pub fn clear(&self) {
+        *self.session.write().expect("RwLock poisoned") = None;
+    }
+}
+
+/// Uploads one file to a highway server as `PicUp.DataUp` frames.
+///
+/// The file is split into `chunk_size` chunks, and up to `concurrent` of them are in flight at
+/// once, each on its own connection. With a `resume` store, an upload that failed half-way
+/// continues after the part the server acknowledged when it is retried.
+#[derive(Debug, Clone)]
+pub struct HighwayUploader {
+    /// `host:port` of the highway servers; each connection goes to the first one that accepts it
+    pub servers: Vec<String>,
+    pub uin: u64,
+    pub app_id: u32,
+    pub login_sig: Vec<u8>,
+    /// `sig_session` of the highway session
+    pub ticket: Vec<u8>,
+    pub chunk_size: usize,
+    pub concurrent: usize,
+    pub resume: Option<Arc<dyn UploadResumeStore>>,
+    /// Options of the connections to the highway server
+    pub socket: SocketOptions,
+}
+
+struct Chunk {
+    offset: u64,
+    sequence: u32,
+    data: Bytes,
+}
+
+/// Sums the acknowledgements of all workers for the progress callback, and records up to where
+/// the file is acknowledged without gaps in the resume store
+struct ProgressTracker {
+    acknowledged: std::sync::Mutex<Acknowledged>,
+    file_md5: [u8; 16],
+    size: u64,
+    resumed: u64,
+    resume: Option<Arc<dyn UploadResumeStore>>,
+    callback: Option<UploadProgress>,
+}
+
+struct Acknowledged {
+    uploaded: u64,
+    /// Every byte before this offset is acknowledged
+    contiguous: u64,
+    /// Length of the acknowledged chunks after a gap, by offset
+    detached: BTreeMap<u64, u64>,
+}
+
+impl ProgressTracker {
+    fn new(
+        file_md5: [u8; 16],
+        size: u64,
+        resumed: u64,
+        resume: Option<Arc<dyn UploadResumeStore>>,
+        callback: Option<UploadProgress>,
+    ) -> Self {
+        Self {
+            acknowledged: std::sync::Mutex::new(Acknowledged {
+                uploaded: 0,
+                contiguous: resumed,
+                detached: BTreeMap::new(),
+            }),
+            file_md5,
+            size,
+            resumed,
+            resume,
+            callback,
+        }
+    }
+
+    fn status(&self, uploaded: u64) -> UploadStatus {
+        UploadStatus { resumed: self.resumed, uploaded, size: self.size }
+    }
+
+    fn advance(&self, offset: u64, length: u64) {
+        // Reported under the lock, so the callback never sees the total go backwards
+        let mut guard = self.acknowledged.lock().expect("Mutex poisoned");
+        let acknowledged = &mut *guard;
+        acknowledged.uploaded += length;
+        acknowledged.detached.insert(offset, length);
+
+        let previous = acknowledged.contiguous;
+        while let Some(length) = acknowledged.detached.remove(&acknowledged.contiguous) {
+            acknowledged.contiguous += length;
+        }
+        if let (Some(resume), true) = (&self.resume, acknowledged.contiguous > previous) {
+            resume.save(&self.file_md5, acknowledged.contiguous);
+        }
+        if let Some(callback) = &self.callback {
+            callback(self.status(acknowledged.uploaded));
+        }
+    }
+}
+
+/// What a worker got acknowledged by the server
+#[derive(Default)]
+struct WorkerReport {
+    acknowledged: u64,
+    /// Extension of the response to the last chunk of the file, if this worker sent it
+    final_extend_info: Option<Vec<u8>>,
+}
+
+impl HighwayUploader {
+    /// Upload `data` and return the extension of the final response.
+    pub async fn upload(&self, command_id: u32, data: &[u8], extend_info: &[u8]) -> crate::error::Result<Vec<u8>> {
+        let file_md5 = md5::compute(data).0;
+        let mut reader = data;
+        self.upload_stream(command_id, &mut reader, data.len() as u64, file_md5, extend_info, None)
+            .await
+    }
+
+    /// Upload `size` bytes read from `reader` and return the extension of the final response.
+    ///
+    /// Every frame carries the md5 of the whole file, so it has to be known up front. `progress`
+    /// is called after every acknowledged chunk, and once before the first one if the upload is
+    /// resumed. The bytes of `reader` before the resumed offset are skipped.
+    pub async fn upload_stream<R>(
+        &self,
+        command_id: u32,
+        reader: &mut R,
+        size: u64,
+        file_md5: [u8; 16],
+        extend_info: &[u8],
+        progress: Option<UploadProgress>,
+    ) -> crate::error::Result<Vec<u8>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        if size == 0 {
+            return Err(crate::error::Error::BuildError("Cannot upload an empty file".to_string()));
+        }
+
+        let chunk_size = self.chunk_size.max(1);
+        let concurrent = self.concurrent.max(1);
+        let template = Arc::new(self.head_template(command_id, size, file_md5, extend_info));
+
+        // A file acknowledged up to its end is sent again, the extension comes with the final response
+        let resumed = self
+            .resume
+            .as_ref()
+            .and_then(|resume| resume.load(&file_md5))
+            .filter(|&offset| offset < size)
+            .unwrap_or(0);
+        if resumed > 0 {
+            tracing::info!(resumed, size, "Resuming highway upload");
+            let skipped = tokio::io::copy(&mut (&mut *reader).take(resumed), &mut tokio::io::sink()).await?;
+            if skipped != resumed {
+                return Err(crate::error::Error::network(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "File ended before the resumed offset",
+                ));
+            }
+        }
+
+        let progress = Arc::new(ProgressTracker::new(file_md5, size, resumed, self.resume.clone(), progress));
+        if let (Some(callback), true) = (&progress.callback, resumed > 0) {
+            callback(progress.status(0));
+        }
+
+        let (chunk_tx, chunk_rx) = mpsc::channel::<Chunk>(concurrent);
+        let chunk_rx = Arc::new(Mutex::new(chunk_rx));
+        let mut workers = JoinSet::new();
+        for _ in 0..concurrent {
+            workers.spawn(upload_worker(
+                self.servers.clone(),
+                self.socket.clone(),
+                template.clone(),
+                size,
+                chunk_rx.clone(),
+                progress.clone(),
+            ));
+        }
+        drop(chunk_rx);
+
+        let produce = async move {
+            let mut offset = resumed;
+            let mut sequence = (resumed / chunk_size as u64) as u32;
+            while offset < size {
+                let length = (size - offset).min(chunk_size as u64) as usize;
+                let mut data = vec![0; length];
+                reader.read_exact(&mut data).await?;
+
+                let chunk = Chunk { offset, sequence, data: Bytes::from(data) };
+                if chunk_tx.send(chunk).await.is_err() {
+                    // Every worker is gone, the one that failed reports why
+                    break;
+                }
+                offset += length as u64;
+                sequence += 1;
+            }
+            Ok::<_, crate::error::Error>(())
+        };
+
+        let collect = async move {
+            let mut acknowledged = 0;
+            let mut final_extend_info = None;
+            while let Some(joined) = workers.join_next().await {
+                let report = joined
+                    .map_err(|e| anyhow::Error::new(e).context("Highway worker failed"))??;
+                acknowledged += report.acknowledged;
+                final_extend_info = final_extend_info.or(report.final_extend_info);
+            }
+            Ok::<_, crate::error::Error>((acknowledged, final_extend_info))
+        };
+
+        let (produced, collected) = tokio::join!(produce, collect);
+        let (acknowledged, final_extend_info) = collected?;
+        produced?;
+
+        if resumed + acknowledged != size {
+            return Err(crate::error::Error::ProtocolError(format!(
+                "Highway acknowledged {} of {} bytes",
+                resumed + acknowledged,
+                size
+            )));
+        }
+        let final_extend_info = final_extend_info.ok_or_else(|| {
+            crate::error::Error::ProtocolError("Highway did not answer the final chunk".to_string())
+        })?;
+        if let Some(resume) = &self.resume {
+            resume.remove(&file_md5);
+        }
+        Ok(final_extend_info)
+    }
+
+    /// Head shared by all chunks, the sequence and segment position are filled per chunk
+    fn head_template(&self, command_id: u32, size: u64, file_md5: [u8; 16], extend_info: &[u8]) -> ReqDataHighwayHead {
+        ReqDataHighwayHead {
+            base_head: Some(DataHighwayHead {
+                version: 1,
+                uin: Some(self.uin.to_string()),
+                command: Some("PicUp.DataUp".to_string()),
+                retry_times: Some(0),
+                app_id: Some(self.app_id),
+                data_flag: Some(16),
+                command_id: Some(command_id),
+                locale_id: Some(2052),
+                ..Default::default()
+            }),
+            seg_head: Some(SegHead {
+                file_size: Some(size),
+                service_ticket: Some(self.ticket.clone()),
+                file_md5: Some(file_md5.to_vec()),
+                ..Default::default()
+            }),
+            req_extend_info: Some(extend_info.to_vec()),
+            timestamp: 0,
+            login_sig_head: Some(LoginSigHead {
+                login_sig_type: 8,
+                login_sig: Some(self.login_sig.clone()),
+                app_id: self.app_id,
+            }),
+        }
+    }
+}
+
+/// Connect to the first of `servers` that accepts, in order
+async fn connect_first(servers: &[String], socket: &SocketOptions) -> crate::error::Result<TcpStream> {
+    let mut last_error = None;
+    for server in servers {
+        match connect_tcp(server, socket).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                tracing::debug!(%server, error = %e, "Highway server unreachable, trying the next one");
+                last_error = Some(crate::error::Error::network(
+                    e.kind(),
+                    format!("Failed to connect to highway {}: {}", server, e),
+                ));
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| crate::error::Error::ProtocolError("No highway server to upload to".to_string())))
+}
+
+/// Send chunks from the shared queue until it is drained.
+///
+/// The connection is only opened once there is a chunk to send, so small files do not open
+/// `concurrent` connections.
+async fn upload_worker(
+    servers: Vec<String>,
+    socket: SocketOptions,
+    template: Arc<ReqDataHighwayHead>,
+    size: u64,
+    chunks: Arc<Mutex<mpsc::Receiver<Chunk>>>,
+    progress: Arc<ProgressTracker>,
+) -> crate::error::Result<WorkerReport> {
+    let mut stream: Option<TcpStream> = None;
+    let mut report = WorkerReport::default();
+
+    loop {
+        let Some(chunk) = chunks.lock().await.recv().await else {
+            return Ok(report);
+        };
+        let stream = match &mut stream {
+            Some(stream) => stream,
+            None => stream.insert(connect_first(&servers, &socket).await?),
+        };
+
+        let mut head = (*template).clone();
+        if let Some(base_head) = head.base_head.as_mut() {
+            base_head.seq = Some(chunk.sequence);
+        }
+        if let Some(seg_head) = head.seg_head.as_mut() {
+            seg_head.data_offset = Some(chunk.offset);
+            seg_head.data_length = Some(chunk.data.len() as u32);
+            seg_head.md5 = Some(md5::compute(&chunk.data).0.to_vec());
+        }
+        let head = head
+            .encode_to_vec()
+            .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+        stream.write_all(&encode_frame(&head, &chunk.data)).await.map_err(crate::error::Error::Network)?;
+
+        let response = read_response(stream).await?;
+        if let Some(code @ 1..) = response.error_code {
+            return Err(crate::error::Error::ProtocolError(format!(
+                "Highway rejected the chunk at offset {} ({})",
+                chunk.offset, code
+            )));
+        }
+
+        report.acknowledged += chunk.data.len() as u64;
+        progress.advance(chunk.offset, chunk.data.len() as u64);
+        if chunk.offset + chunk.data.len() as u64 == size {
+            report.final_extend_info = Some(response.rsp_extend_info.unwrap_or_default());
+        }
+    }
+}
+
+async fn read_response(stream: &mut TcpStream) -> crate::error::Result<RespDataHighwayHead> {
+    let mut header = [0; FRAME_HEADER_SIZE];
+    stream.read_exact(&mut header).await.map_err(crate::error::Error::Network)?;
+    let (head_length, body_length) = decode_frame_header(&header)
+        .ok_or_else(|| crate::error::Error::ProtocolError("Invalid highway frame start".to_string()))?;
+    if head_length + body_length > MAX_RESPONSE_LENGTH {
+        return Err(crate::error::Error::ProtocolError(format!(
+            "Highway response of {} bytes is too large",
+            head_length + body_length
+        )));
+    }
+
+    let mut frame = vec![0; head_length + body_length + 1];
+    stream.read_exact(&mut frame).await.map_err(crate::error::Error::Network)?;
+    if frame.last() != Some(&FRAME_END) {
+        return Err(crate::error::Error::ProtocolError("Invalid highway frame end".to_string()));
+    }
+
+    RespDataHighwayHead::decode_from_slice(&frame[..head_length])
+        .map_err(|e| crate::error::Error::ParseError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    /// Everything the mock server saw, keyed by offset
+    #[derive(Default)]
+    struct Received {
+        chunks: BTreeMap<u64, Vec<u8>>,
+        /// Offset of every chunk in the order they arrived, including those never answered
+        sent: Vec<u64>,
+        file_md5s: Vec<Vec<u8>>,
+        tickets: Vec<Vec<u8>>,
+        connections: usize,
+    }
+
+    /// In-process highway server: checks each chunk md5, answers after a short delay and
+    /// records how many chunks were in flight at once
+    struct MockHighway {
+        server: String,
+        received: Arc<std::sync::Mutex<Received>>,
+        max_in_flight: Arc<AtomicUsize>,
+        /// Offset of the chunk upon which the connection is closed without an answer, once
+        drop_at_offset: Arc<std::sync::Mutex<Option<u64>>>,
+    }
+
+    impl MockHighway {
+        async fn start(file_size: u64, fail_at_offset: Option<u64>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let server = listener.local_addr().unwrap().to_string();
+            let received = Arc::new(std::sync::Mutex::new(Received::default()));
+            let max_in_flight = Arc::new(AtomicUsize::new(0));
+            let in_flight = Arc::new(AtomicUsize::new(0));
+            let drop_at_offset = Arc::new(std::sync::Mutex::new(None));
+
+            {
+                let received = received.clone();
+                let max_in_flight = max_in_flight.clone();
+                let drop_at_offset = drop_at_offset.clone();
+                tokio::spawn(async move {
+                    while let Ok((mut stream, _)) = listener.accept().await {
+                        received.lock().unwrap().connections += 1;
+                        let received = received.clone();
+                        let max_in_flight = max_in_flight.clone();
+                        let in_flight = in_flight.clone();
+                        let drop_at_offset = drop_at_offset.clone();
+                        tokio::spawn(async move {
+                            loop {
+                                let mut header = [0; FRAME_HEADER_SIZE];
+                                if stream.read_exact(&mut header).await.is_err() {
+                                    return;
+                                }
+                                let (head_length, body_length) = decode_frame_header(&header).unwrap();
+                                let mut frame = vec![0; head_length + body_length + 1];
+                                stream.read_exact(&mut frame).await.unwrap();
+                                assert_eq!(frame.last(), Some(&FRAME_END));
+
+                                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                                max_in_flight.fetch_max(current, Ordering::SeqCst);
+
+                                let head = ReqDataHighwayHead::decode_from_slice(&frame[..head_length]).unwrap();
+                                let body = &frame[head_length..head_length + body_length];
+                                let seg = head.seg_head.unwrap();
+                                let offset = seg.data_offset.unwrap();
+                                assert_eq!(seg.data_length, Some(body.len() as u32));
+                                assert_eq!(seg.md5, Some(md5::compute(body).0.to_vec()));
+                                assert_eq!(seg.file_size, Some(file_size));
+                                assert_eq!(head.base_head.unwrap().command.as_deref(), Some("PicUp.DataUp"));
+                                received.lock().unwrap().sent.push(offset);
+                                let dropped = {
+                                    let mut drop_at_offset = drop_at_offset.lock().unwrap();
+                                    drop_at_offset.take_if(|drop_at| *drop_at == offset).is_some()
+                                };
+                                if dropped {
+                                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                                    return;
+                                }
+                                {
+                                    let mut received = received.lock().unwrap();
+                                    received.chunks.insert(offset, body.to_vec());
+                                    received.file_md5s.push(seg.file_md5.unwrap());
+                                    received.tickets.push(seg.service_ticket.unwrap());
+                                }
+
+                                tokio::time::sleep(Duration::from_millis(20)).await;
+                                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                                let is_final = offset + body.len() as u64 == file_size;
+                                let response = RespDataHighwayHead {
+                                    error_code: Some(if fail_at_offset == Some(offset) { 81 } else { 0 }),
+                                    rsp_extend_info: is_final.then(|| b"done".to_vec()),
+                                    ..Default::default()
+                                };
+                                let head = response.encode_to_vec().unwrap();
+                                if stream.write_all(&encode_frame(&head, &[])).await.is_err() {
+                                    return;
+                                }
+                            }
+                        });
+                    }
+                });
+            }
+
+            Self { server, received, max_in_flight, drop_at_offset }
+        }
+
+        fn uploader(&self, chunk_size: usize, concurrent: usize) -> HighwayUploader {
+            HighwayUploader {
+                servers: vec![self.server.clone()],
+                uin: 10000,
+                app_id: 1600001615,
+                login_sig: vec![0xA2; 8],
+                ticket: vec![0x51; 4],
+                chunk_size,
+                concurrent,
+                resume: None,
+                socket: SocketOptions::default(),
+            }
+        }
+    }
+
+    fn file(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_upload_slice() {
+        let data = file(10 * 1000 + 123);
+        let highway = MockHighway::start(data.len() as u64, None).await;
+
+        let extend_info = highway.uploader(1000, 4).upload(1004, &data, b"ext").await.unwrap();
+        assert_eq!(extend_info, b"done");
+
+        let received = highway.received.lock().unwrap();
+        let offsets: Vec<u64> = received.chunks.keys().copied().collect();
+        assert_eq!(offsets, (0..=10).map(|i| i * 1000).collect::<Vec<u64>>());
+        assert_eq!(received.chunks.values().flatten().copied().collect::<Vec<u8>>(), data);
+        assert_eq!(received.chunks[&10000].len(), 123);
+        assert!(received.file_md5s.iter().all(|md5| md5 == &md5::compute(&data).0));
+        assert!(received.tickets.iter().all(|ticket| ticket == &[0x51; 4]));
+
+        let max_in_flight = highway.max_in_flight.load(Ordering::SeqCst);
+        assert!(max_in_flight > 1, "chunks were sent one at a time");
+        assert!(max_in_flight <= 4, "{} chunks were in flight", max_in_flight);
+        assert!(received.connections <= 4);
+    }
+
+    #[tokio::test]
+    async fn test_upload_falls_back_to_next_server() {
+        let data = file(3000);
+        let highway = MockHighway::start(data.len() as u64, None).await;
+
+        // A port nothing listens on any more
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let mut uploader = highway.uploader(1000, 2);
+        uploader.servers.insert(0, closed.to_string());
+
+        let extend_info = uploader.upload(1004, &data, b"").await.unwrap();
+        assert_eq!(extend_info, b"done");
+        assert_eq!(highway.received.lock().unwrap().chunks.values().flatten().copied().collect::<Vec<u8>>(), data);
+
+        uploader.servers.truncate(1);
+        let result = uploader.upload(1004, &data, b"").await;
+        assert!(matches!(result, Err(crate::error::Error::Network(e)) if e.to_string().contains(&closed.to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_upload_stream() {
+        let data = file(5000);
+        let highway = MockHighway::start(data.len() as u64, None).await;
+
+        // Feed the uploader through a pipe, a few bytes at a time
+        let (mut writer, mut reader) = tokio::io::duplex(64);
+        let source = data.clone();
+        tokio::spawn(async move {
+            for piece in source.chunks(300) {
+                writer.write_all(piece).await.unwrap();
+            }
+        });
+
+        let extend_info = highway
+            .uploader(1024, 2)
+            .upload_stream(1004, &mut reader, data.len() as u64, md5::compute(&data).0, b"", None)
+            .await
+            .unwrap();
+        assert_eq!(extend_info, b"done");
+
+        let received = highway.received.lock().unwrap();
+        assert_eq!(received.chunks.len(), 5);
+        assert_eq!(received.chunks.values().flatten().copied().collect::<Vec<u8>>(), data);
+        assert!(highway.max_in_flight.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_progress() {
+        let data = file(3500);
+        let highway = MockHighway::start(data.len() as u64, None).await;
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let progress: UploadProgress = {
+            let reports = reports.clone();
+            Arc::new(move |status: UploadStatus| reports.lock().unwrap().push((status.acknowledged(), status.size)))
+        };
+        highway
+            .uploader(1000, 3)
+            .upload_stream(1004, &mut data.as_slice(), data.len() as u64, md5::compute(&data).0, b"", Some(progress))
+            .await
+            .unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 4);
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(reports.last(), Some(&(3500, 3500)));
+    }
+
+    #[tokio::test]
+    async fn test_single_connection_is_sequential() {
+        let data = file(3000);
+        let highway = MockHighway::start(data.len() as u64, None).await;
+
+        highway.uploader(1000, 1).upload(1004, &data, b"").await.unwrap();
+        assert_eq!(highway.max_in_flight.load(Ordering::SeqCst), 1);
+        assert_eq!(highway.received.lock().unwrap().connections, 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_chunk() {
+        let data = file(4000);
+        let highway = MockHighway::start(data.len() as u64, Some(2000)).await;
+
+        let result = highway.uploader(1000, 2).upload(1004, &data, b"").await;
+        assert!(matches!(result, Err(crate::Error::ProtocolError(message)) if message.contains("offset 2000")));
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_dropped_connection() {
+        let data = file(10 * 1000);
+        let file_md5 = md5::compute(&data).0;
+        let highway = MockHighway::start(data.len() as u64, None).await;
+        *highway.drop_at_offset.lock().unwrap() = Some(3000);
+
+        let store: Arc<dyn UploadResumeStore> = Arc::new(MemoryResumeStore::default());
+        let mut uploader = highway.uploader(1000, 1);
+        uploader.resume = Some(store.clone());
+
+        let result = uploader.upload(1004, &data, b"").await;
+        assert!(matches!(result, Err(crate::Error::Network(_))), "{:?}", result);
+        assert_eq!(store.load(&file_md5), Some(3000));
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let progress: UploadProgress = {
+            let reports = reports.clone();
+            Arc::new(move |status| reports.lock().unwrap().push(status))
+        };
+        let extend_info = uploader
+            .upload_stream(1004, &mut data.as_slice(), data.len() as u64, file_md5, b"", Some(progress))
+            .await
+            .unwrap();
+        assert_eq!(extend_info, b"done");
+
+        // The chunk at 3000 was never answered, so it is the first one sent again
+        let received = highway.received.lock().unwrap();
+        let mut expected = vec![0, 1000, 2000, 3000];
+        expected.extend((3..10).map(|i| i * 1000));
+        assert_eq!(received.sent, expected);
+        assert_eq!(received.chunks.values().flatten().copied().collect::<Vec<u8>>(), data);
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 8);
+        assert_eq!(reports[0], UploadStatus { resumed: 3000, uploaded: 0, size: 10000 });
+        assert_eq!(reports[7], UploadStatus { resumed: 3000, uploaded: 7000, size: 10000 });
+        assert_eq!(store.load(&file_md5), None);
+    }
+
+    #[tokio::test]
+    async fn test_resume_offset_is_contiguous() {
+        let store: Arc<dyn UploadResumeStore> = Arc::new(MemoryResumeStore::default());
+        let tracker = ProgressTracker::new([7; 16], 5000, 0, Some(store.clone()), None);
+
+        // Acknowledged out of order by concurrent workers
+        tracker.advance(1000, 1000);
+        assert_eq!(store.load(&[7; 16]), None);
+        tracker.advance(0, 1000);
+        assert_eq!(store.load(&[7; 16]), Some(2000));
+        tracker.advance(3000, 1000);
+        assert_eq!(store.load(&[7; 16]), Some(2000));
+        tracker.advance(2000, 1000);
+        assert_eq!(store.load(&[7; 16]), Some(4000));
+    }
+}
@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+/// A request to a web API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    /// Whether redirects are followed; cookies set by a redirecting response need `false`
+    pub follow_redirects: bool,
+}
+
+/// Headers and body of a response to an [`HttpRequest`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// Status code, like 200; redirects and 304 Not Modified are returned as responses
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+impl HttpResponse {
+    /// Values of all headers called `name`, ignoring case
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Value of the first header called `name`, ignoring case
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl HttpRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            method: HttpMethod::Get,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+            follow_redirects: true,
+        }
+    }
+
+    pub fn post(url: impl Into<String>, body: Vec<u8>) -> Self {
+        Self {
+            method: HttpMethod::Post,
+            url: url.into(),
+            headers: Vec::new(),
+            body: Some(body),
+            follow_redirects: true,
+        }
+    }
+
+    /// POST `fields` as `application/x-www-form-urlencoded`
+    pub fn post_form<'a>(url: impl Into<String>, fields: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(fields)
+            .finish();
+        Self::post(url, body.into_bytes()).header("Content-Type", "application/x-www-form-urlencoded")
+    }
+
+    /// Return the first response as is, even if it redirects
+    pub fn without_redirects(mut self) -> Self {
+        self.follow_redirects = false;
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Value of the first header called `name`, ignoring case
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// HTTP access for media downloads and web APIs, replaceable to mock the network in tests
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    /// Send `request`, failing on client and server error statuses
+    async fn send(&self, request: HttpRequest) -> crate::error::Result<HttpResponse>;
+
+    /// GET `url` and return the body, failing on statuses other than 2xx
+    async fn get(&self, url: &str) -> crate::error::Result<Bytes> {
+        Ok(self.send(HttpRequest::get(url)).await?.body)
+    }
+}
+
+/// [`HttpClient`] backed by reqwest
+#[derive(Debug, Clone)]
+pub struct ReqwestHttpClient {
+    client: reqwest::Client,
+    no_redirect_client: reqwest::Client,
+}
+
+impl Default for ReqwestHttpClient {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            no_redirect_client: reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for ReqwestHttpClient {
+    async fn send(&self, request: HttpRequest) -> crate::error::Result<HttpResponse> {
+        let method = match request.method {
+            HttpMethod::Get => reqwest::Method::GET,
+            HttpMethod::Post => reqwest::Method::POST,
+        };
+        let describe = |e: reqwest::Error| {
+            crate::error::Error::network(
+                std::io::ErrorKind::Other,
+                format!("{} {} failed: {}", method_name(request.method), request.url, e),
+            )
+        };
+
+        let client = if request.follow_redirects { &self.client } else { &self.no_redirect_client };
+        let mut builder = client.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body.clone() {
+            builder = builder.body(body);
+        }
+
+        let response = builder
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(describe)?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes().await.map_err(describe)?;
+        Ok(HttpResponse { status, headers, body })
+    }
+}
+
+fn method_name(method: HttpMethod) -> &'static str {
+    match method {
+        HttpMethod::Get => "GET",
+        HttpMethod::Post => "POST",
+    }
+}
+
+pub struct HttpContext {
+    client: Arc<dyn HttpClient>,
+}
+
+impl HttpContext {
+    pub fn new(client: Arc<dyn HttpClient>) -> Arc<Self> {
+        Arc::new(Self { client })
+    }
+
+    pub async fn get(&self, url: &str) -> crate::error::Result<Bytes> {
+        self.client.get(url).await
+    }
+
+    pub async fn send(&self, request: HttpRequest) -> crate::error::Result<HttpResponse> {
+        self.client.send(request).await
+    }
+}
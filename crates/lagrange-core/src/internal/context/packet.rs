@@ -2,13 +2,10 @@ use crate::{
     common::{sign::BoxedSignProvider, AppInfo, BotAppInfo},
     config::BotConfig,
     error::{Error, Result},
-    internal::packets::{
-        service_build_protocol_12, service_build_protocol_13, service_parse,
-        sso_build_protocol_12, sso_build_protocol_13, sso_parse,
-        SsoPacket, SsoSecureInfo,
-    },
+    internal::packets::{sso, SsoPacket, SsoSecureInfo},
     keystore::BotKeystore,
     protocol::{EncryptType, Protocols, RequestType},
+    utils::{common::to_hex, Redacted},
 };
 use bytes::Bytes;
 use dashmap::DashMap;
@@ -16,7 +13,21 @@ use std::sync::{
     atomic::{AtomicU32, Ordering},
     Arc, RwLock,
 };
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
+
+/// Upper bound of remembered sequences whose caller stopped waiting
+const MAX_ABANDONED: usize = 1024;
+
+/// Commands whose bodies carry credentials, such as web keys or highway session tickets
+const SENSITIVE_COMMANDS: &[&str] = &["OidbSvcTrpcTcp.0x102a_0", "OidbSvcTrpcTcp.0x102a_1", "HttpConn.0x6ff_501"];
+
+/// Prefixes of the login commands, whose bodies carry passwords, tickets and session keys
+const SENSITIVE_PREFIXES: &[&str] = &["wtlogin.", "trpc.login."];
+
+/// Whether bodies of `command` are redacted in packet dumps, see [`BotConfig::log_sensitive`]
+pub fn is_sensitive_command(command: &str) -> bool {
+    SENSITIVE_COMMANDS.contains(&command) || SENSITIVE_PREFIXES.iter().any(|prefix| command.starts_with(prefix))
+}
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct ServiceAttribute {
@@ -43,11 +54,18 @@ impl ServiceAttribute {
 pub struct PacketContext {
     sequence: AtomicU32,
     pending_tasks: DashMap<u32, oneshot::Sender<SsoPacket>>,
+    /// Sequences whose caller gave up (timeout or cancellation) before the response arrived
+    abandoned: DashMap<u32, String>,
+
+    push_tx: mpsc::UnboundedSender<SsoPacket>,
+    push_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<SsoPacket>>>,
 
     keystore: Arc<RwLock<BotKeystore>>,
     app_info: Arc<BotAppInfo>,
     protocol: Protocols,
     sign_provider: BoxedSignProvider,
+    verbose: bool,
+    log_sensitive: bool,
 }
 
 impl PacketContext {
@@ -56,23 +74,53 @@ impl PacketContext {
         app_info: Arc<BotAppInfo>,
         config: &BotConfig,
     ) -> Arc<Self> {
+        let (push_tx, push_rx) = mpsc::unbounded_channel();
         Arc::new(Self {
             sequence: AtomicU32::new(1),
             pending_tasks: DashMap::new(),
+            abandoned: DashMap::new(),
+            push_tx,
+            push_rx: std::sync::Mutex::new(Some(push_rx)),
             keystore,
             app_info,
             protocol: config.protocol,
             sign_provider: config.get_sign_provider(),
+            verbose: config.verbose,
+            log_sensitive: config.log_sensitive,
         })
     }
 
-    fn get_app_info(&self) -> &AppInfo {
-        match self.app_info.as_ref() {
-            BotAppInfo::Windows(info) | BotAppInfo::Linux(info) | BotAppInfo::MacOs(info) => info,
-            BotAppInfo::Android { info, .. } => info,
+    /// Log the body of `packet` at trace level if [`BotConfig::verbose`] is set
+    pub(crate) fn dump_body(&self, direction: &'static str, packet: &SsoPacket) {
+        if !tracing::enabled!(tracing::Level::TRACE) {
+            return;
+        }
+        if let Some(body) = self.body_dump(&packet.command, &packet.data) {
+            tracing::trace!(
+                direction,
+                command = %packet.command,
+                sequence = packet.sequence,
+                body = %body,
+                "Packet body"
+            );
         }
     }
 
+    /// Hex of `data` as dumped for `command`, redacted for sensitive commands
+    fn body_dump(&self, command: &str, data: &[u8]) -> Option<String> {
+        if !self.verbose {
+            None
+        } else if self.log_sensitive || !is_sensitive_command(command) {
+            Some(to_hex(data))
+        } else {
+            Some(Redacted(data).to_string())
+        }
+    }
+
+    fn get_app_info(&self) -> &AppInfo {
+        self.app_info.inner()
+    }
+
     pub fn next_sequence(&self) -> u32 {
         self.sequence.fetch_add(1, Ordering::Relaxed)
     }
@@ -85,9 +133,9 @@ impl PacketContext {
         attributes: Option<ServiceAttribute>,
     ) -> Result<SsoPacket> {
         let sequence = self.next_sequence();
-        let (tx, rx) = oneshot::channel();
-
-        self.pending_tasks.insert(sequence, tx);
+        let (rx, mut guard) = self.register_pending(sequence, &command);
+        // Fills in the `packet` span opened by the caller, see `EventContext::send_erased`
+        tracing::Span::current().record("sequence", sequence);
 
         let sso_packet = SsoPacket {
             command: command.clone(),
@@ -103,25 +151,57 @@ impl PacketContext {
             command = %command,
             "Sending packet and registering pending task"
         );
+        self.dump_body("outbound", &sso_packet);
 
         let encoded = self.encode_packet(&sso_packet, attributes).await?;
 
         socket.send(encoded).await?;
 
-        let response = rx.await.map_err(|_| {
+        let response = rx.await;
+        guard.completed = true;
+        let response = response.map_err(|_| {
             tracing::warn!(
                 sequence = sequence,
                 command = %command,
                 "Response channel closed, removing pending task"
             );
             self.pending_tasks.remove(&sequence);
-            Error::NetworkError("Response channel closed".to_string())
+            Error::network(std::io::ErrorKind::ConnectionAborted, "Response channel closed")
         })?;
 
         Ok(response)
     }
 
-    pub fn dispatch_packet(&self, packet: SsoPacket) -> Option<SsoPacket> {
+    /// Register a caller waiting for the response with `sequence`.
+    ///
+    /// Dropping the guard before the response arrived marks the sequence as abandoned, so a
+    /// late response is logged instead of being mistaken for a push.
+    fn register_pending(&self, sequence: u32, command: &str) -> (oneshot::Receiver<SsoPacket>, PendingGuard<'_>) {
+        let (tx, rx) = oneshot::channel();
+        self.pending_tasks.insert(sequence, tx);
+
+        let guard = PendingGuard {
+            context: self,
+            sequence,
+            command: command.to_string(),
+            completed: false,
+        };
+        (rx, guard)
+    }
+
+    /// Take the receiving end of the push queue.
+    ///
+    /// Frames whose sequence matches no pending request are server-initiated pushes and are
+    /// queued here in arrival order. Returns `None` if the receiver has already been taken.
+    pub fn take_push_receiver(&self) -> Option<mpsc::UnboundedReceiver<SsoPacket>> {
+        self.push_rx.lock().expect("Mutex poisoned").take()
+    }
+
+    /// Route an inbound packet by the sequence in its SSO header.
+    ///
+    /// Responses go to the caller waiting for that sequence, regardless of the order in which
+    /// they arrive. Unknown sequences are queued as pushes, see [`Self::take_push_receiver`].
+    pub fn dispatch_packet(&self, packet: SsoPacket) {
         let sequence = packet.sequence as u32;
 
         tracing::debug!(
@@ -148,23 +228,31 @@ impl PacketContext {
                 "Successfully matched and removed pending task"
             );
 
-            let _ = sender.send(packet);
-            None
-        } else {
-            // Collect all pending sequence numbers for debugging
-            let pending_sequences: Vec<u32> = self.pending_tasks.iter()
-                .map(|entry| *entry.key())
-                .collect();
-
+            if let Err(packet) = sender.send(packet) {
+                tracing::warn!(
+                    sequence = sequence,
+                    command = %packet.command,
+                    "Response arrived after its caller stopped waiting"
+                );
+            }
+        } else if let Some((_, command)) = self.abandoned.remove(&sequence) {
             tracing::warn!(
-                sequence_i32 = packet.sequence,
-                sequence_u32 = sequence,
+                sequence = sequence,
+                command = %packet.command,
+                request_command = %command,
+                data_len = packet.data.len(),
+                "Response arrived after its caller stopped waiting"
+            );
+        } else {
+            tracing::debug!(
+                sequence = packet.sequence,
                 command = %packet.command,
-                pending_tasks_count = self.pending_tasks.len(),
-                pending_sequences = ?pending_sequences,
-                "Failed to find pending task for sequence - packet will be routed to services"
+                "No pending request for sequence, routing packet as push"
             );
-            Some(packet)
+
+            if self.push_tx.send(packet).is_err() {
+                tracing::warn!(sequence = sequence, "Push receiver dropped, packet discarded");
+            }
         }
     }
 
@@ -176,50 +264,30 @@ impl PacketContext {
         let attrs = attributes.unwrap_or_default();
         let request_type = attrs.request_type.unwrap_or(RequestType::Simple);
 
-        match request_type {
-            RequestType::D2Auth => {
-                // Acquire lock for sec_info preparation, then drop it before await
-                let sec_info = self.get_secure_info(packet).await;
-
-                // Reacquire lock for encoding
-                let keystore = self.keystore.read().expect("RwLock poisoned");
-                let encrypt_type = attrs.encrypt_type.unwrap_or_else(|| {
-                    if keystore.sigs.d2_key.is_empty() {
-                        EncryptType::EncryptEmpty
-                    } else {
-                        EncryptType::EncryptD2Key
-                    }
-                });
-
-                let app_info = self.get_app_info();
-
-                let sso_frame = sso_build_protocol_12(&keystore, app_info, self.protocol, packet, sec_info.as_ref());
-                let service_frame = service_build_protocol_12(&keystore, sso_frame, encrypt_type);
-
-                Ok(Bytes::from(service_frame))
-            }
-            RequestType::Simple => {
-                let keystore = self.keystore.read().expect("RwLock poisoned");
-                let encrypt_type = attrs.encrypt_type.unwrap_or_else(|| {
-                    if keystore.sigs.d2_key.is_empty() {
-                        EncryptType::EncryptEmpty
-                    } else {
-                        EncryptType::EncryptD2Key
-                    }
-                });
-
-                let sso_frame = sso_build_protocol_13(&keystore, self.protocol, packet);
-
-                let service_frame = service_build_protocol_13(
-                    &keystore,
-                    packet.sequence,
-                    sso_frame.as_slice(),
-                    encrypt_type,
-                );
+        // Sign before taking the lock so it is not held across the await
+        let sec_info = match request_type {
+            RequestType::D2Auth => self.get_secure_info(packet).await,
+            RequestType::Simple => None,
+        };
 
-                Ok(Bytes::from(service_frame))
+        let keystore = self.keystore.read().expect("RwLock poisoned");
+        let encrypt_type = attrs.encrypt_type.unwrap_or_else(|| {
+            if keystore.sigs.d2_key.is_empty() {
+                EncryptType::EncryptEmpty
+            } else {
+                EncryptType::EncryptD2Key
             }
-        }
+        });
+
+        Ok(sso::build(
+            packet,
+            &keystore,
+            self.get_app_info(),
+            self.protocol,
+            request_type,
+            encrypt_type,
+            sec_info.as_ref(),
+        ))
     }
 
     async fn get_secure_info(&self, packet: &SsoPacket) -> Option<SsoSecureInfo> {
@@ -238,11 +306,112 @@ impl PacketContext {
     pub fn decode_packet(&self, data: Bytes) -> Result<SsoPacket> {
         let keystore = self.keystore.read().expect("RwLock poisoned");
 
-        let sso_data = service_parse(&keystore, &data)
-            .map_err(|e| Error::ParseError(format!("Service parse failed: {}", e)))?;
-        let packet = sso_parse(&sso_data)
-            .map_err(|e| Error::ParseError(format!("SSO parse failed: {}", e)))?;
+        Ok(sso::parse(data, &keystore)?)
+    }
+}
+
+/// Removes a pending request whose caller stopped waiting, see [`PacketContext::register_pending`]
+struct PendingGuard<'a> {
+    context: &'a PacketContext,
+    sequence: u32,
+    command: String,
+    completed: bool,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if self.completed || self.context.pending_tasks.remove(&self.sequence).is_none() {
+            return;
+        }
+
+        let abandoned = &self.context.abandoned;
+        if abandoned.len() >= MAX_ABANDONED {
+            abandoned.clear();
+        }
+        abandoned.insert(self.sequence, std::mem::take(&mut self.command));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet_context() -> Arc<PacketContext> {
+        let app_info = Arc::new(BotAppInfo::default());
+        let keystore = Arc::new(RwLock::new(BotKeystore::default()));
+        PacketContext::new(keystore, app_info, &BotConfig::default())
+    }
+
+    #[test]
+    fn test_body_dump() {
+        let config = BotConfig::builder().verbose(true).build();
+        let context = PacketContext::new(Default::default(), Default::default(), &config);
+        let key = [0x5a; 16];
+
+        assert_eq!(context.body_dump("MessageSvc.PbSendMsg", &key).unwrap(), "5a".repeat(16));
+        for command in ["wtlogin.login", "trpc.login.ecdh.EcdhService.SsoKeyExchange", "OidbSvcTrpcTcp.0x102a_0"] {
+            let dump = context.body_dump(command, &key).unwrap();
+            assert_eq!(dump, "<16 bytes 5a5a5a5a..>");
+        }
+
+        let config = BotConfig::builder().verbose(true).log_sensitive(true).build();
+        let context = PacketContext::new(Default::default(), Default::default(), &config);
+        assert_eq!(context.body_dump("wtlogin.login", &key).unwrap(), "5a".repeat(16));
+
+        // Bodies are not dumped at all unless verbose
+        assert_eq!(packet_context().body_dump("MessageSvc.PbSendMsg", &key), None);
+    }
+
+    fn response(sequence: u32, command: &str, data: &'static [u8]) -> SsoPacket {
+        SsoPacket::new(command.to_string(), Bytes::from_static(data), sequence as i32)
+    }
+
+    #[tokio::test]
+    async fn test_responses_in_reverse_order() {
+        let context = packet_context();
+        let mut push_rx = context.take_push_receiver().unwrap();
+
+        let sequences: Vec<u32> = (0..3).map(|_| context.next_sequence()).collect();
+        let (rx_0, _guard_0) = context.register_pending(sequences[0], "cmd.first");
+        let (rx_1, _guard_1) = context.register_pending(sequences[1], "cmd.second");
+        let (rx_2, _guard_2) = context.register_pending(sequences[2], "cmd.third");
+
+        context.dispatch_packet(response(sequences[2], "cmd.third", b"third"));
+        context.dispatch_packet(response(sequences[1], "cmd.second", b"second"));
+        context.dispatch_packet(response(sequences[0], "cmd.first", b"first"));
+
+        assert_eq!(rx_0.await.unwrap().data.as_ref(), b"first");
+        assert_eq!(rx_1.await.unwrap().data.as_ref(), b"second");
+        assert_eq!(rx_2.await.unwrap().data.as_ref(), b"third");
+        assert!(push_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_sequence_is_push() {
+        let context = packet_context();
+        let mut push_rx = context.take_push_receiver().unwrap();
+        assert!(context.take_push_receiver().is_none());
+
+        context.dispatch_packet(response(9999, "trpc.msg.olpush.OlPushService.MsgPush", b"push"));
+
+        let push = push_rx.try_recv().unwrap();
+        assert_eq!(push.command, "trpc.msg.olpush.OlPushService.MsgPush");
+        assert_eq!(push.data.as_ref(), b"push");
+    }
+
+    #[tokio::test]
+    async fn test_late_response_is_not_a_push() {
+        let context = packet_context();
+        let mut push_rx = context.take_push_receiver().unwrap();
+
+        let sequence = context.next_sequence();
+        let (rx, guard) = context.register_pending(sequence, "cmd.slow");
+        drop(rx);
+        drop(guard);
+        assert!(context.pending_tasks.is_empty());
 
-        Ok(packet)
+        context.dispatch_packet(response(sequence, "cmd.slow", b"late"));
+        assert!(push_rx.try_recv().is_err());
+        assert!(context.abandoned.is_empty());
     }
 }
@@ -0,0 +1,338 @@
+use crate::context::BotContext;
+use crate::error::Error;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::AbortHandle;
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+type BoxedFuture = Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
+type ErasedJob = Arc<dyn Fn(Arc<BotContext>) -> BoxedFuture + Send + Sync>;
+
+/// When a scheduled job runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobPolicy {
+    /// Only while the bot is online; the first run after going online again is immediate
+    WhileOnline,
+    /// Whether the bot is online or not, e.g. to reconnect it
+    Always,
+}
+
+/// What is known about a scheduled job, see [`JobHandle::status`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobStatus {
+    pub name: String,
+    pub interval: Duration,
+    pub policy: JobPolicy,
+    /// Completed runs, successful or not
+    pub runs: u64,
+    /// When the last run completed
+    pub last_run: Option<Instant>,
+    /// Error of the last run, `None` if it succeeded
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Runs {
+    count: u64,
+    last_run: Option<Instant>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug)]
+struct JobState {
+    name: String,
+    interval: Duration,
+    policy: JobPolicy,
+    runs: Mutex<Runs>,
+}
+
+impl JobState {
+    fn record(&self, result: Result<(), Error>) {
+        let error = result.err().map(|e| {
+            tracing::warn!(job = %self.name, error = %e, "Scheduled job failed");
+            e.to_string()
+        });
+        let mut runs = self.runs.lock().expect("Mutex poisoned");
+        runs.count += 1;
+        runs.last_run = Some(Instant::now());
+        runs.last_error = error;
+    }
+
+    fn status(&self) -> JobStatus {
+        let runs = self.runs.lock().expect("Mutex poisoned");
+        JobStatus {
+            name: self.name.clone(),
+            interval: self.interval,
+            policy: self.policy,
+            runs: runs.count,
+            last_run: runs.last_run,
+            last_error: runs.last_error.clone(),
+        }
+    }
+}
+
+struct ScheduledJob {
+    state: Arc<JobState>,
+    task: AbortHandle,
+}
+
+/// Periodic jobs of a [`BotContext`], see [`BotContext::schedule`].
+///
+/// Jobs are named; scheduling a job under a name in use replaces the previous one. A failed
+/// run is logged and recorded in the [`JobStatus`], the job keeps running. All jobs are
+/// cancelled by [`BotContext::shutdown`] or when the context is dropped.
+///
+/// The periodic work of the library runs here as well, e.g. the `token-refresh` job started by
+/// [`BotContext::start_token_refresh`]; jobs hold the context weakly, so none keeps it alive.
+pub struct SchedulerContext {
+    online: watch::Sender<bool>,
+    jobs: Mutex<HashMap<String, ScheduledJob>>,
+}
+
+impl SchedulerContext {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            online: watch::Sender::new(false),
+            jobs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Run `job` every `interval`, starting right away, until it is cancelled
+    pub(crate) fn schedule<F, Fut>(
+        &self,
+        context: &Arc<BotContext>,
+        name: impl Into<String>,
+        interval: Duration,
+        policy: JobPolicy,
+        job: F,
+    ) -> JobHandle
+    where
+        F: Fn(Arc<BotContext>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        let job: ErasedJob = Arc::new(move |context| Box::pin(job(context)));
+        let state = Arc::new(JobState {
+            name: name.into(),
+            interval,
+            policy,
+            runs: Mutex::new(Runs::default()),
+        });
+        let task = tokio::spawn(run(Arc::downgrade(context), state.clone(), self.online.subscribe(), job));
+
+        let handle = JobHandle { state: state.clone(), task: task.abort_handle() };
+        let replaced = self
+            .jobs
+            .lock()
+            .expect("Mutex poisoned")
+            .insert(state.name.clone(), ScheduledJob { state, task: task.abort_handle() });
+        if let Some(replaced) = replaced {
+            replaced.task.abort();
+        }
+        handle
+    }
+
+    /// Pause or resume the [`JobPolicy::WhileOnline`] jobs
+    pub(crate) fn set_online(&self, online: bool) {
+        self.online.send_if_modified(|current| std::mem::replace(current, online) != online);
+    }
+
+    /// Status of the job scheduled under `name`, `None` if there is none or it was cancelled
+    pub fn status(&self, name: &str) -> Option<JobStatus> {
+        let mut jobs = self.jobs.lock().expect("Mutex poisoned");
+        jobs.retain(|_, job| !job.task.is_finished());
+        jobs.get(name).map(|job| job.state.status())
+    }
+
+    /// Status of every scheduled job, by name
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        let mut jobs = self.jobs.lock().expect("Mutex poisoned");
+        jobs.retain(|_, job| !job.task.is_finished());
+        let mut statuses: Vec<_> = jobs.values().map(|job| job.state.status()).collect();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// Stop the job scheduled under `name`, `false` if there is none
+    pub fn cancel(&self, name: &str) -> bool {
+        let job = self.jobs.lock().expect("Mutex poisoned").remove(name);
+        job.map(|job| job.task.abort()).is_some()
+    }
+
+    /// Stop every job
+    pub fn cancel_all(&self) {
+        for (_, job) in self.jobs.lock().expect("Mutex poisoned").drain() {
+            job.task.abort();
+        }
+    }
+}
+
+impl Drop for SchedulerContext {
+    fn drop(&mut self) {
+        self.cancel_all();
+    }
+}
+
+async fn run(context: Weak<BotContext>, state: Arc<JobState>, mut online: watch::Receiver<bool>, job: ErasedJob) {
+    let while_online = state.policy == JobPolicy::WhileOnline;
+    let mut interval = time::interval(state.interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        if while_online && !*online.borrow_and_update() {
+            // The sender is dropped along with the context
+            if online.wait_for(|online| *online).await.is_err() {
+                return;
+            }
+            interval.reset_immediately();
+        }
+        interval.tick().await;
+        if while_online && !*online.borrow() {
+            continue;
+        }
+
+        let Some(context) = context.upgrade() else {
+            return;
+        };
+        state.record(job(context).await);
+    }
+}
+
+/// A job scheduled with [`BotContext::schedule`]; dropping the handle leaves the job running
+#[derive(Debug, Clone)]
+pub struct JobHandle {
+    state: Arc<JobState>,
+    task: AbortHandle,
+}
+
+impl JobHandle {
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    pub fn status(&self) -> JobStatus {
+        self.state.status()
+    }
+
+    /// Stop the job; a run in progress is aborted at its next await
+    pub fn cancel(&self) {
+        self.task.abort();
+    }
+
+    /// Whether the job was cancelled, replaced or its context dropped
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A job counting its runs
+    fn counter(context: &Arc<BotContext>, name: &str, policy: JobPolicy) -> (JobHandle, Arc<AtomicU32>) {
+        let runs = Arc::new(AtomicU32::new(0));
+        let counted = runs.clone();
+        let handle = context.scheduler.schedule(context, name, Duration::from_secs(10), policy, move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+            async { Ok(()) }
+        });
+        (handle, runs)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cadence() {
+        let context = BotContext::builder().build();
+        context.set_online(true);
+        let (handle, runs) = counter(&context, "tick", JobPolicy::WhileOnline);
+
+        // The first run is immediate, then one every 10 seconds
+        time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+
+        let status = handle.status();
+        assert_eq!(status.name, "tick");
+        assert_eq!(status.runs, 4);
+        assert_eq!(status.last_run, Some(Instant::now() - Duration::from_secs(1)));
+        assert_eq!(status.last_error, None);
+        assert_eq!(context.scheduler.statuses(), [status]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paused_while_offline() {
+        let context = BotContext::builder().build();
+        let (_, online_runs) = counter(&context, "online", JobPolicy::WhileOnline);
+        let (_, always_runs) = counter(&context, "always", JobPolicy::Always);
+
+        time::sleep(Duration::from_secs(25)).await;
+        assert_eq!(online_runs.load(Ordering::SeqCst), 0);
+        assert_eq!(always_runs.load(Ordering::SeqCst), 3);
+
+        // Resumes right away when the bot comes online
+        context.set_online(true);
+        time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(online_runs.load(Ordering::SeqCst), 1);
+        time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(online_runs.load(Ordering::SeqCst), 2);
+
+        context.set_online(false);
+        time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(online_runs.load(Ordering::SeqCst), 2);
+        assert_eq!(always_runs.load(Ordering::SeqCst), 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_errors_are_recorded() {
+        let context = BotContext::builder().build();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counted = runs.clone();
+        let handle = context.scheduler.schedule(&context, "flaky", Duration::from_secs(10), JobPolicy::Always, move |_| {
+            let run = counted.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match run {
+                    0 => Err(Error::LoggedOut),
+                    _ => Ok(()),
+                }
+            }
+        });
+
+        time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(handle.status().last_error.as_deref(), Some("Logged out"));
+        // Failures do not stop the job
+        time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(handle.status().runs, 2);
+        assert_eq!(handle.status().last_error, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancellation() {
+        let context = BotContext::builder().build();
+        let (first, first_runs) = counter(&context, "job", JobPolicy::Always);
+        time::sleep(Duration::from_secs(1)).await;
+
+        // The same name replaces the job
+        let (second, second_runs) = counter(&context, "job", JobPolicy::Always);
+        let (_, other_runs) = counter(&context, "other", JobPolicy::Always);
+        time::sleep(Duration::from_secs(15)).await;
+        assert!(first.is_finished());
+        assert_eq!(first_runs.load(Ordering::SeqCst), 1);
+        assert_eq!(second_runs.load(Ordering::SeqCst), 2);
+
+        second.cancel();
+        time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(context.scheduler.status("job"), None);
+        assert!(context.scheduler.status("other").is_some());
+
+        context.shutdown().await;
+        time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(other_runs.load(Ordering::SeqCst), 2);
+        assert_eq!(second_runs.load(Ordering::SeqCst), 2);
+        assert!(context.scheduler.statuses().is_empty());
+    }
+}
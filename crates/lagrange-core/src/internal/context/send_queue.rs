@@ -0,0 +1,225 @@
+use crate::config::SendQueueConfig;
+use crate::error::Error;
+use crate::message::MessageTarget;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tokio::time::{self, Instant};
+
+/// Messages waiting for one chat, the one being sent included
+struct TargetQueue {
+    /// Held while a message is sent; Tokio mutexes are fair, so waiters go in arrival order
+    turn: Arc<tokio::sync::Mutex<()>>,
+    depth: watch::Sender<usize>,
+}
+
+/// Orders and paces outgoing messages.
+///
+/// Messages to the same chat are sent one at a time, in the order they were handed in, so
+/// concurrent sends cannot overtake each other. Sends to different chats run concurrently but
+/// share one rate limit, as risk control counts the messages of the account rather than those
+/// of a chat. Disabled unless [`BotConfig::send_queue`](crate::config::BotConfig::send_queue)
+/// is set.
+pub struct SendQueueContext {
+    config: Option<SendQueueConfig>,
+    queues: Mutex<HashMap<MessageTarget, TargetQueue>>,
+    /// Earliest time the next message may go out
+    next_slot: Mutex<Option<Instant>>,
+}
+
+/// Counts a message as queued until it is dropped, whether it was sent or cancelled
+struct Ticket<'a> {
+    queue: &'a SendQueueContext,
+    target: MessageTarget,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut queues = self.queue.queues.lock().expect("Mutex poisoned");
+        if let Some(queue) = queues.get(&self.target) {
+            queue.depth.send_modify(|depth| *depth -= 1);
+            if *queue.depth.borrow() == 0 {
+                queues.remove(&self.target);
+            }
+        }
+    }
+}
+
+impl SendQueueContext {
+    pub fn new(config: Option<SendQueueConfig>) -> Arc<Self> {
+        Arc::new(Self { config, queues: Mutex::new(HashMap::new()), next_slot: Mutex::new(None) })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Run `send` once the messages queued before it for `target` are sent and the rate limit
+    /// allows another message.
+    ///
+    /// Fails with [`Error::SendQueueFull`] without running `send` if `target` has as many
+    /// messages queued as the configured capacity. Runs `send` right away if the queue is
+    /// disabled.
+    pub async fn run<F, Fut, T>(&self, target: MessageTarget, send: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let Some(config) = self.config else {
+            return send().await;
+        };
+
+        let (turn, _ticket) = self.enqueue(target, config.capacity)?;
+        let _turn = turn.lock().await;
+        self.pace(config).await;
+        send().await
+    }
+
+    /// Messages queued for `target`, the one being sent included
+    pub fn depth(&self, target: MessageTarget) -> usize {
+        let queues = self.queues.lock().expect("Mutex poisoned");
+        queues.get(&target).map_or(0, |queue| *queue.depth.borrow())
+    }
+
+    /// Messages queued over all chats, and the number of chats they go to
+    pub fn total_depth(&self) -> (usize, usize) {
+        let queues = self.queues.lock().expect("Mutex poisoned");
+        (queues.values().map(|queue| *queue.depth.borrow()).sum(), queues.len())
+    }
+
+    /// Wait until the messages queued for `target`, also those queued while waiting, are sent
+    pub async fn flush(&self, target: MessageTarget) {
+        let mut receiver = {
+            let queues = self.queues.lock().expect("Mutex poisoned");
+            match queues.get(&target) {
+                Some(queue) => queue.depth.subscribe(),
+                None => return,
+            }
+        };
+        // The sender is dropped together with the emptied queue
+        let _ = receiver.wait_for(|depth| *depth == 0).await;
+    }
+
+    fn enqueue(
+        &self,
+        target: MessageTarget,
+        capacity: usize,
+    ) -> Result<(Arc<tokio::sync::Mutex<()>>, Ticket<'_>), Error> {
+        let mut queues = self.queues.lock().expect("Mutex poisoned");
+        let queue = queues.entry(target).or_insert_with(|| TargetQueue {
+            turn: Arc::new(tokio::sync::Mutex::new(())),
+            depth: watch::channel(0).0,
+        });
+        if *queue.depth.borrow() >= capacity {
+            return Err(Error::SendQueueFull { target, capacity });
+        }
+        queue.depth.send_modify(|depth| *depth += 1);
+        Ok((queue.turn.clone(), Ticket { queue: self, target }))
+    }
+
+    /// Reserve the next free send slot and sleep until it comes
+    async fn pace(&self, config: SendQueueConfig) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().expect("Mutex poisoned");
+            let slot = next_slot.map_or_else(Instant::now, |next| next.max(Instant::now()));
+            *next_slot = Some(slot + config.interval());
+            slot
+        };
+        time::sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn queue(capacity: usize) -> Arc<SendQueueContext> {
+        SendQueueContext::new(Some(SendQueueConfig { capacity, interval_ms: 100 }))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_order_and_pacing() {
+        let queue = queue(8);
+        let start = Instant::now();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+
+        let mut tasks = Vec::new();
+        for i in 0..3u64 {
+            for target in [MessageTarget::Group(1), MessageTarget::Friend(2)] {
+                let (queue, sent) = (queue.clone(), sent.clone());
+                tasks.push(tokio::spawn(async move {
+                    queue
+                        .run(target, || async {
+                            let started = start.elapsed();
+                            // Later sends of the chat must not overtake a slow one
+                            time::sleep(Duration::from_millis(if i == 0 { 250 } else { 10 })).await;
+                            sent.lock().unwrap().push((target, i, started));
+                            Ok(())
+                        })
+                        .await
+                }));
+            }
+        }
+        tokio::task::yield_now().await;
+        assert_eq!(queue.depth(MessageTarget::Group(1)), 3);
+        assert_eq!(queue.total_depth(), (6, 2));
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        let sent = sent.lock().unwrap().clone();
+        for target in [MessageTarget::Group(1), MessageTarget::Friend(2)] {
+            let order: Vec<u64> = sent.iter().filter(|(t, ..)| *t == target).map(|(_, i, _)| *i).collect();
+            assert_eq!(order, vec![0, 1, 2]);
+        }
+
+        // Sends start at least 100ms apart whatever chat they go to
+        let mut started: Vec<Duration> = sent.iter().map(|(.., at)| *at).collect();
+        started.sort();
+        assert_eq!(started[0], Duration::ZERO);
+        assert!(started.windows(2).all(|pair| pair[1] - pair[0] >= Duration::from_millis(100)));
+        assert_eq!(queue.total_depth(), (0, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queue_full() {
+        let queue = queue(2);
+        let target = MessageTarget::Group(1);
+
+        let mut tasks = Vec::new();
+        for _ in 0..2 {
+            let queue = queue.clone();
+            tasks.push(tokio::spawn(async move {
+                queue.run(target, || async {
+                    time::sleep(Duration::from_secs(1)).await;
+                    Ok(())
+                })
+                .await
+            }));
+        }
+        tokio::task::yield_now().await;
+
+        let result = queue.run(target, || async { Ok(()) }).await;
+        assert!(matches!(result, Err(Error::SendQueueFull { capacity: 2, .. })));
+        // Other chats are not affected
+        queue.run(MessageTarget::Group(2), || async { Ok(()) }).await.unwrap();
+
+        queue.flush(target).await;
+        assert_eq!(queue.depth(target), 0);
+        for task in tasks {
+            assert!(task.is_finished());
+            task.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disabled() {
+        let queue = SendQueueContext::new(None);
+        assert!(!queue.is_enabled());
+        let value = queue.run(MessageTarget::Friend(1), || async { Ok(7) }).await.unwrap();
+        assert_eq!(value, 7);
+        assert_eq!(queue.total_depth(), (0, 0));
+    }
+}
@@ -1,14 +1,11 @@
-use bytes::{BufMut, Bytes, BytesMut};
+use super::transport::Transport;
+use bytes::Bytes;
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 
-const IPV4_SERVER: &str = "msfwifi.3g.qq.com:8080";
-const IPV6_SERVER: &str = "msfwifiv6.3g.qq.com:8080";
-const HEADER_SIZE: usize = 4;
-
+/// Queues outgoing frames and runs the read and write loops over the [`Transport`]
 pub struct SocketContext {
+    transport: Arc<dyn Transport>,
     outbound_tx: tokio::sync::RwLock<mpsc::UnboundedSender<Bytes>>,
     connected: tokio::sync::RwLock<bool>,
     read_task: tokio::sync::Mutex<Option<tokio::task::AbortHandle>>,
@@ -16,9 +13,10 @@ pub struct SocketContext {
 }
 
 impl SocketContext {
-    pub fn new() -> Arc<Self> {
+    pub fn new(transport: Arc<dyn Transport>) -> Arc<Self> {
         let (tx, _rx) = mpsc::unbounded_channel();
         Arc::new(Self {
+            transport,
             outbound_tx: tokio::sync::RwLock::new(tx),
             connected: tokio::sync::RwLock::new(false),
             read_task: tokio::sync::Mutex::new(None),
@@ -31,7 +29,15 @@ impl SocketContext {
             .read()
             .await
             .send(data)
-            .map_err(|_| crate::error::Error::NetworkError("Socket closed".to_string()))
+            .map_err(|_| crate::error::Error::network(std::io::ErrorKind::NotConnected, "Socket closed"))
+    }
+
+    /// Route outgoing frames to the returned receiver instead of the server
+    #[cfg(test)]
+    pub(crate) async fn attach_test_channel(&self) -> mpsc::UnboundedReceiver<Bytes> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *self.outbound_tx.write().await = tx;
+        rx
     }
 
     async fn set_connected(&self, connected: bool) {
@@ -44,29 +50,27 @@ impl SocketContext {
 
     pub async fn connect(
         self: &Arc<Self>,
-        use_ipv6: bool,
         packet_ctx: Arc<super::PacketContext>,
+        supervisor: &Arc<super::SupervisorContext>,
     ) -> crate::error::Result<()> {
         self.disconnect().await;
 
         let (tx, rx) = mpsc::unbounded_channel();
         *self.outbound_tx.write().await = tx;
 
-        let server = if use_ipv6 { IPV6_SERVER } else { IPV4_SERVER };
-        let stream = TcpStream::connect(server)
-            .await
-            .map_err(|e| crate::error::Error::NetworkError(format!("Failed to connect: {}", e)))?;
-
-        let (read_half, write_half) = stream.into_split();
+        self.transport.connect().await?;
         self.set_connected(true).await;
 
         let read_task = {
-            let packet_ctx = packet_ctx.clone();
+            // A restarted read loop carries on with the frame the transport is in the middle of
             let socket_ctx = Arc::clone(self);
 
-            tokio::spawn(async move {
-                if let Err(e) = Self::read_loop(read_half, packet_ctx, socket_ctx).await {
-                    tracing::error!(error = %e, "Socket read loop terminated");
+            supervisor.supervise("socket reader", move || {
+                let (packet_ctx, socket_ctx) = (packet_ctx.clone(), socket_ctx.clone());
+                async move {
+                    if let Err(e) = Self::read_loop(packet_ctx, socket_ctx).await {
+                        tracing::error!(error = %e, "Socket read loop terminated");
+                    }
                 }
             })
         };
@@ -75,7 +79,7 @@ impl SocketContext {
             let socket_ctx = Arc::clone(self);
 
             tokio::spawn(async move {
-                if let Err(e) = Self::write_loop(write_half, rx, socket_ctx).await {
+                if let Err(e) = Self::write_loop(rx, socket_ctx).await {
                     tracing::error!(error = %e, "Socket write loop terminated");
                 }
             })
@@ -88,117 +92,83 @@ impl SocketContext {
     }
 
     async fn read_loop(
-        mut reader: tokio::net::tcp::OwnedReadHalf,
         packet_ctx: Arc<super::PacketContext>,
         socket_ctx: Arc<SocketContext>,
     ) -> crate::error::Result<()> {
-        let mut header_buf = [0u8; HEADER_SIZE];
-
         loop {
-            match reader.read_exact(&mut header_buf).await {
-                Ok(_) => {}
+            match socket_ctx.transport.read_frame().await {
+                Ok(frame) => Self::handle_frame(frame, &packet_ctx),
                 Err(e) => {
                     socket_ctx.set_connected(false).await;
 
-                    if e.kind() == std::io::ErrorKind::UnexpectedEof
-                        || e.kind() == std::io::ErrorKind::ConnectionReset
-                        || e.kind() == std::io::ErrorKind::ConnectionAborted {
-                        tracing::info!("Connection closed");
-                    } else {
-                        tracing::error!(error = %e, "Failed to read header");
+                    match &e {
+                        crate::error::Error::Network(io)
+                            if matches!(
+                                io.kind(),
+                                std::io::ErrorKind::UnexpectedEof
+                                    | std::io::ErrorKind::ConnectionReset
+                                    | std::io::ErrorKind::ConnectionAborted
+                            ) =>
+                        {
+                            tracing::info!(error = %e, "Connection closed");
+                        }
+                        _ => tracing::error!(error = %e, "Failed to read from socket, resetting connection"),
                     }
 
-                    return Err(crate::error::Error::NetworkError(format!(
-                        "Failed to read header: {}",
-                        e
-                    )));
+                    return Err(e);
                 }
             }
+        }
+    }
 
-            let length = u32::from_be_bytes(header_buf) as usize;
-            let mut data = BytesMut::zeroed(length - 4);
+    fn handle_frame(data: Bytes, packet_ctx: &super::PacketContext) {
+        let size = data.len();
+        tracing::debug!(size = size, "Received packet");
 
-            match reader.read_exact(&mut data).await {
-                Ok(_) => {}
-                Err(e) => {
-                    socket_ctx.set_connected(false).await;
-                    return Err(crate::error::Error::NetworkError(format!(
-                        "Failed to read packet: {}",
-                        e
-                    )));
-                }
-            }
+        match packet_ctx.decode_packet(data) {
+            Ok(packet) => {
+                tracing::debug!(command = %packet.command, sequence = packet.sequence, data_len = packet.data.len(), ret_code = packet.ret_code, "Decoded packet");
 
-            let data_frozen = data.freeze();
-            let hex = data_frozen.iter().map(|b| format!("{:02x}", b)).collect::<String>();
-            tracing::debug!(
-                size = data_frozen.len(),
-                hex = %hex,
-                "Received packet"
-            );
-
-            match packet_ctx.decode_packet(data_frozen) {
-                Ok(packet) => {
-                    tracing::debug!(command = %packet.command, sequence = packet.sequence, data_len = packet.data.len(), ret_code = packet.ret_code, "Decoded packet");
-
-                    let command = packet.command.clone();
-                    let sequence = packet.sequence;
-
-                    if let Some(packet) = packet_ctx.dispatch_packet(packet) {
-                        tracing::debug!(command = %packet.command, sequence = packet.sequence, "Packet routed to services");
-                        drop(packet);
-                    } else {
-                        tracing::debug!(command = %command, sequence = sequence, "Packet matched to pending request");
-                    }
-                }
-                Err(e) => {
-                    tracing::error!(error = %e,size = length - 4, "Failed to decode packet");
-                }
+                packet_ctx.dump_body("inbound", &packet);
+                packet_ctx.dispatch_packet(packet);
+            }
+            Err(e) => {
+                tracing::error!(error = %e, size = size, "Failed to decode packet");
             }
         }
     }
 
     async fn write_loop(
-        mut writer: tokio::net::tcp::OwnedWriteHalf,
         mut outbound_rx: mpsc::UnboundedReceiver<Bytes>,
         socket_ctx: Arc<SocketContext>,
     ) -> crate::error::Result<()> {
         while let Some(data) = outbound_rx.recv().await {
-            let length = data.len() as u32;
-            let mut buffer = BytesMut::with_capacity(HEADER_SIZE + data.len());
-            buffer.put_u32(length + HEADER_SIZE as u32);
-            buffer.put(data);
-
-            let hex = buffer.iter().map(|b| format!("{:02x}", b)).collect::<String>();
-            tracing::debug!(
-                size = buffer.len(),
-                hex = %hex,
-                "Sending packet"
-            );
-
-            match writer.write_all(&buffer).await {
-                Ok(_) => {
-                    tracing::debug!(
-                        size = buffer.len(),
-                        "Packet sent successfully"
-                    );
+            let size = data.len();
+            tracing::debug!(size, "Sending packet");
+
+            match socket_ctx.transport.write_frame(data).await {
+                Ok(()) => {
+                    tracing::debug!(size, "Packet sent successfully");
                 }
                 Err(e) => {
                     socket_ctx.set_connected(false).await;
 
-                    if e.kind() == std::io::ErrorKind::UnexpectedEof
-                        || e.kind() == std::io::ErrorKind::ConnectionReset
-                        || e.kind() == std::io::ErrorKind::ConnectionAborted
-                        || e.kind() == std::io::ErrorKind::BrokenPipe {
-                        tracing::info!("Connection closed while writing");
-                    } else {
-                        tracing::error!(error = %e, "Failed to write packet");
+                    match &e {
+                        crate::error::Error::Network(io)
+                            if matches!(
+                                io.kind(),
+                                std::io::ErrorKind::UnexpectedEof
+                                    | std::io::ErrorKind::ConnectionReset
+                                    | std::io::ErrorKind::ConnectionAborted
+                                    | std::io::ErrorKind::BrokenPipe
+                            ) =>
+                        {
+                            tracing::info!("Connection closed while writing");
+                        }
+                        _ => tracing::error!(error = %e, "Failed to write packet"),
                     }
 
-                    return Err(crate::error::Error::NetworkError(format!(
-                        "Failed to write packet: {}",
-                        e
-                    )));
+                    return Err(e);
                 }
             }
         }
@@ -215,6 +185,7 @@ impl SocketContext {
         if let Some(handle) = self.write_task.lock().await.take() {
             handle.abort();
         }
+        self.transport.close().await;
     }
 }
 
@@ -0,0 +1,231 @@
+use super::EventContext;
+use crate::common::ConnectionEvent;
+use std::any::Any;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+
+/// Crashes in a row after which a task is given up
+pub const MAX_TASK_RESTARTS: u32 = 5;
+
+/// Delay before the first restart, doubled for every further crash in a row
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A task that ran this long before it crashed is restarted as if it never crashed before
+const HEALTHY_RUN: Duration = Duration::from_secs(60);
+
+/// Counters of the [`SupervisorContext`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SupervisorStats {
+    /// Panics of supervised internal tasks
+    pub task_panics: u64,
+    /// Internal tasks restarted after a panic
+    pub restarts: u64,
+    /// Panics of handlers registered with [`BotContext::on`](crate::BotContext::on), which are
+    /// isolated and never restarted
+    pub handler_panics: u64,
+}
+
+/// Keeps the internal tasks of a [`BotContext`](crate::BotContext) alive: the socket reader,
+/// the push dispatcher and the event handler dispatcher.
+///
+/// A task that panics is logged and restarted after a backoff. After [`MAX_TASK_RESTARTS`]
+/// crashes in a row it is given up: the context is marked as failed and a
+/// [`ConnectionEvent::Fatal`] is posted, since the bot no longer processes what it receives.
+pub struct SupervisorContext {
+    event: Arc<EventContext>,
+    max_restarts: u32,
+    task_panics: AtomicU64,
+    restarts: AtomicU64,
+    handler_panics: AtomicU64,
+    failed: AtomicBool,
+}
+
+impl SupervisorContext {
+    pub fn new(event: Arc<EventContext>) -> Arc<Self> {
+        Self::with_max_restarts(event, MAX_TASK_RESTARTS)
+    }
+
+    fn with_max_restarts(event: Arc<EventContext>, max_restarts: u32) -> Arc<Self> {
+        Arc::new(Self {
+            event,
+            max_restarts,
+            task_panics: AtomicU64::new(0),
+            restarts: AtomicU64::new(0),
+            handler_panics: AtomicU64::new(0),
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn stats(&self) -> SupervisorStats {
+        SupervisorStats {
+            task_panics: self.task_panics.load(Ordering::Relaxed),
+            restarts: self.restarts.load(Ordering::Relaxed),
+            handler_panics: self.handler_panics.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether an internal task was given up after crashing too often
+    pub fn is_failed(&self) -> bool {
+        self.failed.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn record_handler_panic(&self) {
+        self.handler_panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Run the task made by `task` until it returns, making a new one whenever it panics.
+    ///
+    /// Aborting the returned handle also aborts the running task. Whatever the task must keep
+    /// across restarts, such as a receiver, has to live outside of it.
+    pub(crate) fn supervise<F, Fut>(self: &Arc<Self>, name: &'static str, task: F) -> JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let supervisor = self.clone();
+        tokio::spawn(async move {
+            let mut crashes = 0u32;
+            loop {
+                let started = Instant::now();
+                let mut running = AbortOnDrop(tokio::spawn(task()));
+                let payload = match (&mut running.0).await {
+                    Ok(()) => return,
+                    Err(e) if e.is_cancelled() => return,
+                    Err(e) => panic_message(e.into_panic()),
+                };
+
+                supervisor.task_panics.fetch_add(1, Ordering::Relaxed);
+                if started.elapsed() >= HEALTHY_RUN {
+                    crashes = 0;
+                }
+                crashes += 1;
+                if crashes > supervisor.max_restarts {
+                    tracing::error!(task = name, panic = %payload, crashes, "Internal task keeps crashing, giving up");
+                    supervisor.failed.store(true, Ordering::SeqCst);
+                    supervisor.event.post(ConnectionEvent::Fatal { task: name.to_string(), reason: payload });
+                    return;
+                }
+
+                let backoff = INITIAL_BACKOFF.saturating_mul(1 << (crashes - 1).min(16)).min(MAX_BACKOFF);
+                tracing::error!(task = name, panic = %payload, crashes, ?backoff, "Internal task panicked, restarting");
+                time::sleep(backoff).await;
+                supervisor.restarts.fetch_add(1, Ordering::Relaxed);
+            }
+        })
+    }
+}
+
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "non-string panic payload".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BotContext;
+    use std::sync::atomic::AtomicU32;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_restarts_crashed_task() {
+        let context = BotContext::builder().build();
+        let starts = Arc::new(AtomicU32::new(0));
+        let counted = starts.clone();
+        let task = context.supervisor.supervise("flaky", move || {
+            let start = counted.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if start < 2 {
+                    panic!("crash {}", start);
+                }
+            }
+        });
+
+        task.await.unwrap();
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(context.supervisor.stats(), SupervisorStats { task_panics: 2, restarts: 2, handler_panics: 0 });
+        assert!(!context.supervisor.is_failed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gives_up() {
+        let context = BotContext::builder().build();
+        let mut events = context.event.subscribe_to::<ConnectionEvent>();
+        let started = Instant::now();
+        let task = context.supervisor.supervise("broken", || async { panic!("always broken") });
+
+        task.await.unwrap();
+        let stats = context.supervisor.stats();
+        assert_eq!(stats.task_panics, MAX_TASK_RESTARTS as u64 + 1);
+        assert_eq!(stats.restarts, MAX_TASK_RESTARTS as u64);
+        assert!(context.supervisor.is_failed());
+        // Backoff of 1, 2, 4, 8 and 16 seconds
+        assert_eq!(started.elapsed(), Duration::from_secs(31));
+        assert_eq!(
+            *events.recv().await.unwrap(),
+            ConnectionEvent::Fatal { task: "broken".to_string(), reason: "always broken".to_string() }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_healthy_run_resets_crashes() {
+        let context = BotContext::builder().build();
+        let supervisor = SupervisorContext::with_max_restarts(context.event.clone(), 1);
+        let starts = Arc::new(AtomicU32::new(0));
+        let counted = starts.clone();
+        let task = supervisor.supervise("long-lived", move || {
+            let start = counted.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match start {
+                    0..=2 => {
+                        time::sleep(HEALTHY_RUN).await;
+                        panic!("crash after a while");
+                    }
+                    _ => panic!("crash right away"),
+                }
+            }
+        });
+
+        task.await.unwrap();
+        // Crashes after a healthy run are forgiven, a second crash right after one is not
+        assert_eq!(starts.load(Ordering::SeqCst), 4);
+        assert_eq!(supervisor.stats().restarts, 3);
+        assert!(supervisor.is_failed());
+    }
+
+    #[tokio::test]
+    async fn test_abort_stops_task() {
+        let context = BotContext::builder().build();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<()>();
+        let task = context.supervisor.supervise("pending", move || {
+            let sender = sender.clone();
+            async move {
+                let _sender = sender;
+                std::future::pending::<()>().await;
+            }
+        });
+        tokio::task::yield_now().await;
+
+        task.abort();
+        // The sender of the running task is dropped with it
+        assert_eq!(receiver.recv().await, None);
+    }
+}
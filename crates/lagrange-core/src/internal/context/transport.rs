@@ -0,0 +1,261 @@
+use crate::config::{BotConfig, SocketOptions};
+use crate::error::{Error, Result};
+use crate::internal::packets::frame::{FrameDecoder, HEADER_SIZE};
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::sync::Mutex;
+
+const IPV4_SERVER: &str = "msfwifi.3g.qq.com:8080";
+const IPV6_SERVER: &str = "msfwifiv6.3g.qq.com:8080";
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The connection to the SSO server, replaceable to run the bot against an in-memory server
+/// in tests, see [`MemoryTransport`](crate::testing::MemoryTransport).
+///
+/// Frames are service packets as built by [`sso::build`](crate::internal::sso::build), without
+/// the length header of the wire format. A frame may be read while another one is written.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Open a new connection, replacing the current one
+    async fn connect(&self) -> Result<()>;
+
+    /// Wait for the next frame of the server. Fails once the connection ended; frames that
+    /// arrived in parts are kept until they are complete, so reading can be resumed after the
+    /// future was dropped.
+    async fn read_frame(&self) -> Result<Bytes>;
+
+    async fn write_frame(&self, frame: Bytes) -> Result<()>;
+
+    /// Close the connection, a no-op if it is not open
+    async fn close(&self);
+}
+
+/// Read half of the connection with the frame it is in the middle of
+struct InboundStream {
+    reader: OwnedReadHalf,
+    decoder: FrameDecoder,
+    buffer: BytesMut,
+}
+
+/// [`Transport`] over TCP, with the length-prefixed frames of the SSO server
+pub struct TcpTransport {
+    server: String,
+    max_frame_length: usize,
+    options: SocketOptions,
+    inbound: Mutex<Option<InboundStream>>,
+    writer: Mutex<Option<OwnedWriteHalf>>,
+}
+
+impl TcpTransport {
+    pub fn new(config: &BotConfig) -> Self {
+        Self {
+            server: if config.use_ipv6_network { IPV6_SERVER } else { IPV4_SERVER }.to_string(),
+            max_frame_length: config.max_frame_length,
+            options: config.socket.clone(),
+            inbound: Mutex::new(None),
+            writer: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for TcpTransport {
+    async fn connect(&self) -> Result<()> {
+        self.close().await;
+
+        let stream = connect_tcp(&self.server, &self.options).await.map_err(Error::Network)?;
+        let (reader, writer) = stream.into_split();
+        *self.inbound.lock().await = Some(InboundStream {
+            reader,
+            decoder: FrameDecoder::new(self.max_frame_length),
+            buffer: BytesMut::with_capacity(READ_BUFFER_SIZE),
+        });
+        *self.writer.lock().await = Some(writer);
+        Ok(())
+    }
+
+    async fn read_frame(&self) -> Result<Bytes> {
+        let mut inbound = self.inbound.lock().await;
+        let InboundStream { reader, decoder, buffer } = inbound
+            .as_mut()
+            .ok_or_else(|| Error::network(std::io::ErrorKind::NotConnected, "Socket closed"))?;
+
+        loop {
+            match decoder.decode() {
+                Ok(Some(frame)) => return Ok(frame),
+                Ok(None) => {}
+                Err(e) => {
+                    // The stream is out of sync, the connection monitor will reconnect
+                    return Err(Error::network(
+                        std::io::ErrorKind::InvalidData,
+                        format!("Invalid inbound frame: {}", e),
+                    ));
+                }
+            }
+
+            buffer.clear();
+            if reader.read_buf(buffer).await.map_err(Error::Network)? == 0 {
+                tracing::info!(buffered = decoder.buffered(), "Connection closed");
+                return Err(Error::network(std::io::ErrorKind::UnexpectedEof, "Connection closed by server"));
+            }
+            decoder.extend(buffer);
+        }
+    }
+
+    async fn write_frame(&self, frame: Bytes) -> Result<()> {
+        let mut writer = self.writer.lock().await;
+        let writer = writer
+            .as_mut()
+            .ok_or_else(|| Error::network(std::io::ErrorKind::NotConnected, "Socket closed"))?;
+
+        let mut buffer = BytesMut::with_capacity(HEADER_SIZE + frame.len());
+        buffer.put_u32((frame.len() + HEADER_SIZE) as u32);
+        buffer.put(frame);
+        writer.write_all(&buffer).await.map_err(Error::Network)
+    }
+
+    async fn close(&self) {
+        if let Some(mut writer) = self.writer.lock().await.take() {
+            let _ = writer.shutdown().await;
+        }
+        self.inbound.lock().await.take();
+    }
+}
+
+/// Connect to `server`, a `host:port`, with `options` applied before the handshake so the
+/// buffer sizes also shape the TCP window. Every resolved address is tried in turn.
+pub(crate) async fn connect_tcp(server: &str, options: &SocketOptions) -> std::io::Result<TcpStream> {
+    let mut last_error = None;
+    for addr in tokio::net::lookup_host(server).await? {
+        let result = async {
+            let socket = configured_socket(addr, options)?;
+            socket.connect(addr).await
+        };
+        match result.await {
+            Ok(stream) => {
+                tracing::debug!(server, %addr, ?options, "Connected with socket options");
+                return Ok(stream);
+            }
+            Err(e) => {
+                tracing::debug!(server, %addr, error = %e, "Failed to connect");
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} resolved to no address", server))
+    }))
+}
+
+fn configured_socket(addr: SocketAddr, options: &SocketOptions) -> std::io::Result<TcpSocket> {
+    let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    socket.set_nodelay(options.nodelay)?;
+    if let Some(keepalive) = &options.keepalive {
+        let mut params = socket2::TcpKeepalive::new().with_time(keepalive.idle());
+        if let Some(interval) = keepalive.interval() {
+            params = params.with_interval(interval);
+        }
+        socket2::SockRef::from(&socket).set_tcp_keepalive(&params)?;
+    }
+    if let Some(size) = options.send_buffer {
+        socket.set_send_buffer_size(size as u32)?;
+    }
+    if let Some(size) = options.recv_buffer {
+        socket.set_recv_buffer_size(size as u32)?;
+    }
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeepaliveConfig;
+    use socket2::SockRef;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    async fn connect_local(options: &SocketOptions) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let (stream, accepted) = tokio::join!(connect_tcp(&server, options), listener.accept());
+        accepted.unwrap();
+        stream.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_options_are_applied() {
+        let options = SocketOptions {
+            nodelay: true,
+            keepalive: Some(KeepaliveConfig { idle_secs: 45, interval_secs: Some(5) }),
+            send_buffer: Some(256 * 1024),
+            recv_buffer: Some(128 * 1024),
+        };
+        let stream = connect_local(&options).await;
+        let socket = SockRef::from(&stream);
+
+        assert!(socket.tcp_nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(socket.tcp_keepalive_time().unwrap(), Duration::from_secs(45));
+        // The kernel may round the buffers up, e.g. Linux doubles them for its bookkeeping
+        assert!(socket.send_buffer_size().unwrap() >= 256 * 1024);
+        assert!(socket.recv_buffer_size().unwrap() >= 128 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_default_options() {
+        let options = SocketOptions::default();
+        assert!(options.nodelay);
+        assert_eq!(options.keepalive, None);
+        assert_eq!((options.send_buffer, options.recv_buffer), (None, None));
+
+        let stream = connect_local(&options).await;
+        let socket = SockRef::from(&stream);
+        assert!(socket.tcp_nodelay().unwrap());
+        assert!(!socket.keepalive().unwrap());
+
+        let stream = connect_local(&SocketOptions { nodelay: false, ..Default::default() }).await;
+        assert!(!SockRef::from(&stream).tcp_nodelay().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_framing() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = BotConfig { max_frame_length: 64, ..Default::default() };
+        let server = listener.local_addr().unwrap().to_string();
+        let transport = TcpTransport { server, ..TcpTransport::new(&config) };
+        let (connected, accepted) = tokio::join!(transport.connect(), listener.accept());
+        connected.unwrap();
+        let (mut server, _) = accepted.unwrap();
+
+        transport.write_frame(Bytes::from_static(b"ping")).await.unwrap();
+        let mut sent = [0; 8];
+        server.read_exact(&mut sent).await.unwrap();
+        assert_eq!(&sent, b"\0\0\0\x08ping");
+
+        // A frame split across writes is reassembled
+        server.write_all(b"\0\0\0\x09po").await.unwrap();
+        let reading = transport.read_frame();
+        server.write_all(b"ng!").await.unwrap();
+        assert_eq!(reading.await.unwrap().as_ref(), b"pong!");
+
+        server.write_all(&[0, 0, 1, 0]).await.unwrap();
+        let invalid = transport.read_frame().await;
+        assert!(matches!(invalid, Err(Error::Network(e)) if e.kind() == std::io::ErrorKind::InvalidData));
+
+        transport.close().await;
+        assert!(transport.write_frame(Bytes::from_static(b"late")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        assert!(connect_tcp(&server, &SocketOptions::default()).await.is_err());
+    }
+}
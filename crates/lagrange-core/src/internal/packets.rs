@@ -1,9 +1,9 @@
+pub mod frame;
+pub mod highway;
 pub mod login;
+pub mod message;
+pub mod oidb;
+pub mod sso;
 pub mod structs;
 
-pub use structs::{
-    service_packer::{service_build_protocol_12, service_build_protocol_13, service_parse},
-    sso_packer::{sso_build_protocol_12, sso_build_protocol_13, sso_parse},
-    sso_packet::SsoPacket,
-    sso_secure_info::SsoSecureInfo,
-};
+pub use structs::{sso_packet::SsoPacket, sso_secure_info::SsoSecureInfo};
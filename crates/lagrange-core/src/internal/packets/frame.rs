@@ -0,0 +1,165 @@
+use bytes::{Buf, Bytes, BytesMut};
+
+/// Size of the big-endian length prefix in front of every frame. The length counts itself.
+pub const HEADER_SIZE: usize = 4;
+
+/// Default upper bound for a single inbound frame
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Errors produced while splitting the inbound byte stream into frames.
+///
+/// Both variants mean the stream is out of sync and the connection has to be reset.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FrameError {
+    #[error("Frame length {0} is smaller than its header")]
+    TooShort(usize),
+
+    #[error("Frame length {length} exceeds the limit of {max} bytes")]
+    TooLarge { length: usize, max: usize },
+}
+
+/// Reassembles length-prefixed frames from arbitrary TCP reads.
+///
+/// Feed every read with [`FrameDecoder::extend`], then call [`FrameDecoder::decode`] until it
+/// returns `Ok(None)`: a read may carry a partial frame, exactly one, or several glued together.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buffer: BytesMut,
+    max_length: usize,
+}
+
+impl FrameDecoder {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            buffer: BytesMut::with_capacity(8 * 1024),
+            max_length,
+        }
+    }
+
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Bytes received but not yet returned as a frame
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the payload of the next complete frame, without its length prefix
+    pub fn decode(&mut self) -> Result<Option<Bytes>, FrameError> {
+        if self.buffer.len() < HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let length = u32::from_be_bytes([self.buffer[0], self.buffer[1], self.buffer[2], self.buffer[3]]) as usize;
+        if length < HEADER_SIZE {
+            return Err(FrameError::TooShort(length));
+        }
+        if length > self.max_length {
+            return Err(FrameError::TooLarge { length, max: self.max_length });
+        }
+
+        if self.buffer.len() < length {
+            self.buffer.reserve(length - self.buffer.len());
+            return Ok(None);
+        }
+
+        let mut frame = self.buffer.split_to(length);
+        frame.advance(HEADER_SIZE);
+        Ok(Some(frame.freeze()))
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FRAME_LENGTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + HEADER_SIZE) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_split_at_every_boundary() {
+        let payload: Vec<u8> = (0..=255u8).collect();
+        let data = frame(&payload);
+
+        for split in 0..=data.len() {
+            let mut decoder = FrameDecoder::default();
+
+            decoder.extend(&data[..split]);
+            let first = decoder.decode().unwrap();
+            if split < data.len() {
+                assert_eq!(first, None, "split at {}", split);
+                decoder.extend(&data[split..]);
+                assert_eq!(decoder.decode().unwrap().as_deref(), Some(payload.as_slice()));
+            } else {
+                assert_eq!(first.as_deref(), Some(payload.as_slice()));
+            }
+
+            assert_eq!(decoder.decode().unwrap(), None);
+            assert_eq!(decoder.buffered(), 0);
+        }
+    }
+
+    #[test]
+    fn test_byte_by_byte() {
+        let data = frame(b"trickle");
+        let mut decoder = FrameDecoder::default();
+        let mut frames = Vec::new();
+
+        for byte in &data {
+            decoder.extend(std::slice::from_ref(byte));
+            while let Some(frame) = decoder.decode().unwrap() {
+                frames.push(frame);
+            }
+        }
+
+        assert_eq!(frames, vec![Bytes::from_static(b"trickle")]);
+    }
+
+    #[test]
+    fn test_coalesced_frames() {
+        let mut data = frame(b"first");
+        data.extend_from_slice(&frame(b"second"));
+        data.extend_from_slice(&frame(b"thi")[..5]);
+
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&data);
+
+        assert_eq!(decoder.decode().unwrap().as_deref(), Some(&b"first"[..]));
+        assert_eq!(decoder.decode().unwrap().as_deref(), Some(&b"second"[..]));
+        assert_eq!(decoder.decode().unwrap(), None);
+
+        decoder.extend(b"hi");
+        assert_eq!(decoder.decode().unwrap().as_deref(), Some(&b"thi"[..]));
+    }
+
+    #[test]
+    fn test_empty_payload() {
+        let mut decoder = FrameDecoder::default();
+        decoder.extend(&frame(&[]));
+        assert_eq!(decoder.decode().unwrap(), Some(Bytes::new()));
+    }
+
+    #[test]
+    fn test_absurd_lengths() {
+        let mut decoder = FrameDecoder::new(1024);
+        decoder.extend(&u32::MAX.to_be_bytes());
+        assert_eq!(
+            decoder.decode(),
+            Err(FrameError::TooLarge { length: u32::MAX as usize, max: 1024 })
+        );
+
+        let mut decoder = FrameDecoder::new(1024);
+        decoder.extend(&2u32.to_be_bytes());
+        assert_eq!(decoder.decode(), Err(FrameError::TooShort(2)));
+    }
+}
@@ -0,0 +1,191 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use lagrange_proto::{Fixed32, ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Marks the start of a highway frame
+pub const FRAME_START: u8 = 0x28;
+/// Marks the end of a highway frame
+pub const FRAME_END: u8 = 0x29;
+/// Start marker plus the big-endian lengths of head and body
+pub const FRAME_HEADER_SIZE: usize = 9;
+
+/// Lay out `0x28 | head length | body length | head | body | 0x29`
+pub fn encode_frame(head: &[u8], body: &[u8]) -> Bytes {
+    let mut frame = BytesMut::with_capacity(FRAME_HEADER_SIZE + head.len() + body.len() + 1);
+    frame.put_u8(FRAME_START);
+    frame.put_u32(head.len() as u32);
+    frame.put_u32(body.len() as u32);
+    frame.put_slice(head);
+    frame.put_slice(body);
+    frame.put_u8(FRAME_END);
+    frame.freeze()
+}
+
+/// Lengths of head and body announced by a frame header, `None` if the start marker is missing
+pub fn decode_frame_header(header: &[u8; FRAME_HEADER_SIZE]) -> Option<(usize, usize)> {
+    if header[0] != FRAME_START {
+        return None;
+    }
+    let head_length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    let body_length = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as usize;
+    Some((head_length, body_length))
+}
+
+/// Head of every frame sent to the highway
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ReqDataHighwayHead {
+    #[proto(tag = 1)]
+    pub base_head: Option<DataHighwayHead>,
+    #[proto(tag = 2)]
+    pub seg_head: Option<SegHead>,
+    /// Command-specific extension, e.g. the upload request of the media
+    #[proto(tag = 3)]
+    pub req_extend_info: Option<Vec<u8>>,
+    #[proto(tag = 4)]
+    pub timestamp: u64,
+    #[proto(tag = 5)]
+    pub login_sig_head: Option<LoginSigHead>,
+}
+
+/// Head of every frame answered by the highway
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct RespDataHighwayHead {
+    #[proto(tag = 1)]
+    pub base_head: Option<DataHighwayHead>,
+    #[proto(tag = 2)]
+    pub seg_head: Option<SegHead>,
+    #[proto(tag = 3)]
+    pub error_code: Option<u32>,
+    #[proto(tag = 4)]
+    pub allow_retry: Option<u32>,
+    #[proto(tag = 7)]
+    pub rsp_extend_info: Option<Vec<u8>>,
+    #[proto(tag = 8)]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct DataHighwayHead {
+    #[proto(tag = 1)]
+    pub version: u32,
+    #[proto(tag = 2)]
+    pub uin: Option<String>,
+    /// `PicUp.DataUp` for uploads
+    #[proto(tag = 3)]
+    pub command: Option<String>,
+    #[proto(tag = 4)]
+    pub seq: Option<u32>,
+    #[proto(tag = 5)]
+    pub retry_times: Option<u32>,
+    #[proto(tag = 6)]
+    pub app_id: Option<u32>,
+    #[proto(tag = 7)]
+    pub data_flag: Option<u32>,
+    /// Kind of upload, e.g. group image or private record
+    #[proto(tag = 8)]
+    pub command_id: Option<u32>,
+    #[proto(tag = 10)]
+    pub locale_id: Option<u32>,
+}
+
+/// Position of the chunk carried by a frame
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SegHead {
+    #[proto(tag = 2)]
+    pub file_size: Option<u64>,
+    #[proto(tag = 3)]
+    pub data_offset: Option<u64>,
+    #[proto(tag = 4)]
+    pub data_length: Option<u32>,
+    #[proto(tag = 5)]
+    pub ret_code: Option<u32>,
+    /// `sig_session` of the highway session
+    #[proto(tag = 6)]
+    pub service_ticket: Option<Vec<u8>>,
+    #[proto(tag = 7)]
+    pub flag: Option<u32>,
+    /// Md5 of the chunk
+    #[proto(tag = 8)]
+    pub md5: Option<Vec<u8>>,
+    /// Md5 of the whole file
+    #[proto(tag = 9)]
+    pub file_md5: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LoginSigHead {
+    #[proto(tag = 1)]
+    pub login_sig_type: u32,
+    #[proto(tag = 2)]
+    pub login_sig: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    pub app_id: u32,
+}
+
+/// Body of `HttpConn.0x6ff_501`, asks for the highway servers and their ticket
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct HighwaySessionRequest {
+    #[proto(tag = 1281)]
+    pub body: Option<HighwaySessionRequestBody>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct HighwaySessionRequestBody {
+    #[proto(tag = 1)]
+    pub uin: u32,
+    #[proto(tag = 2)]
+    pub idc_id: u32,
+    #[proto(tag = 3)]
+    pub app_id: u32,
+    #[proto(tag = 4)]
+    pub login_sig_type: u32,
+    #[proto(tag = 7)]
+    pub request_flag: u32,
+    #[proto(tag = 8)]
+    pub service_types: Vec<u32>,
+    #[proto(tag = 9)]
+    pub field9: u32,
+    #[proto(tag = 10)]
+    pub field10: u32,
+    #[proto(tag = 11)]
+    pub field11: u32,
+    #[proto(tag = 15)]
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct HighwaySessionResponse {
+    #[proto(tag = 1281)]
+    pub body: Option<HighwaySessionResponseBody>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct HighwaySessionResponseBody {
+    #[proto(tag = 1)]
+    pub sig_session: Option<Vec<u8>>,
+    #[proto(tag = 2)]
+    pub session_key: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    pub server_lists: Vec<HighwayServerList>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct HighwayServerList {
+    /// 1 for the upload servers
+    #[proto(tag = 1)]
+    pub service_type: u32,
+    #[proto(tag = 2)]
+    pub addrs: Vec<HighwayAddr>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct HighwayAddr {
+    #[proto(tag = 1)]
+    pub ip_type: u32,
+    /// IPv4 address with the first octet in the lowest byte
+    #[proto(tag = 2)]
+    pub ip: Fixed32,
+    #[proto(tag = 3)]
+    pub port: u32,
+    #[proto(tag = 4)]
+    pub area: Option<u32>,
+}
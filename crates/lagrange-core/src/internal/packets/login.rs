@@ -1,8 +1,14 @@
+pub mod kick;
 pub mod qr_login_ext_info;
+pub mod register;
 pub mod tlv;
+pub mod tlv119;
 pub mod tlv_qrcode;
 pub mod tlv_writer;
 pub mod wtlogin;
 
-pub use qr_login_ext_info::{DevInfo, GenInfo, QrExtInfo, ScanExtInfo};
-pub use wtlogin::WtLogin;
+pub use kick::ServiceKickNt;
+pub use register::{
+    OnlineOsInfo, ServiceRegister, ServiceRegisterResponse, ServiceUnRegister, ServiceUnRegisterResponse, SetStatus,
+    SetStatusCustomExt, SetStatusResponse,
+};
@@ -0,0 +1,13 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `trpc.qq_new_tech.status_svc.StatusService.KickNT`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ServiceKickNt {
+    #[proto(tag = 1)]
+    pub uin: Option<u64>,
+    /// Explanation shown to the user, naming the device that took over
+    #[proto(tag = 3)]
+    pub tips: Option<String>,
+    #[proto(tag = 4)]
+    pub title: Option<String>,
+}
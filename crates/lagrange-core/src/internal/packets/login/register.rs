@@ -0,0 +1,132 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `trpc.qq_new_tech.status_svc.StatusService.Register`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ServiceRegister {
+    /// Upper case hex of the device guid
+    #[proto(tag = 1)]
+    pub guid: String,
+    #[proto(tag = 2)]
+    pub register_type: u32,
+    #[proto(tag = 3)]
+    pub current_version: String,
+    #[proto(tag = 4)]
+    pub field4: u32,
+    #[proto(tag = 5)]
+    pub locale_id: u32,
+    #[proto(tag = 6)]
+    pub online: Option<OnlineOsInfo>,
+    #[proto(tag = 7)]
+    pub set_mute: u32,
+    #[proto(tag = 8)]
+    pub register_vendor_type: u32,
+    #[proto(tag = 9)]
+    pub reg_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OnlineOsInfo {
+    /// Device name shown in the list of logged in devices
+    #[proto(tag = 1)]
+    pub user: String,
+    #[proto(tag = 2)]
+    pub os: String,
+    #[proto(tag = 3)]
+    pub os_version: String,
+    #[proto(tag = 4)]
+    pub vendor_name: Option<String>,
+    #[proto(tag = 5)]
+    pub os_lower: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ServiceRegisterResponse {
+    #[proto(tag = 2)]
+    pub message: Option<String>,
+    #[proto(tag = 3)]
+    pub timestamp: Option<u64>,
+}
+
+impl ServiceRegisterResponse {
+    /// `message` of a successful registration
+    pub const SUCCESS: &'static str = "register success";
+}
+
+/// Body of `trpc.qq_new_tech.status_svc.StatusService.UnRegister`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ServiceUnRegister {
+    #[proto(tag = 1)]
+    pub field1: u32,
+    #[proto(tag = 2)]
+    pub online: Option<OnlineOsInfo>,
+    /// 1 when the user asked to log out
+    #[proto(tag = 3)]
+    pub user_trigger: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ServiceUnRegisterResponse {
+    #[proto(tag = 2)]
+    pub message: Option<String>,
+}
+
+/// Body of `trpc.qq_new_tech.status_svc.StatusService.SetStatus`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SetStatus {
+    /// Always 10
+    #[proto(tag = 1)]
+    pub field1: u32,
+    #[proto(tag = 2)]
+    pub status: u32,
+    #[proto(tag = 3)]
+    pub ext_status: u32,
+    #[proto(tag = 4)]
+    pub custom_ext: Option<SetStatusCustomExt>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SetStatusCustomExt {
+    #[proto(tag = 1)]
+    pub face_id: u32,
+    #[proto(tag = 2)]
+    pub text: Option<String>,
+    /// Always 1
+    #[proto(tag = 3)]
+    pub field3: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SetStatusResponse {
+    #[proto(tag = 2)]
+    pub message: Option<String>,
+}
+
+impl SetStatusResponse {
+    /// `message` of an accepted status
+    pub const SUCCESS: &'static str = "set status success";
+}
+
+/// Proto in TLV 0x543 of a successful login, carrying the uid of the account
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct Tlv543 {
+    #[proto(tag = 9)]
+    pub layer1: Option<Tlv543Layer1>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct Tlv543Layer1 {
+    #[proto(tag = 11)]
+    pub layer2: Option<Tlv543Layer2>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct Tlv543Layer2 {
+    #[proto(tag = 1)]
+    pub uid: Option<String>,
+}
+
+impl Tlv543 {
+    pub fn uid(self) -> Option<String> {
+        self.layer1?.layer2?.uid.filter(|uid| !uid.is_empty())
+    }
+}
@@ -0,0 +1,293 @@
+//! TLV 0x119 of a successful wtlogin: the TLVs carrying the session keys, TEA-encrypted with
+//! the tgtgt key (or A1, when exchanging it for new tickets).
+
+use crate::error::{Error, Result};
+use crate::internal::packets::login::register::Tlv543;
+use crate::internal::packets::login::wtlogin::tea_key;
+use crate::utils::binary::{BinaryPacket, Prefix};
+use crate::utils::crypto::tea;
+use crate::utils::redact::{redact_values, Redacted};
+use crate::utils::tlv_unpack;
+use lagrange_proto::ProtoMessage;
+use std::collections::HashMap;
+use std::fmt;
+
+pub const TLV_119: u16 = 0x119;
+
+const TLV_ST_WEB: u16 = 0x103;
+const TLV_A1: u16 = 0x106;
+const TLV_KSID: u16 = 0x108;
+const TLV_A2: u16 = 0x10A;
+const TLV_A2_KEY: u16 = 0x10D;
+const TLV_ST_KEY: u16 = 0x10E;
+const TLV_ST: u16 = 0x114;
+const TLV_PROFILE: u16 = 0x11A;
+const TLV_S_KEY: u16 = 0x120;
+const TLV_WT_SESSION_TICKET: u16 = 0x133;
+const TLV_WT_SESSION_TICKET_KEY: u16 = 0x134;
+const TLV_LIFETIMES: u16 = 0x138;
+const TLV_D2: u16 = 0x143;
+const TLV_NO_PIC_SIG: u16 = 0x16A;
+const TLV_SUPER_KEY: u16 = 0x16D;
+const TLV_D2_KEY: u16 = 0x305;
+const TLV_PS_KEYS: u16 = 0x512;
+const TLV_UID: u16 = 0x543;
+
+/// Tags read elsewhere, which are not worth a log line
+const READ_ELSEWHERE: &[u16] = &[TLV_PROFILE];
+
+/// Keys and tickets of TLV 0x119; tags the server did not send are `None`. [`Debug`](fmt::Debug)
+/// shows them [`Redacted`]
+#[derive(Clone, Default, PartialEq)]
+pub struct SessionKeys {
+    pub a1: Option<Vec<u8>>,
+    /// A2, the ticket granting ticket
+    pub a2: Option<Vec<u8>>,
+    pub a2_key: Option<Vec<u8>>,
+    pub d2: Option<Vec<u8>>,
+    pub d2_key: Option<Vec<u8>>,
+    pub ksid: Option<Vec<u8>>,
+    pub super_key: Option<Vec<u8>>,
+    pub st: Option<Vec<u8>>,
+    pub st_key: Option<Vec<u8>>,
+    pub st_web: Option<Vec<u8>>,
+    pub s_key: Option<Vec<u8>>,
+    pub wt_session_ticket: Option<Vec<u8>>,
+    pub wt_session_ticket_key: Option<Vec<u8>>,
+    pub no_pic_sig: Option<Vec<u8>>,
+    /// Keys of the web services by domain, from TLV 0x512
+    pub ps_key: HashMap<String, Vec<u8>>,
+    /// Shortest lifetime of A2 and D2 in seconds, from TLV 0x138
+    pub lifetime: Option<u32>,
+    /// NT uid of the account, from the proto in TLV 0x543
+    pub uid: Option<String>,
+}
+
+impl fmt::Debug for SessionKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionKeys")
+            .field("a1", &self.a1.as_ref().map(Redacted))
+            .field("a2", &self.a2.as_ref().map(Redacted))
+            .field("a2_key", &self.a2_key.as_ref().map(Redacted))
+            .field("d2", &self.d2.as_ref().map(Redacted))
+            .field("d2_key", &self.d2_key.as_ref().map(Redacted))
+            .field("ksid", &self.ksid.as_ref().map(Redacted))
+            .field("super_key", &self.super_key.as_ref().map(Redacted))
+            .field("st", &self.st.as_ref().map(Redacted))
+            .field("st_key", &self.st_key.as_ref().map(Redacted))
+            .field("st_web", &self.st_web.as_ref().map(Redacted))
+            .field("s_key", &self.s_key.as_ref().map(Redacted))
+            .field("wt_session_ticket", &self.wt_session_ticket.as_ref().map(Redacted))
+            .field("wt_session_ticket_key", &self.wt_session_ticket_key.as_ref().map(Redacted))
+            .field("no_pic_sig", &self.no_pic_sig.as_ref().map(Redacted))
+            .field("ps_key", &redact_values(&self.ps_key))
+            .field("lifetime", &self.lifetime)
+            .field("uid", &self.uid)
+            .finish()
+    }
+}
+
+impl SessionKeys {
+    /// Maps the decrypted TLVs of 0x119, logging the tags it does not know
+    pub fn from_tlvs(tlvs: &HashMap<u16, Vec<u8>>) -> Self {
+        let mut keys = SessionKeys::default();
+        for (&tag, data) in tlvs {
+            let slot = match tag {
+                TLV_ST_WEB => &mut keys.st_web,
+                TLV_A1 => &mut keys.a1,
+                TLV_KSID => &mut keys.ksid,
+                TLV_A2 => &mut keys.a2,
+                TLV_A2_KEY => &mut keys.a2_key,
+                TLV_ST_KEY => &mut keys.st_key,
+                TLV_ST => &mut keys.st,
+                TLV_S_KEY => &mut keys.s_key,
+                TLV_WT_SESSION_TICKET => &mut keys.wt_session_ticket,
+                TLV_WT_SESSION_TICKET_KEY => &mut keys.wt_session_ticket_key,
+                TLV_D2 => &mut keys.d2,
+                TLV_NO_PIC_SIG => &mut keys.no_pic_sig,
+                TLV_SUPER_KEY => &mut keys.super_key,
+                TLV_D2_KEY => &mut keys.d2_key,
+                TLV_LIFETIMES => {
+                    keys.lifetime = parse_lifetimes(data);
+                    continue;
+                }
+                TLV_PS_KEYS => {
+                    keys.ps_key = parse_ps_keys(data).unwrap_or_default();
+                    continue;
+                }
+                TLV_UID => {
+                    keys.uid = Tlv543::decode_from_slice(data).ok().and_then(Tlv543::uid);
+                    continue;
+                }
+                tag if READ_ELSEWHERE.contains(&tag) => continue,
+                tag => {
+                    tracing::debug!(tag = format_args!("{:#x}", tag), len = data.len(), "Ignoring TLV in 0x119");
+                    continue;
+                }
+            };
+            *slot = Some(data.clone());
+        }
+        keys
+    }
+}
+
+/// The TLVs inside the body of TLV 0x119
+pub fn decrypt(body: &[u8], key: &[u8]) -> Result<HashMap<u16, Vec<u8>>> {
+    let key = tea_key(key).map_err(|e| Error::ParseError(e.to_string()))?;
+    let decrypted = tea::decrypt(body, &key)
+        .map_err(|e| Error::ParseError(format!("Failed to decrypt TLV 0x119: {}", e)))?;
+
+    let tlvs = tlv_unpack(&mut BinaryPacket::from_slice(&decrypted))?;
+    tracing::debug!(inner_tlv_count = tlvs.len(), "Decrypted TLV 0x119");
+    Ok(tlvs)
+}
+
+/// The session keys in the body of TLV 0x119, encrypted with `tgtgt_key`.
+///
+/// The login services keep the raw TLVs for the states that need other tags, and map them
+/// with [`SessionKeys::from_tlvs`] once the login succeeds.
+#[allow(dead_code)]
+pub fn decrypt_and_parse(body: &[u8], tgtgt_key: &[u8]) -> Result<SessionKeys> {
+    decrypt(body, tgtgt_key).map(|tlvs| SessionKeys::from_tlvs(&tlvs))
+}
+
+/// Parses TLV 0x138 and returns the shortest lifetime (in seconds) of the A2/D2 tickets
+fn parse_lifetimes(data: &[u8]) -> Option<u32> {
+    let mut reader = BinaryPacket::from_slice(data);
+    let count = reader.read::<u32>().ok()?;
+
+    let mut shortest: Option<u32> = None;
+    for _ in 0..count {
+        let tag = reader.read::<u16>().ok()?;
+        let lifetime = reader.read::<u32>().ok()?;
+        let _reserved = reader.read::<u32>().ok()?;
+
+        if matches!(tag, TLV_A2 | TLV_D2) && lifetime > 0 {
+            shortest = Some(shortest.map_or(lifetime, |s| s.min(lifetime)));
+        }
+    }
+
+    shortest
+}
+
+/// Parses TLV 0x512: a count, then the domain, ps key and pt4 token of every entry
+fn parse_ps_keys(data: &[u8]) -> Option<HashMap<String, Vec<u8>>> {
+    let mut reader = BinaryPacket::from_slice(data);
+    let count = reader.read::<u16>().ok()?;
+
+    let mut keys = HashMap::new();
+    for _ in 0..count {
+        let domain = reader.read_string(Prefix::INT16).ok()?;
+        let key = reader.read_bytes_with_prefix(Prefix::INT16).ok()?.to_vec();
+        let _pt4_token = reader.read_bytes_with_prefix(Prefix::INT16).ok()?;
+
+        if !key.is_empty() {
+            keys.insert(domain, key);
+        }
+    }
+
+    Some(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::login::register::{Tlv543Layer1, Tlv543Layer2};
+
+    const TGTGT_KEY: [u8; 16] = [0x7A; 16];
+
+    fn pack_tlvs(tlvs: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut writer = BinaryPacket::with_capacity(256);
+        writer.write(tlvs.len() as u16);
+        for (tag, data) in tlvs {
+            writer.write(*tag).write_bytes_with_prefix(data, Prefix::INT16);
+        }
+        writer.to_vec()
+    }
+
+    /// Synthetic TLV 0x119 with a distinct value in every tag the session needs
+    fn synthetic_tlv119() -> Vec<u8> {
+        let uid = Tlv543 {
+            layer1: Some(Tlv543Layer1 {
+                layer2: Some(Tlv543Layer2 { uid: Some("u_synthetic".to_string()) }),
+            }),
+        };
+
+        let mut lifetimes = BinaryPacket::with_capacity(64);
+        lifetimes.write(3u32);
+        lifetimes.write(TLV_A2).write(2_592_000u32).write(0u32);
+        lifetimes.write(TLV_D2).write(1_728_000u32).write(0u32);
+        lifetimes.write(TLV_ST).write(7_200u32).write(0u32);
+
+        let mut ps_keys = BinaryPacket::with_capacity(128);
+        ps_keys.write(2u16);
+        ps_keys.write_str("qun.qq.com", Prefix::INT16);
+        ps_keys.write_str("pskey-qun", Prefix::INT16);
+        ps_keys.write_str("pt4-qun", Prefix::INT16);
+        ps_keys.write_str("vip.qq.com", Prefix::INT16);
+        ps_keys.write_str("", Prefix::INT16);
+        ps_keys.write_str("", Prefix::INT16);
+
+        let inner = pack_tlvs(&[
+            (TLV_ST_WEB, vec![0x03; 16]),
+            (TLV_A1, vec![0x06; 32]),
+            (TLV_KSID, vec![0x08; 16]),
+            (TLV_A2, vec![0x0A; 48]),
+            (TLV_A2_KEY, vec![0x0D; 16]),
+            (TLV_ST_KEY, vec![0x0E; 16]),
+            (TLV_ST, vec![0x14; 32]),
+            (TLV_PROFILE, vec![0x00, 0x01, 25, 1, 3, b'B', b'o', b't']),
+            (TLV_S_KEY, b"@Synthetic".to_vec()),
+            (TLV_WT_SESSION_TICKET, vec![0x33; 48]),
+            (TLV_WT_SESSION_TICKET_KEY, vec![0x34; 16]),
+            (TLV_LIFETIMES, lifetimes.to_vec()),
+            (TLV_D2, vec![0x43; 48]),
+            (TLV_NO_PIC_SIG, vec![0x6A; 32]),
+            (TLV_SUPER_KEY, vec![0x6D; 32]),
+            (TLV_D2_KEY, vec![0x05; 16]),
+            (TLV_PS_KEYS, ps_keys.to_vec()),
+            (TLV_UID, uid.encode_to_vec().unwrap()),
+            (0x11F, vec![0x1F; 8]),
+        ]);
+        tea::encrypt(&inner, &TGTGT_KEY)
+    }
+
+    #[test]
+    fn test_decrypt_and_parse() {
+        let keys = decrypt_and_parse(&synthetic_tlv119(), &TGTGT_KEY).unwrap();
+
+        assert_eq!(keys.st_web, Some(vec![0x03; 16]));
+        assert_eq!(keys.a1, Some(vec![0x06; 32]));
+        assert_eq!(keys.ksid, Some(vec![0x08; 16]));
+        assert_eq!(keys.a2, Some(vec![0x0A; 48]));
+        assert_eq!(keys.a2_key, Some(vec![0x0D; 16]));
+        assert_eq!(keys.st_key, Some(vec![0x0E; 16]));
+        assert_eq!(keys.st, Some(vec![0x14; 32]));
+        assert_eq!(keys.s_key, Some(b"@Synthetic".to_vec()));
+        assert_eq!(keys.wt_session_ticket, Some(vec![0x33; 48]));
+        assert_eq!(keys.wt_session_ticket_key, Some(vec![0x34; 16]));
+        assert_eq!(keys.d2, Some(vec![0x43; 48]));
+        assert_eq!(keys.no_pic_sig, Some(vec![0x6A; 32]));
+        assert_eq!(keys.super_key, Some(vec![0x6D; 32]));
+        assert_eq!(keys.d2_key, Some(vec![0x05; 16]));
+        assert_eq!(keys.lifetime, Some(1_728_000));
+        assert_eq!(keys.uid.as_deref(), Some("u_synthetic"));
+        assert_eq!(keys.ps_key, HashMap::from([("qun.qq.com".to_string(), b"pskey-qun".to_vec())]));
+    }
+
+    #[test]
+    fn test_missing_tags_stay_none() {
+        let body = tea::encrypt(&pack_tlvs(&[(TLV_A2, vec![0x0A; 48])]), &TGTGT_KEY);
+        let keys = decrypt_and_parse(&body, &TGTGT_KEY).unwrap();
+
+        assert_eq!(keys.a2, Some(vec![0x0A; 48]));
+        assert!(keys.d2.is_none() && keys.lifetime.is_none() && keys.uid.is_none());
+        assert!(keys.ps_key.is_empty());
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        assert!(decrypt_and_parse(&synthetic_tlv119(), &[0x00; 16]).is_err());
+        assert!(decrypt_and_parse(&synthetic_tlv119(), &[0x7A; 8]).is_err());
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     common::AppInfo,
     keystore::BotKeystore,
     utils::{
-        binary::{BinaryPacket, Prefix},
+        binary::{BinaryPacket, PacketError, Prefix},
         crypto::{tea, EcdhProvider, EllipticCurveType},
     },
 };
@@ -16,6 +16,36 @@ const SERVER_PUBLIC_KEY: [u8; 49] = [
     0xA8,
 ];
 
+/// Errors produced while parsing wtlogin responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum WtLoginError {
+    #[error("Packet is truncated")]
+    Truncated,
+
+    #[error("Packet does not end with 0x03")]
+    BadTrailer,
+
+    #[error("Unknown encrypt type: {0}")]
+    UnknownEncryptType(u8),
+
+    #[error("Failed to decrypt packet")]
+    DecryptFailed,
+
+    #[error("Key is shorter than 16 bytes")]
+    KeyTooShort,
+}
+
+impl From<PacketError> for WtLoginError {
+    fn from(_: PacketError) -> Self {
+        WtLoginError::Truncated
+    }
+}
+
+/// Converts the first 16 bytes of `key` into a TEA key
+pub(crate) fn tea_key(key: &[u8]) -> Result<[u8; 16], WtLoginError> {
+    tea::key(key).map_err(|_| WtLoginError::KeyTooShort)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum EncryptMethod {
@@ -214,7 +244,12 @@ impl<'a> WtLogin<'a> {
 
         tlvs.tlv_018_android();
         tlvs.tlv_001();
-        tlvs.tlv_106_pwd(password);
+        // Without a password, e.g. after confirming an unusual device, the A1 from trans_emp stands in
+        if password.is_empty() {
+            tlvs.tlv_106_encrypted_a1();
+        } else {
+            tlvs.tlv_106_pwd(password);
+        }
         tlvs.tlv_116();
         tlvs.tlv_100_android(self.app_info.sdk_info.main_sig_map);
         tlvs.tlv_107_android();
@@ -415,7 +450,7 @@ impl<'a> WtLogin<'a> {
         req_body
             .with_length_prefix::<u16, _, _>(true, 1, |w| {
                 w.write(command);
-                w.write_bytes(&*vec![0u8; 21]); // randKey
+                w.write_bytes(&[0u8; 21]); // randKey
                 w.write(3u8); // flag, 4 for oidb_func, 1 for register, 3 for code_2d, 2 for name_func, 5 for devlock
                 w.write(0x00i16); // close
                 w.write(0x32i16); // Version Code: 50
@@ -478,31 +513,33 @@ impl<'a> WtLogin<'a> {
         }
     }
 
-    pub fn parse(&self, input: &[u8]) -> Result<(u16, Vec<u8>), &'static str> {
+    pub fn parse(&self, input: &[u8]) -> Result<(u16, Vec<u8>), WtLoginError> {
+        // header(1) length(2) version(2) command(2) sequence(2) uin(4) flag(1) encrypt_type(1) state(1)
+        const HEADER_LEN: usize = 16;
+
         let mut reader = BinaryPacket::from_slice(input);
-        let _header = reader.read::<u8>().map_err(|_| "Failed to read header")?;
-        let _length = reader.read::<u16>().map_err(|_| "Failed to read length")?;
-        let _version = reader.read::<u16>().map_err(|_| "Failed to read version")?;
-        let command = reader.read::<u16>().map_err(|_| "Failed to read command")?;
-        let _sequence = reader
-            .read::<u16>()
-            .map_err(|_| "Failed to read sequence")?;
-        let _uin = reader.read::<u32>().map_err(|_| "Failed to read uin")?;
-        let _flag = reader.read::<u8>().map_err(|_| "Failed to read flag")?;
-        let encrypt_type = reader
-            .read::<u8>()
-            .map_err(|_| "Failed to read encrypt type")?;
-        let state = reader.read::<u8>().map_err(|_| "Failed to read state")?;
-
-        let remaining = reader.remaining();
-        if remaining == 0 {
-            return Err("No encrypted data");
+        let _header = reader.read::<u8>()?;
+        let length = reader.read::<u16>()? as usize;
+        if length < HEADER_LEN + 1 || length > input.len() {
+            return Err(WtLoginError::Truncated);
+        }
+
+        let _version = reader.read::<u16>()?;
+        let command = reader.read::<u16>()?;
+        let _sequence = reader.read::<u16>()?;
+        let _uin = reader.read::<u32>()?;
+        let _flag = reader.read::<u8>()?;
+        let encrypt_type = reader.read::<u8>()?;
+        let state = reader.read::<u8>()?;
+
+        if input[length - 1] != 0x03 {
+            return Err(WtLoginError::BadTrailer);
         }
 
-        let mut encrypted = reader
-            .read_bytes(remaining - 1)
-            .map_err(|_| "Failed to read encrypted data")?
-            .to_vec();
+        let mut encrypted = input[HEADER_LEN..length - 1].to_vec();
+        if encrypted.is_empty() {
+            return Err(WtLoginError::Truncated);
+        }
 
         let (key, owned_key, encrypted_override) = match encrypt_type {
             0 => {
@@ -522,41 +559,44 @@ impl<'a> WtLogin<'a> {
                 None,
             ),
             4 => {
-                let decrypted = tea::decrypt(&encrypted, &self.share_key[..16].try_into().unwrap())
-                    .map_err(|_| "Failed to decrypt for ecdh key")?;
+                let decrypted = tea::decrypt(&encrypted, &tea_key(&self.share_key)?)
+                    .map_err(|_| WtLoginError::DecryptFailed)?;
                 let mut inner_reader = BinaryPacket::from_vec(decrypted);
-                let server_public_key = inner_reader
-                    .read_bytes_with_prefix(Prefix::INT16)
-                    .map_err(|_| "Failed to read server public key")?;
+                let server_public_key = inner_reader.read_bytes_with_prefix(Prefix::INT16)?;
 
-                let exchange_key = self.ecdh.key_exchange(server_public_key, true)?;
+                let exchange_key = self
+                    .ecdh
+                    .key_exchange(server_public_key, true)
+                    .map_err(|_| WtLoginError::DecryptFailed)?;
                 let new_encrypted = inner_reader.read_remaining().to_vec();
                 (&[] as &[u8], Some(exchange_key), Some(new_encrypted))
             }
-            _ => return Err("Unknown encrypt type"),
+            other => return Err(WtLoginError::UnknownEncryptType(other)),
         };
 
         if let Some(new_encrypted) = encrypted_override {
             encrypted = new_encrypted;
         }
 
-        let final_key = owned_key.as_ref().map(|v| v.as_slice()).unwrap_or(key);
-        let key_array: [u8; 16] = final_key[..16].try_into().map_err(|_| "Invalid key length")?;
-        let decrypted = tea::decrypt(&encrypted, &key_array).map_err(|_| "Failed to decrypt")?;
+        let final_key = owned_key.as_deref().unwrap_or(key);
+        let decrypted =
+            tea::decrypt(&encrypted, &tea_key(final_key)?).map_err(|_| WtLoginError::DecryptFailed)?;
 
         Ok((command, decrypted))
     }
 
-    pub fn parse_code_2d_packet(&self, input: &[u8]) -> Result<(u16, Vec<u8>), &'static str> {
+    pub fn parse_code_2d_packet(&self, input: &[u8]) -> Result<(u16, Vec<u8>), WtLoginError> {
         if input.len() < 5 {
-            return Err("Input too short");
+            return Err(WtLoginError::Truncated);
         }
 
         let encrypt = input[1];
-        let layer = u16::from_be_bytes([input[2], input[3]]);
+        let layer = u16::from_be_bytes([input[2], input[3]]) as usize;
+        let span = input.get(5..5 + layer).ok_or(WtLoginError::Truncated)?;
 
+        let decrypted;
         let span = if encrypt == 0 {
-            &input[5..5 + layer as usize]
+            span
         } else {
             let st_key = self
                 .keystore
@@ -564,26 +604,21 @@ impl<'a> WtLogin<'a> {
                 .st_key
                 .as_ref()
                 .unwrap_or(&self.keystore.sigs.random_key);
-            let key_array: [u8; 16] = st_key[..16].try_into().unwrap();
-            &tea::decrypt(&input[5..5 + layer as usize], &key_array)
-                .map_err(|_| "Failed to decrypt code2d packet")?
+            decrypted = tea::decrypt(span, &tea_key(st_key)?).map_err(|_| WtLoginError::DecryptFailed)?;
+            &decrypted
         };
 
         let mut reader = BinaryPacket::from_slice(span);
 
-        let _header = reader.read::<u8>().map_err(|_| "Failed to read header")?;
-        let _length = reader.read::<u16>().map_err(|_| "Failed to read length")?;
-        let command = reader.read::<u16>().map_err(|_| "Failed to read command")?;
-        reader.skip(21);
-        let _flag = reader.read::<u8>().map_err(|_| "Failed to read flag")?;
-        let _retry_time = reader
-            .read::<u16>()
-            .map_err(|_| "Failed to read retry_time")?;
-        let _version = reader.read::<u16>().map_err(|_| "Failed to read version")?;
-        let _sequence = reader
-            .read::<u32>()
-            .map_err(|_| "Failed to read sequence")?;
-        let _uin = reader.read::<i64>().map_err(|_| "Failed to read uin")?;
+        let _header = reader.read::<u8>()?;
+        let _length = reader.read::<u16>()?;
+        let command = reader.read::<u16>()?;
+        reader.read_bytes(21)?;
+        let _flag = reader.read::<u8>()?;
+        let _retry_time = reader.read::<u16>()?;
+        let _version = reader.read::<u16>()?;
+        let _sequence = reader.read::<u32>()?;
+        let _uin = reader.read::<i64>()?;
 
         Ok((command, reader.read_remaining().to_vec()))
     }
@@ -595,3 +630,172 @@ impl<'a> WtLogin<'a> {
             .as_secs()
     }
 }
+
+#[cfg(test)]
+impl WtLogin<'_> {
+    /// Builds a server response as `parse` expects it, encrypted with the share key
+    pub(crate) fn build_test_response(&self, command: u16, state: u8, payload: &[u8]) -> Vec<u8> {
+        let key_array: [u8; 16] = self.share_key[..16].try_into().unwrap();
+        let encrypted = tea::encrypt(payload, &key_array);
+
+        let mut writer = BinaryPacket::with_capacity(encrypted.len() + 16);
+        writer.write(2u8);
+        writer.write((encrypted.len() + 17) as u16);
+        writer.write(8001u16);
+        writer.write(command);
+        writer.write(0u16); // sequence
+        writer.write(self.keystore.uin.unwrap_or(0) as u32);
+        writer.write(0u8); // flag
+        writer.write(0u8); // encrypt type
+        writer.write(state);
+        writer.write_bytes(&encrypted);
+        writer.write(3u8);
+        writer.to_vec()
+    }
+
+    /// Builds an unencrypted code2d response wrapping `body` as `parse_code_2d_packet` expects it
+    pub(crate) fn build_test_code_2d_response(&self, command: u16, body: &[u8]) -> Vec<u8> {
+        let mut span = BinaryPacket::with_capacity(body.len() + 48);
+        span.write(2u8);
+        span.write((body.len() + 43) as u16);
+        span.write(command);
+        span.write_bytes(&[0u8; 21]);
+        span.write(3u8); // flag
+        span.write(0u16); // retry time
+        span.write(0x32u16); // version
+        span.write(0u32); // sequence
+        span.write(0i64); // uin
+        span.write_bytes(body);
+
+        let mut writer = BinaryPacket::with_capacity(span.as_slice().len() + 5);
+        writer.write(0u8);
+        writer.write(0u8); // not encrypted
+        writer.write(span.as_slice().len() as u16);
+        writer.write(0u8);
+        writer.write_bytes(span.as_slice());
+
+        self.build_test_response(0x812, 0, writer.as_slice())
+    }
+
+    /// Decrypts a request produced by one of the builders, returning its TEA-encrypted payload
+    pub(crate) fn decrypt_test_request(&self, packet: &[u8]) -> Vec<u8> {
+        let mut reader = BinaryPacket::from_slice(packet);
+        reader.skip(1 + 2 + 2 + 2 + 2 + 4 + 1 + 1 + 4 + 1 + 2 + 2 + 4);
+        reader.skip(1 + 1 + 16 + 2); // encrypt head
+        let public_key_len = reader.read::<u16>().unwrap() as usize;
+        reader.skip(public_key_len);
+        let encrypted = reader.read_bytes(reader.remaining() - 1).unwrap();
+
+        let key_array: [u8; 16] = self.share_key[..16].try_into().unwrap();
+        tea::decrypt(encrypted, &key_array).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, RngCore};
+
+    fn with_wtlogin(f: impl FnOnce(&WtLogin)) {
+        let mut keystore = BotKeystore::new();
+        let app_info = AppInfo::linux();
+        let packet = WtLogin::new(&mut keystore, &app_info).unwrap();
+        f(&packet);
+    }
+
+    #[test]
+    fn test_parse_roundtrip() {
+        with_wtlogin(|packet| {
+            let response = packet.build_test_response(0x810, 0, b"payload");
+            assert_eq!(packet.parse(&response), Ok((0x810, b"payload".to_vec())));
+
+            let code_2d = packet.build_test_code_2d_response(0x12, b"body");
+            let (_, payload) = packet.parse(&code_2d).unwrap();
+            assert_eq!(packet.parse_code_2d_packet(&payload), Ok((0x12, b"body".to_vec())));
+        });
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_packets() {
+        with_wtlogin(|packet| {
+            let response = packet.build_test_response(0x810, 0, b"payload");
+
+            let mut bad_trailer = response.clone();
+            *bad_trailer.last_mut().unwrap() = 0x02;
+            assert_eq!(packet.parse(&bad_trailer), Err(WtLoginError::BadTrailer));
+
+            let mut bad_encrypt_type = response.clone();
+            bad_encrypt_type[14] = 9;
+            assert_eq!(
+                packet.parse(&bad_encrypt_type),
+                Err(WtLoginError::UnknownEncryptType(9))
+            );
+
+            let mut bad_length = response.clone();
+            bad_length[1..3].copy_from_slice(&u16::MAX.to_be_bytes());
+            assert_eq!(packet.parse(&bad_length), Err(WtLoginError::Truncated));
+
+            // Ciphertext that is not a multiple of the TEA block size
+            let mut corrupted = response.clone();
+            corrupted.remove(16);
+            let len = corrupted.len() as u16;
+            corrupted[1..3].copy_from_slice(&len.to_be_bytes());
+            assert_eq!(packet.parse(&corrupted), Err(WtLoginError::DecryptFailed));
+
+            assert_eq!(packet.parse_code_2d_packet(&[0, 0, 0xFF, 0xFF, 0, 1]), Err(WtLoginError::Truncated));
+        });
+    }
+
+    #[test]
+    fn test_parse_truncated_packets() {
+        with_wtlogin(|packet| {
+            let response = packet.build_test_code_2d_response(0x31, &[0xAB; 64]);
+            for len in 0..response.len() {
+                assert!(packet.parse(&response[..len]).is_err());
+            }
+
+            let (_, payload) = packet.parse(&response).unwrap();
+            for len in 0..payload.len() - 64 {
+                assert!(packet.parse_code_2d_packet(&payload[..len]).is_err());
+            }
+        });
+    }
+
+    #[test]
+    fn test_parse_garbage_does_not_panic() {
+        let mut rng = rand::thread_rng();
+        with_wtlogin(|packet| {
+            for _ in 0..2000 {
+                let mut buf = vec![0u8; rng.gen_range(0..256)];
+                rng.fill_bytes(&mut buf);
+
+                let _ = packet.parse(&buf);
+                let _ = packet.parse_code_2d_packet(&buf);
+
+                // Make the declared length plausible to get past the first checks
+                if buf.len() >= 17 {
+                    let len = buf.len() as u16;
+                    buf[1..3].copy_from_slice(&len.to_be_bytes());
+                    *buf.last_mut().unwrap() = 0x03;
+                    buf[14] = [0u8, 3, 4][rng.gen_range(0..3)];
+                    let _ = packet.parse(&buf);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_short_keys_are_rejected() {
+        assert_eq!(tea_key(&[0u8; 8]), Err(WtLoginError::KeyTooShort));
+        assert_eq!(tea_key(&[7u8; 20]), Ok([7u8; 16]));
+
+        let mut keystore = BotKeystore::new();
+        let app_info = AppInfo::linux();
+        keystore.sigs.st_key = Some(vec![1, 2, 3]);
+        let packet = WtLogin::new(&mut keystore, &app_info).unwrap();
+
+        let mut encrypted = vec![0u8, 1, 0, 16, 0];
+        encrypted.extend_from_slice(&[0u8; 16]);
+        assert_eq!(packet.parse_code_2d_packet(&encrypted), Err(WtLoginError::KeyTooShort));
+    }
+}
@@ -0,0 +1,30 @@
+pub mod elem;
+pub mod input_status;
+pub mod long_msg;
+pub mod push;
+pub mod recall;
+pub mod send;
+
+pub use elem::{
+    CommonElem, CustomFace, Elem, Face, LightAppElem, MarketFace, MentionExtra, ObjMsg,
+    ObjMsgContentInfo, ObjMsgFile, RichMsg, SrcMsg, Text, TransElem,
+};
+pub use input_status::{InputStatusRequest, InputStatusResponse};
+pub use long_msg::{
+    LongMsgAction, LongMsgAttr, LongMsgContent, LongMsgInterfaceReq, LongMsgInterfaceRsp,
+    LongMsgPeerInfo, LongMsgRecvReq, LongMsgResult, LongMsgSendReq,
+};
+pub use push::{
+    FriendInputStatusContent, FriendRecallContent, FriendRequestContent, GeneralGrayTip, GroupEssenceNotice,
+    GroupInvitedJoinContent, GroupJoinRequestContent, GroupMemberChange, GroupMemberChangeOperator, GroupMuteContent,
+    GroupNotifyBody, GroupReactionChange, ProfileChangeContent, ProfileField, PushContentHead, PushMessageBody,
+    PushMsg, PushMsgBody, PushRichText, ResponseForward, ResponseGrp, ResponseHead,
+};
+pub use recall::{
+    FriendRecallInfo, FriendRecallRequest, FriendRecallSettings, GroupRecallInfo,
+    GroupRecallRequest, GroupRecallSettings, RecallResponse,
+};
+pub use send::{
+    C2c, ContentHead, Grp, MessageBody, MessageControl, PbSendMsg, PbSendMsgResp, RichText,
+    RoutingHead,
+};
@@ -0,0 +1,226 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage, UnknownFields};
+
+/// A single rich-text element; exactly one field is set.
+///
+/// Element types this crate does not model are kept in `_unknown_fields`, so they survive a
+/// decode/encode round trip unchanged.
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+#[proto(preserve_unknown)]
+pub struct Elem {
+    #[proto(tag = 1)]
+    pub text: Option<Text>,
+    #[proto(tag = 2)]
+    pub face: Option<Face>,
+    #[proto(tag = 4)]
+    pub not_online_image: Option<NotOnlineImage>,
+    #[proto(tag = 5)]
+    pub trans_elem: Option<TransElem>,
+    #[proto(tag = 6)]
+    pub market_face: Option<MarketFace>,
+    #[proto(tag = 8)]
+    pub custom_face: Option<CustomFace>,
+    #[proto(tag = 12)]
+    pub rich_msg: Option<RichMsg>,
+    #[proto(tag = 45)]
+    pub src_msg: Option<SrcMsg>,
+    #[proto(tag = 51)]
+    pub light_app: Option<LightAppElem>,
+    #[proto(tag = 53)]
+    pub common_elem: Option<CommonElem>,
+    pub _unknown_fields: UnknownFields,
+}
+
+/// Plain text element, also used for mentions (see [`MentionExtra`])
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct Text {
+    #[proto(tag = 1)]
+    pub str: Option<String>,
+    #[proto(tag = 2)]
+    pub link: Option<String>,
+    #[proto(tag = 3)]
+    pub attr6_buf: Option<Vec<u8>>,
+    #[proto(tag = 4)]
+    pub attr7_buf: Option<Vec<u8>>,
+    #[proto(tag = 11)]
+    pub buf: Option<Vec<u8>>,
+    #[proto(tag = 12)]
+    pub pb_reserve: Option<Vec<u8>>,
+}
+
+/// Carried in [`Text::pb_reserve`] when the text mentions someone
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MentionExtra {
+    /// 1 for everyone, 2 for a single member
+    #[proto(tag = 3)]
+    pub mention_type: Option<u32>,
+    #[proto(tag = 4)]
+    pub uin: Option<u32>,
+    #[proto(tag = 5)]
+    pub field5: Option<u32>,
+    #[proto(tag = 9)]
+    pub uid: Option<String>,
+}
+
+/// Classic (small) face
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct Face {
+    #[proto(tag = 1)]
+    pub index: Option<u32>,
+    #[proto(tag = 2)]
+    pub old: Option<Vec<u8>>,
+    #[proto(tag = 11)]
+    pub buf: Option<Vec<u8>>,
+}
+
+/// Image sent to a friend
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NotOnlineImage {
+    #[proto(tag = 1)]
+    pub file_path: Option<String>,
+    #[proto(tag = 2)]
+    pub file_len: Option<u32>,
+    #[proto(tag = 7)]
+    pub pic_md5: Option<Vec<u8>>,
+    #[proto(tag = 8)]
+    pub pic_height: Option<u32>,
+    #[proto(tag = 9)]
+    pub pic_width: Option<u32>,
+    #[proto(tag = 15)]
+    pub orig_url: Option<String>,
+}
+
+/// Image sent to a group
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct CustomFace {
+    #[proto(tag = 7)]
+    pub file_path: Option<String>,
+    #[proto(tag = 13)]
+    pub md5: Option<Vec<u8>>,
+    #[proto(tag = 16)]
+    pub orig_url: Option<String>,
+    #[proto(tag = 22)]
+    pub width: Option<u32>,
+    #[proto(tag = 23)]
+    pub height: Option<u32>,
+    #[proto(tag = 25)]
+    pub size: Option<u32>,
+}
+
+/// Element with a type-specific binary payload; type 24 is a group file
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct TransElem {
+    #[proto(tag = 1)]
+    pub elem_type: Option<u32>,
+    /// Flag byte, u16 BE length and the payload, an [`ObjMsg`] for group files
+    #[proto(tag = 2)]
+    pub elem_value: Option<Vec<u8>>,
+}
+
+/// Payload of a group file [`TransElem`]
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ObjMsg {
+    #[proto(tag = 1)]
+    pub msg_type: Option<u32>,
+    #[proto(tag = 7)]
+    pub content_infos: Vec<ObjMsgContentInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ObjMsgContentInfo {
+    #[proto(tag = 1)]
+    pub content_info_id: Option<Vec<u8>>,
+    #[proto(tag = 2)]
+    pub file: Option<ObjMsgFile>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ObjMsgFile {
+    #[proto(tag = 1)]
+    pub bus_id: Option<u32>,
+    /// File id in the group, as used by the group file requests
+    #[proto(tag = 2)]
+    pub file_path: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    pub file_size: Option<u64>,
+    #[proto(tag = 4)]
+    pub file_name: Option<String>,
+    #[proto(tag = 5)]
+    pub dead_time: Option<u64>,
+    #[proto(tag = 6)]
+    pub file_sha1: Option<Vec<u8>>,
+    #[proto(tag = 8)]
+    pub file_md5: Option<Vec<u8>>,
+}
+
+/// Sticker from the sticker store
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MarketFace {
+    /// Summary like `[开心]`
+    #[proto(tag = 1)]
+    pub face_name: Option<Vec<u8>>,
+    #[proto(tag = 2)]
+    pub item_type: Option<u32>,
+    #[proto(tag = 3)]
+    pub face_info: Option<u32>,
+    #[proto(tag = 4)]
+    pub face_id: Option<Vec<u8>>,
+    /// Id of the sticker pack
+    #[proto(tag = 5)]
+    pub tab_id: Option<u32>,
+    #[proto(tag = 6)]
+    pub sub_type: Option<u32>,
+    #[proto(tag = 7)]
+    pub key: Option<Vec<u8>>,
+    #[proto(tag = 9)]
+    pub media_type: Option<u32>,
+    #[proto(tag = 10)]
+    pub image_width: Option<u32>,
+    #[proto(tag = 11)]
+    pub image_height: Option<u32>,
+}
+
+/// XML card; `service_id` 35 is a forwarded message bundle
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct RichMsg {
+    /// Template, prefixed with 0x00 (raw) or 0x01 (zlib-compressed)
+    #[proto(tag = 1)]
+    pub template1: Option<Vec<u8>>,
+    #[proto(tag = 2)]
+    pub service_id: Option<u32>,
+}
+
+/// Source of a reply
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SrcMsg {
+    #[proto(tag = 1)]
+    pub orig_seqs: Vec<u32>,
+    #[proto(tag = 2)]
+    pub sender_uin: Option<u64>,
+    #[proto(tag = 3)]
+    pub time: Option<u32>,
+    #[proto(tag = 4)]
+    pub flag: Option<u32>,
+    #[proto(tag = 5)]
+    pub elems: Vec<Elem>,
+}
+
+/// JSON card (mini apps, shares)
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LightAppElem {
+    /// JSON, prefixed with 0x00 (raw) or 0x01 (zlib-compressed)
+    #[proto(tag = 1)]
+    pub data: Option<Vec<u8>>,
+    #[proto(tag = 2)]
+    pub msg_resid: Option<Vec<u8>>,
+}
+
+/// NT element; rich media (images, records, videos) use `service_type` 48
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct CommonElem {
+    #[proto(tag = 1)]
+    pub service_type: Option<u32>,
+    #[proto(tag = 2)]
+    pub pb_elem: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    pub business_type: Option<u32>,
+}
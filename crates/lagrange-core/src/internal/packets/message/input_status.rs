@@ -0,0 +1,24 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `trpc.msg.msg_svc.MsgService.SsoC2CInputStatus`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct InputStatusRequest {
+    #[proto(tag = 1)]
+    pub target_uid: String,
+    /// [`InputStatusRequest::TYPING`] or [`InputStatusRequest::STOPPED`]
+    #[proto(tag = 2)]
+    pub event_type: u32,
+}
+
+impl InputStatusRequest {
+    pub const TYPING: u32 = 1;
+    pub const STOPPED: u32 = 2;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct InputStatusResponse {
+    #[proto(tag = 1)]
+    pub result: Option<u32>,
+    #[proto(tag = 2)]
+    pub err_msg: Option<String>,
+}
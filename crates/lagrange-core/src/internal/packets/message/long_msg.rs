@@ -0,0 +1,101 @@
+use super::PushMsgBody;
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `trpc.group.long_msg_interface.MsgService.SsoSendLongMsg` and `SsoRecvLongMsg`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgInterfaceReq {
+    #[proto(tag = 1)]
+    pub recv_req: Option<LongMsgRecvReq>,
+    #[proto(tag = 2)]
+    pub send_req: Option<LongMsgSendReq>,
+    #[proto(tag = 15)]
+    pub attr: Option<LongMsgAttr>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgInterfaceRsp {
+    #[proto(tag = 1)]
+    pub recv_rsp: Option<LongMsgRecvRsp>,
+    #[proto(tag = 2)]
+    pub send_rsp: Option<LongMsgSendRsp>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgSendReq {
+    /// 1 for a friend, 3 for a group
+    #[proto(tag = 1)]
+    pub msg_type: u32,
+    #[proto(tag = 2)]
+    pub peer_info: Option<LongMsgPeerInfo>,
+    #[proto(tag = 3)]
+    pub group_uin: Option<u32>,
+    /// Gzip-compressed [`LongMsgResult`]
+    #[proto(tag = 4)]
+    pub payload: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgSendRsp {
+    #[proto(tag = 3)]
+    pub res_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgRecvReq {
+    #[proto(tag = 1)]
+    pub peer_info: Option<LongMsgPeerInfo>,
+    #[proto(tag = 2)]
+    pub res_id: String,
+    #[proto(tag = 3)]
+    pub acquire: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgRecvRsp {
+    #[proto(tag = 3)]
+    pub res_id: Option<String>,
+    /// Gzip-compressed [`LongMsgResult`]
+    #[proto(tag = 4)]
+    pub payload: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgPeerInfo {
+    #[proto(tag = 2)]
+    pub peer_uid: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgAttr {
+    /// 4 to send, 2 to receive
+    #[proto(tag = 1)]
+    pub sub_cmd: u32,
+    #[proto(tag = 2)]
+    pub client_type: u32,
+    #[proto(tag = 3)]
+    pub platform: u32,
+    #[proto(tag = 4)]
+    pub proxy_type: u32,
+}
+
+/// Uncompressed payload of a forwarded message bundle
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgResult {
+    #[proto(tag = 2)]
+    pub actions: Vec<LongMsgAction>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgAction {
+    /// `MultiMsg` for the messages of the bundle itself
+    #[proto(tag = 1)]
+    pub action_command: String,
+    #[proto(tag = 2)]
+    pub action_data: Option<LongMsgContent>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct LongMsgContent {
+    #[proto(tag = 1)]
+    pub msg_body: Vec<PushMsgBody>,
+}
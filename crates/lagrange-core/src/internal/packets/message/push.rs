@@ -0,0 +1,533 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `trpc.msg.olpush.OlPushService.MsgPush`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PushMsg {
+    #[proto(tag = 1)]
+    pub message: Option<PushMsgBody>,
+    #[proto(tag = 3)]
+    pub status: Option<u32>,
+    #[proto(tag = 5)]
+    pub ping_flag: Option<u32>,
+    #[proto(tag = 9)]
+    pub general_flag: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PushMsgBody {
+    #[proto(tag = 1)]
+    pub response_head: Option<ResponseHead>,
+    #[proto(tag = 2)]
+    pub content_head: Option<PushContentHead>,
+    #[proto(tag = 3)]
+    pub body: Option<PushMessageBody>,
+}
+
+/// Same layout as [`MessageBody`](super::MessageBody), but elements are left encoded so a
+/// malformed one does not fail the whole push
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PushMessageBody {
+    #[proto(tag = 1)]
+    pub rich_text: Option<PushRichText>,
+    #[proto(tag = 2)]
+    pub msg_content: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    pub msg_encrypt_content: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PushRichText {
+    /// Encoded [`Elem`](super::Elem)s
+    #[proto(tag = 2)]
+    pub elems: Vec<Vec<u8>>,
+}
+
+/// Sender and receiver of a pushed message
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ResponseHead {
+    #[proto(tag = 1)]
+    pub from_uin: Option<u32>,
+    #[proto(tag = 2)]
+    pub from_uid: Option<String>,
+    #[proto(tag = 3)]
+    pub msg_type: Option<u32>,
+    #[proto(tag = 4)]
+    pub sig_map: Option<u32>,
+    #[proto(tag = 5)]
+    pub to_uin: Option<u32>,
+    #[proto(tag = 6)]
+    pub to_uid: Option<String>,
+    #[proto(tag = 7)]
+    pub forward: Option<ResponseForward>,
+    #[proto(tag = 8)]
+    pub grp: Option<ResponseGrp>,
+}
+
+/// Set on friend and temp messages
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ResponseForward {
+    #[proto(tag = 6)]
+    pub friend_name: Option<String>,
+}
+
+/// Set on group messages
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ResponseGrp {
+    #[proto(tag = 1)]
+    pub group_uin: Option<u32>,
+    /// Group card of the sender, empty when unset
+    #[proto(tag = 4)]
+    pub member_name: Option<String>,
+    #[proto(tag = 5)]
+    pub unknown5: Option<u32>,
+    #[proto(tag = 7)]
+    pub group_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PushContentHead {
+    /// Message type, see [`PushContentHead::FRIEND`] and friends
+    #[proto(tag = 1)]
+    pub msg_type: Option<u32>,
+    #[proto(tag = 2)]
+    pub sub_type: Option<u32>,
+    #[proto(tag = 3)]
+    pub c2c_cmd: Option<u32>,
+    #[proto(tag = 4)]
+    pub random: Option<u32>,
+    #[proto(tag = 5)]
+    pub sequence: Option<u32>,
+    /// `int64` on the wire; kept unsigned because `i64` fields are zigzag-encoded
+    #[proto(tag = 6)]
+    pub timestamp: Option<u64>,
+    #[proto(tag = 7)]
+    pub pkg_num: Option<u32>,
+    #[proto(tag = 8)]
+    pub pkg_index: Option<u32>,
+    #[proto(tag = 9)]
+    pub div_seq: Option<u32>,
+    #[proto(tag = 11)]
+    pub msg_id: Option<u64>,
+}
+
+impl PushContentHead {
+    pub const GROUP: u32 = 82;
+    pub const GROUP_JOIN_REQUEST: u32 = 84;
+    pub const TEMP: u32 = 141;
+    pub const FRIEND: u32 = 166;
+    pub const GROUP_INVITED_JOIN_REQUEST: u32 = 525;
+    /// A member joined a group, see [`GroupMemberChange`]
+    pub const GROUP_MEMBER_INCREASE: u32 = 33;
+    /// A member left or was removed from a group, see [`GroupMemberChange`]
+    pub const GROUP_MEMBER_DECREASE: u32 = 34;
+    /// System notifications, told apart by `sub_type`
+    pub const EVENT: u32 = 528;
+    /// Group notifications, told apart by `sub_type`
+    pub const GROUP_EVENT: u32 = 732;
+
+    /// `sub_type` of [`PushContentHead::EVENT`] for friend requests
+    pub const SUB_FRIEND_REQUEST: u32 = 35;
+    /// `sub_type` of [`PushContentHead::EVENT`] for profile and contact list changes, see
+    /// [`ProfileChangeContent`]
+    pub const SUB_PROFILE_CHANGE: u32 = 39;
+    /// `sub_type` of [`PushContentHead::EVENT`] for recalled friend messages
+    pub const SUB_FRIEND_RECALL: u32 = 138;
+    /// `sub_type` of [`PushContentHead::EVENT`] for a friend starting or stopping to type
+    pub const SUB_FRIEND_INPUT_STATUS: u32 = 349;
+    /// `sub_type` of [`PushContentHead::GROUP_EVENT`] for emoji reactions to messages
+    pub const SUB_GROUP_REACTION: u32 = 16;
+    /// `sub_type` of [`PushContentHead::GROUP_EVENT`] for mutes, see [`GroupMuteContent`]
+    pub const SUB_GROUP_MUTE: u32 = 12;
+    /// `sub_type` of [`PushContentHead::GROUP_EVENT`] for recalled group messages
+    pub const SUB_GROUP_RECALL: u32 = 17;
+    /// `sub_type` of [`PushContentHead::GROUP_EVENT`] for gray tips, e.g. pokes
+    pub const SUB_GROUP_GRAY_TIP: u32 = 20;
+    /// `sub_type` of [`PushContentHead::GROUP_EVENT`] for changes of the essence list
+    pub const SUB_GROUP_ESSENCE: u32 = 21;
+}
+
+/// `msg_content` of a friend request
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRequestContent {
+    #[proto(tag = 1)]
+    pub info: Option<FriendRequestInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRequestInfo {
+    #[proto(tag = 1)]
+    pub target_uid: Option<String>,
+    #[proto(tag = 2)]
+    pub source_uid: Option<String>,
+    #[proto(tag = 10)]
+    pub message: Option<String>,
+    /// Where the requester found the bot, e.g. "QQ search"
+    #[proto(tag = 11)]
+    pub source: Option<String>,
+}
+
+/// `msg_content` of a request to join a group
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupJoinRequestContent {
+    #[proto(tag = 1)]
+    pub group_uin: Option<u32>,
+    #[proto(tag = 3)]
+    pub target_uid: Option<String>,
+    #[proto(tag = 5)]
+    pub comment: Option<String>,
+    #[proto(tag = 6)]
+    pub sequence: Option<u64>,
+}
+
+/// `msg_content` of a request to join a group on a member's invitation
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupInvitedJoinContent {
+    #[proto(tag = 1)]
+    pub command: Option<u32>,
+    #[proto(tag = 2)]
+    pub info: Option<GroupInvitedJoinInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupInvitedJoinInfo {
+    #[proto(tag = 1)]
+    pub inner: Option<GroupInvitedJoinInner>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupInvitedJoinInner {
+    #[proto(tag = 1)]
+    pub group_uin: Option<u32>,
+    #[proto(tag = 5)]
+    pub target_uid: Option<String>,
+    #[proto(tag = 6)]
+    pub invitor_uid: Option<String>,
+    #[proto(tag = 7)]
+    pub sequence: Option<u64>,
+}
+
+/// `msg_content` of a member joining or leaving a group
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupMemberChange {
+    #[proto(tag = 1)]
+    pub group_uin: Option<u32>,
+    #[proto(tag = 3)]
+    pub member_uid: Option<String>,
+    /// How the member joined or left, see the constants
+    #[proto(tag = 4)]
+    pub change_type: Option<u32>,
+    /// Uid of the operator as UTF-8, or a [`GroupMemberChangeOperator`] when the bot was kicked
+    #[proto(tag = 5)]
+    pub operator: Option<Vec<u8>>,
+}
+
+impl GroupMemberChange {
+    /// Joined after a request was approved
+    pub const JOINED: u32 = 130;
+    /// Joined on the invitation of the operator
+    pub const INVITED: u32 = 131;
+    /// The bot was removed by the operator
+    pub const KICKED_SELF: u32 = 3;
+    /// Left the group on their own
+    pub const LEFT: u32 = 130;
+    /// Removed by the operator
+    pub const KICKED: u32 = 131;
+}
+
+/// `operator` of a [`GroupMemberChange`] removing the bot
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupMemberChangeOperator {
+    #[proto(tag = 1)]
+    pub operator: Option<GroupMemberChangeOperatorInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupMemberChangeOperatorInfo {
+    #[proto(tag = 1)]
+    pub uid: Option<String>,
+}
+
+/// `msg_content` of a member or the whole group being muted or unmuted
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupMuteContent {
+    #[proto(tag = 1)]
+    pub group_uin: Option<u32>,
+    #[proto(tag = 2)]
+    pub sub_type: Option<u32>,
+    #[proto(tag = 4)]
+    pub operator_uid: Option<String>,
+    #[proto(tag = 5)]
+    pub data: Option<GroupMuteData>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupMuteData {
+    #[proto(tag = 1)]
+    pub timestamp: Option<u32>,
+    #[proto(tag = 2)]
+    pub mute_type: Option<u32>,
+    #[proto(tag = 3)]
+    pub state: Option<GroupMuteState>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupMuteState {
+    /// Muted member, absent when the whole group is muted
+    #[proto(tag = 1)]
+    pub target_uid: Option<String>,
+    /// Seconds, `0` to unmute; [`u32::MAX`] when the whole group is muted
+    #[proto(tag = 2)]
+    pub duration: Option<u32>,
+}
+
+/// `msg_content` of profile and contact list changes
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ProfileChangeContent {
+    #[proto(tag = 1)]
+    pub changes: Vec<ProfileChange>,
+}
+
+/// One change, described by whichever of the fields is set
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ProfileChange {
+    #[proto(tag = 1)]
+    pub notify_type: Option<u32>,
+    #[proto(tag = 2)]
+    pub op_type: Option<u32>,
+    #[proto(tag = 8)]
+    pub profile: Option<ProfileFields>,
+    #[proto(tag = 9)]
+    pub friend_remark: Option<FriendRemarkChanges>,
+    #[proto(tag = 11)]
+    pub avatar: Option<AvatarChange>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ProfileFields {
+    #[proto(tag = 1)]
+    pub uin: Option<u64>,
+    #[proto(tag = 2)]
+    pub fields: Vec<ProfileField>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ProfileField {
+    /// Which field changed, e.g. [`ProfileField::NICKNAME`]
+    #[proto(tag = 1)]
+    pub field: Option<u32>,
+    #[proto(tag = 2)]
+    pub value: Option<Vec<u8>>,
+}
+
+impl ProfileField {
+    pub const NICKNAME: u32 = 20002;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRemarkChanges {
+    #[proto(tag = 1)]
+    pub remarks: Vec<FriendRemarkChange>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRemarkChange {
+    #[proto(tag = 1)]
+    pub remark_type: Option<u32>,
+    #[proto(tag = 2)]
+    pub uin: Option<u64>,
+    /// The new remark, empty when it was cleared
+    #[proto(tag = 3)]
+    pub remark: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct AvatarChange {
+    #[proto(tag = 1)]
+    pub avatar_type: Option<u32>,
+    #[proto(tag = 2)]
+    pub uin: Option<u64>,
+}
+
+/// `msg_content` of a recalled friend message
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRecallContent {
+    #[proto(tag = 1)]
+    pub info: Option<FriendRecallNotice>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRecallNotice {
+    #[proto(tag = 1)]
+    pub from_uid: Option<String>,
+    #[proto(tag = 2)]
+    pub to_uid: Option<String>,
+    #[proto(tag = 3)]
+    pub sequence: Option<u32>,
+    #[proto(tag = 5)]
+    pub time: Option<u32>,
+    #[proto(tag = 6)]
+    pub random: Option<u32>,
+}
+
+/// `msg_content` of a friend's input status
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendInputStatusContent {
+    #[proto(tag = 1)]
+    pub from_uid: Option<String>,
+    /// Same values as [`InputStatusRequest::event_type`](super::InputStatusRequest::event_type)
+    #[proto(tag = 2)]
+    pub event_type: Option<u32>,
+    /// Text shown instead of the friend's name, e.g. "正在输入..."
+    #[proto(tag = 3)]
+    pub status_text: Option<String>,
+}
+
+/// Proto part of the `msg_content` of a group notification.
+///
+/// It follows a binary header of the group uin (4 bytes), one unknown byte and the length of the
+/// proto (2 bytes), all big endian.
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupNotifyBody {
+    #[proto(tag = 1)]
+    pub notify_type: Option<u32>,
+    #[proto(tag = 4)]
+    pub group_uin: Option<u32>,
+    #[proto(tag = 11)]
+    pub recall: Option<GroupRecallNotice>,
+    #[proto(tag = 26)]
+    pub gray_tip: Option<GeneralGrayTip>,
+    #[proto(tag = 33)]
+    pub essence: Option<GroupEssenceNotice>,
+    #[proto(tag = 44)]
+    pub reaction: Option<GroupReactionNotice>,
+}
+
+/// Change of an emoji reaction, nested a few levels deep
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupReactionNotice {
+    #[proto(tag = 1)]
+    pub data: Option<GroupReactionNoticeData>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupReactionNoticeData {
+    #[proto(tag = 1)]
+    pub inner: Option<GroupReactionNoticeInner>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupReactionNoticeInner {
+    #[proto(tag = 2)]
+    pub target: Option<GroupReactionTarget>,
+    #[proto(tag = 3)]
+    pub reaction: Option<GroupReactionChange>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupReactionTarget {
+    #[proto(tag = 1)]
+    pub sequence: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupReactionChange {
+    /// Id of the emoji as decimal string
+    #[proto(tag = 1)]
+    pub code: Option<String>,
+    #[proto(tag = 3)]
+    pub count: Option<u32>,
+    #[proto(tag = 4)]
+    pub operator_uid: Option<String>,
+    /// See [`GroupReactionChange::ADDED`]
+    #[proto(tag = 5)]
+    pub action: Option<u32>,
+}
+
+impl GroupReactionChange {
+    /// `action` of an added reaction, it is `2` when one is taken back
+    pub const ADDED: u32 = 1;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupEssenceNotice {
+    #[proto(tag = 1)]
+    pub group_uin: Option<u32>,
+    #[proto(tag = 2)]
+    pub sequence: Option<u32>,
+    #[proto(tag = 3)]
+    pub random: Option<u32>,
+    /// See [`GroupEssenceNotice::SET`]
+    #[proto(tag = 4)]
+    pub set_flag: Option<u32>,
+    #[proto(tag = 5)]
+    pub author_uin: Option<u32>,
+    #[proto(tag = 6)]
+    pub operator_uin: Option<u32>,
+    #[proto(tag = 7)]
+    pub time: Option<u32>,
+}
+
+impl GroupEssenceNotice {
+    /// `set_flag` of a message added to the list, it is `2` for removals
+    pub const SET: u32 = 1;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRecallNotice {
+    #[proto(tag = 1)]
+    pub operator_uid: Option<String>,
+    #[proto(tag = 3)]
+    pub messages: Vec<GroupRecallNoticeMessage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRecallNoticeMessage {
+    #[proto(tag = 1)]
+    pub sequence: Option<u32>,
+    #[proto(tag = 2)]
+    pub time: Option<u32>,
+    #[proto(tag = 3)]
+    pub random: Option<u32>,
+    #[proto(tag = 6)]
+    pub author_uid: Option<String>,
+}
+
+/// Gray notice line rendered from a template, e.g. "A 戳了戳 B"
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GeneralGrayTip {
+    #[proto(tag = 1)]
+    pub busi_type: Option<u64>,
+    /// Kind of tip, see [`GeneralGrayTip::POKE`]
+    #[proto(tag = 2)]
+    pub busi_id: Option<u64>,
+    #[proto(tag = 6)]
+    pub templ_id: Option<u64>,
+    #[proto(tag = 7)]
+    pub templ_params: Vec<GrayTipTemplParam>,
+    #[proto(tag = 8)]
+    pub content: Option<String>,
+}
+
+impl GeneralGrayTip {
+    pub const POKE: u64 = 1061;
+    /// A member gained a group honor
+    pub const HONOR: u64 = 1052;
+    /// A special title was granted
+    pub const TITLE: u64 = 2407;
+
+    /// Value of the template parameter `name`
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.templ_params
+            .iter()
+            .find(|param| param.name.as_deref() == Some(name))
+            .and_then(|param| param.value.as_deref())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GrayTipTemplParam {
+    #[proto(tag = 1)]
+    pub name: Option<String>,
+    #[proto(tag = 2)]
+    pub value: Option<String>,
+}
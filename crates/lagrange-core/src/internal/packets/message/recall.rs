@@ -0,0 +1,79 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `trpc.msg.msg_svc.MsgService.SsoGroupRecallMsg`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRecallRequest {
+    #[proto(tag = 1)]
+    pub recall_type: u32,
+    #[proto(tag = 2)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub info: Option<GroupRecallInfo>,
+    #[proto(tag = 4)]
+    pub settings: Option<GroupRecallSettings>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRecallInfo {
+    #[proto(tag = 1)]
+    pub sequence: u32,
+    #[proto(tag = 2)]
+    pub random: u32,
+    #[proto(tag = 3)]
+    pub field3: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRecallSettings {
+    #[proto(tag = 1)]
+    pub field1: u32,
+}
+
+/// Body of `trpc.msg.msg_svc.MsgService.SsoC2CRecallMsg`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRecallRequest {
+    #[proto(tag = 1)]
+    pub recall_type: u32,
+    #[proto(tag = 3)]
+    pub target_uid: String,
+    #[proto(tag = 4)]
+    pub info: Option<FriendRecallInfo>,
+    #[proto(tag = 5)]
+    pub settings: Option<FriendRecallSettings>,
+    #[proto(tag = 6)]
+    pub field6: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRecallInfo {
+    #[proto(tag = 1)]
+    pub client_sequence: u32,
+    #[proto(tag = 2)]
+    pub random: u32,
+    /// `0x01000000 << 32 | random`
+    #[proto(tag = 3)]
+    pub message_id: u64,
+    #[proto(tag = 4)]
+    pub timestamp: u32,
+    #[proto(tag = 5)]
+    pub field5: u32,
+    #[proto(tag = 6)]
+    pub message_sequence: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRecallSettings {
+    #[proto(tag = 1)]
+    pub field1: bool,
+    #[proto(tag = 2)]
+    pub field2: bool,
+}
+
+/// Response of both recall commands
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct RecallResponse {
+    #[proto(tag = 1)]
+    pub result: Option<u32>,
+    #[proto(tag = 2)]
+    pub err_msg: Option<String>,
+}
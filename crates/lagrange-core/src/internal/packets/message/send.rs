@@ -0,0 +1,102 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+use super::Elem;
+
+/// Request body of `MessageSvc.PbSendMsg`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PbSendMsg {
+    #[proto(tag = 1)]
+    pub routing_head: Option<RoutingHead>,
+    #[proto(tag = 2)]
+    pub content_head: Option<ContentHead>,
+    #[proto(tag = 3)]
+    pub message_body: Option<MessageBody>,
+    #[proto(tag = 4)]
+    pub client_sequence: Option<u32>,
+    #[proto(tag = 5)]
+    pub random: Option<u32>,
+    #[proto(tag = 6)]
+    pub sync_cookie: Option<Vec<u8>>,
+    #[proto(tag = 8)]
+    pub via: Option<u32>,
+    #[proto(tag = 9)]
+    pub data_statistics: Option<u32>,
+    #[proto(tag = 12)]
+    pub ctrl: Option<MessageControl>,
+    #[proto(tag = 14)]
+    pub multi_send_seq: Option<u32>,
+}
+
+/// Where the message is delivered to; exactly one field is set
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct RoutingHead {
+    #[proto(tag = 1)]
+    pub c2c: Option<C2c>,
+    #[proto(tag = 2)]
+    pub grp: Option<Grp>,
+}
+
+/// Routing to a friend (C2C)
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct C2c {
+    #[proto(tag = 1)]
+    pub uin: Option<u32>,
+    #[proto(tag = 2)]
+    pub uid: Option<String>,
+}
+
+/// Routing to a group
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct Grp {
+    #[proto(tag = 1)]
+    pub group_code: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ContentHead {
+    #[proto(tag = 1)]
+    pub pkg_num: Option<u32>,
+    #[proto(tag = 2)]
+    pub pkg_index: Option<u32>,
+    #[proto(tag = 3)]
+    pub div_seq: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MessageBody {
+    #[proto(tag = 1)]
+    pub rich_text: Option<RichText>,
+    #[proto(tag = 2)]
+    pub msg_content: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    pub msg_encrypt_content: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct RichText {
+    #[proto(tag = 2)]
+    pub elems: Vec<Elem>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MessageControl {
+    /// `int32` on the wire; kept unsigned because `i32` fields are zigzag-encoded
+    #[proto(tag = 1)]
+    pub msg_flag: Option<u32>,
+}
+
+/// Response body of `MessageSvc.PbSendMsg`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PbSendMsgResp {
+    /// `int32` on the wire; kept unsigned because `i32` fields are zigzag-encoded
+    #[proto(tag = 1)]
+    pub result: Option<u32>,
+    #[proto(tag = 2)]
+    pub err_msg: Option<String>,
+    #[proto(tag = 3)]
+    pub send_time: Option<u32>,
+    #[proto(tag = 11)]
+    pub group_sequence: Option<u32>,
+    #[proto(tag = 14)]
+    pub private_sequence: Option<u32>,
+}
@@ -0,0 +1,217 @@
+pub mod at_all;
+pub mod fetch_friends;
+pub mod fetch_groups;
+pub mod fetch_members;
+pub mod cookie;
+pub mod essence;
+pub mod fetch_user_info;
+pub mod group_admin;
+pub mod group_extra;
+pub mod group_file;
+pub mod group_requests;
+pub mod poke;
+pub mod reaction;
+pub mod request_action;
+pub mod rich_media;
+pub mod stranger_info;
+
+#[allow(unused_imports)]
+pub use at_all::{AtAllRemainRequest, AtAllRemainResponse};
+#[allow(unused_imports)]
+pub use fetch_friends::{
+    FriendCategory, FriendLayer1, FriendNumberProperty, FriendProperty, FriendPropertyGroup,
+    FriendsRequest, FriendsRequestBody, FriendsRequestNext, FriendsRequestNumbers, FriendsResponse,
+    FriendsResponseNext, OidbFriend,
+};
+#[allow(unused_imports)]
+pub use fetch_groups::{
+    GroupsRequest, GroupsRequestConfig, GroupsRequestFields, GroupsResponse, OidbGroup,
+    OidbGroupInfo, OidbGroupOwner,
+};
+#[allow(unused_imports)]
+pub use fetch_members::{
+    MembersRequest, MembersRequestFields, MembersResponse, OidbGroupMember, OidbGroupMemberCard,
+    OidbGroupMemberId, OidbGroupMemberLevel,
+};
+#[allow(unused_imports)]
+pub use cookie::{ClientKeyRequest, PsKeyEntry, PsKeyRequest, WebKeyResponse};
+#[allow(unused_imports)]
+pub use essence::EssenceRequest;
+#[allow(unused_imports)]
+pub use fetch_user_info::{
+    UserInfoBody, UserInfoBytesProperty, UserInfoKey, UserInfoNumberProperty, UserInfoProperties,
+    UserInfoRequestByUid, UserInfoRequestByUin, UserInfoResponse,
+};
+#[allow(unused_imports)]
+pub use group_admin::{
+    GroupSettings, GroupSettingsRequest, KickMemberRequest, MuteMemberBody, MuteMemberRequest,
+    SetAdminRequest, SetMemberCardBody, SetMemberCardRequest,
+};
+#[allow(unused_imports)]
+pub use group_extra::{
+    GroupExtraFields, GroupExtraInfo, GroupExtraRequest, GroupExtraRequestGroup,
+    GroupExtraResponse, GroupExtraResponseGroup,
+};
+#[allow(unused_imports)]
+pub use group_file::{
+    FileUploadBusiness, FileUploadClientInfo, FileUploadEntry, FileUploadExt, FileUploadFileEntry,
+    FileUploadFileName, FileUploadHost, FileUploadHosts, FileUploadUrl, GroupFileDownloadRequest,
+    GroupFileDownloadResponse, GroupFileFeed,
+    GroupFileFeedRequest, GroupFileFeedResponse, GroupFileFeedResult, GroupFileFeeds,
+    GroupFileRequest, GroupFileResponse, GroupFileUploadRequest, GroupFileUploadResponse,
+    GroupFileDeleteRequest, GroupFileInfo, GroupFileListItem, GroupFileListPage, GroupFileListQuery,
+    GroupFileListRequest, GroupFileListResponse, GroupFileMoveRequest, GroupFileRenameRequest, GroupFileResult,
+    GroupFolderCreateRequest, GroupFolderDeleteRequest, GroupFolderInfo, GroupFolderRenameRequest,
+    GroupFolderRequest, GroupFolderResponse,
+};
+#[allow(unused_imports)]
+pub use group_requests::{
+    GroupRequestsRequest, GroupRequestsResponse, OidbGroupRequest, OidbGroupRequestGroup, OidbGroupRequestUser,
+};
+#[allow(unused_imports)]
+pub use poke::PokeRequest;
+#[allow(unused_imports)]
+pub use reaction::ReactionRequest;
+#[allow(unused_imports)]
+pub use request_action::{
+    FriendRequestActionRequest, GroupRequestActionBody, GroupRequestActionRequest,
+};
+#[allow(unused_imports)]
+pub use rich_media::{
+    C2cUserInfo, ClientMeta, CommonHead, DownloadRKeyReq, DownloadRKeyResp, DownloadReq, ExtBizInfo, FileInfo, FileType, IndexNode,
+    MsgInfo, MsgInfoBody, MultiMediaReqHead, NtGroupInfo, NtHighwayDomain, NtHighwayExt,
+    NtHighwayHash, NtHighwayIpv4, NtHighwayNetwork, NtV2RichMediaReq, NtV2RichMediaResp,
+    PicExtBizInfo, PicUrlExtInfo, PictureInfo, PttExtBizInfo, RKeyInfo, SceneInfo, UploadInfo,
+    UploadReq,
+};
+#[allow(unused_imports)]
+pub use stranger_info::{OidbStranger, StrangerInfoRequest, StrangerInfoResponse};
+
+use crate::error::OidbError;
+use bytes::Bytes;
+use lagrange_proto::{DecodeOptions, ProtoBuilder, ProtoDecode, ProtoEncode, ProtoMessage};
+
+/// Envelope shared by all `OidbSvcTrpcTcp.0x{command}_{sub_command}` requests and responses
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbSvcTrpcTcpBase {
+    #[proto(tag = 1)]
+    pub command: u32,
+    #[proto(tag = 2)]
+    pub sub_command: u32,
+    /// Non-zero on failure, with the reason in `error_msg`
+    #[proto(tag = 3)]
+    pub error_code: Option<u32>,
+    /// Encoded command-specific body
+    #[proto(tag = 4)]
+    pub body: Option<Vec<u8>>,
+    #[proto(tag = 5)]
+    pub error_msg: Option<String>,
+    /// 1 when the body addresses users by uid instead of uin
+    #[proto(tag = 12)]
+    pub reserved: Option<u32>,
+}
+
+/// Wrap `body` in the envelope of `OidbSvcTrpcTcp.0x{command}_{sub_command}`.
+///
+/// `lafter` marks bodies that address users by uid instead of uin.
+pub fn build_oidb(command: u32, sub_command: u32, body: &impl ProtoEncode, lafter: bool) -> crate::error::Result<Bytes> {
+    let mut encoded = bytes::BytesMut::with_capacity(body.encoded_size());
+    body.encode(&mut encoded)
+        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+
+    let oidb = OidbSvcTrpcTcpBase {
+        command,
+        sub_command,
+        body: Some(encoded.to_vec()),
+        reserved: lafter.then_some(1),
+        ..Default::default()
+    };
+    oidb.encode_to_bytes()
+        .map_err(|e| crate::error::Error::BuildError(e.to_string()))
+}
+
+/// Unwrap an OIDB response and decode its body, failing on a non-zero result code
+pub fn parse_oidb<T: ProtoDecode>(input: &[u8]) -> Result<T, OidbError> {
+    parse_oidb_with_options(input, DecodeOptions::default())
+}
+
+/// Like [`parse_oidb`], decoding the response under the limits of the service's
+/// [`ServiceMetadata::decode_options`](crate::protocol::ServiceMetadata::decode_options)
+pub fn parse_oidb_with_options<T: ProtoDecode>(input: &[u8], options: DecodeOptions) -> Result<T, OidbError> {
+    let body = unwrap_oidb_with_options(input, options)?;
+    T::decode_with_options(&body, options).map_err(|e| OidbError::Malformed(e.to_string()))
+}
+
+/// Unwrap an OIDB response whose body is not needed, or is decoded by hand
+pub fn unwrap_oidb(input: &[u8]) -> Result<Vec<u8>, OidbError> {
+    unwrap_oidb_with_options(input, DecodeOptions::default())
+}
+
+fn unwrap_oidb_with_options(input: &[u8], options: DecodeOptions) -> Result<Vec<u8>, OidbError> {
+    let oidb = OidbSvcTrpcTcpBase::decode_with_options(input, options)
+        .map_err(|e| OidbError::Malformed(e.to_string()))?;
+    match oidb.error_code {
+        Some(code @ 1..) => Err(OidbError::Failed {
+            command: oidb.command,
+            sub_command: oidb.sub_command,
+            code,
+            message: oidb.error_msg.unwrap_or_default(),
+        }),
+        _ => Ok(oidb.body.unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_oidb() {
+        let body = PokeRequest { uin: 10001, group_uin: Some(123456), ..Default::default() };
+
+        let bytes = build_oidb(0xed3, 1, &body, false).unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command, oidb.reserved), (0xed3, 1, None));
+        assert_eq!(oidb.body, Some(body.encode_to_vec().unwrap()));
+
+        let lafter = OidbSvcTrpcTcpBase::decode_from_slice(&build_oidb(0xed3, 1, &body, true).unwrap()).unwrap();
+        assert_eq!(lafter.reserved, Some(1));
+    }
+
+    #[test]
+    fn test_parse_oidb() {
+        let body = PokeRequest { uin: 10001, friend_uin: Some(10001), ..Default::default() };
+        let response = OidbSvcTrpcTcpBase {
+            command: 0xed3,
+            sub_command: 1,
+            error_code: Some(0),
+            body: Some(body.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+
+        let parsed: PokeRequest = parse_oidb(&response.encode_to_vec().unwrap()).unwrap();
+        assert_eq!(parsed, body);
+    }
+
+    #[test]
+    fn test_parse_oidb_failure() {
+        let response = OidbSvcTrpcTcpBase {
+            command: 0xed3,
+            sub_command: 1,
+            error_code: Some(10003),
+            error_msg: Some("no permission".to_string()),
+            ..Default::default()
+        };
+
+        let error = parse_oidb::<PokeRequest>(&response.encode_to_vec().unwrap()).unwrap_err();
+        assert_eq!(
+            error,
+            OidbError::Failed { command: 0xed3, sub_command: 1, code: 10003, message: "no permission".to_string() }
+        );
+        assert_eq!(error.result(), Some((10003, "no permission")));
+        assert_eq!(error.to_string(), "OIDB 0xed3_1 failed (10003): no permission");
+
+        // Field 1 with a truncated varint
+        assert!(matches!(parse_oidb::<PokeRequest>(&[0x08, 0x80]), Err(OidbError::Malformed(_))));
+    }
+}
@@ -0,0 +1,30 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x8a7_0`, asks how many @all messages are left today
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct AtAllRemainRequest {
+    #[proto(tag = 1)]
+    pub sub_command: u32,
+    #[proto(tag = 2)]
+    pub limit_interval_type_for_uin: u32,
+    #[proto(tag = 3)]
+    pub limit_interval_type_for_group: u32,
+    #[proto(tag = 4)]
+    pub uin: u64,
+    #[proto(tag = 5)]
+    pub group_uin: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct AtAllRemainResponse {
+    #[proto(tag = 1)]
+    pub can_at_all: bool,
+    #[proto(tag = 2)]
+    pub remain_for_uin: u32,
+    #[proto(tag = 3)]
+    pub remain_for_group: u32,
+    #[proto(tag = 4)]
+    pub prompt_msg1: Option<String>,
+    #[proto(tag = 5)]
+    pub prompt_msg2: Option<String>,
+}
@@ -0,0 +1,32 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x102a_0`, asks for the pskeys of web domains
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PsKeyRequest {
+    #[proto(tag = 1)]
+    pub domains: Vec<String>,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x102a_1`, asks for a client key
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ClientKeyRequest {}
+
+/// Response of both `OidbSvcTrpcTcp.0x102a_0` and `0x102a_1`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct WebKeyResponse {
+    #[proto(tag = 1)]
+    pub ps_keys: Vec<PsKeyEntry>,
+    #[proto(tag = 3)]
+    pub client_key: Option<String>,
+    /// Lifetime of the client key in seconds
+    #[proto(tag = 4)]
+    pub expiration: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PsKeyEntry {
+    #[proto(tag = 1)]
+    pub domain: String,
+    #[proto(tag = 2)]
+    pub key: String,
+}
@@ -0,0 +1,13 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0xeac_1` and `0xeac_2`, adds a message to or removes it from the
+/// essence list of a group
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct EssenceRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub sequence: u32,
+    #[proto(tag = 3)]
+    pub random: u32,
+}
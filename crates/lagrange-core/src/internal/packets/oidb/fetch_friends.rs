@@ -0,0 +1,125 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0xfd4_1`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendsRequest {
+    /// Page size
+    #[proto(tag = 2)]
+    pub count: u32,
+    #[proto(tag = 4)]
+    pub field4: u32,
+    /// Continuation token from the previous page, absent for the first one
+    #[proto(tag = 5)]
+    pub next: Option<FriendsRequestNext>,
+    #[proto(tag = 6)]
+    pub field6: u32,
+    #[proto(tag = 7)]
+    pub field7: u32,
+    /// Properties requested for every friend
+    #[proto(tag = 10001)]
+    pub body: Vec<FriendsRequestBody>,
+    #[proto(tag = 10002)]
+    pub field10002: Vec<u32>,
+    #[proto(tag = 10003)]
+    pub field10003: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendsRequestNext {
+    #[proto(tag = 1)]
+    pub uin: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendsRequestBody {
+    #[proto(tag = 1)]
+    pub property_type: u32,
+    #[proto(tag = 2)]
+    pub numbers: Option<FriendsRequestNumbers>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendsRequestNumbers {
+    #[proto(tag = 1)]
+    pub numbers: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendsResponse {
+    /// Set while more pages are available
+    #[proto(tag = 2)]
+    pub next: Option<FriendsResponseNext>,
+    #[proto(tag = 3)]
+    pub display_friend_count: Option<u32>,
+    #[proto(tag = 6)]
+    pub timestamp: Option<u32>,
+    #[proto(tag = 7)]
+    pub self_uin: Option<u32>,
+    #[proto(tag = 101)]
+    pub friends: Vec<OidbFriend>,
+    #[proto(tag = 102)]
+    pub categories: Vec<FriendCategory>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendsResponseNext {
+    #[proto(tag = 1)]
+    pub uin: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbFriend {
+    #[proto(tag = 1)]
+    pub uid: Option<String>,
+    #[proto(tag = 2)]
+    pub category_id: Option<u32>,
+    #[proto(tag = 3)]
+    pub uin: Option<u32>,
+    #[proto(tag = 10001)]
+    pub additional: Vec<FriendPropertyGroup>,
+}
+
+/// Properties of one [`FriendsRequestBody::property_type`]
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendPropertyGroup {
+    #[proto(tag = 1)]
+    pub property_type: Option<u32>,
+    #[proto(tag = 2)]
+    pub layer1: Option<FriendLayer1>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendLayer1 {
+    #[proto(tag = 1)]
+    pub number_properties: Vec<FriendNumberProperty>,
+    #[proto(tag = 2)]
+    pub properties: Vec<FriendProperty>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendNumberProperty {
+    #[proto(tag = 1)]
+    pub code: u32,
+    #[proto(tag = 2)]
+    pub value: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendProperty {
+    #[proto(tag = 1)]
+    pub code: u32,
+    #[proto(tag = 2)]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendCategory {
+    #[proto(tag = 1)]
+    pub category_id: u32,
+    #[proto(tag = 2)]
+    pub category_name: Option<String>,
+    #[proto(tag = 3)]
+    pub category_member_count: Option<u32>,
+    #[proto(tag = 4)]
+    pub sort_id: Option<u32>,
+}
@@ -0,0 +1,81 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0xfe5_2`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupsRequest {
+    #[proto(tag = 1)]
+    pub config: Option<GroupsRequestConfig>,
+    /// Continuation token from the previous page, absent for the first one
+    #[proto(tag = 2)]
+    pub cookie: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupsRequestConfig {
+    #[proto(tag = 1)]
+    pub info: Option<GroupsRequestFields>,
+}
+
+/// Which [`OidbGroupInfo`] fields the server should fill in
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupsRequestFields {
+    #[proto(tag = 1)]
+    pub owner: bool,
+    #[proto(tag = 2)]
+    pub create_time: bool,
+    #[proto(tag = 3)]
+    pub max_member: bool,
+    #[proto(tag = 4)]
+    pub member_count: bool,
+    #[proto(tag = 5)]
+    pub group_name: bool,
+    #[proto(tag = 18)]
+    pub description: bool,
+    #[proto(tag = 19)]
+    pub question: bool,
+    #[proto(tag = 30)]
+    pub announcement: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupsResponse {
+    #[proto(tag = 2)]
+    pub groups: Vec<OidbGroup>,
+    /// Set while more pages are available
+    #[proto(tag = 3)]
+    pub next_cookie: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroup {
+    #[proto(tag = 3)]
+    pub group_uin: u32,
+    #[proto(tag = 4)]
+    pub info: Option<OidbGroupInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupInfo {
+    #[proto(tag = 1)]
+    pub owner: Option<OidbGroupOwner>,
+    #[proto(tag = 2)]
+    pub create_time: Option<u32>,
+    #[proto(tag = 3)]
+    pub max_member: Option<u32>,
+    #[proto(tag = 4)]
+    pub member_count: Option<u32>,
+    #[proto(tag = 5)]
+    pub group_name: Option<String>,
+    #[proto(tag = 18)]
+    pub description: Option<String>,
+    #[proto(tag = 19)]
+    pub question: Option<String>,
+    #[proto(tag = 30)]
+    pub announcement: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupOwner {
+    #[proto(tag = 2)]
+    pub uid: Option<String>,
+}
@@ -0,0 +1,97 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0xfe7_3`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MembersRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub field2: u32,
+    #[proto(tag = 3)]
+    pub field3: u32,
+    #[proto(tag = 4)]
+    pub fields: Option<MembersRequestFields>,
+    /// Continuation token from the previous page, absent for the first one
+    #[proto(tag = 15)]
+    pub token: Option<String>,
+}
+
+/// Which [`OidbGroupMember`] fields the server should fill in
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MembersRequestFields {
+    #[proto(tag = 10)]
+    pub member_name: bool,
+    #[proto(tag = 11)]
+    pub member_card: bool,
+    #[proto(tag = 12)]
+    pub level: bool,
+    #[proto(tag = 17)]
+    pub special_title: bool,
+    #[proto(tag = 100)]
+    pub join_timestamp: bool,
+    #[proto(tag = 101)]
+    pub last_msg_timestamp: bool,
+    #[proto(tag = 102)]
+    pub shut_up_timestamp: bool,
+    #[proto(tag = 107)]
+    pub permission: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MembersResponse {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub members: Vec<OidbGroupMember>,
+    #[proto(tag = 5)]
+    pub member_change_seq: Option<u32>,
+    #[proto(tag = 6)]
+    pub member_card_change_seq: Option<u32>,
+    /// Set while more pages are available
+    #[proto(tag = 15)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupMember {
+    #[proto(tag = 1)]
+    pub id: Option<OidbGroupMemberId>,
+    #[proto(tag = 10)]
+    pub member_name: Option<String>,
+    #[proto(tag = 11)]
+    pub member_card: Option<OidbGroupMemberCard>,
+    #[proto(tag = 12)]
+    pub level: Option<OidbGroupMemberLevel>,
+    #[proto(tag = 17)]
+    pub special_title: Option<String>,
+    #[proto(tag = 100)]
+    pub join_timestamp: Option<u32>,
+    #[proto(tag = 101)]
+    pub last_msg_timestamp: Option<u32>,
+    /// Absent or in the past when the member is not muted
+    #[proto(tag = 102)]
+    pub shut_up_timestamp: Option<u32>,
+    /// 0 for a member, 1 for the owner, 2 for an admin
+    #[proto(tag = 107)]
+    pub permission: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupMemberId {
+    #[proto(tag = 2)]
+    pub uid: Option<String>,
+    #[proto(tag = 4)]
+    pub uin: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupMemberCard {
+    #[proto(tag = 2)]
+    pub member_card: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupMemberLevel {
+    #[proto(tag = 2)]
+    pub level: Option<u32>,
+}
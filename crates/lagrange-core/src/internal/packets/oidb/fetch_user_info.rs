@@ -0,0 +1,69 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0xfe1_2` addressing the user by uin
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoRequestByUin {
+    #[proto(tag = 1)]
+    pub uin: u32,
+    #[proto(tag = 3)]
+    pub field3: u32,
+    #[proto(tag = 4)]
+    pub keys: Vec<UserInfoKey>,
+}
+
+/// Body of `OidbSvcTrpcTcp.0xfe1_2` addressing the user by uid; the envelope has `reserved = 1`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoRequestByUid {
+    #[proto(tag = 1)]
+    pub uid: String,
+    #[proto(tag = 3)]
+    pub field3: u32,
+    #[proto(tag = 4)]
+    pub keys: Vec<UserInfoKey>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoKey {
+    #[proto(tag = 1)]
+    pub key: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoResponse {
+    #[proto(tag = 1)]
+    pub body: Option<UserInfoBody>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoBody {
+    #[proto(tag = 1)]
+    pub uid: Option<String>,
+    #[proto(tag = 2)]
+    pub properties: Option<UserInfoProperties>,
+    #[proto(tag = 3)]
+    pub uin: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoProperties {
+    #[proto(tag = 1)]
+    pub numbers: Vec<UserInfoNumberProperty>,
+    #[proto(tag = 2)]
+    pub bytes: Vec<UserInfoBytesProperty>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoNumberProperty {
+    #[proto(tag = 1)]
+    pub key: u32,
+    #[proto(tag = 2)]
+    pub value: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UserInfoBytesProperty {
+    #[proto(tag = 1)]
+    pub key: u32,
+    #[proto(tag = 2)]
+    pub value: Option<Vec<u8>>,
+}
@@ -0,0 +1,82 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x1253_1`, mutes a single member
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MuteMemberRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub mute_type: u32,
+    #[proto(tag = 3)]
+    pub body: Option<MuteMemberBody>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MuteMemberBody {
+    #[proto(tag = 1)]
+    pub target_uid: String,
+    /// Seconds, `0` lifts the mute
+    #[proto(tag = 2)]
+    pub duration: u32,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x89a_0` and `0x89a_15`, updates group-wide settings
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupSettingsRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub settings: Option<GroupSettings>,
+}
+
+/// Only the settings that are present are changed
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupSettings {
+    #[proto(tag = 3)]
+    pub group_name: Option<String>,
+    /// `u32::MAX` mutes everyone but admins, `0` lifts it
+    #[proto(tag = 17)]
+    pub mute_all: Option<u32>,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x8a0_1`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct KickMemberRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub target_uid: String,
+    #[proto(tag = 4)]
+    pub reject_add_request: bool,
+    #[proto(tag = 5)]
+    pub reason: String,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x1096_1`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SetAdminRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub target_uid: String,
+    #[proto(tag = 3)]
+    pub is_admin: bool,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x8fc_3`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SetMemberCardRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub body: Option<SetMemberCardBody>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SetMemberCardBody {
+    #[proto(tag = 1)]
+    pub target_uid: String,
+    /// Empty resets the card to the nickname
+    #[proto(tag = 8)]
+    pub card: String,
+}
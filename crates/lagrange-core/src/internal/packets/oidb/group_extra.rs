@@ -0,0 +1,56 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x88d_0`, extended information of several groups at once
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupExtraRequest {
+    #[proto(tag = 1)]
+    pub random: u32,
+    #[proto(tag = 2)]
+    pub groups: Vec<GroupExtraRequestGroup>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupExtraRequestGroup {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub fields: Option<GroupExtraFields>,
+}
+
+/// Which [`GroupExtraInfo`] fields the server should fill in
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupExtraFields {
+    #[proto(tag = 24)]
+    pub question: bool,
+    #[proto(tag = 30)]
+    pub description: bool,
+    #[proto(tag = 45)]
+    pub announcement: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupExtraResponse {
+    #[proto(tag = 1)]
+    pub groups: Vec<GroupExtraResponseGroup>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupExtraResponseGroup {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    /// Per-group result, non-zero when the group could not be queried
+    #[proto(tag = 2)]
+    pub result: Option<u32>,
+    #[proto(tag = 3)]
+    pub info: Option<GroupExtraInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupExtraInfo {
+    #[proto(tag = 24)]
+    pub question: Option<String>,
+    #[proto(tag = 30)]
+    pub description: Option<String>,
+    #[proto(tag = 45)]
+    pub announcement: Option<String>,
+}
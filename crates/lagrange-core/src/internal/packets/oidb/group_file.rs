@@ -0,0 +1,484 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x6d6_*`, one of the operations is set
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileRequest {
+    #[proto(tag = 1)]
+    pub upload: Option<GroupFileUploadRequest>,
+    #[proto(tag = 3)]
+    pub download: Option<GroupFileDownloadRequest>,
+    #[proto(tag = 4)]
+    pub delete: Option<GroupFileDeleteRequest>,
+    #[proto(tag = 5)]
+    pub rename: Option<GroupFileRenameRequest>,
+    #[proto(tag = 6)]
+    pub move_file: Option<GroupFileMoveRequest>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileUploadRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub app_id: u32,
+    /// 102 for permanent group files
+    #[proto(tag = 3)]
+    pub bus_id: u32,
+    #[proto(tag = 4)]
+    pub entrance: u32,
+    /// Folder id, `/` for the root
+    #[proto(tag = 5)]
+    pub parent_folder_id: String,
+    #[proto(tag = 6)]
+    pub file_name: String,
+    #[proto(tag = 7)]
+    pub local_path: String,
+    #[proto(tag = 8)]
+    pub file_size: u64,
+    #[proto(tag = 9)]
+    pub sha1: Option<Vec<u8>>,
+    #[proto(tag = 10)]
+    pub sha3: Option<Vec<u8>>,
+    #[proto(tag = 11)]
+    pub md5: Option<Vec<u8>>,
+    #[proto(tag = 15)]
+    pub support_multi_upload: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileResponse {
+    #[proto(tag = 1)]
+    pub upload: Option<GroupFileUploadResponse>,
+    #[proto(tag = 3)]
+    pub download: Option<GroupFileDownloadResponse>,
+    #[proto(tag = 4)]
+    pub delete: Option<GroupFileResult>,
+    #[proto(tag = 5)]
+    pub rename: Option<GroupFileResult>,
+    #[proto(tag = 6)]
+    pub move_file: Option<GroupFileResult>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileUploadResponse {
+    /// int32 on the wire, negative codes arrive sign-extended
+    #[proto(tag = 1)]
+    pub ret_code: u64,
+    #[proto(tag = 2)]
+    pub ret_msg: String,
+    #[proto(tag = 3)]
+    pub client_wording: String,
+    #[proto(tag = 4)]
+    pub upload_ip: String,
+    #[proto(tag = 5)]
+    pub server_dns: String,
+    #[proto(tag = 6)]
+    pub bus_id: u32,
+    #[proto(tag = 7)]
+    pub file_id: String,
+    #[proto(tag = 8)]
+    pub check_key: Option<Vec<u8>>,
+    #[proto(tag = 9)]
+    pub file_key: Option<Vec<u8>>,
+    /// Set when the server already has a file with these hashes
+    #[proto(tag = 10)]
+    pub file_exist: bool,
+    #[proto(tag = 12)]
+    pub upload_ip_lan_v4: Vec<String>,
+    #[proto(tag = 13)]
+    pub upload_ip_lan_v6: Vec<String>,
+    #[proto(tag = 14)]
+    pub upload_port: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileDownloadRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub app_id: u32,
+    #[proto(tag = 3)]
+    pub bus_id: u32,
+    #[proto(tag = 4)]
+    pub file_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileDownloadResponse {
+    /// int32 on the wire, negative codes arrive sign-extended
+    #[proto(tag = 1)]
+    pub ret_code: u64,
+    #[proto(tag = 2)]
+    pub ret_msg: String,
+    #[proto(tag = 3)]
+    pub client_wording: String,
+    #[proto(tag = 4)]
+    pub download_ip: String,
+    #[proto(tag = 5)]
+    pub download_dns: String,
+    /// Path key of the download, hex-encoded into the url
+    #[proto(tag = 6)]
+    pub download_url: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileDeleteRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub bus_id: u32,
+    #[proto(tag = 5)]
+    pub file_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileRenameRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub bus_id: u32,
+    #[proto(tag = 4)]
+    pub file_id: String,
+    /// Folder the file is in
+    #[proto(tag = 5)]
+    pub parent_folder_id: String,
+    #[proto(tag = 6)]
+    pub new_file_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileMoveRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub app_id: u32,
+    #[proto(tag = 3)]
+    pub bus_id: u32,
+    #[proto(tag = 4)]
+    pub file_id: String,
+    #[proto(tag = 5)]
+    pub parent_folder_id: String,
+    #[proto(tag = 6)]
+    pub target_folder_id: String,
+}
+
+/// Outcome of a group file or folder operation without a result of its own
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileResult {
+    /// int32 on the wire, negative codes arrive sign-extended
+    #[proto(tag = 1)]
+    pub ret_code: u64,
+    #[proto(tag = 2)]
+    pub ret_msg: String,
+    #[proto(tag = 3)]
+    pub client_wording: String,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x6d7_*`, one of the folder operations is set
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFolderRequest {
+    #[proto(tag = 1)]
+    pub create: Option<GroupFolderCreateRequest>,
+    #[proto(tag = 2)]
+    pub delete: Option<GroupFolderDeleteRequest>,
+    #[proto(tag = 3)]
+    pub rename: Option<GroupFolderRenameRequest>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFolderCreateRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    /// Folder the new one is created in, `/` for the root
+    #[proto(tag = 2)]
+    pub parent_folder_id: String,
+    #[proto(tag = 3)]
+    pub folder_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFolderDeleteRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub folder_id: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFolderRenameRequest {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub folder_id: String,
+    #[proto(tag = 4)]
+    pub new_folder_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFolderResponse {
+    #[proto(tag = 1)]
+    pub create: Option<GroupFileResult>,
+    #[proto(tag = 2)]
+    pub delete: Option<GroupFileResult>,
+    #[proto(tag = 3)]
+    pub rename: Option<GroupFileResult>,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x6d8_1`, which lists a folder page by page
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileListRequest {
+    #[proto(tag = 1)]
+    pub list: Option<GroupFileListQuery>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileListQuery {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub app_id: u32,
+    #[proto(tag = 3)]
+    pub folder_id: String,
+    /// Entries per page
+    #[proto(tag = 5)]
+    pub file_count: u32,
+    #[proto(tag = 9)]
+    pub sort_by: u32,
+    /// Index of the first entry of the page
+    #[proto(tag = 13)]
+    pub start_index: u32,
+    #[proto(tag = 17)]
+    pub field17: u32,
+    #[proto(tag = 18)]
+    pub field18: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileListResponse {
+    #[proto(tag = 1)]
+    pub list: Option<GroupFileListPage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileListPage {
+    /// int32 on the wire, negative codes arrive sign-extended
+    #[proto(tag = 1)]
+    pub ret_code: u64,
+    #[proto(tag = 2)]
+    pub ret_msg: String,
+    #[proto(tag = 3)]
+    pub client_wording: String,
+    /// Set on the last page
+    #[proto(tag = 4)]
+    pub is_end: bool,
+    #[proto(tag = 5)]
+    pub items: Vec<GroupFileListItem>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileListItem {
+    /// 1 for files, 2 for folders
+    #[proto(tag = 1)]
+    pub item_type: u32,
+    #[proto(tag = 2)]
+    pub folder_info: Option<GroupFolderInfo>,
+    #[proto(tag = 3)]
+    pub file_info: Option<GroupFileInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFolderInfo {
+    #[proto(tag = 1)]
+    pub folder_id: String,
+    #[proto(tag = 2)]
+    pub parent_folder_id: String,
+    #[proto(tag = 3)]
+    pub folder_name: String,
+    #[proto(tag = 4)]
+    pub create_time: u32,
+    #[proto(tag = 5)]
+    pub modified_time: u32,
+    #[proto(tag = 6)]
+    pub creator_uin: u32,
+    #[proto(tag = 7)]
+    pub creator_name: String,
+    #[proto(tag = 8)]
+    pub total_file_count: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileInfo {
+    #[proto(tag = 1)]
+    pub file_id: String,
+    #[proto(tag = 2)]
+    pub file_name: String,
+    #[proto(tag = 3)]
+    pub file_size: u64,
+    #[proto(tag = 4)]
+    pub bus_id: u32,
+    #[proto(tag = 5)]
+    pub uploaded_size: u64,
+    #[proto(tag = 6)]
+    pub upload_time: u32,
+    #[proto(tag = 7)]
+    pub expire_time: u32,
+    #[proto(tag = 8)]
+    pub modified_time: u32,
+    #[proto(tag = 9)]
+    pub download_times: u32,
+    #[proto(tag = 10)]
+    pub sha1: Option<Vec<u8>>,
+    #[proto(tag = 11)]
+    pub sha3: Option<Vec<u8>>,
+    #[proto(tag = 12)]
+    pub md5: Option<Vec<u8>>,
+    #[proto(tag = 14)]
+    pub uploader_name: String,
+    #[proto(tag = 15)]
+    pub uploader_uin: u64,
+    #[proto(tag = 16)]
+    pub parent_folder_id: String,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x6d9_4`, which posts an uploaded file to the group
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileFeedRequest {
+    #[proto(tag = 5)]
+    pub feeds: Option<GroupFileFeeds>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileFeeds {
+    #[proto(tag = 1)]
+    pub group_uin: u64,
+    #[proto(tag = 2)]
+    pub app_id: u32,
+    #[proto(tag = 3)]
+    pub feeds: Vec<GroupFileFeed>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileFeed {
+    #[proto(tag = 1)]
+    pub bus_id: u32,
+    #[proto(tag = 2)]
+    pub file_id: String,
+    #[proto(tag = 3)]
+    pub msg_random: u32,
+    #[proto(tag = 5)]
+    pub feed_flag: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileFeedResponse {
+    #[proto(tag = 5)]
+    pub feeds: Option<GroupFileFeedResult>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupFileFeedResult {
+    /// int32 on the wire, negative codes arrive sign-extended
+    #[proto(tag = 1)]
+    pub ret_code: u64,
+    #[proto(tag = 2)]
+    pub ret_msg: String,
+    #[proto(tag = 3)]
+    pub client_wording: String,
+}
+
+/// Highway extension of file uploads, command id 71
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadExt {
+    #[proto(tag = 1)]
+    pub unknown1: u32,
+    #[proto(tag = 2)]
+    pub unknown2: u32,
+    #[proto(tag = 100)]
+    pub entry: Option<FileUploadEntry>,
+    #[proto(tag = 200)]
+    pub unknown200: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadEntry {
+    #[proto(tag = 100)]
+    pub busi_buff: Option<FileUploadBusiness>,
+    #[proto(tag = 200)]
+    pub file_entry: Option<FileUploadFileEntry>,
+    #[proto(tag = 300)]
+    pub client_info: Option<FileUploadClientInfo>,
+    #[proto(tag = 400)]
+    pub file_name_info: Option<FileUploadFileName>,
+    #[proto(tag = 500)]
+    pub host: Option<FileUploadHosts>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadBusiness {
+    #[proto(tag = 1)]
+    pub bus_id: u32,
+    #[proto(tag = 100)]
+    pub sender_uin: u64,
+    #[proto(tag = 200)]
+    pub receiver_uin: u64,
+    #[proto(tag = 400)]
+    pub group_code: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadFileEntry {
+    #[proto(tag = 100)]
+    pub file_size: u64,
+    #[proto(tag = 200)]
+    pub md5: Option<Vec<u8>>,
+    #[proto(tag = 300)]
+    pub check_key: Option<Vec<u8>>,
+    #[proto(tag = 400)]
+    pub md5_s2: Option<Vec<u8>>,
+    #[proto(tag = 600)]
+    pub file_id: String,
+    #[proto(tag = 700)]
+    pub upload_key: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadClientInfo {
+    #[proto(tag = 100)]
+    pub client_type: u32,
+    #[proto(tag = 200)]
+    pub app_id: String,
+    #[proto(tag = 300)]
+    pub terminal_type: u32,
+    #[proto(tag = 400)]
+    pub client_version: String,
+    #[proto(tag = 600)]
+    pub unknown: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadFileName {
+    #[proto(tag = 100)]
+    pub file_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadHosts {
+    #[proto(tag = 200)]
+    pub hosts: Vec<FileUploadHost>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadHost {
+    #[proto(tag = 1)]
+    pub url: Option<FileUploadUrl>,
+    #[proto(tag = 2)]
+    pub port: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileUploadUrl {
+    #[proto(tag = 1)]
+    pub unknown: u32,
+    #[proto(tag = 2)]
+    pub host: String,
+}
@@ -0,0 +1,60 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x10c0_1`, lists the latest join requests of the groups the bot
+/// manages
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRequestsRequest {
+    #[proto(tag = 1)]
+    pub count: u32,
+    #[proto(tag = 2)]
+    pub field2: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRequestsResponse {
+    /// Newest first
+    #[proto(tag = 1)]
+    pub requests: Vec<OidbGroupRequest>,
+    /// Sequence of the newest request
+    #[proto(tag = 3)]
+    pub latest_sequence: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupRequest {
+    #[proto(tag = 1)]
+    pub sequence: u64,
+    /// 1 for requests sent by the target, 22 for invitations
+    #[proto(tag = 2)]
+    pub event_type: u32,
+    /// 1 while pending, 2 once approved, 3 once rejected, 4 once ignored
+    #[proto(tag = 3)]
+    pub state: u32,
+    #[proto(tag = 4)]
+    pub group: Option<OidbGroupRequestGroup>,
+    #[proto(tag = 5)]
+    pub target: Option<OidbGroupRequestUser>,
+    #[proto(tag = 6)]
+    pub invitor: Option<OidbGroupRequestUser>,
+    /// Admin who handled the request
+    #[proto(tag = 7)]
+    pub operator: Option<OidbGroupRequestUser>,
+    #[proto(tag = 10)]
+    pub comment: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupRequestGroup {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+    #[proto(tag = 2)]
+    pub group_name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbGroupRequestUser {
+    #[proto(tag = 1)]
+    pub uid: String,
+    #[proto(tag = 2)]
+    pub name: String,
+}
@@ -0,0 +1,16 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0xed3_1`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PokeRequest {
+    #[proto(tag = 1)]
+    pub uin: u32,
+    /// Set when poking in a group
+    #[proto(tag = 2)]
+    pub group_uin: Option<u32>,
+    /// Set when poking a friend, same as `uin`
+    #[proto(tag = 5)]
+    pub friend_uin: Option<u32>,
+    #[proto(tag = 6)]
+    pub ext: u32,
+}
@@ -0,0 +1,24 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x9082_1` and `0x9082_2`, adds an emoji reaction to a group message
+/// or takes it back
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ReactionRequest {
+    #[proto(tag = 2)]
+    pub group_uin: u32,
+    #[proto(tag = 3)]
+    pub sequence: u32,
+    /// Id of the emoji as decimal string
+    #[proto(tag = 4)]
+    pub code: String,
+    /// See [`ReactionRequest::FACE`]
+    #[proto(tag = 5)]
+    pub emoji_type: u32,
+}
+
+impl ReactionRequest {
+    /// `emoji_type` of QQ faces
+    pub const FACE: u32 = 1;
+    /// `emoji_type` of Unicode emoji
+    pub const EMOJI: u32 = 2;
+}
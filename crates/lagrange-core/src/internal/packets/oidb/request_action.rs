@@ -0,0 +1,35 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0xb5d_44`, answers a friend request
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FriendRequestActionRequest {
+    /// 3 to accept, 5 to decline
+    #[proto(tag = 1)]
+    pub accept: u32,
+    #[proto(tag = 2)]
+    pub target_uid: String,
+}
+
+/// Body of `OidbSvcTrpcTcp.0x10c8_1`, answers a group join request
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRequestActionRequest {
+    /// 1 to approve, 2 to reject
+    #[proto(tag = 1)]
+    pub accept: u32,
+    #[proto(tag = 2)]
+    pub body: Option<GroupRequestActionBody>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct GroupRequestActionBody {
+    #[proto(tag = 1)]
+    pub sequence: u64,
+    /// 1 for requests sent by the target, 22 for invitations
+    #[proto(tag = 2)]
+    pub event_type: u32,
+    #[proto(tag = 3)]
+    pub group_uin: u32,
+    /// Reason shown to the target when rejected
+    #[proto(tag = 4)]
+    pub message: String,
+}
@@ -0,0 +1,444 @@
+use lagrange_proto::{ProtoBuilder, ProtoEncode, ProtoMessage};
+
+/// Body of the NT rich media commands (`0x11c4`, `0x11c5`, `0x126d`, `0x126e`, ...)
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtV2RichMediaReq {
+    #[proto(tag = 1)]
+    pub req_head: Option<MultiMediaReqHead>,
+    #[proto(tag = 2)]
+    pub upload: Option<UploadReq>,
+    #[proto(tag = 3)]
+    pub download: Option<DownloadReq>,
+    #[proto(tag = 35)]
+    pub download_rkey: Option<DownloadRKeyReq>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtV2RichMediaResp {
+    #[proto(tag = 1)]
+    pub resp_head: Option<MultiMediaRespHead>,
+    #[proto(tag = 2)]
+    pub upload: Option<UploadResp>,
+    #[proto(tag = 3)]
+    pub download: Option<DownloadResp>,
+    #[proto(tag = 35)]
+    pub download_rkey: Option<DownloadRKeyResp>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MultiMediaReqHead {
+    #[proto(tag = 1)]
+    pub common: Option<CommonHead>,
+    #[proto(tag = 2)]
+    pub scene: Option<SceneInfo>,
+    #[proto(tag = 3)]
+    pub client: Option<ClientMeta>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MultiMediaRespHead {
+    #[proto(tag = 1)]
+    pub common: Option<CommonHead>,
+    #[proto(tag = 2)]
+    pub ret_code: Option<u32>,
+    #[proto(tag = 3)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct CommonHead {
+    #[proto(tag = 1)]
+    pub request_id: u32,
+    /// 100 to upload, 200 to download
+    #[proto(tag = 2)]
+    pub command: u32,
+}
+
+/// Who the media is for
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct SceneInfo {
+    /// 2 for images, 1 for records
+    #[proto(tag = 101)]
+    pub request_type: u32,
+    /// 1 for images, 3 for records
+    #[proto(tag = 102)]
+    pub business_type: u32,
+    /// 1 for a friend, 2 for a group
+    #[proto(tag = 200)]
+    pub scene_type: u32,
+    #[proto(tag = 201)]
+    pub c2c: Option<C2cUserInfo>,
+    #[proto(tag = 202)]
+    pub group: Option<NtGroupInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct C2cUserInfo {
+    /// 2 when addressed by uid
+    #[proto(tag = 1)]
+    pub account_type: u32,
+    #[proto(tag = 2)]
+    pub target_uid: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtGroupInfo {
+    #[proto(tag = 1)]
+    pub group_uin: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ClientMeta {
+    #[proto(tag = 1)]
+    pub agent_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UploadReq {
+    #[proto(tag = 1)]
+    pub upload_info: Vec<UploadInfo>,
+    #[proto(tag = 2)]
+    pub try_fast_upload_completed: bool,
+    #[proto(tag = 3)]
+    pub srv_send_msg: bool,
+    #[proto(tag = 4)]
+    pub client_random_id: u64,
+    /// 1 for a friend, 2 for a group
+    #[proto(tag = 5)]
+    pub compat_q_msg_scene_type: u32,
+    #[proto(tag = 6)]
+    pub ext_biz_info: Option<ExtBizInfo>,
+    #[proto(tag = 7)]
+    pub client_seq: u32,
+    #[proto(tag = 8)]
+    pub no_need_compat_msg: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UploadInfo {
+    #[proto(tag = 1)]
+    pub file_info: Option<FileInfo>,
+    #[proto(tag = 2)]
+    pub sub_file_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileInfo {
+    #[proto(tag = 1)]
+    pub file_size: u32,
+    /// Md5 as lowercase hex
+    #[proto(tag = 2)]
+    pub file_hash: String,
+    /// Sha1 as lowercase hex
+    #[proto(tag = 3)]
+    pub file_sha1: String,
+    #[proto(tag = 4)]
+    pub file_name: String,
+    #[proto(tag = 5)]
+    pub file_type: Option<FileType>,
+    #[proto(tag = 6)]
+    pub width: u32,
+    #[proto(tag = 7)]
+    pub height: u32,
+    /// Duration in seconds, for records and videos
+    #[proto(tag = 8)]
+    pub time: u32,
+    #[proto(tag = 9)]
+    pub original: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct FileType {
+    /// 1 for images, 3 for records
+    #[proto(tag = 1)]
+    pub file_type: u32,
+    #[proto(tag = 2)]
+    pub pic_format: u32,
+    #[proto(tag = 3)]
+    pub video_format: u32,
+    /// 1 for silk
+    #[proto(tag = 4)]
+    pub voice_format: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct ExtBizInfo {
+    #[proto(tag = 1)]
+    pub pic: Option<PicExtBizInfo>,
+    #[proto(tag = 2)]
+    pub video: Option<VideoExtBizInfo>,
+    #[proto(tag = 3)]
+    pub ptt: Option<PttExtBizInfo>,
+    #[proto(tag = 10)]
+    pub busi_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PicExtBizInfo {
+    /// 0 for a picture, 1 for a sticker
+    #[proto(tag = 1)]
+    pub biz_type: u32,
+    #[proto(tag = 2)]
+    pub text_summary: String,
+    #[proto(tag = 11)]
+    pub bytes_pb_reserve_c2c: Option<Vec<u8>>,
+    #[proto(tag = 12)]
+    pub bytes_pb_reserve_troop: Option<Vec<u8>>,
+    #[proto(tag = 1001)]
+    pub from_scene: u32,
+    #[proto(tag = 1002)]
+    pub to_scene: u32,
+    #[proto(tag = 1003)]
+    pub old_file_id: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct VideoExtBizInfo {
+    #[proto(tag = 1)]
+    pub from_scene: u32,
+    #[proto(tag = 2)]
+    pub to_scene: u32,
+    #[proto(tag = 3)]
+    pub bytes_pb_reserve: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PttExtBizInfo {
+    #[proto(tag = 1)]
+    pub src_uin: u64,
+    #[proto(tag = 2)]
+    pub ptt_scene: u32,
+    #[proto(tag = 3)]
+    pub ptt_type: u32,
+    #[proto(tag = 4)]
+    pub change_voice: u32,
+    #[proto(tag = 5)]
+    pub waveform: Option<Vec<u8>>,
+    #[proto(tag = 6)]
+    pub auto_convert_text: u32,
+    #[proto(tag = 11)]
+    pub bytes_reserve: Option<Vec<u8>>,
+    #[proto(tag = 12)]
+    pub bytes_pb_reserve: Option<Vec<u8>>,
+    #[proto(tag = 13)]
+    pub bytes_general_flags: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct UploadResp {
+    /// Upload key, missing when the server already has the file
+    #[proto(tag = 1)]
+    pub u_key: Option<String>,
+    #[proto(tag = 2)]
+    pub u_key_ttl_second: u32,
+    #[proto(tag = 3)]
+    pub ipv4s: Vec<MediaIpv4>,
+    #[proto(tag = 4)]
+    pub ipv6s: Vec<MediaIpv6>,
+    #[proto(tag = 5)]
+    pub msg_seq: u64,
+    /// Descriptor to put into the message
+    #[proto(tag = 6)]
+    pub msg_info: Option<MsgInfo>,
+    #[proto(tag = 8)]
+    pub compat_q_msg: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MediaIpv4 {
+    #[proto(tag = 1)]
+    pub out_ip: u32,
+    #[proto(tag = 2)]
+    pub out_port: u32,
+    #[proto(tag = 3)]
+    pub in_ip: u32,
+    #[proto(tag = 4)]
+    pub in_port: u32,
+    #[proto(tag = 5)]
+    pub ip_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MediaIpv6 {
+    #[proto(tag = 1)]
+    pub out_ip: Option<Vec<u8>>,
+    #[proto(tag = 2)]
+    pub out_port: u32,
+    #[proto(tag = 3)]
+    pub in_ip: Option<Vec<u8>>,
+    #[proto(tag = 4)]
+    pub in_port: u32,
+    #[proto(tag = 5)]
+    pub ip_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct DownloadReq {
+    #[proto(tag = 1)]
+    pub node: Option<IndexNode>,
+    #[proto(tag = 2)]
+    pub download: Option<DownloadExt>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct DownloadExt {
+    #[proto(tag = 1)]
+    pub pic: Option<Vec<u8>>,
+    #[proto(tag = 2)]
+    pub video: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    pub ptt: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct DownloadResp {
+    #[proto(tag = 1)]
+    pub r_key_param: Option<String>,
+    #[proto(tag = 2)]
+    pub r_key_ttl_second: u32,
+    #[proto(tag = 3)]
+    pub info: Option<DownloadInfo>,
+    #[proto(tag = 4)]
+    pub r_key_create_time: u32,
+}
+
+/// Request for the rkeys that authorize image downloads, `OidbSvcTrpcTcp.0x9067_202`
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct DownloadRKeyReq {
+    /// 10 for private, 20 for group images
+    #[proto(tag = 1)]
+    pub types: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct DownloadRKeyResp {
+    #[proto(tag = 1)]
+    pub rkeys: Vec<RKeyInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct RKeyInfo {
+    /// Query parameter to append to download urls, starting with `&rkey=`
+    #[proto(tag = 1)]
+    pub rkey: String,
+    #[proto(tag = 2)]
+    pub rkey_ttl_sec: u64,
+    #[proto(tag = 3)]
+    pub store_id: u32,
+    /// Unix timestamp (seconds)
+    #[proto(tag = 4)]
+    pub rkey_create_time: u64,
+    #[proto(tag = 5)]
+    pub rkey_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct DownloadInfo {
+    #[proto(tag = 1)]
+    pub domain: Option<String>,
+    #[proto(tag = 2)]
+    pub url_path: Option<String>,
+    #[proto(tag = 3)]
+    pub https_port: u32,
+}
+
+/// Rich media descriptor, carried in [`CommonElem::pb_elem`](crate::internal::packets::message::CommonElem)
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MsgInfo {
+    #[proto(tag = 1)]
+    pub msg_info_body: Vec<MsgInfoBody>,
+    #[proto(tag = 2)]
+    pub ext_biz_info: Option<ExtBizInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct MsgInfoBody {
+    #[proto(tag = 1)]
+    pub index: Option<IndexNode>,
+    #[proto(tag = 2)]
+    pub picture: Option<PictureInfo>,
+    #[proto(tag = 5)]
+    pub file_exist: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct IndexNode {
+    #[proto(tag = 1)]
+    pub info: Option<FileInfo>,
+    #[proto(tag = 2)]
+    pub file_uuid: String,
+    #[proto(tag = 3)]
+    pub store_id: u32,
+    #[proto(tag = 4)]
+    pub upload_time: u32,
+    #[proto(tag = 5)]
+    pub ttl: u32,
+    #[proto(tag = 6)]
+    pub sub_type: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PictureInfo {
+    #[proto(tag = 1)]
+    pub url_path: String,
+    #[proto(tag = 2)]
+    pub ext: Option<PicUrlExtInfo>,
+    #[proto(tag = 3)]
+    pub domain: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct PicUrlExtInfo {
+    /// Query parameters of the original image, starting with `&`
+    #[proto(tag = 1)]
+    pub original_parameter: String,
+    #[proto(tag = 2)]
+    pub big_parameter: String,
+    #[proto(tag = 3)]
+    pub thumb_parameter: String,
+}
+
+/// Highway `req_extend_info` of NT rich media uploads
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtHighwayExt {
+    #[proto(tag = 1)]
+    pub file_uuid: String,
+    #[proto(tag = 2)]
+    pub u_key: String,
+    #[proto(tag = 5)]
+    pub network: Option<NtHighwayNetwork>,
+    #[proto(tag = 6)]
+    pub msg_info_body: Vec<MsgInfoBody>,
+    #[proto(tag = 10)]
+    pub block_size: u32,
+    #[proto(tag = 11)]
+    pub hash: Option<NtHighwayHash>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtHighwayNetwork {
+    #[proto(tag = 1)]
+    pub ipv4s: Vec<NtHighwayIpv4>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtHighwayIpv4 {
+    #[proto(tag = 1)]
+    pub domain: Option<NtHighwayDomain>,
+    #[proto(tag = 2)]
+    pub port: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtHighwayDomain {
+    #[proto(tag = 1)]
+    pub is_enable: bool,
+    #[proto(tag = 2)]
+    pub ip: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct NtHighwayHash {
+    #[proto(tag = 1)]
+    pub file_sha1: Vec<Vec<u8>>,
+}
@@ -0,0 +1,45 @@
+use lagrange_proto::{ProtoBuilder, ProtoMessage};
+
+/// Body of `OidbSvcTrpcTcp.0x5eb_22`, the basic profile of users who are not contacts.
+///
+/// Each `req_*` field set to 1 asks for the matching field of [`OidbStranger`].
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct StrangerInfoRequest {
+    #[proto(tag = 1)]
+    pub uins: Vec<u64>,
+    #[proto(tag = 102)]
+    pub req_personal_sign: u32,
+    #[proto(tag = 105)]
+    pub req_level: u32,
+    #[proto(tag = 20002)]
+    pub req_nickname: u32,
+    #[proto(tag = 20009)]
+    pub req_gender: u32,
+    #[proto(tag = 20037)]
+    pub req_age: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct StrangerInfoResponse {
+    /// Empty when none of the users exist
+    #[proto(tag = 11)]
+    pub users: Vec<OidbStranger>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, ProtoMessage, ProtoBuilder)]
+pub struct OidbStranger {
+    #[proto(tag = 1)]
+    pub uin: u64,
+    #[proto(tag = 2)]
+    pub uid: Option<String>,
+    #[proto(tag = 102)]
+    pub personal_sign: Option<String>,
+    #[proto(tag = 105)]
+    pub level: Option<u32>,
+    #[proto(tag = 20002)]
+    pub nickname: Option<String>,
+    #[proto(tag = 20009)]
+    pub gender: Option<u32>,
+    #[proto(tag = 20037)]
+    pub age: Option<u32>,
+}
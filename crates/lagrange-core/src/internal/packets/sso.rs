@@ -0,0 +1,476 @@
+use super::structs::{
+    service_build_protocol_12, service_build_protocol_13, service_build_response, service_parse,
+    service_parse_request, sso_build_protocol_12, sso_build_protocol_13, sso_parse, sso_parse_request, SsoPacket,
+    SsoSecureInfo,
+};
+use super::frame::DEFAULT_MAX_FRAME_LENGTH;
+use crate::{
+    common::AppInfo,
+    keystore::BotKeystore,
+    protocol::{EncryptType, Protocols, RequestType},
+    utils::binary::{BinaryPacket, PacketError, Prefix},
+};
+use bytes::Bytes;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+
+/// Body is sent as is
+pub const DATA_FLAG_NONE: i32 = 0;
+/// Body is zlib-compressed
+pub const DATA_FLAG_ZLIB: i32 = 1;
+/// Body is sent as is (legacy value)
+pub const DATA_FLAG_RAW: i32 = 4;
+/// Body is not compressed but preceded by a 4-byte length header that counts itself
+pub const DATA_FLAG_LENGTH_PREFIXED: i32 = 8;
+
+/// Zlib bodies inflating to more than this many bytes are rejected
+pub const MAX_INFLATED_BODY_LENGTH: usize = DEFAULT_MAX_FRAME_LENGTH;
+
+/// Errors produced while parsing the SSO layer of an incoming packet
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SsoError {
+    #[error("Malformed packet: {0}")]
+    Malformed(#[from] PacketError),
+
+    #[error("Unknown service protocol: {0}")]
+    UnknownProtocol(i32),
+
+    #[error("Unknown auth flag: {0}")]
+    UnknownAuthFlag(u8),
+
+    #[error("Unknown data flag: {0}")]
+    UnknownDataFlag(i32),
+
+    #[error("Failed to decompress body: {0}")]
+    DecompressFailed(String),
+
+    #[error("Decompressed body exceeds {0} bytes")]
+    BodyTooLarge(usize),
+
+    #[error("Failed to decrypt packet")]
+    DecryptFailed,
+}
+
+/// Builds the service frame for `packet`, without the outer length header added by the socket.
+///
+/// `RequestType::D2Auth` produces a protocol 12 frame carrying the A2/D2 tickets and the
+/// secure info from the sign provider, `RequestType::Simple` produces a protocol 13 frame.
+pub fn build(
+    packet: &SsoPacket,
+    keystore: &BotKeystore,
+    app_info: &AppInfo,
+    protocol: Protocols,
+    request_type: RequestType,
+    encrypt_type: EncryptType,
+    sec_info: Option<&SsoSecureInfo>,
+) -> Bytes {
+    let frame = match request_type {
+        RequestType::D2Auth => {
+            let sso_frame = sso_build_protocol_12(keystore, app_info, protocol, packet, sec_info);
+            service_build_protocol_12(keystore, sso_frame, encrypt_type)
+        }
+        RequestType::Simple => {
+            let sso_frame = sso_build_protocol_13(keystore, protocol, packet);
+            service_build_protocol_13(keystore, packet.sequence, sso_frame.as_slice(), encrypt_type)
+        }
+    };
+
+    Bytes::from(frame)
+}
+
+/// Parses an incoming service frame, decrypting it with the D2 key or the empty key
+/// depending on its auth flag.
+///
+/// A non-zero SSO return code is not an error here; it is reported through
+/// `SsoPacket::ret_code` and `SsoPacket::extra`.
+pub fn parse(input: Bytes, keystore: &BotKeystore) -> Result<SsoPacket, SsoError> {
+    let sso_data = service_parse(keystore, &input)?;
+    sso_parse(&sso_data)
+}
+
+/// Parses a frame the way the server receives it, the counterpart of [`build`].
+///
+/// Only the command, sequence and body are read back. Useful for relays and for mocking the
+/// server.
+pub fn parse_request(input: &[u8], keystore: &BotKeystore) -> Result<SsoPacket, SsoError> {
+    let (sequence, sso_data) = service_parse_request(keystore, input)?;
+    sso_parse_request(&sso_data, sequence)
+}
+
+/// Builds a frame the way the server sends it, the counterpart of [`parse`].
+///
+/// Request frames have no data flag, so this is the only place where bodies larger than
+/// `compress_threshold` get zlib-compressed. Useful for relays and for mocking the server.
+pub fn build_response(
+    packet: &SsoPacket,
+    keystore: &BotKeystore,
+    encrypt_type: EncryptType,
+    compress_threshold: Option<usize>,
+) -> Bytes {
+    let (data_flag, body) = encode_body(&packet.data, compress_threshold);
+
+    let mut head = BinaryPacket::with_capacity(0x100);
+    head.write(packet.sequence);
+    head.write(packet.ret_code);
+    head.write_str(&packet.extra, Prefix::INT32 | Prefix::WITH_PREFIX);
+    head.write_str(&packet.command, Prefix::INT32 | Prefix::WITH_PREFIX);
+    head.write_bytes_with_prefix(&[], Prefix::INT32 | Prefix::WITH_PREFIX); // msg cookie
+    head.write(data_flag);
+    head.write_bytes_with_prefix(&[], Prefix::INT32 | Prefix::WITH_PREFIX); // reserve field
+
+    let mut sso = BinaryPacket::with_capacity(head.as_slice().len() + body.len() + 8);
+    sso.write_bytes_with_prefix(head.as_slice(), Prefix::INT32 | Prefix::WITH_PREFIX);
+    sso.write_bytes_with_prefix(&body, Prefix::INT32 | Prefix::WITH_PREFIX);
+
+    Bytes::from(service_build_response(keystore, sso.as_slice(), encrypt_type))
+}
+
+/// Decodes an SSO body according to the data flag found in the SSO head
+pub fn decode_body(data_flag: i32, body: &[u8]) -> Result<Bytes, SsoError> {
+    match data_flag {
+        DATA_FLAG_NONE | DATA_FLAG_RAW => Ok(Bytes::copy_from_slice(body)),
+        DATA_FLAG_ZLIB => inflate(body, MAX_INFLATED_BODY_LENGTH),
+        DATA_FLAG_LENGTH_PREFIXED => {
+            let (header, payload) = body
+                .split_first_chunk::<4>()
+                .ok_or(PacketError::InsufficientData { requested: 4, available: body.len() })?;
+            let length = u32::from_be_bytes(*header) as usize;
+            if length != body.len() {
+                return Err(PacketError::InsufficientData { requested: length, available: body.len() }.into());
+            }
+            Ok(Bytes::copy_from_slice(payload))
+        }
+        other => Err(SsoError::UnknownDataFlag(other)),
+    }
+}
+
+/// Inflates a zlib body, failing once it grows past `limit` rather than allocating for all of it
+fn inflate(body: &[u8], limit: usize) -> Result<Bytes, SsoError> {
+    let mut decoded = Vec::with_capacity((body.len() * 4).min(limit));
+    ZlibDecoder::new(body)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decoded)
+        .map_err(|e| SsoError::DecompressFailed(e.to_string()))?;
+    if decoded.len() > limit {
+        return Err(SsoError::BodyTooLarge(limit));
+    }
+    Ok(Bytes::from(decoded))
+}
+
+/// Encodes an SSO body, compressing it with zlib when it is larger than `threshold`.
+///
+/// Returns the data flag to put in the SSO head alongside the encoded body.
+pub fn encode_body(body: &[u8], threshold: Option<usize>) -> (i32, Bytes) {
+    match threshold {
+        Some(threshold) if body.len() > threshold => {
+            let mut encoder = ZlibEncoder::new(Vec::with_capacity(body.len() / 2), Compression::default());
+            // Writing into a Vec cannot fail
+            encoder.write_all(body).expect("zlib compression into memory failed");
+            let compressed = encoder.finish().expect("zlib compression into memory failed");
+            (DATA_FLAG_ZLIB, Bytes::from(compressed))
+        }
+        _ => (DATA_FLAG_NONE, Bytes::copy_from_slice(body)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::crypto::tea;
+
+    fn keystore() -> BotKeystore {
+        let mut keystore = BotKeystore::default().with_uin(10001);
+        keystore.sigs.a2 = vec![0xA2; 4];
+        keystore.sigs.d2 = vec![0xD2; 4];
+        keystore.sigs.d2_key = (0..16).collect();
+        keystore
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Replaces the random trace id in the reserved field so the frame can be compared
+    fn mask_trace(frame: &mut [u8]) {
+        let start = frame
+            .windows(3)
+            .position(|w| w == b"01-")
+            .expect("trace parent not found")
+            + 3;
+        for byte in &mut frame[start..start + 49] {
+            if *byte != b'-' {
+                *byte = b'0';
+            }
+        }
+    }
+
+    fn response(auth_flag: u8, key: &[u8; 16], ret_code: i32, extra: &str, data_flag: i32) -> Bytes {
+        response_with_body(auth_flag, key, ret_code, extra, data_flag, &[0xCA, 0xFE])
+    }
+
+    /// A response as sent by the server: protocol 12 service frame around an SSO frame
+    fn response_with_body(
+        auth_flag: u8,
+        key: &[u8; 16],
+        ret_code: i32,
+        extra: &str,
+        data_flag: i32,
+        body: &[u8],
+    ) -> Bytes {
+        let mut head = BinaryPacket::with_capacity(64);
+        head.write(7i32); // sequence
+        head.write(ret_code);
+        head.write_str(extra, Prefix::INT32 | Prefix::WITH_PREFIX);
+        head.write_str("Heartbeat.Alive", Prefix::INT32 | Prefix::WITH_PREFIX);
+        head.write_bytes_with_prefix(&[], Prefix::INT32 | Prefix::WITH_PREFIX); // msg cookie
+        head.write(data_flag);
+        head.write_bytes_with_prefix(&[], Prefix::INT32 | Prefix::WITH_PREFIX); // reserve field
+
+        let mut sso = BinaryPacket::with_capacity(128);
+        sso.write_bytes_with_prefix(head.as_slice(), Prefix::INT32 | Prefix::WITH_PREFIX);
+        sso.write_bytes_with_prefix(body, Prefix::INT32 | Prefix::WITH_PREFIX);
+
+        let body = match auth_flag {
+            0 => sso.as_slice().to_vec(),
+            _ => tea::encrypt(sso.as_slice(), key),
+        };
+
+        let mut service = BinaryPacket::with_capacity(body.len() + 16);
+        service.write(12i32);
+        service.write(auth_flag);
+        service.write(0u8);
+        service.write_str("10001", Prefix::INT32 | Prefix::WITH_PREFIX);
+        service.write_bytes(&body);
+        Bytes::from(service.to_vec())
+    }
+
+    #[test]
+    fn test_build_protocol_13_golden() {
+        let packet = SsoPacket::new("Heartbeat.Alive".to_string(), Bytes::from_static(&[1, 2]), 1);
+        let mut frame = build(
+            &packet,
+            &keystore(),
+            &AppInfo::linux(),
+            Protocols::Linux,
+            RequestType::Simple,
+            EncryptType::NoEncrypt,
+            None,
+        )
+        .to_vec();
+        mask_trace(&mut frame);
+
+        let expected = [
+            hex("0000000d 00 00000001 00 00000009"),
+            b"10001".to_vec(),
+            hex("00000058 00000013"),
+            b"Heartbeat.Alive".to_vec(),
+            hex("00000004 0000003d 7a37"),
+            b"01-00000000000000000000000000000000-0000000000000000-01".to_vec(),
+            hex("00000006 0102"),
+        ]
+        .concat();
+        assert_eq!(frame, expected);
+    }
+
+    #[test]
+    fn test_build_protocol_12_golden() {
+        let packet = SsoPacket::new("Heartbeat.Alive".to_string(), Bytes::from_static(&[1, 2]), 1);
+        let keystore = keystore();
+        let app_info = AppInfo::linux();
+        let frame = build(
+            &packet,
+            &keystore,
+            &app_info,
+            Protocols::Linux,
+            RequestType::D2Auth,
+            EncryptType::EncryptD2Key,
+            None,
+        );
+
+        let service_head = [
+            hex("0000000c 01 00000008 d2d2d2d2 00 00000009"),
+            b"10001".to_vec(),
+        ]
+        .concat();
+        assert_eq!(&frame[..service_head.len()], service_head.as_slice());
+
+        let d2_key: [u8; 16] = keystore.sigs.d2_key[..].try_into().unwrap();
+        let mut sso = tea::decrypt(&frame[service_head.len()..], &d2_key).unwrap();
+        mask_trace(&mut sso);
+
+        let guid = "0".repeat(32);
+        let expected = [
+            hex("000000ae 00000001"),
+            app_info.sub_app_id.to_be_bytes().to_vec(),
+            hex("00000804 020000000000000000000000 00000008 a2a2a2a2 00000013"),
+            b"Heartbeat.Alive".to_vec(),
+            hex("00000004 00000024"),
+            guid.into_bytes(),
+            hex("00000004 000e"),
+            app_info.current_version.as_bytes().to_vec(),
+            hex("0000003d 7a37"),
+            b"01-00000000000000000000000000000000-0000000000000000-01".to_vec(),
+            hex("00000006 0102"),
+        ]
+        .concat();
+        assert_eq!(sso, expected);
+    }
+
+    #[test]
+    fn test_roundtrip_through_parse() {
+        let keystore = keystore();
+        let key: [u8; 16] = keystore.sigs.d2_key[..].try_into().unwrap();
+
+        for (auth_flag, key) in [(0u8, [0u8; 16]), (1, key), (2, [0u8; 16])] {
+            let packet = parse(response(auth_flag, &key, 0, "", 0), &keystore).unwrap();
+            assert_eq!(packet.command, "Heartbeat.Alive");
+            assert_eq!(packet.sequence, 7);
+            assert_eq!(packet.data.as_ref(), &[0xCA, 0xFE]);
+            assert!(packet.is_success());
+        }
+    }
+
+    #[test]
+    fn test_parse_ret_code() {
+        let packet = parse(response(0, &[0; 16], -10001, "session expired", 0), &keystore()).unwrap();
+        assert_eq!(packet.ret_code, -10001);
+        assert_eq!(packet.extra, "session expired");
+        assert!(packet.data.is_empty());
+        assert!(!packet.is_success());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let keystore = keystore();
+        let key: [u8; 16] = keystore.sigs.d2_key[..].try_into().unwrap();
+        let valid = response(1, &key, 0, "", 0);
+
+        // Ciphertext that is not a multiple of the TEA block size
+        let truncated = valid.slice(..valid.len() - 1);
+        assert_eq!(parse(truncated, &keystore).unwrap_err(), SsoError::DecryptFailed);
+
+        // Encrypted with the empty key but flagged as D2-encrypted
+        assert!(parse(response(1, &[0; 16], 0, "", 0), &keystore).is_err());
+
+        let mut bad_flag = valid.to_vec();
+        bad_flag[4] = 9;
+        assert_eq!(parse(Bytes::from(bad_flag), &keystore).unwrap_err(), SsoError::UnknownAuthFlag(9));
+
+        let mut bad_protocol = valid.to_vec();
+        bad_protocol[3] = 14;
+        assert_eq!(parse(Bytes::from(bad_protocol), &keystore).unwrap_err(), SsoError::UnknownProtocol(14));
+
+        assert_eq!(
+            parse(response(0, &[0; 16], 0, "", 3), &keystore).unwrap_err(),
+            SsoError::UnknownDataFlag(3)
+        );
+
+        for len in 0..valid.len() {
+            assert!(parse(valid.slice(..len), &keystore).is_err());
+        }
+    }
+
+    #[test]
+    fn test_zlib_body_roundtrip() {
+        let keystore = keystore();
+        let body: Vec<u8> = (0..4096u32).flat_map(|i| (i % 64).to_be_bytes()).collect();
+
+        let (flag, encoded) = encode_body(&body, Some(1024));
+        assert_eq!(flag, DATA_FLAG_ZLIB);
+        assert!(encoded.len() < body.len());
+
+        let packet = parse(response_with_body(0, &[0; 16], 0, "", flag, &encoded), &keystore).unwrap();
+        assert_eq!(packet.data.as_ref(), body.as_slice());
+
+        let original = SsoPacket::new("OidbSvcTrpcTcp.0xfd4_1".to_string(), Bytes::from(body.clone()), 3);
+        for encrypt_type in [EncryptType::NoEncrypt, EncryptType::EncryptD2Key, EncryptType::EncryptEmpty] {
+            let frame = build_response(&original, &keystore, encrypt_type, Some(1024));
+            let packet = parse(frame, &keystore).unwrap();
+            assert_eq!(packet.command, original.command);
+            assert_eq!(packet.sequence, 3);
+            assert_eq!(packet.data, original.data);
+        }
+    }
+
+    #[test]
+    fn test_encode_body_threshold() {
+        assert_eq!(encode_body(&[1, 2, 3], None), (DATA_FLAG_NONE, Bytes::from_static(&[1, 2, 3])));
+        assert_eq!(encode_body(&[1, 2, 3], Some(3)), (DATA_FLAG_NONE, Bytes::from_static(&[1, 2, 3])));
+        assert_eq!(encode_body(&[1, 2, 3, 4], Some(3)).0, DATA_FLAG_ZLIB);
+    }
+
+    #[test]
+    fn test_length_prefixed_body() {
+        let keystore = keystore();
+        let body = [0x00, 0x00, 0x00, 0x06, 0xCA, 0xFE];
+
+        let packet = parse(
+            response_with_body(0, &[0; 16], 0, "", DATA_FLAG_LENGTH_PREFIXED, &body),
+            &keystore,
+        )
+        .unwrap();
+        assert_eq!(packet.data.as_ref(), &[0xCA, 0xFE]);
+
+        let bad_header = [0x00, 0x00, 0x00, 0x10, 0xCA, 0xFE];
+        assert!(matches!(
+            decode_body(DATA_FLAG_LENGTH_PREFIXED, &bad_header),
+            Err(SsoError::Malformed(_))
+        ));
+        assert!(decode_body(DATA_FLAG_LENGTH_PREFIXED, &[0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let packet = SsoPacket::new("Heartbeat.Alive".to_string(), Bytes::from_static(&[1, 2, 3]), 41);
+        for request_type in [RequestType::D2Auth, RequestType::Simple] {
+            for encrypt_type in [EncryptType::NoEncrypt, EncryptType::EncryptEmpty, EncryptType::EncryptD2Key] {
+                let app_info = AppInfo::linux();
+                let frame = build(&packet, &keystore(), &app_info, Protocols::Linux, request_type, encrypt_type, None);
+                let parsed = parse_request(&frame, &keystore()).unwrap();
+                assert_eq!(
+                    (parsed.command.as_str(), parsed.sequence, parsed.data.as_ref()),
+                    ("Heartbeat.Alive", 41, &[1u8, 2, 3][..]),
+                    "{:?} {:?}",
+                    request_type,
+                    encrypt_type
+                );
+            }
+        }
+
+        assert!(matches!(parse_request(&[0, 0, 0, 11, 0], &keystore()), Err(SsoError::UnknownProtocol(11))));
+    }
+
+    #[test]
+    fn test_corrupt_zlib_body() {
+        let (_, mut encoded) = {
+            let (flag, encoded) = encode_body(&[0x42; 256], Some(0));
+            (flag, encoded.to_vec())
+        };
+        let mid = encoded.len() / 2;
+        encoded[mid] ^= 0xFF;
+        encoded.truncate(encoded.len() - 2);
+
+        assert!(matches!(
+            decode_body(DATA_FLAG_ZLIB, &encoded),
+            Err(SsoError::DecompressFailed(_))
+        ));
+        assert!(matches!(
+            decode_body(DATA_FLAG_ZLIB, b"definitely not zlib"),
+            Err(SsoError::DecompressFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_inflate_limit() {
+        let (_, encoded) = encode_body(&[0u8; 4096], Some(0));
+        assert!(encoded.len() < 64);
+
+        assert_eq!(inflate(&encoded, 4096).unwrap().len(), 4096);
+        assert_eq!(inflate(&encoded, 4095).unwrap_err(), SsoError::BodyTooLarge(4095));
+        assert_eq!(inflate(&encoded, 16).unwrap_err(), SsoError::BodyTooLarge(16));
+    }
+}
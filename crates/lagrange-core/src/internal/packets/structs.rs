@@ -6,9 +6,12 @@ pub mod sso_secure_info;
 
 // Re-exports are kept for future use when implementing protocol handlers
 #[allow(unused_imports)]
-pub use service_packer::{service_build_protocol_12, service_build_protocol_13, service_parse};
+pub use service_packer::{
+    service_build_protocol_12, service_build_protocol_13, service_build_response, service_parse,
+    service_parse_request,
+};
 #[allow(unused_imports)]
-pub use sso_packer::{sso_build_protocol_12, sso_build_protocol_13, sso_parse};
+pub use sso_packer::{sso_build_protocol_12, sso_build_protocol_13, sso_parse, sso_parse_request};
 #[allow(unused_imports)]
 pub use sso_packet::SsoPacket;
 #[allow(unused_imports)]
@@ -1,4 +1,5 @@
 use crate::{
+    internal::packets::sso::SsoError,
     keystore::BotKeystore,
     protocol::EncryptType,
     utils::{
@@ -19,10 +20,7 @@ pub fn service_build_protocol_12(
         EncryptType::NoEncrypt => sso.as_slice().to_vec(),
         EncryptType::EncryptEmpty => tea::encrypt(sso.as_slice(), &EMPTY_D2_KEY),
         EncryptType::EncryptD2Key => {
-            let d2_key: [u8; 16] = keystore.sigs.d2_key[..16]
-                .try_into()
-                .unwrap_or(EMPTY_D2_KEY);
-            tea::encrypt(sso.as_slice(), &d2_key)
+            tea::encrypt(sso.as_slice(), &d2_key(keystore))
         }
     };
 
@@ -61,10 +59,7 @@ pub fn service_build_protocol_13(
         EncryptType::NoEncrypt => payload.to_vec(),
         EncryptType::EncryptEmpty => tea::encrypt(payload, &EMPTY_D2_KEY),
         EncryptType::EncryptD2Key => {
-            let d2_key: [u8; 16] = keystore.sigs.d2_key[..16]
-                .try_into()
-                .unwrap_or(EMPTY_D2_KEY);
-            tea::encrypt(payload, &d2_key)
+            tea::encrypt(payload, &d2_key(keystore))
         }
     };
 
@@ -83,39 +78,91 @@ pub fn service_build_protocol_13(
     writer.to_vec()
 }
 
+/// Build a service packet the way the server sends it (protocol 12 with an auth flag)
+pub fn service_build_response(keystore: &BotKeystore, sso: &[u8], encrypt_type: EncryptType) -> Vec<u8> {
+    let cipher = match encrypt_type {
+        EncryptType::NoEncrypt => sso.to_vec(),
+        EncryptType::EncryptEmpty => tea::encrypt(sso, &EMPTY_D2_KEY),
+        EncryptType::EncryptD2Key => tea::encrypt(sso, &d2_key(keystore)),
+    };
+
+    let mut writer = BinaryPacket::with_capacity(cipher.len() + 32);
+
+    writer.write(12i32);
+    writer.write(encrypt_type as u8);
+    writer.write(0u8);
+    writer.write_str(
+        &keystore.uin.unwrap_or(0).to_string(),
+        Prefix::INT32 | Prefix::WITH_PREFIX,
+    );
+    writer.write_bytes(&cipher);
+
+    writer.to_vec()
+}
+
 /// Parse a service packet response
-pub fn service_parse(keystore: &BotKeystore, input: &[u8]) -> Result<Vec<u8>, &'static str> {
+pub fn service_parse(keystore: &BotKeystore, input: &[u8]) -> Result<Vec<u8>, SsoError> {
     let mut reader = BinaryPacket::from_slice(input);
 
-    let _protocol = reader
-        .read::<i32>()
-        .map_err(|_| "Failed to read protocol")?;
-    let auth_flag = reader
-        .read::<u8>()
-        .map_err(|_| "Failed to read auth flag")?;
-    let _dummy = reader.read::<u8>().map_err(|_| "Failed to read dummy")?;
+    let protocol = reader.read::<i32>()?;
+    if protocol != 12 && protocol != 13 {
+        return Err(SsoError::UnknownProtocol(protocol));
+    }
+    let auth_flag = reader.read::<u8>()?;
+    let _dummy = reader.read::<u8>()?;
 
-    let _uin_str = reader
-        .read_string(Prefix::INT32 | Prefix::WITH_PREFIX)
-        .map_err(|_| "Failed to read UIN")?;
+    let _uin_str = reader.read_string(Prefix::INT32 | Prefix::WITH_PREFIX)?;
 
     let encrypted = reader.read_remaining();
 
     let decrypted = match auth_flag {
         0x00 => encrypted.to_vec(),
-        0x02 => {
-            tea::decrypt(encrypted, &EMPTY_D2_KEY)
-                .map_err(|_| "Failed to decrypt with empty key")?
-        }
-        0x01 => {
-            let d2_key: [u8; 16] = keystore.sigs.d2_key[..16]
-                .try_into()
-                .unwrap_or(EMPTY_D2_KEY);
-            tea::decrypt(encrypted, &d2_key)
-                .map_err(|_| "Failed to decrypt with D2 key")?
-        }
-        _ => return Err("Unrecognized auth flag"),
+        0x02 => tea::decrypt(encrypted, &EMPTY_D2_KEY).map_err(|_| SsoError::DecryptFailed)?,
+        0x01 => tea::decrypt(encrypted, &d2_key(keystore)).map_err(|_| SsoError::DecryptFailed)?,
+        other => return Err(SsoError::UnknownAuthFlag(other)),
     };
 
     Ok(decrypted)
 }
+
+/// Parse a service packet the way the server receives it, the counterpart of
+/// [`service_build_protocol_12`] and [`service_build_protocol_13`].
+///
+/// Returns the sequence for protocol 13, which carries it in the service head, and the
+/// decrypted SSO frame.
+pub fn service_parse_request(keystore: &BotKeystore, input: &[u8]) -> Result<(Option<i32>, Vec<u8>), SsoError> {
+    let mut reader = BinaryPacket::from_slice(input);
+
+    let protocol = reader.read::<i32>()?;
+    let encrypt_flag = reader.read::<u8>()?;
+    let sequence = match protocol {
+        12 => {
+            let _d2 = reader.read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+            None
+        }
+        13 => Some(reader.read::<i32>()?),
+        other => return Err(SsoError::UnknownProtocol(other)),
+    };
+    let _dummy = reader.read::<u8>()?;
+    let _uin_str = reader.read_string(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+
+    let encrypted = reader.read_remaining();
+    let decrypted = match encrypt_flag {
+        0x00 => encrypted.to_vec(),
+        0x02 => tea::decrypt(encrypted, &EMPTY_D2_KEY).map_err(|_| SsoError::DecryptFailed)?,
+        0x01 => tea::decrypt(encrypted, &d2_key(keystore)).map_err(|_| SsoError::DecryptFailed)?,
+        other => return Err(SsoError::UnknownAuthFlag(other)),
+    };
+
+    Ok((sequence, decrypted))
+}
+
+/// The D2 key used for `EncryptD2Key`, falling back to the empty key before login
+fn d2_key(keystore: &BotKeystore) -> [u8; 16] {
+    keystore
+        .sigs
+        .d2_key
+        .get(..16)
+        .and_then(|key| key.try_into().ok())
+        .unwrap_or(EMPTY_D2_KEY)
+}
@@ -3,11 +3,11 @@ use super::{
 };
 use crate::{
     common::AppInfo,
+    internal::packets::sso::{decode_body, SsoError},
     keystore::BotKeystore,
     protocol::Protocols,
     utils::binary::{BinaryPacket, Prefix},
 };
-use bytes::Bytes;
 use lagrange_proto::ProtoMessage;
 use rand::Rng;
 
@@ -80,49 +80,24 @@ pub fn sso_build_protocol_13(
 }
 
 /// Parse an SSO packet response
-pub fn sso_parse(data: &[u8]) -> Result<SsoPacket, &'static str> {
+pub fn sso_parse(data: &[u8]) -> Result<SsoPacket, SsoError> {
     let mut parent = BinaryPacket::from_slice(data);
     let head = parent
-        .read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)
-        .map_err(|_| "Failed to read head")?
+        .read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?
         .to_vec();
     let body = parent
-        .read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)
-        .map_err(|_| "Failed to read body")?
-        .to_vec();
+        .read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?;
 
     let mut head_reader = BinaryPacket::from_slice(&head);
-    let sequence = head_reader
-        .read::<i32>()
-        .map_err(|_| "Failed to read sequence")?;
-    let ret_code = head_reader
-        .read::<i32>()
-        .map_err(|_| "Failed to read ret_code")?;
-    let extra = head_reader
-        .read_string(Prefix::INT32 | Prefix::WITH_PREFIX)
-        .map_err(|_| "Failed to read extra")?;
-    let command = head_reader
-        .read_string(Prefix::INT32 | Prefix::WITH_PREFIX)
-        .map_err(|_| "Failed to read command")?;
-    let _msg_cookie = head_reader
-        .read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)
-        .map_err(|_| "Failed to read msg_cookie")?;
-    let data_flag = head_reader
-        .read::<i32>()
-        .map_err(|_| "Failed to read data_flag")?;
-    let _reserve_field = head_reader
-        .read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)
-        .map_err(|_| "Failed to read reserve_field")?;
-
-    let payload = match data_flag {
-        0 | 4 => Bytes::copy_from_slice(&body),
-        1 => {
-            // TODO: Implement ZCompression decompression
-            // For now, return error or empty bytes
-            return Err("Compression not yet implemented");
-        }
-        _ => return Err("Unknown data flag"),
-    };
+    let sequence = head_reader.read::<i32>()?;
+    let ret_code = head_reader.read::<i32>()?;
+    let extra = head_reader.read_string(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+    let command = head_reader.read_string(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+    let _msg_cookie = head_reader.read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+    let data_flag = head_reader.read::<i32>()?;
+    let _reserve_field = head_reader.read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+
+    let payload = decode_body(data_flag, body)?;
 
     if ret_code == 0 {
         Ok(SsoPacket::new(command, payload, sequence))
@@ -131,6 +106,32 @@ pub fn sso_parse(data: &[u8]) -> Result<SsoPacket, &'static str> {
     }
 }
 
+/// Parse an SSO request frame, the counterpart of [`sso_build_protocol_12`] and
+/// [`sso_build_protocol_13`]. `sequence` comes from the service head for protocol 13 and is
+/// `None` for protocol 12, which carries it in the SSO head.
+pub fn sso_parse_request(data: &[u8], sequence: Option<i32>) -> Result<SsoPacket, SsoError> {
+    let mut parent = BinaryPacket::from_slice(data);
+    let head = parent
+        .read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?
+        .to_vec();
+    let body = parent.read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+
+    let mut head_reader = BinaryPacket::from_slice(&head);
+    let sequence = match sequence {
+        Some(sequence) => sequence,
+        None => {
+            let sequence = head_reader.read::<i32>()?;
+            // subAppId, locale and the fixed bytes before the tickets
+            head_reader.read_bytes(4 + 4 + 12)?;
+            let _tgt = head_reader.read_bytes_with_prefix(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+            sequence
+        }
+    };
+    let command = head_reader.read_string(Prefix::INT32 | Prefix::WITH_PREFIX)?;
+
+    Ok(SsoPacket::new(command, bytes::Bytes::copy_from_slice(body), sequence))
+}
+
 /// Helper function to write SSO reserved fields
 fn write_sso_reserved_field(
     writer: &mut BinaryPacket,
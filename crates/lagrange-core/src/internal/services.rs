@@ -1,7 +1,7 @@
 use crate::{
     context::BotContext,
     error::Result,
-    protocol::{ServiceMetadata, TypedService},
+    protocol::{EventMessage, ServiceMetadata, TypedService},
 };
 use bytes::Bytes;
 use std::{
@@ -15,6 +15,14 @@ use std::{
 /// Type alias for boxed futures to simplify type signatures
 type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
+/// Type-erased build function stored in a [`TypedServiceEntry`]
+type BuildFn =
+    Arc<dyn Fn(Box<dyn Any + Send>, Arc<BotContext>) -> BoxFuture<'static, Result<Bytes>> + Send + Sync>;
+
+/// Type-erased parse function stored in a [`TypedServiceEntry`]
+type ParseEventFn =
+    Arc<dyn Fn(Bytes, Arc<BotContext>) -> BoxFuture<'static, Result<EventMessage>> + Send + Sync>;
+
 /// Entry for a typed service in the registry.
 ///
 /// This stores type-erased dispatch functions that maintain type safety through
@@ -40,19 +48,12 @@ pub struct TypedServiceEntry {
     ///
     /// The Box<dyn Any + Send> must contain the concrete Request type.
     /// This is guaranteed safe by the registration process.
-    build_fn: Arc<
-        dyn Fn(Box<dyn Any + Send>, Arc<BotContext>) -> BoxFuture<'static, Result<Bytes>> + Send + Sync,
-    >,
+    build_fn: BuildFn,
 
-    /// Type-erased parse function: Bytes -> Response
+    /// Type-erased parse function: Bytes -> Response, wrapped in an [`EventMessage`]
     ///
-    /// Returns Box<dyn Any> containing the concrete Response type.
-    /// This is guaranteed safe by the registration process.
-    parse_fn: Arc<
-        dyn Fn(Bytes, Arc<BotContext>) -> BoxFuture<'static, Result<Box<dyn Any + Send>>>
-            + Send
-            + Sync,
-    >,
+    /// The message holds the concrete Response type, matching `response_type_id`.
+    parse_event_fn: ParseEventFn,
 }
 
 impl TypedServiceEntry {
@@ -67,11 +68,10 @@ impl TypedServiceEntry {
         (self.build_fn)(request, context).await
     }
 
-    /// Execute the parse function to produce a type-erased response.
-    ///
-    /// The returned `Box<dyn Any>` contains the response type matching `response_type_id`.
-    pub async fn parse(&self, bytes: Bytes, context: Arc<BotContext>) -> Result<Box<dyn Any + Send>> {
-        (self.parse_fn)(bytes, context).await
+    /// Execute the parse function and wrap the response for the event bus, or for the caller
+    /// waiting on it to unwrap.
+    pub async fn parse_event(&self, bytes: Bytes, context: Arc<BotContext>) -> Result<EventMessage> {
+        (self.parse_event_fn)(bytes, context).await
     }
 }
 
@@ -133,31 +133,23 @@ impl ServiceRegistry {
                     Box::pin(future)
                 },
             )
-                as Arc<
-                    dyn Fn(Box<dyn Any + Send>, Arc<BotContext>) -> BoxFuture<'static, Result<Bytes>>
-                        + Send
-                        + Sync,
-                >
+                as BuildFn
         };
 
         // Create type-erased parse function
-        let parse_fn = {
+        let parse_event_fn = {
             let service = Arc::clone(&service);
             Arc::new(
-                move |bytes: Bytes, context: Arc<BotContext>| -> BoxFuture<'static, Result<Box<dyn Any + Send>>> {
+                move |bytes: Bytes, context: Arc<BotContext>| -> BoxFuture<'static, Result<EventMessage>> {
                     let service = Arc::clone(&service);
                     let future = async move {
                         let response = service.parse(bytes, context).await?;
-                        Ok(Box::new(response) as Box<dyn Any + Send>)
+                        Ok(EventMessage::new(response))
                     };
                     Box::pin(future)
                 },
             )
-                as Arc<
-                    dyn Fn(Bytes, Arc<BotContext>) -> BoxFuture<'static, Result<Box<dyn Any + Send>>>
-                        + Send
-                        + Sync,
-                >
+                as ParseEventFn
         };
 
         // Create the service entry
@@ -168,7 +160,7 @@ impl ServiceRegistry {
             response_type_id: TypeId::of::<S::Response>(),
             protocol_mask,
             build_fn,
-            parse_fn,
+            parse_event_fn,
         });
 
         // Register by command
@@ -219,7 +211,6 @@ impl ServiceRegistry {
             .get(&request_type)?
             .iter()
             .find(|entry| entry.protocol_mask & protocol != 0)
-            .map(|arc| arc)
     }
 }
 
@@ -255,6 +246,7 @@ use lagrange_macros::auto_reexport;
 
 auto_reexport! {
     pub mod login;
+    pub mod message;
     pub mod system;
 }
 
@@ -2,8 +2,11 @@ use lagrange_macros::auto_reexport;
 
 auto_reexport! {
     pub mod exchange_emp;
+    pub mod kick;
     pub mod password;
     pub mod qrlogin;
+    pub mod register;
+    pub mod set_status;
     pub mod trans_emp;
     pub mod uin_resolve;
 }
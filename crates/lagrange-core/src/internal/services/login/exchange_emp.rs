@@ -1,4 +1,5 @@
 use crate::context::BotContext;
+use crate::internal::packets::login::tlv119::{self, TLV_119};
 use crate::internal::packets::login::wtlogin::WtLogin;
 use bytes::Bytes;
 use lagrange_macros::define_service;
@@ -7,7 +8,6 @@ use std::sync::Arc;
 
 use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
 use crate::utils::binary::BinaryPacket;
-use crate::utils::crypto::tea;
 use crate::utils::tlv_unpack;
 
 /// Exchange emp command type
@@ -38,7 +38,7 @@ define_service! {
         }
 
         async fn parse(input: Bytes, context: Arc<BotContext>) -> Result<EventMessage> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
@@ -69,7 +69,7 @@ define_service! {
             );
 
             // Check for TLV 0x119 (contains encrypted TLV collection)
-            let tlvs = if let Some(tgtgt_data) = parsed_tlvs.remove(&0x119) {
+            let tlvs = if let Some(tgtgt_data) = parsed_tlvs.remove(&TLV_119) {
                 // Choose decryption key based on internal command
                 let decryption_key = if internal_cmd == 0x0f {
                     // Use A1 key for command 0x0f
@@ -84,23 +84,7 @@ define_service! {
                     &keystore.sigs.tgtgt_key
                 };
 
-                let key_array: [u8; 16] = decryption_key[..16]
-                    .try_into()
-                    .map_err(|_| crate::error::Error::ParseError("Invalid key length".into()))?;
-
-                let decrypted = tea::decrypt(&tgtgt_data, &key_array).map_err(|e| {
-                    crate::error::Error::ParseError(format!("Failed to decrypt TLV 0x119: {}", e))
-                })?;
-
-                let mut tlv119_reader = BinaryPacket::from_slice(&decrypted);
-                let tlv_collection = tlv_unpack(&mut tlv119_reader)?;
-
-                tracing::debug!(
-                    inner_tlv_count = tlv_collection.len(),
-                    "Decrypted TLV 0x119"
-                );
-
-                tlv_collection
+                tlv119::decrypt(&tgtgt_data, decryption_key)?
             } else {
                 parsed_tlvs
             };
@@ -112,7 +96,7 @@ define_service! {
             let input = event.downcast_ref::<ExchangeEmpEventReq>()
                 .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
 
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
@@ -0,0 +1,176 @@
+use crate::common::{BotOfflineEvent, OfflineKind};
+use crate::context::BotContext;
+use crate::internal::packets::login::ServiceKickNt;
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+define_service! {
+    KickService {
+        command: "trpc.qq_new_tech.status_svc.StatusService.KickNT",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            KickEvent(protocol = Protocols::ALL) {
+                request KickEventReq {}
+                response KickEventResp {
+                    event: BotOfflineEvent,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let kick = ServiceKickNt::decode_from_slice(&input)?;
+
+            Ok(EventMessage::new(KickEventResp {
+                event: BotOfflineEvent {
+                    kind: OfflineKind::KickedByOther,
+                    title: kick.title.unwrap_or_default(),
+                    message: kick.tips.unwrap_or_default(),
+                },
+            }))
+        }
+
+        async fn build(_event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            Err(crate::error::Error::BuildError(
+                "KickNT is only sent by the server".to_string(),
+            ))
+        }
+    }
+}
+
+// The bodies of the two legacy notices below are JCE, which this crate does not decode; their
+// arrival alone tells why the session ended.
+
+define_service! {
+    ForceOfflineService {
+        command: "MessageSvc.PushForceOffline",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            ForceOfflineEvent(protocol = Protocols::ALL) {
+                request ForceOfflineEventReq {}
+                response ForceOfflineEventResp {
+                    event: BotOfflineEvent,
+                }
+            }
+        }
+
+        async fn parse(_input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(ForceOfflineEventResp {
+                event: BotOfflineEvent {
+                    kind: OfflineKind::ServerKick,
+                    title: "Forced offline".to_string(),
+                    message: "The server forced the account offline".to_string(),
+                },
+            }))
+        }
+
+        async fn build(_event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            Err(crate::error::Error::BuildError(
+                "PushForceOffline is only sent by the server".to_string(),
+            ))
+        }
+    }
+}
+
+define_service! {
+    MsfOfflineService {
+        command: "StatSvc.ReqMSFOffline",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            MsfOfflineEvent(protocol = Protocols::ALL) {
+                request MsfOfflineEventReq {}
+                response MsfOfflineEventResp {
+                    event: BotOfflineEvent,
+                }
+            }
+        }
+
+        async fn parse(_input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(MsfOfflineEventResp {
+                event: BotOfflineEvent {
+                    kind: OfflineKind::MsfOffline,
+                    title: "Disconnected".to_string(),
+                    message: "The server dropped the connection".to_string(),
+                },
+            }))
+        }
+
+        async fn build(_event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            Err(crate::error::Error::BuildError(
+                "ReqMSFOffline is only sent by the server".to_string(),
+            ))
+        }
+    }
+}
+
+/// The [`BotOfflineEvent`] carried by a parsed push, if it ends the session
+pub fn offline_event(event: &EventMessage) -> Option<BotOfflineEvent> {
+    if let Some(kick) = event.downcast_ref::<KickEventResp>() {
+        return Some(kick.event.clone());
+    }
+    if let Some(offline) = event.downcast_ref::<ForceOfflineEventResp>() {
+        return Some(offline.event.clone());
+    }
+    event.downcast_ref::<MsfOfflineEventResp>().map(|offline| offline.event.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::services::registry;
+    use crate::protocol::TypedService;
+    use crate::utils::common::from_hex;
+
+    /// KickNT for 10001: tips "你的帐号在另一台电脑登录", title "下线通知"
+    const KICK_NT: &str = concat!(
+        "08914e",
+        "1a24e4bda0e79a84e5b890e58fb7e59ca8e58fa6e4b880e58fb0e794b5e88491e799bbe5bd95",
+        "220ce4b88be7babfe9809ae79fa5",
+    );
+
+    #[tokio::test]
+    async fn test_parse_kick() {
+        let context = BotContext::builder().build();
+        let response = KickService::default()
+            .parse(Bytes::from(from_hex(KICK_NT).unwrap()), context)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.event,
+            BotOfflineEvent {
+                kind: OfflineKind::KickedByOther,
+                title: "下线通知".to_string(),
+                message: "你的帐号在另一台电脑登录".to_string(),
+            }
+        );
+        assert!(!response.event.kind.allows_re_login());
+    }
+
+    #[tokio::test]
+    async fn test_offline_pushes() {
+        let context = BotContext::builder().build();
+        let kinds = [
+            ("trpc.qq_new_tech.status_svc.StatusService.KickNT", OfflineKind::KickedByOther),
+            ("MessageSvc.PushForceOffline", OfflineKind::ServerKick),
+            ("StatSvc.ReqMSFOffline", OfflineKind::MsfOffline),
+        ];
+
+        for (command, kind) in kinds {
+            let service = registry().get_typed_service_by_command(command).unwrap();
+            let event = service.parse_event(Bytes::new(), context.clone()).await.unwrap();
+            assert_eq!(offline_event(&event).unwrap().kind, kind, "{command}");
+        }
+        assert!(OfflineKind::MsfOffline.allows_re_login());
+        assert!(!OfflineKind::ServerKick.allows_re_login());
+    }
+}
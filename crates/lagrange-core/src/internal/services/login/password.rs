@@ -1,3 +1,4 @@
+use crate::internal::packets::login::tlv119::{self, TLV_119};
 use crate::internal::packets::login::wtlogin::WtLogin;
 use crate::{context::BotContext, error::Result};
 use bytes::Bytes;
@@ -7,7 +8,6 @@ use std::sync::Arc;
 
 use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
 use crate::utils::binary::{BinaryPacket, Prefix};
-use crate::utils::crypto::tea;
 use crate::utils::tlv_unpack;
 
 /// Command type for login operations
@@ -73,7 +73,7 @@ impl From<u8> for States {
 fn parse_login_response(
     packet: &mut WtLogin,
     input: Bytes,
-    context: Arc<BotContext>,
+    tgtgt_key: &[u8],
     ret_code: &mut u8,
     error: &mut Option<(String, String)>,
     tlvs: &mut HashMap<u16, Vec<u8>>,
@@ -120,24 +120,8 @@ fn parse_login_response(
     }
 
     // Check for TLV 0x119 (contains encrypted TLV collection)
-    if let Some(tgtgt_data) = parsed_tlvs.remove(&0x119) {
-        let keystore = context.keystore.read().expect("RwLock poisoned");
-        let tgtgt_key: [u8; 16] = keystore.sigs.tgtgt_key[..16]
-            .try_into()
-            .map_err(|_| crate::error::Error::ParseError("Invalid tgtgt_key length".into()))?;
-
-        let decrypted = tea::decrypt(&tgtgt_data, &tgtgt_key)
-            .map_err(|e| crate::error::Error::ParseError(format!("Failed to decrypt: {}", e)))?;
-
-        let mut tlv119_reader = BinaryPacket::from_slice(&decrypted);
-        let tlv_collection = tlv_unpack(&mut tlv119_reader)?;
-
-        tracing::debug!(
-            inner_tlv_count = tlv_collection.len(),
-            "Decrypted TLV 0x119"
-        );
-
-        *tlvs = tlv_collection;
+    if let Some(tgtgt_data) = parsed_tlvs.remove(&TLV_119) {
+        *tlvs = tlv119::decrypt(&tgtgt_data, tgtgt_key)?;
         return Ok(());
     }
 
@@ -183,7 +167,9 @@ define_service! {
         }
 
         async fn parse(input: Bytes, context: Arc<BotContext>) -> Result<EventMessage> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
+            // The keystore stays locked by the packet, so take the key out beforehand
+            let tgtgt_key = keystore.sigs.tgtgt_key.clone();
             let app_info = context.app_info.inner();
             let mut packet = WtLogin::new(&mut keystore, app_info)
                 .map_err(|e| crate::error::Error::ParseError(e.to_string()))?;
@@ -192,35 +178,26 @@ define_service! {
             let mut error = None;
             let mut tlvs = HashMap::new();
 
-            parse_login_response(&mut packet, input, context.clone(), &mut ret_code, &mut error, &mut tlvs)?;
-
-            // Return appropriate response based on protocol
-            let protocol = context.config.protocol;
-            match protocol {
-                Protocols::Windows | Protocols::MacOs | Protocols::Linux => {
-                    Ok(EventMessage::new(LoginEventResp {
-                        ret_code,
-                        error,
-                        tlvs,
-                    }))
-                }
-                Protocols::AndroidPhone | Protocols::AndroidPad | Protocols::AndroidWatch => {
-                    Ok(EventMessage::new(LoginEventRespAndroid {
-                        ret_code,
-                        error,
-                        tlvs,
-                    }))
-                }
-                _ => Ok(EventMessage::new(LoginEventResp {
+            parse_login_response(&mut packet, input, &tgtgt_key, &mut ret_code, &mut error, &mut tlvs)?;
+
+            // Return appropriate response based on the kind of client
+            if context.app_info.is_android() {
+                Ok(EventMessage::new(LoginEventRespAndroid {
+                    ret_code,
+                    error,
+                    tlvs,
+                }))
+            } else {
+                Ok(EventMessage::new(LoginEventResp {
                     ret_code,
                     error,
                     tlvs,
-                })),
+                }))
             }
         }
 
         async fn build(event: EventMessage, context: Arc<BotContext>) -> Result<Bytes> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
             let packet = WtLogin::new(&mut keystore, app_info)
                 .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
@@ -263,15 +240,52 @@ define_service! {
     }
 }
 
+/// TLV carrying the signature used to verify an unusual device via QR code
+const TLV_UNUSUAL_SIG: u16 = 0x174;
+/// TLV carrying the prompt shown to the user for the unusual device verification
+const TLV_UNUSUAL_MESSAGE: u16 = 0x17E;
+
+/// Unusual-device verification details returned with login state 0xEF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusualDeviceInfo {
+    pub sig: Vec<u8>,
+    pub message: String,
+}
+
 // Helper methods for response types
 impl LoginEventResp {
     pub fn state(&self) -> States {
         States::from(self.ret_code)
     }
+
+    /// Returns the unusual-device verification details if the server requires
+    /// the login to be confirmed by scanning a QR code
+    pub fn unusual_device(&self) -> Option<UnusualDeviceInfo> {
+        unusual_device(self.state(), &self.tlvs)
+    }
 }
 
 impl LoginEventRespAndroid {
     pub fn state(&self) -> States {
         States::from(self.ret_code)
     }
+
+    /// Like [`LoginEventResp::unusual_device`], for a password login
+    pub fn unusual_device(&self) -> Option<UnusualDeviceInfo> {
+        unusual_device(self.state(), &self.tlvs)
+    }
+}
+
+fn unusual_device(state: States, tlvs: &HashMap<u16, Vec<u8>>) -> Option<UnusualDeviceInfo> {
+    if state != States::DeviceLockViaSmsNewArea {
+        return None;
+    }
+
+    let sig = tlvs.get(&TLV_UNUSUAL_SIG)?.clone();
+    let message = tlvs
+        .get(&TLV_UNUSUAL_MESSAGE)
+        .map(|data| String::from_utf8_lossy(data).into_owned())
+        .unwrap_or_else(|| "Login from an unusual device requires verification".to_string());
+
+    Some(UnusualDeviceInfo { sig, message })
 }
@@ -42,7 +42,7 @@ define_service! {
         }
 
         async fn parse(input: Bytes, context: Arc<BotContext>) -> Result<EventMessage> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
@@ -136,7 +136,7 @@ define_service! {
         }
 
         async fn build(event: EventMessage, context: Arc<BotContext>) -> Result<Bytes> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
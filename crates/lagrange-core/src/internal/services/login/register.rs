@@ -0,0 +1,183 @@
+use crate::context::BotContext;
+use crate::internal::packets::login::{
+    OnlineOsInfo, ServiceRegister, ServiceRegisterResponse, ServiceUnRegister, ServiceUnRegisterResponse,
+};
+use crate::utils::common::to_hex;
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+/// Locale of the client, zh-CN
+const LOCALE_ID: u32 = 2052;
+
+define_service! {
+    RegisterService {
+        command: "trpc.qq_new_tech.status_svc.StatusService.Register",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            RegisterEvent(protocol = Protocols::ALL) {
+                request RegisterEventReq {}
+                response RegisterEventResp {
+                    timestamp: u64,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = ServiceRegisterResponse::decode_from_slice(&input)?;
+
+            let message = response.message.unwrap_or_default();
+            if message != ServiceRegisterResponse::SUCCESS {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Registering online failed: {}",
+                    message
+                )));
+            }
+
+            Ok(EventMessage::new(RegisterEventResp {
+                timestamp: response.timestamp.unwrap_or_default(),
+            }))
+        }
+
+        async fn build(_event: EventMessage, context: Arc<BotContext>) -> Result<Bytes> {
+            let app_info = context.app_info.inner();
+            let (guid, device_name) = {
+                let keystore = context.keystore.read().expect("RwLock poisoned");
+                (to_hex(&keystore.guid).to_uppercase(), keystore.device_name.clone())
+            };
+
+            let request = ServiceRegister {
+                guid,
+                register_type: 0,
+                current_version: app_info.current_version.clone(),
+                field4: 0,
+                locale_id: LOCALE_ID,
+                online: Some(online_os_info(&context, device_name)),
+                set_mute: 0,
+                register_vendor_type: 6,
+                reg_type: 1,
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+define_service! {
+    UnRegisterService {
+        command: "trpc.qq_new_tech.status_svc.StatusService.UnRegister",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            UnRegisterEvent(protocol = Protocols::ALL) {
+                request UnRegisterEventReq {}
+                response UnRegisterEventResp {
+                    message: String,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = ServiceUnRegisterResponse::decode_from_slice(&input)?;
+
+            Ok(EventMessage::new(UnRegisterEventResp {
+                message: response.message.unwrap_or_default(),
+            }))
+        }
+
+        async fn build(_event: EventMessage, context: Arc<BotContext>) -> Result<Bytes> {
+            let device_name = context.keystore.read().expect("RwLock poisoned").device_name.clone();
+
+            let request = ServiceUnRegister {
+                field1: 0,
+                online: Some(online_os_info(&context, device_name)),
+                user_trigger: 1,
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+/// The device as shown in the list of logged in devices
+fn online_os_info(context: &BotContext, device_name: String) -> OnlineOsInfo {
+    let app_info = context.app_info.inner();
+    OnlineOsInfo {
+        user: device_name,
+        os: app_info.kernel.clone(),
+        os_version: String::new(),
+        vendor_name: None,
+        os_lower: app_info.vendor_os.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keystore::BotKeystore;
+    use crate::protocol::TypedService;
+
+    #[tokio::test]
+    async fn test_build_register() {
+        let keystore = BotKeystore { guid: vec![0xAB; 16], ..BotKeystore::new() };
+        let context = BotContext::builder().keystore(keystore).build();
+
+        let bytes = RegisterService::default().build(&RegisterEventReq {}, context.clone()).await.unwrap();
+        let request = ServiceRegister::decode_from_slice(&bytes).unwrap();
+        assert_eq!(request.guid, "AB".repeat(16));
+        assert_eq!(request.current_version, context.app_info.inner().current_version);
+        assert_eq!(request.locale_id, LOCALE_ID);
+        assert_eq!(request.online.unwrap().os_lower, context.app_info.inner().vendor_os);
+    }
+
+    #[tokio::test]
+    async fn test_build_unregister() {
+        let keystore = BotKeystore { device_name: "lagrange-test".to_string(), ..BotKeystore::new() };
+        let context = BotContext::builder().keystore(keystore).build();
+
+        let bytes = UnRegisterService::default().build(&UnRegisterEventReq {}, context.clone()).await.unwrap();
+        let request = ServiceUnRegister::decode_from_slice(&bytes).unwrap();
+        assert_eq!(request.user_trigger, 1);
+        let online = request.online.unwrap();
+        assert_eq!(online.user, "lagrange-test");
+        assert_eq!(online.os, context.app_info.inner().kernel);
+
+        // Field 1, the device, then the trigger
+        let online = online.encode_to_vec().unwrap();
+        let mut expected = vec![0x08, 0x00, 0x12, online.len() as u8];
+        expected.extend_from_slice(&online);
+        expected.extend_from_slice(&[0x18, 0x01]);
+        assert_eq!(bytes.as_ref(), expected.as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_parse_register() {
+        async fn parse(message: &str) -> crate::error::Result<RegisterEventResp> {
+            let response = ServiceRegisterResponse {
+                message: Some(message.to_string()),
+                timestamp: Some(1_700_000_000),
+            };
+            RegisterService::default()
+                .parse(Bytes::from(response.encode_to_vec().unwrap()), BotContext::builder().build())
+                .await
+        }
+
+        assert_eq!(parse("register success").await.unwrap().timestamp, 1_700_000_000);
+        assert!(matches!(
+            parse("register failed").await,
+            Err(crate::error::Error::ProtocolError(_))
+        ));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::common::OnlineStatus;
+use crate::context::BotContext;
+use crate::internal::packets::login::{SetStatus, SetStatusCustomExt, SetStatusResponse};
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+define_service! {
+    SetStatusService {
+        command: "trpc.qq_new_tech.status_svc.StatusService.SetStatus",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SetStatusEvent(protocol = Protocols::ALL) {
+                request SetStatusEventReq {
+                    status: OnlineStatus,
+                }
+                response SetStatusEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = SetStatusResponse::decode_from_slice(&input)?;
+
+            let message = response.message.unwrap_or_default();
+            if message != SetStatusResponse::SUCCESS {
+                return Err(crate::error::Error::StatusRejected(message));
+            }
+
+            Ok(EventMessage::new(SetStatusEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let req = event
+                .downcast_ref::<SetStatusEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let custom_ext = match &req.status {
+                OnlineStatus::Custom { face_id, text } => Some(SetStatusCustomExt {
+                    face_id: *face_id,
+                    text: Some(text.clone()),
+                    field3: 1,
+                }),
+                _ => None,
+            };
+            let request = SetStatus {
+                field1: 10,
+                status: req.status.status_code(),
+                ext_status: req.status.ext_status_code(),
+                custom_ext,
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+
+    async fn build(status: OnlineStatus) -> Bytes {
+        let request = SetStatusEventReq { status };
+        SetStatusService::default().build(&request, BotContext::builder().build()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build_standard_status() {
+        // 10, busy, no extended status
+        assert_eq!(build(OnlineStatus::Busy).await.as_ref(), &[0x08, 0x0A, 0x10, 0x32, 0x18, 0x00]);
+    }
+
+    #[tokio::test]
+    async fn test_build_custom_status() {
+        let bytes = build(OnlineStatus::Custom { face_id: 14, text: "hi".to_string() }).await;
+        // 10, online, extended status 2000, then the
+        // face, its text and field 3
+        let expected = [
+            0x08, 0x0A, 0x10, 0x0A, 0x18, 0xD0, 0x0F, 0x22, 0x08, 0x08, 0x0E, 0x12, 0x02, b'h', b'i', 0x18, 0x01,
+        ];
+        assert_eq!(bytes.as_ref(), &expected);
+    }
+
+    #[tokio::test]
+    async fn test_parse_set_status() {
+        async fn parse(message: &str) -> crate::error::Result<SetStatusEventResp> {
+            let response = SetStatusResponse { message: Some(message.to_string()) };
+            SetStatusService::default()
+                .parse(Bytes::from(response.encode_to_vec().unwrap()), BotContext::builder().build())
+                .await
+        }
+
+        assert!(parse("set status success").await.is_ok());
+        assert!(matches!(
+            parse("status not allowed").await,
+            Err(crate::error::Error::StatusRejected(message)) if message == "status not allowed"
+        ));
+    }
+}
@@ -11,6 +11,36 @@ use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
 use crate::utils::binary::BinaryPacket;
 use crate::utils::tlv_unpack;
 
+/// QR code states reported by `wtlogin.trans_emp` 0x12 polling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum QrCodeState {
+    Confirmed = 0,
+    CodeExpired = 17,
+    WaitingForScan = 48,
+    WaitingForConfirm = 53,
+    Canceled = 54,
+    Unknown = 255,
+}
+
+impl From<u8> for QrCodeState {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => QrCodeState::Confirmed,
+            17 => QrCodeState::CodeExpired,
+            48 => QrCodeState::WaitingForScan,
+            53 => QrCodeState::WaitingForConfirm,
+            54 => QrCodeState::Canceled,
+            _ => QrCodeState::Unknown,
+        }
+    }
+}
+
+/// TLV of a 0x31 response with the QR code as PNG
+const TLV_QRCODE_IMAGE: u16 = 0x17;
+/// TLV of a 0x31 response with the lifetime of the code in seconds
+const TLV_QRCODE_EXPIRATION: u16 = 0x1C;
+
 define_service! {
     TransEmpService {
         command: "wtlogin.trans_emp",
@@ -18,18 +48,21 @@ define_service! {
         encrypt_type: EncryptType::EncryptEmpty,
 
         events {
-            TransEmp31Event(protocol = Protocols::PC) {
+            // Android clients only use these to confirm an unusual device after a password login
+            TransEmp31Event(protocol = Protocols::ALL) {
                 request TransEmp31EventReq {
                     unusual_sig: Option<Vec<u8>>,
                 }
                 response TransEmp31EventResp {
                     qr_url: String,
+                    image: Vec<u8>,
+                    expiration: Option<u32>,
                     tlvs: HashMap<u16, Vec<u8>>,
                     sig: Option<Vec<u8>>,
                 }
             }
 
-            TransEmp12Event(protocol = Protocols::PC) {
+            TransEmp12Event(protocol = Protocols::ALL) {
                 request TransEmp12EventReq {}
                 response TransEmp12EventResp {
                     ret_code: u8,
@@ -43,7 +76,7 @@ define_service! {
         }
 
         async fn parse(input: Bytes, context: Arc<BotContext>) -> Result<EventMessage> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
@@ -88,8 +121,17 @@ define_service! {
                             "Missing qr_url in QrExtInfo".to_string()
                         ))?;
                     
+                    let image = tlvs.get(&TLV_QRCODE_IMAGE).cloned().unwrap_or_default();
+                    let expiration = tlvs.get(&TLV_QRCODE_EXPIRATION).and_then(|data| match data.len() {
+                        2 => Some(u16::from_be_bytes(data[..2].try_into().ok()?) as u32),
+                        4 => Some(u32::from_be_bytes(data[..4].try_into().ok()?)),
+                        _ => None,
+                    });
+
                     Ok(EventMessage::new(TransEmp31EventResp {
                         qr_url,
+                        image,
+                        expiration,
                         tlvs,
                         sig,
                     }))
@@ -137,7 +179,7 @@ define_service! {
         }
 
         async fn build(event: EventMessage, context: Arc<BotContext>) -> Result<Bytes> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
@@ -168,4 +210,72 @@ impl TransEmp12EventResp {
     pub fn is_success(&self) -> bool {
         self.ret_code == 0
     }
+
+    pub fn state(&self) -> QrCodeState {
+        QrCodeState::from(self.ret_code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    /// 0x31 body: header, the sig, then tlvs 0x17, 0x1C and 0xD1
+    fn qrcode_body(context: &Arc<BotContext>, expiration: &[u8]) -> Bytes {
+        let ext_info = QrExtInfo {
+            qr_url: Some("https://txz.qq.com/p?k=abc&f=1600001604".to_string()),
+            ..Default::default()
+        };
+        let ext_info = ext_info.encode_to_vec().unwrap();
+        let tlvs: [(u16, &[u8]); 3] = [
+            (TLV_QRCODE_IMAGE, b"\x89PNG\r\n\x1a\n"),
+            (TLV_QRCODE_EXPIRATION, expiration),
+            (0xD1, &ext_info),
+        ];
+
+        let mut body = BinaryPacket::with_capacity(128);
+        body.write(0i16);
+        body.write(context.app_info.app_id() as i32);
+        body.write(0u8);
+        body.write(6i16);
+        body.write_bytes(b"qr-sig");
+        body.write(tlvs.len() as u16);
+        for (tag, data) in tlvs {
+            body.write(tag);
+            body.write(data.len() as u16);
+            body.write_bytes(data);
+        }
+
+        let mut keystore = context.keystore.write().unwrap();
+        let packet = WtLogin::new(&mut keystore, context.app_info.inner()).unwrap();
+        Bytes::from(packet.build_test_code_2d_response(0x31, body.as_slice()))
+    }
+
+    async fn parse(context: &Arc<BotContext>, expiration: &[u8]) -> TransEmp31EventResp {
+        let response = TransEmpService::default()
+            .parse(qrcode_body(context, expiration), context.clone())
+            .await
+            .unwrap();
+        let TransEmpServiceResponse::TransEmp31Event(resp) = response else {
+            panic!("expected TransEmp31 response");
+        };
+        resp
+    }
+
+    #[tokio::test]
+    async fn test_parse_qrcode() {
+        let context = BotContext::builder().build();
+        let resp = parse(&context, &[0x00, 0x00, 0x00, 0x78]).await;
+
+        assert_eq!(resp.qr_url, "https://txz.qq.com/p?k=abc&f=1600001604");
+        assert_eq!(resp.image, b"\x89PNG\r\n\x1a\n");
+        assert_eq!(resp.sig.as_deref(), Some(&b"qr-sig"[..]));
+        assert_eq!(resp.expiration, Some(120));
+
+        // Some versions send the lifetime as 2 bytes
+        assert_eq!(parse(&context, &[0x00, 0x78]).await.expiration, Some(120));
+        assert_eq!(parse(&context, &[0x78]).await.expiration, None);
+    }
 }
@@ -31,7 +31,7 @@ define_service! {
         }
 
         async fn parse(input: Bytes, context: Arc<BotContext>) -> Result<EventMessage> {
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
@@ -120,7 +120,7 @@ define_service! {
             let input = event.downcast_ref::<UinResolveEventReq>()
                 .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
 
-            let mut keystore = context.keystore.write().expect("RwLock poisoned");
+            let mut keystore = context.keystore_mut();
             let app_info = context.app_info.inner();
 
             let packet = WtLogin::new(&mut keystore, app_info)
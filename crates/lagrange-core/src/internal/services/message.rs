@@ -0,0 +1,13 @@
+use lagrange_macros::auto_reexport;
+
+auto_reexport! {
+    pub mod fetch_rkey;
+    pub mod forward_message;
+    pub mod input_status;
+    pub mod push_message;
+    pub mod recall_message;
+    pub mod rich_media;
+    pub mod send_message;
+    pub mod upload_image;
+    pub mod upload_record;
+}
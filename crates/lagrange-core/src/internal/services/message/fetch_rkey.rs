@@ -0,0 +1,103 @@
+use super::rich_media::{build_rkey_fetch, parse_rkey_fetch};
+use crate::context::BotContext;
+use crate::internal::context::{MediaRKey, RKeyKind};
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+define_service! {
+    FetchRKeyService {
+        command: "OidbSvcTrpcTcp.0x9067_202",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchRKeyEvent(protocol = Protocols::ALL) {
+                request FetchRKeyEventReq {}
+                response FetchRKeyEventResp {
+                    rkeys: Vec<MediaRKey>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let rkeys = parse_rkey_fetch(&input)?
+                .into_iter()
+                .filter_map(|info| {
+                    Some(MediaRKey {
+                        kind: RKeyKind::from_type_id(info.rkey_type)?,
+                        param: info.rkey,
+                        expires_at: (info.rkey_create_time + info.rkey_ttl_sec) as i64,
+                    })
+                })
+                .collect();
+
+            Ok(EventMessage::new(FetchRKeyEventResp { rkeys }))
+        }
+
+        async fn build(_event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            build_rkey_fetch(0x9067, vec![RKeyKind::Private.type_id(), RKeyKind::Group.type_id()])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::{
+        DownloadRKeyResp, NtV2RichMediaReq, NtV2RichMediaResp, OidbSvcTrpcTcpBase, RKeyInfo,
+    };
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    #[tokio::test]
+    async fn test_build_request() {
+        let bytes = FetchRKeyService::default()
+            .build(&FetchRKeyEventReq {}, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x9067, 202));
+        let request = NtV2RichMediaReq::decode_from_slice(&oidb.body.unwrap()).unwrap();
+        assert_eq!(request.req_head.unwrap().common.unwrap().command, 202);
+        assert_eq!(request.download_rkey.unwrap().types, vec![10, 20]);
+    }
+
+    #[tokio::test]
+    async fn test_parse_rkeys() {
+        let rkey = |rkey_type, rkey: &str| RKeyInfo {
+            rkey: rkey.to_string(),
+            rkey_ttl_sec: 3600,
+            rkey_create_time: 1_700_000_000,
+            rkey_type,
+            ..Default::default()
+        };
+        let response = NtV2RichMediaResp {
+            download_rkey: Some(DownloadRKeyResp {
+                rkeys: vec![rkey(10, "&rkey=private"), rkey(20, "&rkey=group"), rkey(2, "&rkey=other")],
+            }),
+            ..Default::default()
+        };
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x9067,
+            sub_command: 202,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+
+        let parsed = FetchRKeyService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!(
+            parsed.rkeys,
+            vec![
+                MediaRKey { kind: RKeyKind::Private, param: "&rkey=private".to_string(), expires_at: 1_700_003_600 },
+                MediaRKey { kind: RKeyKind::Group, param: "&rkey=group".to_string(), expires_at: 1_700_003_600 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use crate::context::BotContext;
+use crate::internal::packets::message::{
+    LongMsgAction, LongMsgAttr, LongMsgContent, LongMsgInterfaceReq, LongMsgInterfaceRsp,
+    LongMsgPeerInfo, LongMsgRecvReq, LongMsgResult, LongMsgSendReq,
+};
+use crate::message::MessageNode;
+use bytes::Bytes;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+/// Action holding the messages of the bundle itself
+const MULTI_MSG_ACTION: &str = "MultiMsg";
+
+define_service! {
+    UploadForwardService {
+        command: "trpc.group.long_msg_interface.MsgService.SsoSendLongMsg",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            UploadForwardEvent(protocol = Protocols::ALL) {
+                request UploadForwardEventReq {
+                    group_uin: Option<u64>,
+                    nodes: Vec<MessageNode>,
+                }
+                response UploadForwardEventResp {
+                    res_id: String,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = LongMsgInterfaceRsp::decode_from_slice(&input)?;
+
+            let res_id = response
+                .send_rsp
+                .and_then(|send| send.res_id)
+                .filter(|res_id| !res_id.is_empty())
+                .ok_or_else(|| crate::error::Error::ProtocolError("Uploading forward returned no resid".to_string()))?;
+
+            Ok(EventMessage::new(UploadForwardEventResp { res_id }))
+        }
+
+        async fn build(event: EventMessage, context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<UploadForwardEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let result = LongMsgResult {
+                actions: vec![LongMsgAction {
+                    action_command: MULTI_MSG_ACTION.to_string(),
+                    action_data: Some(LongMsgContent {
+                        msg_body: input
+                            .nodes
+                            .iter()
+                            .enumerate()
+                            .map(|(index, node)| node.to_push_body(input.group_uin, index as u32 + 1))
+                            .collect(),
+                    }),
+                }],
+            };
+            let payload = result
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+
+            let peer_uid = match input.group_uin {
+                Some(group_uin) => group_uin.to_string(),
+                None => context.bot_uid().unwrap_or_default(),
+            };
+            let request = LongMsgInterfaceReq {
+                send_req: Some(LongMsgSendReq {
+                    msg_type: if input.group_uin.is_some() { 3 } else { 1 },
+                    peer_info: Some(LongMsgPeerInfo { peer_uid }),
+                    group_uin: input.group_uin.map(|group_uin| group_uin as u32),
+                    payload: Some(gzip(&payload)),
+                }),
+                attr: Some(LongMsgAttr {
+                    sub_cmd: 4,
+                    client_type: 1,
+                    platform: 7,
+                    proxy_type: 0,
+                }),
+                ..Default::default()
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+define_service! {
+    DownloadForwardService {
+        command: "trpc.group.long_msg_interface.MsgService.SsoRecvLongMsg",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            DownloadForwardEvent(protocol = Protocols::ALL) {
+                request DownloadForwardEventReq {
+                    res_id: String,
+                }
+                response DownloadForwardEventResp {
+                    nodes: Vec<MessageNode>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = LongMsgInterfaceRsp::decode_from_slice(&input)?;
+
+            let payload = response
+                .recv_rsp
+                .and_then(|recv| recv.payload)
+                .ok_or_else(|| crate::error::Error::ProtocolError("Downloading forward returned no payload".to_string()))?;
+            let payload = gunzip(&payload)
+                .map_err(|e| crate::error::Error::ParseError(format!("Invalid forward payload: {}", e)))?;
+            let result = LongMsgResult::decode_from_slice(&payload)?;
+
+            // Bundles may carry further actions, e.g. for previews, only the messages matter here
+            let nodes = result
+                .actions
+                .into_iter()
+                .find(|action| action.action_command == MULTI_MSG_ACTION)
+                .and_then(|action| action.action_data)
+                .map(|content| content.msg_body.into_iter().map(MessageNode::from_push_body).collect())
+                .unwrap_or_default();
+
+            Ok(EventMessage::new(DownloadForwardEventResp { nodes }))
+        }
+
+        async fn build(event: EventMessage, context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<DownloadForwardEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = LongMsgInterfaceReq {
+                recv_req: Some(LongMsgRecvReq {
+                    peer_info: Some(LongMsgPeerInfo {
+                        peer_uid: context.bot_uid().unwrap_or_default(),
+                    }),
+                    res_id: input.res_id.clone(),
+                    acquire: true,
+                }),
+                attr: Some(LongMsgAttr {
+                    sub_cmd: 2,
+                    client_type: 1,
+                    platform: 7,
+                    proxy_type: 0,
+                }),
+                ..Default::default()
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // Writing into a Vec cannot fail
+    encoder.write_all(data).expect("write to Vec");
+    encoder.finish().expect("write to Vec")
+}
+
+fn gunzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::message::long_msg::{LongMsgRecvRsp, LongMsgSendRsp};
+    use crate::message::{MessageChain, MessageEntity};
+    use crate::protocol::TypedService;
+    use std::collections::HashMap;
+
+    /// Stands in for the server: keeps uploaded payloads and hands them back by resid
+    #[derive(Default)]
+    struct MockLongMsgServer {
+        stored: HashMap<String, Vec<u8>>,
+    }
+
+    impl MockLongMsgServer {
+        async fn upload(&mut self, context: &Arc<BotContext>, request: UploadForwardEventReq) -> String {
+            let service = UploadForwardService::default();
+            let sent = LongMsgInterfaceReq::decode_from_slice(&service.build(&request, context.clone()).await.unwrap())
+                .unwrap()
+                .send_req
+                .unwrap();
+
+            let res_id = format!("res_{}", self.stored.len());
+            self.stored.insert(res_id.clone(), sent.payload.unwrap());
+
+            let response = LongMsgInterfaceRsp {
+                send_rsp: Some(LongMsgSendRsp { res_id: Some(res_id) }),
+                ..Default::default()
+            };
+            service
+                .parse(Bytes::from(response.encode_to_vec().unwrap()), context.clone())
+                .await
+                .unwrap()
+                .res_id
+        }
+
+        async fn download(&self, context: &Arc<BotContext>, res_id: &str) -> Vec<MessageNode> {
+            let service = DownloadForwardService::default();
+            let request = DownloadForwardEventReq { res_id: res_id.to_string() };
+            let sent = LongMsgInterfaceReq::decode_from_slice(&service.build(&request, context.clone()).await.unwrap())
+                .unwrap()
+                .recv_req
+                .unwrap();
+
+            let response = LongMsgInterfaceRsp {
+                recv_rsp: Some(LongMsgRecvRsp {
+                    res_id: Some(sent.res_id.clone()),
+                    payload: self.stored.get(&sent.res_id).cloned(),
+                }),
+                ..Default::default()
+            };
+            service
+                .parse(Bytes::from(response.encode_to_vec().unwrap()), context.clone())
+                .await
+                .unwrap()
+                .nodes
+        }
+    }
+
+    fn nodes() -> Vec<MessageNode> {
+        vec![
+            MessageNode::new(10001, "alice", 1700000000, "first"),
+            MessageNode::new(
+                10002,
+                "bob",
+                1700000060,
+                MessageChain::builder().text("second ").face(14).build(),
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_two_node_round_trip() {
+        let context = BotContext::builder().build();
+        let mut server = MockLongMsgServer::default();
+
+        for group_uin in [Some(123456), None] {
+            let res_id = server.upload(&context, UploadForwardEventReq { group_uin, nodes: nodes() }).await;
+            assert_eq!(server.download(&context, &res_id).await, nodes());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nested_forward() {
+        let context = BotContext::builder().build();
+        let mut server = MockLongMsgServer::default();
+
+        let inner = server.upload(&context, UploadForwardEventReq { group_uin: None, nodes: nodes() }).await;
+        let outer_nodes = vec![MessageNode::new(
+            10003,
+            "carol",
+            1700000120,
+            MessageChain::builder().forward(inner.clone()).build(),
+        )];
+        let outer = server.upload(&context, UploadForwardEventReq { group_uin: Some(123456), nodes: outer_nodes.clone() }).await;
+
+        let downloaded = server.download(&context, &outer).await;
+        assert_eq!(downloaded, outer_nodes);
+        let MessageEntity::Forward { res_id } = &downloaded[0].chain.entities()[0] else {
+            panic!("expected a forward element, got {:?}", downloaded[0].chain);
+        };
+        assert_eq!(server.download(&context, res_id).await, nodes());
+    }
+
+    #[tokio::test]
+    async fn test_payload_is_gzipped() {
+        let context = BotContext::builder().build();
+        let request = UploadForwardEventReq { group_uin: Some(123456), nodes: nodes() };
+        let sent = LongMsgInterfaceReq::decode_from_slice(
+            &UploadForwardService::default().build(&request, context).await.unwrap(),
+        )
+        .unwrap()
+        .send_req
+        .unwrap();
+
+        let payload = sent.payload.unwrap();
+        assert_eq!(&payload[..2], &[0x1f, 0x8b]);
+        let result = LongMsgResult::decode_from_slice(&gunzip(&payload).unwrap()).unwrap();
+        assert_eq!(result.actions[0].action_command, MULTI_MSG_ACTION);
+        assert_eq!(result.actions[0].action_data.as_ref().unwrap().msg_body.len(), 2);
+    }
+}
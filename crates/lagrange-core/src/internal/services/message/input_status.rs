@@ -0,0 +1,87 @@
+use crate::context::BotContext;
+use crate::internal::packets::message::{InputStatusRequest, InputStatusResponse};
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+define_service! {
+    SetInputStatusService {
+        command: "trpc.msg.msg_svc.MsgService.SsoC2CInputStatus",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SetInputStatusEvent(protocol = Protocols::ALL) {
+                request SetInputStatusEventReq {
+                    uid: String,
+                    typing: bool,
+                }
+                response SetInputStatusEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = InputStatusResponse::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = response.result {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Setting input status failed ({}): {}",
+                    code,
+                    response.err_msg.unwrap_or_default()
+                )));
+            }
+
+            Ok(EventMessage::new(SetInputStatusEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<SetInputStatusEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = InputStatusRequest {
+                target_uid: input.uid.clone(),
+                event_type: match input.typing {
+                    true => InputStatusRequest::TYPING,
+                    false => InputStatusRequest::STOPPED,
+                },
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+
+    async fn build(typing: bool) -> Bytes {
+        let request = SetInputStatusEventReq { uid: "u_abc".to_string(), typing };
+        SetInputStatusService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build_body() {
+        // Field 1 "u_abc", field 2 the event type
+        assert_eq!(build(true).await.as_ref(), b"\x0a\x05u_abc\x10\x01");
+        assert_eq!(build(false).await.as_ref(), b"\x0a\x05u_abc\x10\x02");
+    }
+
+    #[tokio::test]
+    async fn test_parse_failure() {
+        let response = InputStatusResponse { result: Some(5), err_msg: Some("not friend".to_string()) };
+        let result = SetInputStatusService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await;
+        assert!(matches!(result, Err(crate::error::Error::ProtocolError(_))));
+    }
+}
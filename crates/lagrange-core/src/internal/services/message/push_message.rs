@@ -0,0 +1,1238 @@
+use crate::common::{
+    user_avatar_url, BotFriend, EmojiId, EssenceMessageEvent, FriendInfoChangedEvent, FriendInfoField,
+    FriendMessageEvent, FriendRequestEvent, FriendTypingEvent, GrayTipEvent, GroupJoinRequestEvent,
+    GroupMemberDecreaseEvent, GroupMemberDecreaseKind, GroupMemberIncreaseEvent, GroupMemberIncreaseKind,
+    GroupMessageEvent, GroupMuteEvent, GroupReactionEvent, MessageRecallEvent, PokeEvent, TempMessageEvent,
+    UnknownGrayTip,
+};
+use crate::context::BotContext;
+use crate::internal::packets::message::{
+    FriendInputStatusContent, FriendRecallContent, FriendRequestContent, GeneralGrayTip, GroupEssenceNotice,
+    GroupInvitedJoinContent, GroupJoinRequestContent, GroupMemberChange, GroupMemberChangeOperator, GroupMuteContent,
+    GroupNotifyBody, GroupReactionChange, InputStatusRequest, ProfileChangeContent, ProfileField, PushContentHead,
+    PushMsg, PushMsgBody,
+};
+use crate::message::MessageChain;
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+/// A message carried by a `MsgPush`, classified by where it was sent.
+///
+/// [`PushMessageEventResp::message`] is `None` for pushes that are not chat messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncomingMessage {
+    Friend(FriendMessageEvent),
+    Group(GroupMessageEvent),
+    Temp(TempMessageEvent),
+}
+
+impl IncomingMessage {
+    /// Wrap the inner event for the event bus
+    pub fn into_event(self) -> EventMessage {
+        match self {
+            IncomingMessage::Friend(event) => EventMessage::new(event),
+            IncomingMessage::Group(event) => EventMessage::new(event),
+            IncomingMessage::Temp(event) => EventMessage::new(event),
+        }
+    }
+}
+
+/// A friend or group join request carried by a `MsgPush`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncomingRequest {
+    Friend(FriendRequestEvent),
+    GroupJoin(GroupJoinRequestEvent),
+}
+
+impl IncomingRequest {
+    /// Wrap the inner event for the event bus
+    pub fn into_event(self) -> EventMessage {
+        match self {
+            IncomingRequest::Friend(event) => EventMessage::new(event),
+            IncomingRequest::GroupJoin(event) => EventMessage::new(event),
+        }
+    }
+}
+
+/// A notification carried by a `MsgPush`, about something that happened to a message or contact
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncomingNotice {
+    Recall(MessageRecallEvent),
+    Poke(PokeEvent),
+    Essence(EssenceMessageEvent),
+    Reaction(GroupReactionEvent),
+    GrayTip(GrayTipEvent),
+    Typing(FriendTypingEvent),
+    MemberIncrease(GroupMemberIncreaseEvent),
+    MemberDecrease(GroupMemberDecreaseEvent),
+    Mute(GroupMuteEvent),
+    FriendInfo(FriendInfoChangedEvent),
+}
+
+impl IncomingNotice {
+    /// Wrap the inner event for the event bus
+    pub fn into_event(self) -> EventMessage {
+        match self {
+            IncomingNotice::Recall(event) => EventMessage::new(event),
+            IncomingNotice::Poke(event) => EventMessage::new(event),
+            IncomingNotice::Essence(event) => EventMessage::new(event),
+            IncomingNotice::Reaction(event) => EventMessage::new(event),
+            IncomingNotice::GrayTip(event) => EventMessage::new(event),
+            IncomingNotice::Typing(event) => EventMessage::new(event),
+            IncomingNotice::MemberIncrease(event) => EventMessage::new(event),
+            IncomingNotice::MemberDecrease(event) => EventMessage::new(event),
+            IncomingNotice::Mute(event) => EventMessage::new(event),
+            IncomingNotice::FriendInfo(event) => EventMessage::new(event),
+        }
+    }
+}
+
+define_service! {
+    PushMessageService {
+        command: "trpc.msg.olpush.OlPushService.MsgPush",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            PushMessageEvent(protocol = Protocols::ALL) {
+                request PushMessageEventReq {}
+                response PushMessageEventResp {
+                    message: Option<IncomingMessage>,
+                    request: Option<IncomingRequest>,
+                    notice: Option<IncomingNotice>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, context: Arc<BotContext>) -> Result<EventMessage> {
+            let push = PushMsg::decode_from_slice(&input)?;
+
+            // Every message names its sender by both uin and uid
+            if let Some(head) = push.message.as_ref().and_then(|message| message.response_head.as_ref()) {
+                context
+                    .cache
+                    .map_uid(head.from_uin.unwrap_or_default() as u64, head.from_uid.as_deref().unwrap_or_default());
+            }
+
+            let request = push.message.as_ref().and_then(|message| classify_request(message, &context));
+            let notice = push.message.as_ref().and_then(|message| classify_notice(message, &context));
+            Ok(EventMessage::new(PushMessageEventResp {
+                message: push.message.and_then(classify_message),
+                request,
+                notice,
+            }))
+        }
+
+        async fn build(_event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            Err(crate::error::Error::BuildError(
+                "MsgPush is only sent by the server".to_string(),
+            ))
+        }
+    }
+}
+
+fn classify_message(message: PushMsgBody) -> Option<IncomingMessage> {
+    let head = message.response_head?;
+    let content = message.content_head?;
+
+    let chain = message
+        .body
+        .and_then(|body| body.rich_text)
+        .map(|rich_text| MessageChain::from_encoded_elems(&rich_text.elems))
+        .unwrap_or_default();
+
+    let sender_uin = head.from_uin.unwrap_or_default() as u64;
+    let sender_uid = head.from_uid.unwrap_or_default();
+    let sequence = content.sequence.unwrap_or_default();
+    let random = content.random.unwrap_or_default();
+    let timestamp = content.timestamp.unwrap_or_default() as i64;
+    let friend_name = head.forward.and_then(|forward| forward.friend_name).unwrap_or_default();
+
+    match content.msg_type? {
+        PushContentHead::FRIEND => Some(IncomingMessage::Friend(FriendMessageEvent {
+            sender_uin,
+            sender_uid,
+            sender_nickname: friend_name,
+            sequence,
+            random,
+            timestamp,
+            chain,
+        })),
+        PushContentHead::GROUP => {
+            let group = head.grp?;
+            Some(IncomingMessage::Group(GroupMessageEvent {
+                group_uin: group.group_uin.unwrap_or_default() as u64,
+                group_name: group.group_name.unwrap_or_default(),
+                sender_uin,
+                sender_uid,
+                sender_nickname: group.member_name.unwrap_or_default(),
+                sequence,
+                random,
+                timestamp,
+                chain,
+            }))
+        }
+        PushContentHead::TEMP => Some(IncomingMessage::Temp(TempMessageEvent {
+            sender_uin,
+            sender_uid,
+            sender_nickname: friend_name,
+            sequence,
+            random,
+            timestamp,
+            chain,
+        })),
+        _ => None,
+    }
+}
+
+fn classify_request(message: &PushMsgBody, context: &BotContext) -> Option<IncomingRequest> {
+    let head = message.response_head.as_ref()?;
+    let content = message.content_head.as_ref()?;
+    let msg_content = message.body.as_ref()?.msg_content.as_deref()?;
+
+    match (content.msg_type?, content.sub_type) {
+        (PushContentHead::EVENT, Some(PushContentHead::SUB_FRIEND_REQUEST)) => {
+            let info = FriendRequestContent::decode_from_slice(msg_content).ok()?.info?;
+            Some(IncomingRequest::Friend(FriendRequestEvent {
+                source_uin: head.from_uin.unwrap_or_default() as u64,
+                message: info.message.unwrap_or_default(),
+                token: info.source_uid?,
+            }))
+        }
+        (PushContentHead::GROUP_JOIN_REQUEST, _) => {
+            let join = GroupJoinRequestContent::decode_from_slice(msg_content).ok()?;
+            let target_uid = join.target_uid?;
+            Some(IncomingRequest::GroupJoin(GroupJoinRequestEvent {
+                group_uin: join.group_uin? as u64,
+                // The sender of the push is the user asking to join
+                target_uin: context
+                    .cache
+                    .resolve_uin(&target_uid)
+                    .unwrap_or(head.from_uin.unwrap_or_default() as u64),
+                target_uid,
+                invitor: None,
+                comment: join.comment.unwrap_or_default(),
+                sequence: join.sequence.unwrap_or_default(),
+            }))
+        }
+        (PushContentHead::GROUP_INVITED_JOIN_REQUEST, _) => {
+            let inner = GroupInvitedJoinContent::decode_from_slice(msg_content).ok()?.info?.inner?;
+            let target_uid = inner.target_uid?;
+            Some(IncomingRequest::GroupJoin(GroupJoinRequestEvent {
+                group_uin: inner.group_uin? as u64,
+                target_uin: context.cache.resolve_uin(&target_uid).unwrap_or_default(),
+                target_uid,
+                invitor: inner
+                    .invitor_uid
+                    .map(|uid| context.cache.resolve_uin(&uid).unwrap_or_default()),
+                comment: String::new(),
+                sequence: inner.sequence.unwrap_or_default(),
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn classify_notice(message: &PushMsgBody, context: &BotContext) -> Option<IncomingNotice> {
+    let content = message.content_head.as_ref()?;
+    let msg_content = message.body.as_ref()?.msg_content.as_deref()?;
+    let resolve = |uid: Option<String>| {
+        uid.and_then(|uid| context.cache.resolve_uin(&uid)).unwrap_or_default()
+    };
+
+    // Member changes come without a sub type
+    match content.msg_type? {
+        PushContentHead::GROUP_MEMBER_INCREASE => {
+            let change = GroupMemberChange::decode_from_slice(msg_content).ok()?;
+            return parse_member_increase(change, context).map(IncomingNotice::MemberIncrease);
+        }
+        PushContentHead::GROUP_MEMBER_DECREASE => {
+            let change = GroupMemberChange::decode_from_slice(msg_content).ok()?;
+            return parse_member_decrease(change, context).map(IncomingNotice::MemberDecrease);
+        }
+        _ => {}
+    }
+
+    match (content.msg_type?, content.sub_type?) {
+        (PushContentHead::EVENT, PushContentHead::SUB_FRIEND_RECALL) => {
+            let info = FriendRecallContent::decode_from_slice(msg_content).ok()?.info?;
+            // Friends can only recall their own messages
+            let author = resolve(info.from_uid);
+            Some(IncomingNotice::Recall(MessageRecallEvent {
+                group: None,
+                operator: author,
+                author,
+                sequence: info.sequence?,
+            }))
+        }
+        (PushContentHead::EVENT, PushContentHead::SUB_FRIEND_INPUT_STATUS) => {
+            let status = FriendInputStatusContent::decode_from_slice(msg_content).ok()?;
+            let uin = match message.response_head.as_ref()?.from_uin {
+                Some(uin) => uin as u64,
+                None => resolve(status.from_uid),
+            };
+            Some(IncomingNotice::Typing(FriendTypingEvent {
+                uin,
+                typing: status.event_type == Some(InputStatusRequest::TYPING),
+            }))
+        }
+        (PushContentHead::EVENT, PushContentHead::SUB_PROFILE_CHANGE) if context.config.sync_friend_info => {
+            let content = ProfileChangeContent::decode_from_slice(msg_content).ok()?;
+            parse_friend_info(content, context).map(IncomingNotice::FriendInfo)
+        }
+        (PushContentHead::GROUP_EVENT, PushContentHead::SUB_GROUP_RECALL) => {
+            let body = decode_group_notify(msg_content)?;
+            let recall = body.recall?;
+            let recalled = recall.messages.into_iter().next()?;
+            Some(IncomingNotice::Recall(MessageRecallEvent {
+                group: Some(body.group_uin? as u64),
+                operator: resolve(recall.operator_uid),
+                author: resolve(recalled.author_uid),
+                sequence: recalled.sequence?,
+            }))
+        }
+        (PushContentHead::GROUP_EVENT, PushContentHead::SUB_GROUP_MUTE) => {
+            let mute = GroupMuteContent::decode_from_slice(msg_content).ok()?;
+            let state = mute.data?.state?;
+            let whole_group = state.target_uid.is_none();
+            Some(IncomingNotice::Mute(GroupMuteEvent {
+                group: mute.group_uin? as u64,
+                operator: mute.operator_uid.and_then(|uid| context.cached_uin(&uid)).unwrap_or_default(),
+                target: state.target_uid.map(|uid| context.cached_uin(&uid).unwrap_or_default()),
+                duration: Duration::from_secs(state.duration.unwrap_or_default() as u64),
+                whole_group,
+            }))
+        }
+        (PushContentHead::GROUP_EVENT, PushContentHead::SUB_GROUP_GRAY_TIP) => {
+            let body = decode_group_notify(msg_content)?;
+            let group_uin = body.group_uin? as u64;
+            let tip = body.gray_tip?;
+            match tip.busi_id? {
+                GeneralGrayTip::POKE => parse_poke(group_uin, &tip).map(IncomingNotice::Poke),
+                _ => Some(IncomingNotice::GrayTip(parse_gray_tip(group_uin, tip))),
+            }
+        }
+        (PushContentHead::GROUP_EVENT, PushContentHead::SUB_GROUP_ESSENCE) => {
+            let body = decode_group_notify(msg_content)?;
+            let essence = body.essence?;
+            Some(IncomingNotice::Essence(EssenceMessageEvent {
+                group: essence.group_uin.or(body.group_uin)? as u64,
+                operator: essence.operator_uin.unwrap_or_default() as u64,
+                sender: essence.author_uin.unwrap_or_default() as u64,
+                sequence: essence.sequence?,
+                is_set: essence.set_flag == Some(GroupEssenceNotice::SET),
+            }))
+        }
+        (PushContentHead::GROUP_EVENT, PushContentHead::SUB_GROUP_REACTION) => {
+            let body = decode_group_notify(msg_content)?;
+            let inner = body.reaction?.data?.inner?;
+            let reaction = inner.reaction?;
+            Some(IncomingNotice::Reaction(GroupReactionEvent {
+                group: body.group_uin? as u64,
+                sequence: inner.target?.sequence?,
+                operator: reaction.operator_uid.and_then(|uid| context.cached_uin(&uid)).unwrap_or_default(),
+                emoji_id: EmojiId::from_code(reaction.code.as_deref()?)?,
+                added: reaction.action == Some(GroupReactionChange::ADDED),
+                current_count: reaction.count.unwrap_or_default(),
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// The first change of a nickname, remark or avatar in `content`, with the old value taken
+/// from the cache; other profile fields are ignored
+fn parse_friend_info(content: ProfileChangeContent, context: &BotContext) -> Option<FriendInfoChangedEvent> {
+    let cached = |uin: u64, field: fn(&BotFriend) -> &String| {
+        context.cache.friend(uin).map(|friend| field(&friend).clone())
+    };
+
+    content.changes.into_iter().find_map(|change| {
+        if let Some(profile) = change.profile {
+            let uin = profile.uin?;
+            let nickname = profile.fields.into_iter().find(|field| field.field == Some(ProfileField::NICKNAME))?;
+            return Some(FriendInfoChangedEvent {
+                uin,
+                field: FriendInfoField::Nickname,
+                old: cached(uin, |friend| &friend.nickname),
+                new: String::from_utf8(nickname.value.unwrap_or_default()).ok()?,
+            });
+        }
+        if let Some(remark) = change.friend_remark.and_then(|remarks| remarks.remarks.into_iter().next()) {
+            let uin = remark.uin?;
+            return Some(FriendInfoChangedEvent {
+                uin,
+                field: FriendInfoField::Remark,
+                old: cached(uin, |friend| &friend.remarks),
+                new: String::from_utf8(remark.remark.unwrap_or_default()).ok()?,
+            });
+        }
+        let uin = change.avatar?.uin?;
+        Some(FriendInfoChangedEvent { uin, field: FriendInfoField::Avatar, old: None, new: user_avatar_url(uin) })
+    })
+}
+
+fn parse_member_increase(change: GroupMemberChange, context: &BotContext) -> Option<GroupMemberIncreaseEvent> {
+    let member_uid = change.member_uid?;
+    let operator = change.operator.and_then(|uid| String::from_utf8(uid).ok());
+    let (kind, invitor) = match change.change_type? {
+        GroupMemberChange::JOINED => (GroupMemberIncreaseKind::Joined, None),
+        GroupMemberChange::INVITED => {
+            let invitor = operator.map(|uid| context.cached_uin(&uid).unwrap_or_default());
+            (GroupMemberIncreaseKind::Invited, invitor)
+        }
+        _ => return None,
+    };
+
+    Some(GroupMemberIncreaseEvent {
+        group: change.group_uin? as u64,
+        member_uin: context.cached_uin(&member_uid).unwrap_or_default(),
+        member_uid,
+        invitor,
+        kind,
+    })
+}
+
+/// Removals of the bot have their own change type, with the operator wrapped in a message;
+/// when the bot leaves on its own, the member is its uid.
+fn parse_member_decrease(change: GroupMemberChange, context: &BotContext) -> Option<GroupMemberDecreaseEvent> {
+    let member_uid = change.member_uid?;
+    let operator = change.operator.as_deref();
+    let is_bot = context.bot_uid().as_deref() == Some(member_uid.as_str());
+    let (kind, operator_uid) = match change.change_type? {
+        GroupMemberChange::KICKED_SELF => {
+            let operator = operator
+                .and_then(|operator| GroupMemberChangeOperator::decode_from_slice(operator).ok())
+                .and_then(|operator| operator.operator?.uid);
+            (GroupMemberDecreaseKind::KickedSelf, operator)
+        }
+        GroupMemberChange::KICKED => {
+            let kind = if is_bot { GroupMemberDecreaseKind::KickedSelf } else { GroupMemberDecreaseKind::Kicked };
+            (kind, operator.and_then(|uid| String::from_utf8(uid.to_vec()).ok()))
+        }
+        GroupMemberChange::LEFT => (GroupMemberDecreaseKind::Left, None),
+        _ => return None,
+    };
+
+    Some(GroupMemberDecreaseEvent {
+        group: change.group_uin? as u64,
+        member_uin: context.cached_uin(&member_uid).unwrap_or_default(),
+        member_uid,
+        operator: operator_uid.map(|uid| context.cached_uin(&uid).unwrap_or_default()),
+        kind,
+    })
+}
+
+/// Fill a [`PokeEvent`] from the template parameters of its gray tip.
+///
+/// `uin_str1` poked `uin_str2`; the verb is in `action_str`, or `alt_str1` on older clients, and
+/// custom pokes add a `suffix_str`.
+fn parse_poke(group_uin: u64, tip: &GeneralGrayTip) -> Option<PokeEvent> {
+    Some(PokeEvent {
+        group_uin,
+        sender: tip.param("uin_str1")?.parse().ok()?,
+        receiver: tip.param("uin_str2")?.parse().ok()?,
+        action: tip
+            .param("action_str")
+            .or_else(|| tip.param("alt_str1"))
+            .filter(|action| !action.is_empty())
+            .unwrap_or("戳了戳")
+            .to_string(),
+        suffix: tip.param("suffix_str").unwrap_or_default().to_string(),
+    })
+}
+
+/// Map the gray tips of known templates to typed events, anything else, or anything missing a
+/// parameter, to [`GrayTipEvent::Unknown`]
+fn parse_gray_tip(group_uin: u64, tip: GeneralGrayTip) -> GrayTipEvent {
+    let member = tip.param("uin_str1").and_then(|uin| uin.parse().ok());
+    let typed = match tip.busi_id {
+        Some(GeneralGrayTip::HONOR) => member.zip(tip.param("honor_str")).map(|(uin, honor)| {
+            GrayTipEvent::HonorChanged { group_uin, uin, honor: honor.to_string() }
+        }),
+        Some(GeneralGrayTip::TITLE) => member.zip(tip.param("title_str")).map(|(uin, title)| {
+            GrayTipEvent::TitleGranted { group_uin, uin, title: title.to_string() }
+        }),
+        _ => None,
+    };
+
+    typed.unwrap_or_else(|| {
+        GrayTipEvent::Unknown(UnknownGrayTip {
+            group_uin,
+            busi_id: tip.busi_id.unwrap_or_default(),
+            template_id: tip.templ_id.unwrap_or_default(),
+            params: tip
+                .templ_params
+                .into_iter()
+                .map(|param| (param.name.unwrap_or_default(), param.value.unwrap_or_default()))
+                .collect(),
+            content: tip.content.unwrap_or_default(),
+        })
+    })
+}
+
+/// Skip the binary header in front of a [`GroupNotifyBody`]
+fn decode_group_notify(content: &[u8]) -> Option<GroupNotifyBody> {
+    let length = u16::from_be_bytes(content.get(5..7)?.try_into().ok()?) as usize;
+    GroupNotifyBody::decode_from_slice(content.get(7..7 + length)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::unhex;
+    use crate::common::{BotContact, BotGender, BotGroupMember, ContactKind, GroupRole, MessageEvent};
+    use crate::internal::packets::message::push::GrayTipTemplParam;
+    use crate::config::BotConfig;
+    use crate::keystore::BotKeystore;
+    use crate::message::{ImageEntity, MessageEntity};
+    use crate::protocol::TypedService;
+
+    fn member(uin: u64, uid: &str, nickname: &str) -> BotGroupMember {
+        BotGroupMember {
+            uin,
+            uid: uid.to_string(),
+            nickname: nickname.to_string(),
+            group_uin: 123456,
+            role: GroupRole::Member,
+            level: 0,
+            card: None,
+            special_title: None,
+            age: 0,
+            gender: BotGender::Unset,
+            join_time: Default::default(),
+            last_msg_time: Default::default(),
+            shut_up_timestamp: Default::default(),
+        }
+    }
+
+    async fn parse(hex: &str) -> PushMessageEventResp {
+        PushMessageService::default()
+            .parse(unhex(hex), BotContext::builder().build())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_friend_text_message() {
+        let parsed = parse(FRIEND_TEXT_PUSH).await;
+
+        let Some(IncomingMessage::Friend(event)) = parsed.message else {
+            panic!("expected a friend message, got {:?}", parsed.message);
+        };
+        assert_eq!(event.sender_uin, 10001);
+        assert_eq!(event.sender_uid, "u_abc");
+        assert_eq!(event.sender_nickname, "alice");
+        assert_eq!(event.sequence, 4242);
+        assert_eq!(event.random, 0x12345678);
+        assert_eq!(event.timestamp, 1700000000);
+        assert_eq!(event.chain, MessageChain::text("hi"));
+    }
+
+    #[tokio::test]
+    async fn test_sender_seeds_uid_cache() {
+        let context = BotContext::builder().build();
+        PushMessageService::default()
+            .parse(unhex(FRIEND_TEXT_PUSH), context.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(context.cache.resolve_uin("u_abc"), Some(10001));
+        assert_eq!(context.cache.resolve_uid(10001).as_deref(), Some("u_abc"));
+    }
+
+    #[tokio::test]
+    async fn test_group_image_message() {
+        let parsed = parse(GROUP_IMAGE_PUSH).await;
+
+        let Some(IncomingMessage::Group(event)) = parsed.message else {
+            panic!("expected a group message, got {:?}", parsed.message);
+        };
+        assert_eq!(event.group_uin, 123456);
+        assert_eq!(event.group_name, "rustaceans");
+        assert_eq!(event.sender_uin, 10002);
+        assert_eq!(event.sender_nickname, "bob");
+        assert_eq!(event.sequence, 777);
+        let sender = event.sender();
+        assert_eq!((sender.contact_kind(), sender.display_name()), (ContactKind::GroupMember, "bob".to_string()));
+        assert_eq!(
+            event.chain.entities()[0],
+            MessageEntity::Image(ImageEntity {
+                file_name: "a.png".to_string(),
+                md5: vec![0xAA; 16],
+                size: 2048,
+                width: 640,
+                height: 480,
+                url: Some("/img".to_string()),
+                ..Default::default()
+            })
+        );
+        // The trailing general-flags element is not modelled, but must not drop the event
+        assert!(matches!(event.chain.entities()[1], MessageEntity::Raw(_)));
+        assert_eq!(event.chain.to_string(), "[Image:a.png]");
+    }
+
+    #[tokio::test]
+    async fn test_non_message_push() {
+        // Message type 528 (system notification) with an empty body
+        let parsed = parse("0a0a0a00120608900410bb04").await;
+        assert_eq!(parsed.message, None);
+    }
+
+    #[tokio::test]
+    async fn test_friend_request() {
+        let parsed = parse(FRIEND_REQUEST_PUSH).await;
+
+        assert_eq!(parsed.message, None);
+        assert_eq!(
+            parsed.request,
+            Some(IncomingRequest::Friend(FriendRequestEvent {
+                source_uin: 10003,
+                message: "hello".to_string(),
+                token: "u_req".to_string(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_group_join_requests() {
+        let parsed = parse(GROUP_JOIN_REQUEST_PUSH).await;
+        assert_eq!(
+            parsed.request,
+            Some(IncomingRequest::GroupJoin(GroupJoinRequestEvent {
+                group_uin: 123456,
+                target_uin: 10004,
+                target_uid: "u_join".to_string(),
+                invitor: None,
+                comment: "let me in".to_string(),
+                sequence: 1700000000123,
+            }))
+        );
+
+        let context = BotContext::builder().build();
+        context.cache.cache_members(
+            123456,
+            vec![member(10002, "u_inv", "bob")],
+        );
+        let parsed = PushMessageService::default()
+            .parse(unhex(GROUP_INVITED_JOIN_PUSH), context)
+            .await
+            .unwrap();
+        assert_eq!(
+            parsed.request,
+            Some(IncomingRequest::GroupJoin(GroupJoinRequestEvent {
+                group_uin: 123456,
+                target_uin: 0,
+                target_uid: "u_new".to_string(),
+                invitor: Some(10002),
+                comment: String::new(),
+                sequence: 42,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recall_notices() {
+        let context = BotContext::builder().build();
+        context.cache.cache_members(
+            123456,
+            vec![
+                member(10001, "u_abc", "alice"),
+                member(10002, "u_def", "bob"),
+                member(10009, "u_op", "admin"),
+            ],
+        );
+
+        let group = PushMessageService::default()
+            .parse(unhex(GROUP_RECALL_PUSH), context.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            group.notice,
+            Some(IncomingNotice::Recall(MessageRecallEvent {
+                group: Some(123456),
+                operator: 10009,
+                author: 10002,
+                sequence: 777,
+            }))
+        );
+
+        let friend = PushMessageService::default()
+            .parse(unhex(FRIEND_RECALL_PUSH), context)
+            .await
+            .unwrap();
+        assert_eq!(
+            friend.notice,
+            Some(IncomingNotice::Recall(MessageRecallEvent {
+                group: None,
+                operator: 10001,
+                author: 10001,
+                sequence: 4242,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_poke() {
+        let parsed = parse(DEFAULT_POKE_PUSH).await;
+        assert_eq!(
+            parsed.notice,
+            Some(IncomingNotice::Poke(PokeEvent {
+                group_uin: 123456,
+                sender: 10001,
+                receiver: 10002,
+                action: "戳了戳".to_string(),
+                suffix: String::new(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_poke() {
+        let parsed = parse(CUSTOM_POKE_PUSH).await;
+        assert_eq!(
+            parsed.notice,
+            Some(IncomingNotice::Poke(PokeEvent {
+                group_uin: 123456,
+                sender: 10002,
+                receiver: 20002,
+                action: "拍了拍".to_string(),
+                suffix: "的脑袋".to_string(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_essence_notice() {
+        let parsed = parse(ESSENCE_SET_PUSH).await;
+        assert_eq!(
+            parsed.notice,
+            Some(IncomingNotice::Essence(EssenceMessageEvent {
+                group: 123456,
+                operator: 10009,
+                sender: 10002,
+                sequence: 777,
+                is_set: true,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reaction_notices() {
+        let reaction = |added, current_count| {
+            Some(IncomingNotice::Reaction(GroupReactionEvent {
+                group: 123456,
+                sequence: 777,
+                operator: 10009,
+                emoji_id: EmojiId::FIRE,
+                added,
+                current_count,
+            }))
+        };
+        assert_eq!(parse_as_bot(REACTION_ADDED_PUSH).await, reaction(true, 2));
+        assert_eq!(parse_as_bot(REACTION_REMOVED_PUSH).await, reaction(false, 1));
+    }
+
+    /// Parse `hex` as seen by bot 10000 "u_bot", with 10001 "u_abc", 10002 "u_def" and 10009
+    /// "u_op" in the member cache of 123456
+    async fn parse_as_bot(hex: &str) -> Option<IncomingNotice> {
+        let mut keystore = BotKeystore::default().with_uin(10000);
+        keystore.uid = Some("u_bot".to_string());
+        let context = BotContext::builder().keystore(keystore).build();
+        context.cache.cache_members(
+            123456,
+            vec![
+                member(10001, "u_abc", "alice"),
+                member(10002, "u_def", "bob"),
+                member(10009, "u_op", "admin"),
+            ],
+        );
+        PushMessageService::default()
+            .parse(unhex(hex), context)
+            .await
+            .unwrap()
+            .notice
+    }
+
+    #[tokio::test]
+    async fn test_member_increase() {
+        assert_eq!(
+            parse_as_bot(MEMBER_JOINED_PUSH).await,
+            Some(IncomingNotice::MemberIncrease(GroupMemberIncreaseEvent {
+                group: 123456,
+                member_uin: 10001,
+                member_uid: "u_abc".to_string(),
+                invitor: None,
+                kind: GroupMemberIncreaseKind::Joined,
+            }))
+        );
+        assert_eq!(
+            parse_as_bot(MEMBER_INVITED_PUSH).await,
+            Some(IncomingNotice::MemberIncrease(GroupMemberIncreaseEvent {
+                group: 123456,
+                member_uin: 0,
+                member_uid: "u_new".to_string(),
+                invitor: Some(10002),
+                kind: GroupMemberIncreaseKind::Invited,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_member_decrease() {
+        assert_eq!(
+            parse_as_bot(MEMBER_LEFT_PUSH).await,
+            Some(IncomingNotice::MemberDecrease(GroupMemberDecreaseEvent {
+                group: 123456,
+                member_uin: 10001,
+                member_uid: "u_abc".to_string(),
+                operator: None,
+                kind: GroupMemberDecreaseKind::Left,
+            }))
+        );
+        assert_eq!(
+            parse_as_bot(MEMBER_KICKED_PUSH).await,
+            Some(IncomingNotice::MemberDecrease(GroupMemberDecreaseEvent {
+                group: 123456,
+                member_uin: 10001,
+                member_uid: "u_abc".to_string(),
+                operator: Some(10009),
+                kind: GroupMemberDecreaseKind::Kicked,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bot_removed() {
+        assert_eq!(
+            parse_as_bot(BOT_KICKED_PUSH).await,
+            Some(IncomingNotice::MemberDecrease(GroupMemberDecreaseEvent {
+                group: 123456,
+                member_uin: 10000,
+                member_uid: "u_bot".to_string(),
+                operator: Some(10009),
+                kind: GroupMemberDecreaseKind::KickedSelf,
+            }))
+        );
+
+        // Leaving on its own is not mistaken for a kick
+        assert_eq!(
+            parse_as_bot(BOT_LEFT_PUSH).await,
+            Some(IncomingNotice::MemberDecrease(GroupMemberDecreaseEvent {
+                group: 123456,
+                member_uin: 10000,
+                member_uid: "u_bot".to_string(),
+                operator: None,
+                kind: GroupMemberDecreaseKind::Left,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_member_mute() {
+        let mute = |duration| {
+            Some(IncomingNotice::Mute(GroupMuteEvent {
+                group: 123456,
+                operator: 10009,
+                target: Some(10001),
+                duration: Duration::from_secs(duration),
+                whole_group: false,
+            }))
+        };
+        assert_eq!(parse_as_bot(MEMBER_MUTE_PUSH).await, mute(600));
+
+        let unmute = parse_as_bot(MEMBER_UNMUTE_PUSH).await;
+        assert_eq!(unmute, mute(0));
+        let Some(IncomingNotice::Mute(unmute)) = unmute else { unreachable!() };
+        assert!(unmute.is_unmute());
+    }
+
+    #[tokio::test]
+    async fn test_whole_group_mute() {
+        let mute = |duration| {
+            Some(IncomingNotice::Mute(GroupMuteEvent {
+                group: 123456,
+                operator: 10009,
+                target: None,
+                duration: Duration::from_secs(duration),
+                whole_group: true,
+            }))
+        };
+        assert_eq!(parse_as_bot(WHOLE_GROUP_MUTE_PUSH).await, mute(u32::MAX as u64));
+        assert_eq!(parse_as_bot(WHOLE_GROUP_UNMUTE_PUSH).await, mute(0));
+    }
+
+    fn friend(uin: u64, uid: &str, nickname: &str) -> BotFriend {
+        BotFriend {
+            uin,
+            uid: uid.to_string(),
+            nickname: nickname.to_string(),
+            age: 0,
+            gender: BotGender::Unset,
+            remarks: "ally".to_string(),
+            personal_sign: String::new(),
+            qid: String::new(),
+            category: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_friend_renamed() {
+        let context = BotContext::builder().build();
+        context.cache.cache_friends(vec![friend(10001, "u_abc", "alice")]);
+
+        let parsed = PushMessageService::default()
+            .parse(unhex(FRIEND_RENAMED_PUSH), context.clone())
+            .await
+            .unwrap();
+        let notice = parsed.notice.unwrap();
+        assert_eq!(
+            notice,
+            IncomingNotice::FriendInfo(FriendInfoChangedEvent {
+                uin: 10001,
+                field: FriendInfoField::Nickname,
+                old: Some("alice".to_string()),
+                new: "Alice Liddell".to_string(),
+            })
+        );
+
+        // Dispatching the event patches the cached friend
+        context.post_event(notice.into_event());
+        let cached = context.cache.friend(10001).unwrap();
+        assert_eq!(cached.nickname, "Alice Liddell");
+        assert_eq!(cached.remarks, "ally");
+    }
+
+    #[tokio::test]
+    async fn test_friend_remark_and_avatar() {
+        let context = BotContext::builder().build();
+        context.cache.cache_friends(vec![friend(10001, "u_abc", "alice")]);
+
+        let notice = PushMessageService::default()
+            .parse(unhex(FRIEND_REMARK_PUSH), context.clone())
+            .await
+            .unwrap()
+            .notice
+            .unwrap();
+        assert_eq!(
+            notice,
+            IncomingNotice::FriendInfo(FriendInfoChangedEvent {
+                uin: 10001,
+                field: FriendInfoField::Remark,
+                old: Some("ally".to_string()),
+                new: "Ali".to_string(),
+            })
+        );
+        context.post_event(notice.into_event());
+        assert_eq!(context.cache.friend(10001).unwrap().remarks, "Ali");
+
+        let notice = PushMessageService::default()
+            .parse(unhex(FRIEND_AVATAR_PUSH), context.clone())
+            .await
+            .unwrap()
+            .notice;
+        assert_eq!(
+            notice,
+            Some(IncomingNotice::FriendInfo(FriendInfoChangedEvent {
+                uin: 10001,
+                field: FriendInfoField::Avatar,
+                old: None,
+                new: "https://q.qlogo.cn/g?b=qq&nk=10001&s=640".to_string(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_friend_info_sync_disabled() {
+        let config = BotConfig::builder().sync_friend_info(false).build();
+        let context = BotContext::builder().config(config).build();
+        context.cache.cache_friends(vec![friend(10001, "u_abc", "alice")]);
+
+        let parsed = PushMessageService::default()
+            .parse(unhex(FRIEND_RENAMED_PUSH), context.clone())
+            .await
+            .unwrap();
+        assert_eq!(parsed.notice, None);
+        assert_eq!(context.cache.friend(10001).unwrap().nickname, "alice");
+    }
+
+    #[tokio::test]
+    async fn test_honor_gray_tip() {
+        let parsed = parse(HONOR_GRAY_TIP_PUSH).await;
+        assert_eq!(
+            parsed.notice,
+            Some(IncomingNotice::GrayTip(GrayTipEvent::HonorChanged {
+                group_uin: 123456,
+                uin: 10002,
+                honor: "龙王".to_string(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_title_gray_tip() {
+        let parsed = parse(TITLE_GRAY_TIP_PUSH).await;
+        assert_eq!(
+            parsed.notice,
+            Some(IncomingNotice::GrayTip(GrayTipEvent::TitleGranted {
+                group_uin: 123456,
+                uin: 10002,
+                title: "Rustacean".to_string(),
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_friend_typing() {
+        let typing = parse(TYPING_PUSH).await;
+        assert_eq!(typing.message, None);
+        assert_eq!(
+            typing.notice,
+            Some(IncomingNotice::Typing(FriendTypingEvent { uin: 10001, typing: true }))
+        );
+
+        let stopped = parse(TYPING_STOPPED_PUSH).await;
+        assert_eq!(
+            stopped.notice,
+            Some(IncomingNotice::Typing(FriendTypingEvent { uin: 10001, typing: false }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_other_gray_tip() {
+        let parsed = parse(OTHER_GRAY_TIP_PUSH).await;
+        assert_eq!(
+            parsed.notice,
+            Some(IncomingNotice::GrayTip(GrayTipEvent::Unknown(UnknownGrayTip {
+                group_uin: 123456,
+                busi_id: 1066,
+                template_id: 0,
+                params: vec![("uin_str1".to_string(), "10002".to_string())],
+                content: String::new(),
+            })))
+        );
+    }
+
+    #[test]
+    fn test_incomplete_gray_tip_is_unknown() {
+        let tip = GeneralGrayTip {
+            busi_id: Some(GeneralGrayTip::TITLE),
+            templ_id: Some(10080),
+            templ_params: vec![GrayTipTemplParam {
+                name: Some("uin_str1".to_string()),
+                value: Some("10002".to_string()),
+            }],
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_gray_tip(1, tip),
+            GrayTipEvent::Unknown(UnknownGrayTip { busi_id: GeneralGrayTip::TITLE, template_id: 10080, .. })
+        ));
+    }
+
+    #[test]
+    fn test_poke_template_params() {
+        let param = |name: &str, value: &str| GrayTipTemplParam {
+            name: Some(name.to_string()),
+            value: Some(value.to_string()),
+        };
+        let mut tip = GeneralGrayTip {
+            busi_id: Some(GeneralGrayTip::POKE),
+            templ_params: vec![param("uin_str1", "10001"), param("action_str", "")],
+            ..Default::default()
+        };
+
+        // The receiver is required
+        assert_eq!(parse_poke(1, &tip), None);
+
+        tip.templ_params.push(param("uin_str2", "10002"));
+        let poke = parse_poke(1, &tip).unwrap();
+        assert_eq!((poke.sender, poke.receiver), (10001, 10002));
+        // An empty verb falls back to the default one
+        assert_eq!(poke.action, "戳了戳");
+
+        tip.templ_params[0] = param("uin_str1", "not a uin");
+        assert_eq!(parse_poke(1, &tip), None);
+    }
+
+    // The pushes below are hand-encoded from the message definitions, not captured from a server
+
+    /// Friend message "hi" from 10001 "u_abc" (friend name "alice") to 20002: type 166, random
+    /// 0x12345678, sequence 4242, time 1700000000
+    const FRIEND_TEXT_PUSH: &str = concat!(
+        "0a390a1708914e1205755f61626328a29c013a073205616c696365121208a60120f8acd19101289221",
+        "3080e2cfaa061a0a0a0812060a040a026869",
+    );
+
+    /// Group message from 10002 "u_def" (card "bob") in 123456 "rustaceans", type 82, sequence
+    /// 777: a custom face, a general-flags element (field 37), then `status = 0`
+    const GROUP_IMAGE_PUSH: &str = concat!(
+        "0a730a2508924e1205755f64656628a29c01421508c0c4072203626f623a0a72757374616365616e73",
+        "120d085220632889063081e2cfaa061a3b0a39122e422c3a05612e706e676a10aaaaaaaaaaaaaaaa",
+        "aaaaaaaaaaaaaaaa8201042f696d67b0018005b801e003c80180101207aa0204080110021800",
+    );
+
+    /// Type 528 sub type 35: "u_req" (10003) asks to be friends, saying "hello", via "QQ search"
+    const FRIEND_REQUEST_PUSH: &str = concat!(
+        "0a450a0e08934e1205755f72657128a29c01120d0890041023280b3082e2cfaa061a2412220a200a05755f",
+        "626f741205755f726571520568656c6c6f5a09515120736561726368",
+    );
+
+    /// Type 84: "u_join" (10004) asks to join 123456 with the comment "let me in", request
+    /// sequence 1700000000123
+    const GROUP_JOIN_REQUEST_PUSH: &str = concat!(
+        "0a350a0b08944e1206755f6a6f696e12040854280c1a20121e08c0c4071a06755f6a6f696e2a096c657420",
+        "6d6520696e30fbd095ffbc31",
+    );
+
+    /// Type 525: "u_inv" invited "u_new" into 123456, request sequence 42
+    const GROUP_INVITED_JOIN_PUSH: &str =
+        "0a2a0a0308954e1205088d04280d1a1c121a085712160a1408c0c4072a05755f6e65773205755f696e76382a";
+
+    /// Type 732 sub type 17: "u_op" recalled sequence 777 of "u_def" in 123456; the body
+    /// starts with the 7 byte binary header
+    const GROUP_RECALL_PUSH: &str = concat!(
+        "0a3c0a0408c0c407120708dc051011280e1a2b12290001e240010022081120c0c4075a1a0a04755f6f70",
+        "1a120889061081e2cfaa0618633205755f646566",
+    );
+
+    /// Type 528 sub type 138: "u_abc" recalled their message 4242
+    const FRIEND_RECALL_PUSH: &str = concat!(
+        "0a390a0a08914e1205755f6162631208089004108a01280f1a21121f0a1d0a05755f6162631205755f62",
+        "6f741892212880e2cfaa0630f8acd19101",
+    );
+
+    /// Type 528 sub type 349: 10001 ("u_abc") started typing, event type 1 with the text
+    /// "正在输入..."
+    const TYPING_PUSH: &str = concat!(
+        "0a340a0a08914e1205755f616263120808900410dd0228101a1c121a0a05755f61626310011a0fe6ada3",
+        "e59ca8e8be93e585a52e2e2e",
+    );
+
+    /// The same friend stopping, event type 2 and no text
+    const TYPING_STOPPED_PUSH: &str =
+        "0a230a0a08914e1205755f616263120808900410dd0228101a0b12090a05755f6162631002";
+
+    /// Gray tip 1061 (type 732 sub type 20) in 123456: 10001 "戳了戳" 10002, with an empty
+    /// `suffix_str` and an `action_img_url` the parser ignores
+    const DEFAULT_POKE_PUSH: &str = concat!(
+        "0ab1010a0408c0c407120708dc05101428101a9f01129c010001e240010095081420c0c407d2018b0108",
+        "0c10a50830bb4e3a170a0a616374696f6e5f7374721209e688b3e4ba86e688b33a110a0875696e5f7374",
+        "7231120531303030313a110a0875696e5f73747232120531303030323a0e0a0a7375666669785f737472",
+        "12003a320a0e616374696f6e5f696d675f75726c122068747470733a2f2f6578616d706c652e696e7661",
+        "6c69642f706f6b652e706e67",
+    );
+
+    /// A customised poke: 10002 "拍了拍" 20002 "的脑袋", the verb in `alt_str1` rather than
+    /// `action_str`
+    const CUSTOM_POKE_PUSH: &str = concat!(
+        "0a81010a0408c0c407120708dc05101428101a70126e0001e240010067081420c0c407d2015e080c10a5",
+        "0830bb4e3a150a08616c745f737472311209e68b8de4ba86e68b8d3a110a0875696e5f73747231120531",
+        "303030323a110a0875696e5f73747232120532303030323a170a0a7375666669785f7374721209e79a84",
+        "e88491e8a28b",
+    );
+
+    /// Type 732 sub type 21: 10009 marked message 777 (random 0x12345678) of 10002 in 123456
+    /// as essence
+    const ESSENCE_SET_PUSH: &str = concat!(
+        "0a3e0a0408c0c407120708dc051015280e1a2d122b0001e240010024081520c0c4078a021b08c0c40710",
+        "890618f8acd19101200128924e30994e3880e2cfaa06",
+    );
+
+    /// Type 732 sub type 16: "u_op" reacted with 128293 to message 777 in 123456, which now
+    /// has 2 of them
+    const REACTION_ADDED_PUSH: &str = concat!(
+        "0a400a0408c0c407120708dc051010280e1a2f122d0001e240010026081020c0c407e2021d0a1b0a1912",
+        "030889061a120a0631323832393318022204755f6f702801",
+    );
+
+    /// Same as [`REACTION_ADDED_PUSH`], with the reaction taken back and 1 left
+    const REACTION_REMOVED_PUSH: &str = concat!(
+        "0a400a0408c0c407120708dc051010280e1a2f122d0001e240010026081020c0c407e2021d0a1b0a1912",
+        "030889061a120a0631323832393318012204755f6f702802",
+    );
+
+    /// Gray tip 1052, template 10093: 10002 became "龙王" in 123456
+    const HONOR_GRAY_TIP_PUSH: &str = concat!(
+        "0a530a0408c0c407120708dc05101428101a4212400001e240010039081420c0c407d20130080c109c08",
+        "30ed4e3a110a0875696e5f73747231120531303030323a130a09686f6e6f725f7374721206e9be99e78e",
+        "8b",
+    );
+
+    /// Gray tip 2407, template 10080: 10002 was granted the title "Rustacean" in 123456
+    const TITLE_GRAY_TIP_PUSH: &str = concat!(
+        "0a560a0408c0c407120708dc05101428101a4512430001e24001003c081420c0c407d20133080c10e712",
+        "30e04e3a110a0875696e5f73747231120531303030323a160a097469746c655f73747212095275737461",
+        "6365616e",
+    );
+
+    /// Gray tip with busi id 1066, which is not parsed
+    const OTHER_GRAY_TIP_PUSH: &str = concat!(
+        "0a3b0a0408c0c407120708dc05101428101a2a12280001e240010021081420c0c407d20118080c10aa08",
+        "3a110a0875696e5f7374723112053130303032",
+    );
+
+    /// Type 33, change type 130: "u_abc" joined 123456, approved by "u_op"
+    const MEMBER_JOINED_PUSH: &str =
+        "0a240a0408c0c4071204082128111a16121408c0c4071a05755f6162632082012a04755f6f70";
+
+    /// Type 33, change type 131: "u_new" joined 123456 on the invitation of "u_def"
+    const MEMBER_INVITED_PUSH: &str =
+        "0a250a0408c0c4071204082128111a17121508c0c4071a05755f6e65772083012a05755f646566";
+
+    /// Type 34, change type 130: "u_abc" left 123456
+    const MEMBER_LEFT_PUSH: &str = "0a1e0a0408c0c4071204082228111a10120e08c0c4071a05755f616263208201";
+
+    /// Type 34, change type 131: "u_op" removed "u_abc" from 123456
+    const MEMBER_KICKED_PUSH: &str =
+        "0a240a0408c0c4071204082228111a16121408c0c4071a05755f6162632083012a04755f6f70";
+
+    /// Type 34, change type 3: "u_op" removed the bot from 123456, the operator wrapped in a
+    /// nested message
+    const BOT_KICKED_PUSH: &str =
+        "0a270a0408c0c4071204082228111a19121708c0c4071a05755f626f7420032a080a060a04755f6f70";
+
+    /// Type 34, change type 130: the bot left 123456
+    const BOT_LEFT_PUSH: &str = "0a1e0a0408c0c4071204082228111a10120e08c0c4071a05755f626f74208201";
+
+    /// Type 732 sub type 12: "u_op" muted "u_abc" in 123456 for 600 seconds
+    const MEMBER_MUTE_PUSH: &str = concat!(
+        "0a350a0408c0c407120708dc05100c28121a24122208c0c407100c2204755f6f702a140880e2cfaa0610",
+        "011a0a0a05755f61626310d804",
+    );
+
+    /// "u_abc" unmuted again, duration 0
+    const MEMBER_UNMUTE_PUSH: &str = concat!(
+        "0a340a0408c0c407120708dc05100c28121a23122108c0c407100c2204755f6f702a130880e2cfaa0610",
+        "011a090a05755f6162631000",
+    );
+
+    /// The whole group muted: no target, duration 0xFFFFFFFF
+    const WHOLE_GROUP_MUTE_PUSH: &str = concat!(
+        "0a310a0408c0c407120708dc05100c28121a20121e08c0c407100c2204755f6f702a100880e2cfaa0610",
+        "011a0610ffffffff0f",
+    );
+
+    /// The whole group unmuted, duration 0
+    const WHOLE_GROUP_UNMUTE_PUSH: &str =
+        "0a2d0a0408c0c407120708dc05100c28121a1c121a08c0c407100c2204755f6f702a0c0880e2cfaa0610011a021000";
+
+    /// Type 528 sub type 39: 10001 renamed themself "Alice Liddell" (profile field 20002)
+    const FRIEND_RENAMED_PUSH: &str = concat!(
+        "0a390a0a08914e1205755f6162631207089004102728131a2212200a1e08001014421808914e121308a2",
+        "9c01120d416c696365204c696464656c6c",
+    );
+
+    /// Type 528 sub type 39: the remark of 10001 set to "Ali"
+    const FRIEND_REMARK_PUSH: &str =
+        "0a2b0a0a08914e1205755f6162631207089004102728131a1412120a10101e4a0c0a0a080010914e1a03416c69";
+
+    /// Type 528 sub type 39: 10001 changed their avatar
+    const FRIEND_AVATAR_PUSH: &str =
+        "0a240a0a08914e1205755f6162631207089004102728131a0d120b0a0910285a05080110914e";
+}
@@ -0,0 +1,176 @@
+use crate::context::BotContext;
+use crate::internal::packets::message::{
+    FriendRecallInfo, FriendRecallRequest, FriendRecallSettings, GroupRecallInfo,
+    GroupRecallRequest, GroupRecallSettings, RecallResponse,
+};
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+define_service! {
+    GroupRecallService {
+        command: "trpc.msg.msg_svc.MsgService.SsoGroupRecallMsg",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupRecallEvent(protocol = Protocols::ALL) {
+                request GroupRecallEventReq {
+                    group_uin: u64,
+                    sequence: u32,
+                }
+                response GroupRecallEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(GroupRecallEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupRecallEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupRecallRequest {
+                recall_type: 1,
+                group_uin: input.group_uin as u32,
+                info: Some(GroupRecallInfo {
+                    sequence: input.sequence,
+                    random: 0,
+                    field3: 0,
+                }),
+                settings: Some(GroupRecallSettings { field1: 0 }),
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+define_service! {
+    FriendRecallService {
+        command: "trpc.msg.msg_svc.MsgService.SsoC2CRecallMsg",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FriendRecallEvent(protocol = Protocols::ALL) {
+                request FriendRecallEventReq {
+                    uid: String,
+                    client_sequence: u32,
+                    sequence: u32,
+                    random: u32,
+                    timestamp: u32,
+                }
+                response FriendRecallEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(FriendRecallEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FriendRecallEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = FriendRecallRequest {
+                recall_type: 1,
+                target_uid: input.uid.clone(),
+                info: Some(FriendRecallInfo {
+                    client_sequence: input.client_sequence,
+                    random: input.random,
+                    message_id: 0x0100_0000u64 << 32 | input.random as u64,
+                    timestamp: input.timestamp,
+                    field5: 0,
+                    message_sequence: input.sequence,
+                }),
+                settings: Some(FriendRecallSettings { field1: false, field2: false }),
+                field6: false,
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+fn check_result(input: &[u8]) -> crate::error::Result<()> {
+    let response = RecallResponse::decode_from_slice(input)?;
+
+    match response.result {
+        Some(code @ 1..) => Err(crate::error::Error::ProtocolError(format!(
+            "Recalling message failed ({}): {}",
+            code,
+            response.err_msg.unwrap_or_default()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+
+    #[tokio::test]
+    async fn test_group_recall_request() {
+        let bytes = GroupRecallService::default()
+            .build(&GroupRecallEventReq { group_uin: 123456, sequence: 777 }, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let request = GroupRecallRequest::decode_from_slice(&bytes).unwrap();
+        assert_eq!(request.recall_type, 1);
+        assert_eq!(request.group_uin, 123456);
+        assert_eq!(request.info.unwrap().sequence, 777);
+    }
+
+    #[tokio::test]
+    async fn test_friend_recall_request() {
+        let event = FriendRecallEventReq {
+            uid: "u_abc".to_string(),
+            client_sequence: 4242,
+            sequence: 4242,
+            random: 0x12345678,
+            timestamp: 1700000000,
+        };
+        let bytes = FriendRecallService::default()
+            .build(&event, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let request = FriendRecallRequest::decode_from_slice(&bytes).unwrap();
+        assert_eq!(request.target_uid, "u_abc");
+        assert_eq!(
+            request.info,
+            Some(FriendRecallInfo {
+                client_sequence: 4242,
+                random: 0x12345678,
+                message_id: 0x0100_0000_1234_5678,
+                timestamp: 1700000000,
+                field5: 0,
+                message_sequence: 4242,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recall_failure() {
+        let response = RecallResponse { result: Some(1001), err_msg: Some("too late".to_string()) };
+        let result = GroupRecallService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await;
+        assert!(matches!(result, Err(crate::Error::ProtocolError(message)) if message.contains("too late")));
+    }
+}
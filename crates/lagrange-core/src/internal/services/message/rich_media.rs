@@ -0,0 +1,147 @@
+use crate::internal::packets::oidb::{
+    build_oidb, parse_oidb, ClientMeta, CommonHead, DownloadRKeyReq, DownloadReq, ExtBizInfo,
+    FileInfo, IndexNode, MsgInfo, MultiMediaReqHead, NtV2RichMediaReq, NtV2RichMediaResp, RKeyInfo,
+    SceneInfo, UploadInfo, UploadReq,
+};
+use bytes::Bytes;
+use std::net::Ipv4Addr;
+
+/// Answer to a rich media upload request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaUploadTicket {
+    /// Key for the highway upload, `None` if the server already has the file
+    pub u_key: Option<String>,
+    /// `host:port` of the servers offered for the upload
+    pub servers: Vec<String>,
+    pub msg_info: MsgInfo,
+}
+
+/// `CommonHead::command` and OIDB sub command of uploads
+const UPLOAD: u32 = 100;
+/// `CommonHead::command` and OIDB sub command of downloads
+const DOWNLOAD: u32 = 200;
+/// `CommonHead::command` and OIDB sub command of rkey requests
+const DOWNLOAD_RKEY: u32 = 202;
+
+/// Wrap an NT rich media upload request for `file_info` into the OIDB envelope of `command`
+pub(crate) fn build_media_upload(command: u32, scene: SceneInfo, file_info: FileInfo, ext_biz_info: ExtBizInfo) -> crate::error::Result<Bytes> {
+    let scene_type = scene.scene_type;
+    let request = NtV2RichMediaReq {
+        req_head: Some(request_head(UPLOAD, scene)),
+        upload: Some(UploadReq {
+            upload_info: vec![UploadInfo {
+                file_info: Some(file_info),
+                sub_file_type: 0,
+            }],
+            try_fast_upload_completed: true,
+            srv_send_msg: false,
+            client_random_id: rand::random(),
+            compat_q_msg_scene_type: scene_type,
+            ext_biz_info: Some(ext_biz_info),
+            client_seq: 0,
+            no_need_compat_msg: false,
+        }),
+        ..Default::default()
+    };
+    wrap(command, UPLOAD, &request)
+}
+
+/// Unwrap the answer to [`build_media_upload`]
+pub(crate) fn parse_media_upload(input: &[u8]) -> crate::error::Result<MediaUploadTicket> {
+    let upload = unwrap(input, "upload")?
+        .upload
+        .ok_or_else(|| crate::error::Error::ProtocolError("Upload response is empty".to_string()))?;
+
+    Ok(MediaUploadTicket {
+        u_key: upload.u_key.filter(|u_key| !u_key.is_empty()),
+        servers: upload
+            .ipv4s
+            .iter()
+            .map(|ip| format!("{}:{}", Ipv4Addr::from(ip.out_ip.to_le_bytes()), ip.out_port))
+            .collect(),
+        msg_info: upload
+            .msg_info
+            .ok_or_else(|| crate::error::Error::ProtocolError("Upload response has no msg info".to_string()))?,
+    })
+}
+
+/// Wrap an NT rich media download request for `node` into the OIDB envelope of `command`
+pub(crate) fn build_media_download(command: u32, scene: SceneInfo, node: IndexNode) -> crate::error::Result<Bytes> {
+    let request = NtV2RichMediaReq {
+        req_head: Some(request_head(DOWNLOAD, scene)),
+        download: Some(DownloadReq {
+            node: Some(node),
+            download: None,
+        }),
+        ..Default::default()
+    };
+    wrap(command, DOWNLOAD, &request)
+}
+
+/// Unwrap the answer to [`build_media_download`] into the full download url
+pub(crate) fn parse_media_download(input: &[u8]) -> crate::error::Result<String> {
+    let download = unwrap(input, "download")?
+        .download
+        .ok_or_else(|| crate::error::Error::ProtocolError("Download response is empty".to_string()))?;
+    let info = download.info.unwrap_or_default();
+    let domain = info
+        .domain
+        .filter(|domain| !domain.is_empty())
+        .ok_or_else(|| crate::error::Error::ProtocolError("Download response has no domain".to_string()))?;
+
+    Ok(format!(
+        "https://{}{}{}",
+        domain,
+        info.url_path.unwrap_or_default(),
+        download.r_key_param.unwrap_or_default()
+    ))
+}
+
+/// Wrap a request for the rkeys of `types` into the OIDB envelope of `command`
+pub(crate) fn build_rkey_fetch(command: u32, types: Vec<u32>) -> crate::error::Result<Bytes> {
+    let scene = SceneInfo {
+        request_type: 2,
+        business_type: 1,
+        scene_type: 0,
+        ..Default::default()
+    };
+    let request = NtV2RichMediaReq {
+        req_head: Some(request_head(DOWNLOAD_RKEY, scene)),
+        download_rkey: Some(DownloadRKeyReq { types }),
+        ..Default::default()
+    };
+    wrap(command, DOWNLOAD_RKEY, &request)
+}
+
+/// Unwrap the answer to [`build_rkey_fetch`]
+pub(crate) fn parse_rkey_fetch(input: &[u8]) -> crate::error::Result<Vec<RKeyInfo>> {
+    Ok(unwrap(input, "rkeys")?
+        .download_rkey
+        .map(|download_rkey| download_rkey.rkeys)
+        .unwrap_or_default())
+}
+
+fn request_head(command: u32, scene: SceneInfo) -> MultiMediaReqHead {
+    MultiMediaReqHead {
+        common: Some(CommonHead { request_id: 1, command }),
+        scene: Some(scene),
+        client: Some(ClientMeta { agent_type: 2 }),
+    }
+}
+
+fn wrap(command: u32, sub_command: u32, request: &NtV2RichMediaReq) -> crate::error::Result<Bytes> {
+    build_oidb(command, sub_command, request, true)
+}
+
+fn unwrap(input: &[u8], action: &str) -> crate::error::Result<NtV2RichMediaResp> {
+    let response: NtV2RichMediaResp = parse_oidb(input)?;
+    if let Some(code @ 1..) = response.resp_head.as_ref().and_then(|head| head.ret_code) {
+        return Err(crate::error::Error::ProtocolError(format!(
+            "Requesting the {} failed ({}): {}",
+            action,
+            code,
+            response.resp_head.and_then(|head| head.message).unwrap_or_default()
+        )));
+    }
+    Ok(response)
+}
@@ -0,0 +1,263 @@
+use crate::context::BotContext;
+use crate::internal::packets::message::{
+    C2c, ContentHead, Grp, MessageBody, MessageControl, PbSendMsg, PbSendMsgResp, RichText,
+    RoutingHead,
+};
+use crate::message::MessageChain;
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+/// Recipient of a `MessageSvc.PbSendMsg` request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendTarget {
+    Friend { uin: u64, uid: String },
+    Group { group_uin: u64 },
+}
+
+define_service! {
+    SendMessageService {
+        command: "MessageSvc.PbSendMsg",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SendMessageEvent(protocol = Protocols::ALL) {
+                request SendMessageEventReq {
+                    target: SendTarget,
+                    chain: MessageChain,
+                    client_sequence: u32,
+                    random: u32,
+                }
+                response SendMessageEventResp {
+                    result: i32,
+                    error_message: String,
+                    sequence: u32,
+                    timestamp: u32,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = PbSendMsgResp::decode_from_slice(&input)?;
+
+            Ok(EventMessage::new(SendMessageEventResp {
+                result: response.result.unwrap_or_default() as i32,
+                error_message: response.err_msg.unwrap_or_default(),
+                // Only one of them is set, depending on the routing of the request
+                sequence: response
+                    .group_sequence
+                    .or(response.private_sequence)
+                    .unwrap_or_default(),
+                timestamp: response.send_time.unwrap_or_default(),
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<SendMessageEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let msg_flag = chrono::Utc::now().timestamp() as u32;
+            let data = build_send_message(input, msg_flag)
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+fn build_send_message(input: &SendMessageEventReq, msg_flag: u32) -> PbSendMsg {
+    let (routing_head, client_sequence) = match &input.target {
+        SendTarget::Friend { uin, uid } => (
+            RoutingHead {
+                c2c: Some(C2c {
+                    uin: Some(*uin as u32),
+                    uid: Some(uid.clone()),
+                }),
+                ..Default::default()
+            },
+            Some(input.client_sequence),
+        ),
+        // Group messages are only identified by the random, the server assigns the sequence
+        SendTarget::Group { group_uin } => (
+            RoutingHead {
+                grp: Some(Grp {
+                    group_code: Some(*group_uin as u32),
+                }),
+                ..Default::default()
+            },
+            None,
+        ),
+    };
+
+    PbSendMsg {
+        routing_head: Some(routing_head),
+        content_head: Some(ContentHead {
+            pkg_num: Some(1),
+            pkg_index: Some(0),
+            div_seq: Some(0),
+        }),
+        message_body: Some(MessageBody {
+            rich_text: Some(RichText {
+                elems: input.chain.to_elems(),
+            }),
+            ..Default::default()
+        }),
+        client_sequence,
+        random: Some(input.random),
+        ctrl: Some(MessageControl {
+            msg_flag: Some(msg_flag),
+        }),
+        ..Default::default()
+    }
+}
+
+impl SendMessageEventResp {
+    pub fn is_success(&self) -> bool {
+        self.result == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::SendMessageError;
+    use crate::protocol::TypedService;
+
+    fn hex(data: &[u8]) -> String {
+        data.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_friend_message_body() {
+        let request = SendMessageEventReq {
+            target: SendTarget::Friend {
+                uin: 10001,
+                uid: "u_abc".to_string(),
+            },
+            chain: MessageChain::text("hi"),
+            client_sequence: 100,
+            random: 0x12345678,
+        };
+
+        let encoded = build_send_message(&request, 1700000000).encode_to_vec().unwrap();
+
+        // A minimal C2C text message
+        let expected = concat!(
+            "0a0c",           // routing_head
+            "0a0a",           //   c2c
+            "08914e",         //     uin = 10001
+            "1205755f616263", //     uid = "u_abc"
+            "1206080110001800", // content_head: pkg_num = 1, pkg_index = 0, div_seq = 0
+            "1a0a",           // message_body
+            "0a08",           //   rich_text
+            "1206",           //     elems
+            "0a04",           //       text
+            "0a026869",       //         str = "hi"
+            "2064",           // client_sequence = 100
+            "28f8acd19101",   // random
+            "62060880e2cfaa06", // ctrl: msg_flag = 1700000000
+        );
+        assert_eq!(hex(&encoded), expected);
+    }
+
+    #[test]
+    fn test_group_message_body() {
+        let request = SendMessageEventReq {
+            target: SendTarget::Group { group_uin: 123456 },
+            chain: MessageChain::text("hi"),
+            client_sequence: 100,
+            random: 0x12345678,
+        };
+
+        let encoded = build_send_message(&request, 1700000000).encode_to_vec().unwrap();
+
+        let expected = concat!(
+            "0a06",             // routing_head
+            "1204",             //   grp
+            "08c0c407",         //     group_code = 123456
+            "1206080110001800", // content_head: pkg_num = 1, pkg_index = 0, div_seq = 0
+            "1a0a",             // message_body
+            "0a08",             //   rich_text
+            "1206",             //     elems
+            "0a04",             //       text
+            "0a026869",         //         str = "hi"
+            "28f8acd19101",     // random, no client_sequence for groups
+            "62060880e2cfaa06", // ctrl: msg_flag = 1700000000
+        );
+        assert_eq!(hex(&encoded), expected);
+    }
+
+    #[tokio::test]
+    async fn test_parse_response() {
+        let context = BotContext::builder().build();
+        let response = PbSendMsgResp {
+            result: Some(0),
+            send_time: Some(1700000001),
+            private_sequence: Some(4242),
+            ..Default::default()
+        };
+
+        let parsed = SendMessageService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), context)
+            .await
+            .unwrap();
+
+        assert!(parsed.is_success());
+        assert_eq!(parsed.sequence, 4242);
+        assert_eq!(parsed.timestamp, 1700000001);
+    }
+
+    #[tokio::test]
+    async fn test_parse_group_response() {
+        let context = BotContext::builder().build();
+        let response = PbSendMsgResp {
+            result: Some(0),
+            send_time: Some(1700000002),
+            group_sequence: Some(777),
+            ..Default::default()
+        };
+
+        let parsed = SendMessageService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), context)
+            .await
+            .unwrap();
+
+        assert_eq!(parsed.sequence, 777);
+    }
+
+    #[tokio::test]
+    async fn test_risk_control_result() {
+        let context = BotContext::builder().build();
+        let response = PbSendMsgResp {
+            result: Some(46),
+            err_msg: Some("blocked".to_string()),
+            ..Default::default()
+        };
+
+        let parsed = SendMessageService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), context)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            SendMessageError::from_result(parsed.result, &parsed.error_message),
+            Some(SendMessageError::RiskControl {
+                code: 46,
+                message: "blocked".to_string(),
+            })
+        );
+        assert_eq!(
+            SendMessageError::from_result(10, "not a friend"),
+            Some(SendMessageError::Rejected {
+                code: 10,
+                message: "not a friend".to_string(),
+            })
+        );
+        assert_eq!(SendMessageError::from_result(0, ""), None);
+    }
+}
@@ -0,0 +1,258 @@
+use super::rich_media::{build_media_upload, parse_media_upload, MediaUploadTicket};
+use crate::context::BotContext;
+use crate::internal::packets::oidb::{
+    C2cUserInfo, ExtBizInfo, FileInfo, FileType, NtGroupInfo, PicExtBizInfo, SceneInfo,
+};
+use crate::utils::common::to_hex;
+use crate::utils::image::ImageInfo;
+use crate::utils::upload::FileDigest;
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+/// Summary shown for images in notifications and the chat list
+const IMAGE_SUMMARY: &str = "[图片]";
+
+/// Hashes and header of an image about to be uploaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageUpload {
+    pub md5: [u8; 16],
+    pub sha1: [u8; 20],
+    pub size: u32,
+    pub info: ImageInfo,
+}
+
+impl ImageUpload {
+    pub fn new(data: &[u8], info: ImageInfo) -> Self {
+        Self::from_digest(&FileDigest::of(data), info)
+    }
+
+    /// Upload of a file hashed while it was read, see [`UploadSource`](crate::utils::UploadSource).
+    /// The caller checks that its size fits the request.
+    pub fn from_digest(digest: &FileDigest, info: ImageInfo) -> Self {
+        Self { md5: digest.md5, sha1: digest.sha1, size: digest.size as u32, info }
+    }
+
+    /// Name the server stores the image under
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", to_hex(&self.md5), self.info.format.extension())
+    }
+}
+
+define_service! {
+    GroupImageUploadService {
+        command: "OidbSvcTrpcTcp.0x11c4_100",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupImageUploadEvent(protocol = Protocols::ALL) {
+                request GroupImageUploadEventReq {
+                    group_uin: u64,
+                    image: ImageUpload,
+                }
+                response GroupImageUploadEventResp {
+                    ticket: MediaUploadTicket,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(GroupImageUploadEventResp {
+                ticket: parse_media_upload(&input)?,
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupImageUploadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let scene = SceneInfo {
+                request_type: 2,
+                business_type: 1,
+                scene_type: 2,
+                group: Some(NtGroupInfo { group_uin: input.group_uin as u32 }),
+                ..Default::default()
+            };
+            build_media_upload(0x11c4, scene, image_file_info(&input.image), image_ext_biz_info())
+        }
+    }
+}
+
+define_service! {
+    FriendImageUploadService {
+        command: "OidbSvcTrpcTcp.0x11c5_100",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FriendImageUploadEvent(protocol = Protocols::ALL) {
+                request FriendImageUploadEventReq {
+                    uid: String,
+                    image: ImageUpload,
+                }
+                response FriendImageUploadEventResp {
+                    ticket: MediaUploadTicket,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(FriendImageUploadEventResp {
+                ticket: parse_media_upload(&input)?,
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FriendImageUploadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let scene = SceneInfo {
+                request_type: 2,
+                business_type: 1,
+                scene_type: 1,
+                c2c: Some(C2cUserInfo { account_type: 2, target_uid: input.uid.clone() }),
+                ..Default::default()
+            };
+            build_media_upload(0x11c5, scene, image_file_info(&input.image), image_ext_biz_info())
+        }
+    }
+}
+
+fn image_file_info(image: &ImageUpload) -> FileInfo {
+    FileInfo {
+        file_size: image.size,
+        file_hash: to_hex(&image.md5),
+        file_sha1: to_hex(&image.sha1),
+        file_name: image.file_name(),
+        file_type: Some(FileType {
+            file_type: 1,
+            pic_format: image.info.format.pic_format(),
+            ..Default::default()
+        }),
+        width: image.info.width,
+        height: image.info.height,
+        time: 0,
+        original: 1,
+    }
+}
+
+fn image_ext_biz_info() -> ExtBizInfo {
+    ExtBizInfo {
+        pic: Some(PicExtBizInfo {
+            text_summary: IMAGE_SUMMARY.to_string(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::rich_media::{MediaIpv4, UploadResp};
+    use crate::internal::packets::oidb::{
+        IndexNode, MsgInfo, MsgInfoBody, NtV2RichMediaReq, NtV2RichMediaResp, OidbSvcTrpcTcpBase,
+    };
+    use lagrange_proto::ProtoMessage;
+    use crate::protocol::TypedService;
+    use crate::utils::image::ImageFormat;
+
+    fn image() -> ImageUpload {
+        ImageUpload::new(
+            b"not really a png",
+            ImageInfo { format: ImageFormat::Png, width: 64, height: 32 },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_build_group_request() {
+        let request = GroupImageUploadEventReq { group_uin: 123456, image: image() };
+        let bytes = GroupImageUploadService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x11c4, 100));
+        let request = NtV2RichMediaReq::decode_from_slice(&oidb.body.unwrap()).unwrap();
+
+        let scene = request.req_head.unwrap().scene.unwrap();
+        assert_eq!(scene.scene_type, 2);
+        assert_eq!(scene.group, Some(NtGroupInfo { group_uin: 123456 }));
+
+        let upload = request.upload.unwrap();
+        assert_eq!(upload.compat_q_msg_scene_type, 2);
+        let file = upload.upload_info[0].file_info.clone().unwrap();
+        assert_eq!(file.file_hash, to_hex(&md5::compute(b"not really a png").0));
+        assert_eq!(file.file_sha1.len(), 40);
+        assert_eq!(file.file_name, format!("{}.png", file.file_hash));
+        assert_eq!(file.file_type.unwrap().pic_format, 1001);
+        assert_eq!((file.width, file.height, file.file_size), (64, 32, 16));
+    }
+
+    #[tokio::test]
+    async fn test_build_friend_request() {
+        let request = FriendImageUploadEventReq { uid: "u_friend".to_string(), image: image() };
+        let bytes = FriendImageUploadService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x11c5, 100));
+        let scene = NtV2RichMediaReq::decode_from_slice(&oidb.body.unwrap())
+            .unwrap()
+            .req_head
+            .unwrap()
+            .scene
+            .unwrap();
+        assert_eq!(scene.scene_type, 1);
+        assert_eq!(scene.c2c.unwrap().target_uid, "u_friend");
+    }
+
+    #[tokio::test]
+    async fn test_parse_ticket() {
+        let msg_info = MsgInfo {
+            msg_info_body: vec![MsgInfoBody {
+                index: Some(IndexNode { file_uuid: "uuid".to_string(), ..Default::default() }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let response = NtV2RichMediaResp {
+            upload: Some(UploadResp {
+                u_key: Some("ukey".to_string()),
+                ipv4s: vec![MediaIpv4 {
+                    out_ip: u32::from_le_bytes([10, 0, 0, 1]),
+                    out_port: 80,
+                    ..Default::default()
+                }],
+                msg_info: Some(msg_info.clone()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x11c4,
+            sub_command: 100,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+
+        let parsed = GroupImageUploadService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!(
+            parsed.ticket,
+            MediaUploadTicket {
+                u_key: Some("ukey".to_string()),
+                servers: vec!["10.0.0.1:80".to_string()],
+                msg_info,
+            }
+        );
+    }
+}
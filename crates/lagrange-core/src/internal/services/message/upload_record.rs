@@ -0,0 +1,324 @@
+use super::rich_media::{
+    build_media_download, build_media_upload, parse_media_download, parse_media_upload,
+    MediaUploadTicket,
+};
+use crate::context::BotContext;
+use crate::internal::packets::oidb::{
+    C2cUserInfo, ExtBizInfo, FileInfo, FileType, IndexNode, NtGroupInfo, PttExtBizInfo, SceneInfo,
+};
+use crate::utils::audio::AudioInfo;
+use crate::utils::common::to_hex;
+use crate::utils::upload::FileDigest;
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use std::sync::Arc;
+
+use crate::protocol::{EncryptType, EventMessage, Protocols, RequestType};
+
+/// Hashes and header of a voice clip about to be uploaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordUpload {
+    pub md5: [u8; 16],
+    pub sha1: [u8; 20],
+    pub size: u32,
+    pub info: AudioInfo,
+}
+
+impl RecordUpload {
+    pub fn new(data: &[u8], info: AudioInfo) -> Self {
+        Self::from_digest(&FileDigest::of(data), info)
+    }
+
+    /// Upload of a file hashed while it was read, see [`UploadSource`](crate::utils::UploadSource).
+    /// The caller checks that its size fits the request.
+    pub fn from_digest(digest: &FileDigest, info: AudioInfo) -> Self {
+        Self { md5: digest.md5, sha1: digest.sha1, size: digest.size as u32, info }
+    }
+
+    /// Name the server stores the clip under
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", to_hex(&self.md5), self.info.format.extension())
+    }
+}
+
+define_service! {
+    GroupRecordUploadService {
+        command: "OidbSvcTrpcTcp.0x126e_100",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupRecordUploadEvent(protocol = Protocols::ALL) {
+                request GroupRecordUploadEventReq {
+                    group_uin: u64,
+                    record: RecordUpload,
+                }
+                response GroupRecordUploadEventResp {
+                    ticket: MediaUploadTicket,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(GroupRecordUploadEventResp {
+                ticket: parse_media_upload(&input)?,
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupRecordUploadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            // Flags the official client sends along with group records
+            let ext_biz_info = ExtBizInfo {
+                ptt: Some(PttExtBizInfo {
+                    bytes_reserve: Some(vec![0x08, 0x00, 0x38, 0x00]),
+                    bytes_general_flags: Some(vec![0x9a, 0x01, 0x07, 0xaa, 0x03, 0x04, 0x08, 0x08, 0x12, 0x00]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            build_media_upload(0x126e, group_scene(input.group_uin), record_file_info(&input.record), ext_biz_info)
+        }
+    }
+}
+
+define_service! {
+    FriendRecordUploadService {
+        command: "OidbSvcTrpcTcp.0x126d_100",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FriendRecordUploadEvent(protocol = Protocols::ALL) {
+                request FriendRecordUploadEventReq {
+                    uid: String,
+                    record: RecordUpload,
+                }
+                response FriendRecordUploadEventResp {
+                    ticket: MediaUploadTicket,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(FriendRecordUploadEventResp {
+                ticket: parse_media_upload(&input)?,
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FriendRecordUploadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            // Flags the official client sends along with private records
+            let ext_biz_info = ExtBizInfo {
+                ptt: Some(PttExtBizInfo {
+                    bytes_reserve: Some(vec![0x08, 0x00, 0x38, 0x00]),
+                    bytes_general_flags: Some(vec![
+                        0x9a, 0x01, 0x0b, 0xaa, 0x03, 0x08, 0x08, 0x04, 0x12, 0x04, 0x00, 0x00, 0x00, 0x00,
+                    ]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            build_media_upload(0x126d, friend_scene(&input.uid), record_file_info(&input.record), ext_biz_info)
+        }
+    }
+}
+
+define_service! {
+    GroupRecordDownloadService {
+        command: "OidbSvcTrpcTcp.0x126e_200",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupRecordDownloadEvent(protocol = Protocols::ALL) {
+                request GroupRecordDownloadEventReq {
+                    group_uin: u64,
+                    node: IndexNode,
+                }
+                response GroupRecordDownloadEventResp {
+                    url: String,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(GroupRecordDownloadEventResp {
+                url: parse_media_download(&input)?,
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupRecordDownloadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            build_media_download(0x126e, group_scene(input.group_uin), input.node.clone())
+        }
+    }
+}
+
+define_service! {
+    FriendRecordDownloadService {
+        command: "OidbSvcTrpcTcp.0x126d_200",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FriendRecordDownloadEvent(protocol = Protocols::ALL) {
+                request FriendRecordDownloadEventReq {
+                    uid: String,
+                    node: IndexNode,
+                }
+                response FriendRecordDownloadEventResp {
+                    url: String,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            Ok(EventMessage::new(FriendRecordDownloadEventResp {
+                url: parse_media_download(&input)?,
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FriendRecordDownloadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            build_media_download(0x126d, friend_scene(&input.uid), input.node.clone())
+        }
+    }
+}
+
+fn group_scene(group_uin: u64) -> SceneInfo {
+    SceneInfo {
+        request_type: 1,
+        business_type: 3,
+        scene_type: 2,
+        group: Some(NtGroupInfo { group_uin: group_uin as u32 }),
+        ..Default::default()
+    }
+}
+
+fn friend_scene(uid: &str) -> SceneInfo {
+    SceneInfo {
+        request_type: 1,
+        business_type: 3,
+        scene_type: 1,
+        c2c: Some(C2cUserInfo { account_type: 2, target_uid: uid.to_string() }),
+        ..Default::default()
+    }
+}
+
+fn record_file_info(record: &RecordUpload) -> FileInfo {
+    FileInfo {
+        file_size: record.size,
+        file_hash: to_hex(&record.md5),
+        file_sha1: to_hex(&record.sha1),
+        file_name: record.file_name(),
+        file_type: Some(FileType {
+            file_type: 3,
+            voice_format: record.info.format.voice_format(),
+            ..Default::default()
+        }),
+        time: record.info.seconds(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::rich_media::{DownloadInfo, DownloadResp};
+    use crate::internal::packets::oidb::{NtV2RichMediaReq, NtV2RichMediaResp, OidbSvcTrpcTcpBase};
+    use crate::protocol::TypedService;
+    use crate::utils::audio::{self, AudioFormat};
+    use lagrange_proto::ProtoMessage;
+    use std::time::Duration;
+
+    const SILK_FIXTURE: &[u8] = include_bytes!("../../../../tests/fixtures/voice.silk");
+
+    async fn unwrap_request(bytes: Bytes, command: u32, sub_command: u32) -> NtV2RichMediaReq {
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (command, sub_command));
+        NtV2RichMediaReq::decode_from_slice(&oidb.body.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_build_group_upload() {
+        let record = RecordUpload::new(SILK_FIXTURE, audio::probe(SILK_FIXTURE).unwrap());
+        let request = GroupRecordUploadEventReq { group_uin: 123456, record };
+        let bytes = GroupRecordUploadService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let request = unwrap_request(bytes, 0x126e, 100).await;
+        let scene = request.req_head.unwrap().scene.unwrap();
+        assert_eq!((scene.request_type, scene.business_type, scene.scene_type), (1, 3, 2));
+
+        let upload = request.upload.unwrap();
+        let file = upload.upload_info[0].file_info.clone().unwrap();
+        assert_eq!(file.file_hash, to_hex(&md5::compute(SILK_FIXTURE).0));
+        assert_eq!(file.file_name, format!("{}.silk", file.file_hash));
+        assert_eq!(file.file_size, SILK_FIXTURE.len() as u32);
+        assert_eq!(file.time, 3);
+        assert_eq!(file.file_type.unwrap(), FileType { file_type: 3, voice_format: 1, ..Default::default() });
+        assert!(upload.ext_biz_info.unwrap().ptt.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_build_friend_download() {
+        let node = IndexNode { file_uuid: "uuid".to_string(), ..Default::default() };
+        let request = FriendRecordDownloadEventReq { uid: "u_friend".to_string(), node: node.clone() };
+        let bytes = FriendRecordDownloadService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let request = unwrap_request(bytes, 0x126d, 200).await;
+        let head = request.req_head.unwrap();
+        assert_eq!(head.common.unwrap().command, 200);
+        assert_eq!(head.scene.unwrap().c2c.unwrap().target_uid, "u_friend");
+        assert_eq!(request.download.unwrap().node, Some(node));
+    }
+
+    #[tokio::test]
+    async fn test_parse_download_url() {
+        let response = NtV2RichMediaResp {
+            download: Some(DownloadResp {
+                r_key_param: Some("&rkey=CAE".to_string()),
+                info: Some(DownloadInfo {
+                    domain: Some("grouptalk.c2c.qq.com".to_string()),
+                    url_path: Some("/download?appid=1403&fileid=uuid".to_string()),
+                    https_port: 443,
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x126e,
+            sub_command: 200,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+
+        let parsed = GroupRecordDownloadService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!(parsed.url, "https://grouptalk.c2c.qq.com/download?appid=1403&fileid=uuid&rkey=CAE");
+    }
+
+    #[test]
+    fn test_amr_file_info() {
+        let info = AudioInfo { format: AudioFormat::Amr, duration: Duration::from_millis(200) };
+        let file = record_file_info(&RecordUpload::new(b"#!AMR\n", info));
+        assert_eq!(file.file_name.rsplit_once('.').unwrap().1, "amr");
+        assert_eq!((file.file_type.unwrap().voice_format, file.time), (0, 1));
+    }
+}
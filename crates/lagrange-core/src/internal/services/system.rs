@@ -1,3 +1,69 @@
+pub mod at_all;
+pub mod essence;
+pub mod fetch_cookies;
+pub mod fetch_friends;
+pub mod fetch_group_extra;
+pub mod fetch_group_requests;
+pub mod fetch_groups;
+pub mod fetch_members;
+pub mod fetch_stranger;
+pub mod fetch_user_info;
+pub mod group_admin;
+pub mod group_file;
 pub mod heartbeat;
+pub mod highway_session;
+pub mod poke;
+pub mod reaction;
+pub mod request_action;
 
+pub use at_all::{FetchAtAllRemainEventReq, FetchAtAllRemainEventResp, FetchAtAllRemainService};
+pub use essence::{
+    RemoveEssenceEventReq, RemoveEssenceEventResp, RemoveEssenceService, SetEssenceEventReq,
+    SetEssenceEventResp, SetEssenceService,
+};
+pub use fetch_cookies::{
+    FetchClientKeyEventReq, FetchClientKeyEventResp, FetchClientKeyService, FetchPsKeysEventReq,
+    FetchPsKeysEventResp, FetchPsKeysService,
+};
+pub use fetch_friends::{FetchFriendsEventReq, FetchFriendsEventResp, FetchFriendsService};
+pub use fetch_group_extra::{
+    FetchGroupExtraEventReq, FetchGroupExtraEventResp, FetchGroupExtraService, GroupExtra,
+};
+pub use fetch_group_requests::{
+    FetchGroupRequestsEventReq, FetchGroupRequestsEventResp, FetchGroupRequestsService,
+};
+pub use fetch_groups::{FetchGroupsEventReq, FetchGroupsEventResp, FetchGroupsService};
+pub use fetch_members::{FetchMembersEventReq, FetchMembersEventResp, FetchMembersService};
+pub use fetch_stranger::{FetchStrangerEventReq, FetchStrangerEventResp, FetchStrangerService};
+pub use fetch_user_info::{FetchUserInfoEventReq, FetchUserInfoEventResp, FetchUserInfoService};
+pub use group_admin::{
+    KickMemberEventReq, KickMemberEventResp, KickMemberService, MuteAllEventReq, MuteAllEventResp,
+    MuteAllService, MuteMemberEventReq, MuteMemberEventResp, MuteMemberService, SetAdminEventReq,
+    SetAdminEventResp, SetAdminService, SetGroupNameEventReq, SetGroupNameEventResp,
+    SetGroupNameService, SetMemberCardEventReq, SetMemberCardEventResp, SetMemberCardService,
+};
+pub use group_file::{
+    GroupFileDeleteEventReq, GroupFileDeleteEventResp, GroupFileDeleteService,
+    GroupFileDownloadEventReq, GroupFileDownloadEventResp, GroupFileDownloadService,
+    GroupFileFeedEventReq, GroupFileFeedEventResp, GroupFileFeedService, GroupFileListEventReq,
+    GroupFileListEventResp, GroupFileListService, GroupFileMoveEventReq, GroupFileMoveEventResp,
+    GroupFileMoveService, GroupFileRenameEventReq, GroupFileRenameEventResp, GroupFileRenameService,
+    GroupFileSlot, GroupFileUpload, GroupFileUploadEventReq, GroupFileUploadEventResp,
+    GroupFileUploadService, GroupFolderCreateEventReq, GroupFolderCreateEventResp,
+    GroupFolderCreateService, GroupFolderDeleteEventReq, GroupFolderDeleteEventResp,
+    GroupFolderDeleteService, GroupFolderRenameEventReq, GroupFolderRenameEventResp,
+    GroupFolderRenameService,
+};
 pub use heartbeat::{AliveEventReq, AliveEventResp, AliveService};
+pub use highway_session::{
+    FetchHighwaySessionEventReq, FetchHighwaySessionEventResp, FetchHighwaySessionService,
+};
+pub use poke::{SendPokeEventReq, SendPokeEventResp, SendPokeService};
+pub use reaction::{
+    AddReactionEventReq, AddReactionEventResp, AddReactionService, RemoveReactionEventReq, RemoveReactionEventResp,
+    RemoveReactionService,
+};
+pub use request_action::{
+    FriendRequestActionEventReq, FriendRequestActionEventResp, FriendRequestActionService,
+    GroupRequestActionEventReq, GroupRequestActionEventResp, GroupRequestActionService,
+};
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+
+use crate::{
+    common::AtAllRemain,
+    context::BotContext,
+    internal::packets::oidb::{build_oidb, parse_oidb, AtAllRemainRequest, AtAllRemainResponse},
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    FetchAtAllRemainService {
+        command: "OidbSvcTrpcTcp.0x8a7_0",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchAtAllRemainEvent(protocol = Protocols::ALL) {
+                request FetchAtAllRemainEventReq {
+                    group_uin: u64,
+                    uin: u64,
+                }
+                response FetchAtAllRemainEventResp {
+                    remain: AtAllRemain,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response: AtAllRemainResponse = parse_oidb(&input)?;
+            Ok(EventMessage::new(FetchAtAllRemainEventResp {
+                remain: AtAllRemain {
+                    can_at_all: response.can_at_all,
+                    remain_for_group: response.remain_for_group,
+                    remain_for_self: response.remain_for_uin,
+                },
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchAtAllRemainEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = AtAllRemainRequest {
+                sub_command: 1,
+                limit_interval_type_for_uin: 2,
+                limit_interval_type_for_group: 1,
+                uin: input.uin,
+                group_uin: input.group_uin,
+            };
+            build_oidb(0x8a7, 0, &request, false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::OidbSvcTrpcTcpBase;
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    #[tokio::test]
+    async fn test_build_request() {
+        let request = FetchAtAllRemainEventReq { group_uin: 123456, uin: 10001 };
+        let bytes = FetchAtAllRemainService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x8a7, 0));
+        assert_eq!(
+            AtAllRemainRequest::decode_from_slice(&oidb.body.unwrap()).unwrap(),
+            AtAllRemainRequest {
+                sub_command: 1,
+                limit_interval_type_for_uin: 2,
+                limit_interval_type_for_group: 1,
+                uin: 10001,
+                group_uin: 123456,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_remain() {
+        // 10 @all left for the bot, 19 for the group
+        let body = [
+            0x08, 0x01, // can_at_all
+            0x10, 0x0a, // remain_for_uin 10
+            0x18, 0x13, // remain_for_group 19
+            0x22, 0x02, 0x6f, 0x6b, // prompt_msg1 "ok"
+        ];
+        let response = OidbSvcTrpcTcpBase {
+            command: 0x8a7,
+            sub_command: 0,
+            error_code: Some(0),
+            body: Some(body.to_vec()),
+            ..Default::default()
+        };
+        let parsed = FetchAtAllRemainService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!(parsed.remain, AtAllRemain { can_at_all: true, remain_for_group: 19, remain_for_self: 10 });
+
+        // A group without quota left leaves the counts out
+        let response = OidbSvcTrpcTcpBase { body: Some(Vec::new()), ..Default::default() };
+        let parsed = FetchAtAllRemainService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!(parsed.remain, AtAllRemain::default());
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+
+use crate::{
+    context::BotContext,
+    error::EssenceError,
+    internal::packets::oidb::{build_oidb, unwrap_oidb, EssenceRequest},
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    SetEssenceService {
+        command: "OidbSvcTrpcTcp.0xeac_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SetEssenceEvent(protocol = Protocols::ALL) {
+                request SetEssenceEventReq {
+                    group_uin: u64,
+                    sequence: u32,
+                    random: u32,
+                }
+                response SetEssenceEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(SetEssenceEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<SetEssenceEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            wrap_request(1, input.group_uin, input.sequence, input.random)
+        }
+    }
+}
+
+define_service! {
+    RemoveEssenceService {
+        command: "OidbSvcTrpcTcp.0xeac_2",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            RemoveEssenceEvent(protocol = Protocols::ALL) {
+                request RemoveEssenceEventReq {
+                    group_uin: u64,
+                    sequence: u32,
+                    random: u32,
+                }
+                response RemoveEssenceEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(RemoveEssenceEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<RemoveEssenceEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            wrap_request(2, input.group_uin, input.sequence, input.random)
+        }
+    }
+}
+
+/// Wrap an essence request in the OIDB envelope
+fn wrap_request(sub_command: u32, group_uin: u64, sequence: u32, random: u32) -> crate::error::Result<Bytes> {
+    let request = EssenceRequest { group_uin: group_uin as u32, sequence, random };
+    build_oidb(0xeac, sub_command, &request, false)
+}
+
+/// Only the result in the envelope matters
+fn check_result(input: &[u8]) -> crate::error::Result<()> {
+    unwrap_oidb(input).map(drop).map_err(|error| {
+        match error.result().and_then(|(code, message)| EssenceError::from_result(code, message)) {
+            Some(essence) => essence.into(),
+            None => error.into(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::OidbSvcTrpcTcpBase;
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    fn response(error_code: u32, error_msg: &str) -> Bytes {
+        let oidb = OidbSvcTrpcTcpBase {
+            error_code: Some(error_code),
+            error_msg: Some(error_msg.to_string()),
+            ..Default::default()
+        };
+        Bytes::from(oidb.encode_to_vec().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_request_bodies() {
+        let context = BotContext::builder().build();
+
+        let set = SetEssenceService::default()
+            .build(&SetEssenceEventReq { group_uin: 123456, sequence: 777, random: 0x12345678 }, context.clone())
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&set).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0xeac, 1));
+        assert_eq!(
+            EssenceRequest::decode_from_slice(&oidb.body.unwrap()).unwrap(),
+            EssenceRequest { group_uin: 123456, sequence: 777, random: 0x12345678 }
+        );
+
+        let remove = RemoveEssenceService::default()
+            .build(&RemoveEssenceEventReq { group_uin: 123456, sequence: 777, random: 0x12345678 }, context)
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&remove).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0xeac, 2));
+    }
+
+    #[tokio::test]
+    async fn test_result_mapping() {
+        let context = BotContext::builder().build();
+
+        assert!(SetEssenceService::default().parse(response(0, ""), context.clone()).await.is_ok());
+
+        let not_admin = SetEssenceService::default()
+            .parse(response(EssenceError::NOT_ADMIN_CODE, "not admin"), context.clone())
+            .await;
+        assert!(matches!(
+            not_admin,
+            Err(crate::Error::Essence(EssenceError::NotAdmin { ref message, .. })) if message == "not admin"
+        ));
+
+        let already = SetEssenceService::default()
+            .parse(response(EssenceError::ALREADY_ESSENCE_CODE, "already essence"), context.clone())
+            .await;
+        assert!(matches!(already, Err(crate::Error::Essence(EssenceError::AlreadyEssence { .. }))));
+
+        let other = RemoveEssenceService::default().parse(response(5, "busy"), context).await;
+        assert!(matches!(other, Err(crate::Error::Essence(EssenceError::Failed { code: 5, .. }))));
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+
+use crate::{
+    context::BotContext,
+    internal::packets::oidb::{build_oidb, parse_oidb, ClientKeyRequest, PsKeyRequest, WebKeyResponse},
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    FetchPsKeysService {
+        command: "OidbSvcTrpcTcp.0x102a_0",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchPsKeysEvent(protocol = Protocols::ALL) {
+                request FetchPsKeysEventReq {
+                    domains: Vec<String>,
+                }
+                response FetchPsKeysEventResp {
+                    ps_keys: HashMap<String, String>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response: WebKeyResponse = parse_oidb(&input)?;
+            Ok(EventMessage::new(FetchPsKeysEventResp {
+                ps_keys: response.ps_keys.into_iter().map(|entry| (entry.domain, entry.key)).collect(),
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchPsKeysEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            build_oidb(0x102a, 0, &PsKeyRequest { domains: input.domains.clone() }, false)
+        }
+    }
+}
+
+define_service! {
+    FetchClientKeyService {
+        command: "OidbSvcTrpcTcp.0x102a_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchClientKeyEvent(protocol = Protocols::ALL) {
+                request FetchClientKeyEventReq {}
+                response FetchClientKeyEventResp {
+                    client_key: String,
+                    expiration: u32,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response: WebKeyResponse = parse_oidb(&input)?;
+            let client_key = response
+                .client_key
+                .filter(|key| !key.is_empty())
+                .ok_or_else(|| crate::error::Error::ParseError("Response carries no client key".to_string()))?;
+            Ok(EventMessage::new(FetchClientKeyEventResp {
+                client_key,
+                expiration: response.expiration.unwrap_or_default(),
+            }))
+        }
+
+        async fn build(_event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            build_oidb(0x102a, 1, &ClientKeyRequest {}, false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::{OidbSvcTrpcTcpBase, PsKeyEntry};
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    fn response(body: WebKeyResponse) -> Bytes {
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x102a,
+            body: Some(body.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+        Bytes::from(oidb.encode_to_vec().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_ps_keys() {
+        let request = FetchPsKeysService::default()
+            .build(&FetchPsKeysEventReq { domains: vec!["qun.qq.com".to_string()] }, BotContext::builder().build())
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&request).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x102a, 0));
+        assert_eq!(PsKeyRequest::decode_from_slice(&oidb.body.unwrap()).unwrap().domains, vec!["qun.qq.com"]);
+
+        let body = WebKeyResponse {
+            ps_keys: vec![PsKeyEntry { domain: "qun.qq.com".to_string(), key: "pskey".to_string() }],
+            ..Default::default()
+        };
+        let parsed = FetchPsKeysService::default()
+            .parse(response(body), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!(parsed.ps_keys.get("qun.qq.com").map(String::as_str), Some("pskey"));
+    }
+
+    #[tokio::test]
+    async fn test_client_key() {
+        let body = WebKeyResponse {
+            client_key: Some("CK".to_string()),
+            expiration: Some(86400),
+            ..Default::default()
+        };
+        let parsed = FetchClientKeyService::default()
+            .parse(response(body), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!((parsed.client_key.as_str(), parsed.expiration), ("CK", 86400));
+
+        let missing = FetchClientKeyService::default()
+            .parse(response(WebKeyResponse::default()), BotContext::builder().build())
+            .await;
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+
+use crate::{
+    common::{BotFriend, BotFriendCategory, BotGender},
+    context::BotContext,
+    internal::packets::oidb::{
+        build_oidb, parse_oidb, FriendsRequest, FriendsRequestBody, FriendsRequestNext,
+        FriendsRequestNumbers, FriendsResponse, OidbFriend,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+/// Friends returned per page
+const PAGE_SIZE: u32 = 300;
+
+const PROPERTY_PERSONAL_SIGN: u32 = 102;
+const PROPERTY_REMARKS: u32 = 103;
+const PROPERTY_NICKNAME: u32 = 20002;
+const PROPERTY_GENDER: u32 = 20009;
+const PROPERTY_AGE: u32 = 20037;
+const PROPERTY_QID: u32 = 27394;
+
+define_service! {
+    FetchFriendsService {
+        command: "OidbSvcTrpcTcp.0xfd4_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchFriendsEvent(protocol = Protocols::ALL) {
+                request FetchFriendsEventReq {
+                    next_uin: Option<u32>,
+                }
+                response FetchFriendsEventResp {
+                    friends: Vec<BotFriend>,
+                    categories: Vec<BotFriendCategory>,
+                    next_uin: Option<u32>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response: FriendsResponse = parse_oidb(&input)?;
+
+            let categories: Vec<BotFriendCategory> = response
+                .categories
+                .into_iter()
+                .map(|category| BotFriendCategory {
+                    category_id: category.category_id,
+                    category_name: category.category_name.unwrap_or_default(),
+                    sort_id: category.sort_id.unwrap_or_default(),
+                })
+                .collect();
+
+            let friends = response
+                .friends
+                .into_iter()
+                .map(|friend| to_bot_friend(friend, &categories))
+                .collect();
+
+            Ok(EventMessage::new(FetchFriendsEventResp {
+                friends,
+                categories,
+                next_uin: response.next.map(|next| next.uin),
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchFriendsEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = FriendsRequest {
+                count: PAGE_SIZE,
+                field4: 0,
+                next: input.next_uin.map(|uin| FriendsRequestNext { uin }),
+                field6: 1,
+                field7: i32::MAX as u32,
+                body: vec![
+                    FriendsRequestBody {
+                        property_type: 1,
+                        numbers: Some(FriendsRequestNumbers {
+                            numbers: vec![
+                                PROPERTY_REMARKS,
+                                PROPERTY_PERSONAL_SIGN,
+                                PROPERTY_NICKNAME,
+                                PROPERTY_QID,
+                                PROPERTY_GENDER,
+                                PROPERTY_AGE,
+                            ],
+                        }),
+                    },
+                    FriendsRequestBody {
+                        property_type: 4,
+                        numbers: Some(FriendsRequestNumbers {
+                            numbers: vec![100, 101, 102],
+                        }),
+                    },
+                ],
+                field10002: vec![13578, 13579, 13573, 13572, 13568],
+                field10003: 4051,
+            };
+
+            build_oidb(0xfd4, 1, &request, false)
+        }
+    }
+}
+
+/// Map a friend entry; the category name is only known if the category is on the same page
+fn to_bot_friend(friend: OidbFriend, categories: &[BotFriendCategory]) -> BotFriend {
+    let mut result = BotFriend {
+        uin: friend.uin.unwrap_or_default() as u64,
+        uid: friend.uid.unwrap_or_default(),
+        nickname: String::new(),
+        age: 0,
+        gender: BotGender::Unset,
+        remarks: String::new(),
+        personal_sign: String::new(),
+        qid: String::new(),
+        category: friend.category_id.map(|category_id| {
+            categories
+                .iter()
+                .find(|category| category.category_id == category_id)
+                .cloned()
+                .unwrap_or(BotFriendCategory {
+                    category_id,
+                    category_name: String::new(),
+                    sort_id: 0,
+                })
+        }),
+    };
+
+    let layers = friend
+        .additional
+        .into_iter()
+        .filter_map(|group| group.layer1);
+    for layer in layers {
+        for property in layer.properties {
+            match property.code {
+                PROPERTY_NICKNAME => result.nickname = property.value,
+                PROPERTY_REMARKS => result.remarks = property.value,
+                PROPERTY_PERSONAL_SIGN => result.personal_sign = property.value,
+                PROPERTY_QID => result.qid = property.value,
+                _ => {}
+            }
+        }
+        for property in layer.number_properties {
+            match property.code {
+                PROPERTY_GENDER => result.gender = BotGender::from(property.value),
+                PROPERTY_AGE => result.age = property.value,
+                _ => {}
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::OidbError;
+    use crate::internal::packets::oidb::{
+        FriendCategory, FriendLayer1, FriendNumberProperty, FriendProperty, FriendPropertyGroup,
+        FriendsResponseNext, OidbSvcTrpcTcpBase,
+    };
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    fn wrap(response: FriendsResponse) -> Bytes {
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0xfd4,
+            sub_command: 1,
+            error_code: Some(0),
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+        Bytes::from(oidb.encode_to_vec().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_build_first_and_next_page() {
+        let context = BotContext::builder().build();
+
+        let first = FetchFriendsService::default()
+            .build(&FetchFriendsEventReq { next_uin: None }, context.clone())
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&first).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0xfd4, 1));
+        let request = FriendsRequest::decode_from_slice(&oidb.body.unwrap()).unwrap();
+        assert_eq!(request.count, PAGE_SIZE);
+        assert_eq!(request.next, None);
+
+        let next = FetchFriendsService::default()
+            .build(&FetchFriendsEventReq { next_uin: Some(10002) }, context)
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&next).unwrap();
+        let request = FriendsRequest::decode_from_slice(&oidb.body.unwrap()).unwrap();
+        assert_eq!(request.next, Some(FriendsRequestNext { uin: 10002 }));
+    }
+
+    #[tokio::test]
+    async fn test_parse_friend_fields() {
+        let response = FriendsResponse {
+            next: Some(FriendsResponseNext { uin: 10002 }),
+            friends: vec![OidbFriend {
+                uid: Some("u_alice".to_string()),
+                category_id: Some(1),
+                uin: Some(10001),
+                additional: vec![FriendPropertyGroup {
+                    property_type: Some(1),
+                    layer1: Some(FriendLayer1 {
+                        number_properties: vec![
+                            FriendNumberProperty { code: PROPERTY_GENDER, value: 2 },
+                            FriendNumberProperty { code: PROPERTY_AGE, value: 20 },
+                        ],
+                        properties: vec![
+                            FriendProperty { code: PROPERTY_NICKNAME, value: "alice".to_string() },
+                            FriendProperty { code: PROPERTY_REMARKS, value: "Alice W.".to_string() },
+                            FriendProperty { code: PROPERTY_PERSONAL_SIGN, value: "hello".to_string() },
+                            FriendProperty { code: PROPERTY_QID, value: "alice_qid".to_string() },
+                        ],
+                    }),
+                }],
+            }],
+            categories: vec![FriendCategory {
+                category_id: 1,
+                category_name: Some("Friends".to_string()),
+                category_member_count: Some(1),
+                sort_id: Some(0),
+            }],
+            ..Default::default()
+        };
+
+        let parsed = FetchFriendsService::default()
+            .parse(wrap(response), BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert_eq!(parsed.next_uin, Some(10002));
+        assert_eq!(
+            parsed.friends,
+            vec![BotFriend {
+                uin: 10001,
+                uid: "u_alice".to_string(),
+                nickname: "alice".to_string(),
+                age: 20,
+                gender: BotGender::Female,
+                remarks: "Alice W.".to_string(),
+                personal_sign: "hello".to_string(),
+                qid: "alice_qid".to_string(),
+                category: Some(BotFriendCategory {
+                    category_id: 1,
+                    category_name: "Friends".to_string(),
+                    sort_id: 0,
+                }),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_error_code() {
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0xfd4,
+            sub_command: 1,
+            error_code: Some(1),
+            error_msg: Some("rate limited".to_string()),
+            ..Default::default()
+        };
+
+        let result = FetchFriendsService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await;
+        assert!(matches!(
+            result,
+            Err(crate::Error::Oidb(OidbError::Failed { code: 1, message, .. })) if message == "rate limited"
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    context::BotContext,
+    internal::packets::oidb::{
+        GroupExtraFields, GroupExtraRequest, GroupExtraRequestGroup, GroupExtraResponse,
+        OidbSvcTrpcTcpBase,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+/// Extended information of one group, `None` where the group has not set the field
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupExtra {
+    pub group_uin: u64,
+    pub description: Option<String>,
+    pub question: Option<String>,
+    pub announcement: Option<String>,
+}
+
+define_service! {
+    FetchGroupExtraService {
+        command: "OidbSvcTrpcTcp.0x88d_0",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchGroupExtraEvent(protocol = Protocols::ALL) {
+                request FetchGroupExtraEventReq {
+                    group_uins: Vec<u64>,
+                }
+                response FetchGroupExtraEventResp {
+                    groups: Vec<GroupExtra>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Fetching group information failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            let response = GroupExtraResponse::decode_from_slice(&oidb.body.unwrap_or_default())?;
+            let non_empty = |value: Option<String>| value.filter(|value| !value.is_empty());
+
+            // Groups the server could not query are left out
+            let groups = response
+                .groups
+                .into_iter()
+                .filter(|group| group.result.unwrap_or_default() == 0)
+                .map(|group| {
+                    let info = group.info.unwrap_or_default();
+                    GroupExtra {
+                        group_uin: group.group_uin as u64,
+                        description: non_empty(info.description),
+                        question: non_empty(info.question),
+                        announcement: non_empty(info.announcement),
+                    }
+                })
+                .collect();
+
+            Ok(EventMessage::new(FetchGroupExtraEventResp { groups }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchGroupExtraEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupExtraRequest {
+                random: 537099973,
+                groups: input
+                    .group_uins
+                    .iter()
+                    .map(|&group_uin| GroupExtraRequestGroup {
+                        group_uin: group_uin as u32,
+                        fields: Some(GroupExtraFields {
+                            question: true,
+                            description: true,
+                            announcement: true,
+                        }),
+                    })
+                    .collect(),
+            };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0x88d,
+                sub_command: 0,
+                body: Some(
+                    request
+                        .encode_to_vec()
+                        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+                ),
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::{GroupExtraInfo, GroupExtraResponseGroup};
+    use crate::protocol::TypedService;
+
+    #[tokio::test]
+    async fn test_parse_skips_failed_groups() {
+        let response = GroupExtraResponse {
+            groups: vec![
+                GroupExtraResponseGroup {
+                    group_uin: 123456,
+                    result: Some(0),
+                    info: Some(GroupExtraInfo {
+                        description: Some("a group".to_string()),
+                        question: Some(String::new()),
+                        announcement: Some("hello".to_string()),
+                    }),
+                },
+                GroupExtraResponseGroup {
+                    group_uin: 654321,
+                    result: Some(72),
+                    info: None,
+                },
+            ],
+        };
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x88d,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+
+        let parsed = FetchGroupExtraService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            parsed.groups,
+            vec![GroupExtra {
+                group_uin: 123456,
+                description: Some("a group".to_string()),
+                question: None,
+                announcement: Some("hello".to_string()),
+            }]
+        );
+    }
+}
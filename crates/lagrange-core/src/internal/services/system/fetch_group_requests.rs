@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    common::{GroupRequest, GroupRequestState, GroupRequestUser},
+    context::BotContext,
+    internal::packets::oidb::{
+        GroupRequestsRequest, GroupRequestsResponse, OidbGroupRequest, OidbGroupRequestUser, OidbSvcTrpcTcpBase,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+/// `event_type` of join requests and invitations, the list also carries members leaving and
+/// being kicked
+const JOIN_EVENT_TYPES: [u32; 2] = [1, 22];
+
+define_service! {
+    FetchGroupRequestsService {
+        command: "OidbSvcTrpcTcp.0x10c0_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchGroupRequestsEvent(protocol = Protocols::ALL) {
+                request FetchGroupRequestsEventReq {
+                    count: u32,
+                }
+                response FetchGroupRequestsEventResp {
+                    requests: Vec<GroupRequest>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Fetching group requests failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            let response = GroupRequestsResponse::decode_from_slice(&oidb.body.unwrap_or_default())?;
+
+            Ok(EventMessage::new(FetchGroupRequestsEventResp {
+                requests: response.requests.into_iter().filter_map(to_group_request).collect(),
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchGroupRequestsEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupRequestsRequest { count: input.count, field2: 0 };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0x10c0,
+                sub_command: 1,
+                body: Some(
+                    request
+                        .encode_to_vec()
+                        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+                ),
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+fn to_group_request(request: OidbGroupRequest) -> Option<GroupRequest> {
+    if !JOIN_EVENT_TYPES.contains(&request.event_type) {
+        return None;
+    }
+    let user = |user: OidbGroupRequestUser| GroupRequestUser { uid: user.uid, nickname: user.name };
+    let group = request.group.unwrap_or_default();
+
+    Some(GroupRequest {
+        group_uin: group.group_uin as u64,
+        group_name: group.group_name,
+        requester: user(request.target.unwrap_or_default()),
+        invitor: request.invitor.filter(|invitor| request.event_type == 22 && !invitor.uid.is_empty()).map(user),
+        operator: request.operator.filter(|operator| !operator.uid.is_empty()).map(user),
+        comment: request.comment,
+        sequence: request.sequence,
+        state: GroupRequestState::from_code(request.state),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+    use crate::testing::unhex;
+
+    fn user(uid: &str, nickname: &str) -> GroupRequestUser {
+        GroupRequestUser { uid: uid.to_string(), nickname: nickname.to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_parse_group_requests() {
+        let parsed = FetchGroupRequestsService::default()
+            .parse(unhex(GROUP_REQUESTS_RESPONSE), BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            parsed.requests,
+            vec![
+                GroupRequest {
+                    group_uin: 123456,
+                    group_name: "rustaceans".to_string(),
+                    requester: user("u_alice", "alice"),
+                    invitor: None,
+                    operator: None,
+                    comment: "hello".to_string(),
+                    sequence: 1700000000003,
+                    state: GroupRequestState::Pending,
+                },
+                GroupRequest {
+                    group_uin: 123456,
+                    group_name: "rustaceans".to_string(),
+                    requester: user("u_bob", "bob"),
+                    invitor: Some(user("u_carol", "carol")),
+                    operator: None,
+                    comment: String::new(),
+                    sequence: 1700000000002,
+                    state: GroupRequestState::Pending,
+                },
+                GroupRequest {
+                    group_uin: 654321,
+                    group_name: "quiet".to_string(),
+                    requester: user("u_dave", "dave"),
+                    invitor: None,
+                    operator: Some(user("u_admin", "admin")),
+                    comment: "let me in".to_string(),
+                    sequence: 1700000000001,
+                    state: GroupRequestState::Rejected,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_group_requests() {
+        let bytes = FetchGroupRequestsService::default()
+            .build(&FetchGroupRequestsEventReq { count: 20 }, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x10c0, 1));
+        assert_eq!(GroupRequestsRequest::decode_from_slice(&oidb.body.unwrap()).unwrap().count, 20);
+    }
+
+    /// Hand-encoded 0x10c0_1 response, newest first: u_alice asking to join 123456 "rustaceans"
+    /// with "hello" (sequence 1700000000003), u_carol inviting u_bob into it (1700000000002),
+    /// u_dave asking to join 654321 "quiet" and rejected by u_admin (1700000000001), and u_erin
+    /// leaving 654321 (event type 13, 1700000000000), which is not a request
+    const GROUP_REQUESTS_RESPONSE: &str = concat!(
+        "08c0211001180022f3010a360883d095ffbc3110011801221008c0c407120a72757374616365616e",
+        "732a100a07755f616c6963651205616c696365520568656c6c6f0a3f0882d095ffbc311016180122",
+        "1008c0c407120a72757374616365616e732a0c0a05755f626f621203626f6232100a07755f636172",
+        "6f6c12056361726f6c52000a450881d095ffbc3110011803220b08f1f727120571756965742a0e0a",
+        "06755f646176651204646176653a100a07755f61646d696e120561646d696e52096c6574206d6520",
+        "696e0a2a0880d095ffbc31100d1802220b08f1f727120571756965742a0e0a06755f6572696e1204",
+        "6572696e52001883d095ffbc31",
+    );
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    common::BotGroup,
+    context::BotContext,
+    internal::packets::oidb::{
+        GroupsRequest, GroupsRequestConfig, GroupsRequestFields, GroupsResponse, OidbGroup,
+        OidbSvcTrpcTcpBase,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    FetchGroupsService {
+        command: "OidbSvcTrpcTcp.0xfe5_2",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchGroupsEvent(protocol = Protocols::ALL) {
+                request FetchGroupsEventReq {
+                    cookie: Option<Vec<u8>>,
+                }
+                response FetchGroupsEventResp {
+                    groups: Vec<BotGroup>,
+                    next_cookie: Option<Vec<u8>>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Fetching groups failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            let response = GroupsResponse::decode_from_slice(&oidb.body.unwrap_or_default())?;
+
+            Ok(EventMessage::new(FetchGroupsEventResp {
+                groups: response.groups.into_iter().map(to_bot_group).collect(),
+                next_cookie: response.next_cookie.filter(|cookie| !cookie.is_empty()),
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchGroupsEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupsRequest {
+                config: Some(GroupsRequestConfig {
+                    info: Some(GroupsRequestFields {
+                        owner: true,
+                        create_time: true,
+                        max_member: true,
+                        member_count: true,
+                        group_name: true,
+                        description: true,
+                        question: true,
+                        announcement: true,
+                    }),
+                }),
+                cookie: input.cookie.clone(),
+            };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0xfe5,
+                sub_command: 2,
+                body: Some(
+                    request
+                        .encode_to_vec()
+                        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+                ),
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+fn to_bot_group(group: OidbGroup) -> BotGroup {
+    let info = group.info.unwrap_or_default();
+    let non_empty = |value: Option<String>| value.filter(|value| !value.is_empty());
+
+    BotGroup {
+        group_uin: group.group_uin as u64,
+        group_uid: group.group_uin.to_string(),
+        group_name: info.group_name.unwrap_or_default(),
+        member_count: info.member_count.unwrap_or_default(),
+        max_member: info.max_member.unwrap_or_default(),
+        create_time: info.create_time.unwrap_or_default() as i64,
+        description: non_empty(info.description),
+        question: non_empty(info.question),
+        announcement: non_empty(info.announcement),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::BotContact;
+    use crate::protocol::TypedService;
+    use crate::testing::unhex;
+
+    #[tokio::test]
+    async fn test_parse_groups() {
+        let parsed = FetchGroupsService::default()
+            .parse(unhex(GROUPS_RESPONSE), BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert_eq!(parsed.next_cookie, None);
+        assert_eq!(
+            parsed.groups,
+            vec![
+                BotGroup {
+                    group_uin: 123456,
+                    group_uid: "123456".to_string(),
+                    group_name: "rustaceans".to_string(),
+                    member_count: 42,
+                    max_member: 500,
+                    create_time: 1600000000,
+                    description: None,
+                    question: Some("favourite crate?".to_string()),
+                    announcement: Some("be nice".to_string()),
+                },
+                BotGroup {
+                    group_uin: 654321,
+                    group_uid: "654321".to_string(),
+                    group_name: "quiet".to_string(),
+                    member_count: 3,
+                    max_member: 200,
+                    create_time: 1700000000,
+                    description: None,
+                    question: None,
+                    announcement: None,
+                },
+            ]
+        );
+
+        let group = &parsed.groups[0];
+        assert_eq!(group.uin(), 123456);
+        assert_eq!(group.uid(), "123456");
+        assert_eq!(group.nickname(), "rustaceans");
+    }
+
+    #[tokio::test]
+    async fn test_build_with_cookie() {
+        let bytes = FetchGroupsService::default()
+            .build(
+                &FetchGroupsEventReq { cookie: Some(vec![1, 2, 3]) },
+                BotContext::builder().build(),
+            )
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0xfe5, 2));
+        let request = GroupsRequest::decode_from_slice(&oidb.body.unwrap()).unwrap();
+        assert_eq!(request.cookie, Some(vec![1, 2, 3]));
+        assert!(request.config.unwrap().info.unwrap().announcement);
+    }
+
+    /// Hand-encoded 0xfe5_2 response with two groups: 123456
+    /// "rustaceans" (owner "u_owner", created 1600000000, 42/500 members, question
+    /// "favourite crate?", announcement "be nice"), group 654321 "quiet" (created 1700000000,
+    /// 3/200 members), and no continuation cookie
+    const GROUPS_RESPONSE: &str = concat!(
+        "08e51f100218002261124518c0c407223f0a091207755f6f776e65721080a0f8fa0518f403202a2a",
+        "0a72757374616365616e739a01106661766f75726974652063726174653ff201076265206e696365",
+        "121818f1f72722121080e2cfaa0618c80120032a057175696574",
+    );
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    common::{BotGender, BotGroupMember, GroupRole},
+    context::BotContext,
+    internal::packets::oidb::{
+        MembersRequest, MembersRequestFields, MembersResponse, OidbGroupMember, OidbSvcTrpcTcpBase,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    FetchMembersService {
+        command: "OidbSvcTrpcTcp.0xfe7_3",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchMembersEvent(protocol = Protocols::ALL) {
+                request FetchMembersEventReq {
+                    group_uin: u64,
+                    token: Option<String>,
+                }
+                response FetchMembersEventResp {
+                    members: Vec<BotGroupMember>,
+                    next_token: Option<String>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Fetching group members failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            let response = MembersResponse::decode_from_slice(&oidb.body.unwrap_or_default())?;
+            let group_uin = response.group_uin as u64;
+
+            Ok(EventMessage::new(FetchMembersEventResp {
+                members: response
+                    .members
+                    .into_iter()
+                    .map(|member| to_bot_member(group_uin, member))
+                    .collect(),
+                next_token: response.token.filter(|token| !token.is_empty()),
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchMembersEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = MembersRequest {
+                group_uin: input.group_uin as u32,
+                field2: 5,
+                field3: 2,
+                fields: Some(MembersRequestFields {
+                    member_name: true,
+                    member_card: true,
+                    level: true,
+                    special_title: true,
+                    join_timestamp: true,
+                    last_msg_timestamp: true,
+                    shut_up_timestamp: true,
+                    permission: true,
+                }),
+                token: input.token.clone(),
+            };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0xfe7,
+                sub_command: 3,
+                body: Some(
+                    request
+                        .encode_to_vec()
+                        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+                ),
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+fn timestamp(seconds: Option<u32>) -> DateTime<Utc> {
+    DateTime::from_timestamp(seconds.unwrap_or_default() as i64, 0).unwrap_or_default()
+}
+
+fn to_bot_member(group_uin: u64, member: OidbGroupMember) -> BotGroupMember {
+    let id = member.id.unwrap_or_default();
+
+    BotGroupMember {
+        uin: id.uin.unwrap_or_default() as u64,
+        uid: id.uid.unwrap_or_default(),
+        nickname: member.member_name.unwrap_or_default(),
+        group_uin,
+        role: GroupRole::from(member.permission.unwrap_or_default()),
+        level: member.level.and_then(|level| level.level).unwrap_or_default(),
+        card: member
+            .member_card
+            .and_then(|card| card.member_card)
+            .filter(|card| !card.is_empty()),
+        special_title: member.special_title.filter(|title| !title.is_empty()),
+        age: 0,
+        gender: BotGender::Unset,
+        join_time: timestamp(member.join_timestamp),
+        last_msg_time: timestamp(member.last_msg_timestamp),
+        shut_up_timestamp: timestamp(member.shut_up_timestamp),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::{OidbGroupMemberCard, OidbGroupMemberId, OidbGroupMemberLevel};
+    use crate::protocol::TypedService;
+
+    fn member(uin: u32, permission: u32) -> OidbGroupMember {
+        OidbGroupMember {
+            id: Some(OidbGroupMemberId {
+                uid: Some(format!("u_{}", uin)),
+                uin: Some(uin),
+            }),
+            member_name: Some(format!("member{}", uin)),
+            join_timestamp: Some(1600000000),
+            last_msg_timestamp: Some(1700000000),
+            permission: Some(permission),
+            ..Default::default()
+        }
+    }
+
+    async fn parse(response: MembersResponse) -> FetchMembersEventResp {
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0xfe7,
+            sub_command: 3,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+
+        FetchMembersService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_parse_pages() {
+        let mut muted = member(10003, 0);
+        muted.member_card = Some(OidbGroupMemberCard {
+            member_card: Some("carol (muted)".to_string()),
+        });
+        muted.level = Some(OidbGroupMemberLevel { level: Some(12) });
+        muted.shut_up_timestamp = Some(1800000000);
+
+        let first = parse(MembersResponse {
+            group_uin: 123456,
+            members: vec![member(10001, 1), member(10002, 2)],
+            token: Some("page-2".to_string()),
+            ..Default::default()
+        })
+        .await;
+        let second = parse(MembersResponse {
+            group_uin: 123456,
+            members: vec![muted],
+            token: Some(String::new()),
+            ..Default::default()
+        })
+        .await;
+
+        assert_eq!(first.next_token.as_deref(), Some("page-2"));
+        assert_eq!(second.next_token, None);
+
+        let members: Vec<BotGroupMember> = first.members.into_iter().chain(second.members).collect();
+        let roles: Vec<(u64, GroupRole)> = members
+            .iter()
+            .map(|member| (member.uin, member.role))
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                (10001, GroupRole::Owner),
+                (10002, GroupRole::Admin),
+                (10003, GroupRole::Member),
+            ]
+        );
+
+        let owner = &members[0];
+        assert_eq!(owner.group_uin, 123456);
+        assert_eq!(owner.uid, "u_10001");
+        assert_eq!(owner.card, None);
+        assert_eq!(owner.join_time.timestamp(), 1600000000);
+        assert_eq!(owner.last_msg_time.timestamp(), 1700000000);
+        assert_eq!(owner.shut_up_timestamp.timestamp(), 0);
+
+        let carol = &members[2];
+        assert_eq!(carol.card.as_deref(), Some("carol (muted)"));
+        assert_eq!(carol.level, 12);
+        assert_eq!(carol.shut_up_timestamp.timestamp(), 1800000000);
+    }
+
+    #[tokio::test]
+    async fn test_build_with_token() {
+        let bytes = FetchMembersService::default()
+            .build(
+                &FetchMembersEventReq {
+                    group_uin: 123456,
+                    token: Some("page-2".to_string()),
+                },
+                BotContext::builder().build(),
+            )
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0xfe7, 3));
+        let request = MembersRequest::decode_from_slice(&oidb.body.unwrap()).unwrap();
+        assert_eq!(request.group_uin, 123456);
+        assert_eq!(request.token.as_deref(), Some("page-2"));
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+
+use crate::{
+    common::{BotGender, BotStranger},
+    context::BotContext,
+    internal::packets::oidb::{
+        build_oidb, parse_oidb_with_options, OidbStranger, StrangerInfoRequest, StrangerInfoResponse,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    FetchStrangerService {
+        command: "OidbSvcTrpcTcp.0x5eb_22",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+        // A single profile, anything longer is not one
+        max_length_delimited: 64 * 1024,
+
+        events {
+            FetchStrangerEvent(protocol = Protocols::ALL) {
+                request FetchStrangerEventReq {
+                    uin: u64,
+                }
+                response FetchStrangerEventResp {
+                    stranger: Option<BotStranger>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response: StrangerInfoResponse = parse_oidb_with_options(&input, self.metadata.decode_options)?;
+            let stranger = response.users.into_iter().find(|user| user.uin != 0).map(to_stranger);
+            Ok(EventMessage::new(FetchStrangerEventResp { stranger }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchStrangerEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = StrangerInfoRequest {
+                uins: vec![input.uin],
+                req_personal_sign: 1,
+                req_level: 1,
+                req_nickname: 1,
+                req_gender: 1,
+                req_age: 1,
+            };
+            build_oidb(0x5eb, 22, &request, false)
+        }
+    }
+}
+
+fn to_stranger(user: OidbStranger) -> BotStranger {
+    BotStranger {
+        uin: user.uin,
+        uid: user.uid.unwrap_or_default(),
+        nickname: user.nickname.unwrap_or_default(),
+        age: user.age.unwrap_or_default(),
+        gender: BotGender::from(user.gender.unwrap_or_default()),
+        level: user.level.unwrap_or_default(),
+        personal_sign: user.personal_sign.unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, OidbError};
+    use crate::internal::packets::oidb::OidbSvcTrpcTcpBase;
+    use crate::protocol::TypedService;
+    use crate::testing::unhex;
+    use lagrange_proto::ProtoMessage;
+
+    async fn parse(input: Bytes) -> crate::error::Result<FetchStrangerEventResp> {
+        FetchStrangerService::default().parse(input, BotContext::builder().build()).await
+    }
+
+    #[tokio::test]
+    async fn test_parse_stranger() {
+        let parsed = parse(unhex(STRANGER_RESPONSE)).await.unwrap();
+        assert_eq!(
+            parsed.stranger,
+            Some(BotStranger {
+                uin: 10002,
+                uid: "u_bob".to_string(),
+                nickname: "bob".to_string(),
+                age: 25,
+                gender: BotGender::Male,
+                level: 16,
+                personal_sign: "hey".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_refused() {
+        // Envelope with an empty 0x5eb_22 body
+        assert_eq!(parse(unhex("08eb0b101618002200")).await.unwrap().stranger, None);
+
+        let refused = OidbSvcTrpcTcpBase {
+            command: 0x5eb,
+            sub_command: 22,
+            error_code: Some(1),
+            error_msg: Some("user not exist".to_string()),
+            ..Default::default()
+        };
+        let result = parse(Bytes::from(refused.encode_to_vec().unwrap())).await;
+        assert!(matches!(result, Err(Error::Oidb(OidbError::Failed { code: 1, .. }))));
+    }
+
+    #[tokio::test]
+    async fn test_parse_oversized() {
+        let user = OidbStranger { uin: 10002, personal_sign: Some("a".repeat(70 * 1024)), ..Default::default() };
+        let response = StrangerInfoResponse { users: vec![user] };
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x5eb,
+            sub_command: 22,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+        let result = parse(Bytes::from(oidb.encode_to_vec().unwrap())).await;
+        assert!(matches!(result, Err(Error::Oidb(OidbError::Malformed(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_build_request() {
+        let bytes = FetchStrangerService::default()
+            .build(&FetchStrangerEventReq { uin: 10002 }, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command, oidb.reserved), (0x5eb, 22, None));
+        let request = StrangerInfoRequest::decode_from_slice(&oidb.body.unwrap()).unwrap();
+        assert_eq!(request.uins, vec![10002]);
+        assert_eq!(request.req_nickname, 1);
+    }
+
+    /// Hand-encoded 0x5eb_22 response for 10002 "u_bob": nickname "bob", sign "hey", level 16,
+    /// gender 1, age 25
+    const STRANGER_RESPONSE: &str =
+        "08eb0b1016180022245a2208924e1205755f626f62b20603686579c8061092e20903626f62c8e20901a8e40919";
+}
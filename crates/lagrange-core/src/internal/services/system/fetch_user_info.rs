@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    common::{BotGender, BotUserInfo, UserId},
+    context::BotContext,
+    internal::packets::oidb::{
+        OidbSvcTrpcTcpBase, UserInfoBody, UserInfoKey, UserInfoRequestByUid, UserInfoRequestByUin,
+        UserInfoResponse,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+const KEY_AVATAR: u32 = 101;
+const KEY_PERSONAL_SIGN: u32 = 102;
+const KEY_LEVEL: u32 = 105;
+const KEY_NICKNAME: u32 = 20002;
+const KEY_COUNTRY: u32 = 20003;
+const KEY_GENDER: u32 = 20009;
+const KEY_CITY: u32 = 20020;
+const KEY_AGE: u32 = 20037;
+const KEY_QID: u32 = 27394;
+
+const KEYS: [u32; 9] = [
+    KEY_AVATAR,
+    KEY_PERSONAL_SIGN,
+    KEY_LEVEL,
+    KEY_NICKNAME,
+    KEY_COUNTRY,
+    KEY_GENDER,
+    KEY_CITY,
+    KEY_AGE,
+    KEY_QID,
+];
+
+/// Size appended to the avatar URL prefix returned by the server
+const AVATAR_SIZE: &str = "640";
+
+define_service! {
+    FetchUserInfoService {
+        command: "OidbSvcTrpcTcp.0xfe1_2",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchUserInfoEvent(protocol = Protocols::ALL) {
+                request FetchUserInfoEventReq {
+                    user: UserId,
+                }
+                response FetchUserInfoEventResp {
+                    info: Option<BotUserInfo>,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Fetching user information failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            let response = UserInfoResponse::decode_from_slice(&oidb.body.unwrap_or_default())?;
+
+            // Unknown users come back without a body, or with an empty one
+            let info = response
+                .body
+                .filter(|body| body.uin.unwrap_or_default() != 0)
+                .map(to_user_info);
+
+            Ok(EventMessage::new(FetchUserInfoEventResp { info }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FetchUserInfoEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let keys = KEYS.iter().map(|&key| UserInfoKey { key }).collect();
+            let (body, reserved) = match &input.user {
+                UserId::Uin(uin) => (
+                    UserInfoRequestByUin { uin: *uin as u32, field3: 1, keys }.encode_to_vec(),
+                    None,
+                ),
+                UserId::Uid(uid) => (
+                    UserInfoRequestByUid { uid: uid.clone(), field3: 1, keys }.encode_to_vec(),
+                    Some(1),
+                ),
+            };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0xfe1,
+                sub_command: 2,
+                body: Some(body.map_err(|e| crate::error::Error::BuildError(e.to_string()))?),
+                reserved,
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+fn to_user_info(body: UserInfoBody) -> BotUserInfo {
+    let mut info = BotUserInfo {
+        uin: body.uin.unwrap_or_default() as u64,
+        uid: body.uid.unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let properties = body.properties.unwrap_or_default();
+    for property in properties.numbers {
+        match property.key {
+            KEY_GENDER => info.gender = BotGender::from(property.value),
+            KEY_AGE => info.age = property.value,
+            KEY_LEVEL => info.level = property.value,
+            _ => {}
+        }
+    }
+    for property in properties.bytes {
+        let value = String::from_utf8_lossy(&property.value.unwrap_or_default()).into_owned();
+        match property.key {
+            KEY_NICKNAME => info.nickname = value,
+            KEY_PERSONAL_SIGN => info.personal_sign = value,
+            KEY_COUNTRY => info.country = value,
+            KEY_CITY => info.city = value,
+            KEY_QID => info.qid = value,
+            KEY_AVATAR if !value.is_empty() => info.avatar_url = value + AVATAR_SIZE,
+            _ => {}
+        }
+    }
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+    use crate::testing::unhex;
+
+    #[tokio::test]
+    async fn test_parse_profile() {
+        let parsed = FetchUserInfoService::default()
+            .parse(unhex(PROFILE_RESPONSE), BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            parsed.info,
+            Some(BotUserInfo {
+                uin: 10001,
+                uid: "u_abc".to_string(),
+                nickname: "alice".to_string(),
+                avatar_url: "https://q.qlogo.cn/g?b=qq&nk=10001&s=640".to_string(),
+                personal_sign: "hello".to_string(),
+                country: "CN".to_string(),
+                city: "Shanghai".to_string(),
+                age: 20,
+                gender: BotGender::Female,
+                qid: "alice_qid".to_string(),
+                level: 32,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_not_found() {
+        // Envelope with an empty 0xfe1_2 body
+        let parsed = FetchUserInfoService::default()
+            .parse(unhex("08e11f100218002200"), BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert_eq!(parsed.info, None);
+    }
+
+    #[tokio::test]
+    async fn test_build_by_uid_sets_reserved() {
+        let bytes = FetchUserInfoService::default()
+            .build(
+                &FetchUserInfoEventReq { user: UserId::from("u_abc") },
+                BotContext::builder().build(),
+            )
+            .await
+            .unwrap();
+
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!(oidb.reserved, Some(1));
+        let request = UserInfoRequestByUid::decode_from_slice(&oidb.body.unwrap()).unwrap();
+        assert_eq!(request.uid, "u_abc");
+        assert_eq!(request.keys.len(), KEYS.len());
+    }
+
+    /// Hand-encoded 0xfe1_2 response for 10001 "u_abc": nickname "alice", gender 2, age 20,
+    /// level 32, sign "hello", country "CN", city "Shanghai", qid "alice_qid", avatar URL prefix
+    const PROFILE_RESPONSE: &str = concat!(
+        "08e11f100218002294010a91010a05755f6162631284010a0608a99c0110020a0608c59c0110140a",
+        "0408691020120b08a29c011205616c69636512090866120568656c6c6f120808a39c011202434e12",
+        "0e08b49c0112085368616e67686169120f0882d6011209616c6963655f7169641229086512256874",
+        "7470733a2f2f712e716c6f676f2e636e2f673f623d7171266e6b3d313030303126733d18914e",
+    );
+}
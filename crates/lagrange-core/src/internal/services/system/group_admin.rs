@@ -0,0 +1,378 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+
+use crate::{
+    context::BotContext,
+    error::GroupAdminError,
+    internal::packets::oidb::{
+        build_oidb, unwrap_oidb, GroupSettings, GroupSettingsRequest, KickMemberRequest,
+        MuteMemberBody, MuteMemberRequest, SetAdminRequest, SetMemberCardBody, SetMemberCardRequest,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    MuteMemberService {
+        command: "OidbSvcTrpcTcp.0x1253_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            MuteMemberEvent(protocol = Protocols::ALL) {
+                request MuteMemberEventReq {
+                    group_uin: u64,
+                    uid: String,
+                    duration: u32,
+                }
+                response MuteMemberEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(MuteMemberEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<MuteMemberEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = MuteMemberRequest {
+                group_uin: input.group_uin as u32,
+                mute_type: 1,
+                body: Some(MuteMemberBody {
+                    target_uid: input.uid.clone(),
+                    duration: input.duration,
+                }),
+            };
+            build_oidb(0x1253, 1, &request, false)
+        }
+    }
+}
+
+define_service! {
+    MuteAllService {
+        command: "OidbSvcTrpcTcp.0x89a_0",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            MuteAllEvent(protocol = Protocols::ALL) {
+                request MuteAllEventReq {
+                    group_uin: u64,
+                    enable: bool,
+                }
+                response MuteAllEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(MuteAllEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<MuteAllEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupSettingsRequest {
+                group_uin: input.group_uin as u32,
+                settings: Some(GroupSettings {
+                    mute_all: Some(if input.enable { u32::MAX } else { 0 }),
+                    ..Default::default()
+                }),
+            };
+            build_oidb(0x89a, 0, &request, false)
+        }
+    }
+}
+
+define_service! {
+    KickMemberService {
+        command: "OidbSvcTrpcTcp.0x8a0_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            KickMemberEvent(protocol = Protocols::ALL) {
+                request KickMemberEventReq {
+                    group_uin: u64,
+                    uid: String,
+                    reject_add_request: bool,
+                }
+                response KickMemberEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(KickMemberEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<KickMemberEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = KickMemberRequest {
+                group_uin: input.group_uin as u32,
+                target_uid: input.uid.clone(),
+                reject_add_request: input.reject_add_request,
+                reason: String::new(),
+            };
+            build_oidb(0x8a0, 1, &request, false)
+        }
+    }
+}
+
+define_service! {
+    SetAdminService {
+        command: "OidbSvcTrpcTcp.0x1096_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SetAdminEvent(protocol = Protocols::ALL) {
+                request SetAdminEventReq {
+                    group_uin: u64,
+                    uid: String,
+                    is_admin: bool,
+                }
+                response SetAdminEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(SetAdminEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<SetAdminEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = SetAdminRequest {
+                group_uin: input.group_uin as u32,
+                target_uid: input.uid.clone(),
+                is_admin: input.is_admin,
+            };
+            build_oidb(0x1096, 1, &request, false)
+        }
+    }
+}
+
+define_service! {
+    SetMemberCardService {
+        command: "OidbSvcTrpcTcp.0x8fc_3",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SetMemberCardEvent(protocol = Protocols::ALL) {
+                request SetMemberCardEventReq {
+                    group_uin: u64,
+                    uid: String,
+                    card: String,
+                }
+                response SetMemberCardEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(SetMemberCardEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<SetMemberCardEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = SetMemberCardRequest {
+                group_uin: input.group_uin as u32,
+                body: Some(SetMemberCardBody {
+                    target_uid: input.uid.clone(),
+                    card: input.card.clone(),
+                }),
+            };
+            build_oidb(0x8fc, 3, &request, false)
+        }
+    }
+}
+
+define_service! {
+    SetGroupNameService {
+        command: "OidbSvcTrpcTcp.0x89a_15",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SetGroupNameEvent(protocol = Protocols::ALL) {
+                request SetGroupNameEventReq {
+                    group_uin: u64,
+                    name: String,
+                }
+                response SetGroupNameEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            check_result(&input)?;
+            Ok(EventMessage::new(SetGroupNameEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<SetGroupNameEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupSettingsRequest {
+                group_uin: input.group_uin as u32,
+                settings: Some(GroupSettings {
+                    group_name: Some(input.name.clone()),
+                    ..Default::default()
+                }),
+            };
+            build_oidb(0x89a, 15, &request, false)
+        }
+    }
+}
+
+/// Administration responses carry no body, only the result in the envelope
+fn check_result(input: &[u8]) -> crate::error::Result<()> {
+    unwrap_oidb(input).map(drop).map_err(|error| {
+        match error.result().and_then(|(code, message)| GroupAdminError::from_result(code, message)) {
+            Some(admin) => admin.into(),
+            None => error.into(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::OidbSvcTrpcTcpBase;
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    fn unwrap_request(bytes: &[u8], command: u32, sub_command: u32) -> Vec<u8> {
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (command, sub_command));
+        oidb.body.unwrap()
+    }
+
+    fn response(error_code: u32, error_msg: &str) -> Bytes {
+        let oidb = OidbSvcTrpcTcpBase {
+            error_code: Some(error_code),
+            error_msg: Some(error_msg.to_string()),
+            ..Default::default()
+        };
+        Bytes::from(oidb.encode_to_vec().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_member_request_bodies() {
+        let context = BotContext::builder().build();
+
+        let mute = MuteMemberService::default()
+            .build(&MuteMemberEventReq { group_uin: 123456, uid: "u_abc".to_string(), duration: 600 }, context.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            MuteMemberRequest::decode_from_slice(&unwrap_request(&mute, 0x1253, 1)).unwrap(),
+            MuteMemberRequest {
+                group_uin: 123456,
+                mute_type: 1,
+                body: Some(MuteMemberBody { target_uid: "u_abc".to_string(), duration: 600 }),
+            }
+        );
+
+        let kick = KickMemberService::default()
+            .build(&KickMemberEventReq { group_uin: 123456, uid: "u_abc".to_string(), reject_add_request: true }, context.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            KickMemberRequest::decode_from_slice(&unwrap_request(&kick, 0x8a0, 1)).unwrap(),
+            KickMemberRequest {
+                group_uin: 123456,
+                target_uid: "u_abc".to_string(),
+                reject_add_request: true,
+                reason: String::new(),
+            }
+        );
+
+        let admin = SetAdminService::default()
+            .build(&SetAdminEventReq { group_uin: 123456, uid: "u_abc".to_string(), is_admin: true }, context.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            SetAdminRequest::decode_from_slice(&unwrap_request(&admin, 0x1096, 1)).unwrap(),
+            SetAdminRequest { group_uin: 123456, target_uid: "u_abc".to_string(), is_admin: true }
+        );
+
+        let card = SetMemberCardService::default()
+            .build(&SetMemberCardEventReq { group_uin: 123456, uid: "u_abc".to_string(), card: "Alice".to_string() }, context)
+            .await
+            .unwrap();
+        assert_eq!(
+            SetMemberCardRequest::decode_from_slice(&unwrap_request(&card, 0x8fc, 3)).unwrap(),
+            SetMemberCardRequest {
+                group_uin: 123456,
+                body: Some(SetMemberCardBody { target_uid: "u_abc".to_string(), card: "Alice".to_string() }),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_group_settings_bodies() {
+        let context = BotContext::builder().build();
+
+        let mute_all = MuteAllService::default()
+            .build(&MuteAllEventReq { group_uin: 123456, enable: true }, context.clone())
+            .await
+            .unwrap();
+        let request = GroupSettingsRequest::decode_from_slice(&unwrap_request(&mute_all, 0x89a, 0)).unwrap();
+        assert_eq!(request.settings, Some(GroupSettings { group_name: None, mute_all: Some(u32::MAX) }));
+
+        let unmute_all = MuteAllService::default()
+            .build(&MuteAllEventReq { group_uin: 123456, enable: false }, context.clone())
+            .await
+            .unwrap();
+        let request = GroupSettingsRequest::decode_from_slice(&unwrap_request(&unmute_all, 0x89a, 0)).unwrap();
+        assert_eq!(request.settings, Some(GroupSettings { group_name: None, mute_all: Some(0) }));
+
+        let rename = SetGroupNameService::default()
+            .build(&SetGroupNameEventReq { group_uin: 123456, name: "rustaceans".to_string() }, context)
+            .await
+            .unwrap();
+        let request = GroupSettingsRequest::decode_from_slice(&unwrap_request(&rename, 0x89a, 15)).unwrap();
+        assert_eq!(request.group_uin, 123456);
+        assert_eq!(
+            request.settings,
+            Some(GroupSettings { group_name: Some("rustaceans".to_string()), mute_all: None })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_result_mapping() {
+        let context = BotContext::builder().build();
+
+        assert!(KickMemberService::default().parse(response(0, ""), context.clone()).await.is_ok());
+
+        let denied = KickMemberService::default()
+            .parse(response(GroupAdminError::PERMISSION_DENIED_CODE, "no permission"), context.clone())
+            .await;
+        assert!(matches!(
+            denied,
+            Err(crate::Error::GroupAdmin(GroupAdminError::PermissionDenied { code: GroupAdminError::PERMISSION_DENIED_CODE, ref message }))
+                if message == "no permission"
+        ));
+
+        let not_in_group = MuteMemberService::default()
+            .parse(response(GroupAdminError::NOT_IN_GROUP_CODE, "not in group"), context.clone())
+            .await;
+        assert!(matches!(not_in_group, Err(crate::Error::GroupAdmin(GroupAdminError::NotInGroup { .. }))));
+
+        let other = SetGroupNameService::default().parse(response(1, "busy"), context).await;
+        assert!(matches!(other, Err(crate::Error::GroupAdmin(GroupAdminError::Failed { code: 1, .. }))));
+    }
+}
@@ -0,0 +1,954 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    common::{GroupFileEntry, GroupFolderEntry, GroupFsEntry},
+    context::BotContext,
+    error::GroupFileError,
+    internal::packets::oidb::{
+        GroupFileDeleteRequest, GroupFileDownloadRequest, GroupFileFeed, GroupFileFeedRequest,
+        GroupFileFeedResponse, GroupFileFeeds, GroupFileListItem, GroupFileListQuery, GroupFileListRequest,
+        GroupFileListResponse, GroupFileMoveRequest, GroupFileRenameRequest, GroupFileRequest, GroupFileResponse,
+        GroupFileResult, GroupFileUploadRequest, GroupFolderCreateRequest, GroupFolderDeleteRequest,
+        GroupFolderRenameRequest, GroupFolderRequest, GroupFolderResponse, OidbSvcTrpcTcpBase,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+    utils::common::to_hex,
+};
+
+/// Business id of permanent group files
+pub const GROUP_FILE_BUS_ID: u32 = 102;
+
+/// App id the group file requests are issued under
+const GROUP_FILE_APP_ID: u32 = 4;
+
+/// Name, size and hashes of a file about to be uploaded to a group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupFileUpload {
+    pub name: String,
+    pub size: u64,
+    pub md5: [u8; 16],
+    pub sha1: [u8; 20],
+}
+
+/// Where and how to upload a group file, as granted by the server
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupFileSlot {
+    pub file_id: String,
+    /// The server already has the content, only the feed is left to post
+    pub exists: bool,
+    pub check_key: Vec<u8>,
+    pub file_key: Vec<u8>,
+    pub upload_ip: String,
+    pub upload_port: u32,
+}
+
+define_service! {
+    GroupFileUploadService {
+        command: "OidbSvcTrpcTcp.0x6d6_0",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFileUploadEvent(protocol = Protocols::ALL) {
+                request GroupFileUploadEventReq {
+                    group_uin: u64,
+                    folder_id: String,
+                    file: GroupFileUpload,
+                }
+                response GroupFileUploadEventResp {
+                    slot: GroupFileSlot,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            let response = GroupFileResponse::decode_from_slice(&body)?
+                .upload
+                .ok_or_else(|| crate::error::Error::ParseError("Missing upload result".to_string()))?;
+            check_result(response.ret_code, &response.client_wording, &response.ret_msg)?;
+
+            Ok(EventMessage::new(GroupFileUploadEventResp {
+                slot: GroupFileSlot {
+                    file_id: response.file_id,
+                    exists: response.file_exist,
+                    check_key: response.check_key.unwrap_or_default(),
+                    file_key: response.file_key.unwrap_or_default(),
+                    upload_ip: response.upload_ip,
+                    upload_port: response.upload_port,
+                },
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFileUploadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFileRequest {
+                upload: Some(GroupFileUploadRequest {
+                    group_uin: input.group_uin as u32,
+                    app_id: GROUP_FILE_APP_ID,
+                    bus_id: GROUP_FILE_BUS_ID,
+                    entrance: 6,
+                    parent_folder_id: input.folder_id.clone(),
+                    file_name: input.file.name.clone(),
+                    local_path: format!("/{}", input.file.name),
+                    file_size: input.file.size,
+                    sha1: Some(input.file.sha1.to_vec()),
+                    sha3: Some(Vec::new()),
+                    md5: Some(input.file.md5.to_vec()),
+                    support_multi_upload: true,
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d6, 0, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFileFeedService {
+        command: "OidbSvcTrpcTcp.0x6d9_4",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFileFeedEvent(protocol = Protocols::ALL) {
+                request GroupFileFeedEventReq {
+                    group_uin: u64,
+                    file_id: String,
+                    random: u32,
+                }
+                response GroupFileFeedEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            let response = GroupFileFeedResponse::decode_from_slice(&body)?;
+            if let Some(result) = response.feeds {
+                check_result(result.ret_code, &result.client_wording, &result.ret_msg)?;
+            }
+
+            Ok(EventMessage::new(GroupFileFeedEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFileFeedEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFileFeedRequest {
+                feeds: Some(GroupFileFeeds {
+                    group_uin: input.group_uin,
+                    app_id: GROUP_FILE_APP_ID,
+                    feeds: vec![GroupFileFeed {
+                        bus_id: GROUP_FILE_BUS_ID,
+                        file_id: input.file_id.clone(),
+                        msg_random: input.random,
+                        feed_flag: 1,
+                    }],
+                }),
+            };
+            wrap_request(0x6d9, 4, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFileDownloadService {
+        command: "OidbSvcTrpcTcp.0x6d6_2",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFileDownloadEvent(protocol = Protocols::ALL) {
+                request GroupFileDownloadEventReq {
+                    group_uin: u64,
+                    file_id: String,
+                }
+                response GroupFileDownloadEventResp {
+                    url: String,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            let response = GroupFileResponse::decode_from_slice(&body)?
+                .download
+                .ok_or_else(|| crate::error::Error::ParseError("Missing download result".to_string()))?;
+            check_result(response.ret_code, &response.client_wording, &response.ret_msg)?;
+
+            let host = if response.download_dns.is_empty() { response.download_ip } else { response.download_dns };
+            Ok(EventMessage::new(GroupFileDownloadEventResp {
+                url: format!(
+                    "https://{}/ftn_handler/{}/?fname=",
+                    host,
+                    to_hex(&response.download_url.unwrap_or_default())
+                ),
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFileDownloadEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFileRequest {
+                download: Some(GroupFileDownloadRequest {
+                    group_uin: input.group_uin as u32,
+                    app_id: GROUP_FILE_APP_ID,
+                    bus_id: GROUP_FILE_BUS_ID,
+                    file_id: input.file_id.clone(),
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d6, 2, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFileListService {
+        command: "OidbSvcTrpcTcp.0x6d8_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFileListEvent(protocol = Protocols::ALL) {
+                request GroupFileListEventReq {
+                    group_uin: u64,
+                    folder_id: String,
+                    start_index: u32,
+                    count: u32,
+                }
+                response GroupFileListEventResp {
+                    entries: Vec<GroupFsEntry>,
+                    is_end: bool,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            let response = GroupFileListResponse::decode_from_slice(&body)?
+                .list
+                .ok_or_else(|| crate::error::Error::ParseError("Missing list result".to_string()))?;
+            check_result(response.ret_code, &response.client_wording, &response.ret_msg)?;
+
+            Ok(EventMessage::new(GroupFileListEventResp {
+                entries: response.items.into_iter().filter_map(parse_list_item).collect(),
+                is_end: response.is_end,
+            }))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFileListEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFileListRequest {
+                list: Some(GroupFileListQuery {
+                    group_uin: input.group_uin as u32,
+                    app_id: GROUP_FILE_APP_ID,
+                    folder_id: input.folder_id.clone(),
+                    file_count: input.count,
+                    sort_by: 1,
+                    start_index: input.start_index,
+                    field17: 2,
+                    field18: 0,
+                }),
+            };
+            wrap_request(0x6d8, 1, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFolderCreateService {
+        command: "OidbSvcTrpcTcp.0x6d7_0",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFolderCreateEvent(protocol = Protocols::ALL) {
+                request GroupFolderCreateEventReq {
+                    group_uin: u64,
+                    parent_folder_id: String,
+                    name: String,
+                }
+                response GroupFolderCreateEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            check_operation(GroupFolderResponse::decode_from_slice(&body)?.create)?;
+            Ok(EventMessage::new(GroupFolderCreateEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFolderCreateEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFolderRequest {
+                create: Some(GroupFolderCreateRequest {
+                    group_uin: input.group_uin as u32,
+                    parent_folder_id: input.parent_folder_id.clone(),
+                    folder_name: input.name.clone(),
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d7, 0, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFolderDeleteService {
+        command: "OidbSvcTrpcTcp.0x6d7_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFolderDeleteEvent(protocol = Protocols::ALL) {
+                request GroupFolderDeleteEventReq {
+                    group_uin: u64,
+                    folder_id: String,
+                }
+                response GroupFolderDeleteEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            check_operation(GroupFolderResponse::decode_from_slice(&body)?.delete)?;
+            Ok(EventMessage::new(GroupFolderDeleteEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFolderDeleteEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFolderRequest {
+                delete: Some(GroupFolderDeleteRequest {
+                    group_uin: input.group_uin as u32,
+                    folder_id: input.folder_id.clone(),
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d7, 1, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFolderRenameService {
+        command: "OidbSvcTrpcTcp.0x6d7_2",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFolderRenameEvent(protocol = Protocols::ALL) {
+                request GroupFolderRenameEventReq {
+                    group_uin: u64,
+                    folder_id: String,
+                    new_name: String,
+                }
+                response GroupFolderRenameEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            check_operation(GroupFolderResponse::decode_from_slice(&body)?.rename)?;
+            Ok(EventMessage::new(GroupFolderRenameEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFolderRenameEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFolderRequest {
+                rename: Some(GroupFolderRenameRequest {
+                    group_uin: input.group_uin as u32,
+                    folder_id: input.folder_id.clone(),
+                    new_folder_name: input.new_name.clone(),
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d7, 2, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFileDeleteService {
+        command: "OidbSvcTrpcTcp.0x6d6_3",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFileDeleteEvent(protocol = Protocols::ALL) {
+                request GroupFileDeleteEventReq {
+                    group_uin: u64,
+                    file_id: String,
+                }
+                response GroupFileDeleteEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            check_operation(GroupFileResponse::decode_from_slice(&body)?.delete)?;
+            Ok(EventMessage::new(GroupFileDeleteEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFileDeleteEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFileRequest {
+                delete: Some(GroupFileDeleteRequest {
+                    group_uin: input.group_uin as u32,
+                    bus_id: GROUP_FILE_BUS_ID,
+                    file_id: input.file_id.clone(),
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d6, 3, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFileRenameService {
+        command: "OidbSvcTrpcTcp.0x6d6_4",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFileRenameEvent(protocol = Protocols::ALL) {
+                request GroupFileRenameEventReq {
+                    group_uin: u64,
+                    file_id: String,
+                    folder_id: String,
+                    new_name: String,
+                }
+                response GroupFileRenameEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            check_operation(GroupFileResponse::decode_from_slice(&body)?.rename)?;
+            Ok(EventMessage::new(GroupFileRenameEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFileRenameEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFileRequest {
+                rename: Some(GroupFileRenameRequest {
+                    group_uin: input.group_uin as u32,
+                    bus_id: GROUP_FILE_BUS_ID,
+                    file_id: input.file_id.clone(),
+                    parent_folder_id: input.folder_id.clone(),
+                    new_file_name: input.new_name.clone(),
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d6, 4, &request)
+        }
+    }
+}
+
+define_service! {
+    GroupFileMoveService {
+        command: "OidbSvcTrpcTcp.0x6d6_5",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupFileMoveEvent(protocol = Protocols::ALL) {
+                request GroupFileMoveEventReq {
+                    group_uin: u64,
+                    file_id: String,
+                    folder_id: String,
+                    target_folder_id: String,
+                }
+                response GroupFileMoveEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let body = unwrap_response(&input)?;
+            check_operation(GroupFileResponse::decode_from_slice(&body)?.move_file)?;
+            Ok(EventMessage::new(GroupFileMoveEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupFileMoveEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupFileRequest {
+                move_file: Some(GroupFileMoveRequest {
+                    group_uin: input.group_uin as u32,
+                    app_id: GROUP_FILE_APP_ID,
+                    bus_id: GROUP_FILE_BUS_ID,
+                    file_id: input.file_id.clone(),
+                    parent_folder_id: input.folder_id.clone(),
+                    target_folder_id: input.target_folder_id.clone(),
+                }),
+                ..Default::default()
+            };
+            wrap_request(0x6d6, 5, &request)
+        }
+    }
+}
+
+/// Typed entry of a listing, `None` for entry types other than files and folders
+fn parse_list_item(item: GroupFileListItem) -> Option<GroupFsEntry> {
+    match item.item_type {
+        1 => item.file_info.map(|file| {
+            GroupFsEntry::File(GroupFileEntry {
+                file_id: file.file_id,
+                name: file.file_name,
+                size: file.file_size,
+                bus_id: file.bus_id,
+                folder_id: file.parent_folder_id,
+                uploader_uin: file.uploader_uin,
+                uploader_name: file.uploader_name,
+                upload_time: file.upload_time,
+                expire_time: file.expire_time,
+                modified_time: file.modified_time,
+                download_count: file.download_times,
+            })
+        }),
+        2 => item.folder_info.map(|folder| {
+            GroupFsEntry::Folder(GroupFolderEntry {
+                folder_id: folder.folder_id,
+                parent_folder_id: folder.parent_folder_id,
+                name: folder.folder_name,
+                creator_uin: folder.creator_uin,
+                creator_name: folder.creator_name,
+                create_time: folder.create_time,
+                modified_time: folder.modified_time,
+                file_count: folder.total_file_count,
+            })
+        }),
+        _ => None,
+    }
+}
+
+fn wrap_request<T: ProtoMessage>(command: u32, sub_command: u32, body: &T) -> crate::error::Result<Bytes> {
+    let oidb = OidbSvcTrpcTcpBase {
+        command,
+        sub_command,
+        body: Some(
+            body.encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+        ),
+        ..Default::default()
+    };
+
+    let data = oidb
+        .encode_to_vec()
+        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+    Ok(Bytes::from(data))
+}
+
+fn unwrap_response(input: &[u8]) -> crate::error::Result<Vec<u8>> {
+    let oidb = OidbSvcTrpcTcpBase::decode_from_slice(input)?;
+    if let Some(code @ 1..) = oidb.error_code {
+        check_result(code as u64, "", &oidb.error_msg.unwrap_or_default())?;
+    }
+    Ok(oidb.body.unwrap_or_default())
+}
+
+/// Map a result code, preferring the wording meant for users over the internal message
+fn check_result(ret_code: u64, client_wording: &str, ret_msg: &str) -> crate::error::Result<()> {
+    let message = if client_wording.is_empty() { ret_msg } else { client_wording };
+    match GroupFileError::from_result(ret_code as i32, message) {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+/// Check the result of an operation that returns nothing else
+fn check_operation(result: Option<GroupFileResult>) -> crate::error::Result<()> {
+    let result = result.ok_or_else(|| crate::error::Error::ParseError("Missing operation result".to_string()))?;
+    check_result(result.ret_code, &result.client_wording, &result.ret_msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::{
+        GroupFileDownloadResponse, GroupFileFeedResult, GroupFileUploadResponse,
+    };
+    use crate::common::{GroupFileEntry, GroupFolderEntry};
+    use crate::protocol::TypedService;
+
+    fn unwrap_request(bytes: &[u8], command: u32, sub_command: u32) -> Vec<u8> {
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (command, sub_command));
+        oidb.body.unwrap()
+    }
+
+    fn upload_response(upload: GroupFileUploadResponse) -> Bytes {
+        let response = GroupFileResponse { upload: Some(upload), ..Default::default() };
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x6d6,
+            body: Some(response.encode_to_vec().unwrap()),
+            ..Default::default()
+        };
+        Bytes::from(oidb.encode_to_vec().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_build_upload_request() {
+        let request = GroupFileUploadEventReq {
+            group_uin: 123456,
+            folder_id: "/".to_string(),
+            file: GroupFileUpload { name: "notes.txt".to_string(), size: 10, md5: [1; 16], sha1: [2; 20] },
+        };
+        let bytes = GroupFileUploadService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let upload = GroupFileRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d6, 0))
+            .unwrap()
+            .upload
+            .unwrap();
+        assert_eq!((upload.group_uin, upload.bus_id, upload.app_id), (123456, 102, 4));
+        assert_eq!((upload.parent_folder_id.as_str(), upload.file_name.as_str()), ("/", "notes.txt"));
+        assert_eq!(upload.local_path, "/notes.txt");
+        assert_eq!(upload.file_size, 10);
+        assert_eq!(upload.md5, Some(vec![1; 16]));
+        assert_eq!(upload.sha1, Some(vec![2; 20]));
+    }
+
+    #[tokio::test]
+    async fn test_parse_upload_slot() {
+        let response = upload_response(GroupFileUploadResponse {
+            file_id: "/abc-123".to_string(),
+            check_key: Some(vec![3; 4]),
+            file_key: Some(vec![4; 4]),
+            upload_ip: "10.0.0.1".to_string(),
+            upload_port: 443,
+            ..Default::default()
+        });
+        let parsed = GroupFileUploadService::default()
+            .parse(response, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            parsed.slot,
+            GroupFileSlot {
+                file_id: "/abc-123".to_string(),
+                exists: false,
+                check_key: vec![3; 4],
+                file_key: vec![4; 4],
+                upload_ip: "10.0.0.1".to_string(),
+                upload_port: 443,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_errors() {
+        let context = BotContext::builder().build();
+
+        // Negative codes are sign-extended to 64 bits on the wire
+        let quota = upload_response(GroupFileUploadResponse {
+            ret_code: GroupFileError::QUOTA_EXCEEDED_CODE as i64 as u64,
+            ret_msg: "space not enough".to_string(),
+            client_wording: "群空间不足".to_string(),
+            ..Default::default()
+        });
+        let result = GroupFileUploadService::default().parse(quota, context.clone()).await;
+        assert!(matches!(
+            result,
+            Err(crate::Error::GroupFile(GroupFileError::QuotaExceeded { code: -134, ref message })) if message == "群空间不足"
+        ));
+
+        let other = upload_response(GroupFileUploadResponse {
+            ret_code: -1i64 as u64,
+            ret_msg: "busy".to_string(),
+            ..Default::default()
+        });
+        let result = GroupFileUploadService::default().parse(other, context).await;
+        assert!(matches!(
+            result,
+            Err(crate::Error::GroupFile(GroupFileError::Failed { code: -1, ref message })) if message == "busy"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_feed_request() {
+        let request = GroupFileFeedEventReq { group_uin: 123456, file_id: "/abc-123".to_string(), random: 7 };
+        let bytes = GroupFileFeedService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let feeds = GroupFileFeedRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d9, 4))
+            .unwrap()
+            .feeds
+            .unwrap();
+        assert_eq!(feeds.group_uin, 123456);
+        assert_eq!(
+            feeds.feeds,
+            vec![GroupFileFeed { bus_id: 102, file_id: "/abc-123".to_string(), msg_random: 7, feed_flag: 1 }]
+        );
+
+        let response = GroupFileFeedResponse {
+            feeds: Some(GroupFileFeedResult {
+                ret_code: GroupFileError::PERMISSION_DENIED_CODE as i64 as u64,
+                ..Default::default()
+            }),
+        };
+        let oidb = OidbSvcTrpcTcpBase { body: Some(response.encode_to_vec().unwrap()), ..Default::default() };
+        let result = GroupFileFeedService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await;
+        assert!(matches!(result, Err(crate::Error::GroupFile(GroupFileError::PermissionDenied { .. }))));
+    }
+
+    #[tokio::test]
+    async fn test_download_url() {
+        let context = BotContext::builder().build();
+        let request = GroupFileDownloadEventReq { group_uin: 123456, file_id: "/abc-123".to_string() };
+        let bytes = GroupFileDownloadService::default().build(&request, context.clone()).await.unwrap();
+        let download = GroupFileRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d6, 2))
+            .unwrap()
+            .download
+            .unwrap();
+        assert_eq!((download.group_uin, download.bus_id), (123456, 102));
+        assert_eq!(download.file_id, "/abc-123");
+
+        let response = GroupFileResponse {
+            download: Some(GroupFileDownloadResponse {
+                download_ip: "10.0.0.1".to_string(),
+                download_dns: "njc-download.ftn.qq.com".to_string(),
+                download_url: Some(vec![0xab, 0x01, 0xff]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let oidb = OidbSvcTrpcTcpBase { body: Some(response.encode_to_vec().unwrap()), ..Default::default() };
+        let parsed = GroupFileDownloadService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), context)
+            .await
+            .unwrap();
+        assert_eq!(parsed.url, "https://njc-download.ftn.qq.com/ftn_handler/ab01ff/?fname=");
+    }
+
+    /// Last listing page, holding one file and one folder
+    const LIST_RESPONSE: &[u8] = &[
+        0x0a, 0x5a, // list
+        0x20, 0x01, // is_end
+        0x2a, 0x30, // items[0]
+        0x08, 0x01, 0x1a, 0x2c, // item_type 1, file_info
+        0x0a, 0x03, 0x2f, 0x61, 0x31, // file_id "/a1"
+        0x12, 0x09, 0x6e, 0x6f, 0x74, 0x65, 0x73, 0x2e, 0x74, 0x78, 0x74, // file_name "notes.txt"
+        0x18, 0x80, 0x10, // file_size 2048
+        0x20, 0x66, // bus_id 102
+        0x30, 0x80, 0xe2, 0xcf, 0xaa, 0x06, // upload_time 1700000000
+        0x48, 0x03, // download_times 3
+        0x72, 0x06, 0x54, 0x65, 0x73, 0x74, 0x65, 0x72, // uploader_name "Tester"
+        0x78, 0x91, 0x4e, // uploader_uin 10001
+        0x82, 0x01, 0x01, 0x2f, // parent_folder_id "/"
+        0x2a, 0x24, // items[1]
+        0x08, 0x02, 0x12, 0x20, // item_type 2, folder_info
+        0x0a, 0x03, 0x2f, 0x66, 0x31, // folder_id "/f1"
+        0x12, 0x01, 0x2f, // parent_folder_id "/"
+        0x1a, 0x04, 0x64, 0x6f, 0x63, 0x73, // folder_name "docs"
+        0x20, 0x80, 0xe2, 0xcf, 0xaa, 0x06, // create_time 1700000000
+        0x30, 0x92, 0x4e, // creator_uin 10002
+        0x3a, 0x05, 0x41, 0x64, 0x6d, 0x69, 0x6e, // creator_name "Admin"
+        0x40, 0x05, // total_file_count 5
+    ];
+
+    #[tokio::test]
+    async fn test_parse_listing() {
+        let oidb = OidbSvcTrpcTcpBase {
+            command: 0x6d8,
+            sub_command: 1,
+            body: Some(LIST_RESPONSE.to_vec()),
+            ..Default::default()
+        };
+        let parsed = GroupFileListService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+
+        assert!(parsed.is_end);
+        assert_eq!(
+            parsed.entries,
+            vec![
+                GroupFsEntry::File(GroupFileEntry {
+                    file_id: "/a1".to_string(),
+                    name: "notes.txt".to_string(),
+                    size: 2048,
+                    bus_id: 102,
+                    folder_id: "/".to_string(),
+                    uploader_uin: 10001,
+                    uploader_name: "Tester".to_string(),
+                    upload_time: 1700000000,
+                    expire_time: 0,
+                    modified_time: 0,
+                    download_count: 3,
+                }),
+                GroupFsEntry::Folder(GroupFolderEntry {
+                    folder_id: "/f1".to_string(),
+                    parent_folder_id: "/".to_string(),
+                    name: "docs".to_string(),
+                    creator_uin: 10002,
+                    creator_name: "Admin".to_string(),
+                    create_time: 1700000000,
+                    modified_time: 0,
+                    file_count: 5,
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_list_request() {
+        let request =
+            GroupFileListEventReq { group_uin: 123456, folder_id: "/f1".to_string(), start_index: 100, count: 100 };
+        let bytes = GroupFileListService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+
+        let list = GroupFileListRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d8, 1))
+            .unwrap()
+            .list
+            .unwrap();
+        assert_eq!((list.group_uin, list.app_id, list.folder_id.as_str()), (123456, 4, "/f1"));
+        assert_eq!((list.start_index, list.file_count), (100, 100));
+    }
+
+    #[tokio::test]
+    async fn test_build_folder_requests() {
+        let context = BotContext::builder().build();
+
+        let request = GroupFolderCreateEventReq {
+            group_uin: 123456,
+            parent_folder_id: "/".to_string(),
+            name: "docs".to_string(),
+        };
+        let bytes = GroupFolderCreateService::default().build(&request, context.clone()).await.unwrap();
+        assert_eq!(
+            GroupFolderRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d7, 0)).unwrap().create,
+            Some(GroupFolderCreateRequest {
+                group_uin: 123456,
+                parent_folder_id: "/".to_string(),
+                folder_name: "docs".to_string(),
+            })
+        );
+
+        let request = GroupFolderRenameEventReq {
+            group_uin: 123456,
+            folder_id: "/f1".to_string(),
+            new_name: "papers".to_string(),
+        };
+        let bytes = GroupFolderRenameService::default().build(&request, context.clone()).await.unwrap();
+        assert_eq!(
+            GroupFolderRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d7, 2)).unwrap().rename,
+            Some(GroupFolderRenameRequest {
+                group_uin: 123456,
+                folder_id: "/f1".to_string(),
+                new_folder_name: "papers".to_string(),
+            })
+        );
+
+        let request = GroupFolderDeleteEventReq { group_uin: 123456, folder_id: "/f1".to_string() };
+        let bytes = GroupFolderDeleteService::default().build(&request, context).await.unwrap();
+        assert_eq!(
+            GroupFolderRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d7, 1)).unwrap().delete,
+            Some(GroupFolderDeleteRequest { group_uin: 123456, folder_id: "/f1".to_string() })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_file_requests() {
+        let context = BotContext::builder().build();
+
+        let request = GroupFileRenameEventReq {
+            group_uin: 123456,
+            file_id: "/a1".to_string(),
+            folder_id: "/".to_string(),
+            new_name: "minutes.txt".to_string(),
+        };
+        let bytes = GroupFileRenameService::default().build(&request, context.clone()).await.unwrap();
+        assert_eq!(
+            GroupFileRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d6, 4)).unwrap().rename,
+            Some(GroupFileRenameRequest {
+                group_uin: 123456,
+                bus_id: 102,
+                file_id: "/a1".to_string(),
+                parent_folder_id: "/".to_string(),
+                new_file_name: "minutes.txt".to_string(),
+            })
+        );
+
+        let request = GroupFileMoveEventReq {
+            group_uin: 123456,
+            file_id: "/a1".to_string(),
+            folder_id: "/".to_string(),
+            target_folder_id: "/f1".to_string(),
+        };
+        let bytes = GroupFileMoveService::default().build(&request, context.clone()).await.unwrap();
+        assert_eq!(
+            GroupFileRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d6, 5)).unwrap().move_file,
+            Some(GroupFileMoveRequest {
+                group_uin: 123456,
+                app_id: 4,
+                bus_id: 102,
+                file_id: "/a1".to_string(),
+                parent_folder_id: "/".to_string(),
+                target_folder_id: "/f1".to_string(),
+            })
+        );
+
+        let request = GroupFileDeleteEventReq { group_uin: 123456, file_id: "/a1".to_string() };
+        let bytes = GroupFileDeleteService::default().build(&request, context).await.unwrap();
+        assert_eq!(
+            GroupFileRequest::decode_from_slice(&unwrap_request(&bytes, 0x6d6, 3)).unwrap().delete,
+            Some(GroupFileDeleteRequest { group_uin: 123456, bus_id: 102, file_id: "/a1".to_string() })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_operation_errors() {
+        let response = GroupFolderResponse {
+            create: Some(GroupFileResult {
+                ret_code: GroupFileError::NAME_CONFLICT_CODE as i64 as u64,
+                client_wording: "folder exists".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let oidb = OidbSvcTrpcTcpBase { body: Some(response.encode_to_vec().unwrap()), ..Default::default() };
+        let result = GroupFolderCreateService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await;
+        assert!(matches!(result, Err(crate::Error::GroupFile(GroupFileError::NameConflict { code: -304, .. }))));
+
+        // A response without the result of the operation is malformed
+        let oidb = OidbSvcTrpcTcpBase { body: Some(Vec::new()), ..Default::default() };
+        let result = GroupFileDeleteService::default()
+            .parse(Bytes::from(oidb.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await;
+        assert!(matches!(result, Err(crate::Error::ParseError(_))));
+    }
+}
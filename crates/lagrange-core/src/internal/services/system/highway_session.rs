@@ -0,0 +1,121 @@
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    context::BotContext,
+    internal::context::HighwaySession,
+    internal::packets::highway::{
+        HighwaySessionRequest, HighwaySessionRequestBody, HighwaySessionResponse,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+/// `service_type` of the servers accepting uploads
+const UPLOAD_SERVICE_TYPE: u32 = 1;
+
+define_service! {
+    FetchHighwaySessionService {
+        command: "HttpConn.0x6ff_501",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FetchHighwaySessionEvent(protocol = Protocols::ALL) {
+                request FetchHighwaySessionEventReq {}
+                response FetchHighwaySessionEventResp {
+                    session: HighwaySession,
+                }
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let response = HighwaySessionResponse::decode_from_slice(&input)?;
+            let body = response
+                .body
+                .ok_or_else(|| crate::error::Error::ProtocolError("Highway session response is empty".to_string()))?;
+
+            let servers = body
+                .server_lists
+                .into_iter()
+                .filter(|list| list.service_type == UPLOAD_SERVICE_TYPE)
+                .flat_map(|list| list.addrs)
+                .map(|addr| format!("{}:{}", Ipv4Addr::from(addr.ip.0.to_le_bytes()), addr.port))
+                .collect();
+
+            Ok(EventMessage::new(FetchHighwaySessionEventResp {
+                session: HighwaySession {
+                    sig_session: body.sig_session.unwrap_or_default(),
+                    session_key: body.session_key.unwrap_or_default(),
+                    servers,
+                },
+            }))
+        }
+
+        async fn build(_event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let request = HighwaySessionRequest {
+                body: Some(HighwaySessionRequestBody {
+                    uin: 0,
+                    idc_id: 0,
+                    app_id: 16,
+                    login_sig_type: 1,
+                    request_flag: 3,
+                    service_types: vec![1, 5, 10, 21],
+                    field9: 2,
+                    field10: 9,
+                    field11: 8,
+                    version: "1.0.1".to_string(),
+                }),
+            };
+
+            let data = request
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::highway::{HighwayAddr, HighwayServerList, HighwaySessionResponseBody};
+    use crate::protocol::TypedService;
+    use lagrange_proto::Fixed32;
+
+    #[tokio::test]
+    async fn test_parse_upload_servers() {
+        let addr = |ip: [u8; 4], port| HighwayAddr {
+            ip_type: 0,
+            ip: Fixed32(u32::from_le_bytes(ip)),
+            port,
+            area: None,
+        };
+        let response = HighwaySessionResponse {
+            body: Some(HighwaySessionResponseBody {
+                sig_session: Some(vec![1, 2, 3]),
+                session_key: Some(vec![4, 5]),
+                server_lists: vec![
+                    HighwayServerList { service_type: 1, addrs: vec![addr([1, 2, 3, 4], 80), addr([5, 6, 7, 8], 443)] },
+                    HighwayServerList { service_type: 5, addrs: vec![addr([9, 9, 9, 9], 8080)] },
+                ],
+            }),
+        };
+
+        let parsed = FetchHighwaySessionService::default()
+            .parse(Bytes::from(response.encode_to_vec().unwrap()), BotContext::builder().build())
+            .await
+            .unwrap();
+        assert_eq!(
+            parsed.session,
+            HighwaySession {
+                sig_session: vec![1, 2, 3],
+                session_key: vec![4, 5],
+                servers: vec!["1.2.3.4:80".to_string(), "5.6.7.8:443".to_string()],
+            }
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    context::BotContext,
+    internal::packets::oidb::{OidbSvcTrpcTcpBase, PokeRequest},
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    SendPokeService {
+        command: "OidbSvcTrpcTcp.0xed3_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            SendPokeEvent(protocol = Protocols::ALL) {
+                request SendPokeEventReq {
+                    uin: u64,
+                    group_uin: Option<u64>,
+                }
+                response SendPokeEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Sending poke failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            Ok(EventMessage::new(SendPokeEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<SendPokeEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = PokeRequest {
+                uin: input.uin as u32,
+                group_uin: input.group_uin.map(|group_uin| group_uin as u32),
+                friend_uin: match input.group_uin {
+                    Some(_) => None,
+                    None => Some(input.uin as u32),
+                },
+                ext: 0,
+            };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0xed3,
+                sub_command: 1,
+                body: Some(
+                    request
+                        .encode_to_vec()
+                        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+                ),
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+
+    async fn build(request: SendPokeEventReq) -> PokeRequest {
+        let bytes = SendPokeService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0xed3, 1));
+        PokeRequest::decode_from_slice(&oidb.body.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_poke_targets() {
+        assert_eq!(
+            build(SendPokeEventReq { uin: 10001, group_uin: Some(123456) }).await,
+            PokeRequest { uin: 10001, group_uin: Some(123456), friend_uin: None, ext: 0 }
+        );
+        assert_eq!(
+            build(SendPokeEventReq { uin: 10001, group_uin: None }).await,
+            PokeRequest { uin: 10001, group_uin: None, friend_uin: Some(10001), ext: 0 }
+        );
+    }
+}
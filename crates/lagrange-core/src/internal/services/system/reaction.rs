@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+
+use crate::{
+    common::EmojiId,
+    context::BotContext,
+    internal::packets::oidb::{build_oidb, unwrap_oidb, ReactionRequest},
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    AddReactionService {
+        command: "OidbSvcTrpcTcp.0x9082_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            AddReactionEvent(protocol = Protocols::ALL) {
+                request AddReactionEventReq {
+                    group_uin: u64,
+                    sequence: u32,
+                    emoji_id: EmojiId,
+                }
+                response AddReactionEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            unwrap_oidb(&input)?;
+            Ok(EventMessage::new(AddReactionEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<AddReactionEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            wrap_request(1, input.group_uin, input.sequence, input.emoji_id)
+        }
+    }
+}
+
+define_service! {
+    RemoveReactionService {
+        command: "OidbSvcTrpcTcp.0x9082_2",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            RemoveReactionEvent(protocol = Protocols::ALL) {
+                request RemoveReactionEventReq {
+                    group_uin: u64,
+                    sequence: u32,
+                    emoji_id: EmojiId,
+                }
+                response RemoveReactionEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            unwrap_oidb(&input)?;
+            Ok(EventMessage::new(RemoveReactionEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<RemoveReactionEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            wrap_request(2, input.group_uin, input.sequence, input.emoji_id)
+        }
+    }
+}
+
+/// Wrap a reaction request in the OIDB envelope
+fn wrap_request(sub_command: u32, group_uin: u64, sequence: u32, emoji_id: EmojiId) -> crate::error::Result<Bytes> {
+    let request = ReactionRequest {
+        group_uin: group_uin as u32,
+        sequence,
+        code: emoji_id.code(),
+        emoji_type: if emoji_id.is_emoji() { ReactionRequest::EMOJI } else { ReactionRequest::FACE },
+    };
+    build_oidb(0x9082, sub_command, &request, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::packets::oidb::OidbSvcTrpcTcpBase;
+    use crate::protocol::TypedService;
+    use lagrange_proto::ProtoMessage;
+
+    #[tokio::test]
+    async fn test_request_bodies() {
+        let context = BotContext::builder().build();
+
+        let add = AddReactionService::default()
+            .build(
+                &AddReactionEventReq { group_uin: 123456, sequence: 777, emoji_id: EmojiId::THUMBS_UP },
+                context.clone(),
+            )
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&add).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x9082, 1));
+        // Group 123456, sequence 777, face "76" of type 1
+        assert_eq!(
+            oidb.body.unwrap(),
+            [0x10, 0xC0, 0xC4, 0x07, 0x18, 0x89, 0x06, 0x22, 0x02, b'7', b'6', 0x28, 0x01]
+        );
+
+        let remove = RemoveReactionService::default()
+            .build(&RemoveReactionEventReq { group_uin: 123456, sequence: 777, emoji_id: EmojiId::FIRE }, context)
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&remove).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x9082, 2));
+        assert_eq!(
+            ReactionRequest::decode_from_slice(&oidb.body.unwrap()).unwrap(),
+            ReactionRequest {
+                group_uin: 123456,
+                sequence: 777,
+                code: "128293".to_string(),
+                emoji_type: ReactionRequest::EMOJI,
+            }
+        );
+    }
+}
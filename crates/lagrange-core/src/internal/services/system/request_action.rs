@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use lagrange_macros::define_service;
+use lagrange_proto::ProtoMessage;
+
+use crate::{
+    context::BotContext,
+    internal::packets::oidb::{
+        FriendRequestActionRequest, GroupRequestActionBody, GroupRequestActionRequest,
+        OidbSvcTrpcTcpBase,
+    },
+    protocol::{EncryptType, EventMessage, Protocols, RequestType},
+};
+
+define_service! {
+    FriendRequestActionService {
+        command: "OidbSvcTrpcTcp.0xb5d_44",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            FriendRequestActionEvent(protocol = Protocols::ALL) {
+                request FriendRequestActionEventReq {
+                    uid: String,
+                    accept: bool,
+                }
+                response FriendRequestActionEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Answering friend request failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            Ok(EventMessage::new(FriendRequestActionEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<FriendRequestActionEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = FriendRequestActionRequest {
+                accept: if input.accept { 3 } else { 5 },
+                target_uid: input.uid.clone(),
+            };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0xb5d,
+                sub_command: 44,
+                body: Some(
+                    request
+                        .encode_to_vec()
+                        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+                ),
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+define_service! {
+    GroupRequestActionService {
+        command: "OidbSvcTrpcTcp.0x10c8_1",
+        request_type: RequestType::D2Auth,
+        encrypt_type: EncryptType::EncryptD2Key,
+
+        events {
+            GroupRequestActionEvent(protocol = Protocols::ALL) {
+                request GroupRequestActionEventReq {
+                    group_uin: u64,
+                    sequence: u64,
+                    is_invitation: bool,
+                    accept: bool,
+                    reason: String,
+                }
+                response GroupRequestActionEventResp {}
+            }
+        }
+
+        async fn parse(input: Bytes, _context: Arc<BotContext>) -> Result<EventMessage> {
+            let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&input)?;
+            if let Some(code @ 1..) = oidb.error_code {
+                return Err(crate::error::Error::ProtocolError(format!(
+                    "Answering group request failed ({}): {}",
+                    code,
+                    oidb.error_msg.unwrap_or_default()
+                )));
+            }
+
+            Ok(EventMessage::new(GroupRequestActionEventResp {}))
+        }
+
+        async fn build(event: EventMessage, _context: Arc<BotContext>) -> Result<Bytes> {
+            let input = event.downcast_ref::<GroupRequestActionEventReq>()
+                .ok_or_else(|| crate::error::Error::BuildError("Invalid event type".to_string()))?;
+
+            let request = GroupRequestActionRequest {
+                accept: if input.accept { 1 } else { 2 },
+                body: Some(GroupRequestActionBody {
+                    sequence: input.sequence,
+                    event_type: if input.is_invitation { 22 } else { 1 },
+                    group_uin: input.group_uin as u32,
+                    message: input.reason.clone(),
+                }),
+            };
+
+            let oidb = OidbSvcTrpcTcpBase {
+                command: 0x10c8,
+                sub_command: 1,
+                body: Some(
+                    request
+                        .encode_to_vec()
+                        .map_err(|e| crate::error::Error::BuildError(e.to_string()))?,
+                ),
+                ..Default::default()
+            };
+
+            let data = oidb
+                .encode_to_vec()
+                .map_err(|e| crate::error::Error::BuildError(e.to_string()))?;
+            Ok(Bytes::from(data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::TypedService;
+
+    #[tokio::test]
+    async fn test_friend_request_action() {
+        let context = BotContext::builder().build();
+
+        let accept = FriendRequestActionService::default()
+            .build(&FriendRequestActionEventReq { uid: "u_abc".to_string(), accept: true }, context.clone())
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&accept).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0xb5d, 44));
+        assert_eq!(
+            FriendRequestActionRequest::decode_from_slice(&oidb.body.unwrap()).unwrap(),
+            FriendRequestActionRequest { accept: 3, target_uid: "u_abc".to_string() }
+        );
+
+        let decline = FriendRequestActionService::default()
+            .build(&FriendRequestActionEventReq { uid: "u_abc".to_string(), accept: false }, context)
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&decline).unwrap();
+        assert_eq!(FriendRequestActionRequest::decode_from_slice(&oidb.body.unwrap()).unwrap().accept, 5);
+    }
+
+    #[tokio::test]
+    async fn test_group_request_action() {
+        let request = GroupRequestActionEventReq {
+            group_uin: 123456,
+            sequence: 1700000000123,
+            is_invitation: true,
+            accept: false,
+            reason: "full".to_string(),
+        };
+
+        let bytes = GroupRequestActionService::default()
+            .build(&request, BotContext::builder().build())
+            .await
+            .unwrap();
+        let oidb = OidbSvcTrpcTcpBase::decode_from_slice(&bytes).unwrap();
+        assert_eq!((oidb.command, oidb.sub_command), (0x10c8, 1));
+        assert_eq!(
+            GroupRequestActionRequest::decode_from_slice(&oidb.body.unwrap()).unwrap(),
+            GroupRequestActionRequest {
+                accept: 2,
+                body: Some(GroupRequestActionBody {
+                    sequence: 1700000000123,
+                    event_type: 22,
+                    group_uin: 123456,
+                    message: "full".to_string(),
+                }),
+            }
+        );
+    }
+}
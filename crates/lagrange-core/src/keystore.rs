@@ -1,7 +1,15 @@
+use crate::error::Error;
+use crate::internal::packets::login::tlv119::SessionKeys;
+use crate::protocol::Protocols;
+use crate::utils::common::to_hex;
+use crate::utils::redact::{redact_values, Redacted};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Session tickets and keys; [`Debug`] shows them [`Redacted`]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct WLoginSigs {
     #[serde(with = "serde_bytes")]
     pub a2: Vec<u8>,
@@ -29,6 +37,10 @@ pub struct WLoginSigs {
 
     #[serde(default)]
     pub ps_key: std::collections::HashMap<String, Vec<u8>>,
+
+    /// Unix timestamp (seconds) at which the A2/D2 tickets expire, if known
+    #[serde(default)]
+    pub expires_at: Option<i64>,
 }
 
 impl Default for WLoginSigs {
@@ -51,10 +63,36 @@ impl Default for WLoginSigs {
             s_key: None,
             no_pic_sig: None,
             ps_key: Default::default(),
+            expires_at: None,
         }
     }
 }
 
+impl fmt::Debug for WLoginSigs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WLoginSigs")
+            .field("a2", &Redacted(&self.a2))
+            .field("a2_key", &Redacted(&self.a2_key))
+            .field("d2", &Redacted(&self.d2))
+            .field("d2_key", &Redacted(&self.d2_key))
+            .field("a1", &Redacted(&self.a1))
+            .field("tgtgt_key", &Redacted(&self.tgtgt_key))
+            .field("ksid", &self.ksid.as_ref().map(Redacted))
+            .field("super_key", &self.super_key.as_ref().map(Redacted))
+            .field("st_key", &self.st_key.as_ref().map(Redacted))
+            .field("st_web", &self.st_web.as_ref().map(Redacted))
+            .field("st", &self.st.as_ref().map(Redacted))
+            .field("wt_session_ticket", &self.wt_session_ticket.as_ref().map(Redacted))
+            .field("wt_session_ticket_key", &self.wt_session_ticket_key.as_ref().map(Redacted))
+            .field("random_key", &Redacted(&self.random_key))
+            .field("s_key", &self.s_key.as_ref().map(Redacted))
+            .field("no_pic_sig", &self.no_pic_sig.as_ref().map(Redacted))
+            .field("ps_key", &redact_values(&self.ps_key))
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
 impl WLoginSigs {
     fn generate_random_key() -> Vec<u8> {
         use rand::Rng;
@@ -68,18 +106,69 @@ impl WLoginSigs {
         self.a1 = vec![0; 16];
         self.random_key = Self::generate_random_key();
         self.ps_key.clear();
+        self.expires_at = None;
+    }
+
+    /// Applies the decrypted contents of TLV 0x119 to the signatures, see
+    /// [`WLoginSigs::apply_session_keys`]
+    pub fn apply_tlvs(&mut self, tlvs: &HashMap<u16, Vec<u8>>, now: i64) {
+        self.apply_session_keys(&SessionKeys::from_tlvs(tlvs), now);
+    }
+
+    /// Applies the session keys of a login or a ticket refresh to the signatures.
+    ///
+    /// Keys missing from `keys` keep their previous value. The expiry is
+    /// recomputed from the ticket lifetime relative to `now`, or reset to
+    /// unknown when the server did not send lifetimes.
+    pub fn apply_session_keys(&mut self, keys: &SessionKeys, now: i64) {
+        let replace = |target: &mut Vec<u8>, value: &Option<Vec<u8>>| {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
+        };
+        replace(&mut self.a1, &keys.a1);
+        replace(&mut self.a2, &keys.a2);
+        replace(&mut self.a2_key, &keys.a2_key);
+        replace(&mut self.d2, &keys.d2);
+        replace(&mut self.d2_key, &keys.d2_key);
+
+        let optional = [
+            (&mut self.ksid, &keys.ksid),
+            (&mut self.super_key, &keys.super_key),
+            (&mut self.st_key, &keys.st_key),
+            (&mut self.st_web, &keys.st_web),
+            (&mut self.st, &keys.st),
+            (&mut self.s_key, &keys.s_key),
+            (&mut self.wt_session_ticket, &keys.wt_session_ticket),
+            (&mut self.wt_session_ticket_key, &keys.wt_session_ticket_key),
+            (&mut self.no_pic_sig, &keys.no_pic_sig),
+        ];
+        for (target, value) in optional {
+            if value.is_some() {
+                *target = value.clone();
+            }
+        }
+        self.ps_key.extend(keys.ps_key.iter().map(|(domain, key)| (domain.clone(), key.clone())));
+
+        self.expires_at = keys.lifetime.map(|lifetime| now + lifetime as i64);
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Keys and credentials of the current session; [`Debug`] shows them [`Redacted`]
+#[derive(Clone, Serialize, Deserialize, Default)]
 pub struct SessionState {
     #[serde(skip)]
     pub exchange_key: Option<Vec<u8>>,
+    /// Web credentials by domain
     #[serde(default)]
-    pub cookies: std::collections::HashMap<String, Vec<u8>>,
+    pub cookies: crate::common::CookieJar,
     pub qr_sig: Option<Vec<u8>>,
     #[serde(default)]
     pub tlv_cache: std::collections::HashMap<u16, Vec<u8>>,
+    /// Status chosen with [`BotContext::set_online_status`](crate::BotContext::set_online_status),
+    /// set again after each login
+    #[serde(default)]
+    pub online_status: Option<crate::common::OnlineStatus>,
 
     #[serde(skip)]
     pub ecdh_secret: Option<Vec<u8>>,
@@ -87,7 +176,33 @@ pub struct SessionState {
     pub share_key: Option<Vec<u8>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl fmt::Debug for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionState")
+            .field("exchange_key", &self.exchange_key.as_ref().map(Redacted))
+            .field("cookies", &self.cookies)
+            .field("qr_sig", &self.qr_sig.as_ref().map(Redacted))
+            .field("tlv_cache", &redact_values(&self.tlv_cache))
+            .field("online_status", &self.online_status)
+            .field("ecdh_secret", &self.ecdh_secret.as_ref().map(Redacted))
+            .field("share_key", &self.share_key.as_ref().map(Redacted))
+            .finish()
+    }
+}
+
+impl SessionState {
+    /// Drop what only the current connection needs, keeping the web cookies and cached TLVs
+    pub fn clear_volatile(&mut self) {
+        self.exchange_key = None;
+        self.qr_sig = None;
+        self.ecdh_secret = None;
+        self.share_key = None;
+    }
+}
+
+/// Everything needed to resume a session; [`Debug`] keeps the secrets [`Redacted`], but the
+/// keystore must still be stored like a password
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BotKeystore {
     pub uin: Option<u64>,
     pub uid: Option<String>,
@@ -107,6 +222,22 @@ pub struct BotKeystore {
     pub state: SessionState,
 }
 
+impl fmt::Debug for BotKeystore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BotKeystore")
+            .field("uin", &self.uin)
+            .field("uid", &self.uid)
+            .field("bot_info", &self.bot_info)
+            .field("guid", &Redacted(&self.guid))
+            .field("android_id", &self.android_id)
+            .field("qimei", &self.qimei)
+            .field("device_name", &self.device_name)
+            .field("sigs", &self.sigs)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
 fn default_guid() -> Vec<u8> {
     vec![0; 16]
 }
@@ -128,6 +259,13 @@ impl Default for BotKeystore {
 }
 
 impl BotKeystore {
+    /// [`Debug`](fmt::Debug) showing the keys and tickets in full instead of [`Redacted`], to
+    /// compare a keystore against another client while debugging locally. Never log it.
+    #[cfg(feature = "debug-secrets")]
+    pub fn debug_full(&self) -> crate::utils::Unredacted<&Self> {
+        crate::utils::Unredacted(self)
+    }
+
     pub fn new() -> Self {
         let mut ks = Self::default();
         rand::thread_rng().fill_bytes(&mut ks.guid);
@@ -137,6 +275,36 @@ impl BotKeystore {
         ks
     }
 
+    /// A new keystore with a generated device for `protocol`; Android protocols also get an
+    /// Android ID
+    pub fn for_protocol(protocol: Protocols) -> Self {
+        let mut ks = Self::new();
+        if protocol.is_android() {
+            let mut android_id = [0u8; 8];
+            rand::thread_rng().fill_bytes(&mut android_id);
+            ks.android_id = to_hex(&android_id);
+        }
+
+        ks
+    }
+
+    /// Check that the device of the keystore can log in as `protocol`
+    pub fn validate(&self, protocol: Protocols) -> Result<(), Error> {
+        if self.guid.len() != 16 {
+            return Err(Error::InvalidKeystore(format!("the guid has {} bytes instead of 16", self.guid.len())));
+        }
+        if protocol.is_android() && self.android_id.is_empty() {
+            return Err(Error::InvalidKeystore(format!("{} needs the Android ID of the device", protocol)));
+        }
+        if protocol.is_pc() && !self.android_id.is_empty() {
+            return Err(Error::InvalidKeystore(format!(
+                "the keystore belongs to an Android device, not to {}",
+                protocol
+            )));
+        }
+        Ok(())
+    }
+
     pub fn with_uin(mut self, uin: u64) -> Self {
         self.uin = Some(uin);
         self
@@ -163,3 +331,74 @@ impl BotKeystore {
         self.state = SessionState::default();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::CookieSet;
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let mut keystore = BotKeystore::new().with_uin(10000);
+        keystore.sigs.d2_key = vec![0xd2; 16];
+        keystore.sigs.a2 = vec![0xa2; 64];
+        keystore.sigs.random_key = vec![0x5e; 16];
+        keystore.sigs.st_key = Some(vec![0x57; 16]);
+        keystore.sigs.ps_key.insert("qun.qq.com".to_string(), vec![0x95; 44]);
+        keystore.state.share_key = Some(vec![0x54; 16]);
+        keystore.state.tlv_cache.insert(0x106, vec![0x06; 32]);
+        keystore.state.cookies.set(
+            "qun.qq.com",
+            CookieSet {
+                skey: "@SkeySecret".to_string(),
+                pskey: "PskeySecretPskeySecret".to_string(),
+                expires_at: 0,
+            },
+        );
+
+        let debug = format!("{:?}", keystore);
+        let secrets = [
+            to_hex(&keystore.sigs.d2_key),
+            to_hex(&keystore.sigs.a2),
+            to_hex(&keystore.sigs.random_key),
+            to_hex(&keystore.sigs.tgtgt_key),
+            to_hex(&[0x57; 16]),
+            to_hex(&[0x95; 44]),
+            to_hex(&[0x54; 16]),
+            to_hex(&[0x06; 32]),
+        ];
+        for secret in secrets {
+            assert!(!debug.contains(&secret), "{} leaked in {}", secret, debug);
+        }
+        // Neither as a list of bytes
+        assert!(!debug.contains("210, 210, 210"));
+        assert!(!debug.contains("SkeySecret"));
+        assert!(!debug.contains("PskeySecret"));
+
+        assert!(debug.contains("uin: Some(10000)"));
+        assert!(debug.contains("d2_key: <16 bytes d2d2d2d2..>"));
+        assert!(debug.contains("a2: <64 bytes a2a2a2a2..>"));
+        assert!(debug.contains("st_key: Some(<16 bytes 57575757..>)"));
+        assert!(debug.contains("ps_key: {\"qun.qq.com\": <44 bytes 95959595..>}"));
+        assert!(debug.contains("tlv_cache: {262: <32 bytes 06060606..>}"));
+        assert!(debug.contains("skey: <11 bytes 40536b65..>"));
+    }
+
+    #[cfg(feature = "debug-secrets")]
+    #[test]
+    fn test_debug_full_shows_secrets() {
+        let mut keystore = BotKeystore::new().with_uin(10000);
+        keystore.sigs.d2_key = vec![0xd2; 16];
+        keystore.state.tlv_cache.insert(0x106, vec![0x06; 32]);
+
+        let full = format!("{:?}", keystore.debug_full());
+        assert!(full.contains(&format!("d2_key: <16 bytes {}>", to_hex(&[0xd2; 16]))));
+        assert!(full.contains(&to_hex(&keystore.sigs.tgtgt_key)));
+        assert!(full.contains(&to_hex(&[0x06; 32])));
+
+        // Only the opted-in output
+        let debug = format!("{:?}", keystore);
+        assert!(!debug.contains(&to_hex(&[0xd2; 16])));
+        assert!(debug.contains("d2_key: <16 bytes d2d2d2d2..>"));
+    }
+}
@@ -4,12 +4,19 @@ pub mod context;
 pub mod error;
 pub mod internal;
 pub mod keystore;
+pub mod message;
 pub mod protocol;
 pub mod utils;
 mod business;
+#[cfg(feature = "bridge")]
+pub mod bridge;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
 
+pub use business::account::QrLoginStream;
+pub use business::contact::GroupMemberPages;
 pub use context::BotContext;
-pub use error::{Error, Result};
+pub use error::{Error, Result, RetCode};
 pub use protocol::{EventMessage, ProtocolEvent, Protocols};
 
 /// Prelude module for services definitions.
@@ -91,6 +98,43 @@ mod tests {
         assert_eq!(android.package_name(), "com.tencent.mobileqq");
     }
 
+    #[test]
+    fn test_custom_app_info() {
+        use crate::internal::packets::login::tlv::Tlv;
+
+        let protocol = Protocols::Custom(200);
+        let info = AppInfo {
+            current_version: "3.2.19-39038".to_string(),
+            app_id: 1600001650,
+            sub_app_id: 537290000,
+            app_client_version: 39038,
+            ..AppInfo::linux()
+        };
+        AppInfoRegistry::register(protocol, Protocols::Linux, info).unwrap();
+        assert!(AppInfoRegistry::register(Protocols::Linux, Protocols::Linux, AppInfo::linux()).is_err());
+
+        let app_info = BotAppInfo::from_protocol(protocol);
+        assert!(matches!(app_info, BotAppInfo::Custom { id: 200, .. }));
+        assert_eq!(app_info.protocol(), protocol);
+        assert_eq!(app_info.app_id(), 1600001650);
+        assert_eq!(app_info.current_version(), "3.2.19-39038");
+        assert_eq!(app_info.package_name(), "com.tencent.qq");
+        assert!(!app_info.is_android());
+        assert!(protocol.matches(Protocols::PC));
+        assert!(!Protocols::Custom(201).matches(Protocols::ALL));
+
+        let keystore = BotKeystore::default();
+        let mut tlv = Tlv::new(0, &keystore, app_info.inner());
+        tlv.tlv_100();
+        let bytes = tlv.create_bytes();
+        let mut expected = vec![0x00, 0x01, 0x01, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05];
+        expected.extend_from_slice(&1600001650u32.to_be_bytes());
+        expected.extend_from_slice(&537290000u32.to_be_bytes());
+        expected.extend_from_slice(&39038u32.to_be_bytes());
+        expected.extend_from_slice(&app_info.inner().sdk_info.main_sig_map.to_be_bytes());
+        assert_eq!(bytes, expected);
+    }
+
     #[test]
     fn test_bot_info() {
         let info = BotInfo::new(25, BotGender::Male, "TestBot".to_string());
@@ -181,6 +225,9 @@ mod tests {
         assert!(config.get_optimum_server);
         assert_eq!(config.highway_chunk_size, 1024 * 1024);
         assert!(!config.verbose);
+        assert!(config.socket.nodelay);
+        assert_eq!(config.socket.keepalive, None);
+        assert_eq!(BotConfig::builder().build().socket, config.socket);
     }
 
     #[test]
@@ -196,6 +243,46 @@ mod tests {
         assert_eq!(keystore.device_name, "lagrange-rs");
     }
 
+    #[test]
+    fn test_builder_keystore() {
+        use crate::internal::packets::login::wtlogin::WtLogin;
+
+        let keystore = BotKeystore::new().with_uin(123456789).with_device(String::new(), vec![0xAB; 16]);
+        let context = BotContext::builder().keystore(keystore).build();
+        assert_eq!(context.keystore().uin, Some(123456789));
+
+        // The password login request carries the uin in its header and tlv 0x18, the guid in tlv 0x145
+        let mut keystore = context.keystore_mut();
+        let wtlogin = WtLogin::new(&mut keystore, context.app_info.inner()).unwrap();
+        let packet = wtlogin.build_oicq_09();
+        assert_eq!(packet[9..13], 123456789u32.to_be_bytes());
+        let payload = wtlogin.decrypt_test_request(&packet);
+        let mut tlv_145 = vec![0x01, 0x45, 0x00, 0x10];
+        tlv_145.extend_from_slice(&[0xAB; 16]);
+        assert!(payload.windows(tlv_145.len()).any(|w| w == tlv_145.as_slice()));
+        let uin = 123456789u32.to_be_bytes();
+        assert!(payload.windows(6).any(|w| w[..2] == [0x1F, 0x41] && w[2..] == uin));
+    }
+
+    #[test]
+    fn test_builder_validates_keystore() {
+        let android = BotConfig::builder().protocol(Protocols::AndroidPhone).build();
+        let context = BotContext::builder().config(android.clone()).build();
+        assert_eq!(context.keystore().android_id.len(), 16);
+        assert!(BotContext::builder().build().keystore().android_id.is_empty());
+
+        let result = BotContext::builder().config(android).keystore(BotKeystore::new()).try_build();
+        assert!(matches!(result, Err(Error::InvalidKeystore(_))));
+
+        let android_keystore = BotKeystore::for_protocol(Protocols::AndroidPad);
+        let result = BotContext::builder().keystore(android_keystore).try_build();
+        assert!(matches!(result, Err(Error::InvalidKeystore(_))));
+
+        let keystore = BotKeystore { guid: vec![0; 8], ..BotKeystore::new() };
+        let result = BotContext::builder().keystore(keystore).try_build();
+        assert!(matches!(result, Err(Error::InvalidKeystore(message)) if message.contains("guid")));
+    }
+
     #[test]
     fn test_session_state() {
         let state = SessionState::default();
@@ -269,6 +356,52 @@ mod tests {
         assert_eq!(macos.app_client_version, 13172);
     }
 
+    #[test]
+    fn test_app_info_table_matches_builtins() {
+        let table = AppInfoTable::from_json(include_str!("../tests/fixtures/app_info.json")).unwrap();
+        assert_eq!(table.get(Protocols::Linux), Some(&AppInfo::linux()));
+        assert_eq!(table.get(Protocols::Windows), Some(&AppInfo::windows()));
+        assert_eq!(table.get(Protocols::AndroidPhone), Some(&AppInfo::android(AndroidVariant::Phone)));
+        assert_eq!(table.get(Protocols::MacOs), None);
+
+        let json = serde_json::to_string(&AppInfo::linux()).unwrap();
+        assert!(json.contains(r#""apk_signature_md5":"636f6d2e74656e63656e742e7171""#));
+        assert_eq!(AppInfo::from_json(&json).unwrap(), AppInfo::linux());
+
+        // Missing fields are errors, not defaults
+        let mut value = serde_json::to_value(AppInfo::linux()).unwrap();
+        value.as_object_mut().unwrap().remove("sub_app_id");
+        assert!(AppInfo::from_json(&value.to_string()).is_err());
+        assert!(AppInfoTable::from_json(r#"{"linux_new": null}"#).is_err());
+        assert!(AppInfo::from_json(&json.replace("636f6d2e", "zz6f6d2e")).is_err());
+    }
+
+    #[test]
+    fn test_app_info_file_overrides_builtin() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/app_info.json");
+        let config = BotConfig::builder().protocol(Protocols::AndroidPhone).app_info_file(path).build();
+        let context = crate::context::BotContext::builder().config(config).build();
+        assert_eq!(context.app_info.android_variant(), Some(AndroidVariant::Phone));
+        assert_eq!(context.app_info.inner(), &AppInfo::android(AndroidVariant::Phone));
+
+        let config = BotConfig::builder().app_info_file("/nonexistent/app_info.json").build();
+        assert!(crate::context::BotContext::builder().config(config).try_build().is_err());
+    }
+
+    #[test]
+    fn test_app_info_falls_back_to_protocol_builtin() {
+        // The fixture has no macOS entry
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/app_info.json");
+        let config = BotConfig::builder().protocol(Protocols::MacOs).app_info_file(path).build();
+        let context = crate::context::BotContext::builder().config(config).build();
+        assert_eq!(context.app_info.inner(), &AppInfo::macos());
+
+        let config = BotConfig::builder().protocol(Protocols::AndroidPad).build();
+        let context = crate::context::BotContext::builder().config(config).build();
+        assert_eq!(context.app_info.android_variant(), Some(AndroidVariant::Pad));
+        assert_eq!(context.app_info.inner(), &AppInfo::android(AndroidVariant::Pad));
+    }
+
     #[test]
     fn test_android_app_info_variants() {
         let phone = AppInfo::android(AndroidVariant::Phone);
@@ -0,0 +1,18 @@
+pub mod builder;
+pub mod chain;
+pub mod entity;
+pub mod error;
+pub mod forward;
+pub mod receipt;
+pub mod store;
+
+pub use builder::MessageChainBuilder;
+pub use chain::MessageChain;
+pub use entity::{
+    FileEntity, ImageEntity, MarketFaceEntity, MediaSource, MessageEntity, RawElem, RecordEntity,
+    ReplyEntity, VideoEntity,
+};
+pub use error::SendMessageError;
+pub use forward::MessageNode;
+pub use receipt::{MessageId, MessageReceipt, MessageTarget};
+pub use store::MessageStore;
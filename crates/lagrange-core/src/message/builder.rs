@@ -0,0 +1,138 @@
+use super::{ImageEntity, MediaSource, MessageChain, MessageEntity, MessageId, RecordEntity, ReplyEntity};
+use std::path::PathBuf;
+
+/// Fluent construction of a [`MessageChain`]
+///
+/// ```
+/// use lagrange_core::message::MessageChain;
+///
+/// let chain = MessageChain::builder().text("hi ").at(123).face(66).build();
+/// assert_eq!(chain.len(), 3);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MessageChainBuilder {
+    chain: MessageChain,
+}
+
+impl MessageChainBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.entity(MessageEntity::Text { text: text.into() })
+    }
+
+    /// Mention `uin`, displayed as the uin itself
+    pub fn at(self, uin: u64) -> Self {
+        self.at_named(uin, uin.to_string())
+    }
+
+    /// Mention `uin`, displayed as `@display` to clients that cannot resolve the member
+    pub fn at_named(self, uin: u64, display: impl Into<String>) -> Self {
+        self.entity(MessageEntity::At {
+            uin,
+            display: display.into(),
+        })
+    }
+
+    /// Mention everyone in the group; groups and members have a daily quota of these
+    pub fn at_all(self) -> Self {
+        self.entity(MessageEntity::AtAll)
+    }
+
+    pub fn face(self, id: u32) -> Self {
+        self.entity(MessageEntity::Face { id })
+    }
+
+    pub fn image(self, image: ImageEntity) -> Self {
+        self.entity(MessageEntity::Image(image))
+    }
+
+    /// Image to upload from memory when the chain is sent
+    pub fn image_from_bytes(self, data: impl Into<bytes::Bytes>) -> Self {
+        self.image(ImageEntity::from_source(MediaSource::Bytes(data.into())))
+    }
+
+    /// Image to read from `path` and upload when the chain is sent
+    pub fn image_from_path(self, path: impl Into<PathBuf>) -> Self {
+        self.image(ImageEntity::from_source(MediaSource::Path(path.into())))
+    }
+
+    /// Quote the message with the given sequence
+    pub fn reply(self, sequence: u32) -> Self {
+        self.entity(MessageEntity::Reply(ReplyEntity { sequence, ..Default::default() }))
+    }
+
+    /// Quote a message the bot sent, looked up in [`BotContext::messages`](crate::BotContext::messages)
+    /// when the chain is sent
+    pub fn reply_to(self, message_id: MessageId) -> Self {
+        self.entity(MessageEntity::Reply(ReplyEntity { message_id: Some(message_id), ..Default::default() }))
+    }
+
+    /// Voice from the rich media descriptor of a group upload
+    pub fn record(self, msg_info: Vec<u8>) -> Self {
+        self.entity(MessageEntity::Record(RecordEntity::from_encoded_msg_info(msg_info, true)))
+    }
+
+    /// SILK or AMR voice to upload from memory when the chain is sent
+    pub fn record_from_bytes(self, data: impl Into<bytes::Bytes>) -> Self {
+        self.entity(MessageEntity::Record(RecordEntity::from_source(MediaSource::Bytes(data.into()))))
+    }
+
+    /// SILK or AMR voice to read from `path` and upload when the chain is sent
+    pub fn record_from_path(self, path: impl Into<PathBuf>) -> Self {
+        self.entity(MessageEntity::Record(RecordEntity::from_source(MediaSource::Path(path.into()))))
+    }
+
+    pub fn json(self, payload: impl Into<String>) -> Self {
+        self.entity(MessageEntity::Json { payload: payload.into() })
+    }
+
+    pub fn xml(self, payload: impl Into<String>) -> Self {
+        self.entity(MessageEntity::Xml { payload: payload.into() })
+    }
+
+    /// Link shown as a card with `title`, `desc` and a preview image from the url `image`
+    pub fn share(self, url: &str, title: &str, desc: &str, image: Option<&str>) -> Self {
+        self.json(share_card(url, title, desc, image))
+    }
+
+    pub fn forward(self, res_id: impl Into<String>) -> Self {
+        self.entity(MessageEntity::Forward {
+            res_id: res_id.into(),
+        })
+    }
+
+    pub fn entity(mut self, entity: MessageEntity) -> Self {
+        self.chain.push(entity);
+        self
+    }
+
+    pub fn build(self) -> MessageChain {
+        self.chain
+    }
+}
+
+/// Ark payload of the news card clients send when sharing a link
+fn share_card(url: &str, title: &str, desc: &str, image: Option<&str>) -> String {
+    serde_json::json!({
+        "app": "com.tencent.structmsg",
+        "desc": "新闻",
+        "view": "news",
+        "ver": "0.0.0.1",
+        "prompt": format!("[分享]{}", title),
+        "meta": {
+            "news": {
+                "title": title,
+                "desc": desc,
+                "jumpUrl": url,
+                "preview": image.unwrap_or_default(),
+                "tag": "",
+                "app_type": 1,
+            }
+        },
+        "config": { "type": "normal", "forward": true },
+    })
+    .to_string()
+}
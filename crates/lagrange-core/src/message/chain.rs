@@ -0,0 +1,529 @@
+use super::{MessageChainBuilder, MessageEntity, RawElem};
+use crate::internal::packets::message::Elem;
+use lagrange_proto::{ProtoDecode, ProtoMessage};
+use std::fmt;
+
+/// An ordered list of message elements
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageChain {
+    entities: Vec<MessageEntity>,
+}
+
+impl MessageChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn builder() -> MessageChainBuilder {
+        MessageChainBuilder::new()
+    }
+
+    /// A chain made of a single text element
+    pub fn text(text: impl Into<String>) -> Self {
+        let mut chain = Self::new();
+        chain.push(MessageEntity::Text { text: text.into() });
+        chain
+    }
+
+    pub fn push(&mut self, entity: MessageEntity) {
+        self.entities.push(entity);
+    }
+
+    pub fn entities(&self) -> &[MessageEntity] {
+        &self.entities
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub(crate) fn to_elems(&self) -> Vec<Elem> {
+        self.entities.iter().map(MessageEntity::to_elem).collect()
+    }
+
+    /// Encode each element on its own, the counterpart of [`MessageChain::from_encoded_elems`].
+    ///
+    /// [`MessageEntity::Raw`] elements are written back unchanged.
+    pub(crate) fn to_encoded_elems(&self) -> Vec<Vec<u8>> {
+        self.entities
+            .iter()
+            .map(|entity| match entity {
+                MessageEntity::Raw(raw) => raw.data.clone(),
+                // Encoding into a Vec cannot fail
+                entity => entity.to_elem().encode_to_vec().expect("encode to Vec"),
+            })
+            .collect()
+    }
+
+    /// Decode received elements one by one; those that fail to decode are kept as
+    /// [`MessageEntity::Raw`] instead of failing the whole chain
+    pub(crate) fn from_encoded_elems<T: AsRef<[u8]>>(elems: &[T]) -> Self {
+        let entities = elems
+            .iter()
+            .map(|data| match Elem::decode(data.as_ref()) {
+                Ok(elem) => MessageEntity::from_elem(&elem),
+                Err(_) => MessageEntity::Raw(RawElem {
+                    data: data.as_ref().to_vec(),
+                }),
+            })
+            .collect();
+        Self { entities }
+    }
+}
+
+impl fmt::Display for MessageChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entity in &self.entities {
+            write!(f, "{}", entity)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<MessageEntity>> for MessageChain {
+    fn from(entities: Vec<MessageEntity>) -> Self {
+        Self { entities }
+    }
+}
+
+impl From<&str> for MessageChain {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for MessageChain {
+    fn from(text: String) -> Self {
+        Self::text(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ImageEntity;
+    use lagrange_proto::ProtoMessage;
+    use crate::internal::packets::message::{LightAppElem, MentionExtra};
+    use std::io::Read;
+
+    #[test]
+    fn test_builder() {
+        let chain = MessageChain::builder().text("hi ").at(123).face(66).build();
+
+        assert_eq!(
+            chain.entities(),
+            &[
+                MessageEntity::Text {
+                    text: "hi ".to_string()
+                },
+                MessageEntity::At {
+                    uin: 123,
+                    display: "123".to_string()
+                },
+                MessageEntity::Face { id: 66 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let chain = MessageChain::builder()
+            .reply(9)
+            .text("look ")
+            .at_named(123, "alice")
+            .image(ImageEntity {
+                file_name: "a.png".to_string(),
+                ..Default::default()
+            })
+            .forward("res")
+            .build();
+
+        assert_eq!(
+            chain.to_string(),
+            "[Reply:9]look [At:alice(123)][Image:a.png][Forward:res]"
+        );
+    }
+
+    #[test]
+    fn test_elem_round_trip() {
+        let chain = MessageChain::builder()
+            .reply(42)
+            .text("hello")
+            .at(10001)
+            .face(14)
+            .image(ImageEntity {
+                file_name: "b.jpg".to_string(),
+                md5: vec![0xAB; 16],
+                size: 2048,
+                width: 640,
+                height: 480,
+                url: Some("/download?id=1".to_string()),
+                ..Default::default()
+            })
+            .record(vec![1, 2, 3])
+            .json(r#"{"app":"com.tencent.miniapp"}"#)
+            .xml(r#"<?xml version="1.0" encoding="utf-8"?><msg serviceID="14" brief="[card]"></msg>"#)
+            .forward("abcdef")
+            .build();
+
+        let encoded: Vec<Vec<u8>> = chain
+            .to_elems()
+            .iter()
+            .map(|elem| elem.encode_to_vec().unwrap())
+            .collect();
+
+        assert_eq!(MessageChain::from_encoded_elems(&encoded), chain);
+    }
+
+    #[test]
+    fn test_uploaded_image_round_trip() {
+        use crate::internal::packets::oidb::{FileInfo, IndexNode, MsgInfo, MsgInfoBody, PicUrlExtInfo, PictureInfo};
+
+        let msg_info = MsgInfo {
+            msg_info_body: vec![MsgInfoBody {
+                index: Some(IndexNode {
+                    info: Some(FileInfo {
+                        file_size: 2048,
+                        file_hash: "abababababababababababababababab".to_string(),
+                        file_name: "abab.png".to_string(),
+                        width: 640,
+                        height: 480,
+                        ..Default::default()
+                    }),
+                    file_uuid: "EhQ_uuid".to_string(),
+                    ..Default::default()
+                }),
+                picture: Some(PictureInfo {
+                    url_path: "/download?appid=1407&fileid=EhQ_uuid".to_string(),
+                    ext: Some(PicUrlExtInfo {
+                        original_parameter: "&spec=0&rkey=CAQ".to_string(),
+                        ..Default::default()
+                    }),
+                    domain: "multimedia.nt.qq.com.cn".to_string(),
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut image = ImageEntity::from_msg_info(&msg_info);
+        image.msg_info = Some(msg_info.encode_to_vec().unwrap());
+        image.is_group = true;
+
+        assert_eq!(image.md5, vec![0xAB; 16]);
+        assert_eq!(image.file_id.as_deref(), Some("EhQ_uuid"));
+        assert_eq!(
+            image.url.as_deref(),
+            Some("https://multimedia.nt.qq.com.cn/download?appid=1407&fileid=EhQ_uuid&spec=0&rkey=CAQ")
+        );
+
+        let chain = MessageChain::builder().image(image).build();
+        let elems = chain.to_elems();
+        assert_eq!(elems[0].common_elem.as_ref().unwrap().business_type, Some(20));
+        assert_eq!(MessageChain::from_encoded_elems(&[elems[0].encode_to_vec().unwrap()]), chain);
+    }
+
+    #[test]
+    fn test_friend_image_decodes() {
+        use crate::internal::packets::message::elem::NotOnlineImage;
+
+        let elem = Elem {
+            not_online_image: Some(NotOnlineImage {
+                file_path: Some("c.png".to_string()),
+                file_len: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let chain = MessageChain::from_encoded_elems(&[elem.encode_to_vec().unwrap()]);
+        assert!(matches!(
+            &chain.entities()[0],
+            MessageEntity::Image(image) if image.file_name == "c.png" && image.size == 10
+        ));
+    }
+
+    #[test]
+    fn test_reply_with_source_decodes() {
+        use crate::internal::packets::message::{SrcMsg, Text};
+        use crate::message::ReplyEntity;
+
+        // Synthetic reply quoting "hi" sent by 10001
+        let quoted = Elem {
+            text: Some(Text { str: Some("hi".to_string()), ..Default::default() }),
+            ..Default::default()
+        };
+        let elem = Elem {
+            src_msg: Some(SrcMsg {
+                orig_seqs: vec![77],
+                sender_uin: Some(10001),
+                time: Some(1_700_000_000),
+                flag: None,
+                elems: vec![quoted],
+            }),
+            ..Default::default()
+        };
+
+        let chain = MessageChain::from_encoded_elems(&[elem.encode_to_vec().unwrap()]);
+        let expected = MessageEntity::Reply(ReplyEntity {
+            sequence: 77,
+            sender_uin: 10001,
+            time: 1_700_000_000,
+            source: vec![MessageEntity::Text { text: "hi".to_string() }],
+            message_id: None,
+        });
+        assert_eq!(chain.entities(), &[expected]);
+        assert_eq!(chain.to_string(), "[Reply:77]");
+        assert_eq!(chain.to_elems()[0], elem);
+    }
+
+    #[test]
+    fn test_market_face_decodes() {
+        use crate::internal::packets::message::MarketFace;
+        use crate::message::MarketFaceEntity;
+
+        // Synthetic sticker as laid out by the official client
+        let elem = Elem {
+            market_face: Some(MarketFace {
+                face_name: Some("[开心]".as_bytes().to_vec()),
+                item_type: Some(6),
+                face_info: Some(1),
+                face_id: Some(vec![0x12, 0xAB]),
+                tab_id: Some(231),
+                sub_type: Some(3),
+                key: Some(b"6f0c2b6e".to_vec()),
+                media_type: Some(0),
+                image_width: Some(200),
+                image_height: Some(200),
+            }),
+            ..Default::default()
+        };
+
+        let chain = MessageChain::from_encoded_elems(&[elem.encode_to_vec().unwrap()]);
+        let expected = MessageEntity::MarketFace(MarketFaceEntity {
+            face_id: "12ab".to_string(),
+            tab_id: 231,
+            key: "6f0c2b6e".to_string(),
+            summary: "[开心]".to_string(),
+            width: 200,
+            height: 200,
+        });
+        assert_eq!(chain.entities(), &[expected]);
+        assert_eq!(chain.to_string(), "[MarketFace:[开心]]");
+        assert_eq!(chain.to_elems()[0], elem);
+    }
+
+    #[test]
+    fn test_group_file_round_trip() {
+        use crate::message::FileEntity;
+
+        let file = FileEntity {
+            file_id: "/a1b2c3".to_string(),
+            file_name: "report.pdf".to_string(),
+            file_size: 4096,
+            bus_id: 102,
+        };
+        let chain = MessageChain::builder().entity(MessageEntity::File(file)).build();
+        let elems = chain.to_elems();
+        let value = elems[0].trans_elem.as_ref().unwrap().elem_value.clone().unwrap();
+        assert_eq!(value[0], 0x01);
+        assert_eq!(u16::from_be_bytes([value[1], value[2]]) as usize, value.len() - 3);
+
+        assert_eq!(MessageChain::from_encoded_elems(&[elems[0].encode_to_vec().unwrap()]), chain);
+        assert_eq!(chain.to_string(), "[File:report.pdf]");
+    }
+
+    #[test]
+    fn test_video_decodes() {
+        use crate::internal::packets::message::CommonElem;
+        use crate::internal::packets::oidb::{FileInfo, IndexNode, MsgInfo, MsgInfoBody};
+
+        let msg_info = MsgInfo {
+            msg_info_body: vec![MsgInfoBody {
+                index: Some(IndexNode {
+                    info: Some(FileInfo {
+                        file_size: 1 << 20,
+                        file_hash: "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd".to_string(),
+                        file_name: "clip.mp4".to_string(),
+                        width: 1280,
+                        height: 720,
+                        time: 15,
+                        ..Default::default()
+                    }),
+                    file_uuid: "video_uuid".to_string(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let elem = Elem {
+            common_elem: Some(CommonElem {
+                service_type: Some(48),
+                pb_elem: Some(msg_info.encode_to_vec().unwrap()),
+                business_type: Some(21),
+            }),
+            ..Default::default()
+        };
+
+        let chain = MessageChain::from_encoded_elems(&[elem.encode_to_vec().unwrap()]);
+        let MessageEntity::Video(video) = &chain.entities()[0] else {
+            panic!("expected a video, got {:?}", chain.entities());
+        };
+        assert_eq!((video.file_name.as_str(), video.duration), ("clip.mp4", 15));
+        assert_eq!((video.width, video.height, video.is_group), (1280, 720, true));
+        assert_eq!(video.file_id.as_deref(), Some("video_uuid"));
+        assert_eq!(chain.to_elems()[0], elem);
+    }
+
+    #[test]
+    fn test_unknown_elem_is_lossless() {
+        // Elem with only field 37 (general flags), which has no model here
+        let data = vec![0xAA, 0x02, 0x04, 0x08, 0x01, 0x10, 0x02];
+        let chain = MessageChain::from_encoded_elems(&[&data]);
+        assert_eq!(
+            chain.entities(),
+            &[MessageEntity::Raw(RawElem { data: data.clone() })]
+        );
+        assert_eq!(chain.to_string(), "");
+
+        let elems = chain.to_elems();
+        assert_eq!(elems[0].encode_to_vec().unwrap(), data);
+    }
+
+    #[test]
+    fn test_malformed_elem_is_kept() {
+        // Field 1 (text) announced as 16 bytes long, but only 2 follow
+        let malformed = vec![0x0A, 0x10, 0x0A, 0x00];
+        let text = Elem {
+            text: Some(crate::internal::packets::message::Text {
+                str: Some("still here".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let chain = MessageChain::from_encoded_elems(&[malformed.clone(), text.encode_to_vec().unwrap()]);
+        assert_eq!(
+            chain.entities(),
+            &[
+                MessageEntity::Raw(RawElem { data: malformed }),
+                MessageEntity::Text { text: "still here".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_card_compression() {
+        let payload = r#"{"app":"com.tencent.miniapp","prompt":"[mini program]"}"#;
+        let elems = MessageChain::builder().json(payload).build().to_elems();
+
+        // Cards are sent as 0x01 followed by the zlib stream
+        let data = elems[0].light_app.as_ref().unwrap().data.clone().unwrap();
+        assert_eq!(data[0], 0x01);
+        let mut inflated = String::new();
+        flate2::read::ZlibDecoder::new(&data[1..]).read_to_string(&mut inflated).unwrap();
+        assert_eq!(inflated, payload);
+
+        // Some clients send them uncompressed behind 0x00
+        let mut raw = vec![0x00];
+        raw.extend(payload.as_bytes());
+        let elem = Elem {
+            light_app: Some(LightAppElem { data: Some(raw), msg_resid: None }),
+            ..Default::default()
+        };
+        let chain = MessageChain::from_encoded_elems(&[elem.encode_to_vec().unwrap()]);
+        assert_eq!(chain.entities(), &[MessageEntity::Json { payload: payload.to_string() }]);
+
+        // Unknown prefixes are kept as raw elements
+        let elem = Elem {
+            light_app: Some(LightAppElem { data: Some(vec![0x02]), msg_resid: None }),
+            ..Default::default()
+        };
+        let chain = MessageChain::from_encoded_elems(&[elem.encode_to_vec().unwrap()]);
+        assert!(matches!(chain.entities(), [MessageEntity::Raw(_)]));
+    }
+
+    #[test]
+    fn test_ark_elem_decodes() {
+        let payload = concat!(
+            r#"{"app":"com.tencent.structmsg","view":"news","#,
+            r#""meta":{"news":{"title":"Weekly notes","jumpUrl":"https://example.com/notes"}}}"#,
+        );
+        assert_eq!(payload.len(), 124);
+
+        // An incoming ark element whose zlib stream holds one stored block, as written by
+        // encoders that skip compressing small payloads
+        let mut data = vec![0x01]; // compressed
+        data.extend([0x78, 0x01]); // zlib header, no dictionary
+        data.extend([0x01, 0x7c, 0x00, 0x83, 0xff]); // final stored block of 124 bytes
+        data.extend(payload.as_bytes());
+        data.extend([0x6b, 0xd5, 0x2b, 0x4d]); // adler-32 of the payload
+        assert_eq!(data.len(), 136);
+
+        let mut elem = vec![0x9a, 0x03, 0x8b, 0x01]; // field 51 (light_app), 139 bytes
+        elem.extend([0x0a, 0x88, 0x01]); // field 1 (data), 136 bytes
+        elem.extend(data);
+
+        let chain = MessageChain::from_encoded_elems(&[elem]);
+        assert_eq!(chain.entities(), &[MessageEntity::Json { payload: payload.to_string() }]);
+        assert_eq!(chain.to_string(), "[Json]");
+    }
+
+    #[test]
+    fn test_xml_service_id() {
+        let payload = r#"<?xml version="1.0" encoding="utf-8"?><msg serviceID="33" brief="[link]"></msg>"#;
+        let elems = MessageChain::builder().xml(payload).xml("<msg></msg>").build().to_elems();
+        assert_eq!(elems[0].rich_msg.as_ref().unwrap().service_id, Some(33));
+        assert_eq!(elems[1].rich_msg.as_ref().unwrap().service_id, Some(1));
+
+        // Service 35 without a resid is not a forwarded bundle
+        let payload = r#"<msg serviceID="35" brief="[history]"></msg>"#;
+        let encoded: Vec<Vec<u8>> = MessageChain::builder()
+            .xml(payload)
+            .build()
+            .to_elems()
+            .iter()
+            .map(|elem| elem.encode_to_vec().unwrap())
+            .collect();
+        let chain = MessageChain::from_encoded_elems(&encoded);
+        assert_eq!(chain.entities(), &[MessageEntity::Xml { payload: payload.to_string() }]);
+    }
+
+    #[test]
+    fn test_share_card() {
+        let chain = MessageChain::builder()
+            .share("https://example.com/a", "Title", "Summary", Some("https://example.com/a.png"))
+            .build();
+        let [MessageEntity::Json { payload }] = chain.entities() else {
+            panic!("expected a card, got {:?}", chain.entities());
+        };
+
+        let card: serde_json::Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(card["app"], "com.tencent.structmsg");
+        assert_eq!(card["view"], "news");
+        assert_eq!(card["prompt"], "[分享]Title");
+        let news = &card["meta"]["news"];
+        assert_eq!((news["title"].as_str(), news["desc"].as_str()), (Some("Title"), Some("Summary")));
+        assert_eq!(news["jumpUrl"], "https://example.com/a");
+        assert_eq!(news["preview"], "https://example.com/a.png");
+    }
+
+    #[test]
+    fn test_at_all() {
+        let chain = MessageChain::builder().at_all().text(" meeting at 3").at(10001).build();
+        assert_eq!(chain.to_string(), "[AtAll] meeting at 3[At:10001(10001)]");
+
+        let elems = chain.to_elems();
+        let text = elems[0].text.as_ref().unwrap();
+        assert_eq!(text.str.as_deref(), Some("@全体成员"));
+        let extra = MentionExtra::decode(text.pb_reserve.as_deref().unwrap()).unwrap();
+        assert_eq!((extra.mention_type, extra.uin), (Some(1), Some(0)));
+
+        let encoded: Vec<Vec<u8>> = elems.iter().map(|elem| elem.encode_to_vec().unwrap()).collect();
+        assert_eq!(MessageChain::from_encoded_elems(&encoded), chain);
+    }
+}
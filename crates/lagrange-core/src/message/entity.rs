@@ -0,0 +1,645 @@
+use crate::internal::packets::message::{
+    CommonElem, CustomFace, Elem, Face, LightAppElem, MarketFace, MentionExtra, ObjMsg,
+    ObjMsgContentInfo, ObjMsgFile, RichMsg, SrcMsg, Text, TransElem,
+};
+use crate::internal::packets::oidb::{MsgInfo, MsgInfoBody};
+use crate::message::MessageId;
+use crate::utils::common::{from_hex, to_hex};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use lagrange_proto::{ProtoDecode, ProtoMessage};
+use std::fmt;
+use std::io::{Read, Write};
+
+/// `CommonElem` service type used by NT rich media
+const RICH_MEDIA_SERVICE_TYPE: u32 = 48;
+/// `CommonElem` business types of images sent to a friend and to a group
+const IMAGE_BUSINESS_TYPES: [u32; 2] = [10, 20];
+/// `CommonElem` business types of records sent to a friend and to a group
+const RECORD_BUSINESS_TYPES: [u32; 2] = [12, 22];
+/// `CommonElem` business types of videos sent to a friend and to a group
+const VIDEO_BUSINESS_TYPES: [u32; 2] = [11, 21];
+/// `TransElem` type of a group file
+const GROUP_FILE_ELEM_TYPE: u32 = 24;
+/// `RichMsg` service id of a forwarded message bundle
+const FORWARD_SERVICE_ID: u32 = 35;
+/// `RichMsg` service id of XML cards that do not name one
+const DEFAULT_XML_SERVICE_ID: u32 = 1;
+
+/// `MentionExtra::mention_type` of everyone in the group
+const MENTION_ALL: u32 = 1;
+/// `MentionExtra::mention_type` of a single member
+const MENTION_MEMBER: u32 = 2;
+/// Text clients show for a mention of everyone
+const AT_ALL_TEXT: &str = "@全体成员";
+
+/// A single element of a [`MessageChain`](super::MessageChain)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageEntity {
+    Text {
+        text: String,
+    },
+    At {
+        uin: u64,
+        display: String,
+    },
+    /// Mention of everyone in the group, limited per day, see
+    /// [`BotContext::get_at_all_remain`](crate::BotContext::get_at_all_remain)
+    AtAll,
+    Face {
+        id: u32,
+    },
+    Image(ImageEntity),
+    Reply(ReplyEntity),
+    /// Voice message
+    Record(RecordEntity),
+    Video(VideoEntity),
+    /// Sticker from the sticker store
+    MarketFace(MarketFaceEntity),
+    /// File uploaded to the group files
+    File(FileEntity),
+    /// JSON card, also called ark, like shares and mini programs
+    Json {
+        payload: String,
+    },
+    /// XML card; the `serviceID` attribute of its `msg` element is sent as the service id
+    Xml {
+        payload: String,
+    },
+    /// Reference to a forwarded message bundle stored on the server
+    Forward {
+        res_id: String,
+    },
+    /// An element this crate does not model, kept as its encoded protobuf
+    Raw(RawElem),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageEntity {
+    pub file_name: String,
+    pub md5: Vec<u8>,
+    pub size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub url: Option<String>,
+    /// Uuid of the file on the NT media servers
+    pub file_id: Option<String>,
+    /// Rich media descriptor of an uploaded image, sent instead of the legacy element when set
+    pub msg_info: Option<Vec<u8>>,
+    /// Whether `msg_info` was issued for a group, friends use another element type
+    pub is_group: bool,
+    /// Content still to be uploaded, done when the chain is sent
+    pub source: Option<MediaSource>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordEntity {
+    pub file_name: String,
+    pub md5: Vec<u8>,
+    pub size: u32,
+    /// Length in seconds
+    pub duration: u32,
+    /// Uuid of the file on the NT media servers, the key to download it
+    pub file_id: Option<String>,
+    /// Rich media descriptor returned by the upload
+    pub msg_info: Option<Vec<u8>>,
+    /// Whether `msg_info` was issued for a group, friends use another element type
+    pub is_group: bool,
+    /// SILK or AMR content still to be uploaded, done when the chain is sent
+    pub source: Option<MediaSource>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VideoEntity {
+    pub file_name: String,
+    pub md5: Vec<u8>,
+    pub size: u32,
+    /// Length in seconds
+    pub duration: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Uuid of the file on the NT media servers
+    pub file_id: Option<String>,
+    /// Rich media descriptor of the video
+    pub msg_info: Option<Vec<u8>>,
+    pub is_group: bool,
+}
+
+/// Quote of an earlier message
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplyEntity {
+    /// Sequence of the quoted message
+    pub sequence: u32,
+    /// Sender of the quoted message, `0` when unknown
+    pub sender_uin: u64,
+    /// Unix timestamp (seconds) of the quoted message, `0` when unknown
+    pub time: u32,
+    /// Content of the quoted message as embedded by the sender, usually shortened
+    pub source: Vec<MessageEntity>,
+    /// Message of the bot to quote, the fields above are filled from its receipt when sending
+    pub message_id: Option<MessageId>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarketFaceEntity {
+    /// Id of the sticker, hex-encoded
+    pub face_id: String,
+    /// Id of the sticker pack
+    pub tab_id: u32,
+    pub key: String,
+    /// Text shown in place of the sticker, like `[开心]`
+    pub summary: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Reference to a file in the group files, resolved with `BotContext::get_group_file_url`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileEntity {
+    pub file_id: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub bus_id: u32,
+}
+
+/// Where the content of media that is not uploaded yet comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaSource {
+    Bytes(bytes::Bytes),
+    Path(std::path::PathBuf),
+}
+
+impl ImageEntity {
+    /// Image waiting to be uploaded from `source`; name, hashes and size are filled in by the
+    /// upload
+    pub fn from_source(source: MediaSource) -> Self {
+        Self {
+            source: Some(source),
+            ..Default::default()
+        }
+    }
+
+    /// Take name, hashes, size and url from a rich media descriptor
+    pub(crate) fn from_msg_info(msg_info: &MsgInfo) -> Self {
+        let body = msg_info.msg_info_body.first().cloned().unwrap_or_default();
+        let index = body.index.unwrap_or_default();
+        let file = index.info.unwrap_or_default();
+        let url = body.picture.filter(|picture| !picture.domain.is_empty()).map(|picture| {
+            format!(
+                "https://{}{}{}",
+                picture.domain,
+                picture.url_path,
+                picture.ext.map(|ext| ext.original_parameter).unwrap_or_default()
+            )
+        });
+
+        Self {
+            file_name: file.file_name,
+            md5: from_hex(&file.file_hash).unwrap_or_default(),
+            size: file.file_size,
+            width: file.width,
+            height: file.height,
+            url,
+            file_id: Some(index.file_uuid).filter(|uuid| !uuid.is_empty()),
+            ..Default::default()
+        }
+    }
+}
+
+impl RecordEntity {
+    /// Record waiting to be uploaded from `source`
+    pub fn from_source(source: MediaSource) -> Self {
+        Self {
+            source: Some(source),
+            ..Default::default()
+        }
+    }
+
+    /// Record of an encoded rich media descriptor, with whatever metadata it carries
+    pub(crate) fn from_encoded_msg_info(msg_info: Vec<u8>, is_group: bool) -> Self {
+        let index = first_body(&msg_info).index.unwrap_or_default();
+        let file = index.info.unwrap_or_default();
+
+        Self {
+            file_name: file.file_name,
+            md5: from_hex(&file.file_hash).unwrap_or_default(),
+            size: file.file_size,
+            duration: file.time,
+            file_id: Some(index.file_uuid).filter(|uuid| !uuid.is_empty()),
+            msg_info: Some(msg_info),
+            is_group,
+            source: None,
+        }
+    }
+}
+
+impl VideoEntity {
+    /// Video of an encoded rich media descriptor, with whatever metadata it carries
+    pub(crate) fn from_encoded_msg_info(msg_info: Vec<u8>, is_group: bool) -> Self {
+        let index = first_body(&msg_info).index.unwrap_or_default();
+        let file = index.info.unwrap_or_default();
+
+        Self {
+            file_name: file.file_name,
+            md5: from_hex(&file.file_hash).unwrap_or_default(),
+            size: file.file_size,
+            duration: file.time,
+            width: file.width,
+            height: file.height,
+            file_id: Some(index.file_uuid).filter(|uuid| !uuid.is_empty()),
+            msg_info: Some(msg_info),
+            is_group,
+        }
+    }
+}
+
+/// First body of an encoded rich media descriptor, empty if it does not decode
+fn first_body(msg_info: &[u8]) -> MsgInfoBody {
+    MsgInfo::decode(msg_info)
+        .ok()
+        .and_then(|info| info.msg_info_body.into_iter().next())
+        .unwrap_or_default()
+}
+
+/// Encoded `Elem` of an unsupported type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawElem {
+    pub data: Vec<u8>,
+}
+
+impl MessageEntity {
+    pub(crate) fn to_elem(&self) -> Elem {
+        match self {
+            MessageEntity::Text { text } => Elem {
+                text: Some(Text {
+                    str: Some(text.clone()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            MessageEntity::At { uin, display } => {
+                let extra = MentionExtra {
+                    mention_type: Some(MENTION_MEMBER),
+                    uin: Some(*uin as u32),
+                    field5: Some(0),
+                    uid: None,
+                };
+                Elem {
+                    text: Some(Text {
+                        str: Some(format!("@{}", display)),
+                        pb_reserve: extra.encode_to_vec().ok(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }
+            }
+            MessageEntity::AtAll => {
+                let extra = MentionExtra {
+                    mention_type: Some(MENTION_ALL),
+                    uin: Some(0),
+                    field5: Some(0),
+                    uid: None,
+                };
+                Elem {
+                    text: Some(Text {
+                        str: Some(AT_ALL_TEXT.to_string()),
+                        pb_reserve: extra.encode_to_vec().ok(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }
+            }
+            MessageEntity::Face { id } => Elem {
+                face: Some(Face {
+                    index: Some(*id),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Image(ImageEntity {
+                msg_info: Some(msg_info),
+                is_group,
+                ..
+            }) => Elem {
+                common_elem: Some(CommonElem {
+                    service_type: Some(RICH_MEDIA_SERVICE_TYPE),
+                    pb_elem: Some(msg_info.clone()),
+                    business_type: Some(IMAGE_BUSINESS_TYPES[*is_group as usize]),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Image(image) => Elem {
+                custom_face: Some(CustomFace {
+                    file_path: Some(image.file_name.clone()),
+                    md5: Some(image.md5.clone()),
+                    orig_url: image.url.clone(),
+                    width: Some(image.width),
+                    height: Some(image.height),
+                    size: Some(image.size),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Reply(reply) => Elem {
+                src_msg: Some(SrcMsg {
+                    orig_seqs: vec![reply.sequence],
+                    sender_uin: Some(reply.sender_uin).filter(|uin| *uin != 0),
+                    time: Some(reply.time).filter(|time| *time != 0),
+                    flag: None,
+                    elems: reply.source.iter().map(MessageEntity::to_elem).collect(),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Record(record) => Elem {
+                common_elem: Some(CommonElem {
+                    service_type: Some(RICH_MEDIA_SERVICE_TYPE),
+                    pb_elem: record.msg_info.clone(),
+                    business_type: Some(RECORD_BUSINESS_TYPES[record.is_group as usize]),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Video(video) => Elem {
+                common_elem: Some(CommonElem {
+                    service_type: Some(RICH_MEDIA_SERVICE_TYPE),
+                    pb_elem: video.msg_info.clone(),
+                    business_type: Some(VIDEO_BUSINESS_TYPES[video.is_group as usize]),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::MarketFace(face) => Elem {
+                market_face: Some(MarketFace {
+                    face_name: Some(face.summary.clone().into_bytes()),
+                    item_type: Some(6),
+                    face_info: Some(1),
+                    face_id: from_hex(&face.face_id),
+                    tab_id: Some(face.tab_id),
+                    sub_type: Some(3),
+                    key: Some(face.key.clone().into_bytes()),
+                    media_type: Some(0),
+                    image_width: Some(face.width),
+                    image_height: Some(face.height),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::File(file) => Elem {
+                trans_elem: Some(TransElem {
+                    elem_type: Some(GROUP_FILE_ELEM_TYPE),
+                    elem_value: Some(pack_group_file(file)),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Json { payload } => Elem {
+                light_app: Some(LightAppElem {
+                    data: Some(pack_content(payload)),
+                    msg_resid: None,
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Xml { payload } => Elem {
+                rich_msg: Some(RichMsg {
+                    template1: Some(pack_content(payload)),
+                    service_id: Some(
+                        xml_attribute(payload, "serviceID")
+                            .and_then(|id| id.parse().ok())
+                            .unwrap_or(DEFAULT_XML_SERVICE_ID),
+                    ),
+                }),
+                ..Default::default()
+            },
+            MessageEntity::Forward { res_id } => {
+                let template = format!(
+                    r#"<?xml version="1.0" encoding="utf-8"?><msg serviceID="35" templateID="1" action="viewMultiMsg" brief="[Chat history]" m_resid="{}" m_fileName="{}" sourceMsgId="0" url="" flag="3" adverSign="0" multiMsgFlag="0"></msg>"#,
+                    res_id, res_id
+                );
+                Elem {
+                    rich_msg: Some(RichMsg {
+                        template1: Some(pack_content(&template)),
+                        service_id: Some(FORWARD_SERVICE_ID),
+                    }),
+                    ..Default::default()
+                }
+            }
+            MessageEntity::Raw(raw) => Elem::decode(&raw.data).unwrap_or_default(),
+        }
+    }
+
+    /// Convert a received element; anything not understood becomes [`MessageEntity::Raw`]
+    pub(crate) fn from_elem(elem: &Elem) -> Self {
+        Self::try_from_elem(elem).unwrap_or_else(|| {
+            MessageEntity::Raw(RawElem {
+                data: elem.encode_to_vec().unwrap_or_default(),
+            })
+        })
+    }
+
+    fn try_from_elem(elem: &Elem) -> Option<Self> {
+        if let Some(text) = &elem.text {
+            let content = text.str.clone().unwrap_or_default();
+            let mention = text
+                .pb_reserve
+                .as_deref()
+                .and_then(|data| MentionExtra::decode(data).ok());
+
+            return Some(match mention {
+                Some(extra) if extra.mention_type == Some(MENTION_MEMBER) => MessageEntity::At {
+                    uin: extra.uin.unwrap_or_default() as u64,
+                    display: content.strip_prefix('@').unwrap_or(&content).to_string(),
+                },
+                Some(extra) if extra.mention_type == Some(MENTION_ALL) => MessageEntity::AtAll,
+                _ => MessageEntity::Text { text: content },
+            });
+        }
+
+        if let Some(face) = &elem.face {
+            return Some(MessageEntity::Face { id: face.index? });
+        }
+
+        if let Some(image) = &elem.custom_face {
+            return Some(MessageEntity::Image(ImageEntity {
+                file_name: image.file_path.clone().unwrap_or_default(),
+                md5: image.md5.clone().unwrap_or_default(),
+                size: image.size.unwrap_or_default(),
+                width: image.width.unwrap_or_default(),
+                height: image.height.unwrap_or_default(),
+                url: image.orig_url.clone(),
+                ..Default::default()
+            }));
+        }
+
+        if let Some(image) = &elem.not_online_image {
+            return Some(MessageEntity::Image(ImageEntity {
+                file_name: image.file_path.clone().unwrap_or_default(),
+                md5: image.pic_md5.clone().unwrap_or_default(),
+                size: image.file_len.unwrap_or_default(),
+                width: image.pic_width.unwrap_or_default(),
+                height: image.pic_height.unwrap_or_default(),
+                url: image.orig_url.clone(),
+                ..Default::default()
+            }));
+        }
+
+        if let Some(src) = &elem.src_msg {
+            return Some(MessageEntity::Reply(ReplyEntity {
+                sequence: *src.orig_seqs.first()?,
+                sender_uin: src.sender_uin.unwrap_or_default(),
+                time: src.time.unwrap_or_default(),
+                source: src.elems.iter().map(MessageEntity::from_elem).collect(),
+                message_id: None,
+            }));
+        }
+
+        if let Some(face) = &elem.market_face {
+            return Some(MessageEntity::MarketFace(MarketFaceEntity {
+                face_id: to_hex(face.face_id.as_deref().unwrap_or_default()),
+                tab_id: face.tab_id.unwrap_or_default(),
+                key: lossy_string(face.key.as_deref()),
+                summary: lossy_string(face.face_name.as_deref()),
+                width: face.image_width.unwrap_or_default(),
+                height: face.image_height.unwrap_or_default(),
+            }));
+        }
+
+        if let Some(trans) = &elem.trans_elem {
+            if trans.elem_type != Some(GROUP_FILE_ELEM_TYPE) {
+                return None;
+            }
+            return unpack_group_file(trans.elem_value.as_deref()?).map(MessageEntity::File);
+        }
+
+        if let Some(common) = &elem.common_elem {
+            let business_type = common.business_type.unwrap_or_default();
+            if common.service_type == Some(RICH_MEDIA_SERVICE_TYPE) && IMAGE_BUSINESS_TYPES.contains(&business_type) {
+                let msg_info = common.pb_elem.clone().unwrap_or_default();
+                let mut image = ImageEntity::from_msg_info(&MsgInfo::decode(&msg_info).ok()?);
+                image.msg_info = Some(msg_info);
+                image.is_group = business_type == IMAGE_BUSINESS_TYPES[1];
+                return Some(MessageEntity::Image(image));
+            }
+
+            if common.service_type == Some(RICH_MEDIA_SERVICE_TYPE) && RECORD_BUSINESS_TYPES.contains(&business_type) {
+                return Some(MessageEntity::Record(RecordEntity::from_encoded_msg_info(
+                    common.pb_elem.clone().unwrap_or_default(),
+                    business_type == RECORD_BUSINESS_TYPES[1],
+                )));
+            }
+
+            if common.service_type == Some(RICH_MEDIA_SERVICE_TYPE) && VIDEO_BUSINESS_TYPES.contains(&business_type) {
+                return Some(MessageEntity::Video(VideoEntity::from_encoded_msg_info(
+                    common.pb_elem.clone().unwrap_or_default(),
+                    business_type == VIDEO_BUSINESS_TYPES[1],
+                )));
+            }
+            return None;
+        }
+
+        if let Some(light_app) = &elem.light_app {
+            return Some(MessageEntity::Json {
+                payload: unpack_content(light_app.data.as_deref()?)?,
+            });
+        }
+
+        if let Some(rich) = &elem.rich_msg {
+            let template = unpack_content(rich.template1.as_deref()?)?;
+            if rich.service_id == Some(FORWARD_SERVICE_ID) {
+                if let Some(res_id) = xml_attribute(&template, "m_resid") {
+                    return Some(MessageEntity::Forward { res_id: res_id.to_string() });
+                }
+            }
+            return Some(MessageEntity::Xml { payload: template });
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for MessageEntity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageEntity::Text { text } => f.write_str(text),
+            MessageEntity::At { uin, display } => write!(f, "[At:{}({})]", display, uin),
+            MessageEntity::AtAll => f.write_str("[AtAll]"),
+            MessageEntity::Face { id } => write!(f, "[Face:{}]", id),
+            MessageEntity::Image(image) => write!(f, "[Image:{}]", image.file_name),
+            MessageEntity::Reply(reply) => write!(f, "[Reply:{}]", reply.sequence),
+            MessageEntity::Record(_) => f.write_str("[Record]"),
+            MessageEntity::Video(_) => f.write_str("[Video]"),
+            MessageEntity::MarketFace(face) => write!(f, "[MarketFace:{}]", face.summary),
+            MessageEntity::File(file) => write!(f, "[File:{}]", file.file_name),
+            MessageEntity::Json { .. } => f.write_str("[Json]"),
+            MessageEntity::Xml { .. } => f.write_str("[Xml]"),
+            MessageEntity::Forward { res_id } => write!(f, "[Forward:{}]", res_id),
+            // Mostly flags and metadata the user has no use for
+            MessageEntity::Raw(_) => Ok(()),
+        }
+    }
+}
+
+/// Prefix `content` with 0x01 and zlib-compress it, as done for card payloads
+fn pack_content(content: &str) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(vec![0x01], Compression::default());
+    // Writing into a Vec cannot fail
+    encoder.write_all(content.as_bytes()).expect("write to Vec");
+    encoder.finish().expect("write to Vec")
+}
+
+fn unpack_content(data: &[u8]) -> Option<String> {
+    match data.split_first()? {
+        (0x00, raw) => String::from_utf8(raw.to_vec()).ok(),
+        (0x01, compressed) => {
+            let mut content = String::new();
+            ZlibDecoder::new(compressed)
+                .read_to_string(&mut content)
+                .ok()?;
+            Some(content)
+        }
+        _ => None,
+    }
+}
+
+fn lossy_string(bytes: Option<&[u8]>) -> String {
+    String::from_utf8_lossy(bytes.unwrap_or_default()).into_owned()
+}
+
+/// Flag byte, u16 BE length and the [`ObjMsg`] describing `file`
+fn pack_group_file(file: &FileEntity) -> Vec<u8> {
+    let obj = ObjMsg {
+        msg_type: Some(6),
+        content_infos: vec![ObjMsgContentInfo {
+            content_info_id: None,
+            file: Some(ObjMsgFile {
+                bus_id: Some(file.bus_id),
+                file_path: Some(file.file_id.clone().into_bytes()),
+                file_size: Some(file.file_size),
+                file_name: Some(file.file_name.clone()),
+                ..Default::default()
+            }),
+        }],
+    };
+    let payload = obj.encode_to_vec().unwrap_or_default();
+
+    let mut data = Vec::with_capacity(payload.len() + 3);
+    data.push(0x01);
+    data.extend((payload.len() as u16).to_be_bytes());
+    data.extend(payload);
+    data
+}
+
+fn unpack_group_file(data: &[u8]) -> Option<FileEntity> {
+    let (&0x01, rest) = data.split_first()? else {
+        return None;
+    };
+    let length = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+    let obj = ObjMsg::decode(rest.get(2..2 + length)?).ok()?;
+    let file = obj.content_infos.into_iter().next()?.file?;
+
+    Some(FileEntity {
+        file_id: String::from_utf8(file.file_path?).ok()?,
+        file_name: file.file_name.unwrap_or_default(),
+        file_size: file.file_size.unwrap_or_default(),
+        bus_id: file.bus_id.unwrap_or_default(),
+    })
+}
+
+fn xml_attribute<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = xml[start..].find('"')?;
+    Some(&xml[start..start + end])
+}
@@ -0,0 +1,58 @@
+/// Non-zero results of `MessageSvc.PbSendMsg`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SendMessageError {
+    #[error("Message blocked by risk control ({code}): {message}")]
+    RiskControl { code: i32, message: String },
+
+    #[error("Bot is muted in the group ({code}): {message}")]
+    Muted { code: i32, message: String },
+
+    #[error("Group is in slow mode ({code}): {message}")]
+    SlowMode { code: i32, message: String },
+
+    /// The @all messages of the group or of the bot are used up for today
+    #[error("No @all messages left today ({code}): {message}")]
+    AtAllQuotaExceeded { code: i32, message: String },
+
+    #[error("Message rejected ({code}): {message}")]
+    Rejected { code: i32, message: String },
+}
+
+impl SendMessageError {
+    /// Result codes the server uses when the message was flagged by risk control
+    pub const RISK_CONTROL_CODES: &'static [i32] = &[46, 299];
+
+    /// Result code when the bot (or the whole group) is muted
+    pub const MUTED_CODE: i32 = 120;
+
+    /// Result code when the group only allows one message per interval
+    pub const SLOW_MODE_CODE: i32 = 121;
+
+    /// Result code when a message mentions everyone after the daily quota is used up
+    pub const AT_ALL_QUOTA_CODE: i32 = 10024;
+
+    /// Maps the result of a send; `None` for success
+    pub fn from_result(code: i32, message: &str) -> Option<Self> {
+        let message = message.to_string();
+        match code {
+            0 => None,
+            code if Self::RISK_CONTROL_CODES.contains(&code) => {
+                Some(SendMessageError::RiskControl { code, message })
+            }
+            Self::MUTED_CODE => Some(SendMessageError::Muted { code, message }),
+            Self::SLOW_MODE_CODE => Some(SendMessageError::SlowMode { code, message }),
+            Self::AT_ALL_QUOTA_CODE => Some(SendMessageError::AtAllQuotaExceeded { code, message }),
+            code => Some(SendMessageError::Rejected { code, message }),
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            SendMessageError::RiskControl { code, .. }
+            | SendMessageError::Muted { code, .. }
+            | SendMessageError::SlowMode { code, .. }
+            | SendMessageError::AtAllQuotaExceeded { code, .. }
+            | SendMessageError::Rejected { code, .. } => *code,
+        }
+    }
+}
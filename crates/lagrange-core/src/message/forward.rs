@@ -0,0 +1,99 @@
+use super::MessageChain;
+use crate::internal::packets::message::{
+    PushContentHead, PushMessageBody, PushMsgBody, PushRichText, ResponseForward, ResponseGrp,
+    ResponseHead,
+};
+
+/// One message of a forwarded bundle.
+///
+/// The sender does not need to be a real contact, nodes are rendered with whatever name and
+/// time they carry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageNode {
+    pub sender_uin: u64,
+    pub sender_name: String,
+    /// Unix timestamp in seconds
+    pub time: i64,
+    pub chain: MessageChain,
+}
+
+impl MessageNode {
+    pub fn new(sender_uin: u64, sender_name: impl Into<String>, time: i64, chain: impl Into<MessageChain>) -> Self {
+        Self {
+            sender_uin,
+            sender_name: sender_name.into(),
+            time,
+            chain: chain.into(),
+        }
+    }
+
+    /// Lay the node out like a pushed message; `group_uin` decides between the group and the
+    /// friend layout
+    pub(crate) fn to_push_body(&self, group_uin: Option<u64>, sequence: u32) -> PushMsgBody {
+        let (msg_type, forward, grp) = match group_uin {
+            Some(group_uin) => (
+                PushContentHead::GROUP,
+                None,
+                Some(ResponseGrp {
+                    group_uin: Some(group_uin as u32),
+                    member_name: Some(self.sender_name.clone()),
+                    ..Default::default()
+                }),
+            ),
+            None => (
+                PushContentHead::FRIEND,
+                Some(ResponseForward {
+                    friend_name: Some(self.sender_name.clone()),
+                }),
+                None,
+            ),
+        };
+
+        PushMsgBody {
+            response_head: Some(ResponseHead {
+                from_uin: Some(self.sender_uin as u32),
+                forward,
+                grp,
+                ..Default::default()
+            }),
+            content_head: Some(PushContentHead {
+                msg_type: Some(msg_type),
+                random: Some(rand::random()),
+                sequence: Some(sequence),
+                timestamp: Some(self.time as u64),
+                pkg_num: Some(1),
+                ..Default::default()
+            }),
+            body: Some(PushMessageBody {
+                rich_text: Some(PushRichText {
+                    elems: self.chain.to_encoded_elems(),
+                }),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub(crate) fn from_push_body(body: PushMsgBody) -> Self {
+        let head = body.response_head.unwrap_or_default();
+        let sender_name = head
+            .grp
+            .and_then(|grp| grp.member_name)
+            .or_else(|| head.forward.and_then(|forward| forward.friend_name))
+            .unwrap_or_default();
+        let chain = body
+            .body
+            .and_then(|body| body.rich_text)
+            .map(|rich_text| MessageChain::from_encoded_elems(&rich_text.elems))
+            .unwrap_or_default();
+
+        Self {
+            sender_uin: head.from_uin.unwrap_or_default() as u64,
+            sender_name,
+            time: body
+                .content_head
+                .and_then(|content| content.timestamp)
+                .unwrap_or_default() as i64,
+            chain,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/// Identifies a sent message in the [`MessageStore`](crate::message::MessageStore); `0` is never
+/// handed out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MessageId(pub u64);
+
+impl std::fmt::Display for MessageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Chat a message was sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageTarget {
+    Friend(u64),
+    Group(u64),
+}
+
+/// Returned once the server accepted a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageReceipt {
+    /// Key of the receipt in [`BotContext::messages`](crate::BotContext::messages)
+    pub id: MessageId,
+    pub target: MessageTarget,
+    /// Sequence assigned by the server, needed to recall or reply to the message
+    pub sequence: u32,
+    /// Sequence chosen by the client when sending
+    pub client_sequence: u32,
+    pub random: u32,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+}
@@ -0,0 +1,144 @@
+use super::{MessageId, MessageReceipt};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Receipts kept by default, see [`BotConfig::message_store_capacity`](crate::config::BotConfig::message_store_capacity)
+pub const DEFAULT_MESSAGE_STORE_CAPACITY: usize = 1024;
+
+/// How long receipts are kept by default
+pub const DEFAULT_MESSAGE_STORE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Receipts of the messages the bot sent, to recall or quote them later by [`MessageId`].
+///
+/// Ids are handed out in sending order, so the receipts form a ring buffer in which the oldest
+/// entry is dropped first, once there are more than `capacity` or it is older than `ttl`.
+#[derive(Debug)]
+pub struct MessageStore {
+    capacity: usize,
+    ttl: Duration,
+    inner: Mutex<Ring>,
+}
+
+#[derive(Debug)]
+struct Ring {
+    /// Id of the first entry of `entries`
+    first_id: u64,
+    entries: VecDeque<(Instant, MessageReceipt)>,
+}
+
+impl MessageStore {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            inner: Mutex::new(Ring { first_id: 1, entries: VecDeque::new() }),
+        }
+    }
+
+    /// Store `receipt` under a new id, which is also set on the returned receipt
+    pub fn insert(&self, mut receipt: MessageReceipt) -> MessageReceipt {
+        let now = Instant::now();
+        let mut ring = self.inner.lock().expect("Mutex poisoned");
+        receipt.id = MessageId(ring.first_id + ring.entries.len() as u64);
+        ring.entries.push_back((now, receipt));
+        self.prune(&mut ring, now);
+        receipt
+    }
+
+    /// The receipt stored under `id`, `None` if it was never stored or evicted already
+    pub fn get(&self, id: MessageId) -> Option<MessageReceipt> {
+        let now = Instant::now();
+        let mut ring = self.inner.lock().expect("Mutex poisoned");
+        self.prune(&mut ring, now);
+        let index = id.0.checked_sub(ring.first_id)?;
+        ring.entries.get(index as usize).map(|(_, receipt)| *receipt)
+    }
+
+    pub fn len(&self) -> usize {
+        let mut ring = self.inner.lock().expect("Mutex poisoned");
+        self.prune(&mut ring, Instant::now());
+        ring.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn prune(&self, ring: &mut Ring, now: Instant) {
+        while let Some((stored_at, _)) = ring.entries.front() {
+            if ring.entries.len() <= self.capacity && now.duration_since(*stored_at) < self.ttl {
+                break;
+            }
+            ring.entries.pop_front();
+            ring.first_id += 1;
+        }
+    }
+}
+
+impl Default for MessageStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MESSAGE_STORE_CAPACITY, DEFAULT_MESSAGE_STORE_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::MessageTarget;
+
+    fn receipt(sequence: u32) -> MessageReceipt {
+        MessageReceipt {
+            id: MessageId::default(),
+            target: MessageTarget::Group(123456),
+            sequence,
+            client_sequence: sequence,
+            random: 42,
+            timestamp: 1700000000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_and_get() {
+        let store = MessageStore::default();
+        let first = store.insert(receipt(10));
+        let second = store.insert(receipt(11));
+        assert_eq!(first.id, MessageId(1));
+        assert_eq!(second.id, MessageId(2));
+
+        assert_eq!(store.get(first.id), Some(first));
+        assert_eq!(store.get(second.id).unwrap().sequence, 11);
+        assert_eq!(store.get(MessageId(3)), None);
+        assert_eq!(store.get(MessageId::default()), None);
+    }
+
+    #[tokio::test]
+    async fn test_evicts_oldest_over_capacity() {
+        let store = MessageStore::new(2, DEFAULT_MESSAGE_STORE_TTL);
+        let ids: Vec<_> = (0..3).map(|sequence| store.insert(receipt(sequence)).id).collect();
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(ids[0]), None);
+        assert_eq!(store.get(ids[1]).unwrap().sequence, 1);
+        assert_eq!(store.get(ids[2]).unwrap().sequence, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_evicts_expired() {
+        let store = MessageStore::new(16, Duration::from_secs(60));
+        let old = store.insert(receipt(1));
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let recent = store.insert(receipt(2));
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(store.get(old.id), None);
+        assert_eq!(store.get(recent.id), Some(recent));
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(store.is_empty());
+
+        // Ids are not reused after evictions
+        assert_eq!(store.insert(receipt(3)).id, MessageId(3));
+    }
+}
@@ -1,31 +1,85 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-#[repr(u8)]
+/// A client to log in as; the built-in ones double as bits of service protocol masks.
+///
+/// Parsed from and displayed as case-insensitive names like `linux` or `android-phone`, which
+/// is also how it is written in config files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum Protocols {
-    None = 0b00000000,
-    Windows = 0b00000001,
-    MacOs = 0b00000010,
-    Linux = 0b00000100,
-    AndroidPhone = 0b00001000,
-    AndroidPad = 0b00010000,
-    AndroidWatch = 0b00100000,
+    None,
+    Windows,
+    MacOs,
+    #[default]
+    Linux,
+    AndroidPhone,
+    AndroidPad,
+    AndroidWatch,
+    /// A client build registered with [`AppInfoRegistry::register`](crate::common::AppInfoRegistry::register)
+    Custom(u8),
 }
 
 impl Protocols {
-    pub const PC: u8 = Self::Windows as u8 | Self::MacOs as u8 | Self::Linux as u8;
+    pub const PC: u8 = Self::Windows.bits() | Self::MacOs.bits() | Self::Linux.bits();
 
     pub const ANDROID: u8 =
-        Self::AndroidPhone as u8 | Self::AndroidPad as u8 | Self::AndroidWatch as u8;
+        Self::AndroidPhone.bits() | Self::AndroidPad.bits() | Self::AndroidWatch.bits();
 
     pub const ALL: u8 = Self::PC | Self::ANDROID;
 
+    /// Every protocol with a bit of its own, in bit order
+    pub const BUILT_IN: [Protocols; 6] = [
+        Self::Windows,
+        Self::MacOs,
+        Self::Linux,
+        Self::AndroidPhone,
+        Self::AndroidPad,
+        Self::AndroidWatch,
+    ];
+
+    /// Bit of a built-in protocol in service protocol masks, 0 for [`Protocols::Custom`]
+    pub const fn bits(&self) -> u8 {
+        match self {
+            Self::None | Self::Custom(_) => 0b00000000,
+            Self::Windows => 0b00000001,
+            Self::MacOs => 0b00000010,
+            Self::Linux => 0b00000100,
+            Self::AndroidPhone => 0b00001000,
+            Self::AndroidPad => 0b00010000,
+            Self::AndroidWatch => 0b00100000,
+        }
+    }
+
+    /// Bit in service protocol masks; custom protocols use the bit of the protocol they were
+    /// registered on
+    pub fn mask(&self) -> u8 {
+        match self {
+            Self::Custom(_) => crate::common::AppInfoRegistry::base(*self).map_or(0, |base| base.bits()),
+            protocol => protocol.bits(),
+        }
+    }
+
     pub fn matches(&self, mask: u8) -> bool {
-        (*self as u8) & mask != 0
+        self.mask() & mask != 0
     }
 
-    pub fn is_desktop(&self) -> bool {
+    /// Whether every protocol of the mask `other` is also in `mask`, e.g. `contains(ALL, PC)`;
+    /// always `false` for an empty `other`
+    pub const fn contains(mask: u8, other: u8) -> bool {
+        other != 0 && mask & other == other
+    }
+
+    /// The built-in protocols in `mask`
+    pub fn iter_mask(mask: u8) -> impl Iterator<Item = Protocols> {
+        Self::BUILT_IN.into_iter().filter(move |protocol| protocol.bits() & mask != 0)
+    }
+
+    pub fn is_pc(&self) -> bool {
         self.matches(Self::PC)
     }
 
+    /// Same as [`Protocols::is_pc`]
+    pub fn is_desktop(&self) -> bool {
+        self.is_pc()
+    }
+
     pub fn is_android(&self) -> bool {
         self.matches(Self::ANDROID)
     }
@@ -35,9 +89,65 @@ impl Protocols {
     }
 }
 
-impl Default for Protocols {
-    fn default() -> Self {
-        Self::Linux
+impl std::fmt::Display for Protocols {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Windows => f.write_str("windows"),
+            Self::MacOs => f.write_str("macos"),
+            Self::Linux => f.write_str("linux"),
+            Self::AndroidPhone => f.write_str("android-phone"),
+            Self::AndroidPad => f.write_str("android-pad"),
+            Self::AndroidWatch => f.write_str("android-watch"),
+            Self::Custom(id) => write!(f, "custom-{}", id),
+        }
+    }
+}
+
+impl std::str::FromStr for Protocols {
+    type Err = crate::error::Error;
+
+    /// Case-insensitive, with `-`, `_` and spaces between words optional: `AndroidPhone`,
+    /// `android_phone` and `android-phone` are the same
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let normalized: String = name
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        let protocol = match normalized.as_str() {
+            "none" => Self::None,
+            "windows" => Self::Windows,
+            "macos" => Self::MacOs,
+            "linux" => Self::Linux,
+            "androidphone" => Self::AndroidPhone,
+            "androidpad" => Self::AndroidPad,
+            "androidwatch" => Self::AndroidWatch,
+            other => match other.strip_prefix("custom").map(str::parse) {
+                Some(Ok(id)) => Self::Custom(id),
+                _ => {
+                    return Err(crate::error::Error::ParseError(format!(
+                        "unknown protocol `{}`, expected one of windows, macos, linux, android-phone, android-pad, \
+                         android-watch or custom-<id>",
+                        name
+                    )))
+                }
+            },
+        };
+        Ok(protocol)
+    }
+}
+
+impl serde::Serialize for Protocols {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Protocols {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -136,10 +246,21 @@ pub trait TypedService: Send + Sync + 'static {
     ) -> crate::Result<Self::Response>;
 }
 
+/// Packet an [`EventMessage`] was parsed from, to correlate the event with the packet logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventOrigin {
+    pub command: String,
+    pub sequence: i32,
+}
+
 #[derive(Clone)]
 pub struct EventMessage {
     type_id: std::any::TypeId,
 
+    type_name: &'static str,
+
+    origin: Option<std::sync::Arc<EventOrigin>>,
+
     payload: std::sync::Arc<dyn std::any::Any + Send + Sync>,
 }
 
@@ -147,40 +268,79 @@ impl EventMessage {
     pub fn new<T: ProtocolEvent>(event: T) -> Self {
         Self {
             type_id: std::any::TypeId::of::<T>(),
+            type_name: event.event_type(),
+            origin: None,
             payload: std::sync::Arc::new(event),
         }
     }
 
+    /// Record the packet this event was parsed from
+    pub fn with_origin(mut self, command: impl Into<String>, sequence: i32) -> Self {
+        self.origin = Some(std::sync::Arc::new(EventOrigin { command: command.into(), sequence }));
+        self
+    }
+
     pub fn type_id(&self) -> std::any::TypeId {
         self.type_id
     }
 
-    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+    /// [`ProtocolEvent::event_type`] of the payload
+    pub fn event_type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn origin(&self) -> Option<&EventOrigin> {
+        self.origin.as_deref()
+    }
+
+    pub fn is<T: ProtocolEvent>(&self) -> bool {
+        self.type_id == std::any::TypeId::of::<T>()
+    }
+
+    pub fn downcast_ref<T: ProtocolEvent>(&self) -> Option<&T> {
         self.payload.downcast_ref::<T>()
     }
 
-    pub fn downcast<T: 'static>(&self) -> Option<std::sync::Arc<T>> {
-        if self.type_id == std::any::TypeId::of::<T>() {
-            let ptr = std::sync::Arc::as_ptr(&self.payload);
-            let typed_ptr = ptr as *const T;
-            unsafe {
-                std::sync::Arc::increment_strong_count(typed_ptr);
-                Some(std::sync::Arc::from_raw(typed_ptr))
-            }
-        } else {
-            None
+    /// The payload as `T`, or the message back to try another type.
+    ///
+    /// The payload is shared with every clone of the message, such as those handed to other
+    /// subscribers, see [`EventMessage::try_unwrap`] to take it.
+    pub fn downcast<T: ProtocolEvent>(self) -> Result<std::sync::Arc<T>, EventMessage> {
+        match self.payload.downcast::<T>() {
+            Ok(payload) => Ok(payload),
+            Err(payload) => Err(Self { payload, ..self }),
         }
     }
+
+    /// The payload as an owned `T`, or the message back if it holds another type or is shared
+    /// with a clone
+    pub fn try_unwrap<T: ProtocolEvent>(self) -> Result<T, EventMessage> {
+        let (type_id, type_name, origin) = (self.type_id, self.type_name, self.origin.clone());
+        let payload = self.downcast::<T>()?;
+        std::sync::Arc::try_unwrap(payload).map_err(|payload| Self { type_id, type_name, origin, payload })
+    }
 }
 
 impl std::fmt::Debug for EventMessage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("EventMessage")
-            .field("type_id", &self.type_id)
+            .field("type_name", &self.type_name)
+            .field("origin", &self.origin)
             .finish()
     }
 }
 
+/// The event type, followed by the command and sequence of the packet it was parsed from
+impl std::fmt::Display for EventMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.type_name)?;
+        if let Some(origin) = &self.origin {
+            write!(f, " ({} #{})", origin.command, origin.sequence)?;
+        }
+        Ok(())
+    }
+}
+
 /// Request type for services commands.
 ///
 /// Specifies how the services request should be handled by the protocol layer.
@@ -309,6 +469,8 @@ pub struct ServiceMetadata {
     pub request_type: RequestType,
     pub encrypt_type: EncryptType,
     pub disable_log: bool,
+    /// Limits the service decodes its responses under
+    pub decode_options: lagrange_proto::DecodeOptions,
 }
 
 impl ServiceMetadata {
@@ -318,6 +480,7 @@ impl ServiceMetadata {
             request_type: RequestType::D2Auth,
             encrypt_type: EncryptType::EncryptD2Key,
             disable_log: false,
+            decode_options: lagrange_proto::DecodeOptions::default(),
         }
     }
 
@@ -335,4 +498,145 @@ impl ServiceMetadata {
         self.disable_log = disable;
         self
     }
+
+    /// Reject length-delimited fields longer than `max` in responses, for commands whose
+    /// responses are known to stay small
+    pub fn with_max_length_delimited(mut self, max: usize) -> Self {
+        self.decode_options = self.decode_options.with_max_length_delimited(max);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Ping(u32);
+
+    impl ProtocolEvent for Ping {}
+
+    #[derive(Debug)]
+    struct Pong;
+
+    impl ProtocolEvent for Pong {
+        fn event_type(&self) -> &'static str {
+            "Pong"
+        }
+    }
+
+    #[test]
+    fn test_event_downcast() {
+        let event = EventMessage::new(Ping(7));
+        assert!(event.is::<Ping>());
+        assert_eq!(event.downcast_ref::<Ping>(), Some(&Ping(7)));
+        assert!(event.downcast_ref::<Pong>().is_none());
+
+        // A failed downcast hands the message back for the next attempt
+        let event = event.downcast::<Pong>().unwrap_err();
+        assert_eq!(*event.downcast::<Ping>().unwrap(), Ping(7));
+    }
+
+    #[test]
+    fn test_event_try_unwrap() {
+        assert_eq!(EventMessage::new(Ping(7)).try_unwrap::<Ping>().unwrap(), Ping(7));
+
+        let event = EventMessage::new(Ping(7)).with_origin("Test.Command", 3);
+        let event = event.try_unwrap::<Pong>().unwrap_err();
+        assert_eq!(event.origin().unwrap().sequence, 3);
+
+        // Shared with a clone, such as one handed to another subscriber
+        let shared = event.clone();
+        let event = event.try_unwrap::<Ping>().unwrap_err();
+        drop(shared);
+        assert_eq!(event.try_unwrap::<Ping>().unwrap(), Ping(7));
+    }
+
+    #[test]
+    fn test_event_display() {
+        let event = EventMessage::new(Ping(7));
+        assert_eq!(event.event_type_name(), std::any::type_name::<Ping>());
+        assert_eq!(event.to_string(), std::any::type_name::<Ping>());
+
+        let event = EventMessage::new(Pong).with_origin("trpc.msg.olpush.OlPushService.MsgPush", 42);
+        assert_eq!(event.event_type_name(), "Pong");
+        assert_eq!(event.to_string(), "Pong (trpc.msg.olpush.OlPushService.MsgPush #42)");
+    }
+
+    #[test]
+    fn test_parse_names() {
+        let cases = [
+            ("none", Protocols::None),
+            ("windows", Protocols::Windows),
+            ("macos", Protocols::MacOs),
+            ("MacOs", Protocols::MacOs),
+            ("linux", Protocols::Linux),
+            ("LINUX", Protocols::Linux),
+            ("android-phone", Protocols::AndroidPhone),
+            ("AndroidPhone", Protocols::AndroidPhone),
+            ("android_pad", Protocols::AndroidPad),
+            ("Android Watch", Protocols::AndroidWatch),
+            ("custom-7", Protocols::Custom(7)),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(name.parse::<Protocols>().unwrap(), expected, "{name}");
+        }
+
+        for protocol in Protocols::BUILT_IN.into_iter().chain([Protocols::None, Protocols::Custom(200)]) {
+            assert_eq!(protocol.to_string().parse::<Protocols>().unwrap(), protocol);
+        }
+        assert_eq!(Protocols::AndroidPhone.to_string(), "android-phone");
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for name in ["", "ios", "linux2", "android", "custom", "custom-256"] {
+            assert!(name.parse::<Protocols>().is_err(), "{name}");
+        }
+        let err = "ios".parse::<Protocols>().unwrap_err();
+        assert!(err.to_string().contains("unknown protocol `ios`"), "{err}");
+    }
+
+    #[test]
+    fn test_serde_names() {
+        assert_eq!(serde_json::to_string(&Protocols::AndroidPad).unwrap(), "\"android-pad\"");
+        assert_eq!(serde_json::from_str::<Protocols>("\"Linux\"").unwrap(), Protocols::Linux);
+        assert!(serde_json::from_str::<Protocols>("\"ios\"").is_err());
+    }
+
+    #[test]
+    fn test_mask_membership() {
+        let masks = [("PC", Protocols::PC), ("ANDROID", Protocols::ANDROID), ("ALL", Protocols::ALL)];
+        for protocol in Protocols::BUILT_IN {
+            let is_pc = matches!(protocol, Protocols::Windows | Protocols::MacOs | Protocols::Linux);
+            for (name, mask) in masks {
+                let expected = match name {
+                    "PC" => is_pc,
+                    "ANDROID" => !is_pc,
+                    _ => true,
+                };
+                assert_eq!(Protocols::contains(mask, protocol.bits()), expected, "{name} {protocol}");
+                assert_eq!(protocol.matches(mask), expected, "{name} {protocol}");
+            }
+            assert_eq!(protocol.is_pc(), is_pc);
+            assert_eq!(protocol.is_android(), !is_pc);
+        }
+
+        assert!(Protocols::contains(Protocols::ALL, Protocols::PC));
+        assert!(Protocols::contains(Protocols::ALL, Protocols::ANDROID));
+        assert!(!Protocols::contains(Protocols::PC, Protocols::ALL));
+        assert!(!Protocols::contains(Protocols::PC, Protocols::ANDROID));
+        assert!(!Protocols::contains(Protocols::ALL, Protocols::None.bits()));
+        assert!(!Protocols::None.is_pc() && !Protocols::None.is_android());
+    }
+
+    #[test]
+    fn test_iter_mask() {
+        let pc: Vec<_> = Protocols::iter_mask(Protocols::PC).collect();
+        assert_eq!(pc, [Protocols::Windows, Protocols::MacOs, Protocols::Linux]);
+        let android: Vec<_> = Protocols::iter_mask(Protocols::ANDROID).collect();
+        assert_eq!(android, [Protocols::AndroidPhone, Protocols::AndroidPad, Protocols::AndroidWatch]);
+        assert_eq!(Protocols::iter_mask(Protocols::ALL).count(), 6);
+        assert_eq!(Protocols::iter_mask(0).count(), 0);
+    }
 }
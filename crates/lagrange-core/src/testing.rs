@@ -0,0 +1,353 @@
+//! Helpers to run a [`BotContext`](crate::BotContext) against an in-memory server, enabled by
+//! the `test-util` feature.
+//!
+//! ```
+//! use lagrange_core::internal::services::system::{AliveEventReq, AliveService};
+//! use lagrange_core::keystore::BotKeystore;
+//! use lagrange_core::testing::{Expectation, MockServer};
+//! use lagrange_core::BotContext;
+//! use bytes::Bytes;
+//! use std::time::Duration;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let (transport, server) = MockServer::start(BotKeystore::default());
+//! server.expect(Expectation::new("Heartbeat.Alive").respond(Bytes::new()).after(Duration::from_millis(20)));
+//!
+//! let context = BotContext::builder().transport(transport).try_build().unwrap();
+//! context.connect().await.unwrap();
+//! context.event.send::<AliveService>(AliveEventReq {}, context.clone()).await.unwrap();
+//! assert_eq!(server.received()[0].command, "Heartbeat.Alive");
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::internal::context::Transport;
+use crate::internal::sso;
+use crate::internal::SsoPacket;
+use crate::keystore::BotKeystore;
+use crate::protocol::EncryptType;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch, Notify};
+use tokio::task::JoinHandle;
+
+/// [`Transport`] whose connections are accepted by a [`MemoryListener`] in the same process.
+///
+/// Frames are passed as they are, without the length header of TCP.
+pub struct MemoryTransport {
+    accept_tx: mpsc::UnboundedSender<ServerConnection>,
+    inbound: tokio::sync::Mutex<Option<mpsc::UnboundedReceiver<Bytes>>>,
+    outbound: Mutex<Option<mpsc::UnboundedSender<Bytes>>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> (Arc<Self>, MemoryListener) {
+        let (accept_tx, accept_rx) = mpsc::unbounded_channel();
+        let transport = Arc::new(Self {
+            accept_tx,
+            inbound: tokio::sync::Mutex::new(None),
+            outbound: Mutex::new(None),
+        });
+        (transport, MemoryListener { accept_rx })
+    }
+}
+
+fn not_connected() -> Error {
+    Error::network(std::io::ErrorKind::NotConnected, "Socket closed")
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn connect(&self) -> Result<()> {
+        self.close().await;
+
+        let (to_server, from_client) = mpsc::unbounded_channel();
+        let (to_client, from_server) = mpsc::unbounded_channel();
+        self.accept_tx
+            .send(ServerConnection { from_client, to_client })
+            .map_err(|_| Error::network(std::io::ErrorKind::ConnectionRefused, "Memory listener dropped"))?;
+        *self.inbound.lock().await = Some(from_server);
+        *self.outbound.lock().expect("Mutex poisoned") = Some(to_server);
+        Ok(())
+    }
+
+    async fn read_frame(&self) -> Result<Bytes> {
+        let mut inbound = self.inbound.lock().await;
+        let inbound = inbound.as_mut().ok_or_else(not_connected)?;
+        inbound
+            .recv()
+            .await
+            .ok_or_else(|| Error::network(std::io::ErrorKind::UnexpectedEof, "Connection closed by server"))
+    }
+
+    async fn write_frame(&self, frame: Bytes) -> Result<()> {
+        let outbound = self.outbound.lock().expect("Mutex poisoned").clone().ok_or_else(not_connected)?;
+        outbound
+            .send(frame)
+            .map_err(|_| Error::network(std::io::ErrorKind::BrokenPipe, "Connection closed by server"))
+    }
+
+    async fn close(&self) {
+        self.outbound.lock().expect("Mutex poisoned").take();
+        self.inbound.lock().await.take();
+    }
+}
+
+/// Server side of a [`MemoryTransport`]
+pub struct MemoryListener {
+    accept_rx: mpsc::UnboundedReceiver<ServerConnection>,
+}
+
+impl MemoryListener {
+    /// Wait for the next connection, `None` once the transport was dropped
+    pub async fn accept(&mut self) -> Option<ServerConnection> {
+        self.accept_rx.recv().await
+    }
+}
+
+/// One connection accepted by a [`MemoryListener`]; dropping it closes the connection
+pub struct ServerConnection {
+    from_client: mpsc::UnboundedReceiver<Bytes>,
+    to_client: mpsc::UnboundedSender<Bytes>,
+}
+
+impl ServerConnection {
+    /// Next frame of the client, `None` once it closed the connection
+    pub async fn recv(&mut self) -> Option<Bytes> {
+        self.from_client.recv().await
+    }
+
+    /// Send `frame` to the client, `false` if it closed the connection
+    pub fn send(&self, frame: Bytes) -> bool {
+        self.to_client.send(frame).is_ok()
+    }
+}
+
+type Responder = Box<dyn Fn(&SsoPacket) -> SsoPacket + Send + Sync>;
+
+/// What a [`MockServer`] does with the requests to one command
+pub struct Expectation {
+    command: String,
+    responder: Option<Responder>,
+    delay: Duration,
+    remaining: Option<usize>,
+}
+
+impl Expectation {
+    /// Accept requests to `command` without answering them, so they time out like they would
+    /// against a server that ignores them
+    pub fn new(command: impl Into<String>) -> Self {
+        Self { command: command.into(), responder: None, delay: Duration::ZERO, remaining: None }
+    }
+
+    /// Answer with `body`
+    pub fn respond(self, body: impl Into<Bytes>) -> Self {
+        let body = body.into();
+        self.respond_with(move |_| body.clone())
+    }
+
+    /// Answer with the body `respond` builds for the request
+    pub fn respond_with(mut self, respond: impl Fn(&SsoPacket) -> Bytes + Send + Sync + 'static) -> Self {
+        self.responder = Some(Box::new(move |request| {
+            SsoPacket::new(request.command.clone(), respond(request), request.sequence)
+        }));
+        self
+    }
+
+    /// Answer with an SSO error, e.g. `-10003` for an expired token
+    pub fn reject(mut self, ret_code: i32, message: impl Into<String>) -> Self {
+        let message = message.into();
+        self.responder = Some(Box::new(move |request| {
+            SsoPacket::new_error(request.command.clone(), request.sequence, ret_code, message.clone())
+        }));
+        self
+    }
+
+    /// Send the answer `delay` after the request arrived
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Match only the next `times` requests; later ones are unexpected unless another
+    /// expectation covers them
+    pub fn times(mut self, times: usize) -> Self {
+        self.remaining = Some(times);
+        self
+    }
+}
+
+/// A fake SSO server answering requests by the [`Expectation`]s registered for their command.
+///
+/// Requests matching no expectation are recorded as unmatched and left unanswered, and the
+/// server panics when dropped if there were any, so a test fails on packets it did not plan
+/// for. Heartbeats are answered on their own unless an expectation covers them.
+pub struct MockServer {
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+struct Shared {
+    keystore: BotKeystore,
+    expectations: Mutex<Vec<Expectation>>,
+    received: Mutex<Vec<SsoPacket>>,
+    unmatched: Mutex<Vec<SsoPacket>>,
+    /// Sender to the client of the current connection
+    client: watch::Sender<Option<mpsc::UnboundedSender<Bytes>>>,
+    connections: AtomicUsize,
+    drop_connection: Notify,
+    push_sequence: AtomicI32,
+}
+
+const HEARTBEAT_COMMAND: &str = "Heartbeat.Alive";
+
+impl MockServer {
+    /// Serve a new [`MemoryTransport`], decrypting requests with the D2 key of `keystore`, the
+    /// one the bot logged in with
+    pub fn start(keystore: BotKeystore) -> (Arc<MemoryTransport>, Self) {
+        let (transport, listener) = MemoryTransport::new();
+        (transport, Self::listen(listener, keystore))
+    }
+
+    /// Serve the connections of `listener` one after the other until the transport is dropped
+    pub fn listen(mut listener: MemoryListener, keystore: BotKeystore) -> Self {
+        let shared = Arc::new(Shared {
+            keystore,
+            expectations: Mutex::new(Vec::new()),
+            received: Mutex::new(Vec::new()),
+            unmatched: Mutex::new(Vec::new()),
+            client: watch::Sender::new(None),
+            connections: AtomicUsize::new(0),
+            drop_connection: Notify::new(),
+            // Counting down, out of the way of the sequences of the bot
+            push_sequence: AtomicI32::new(i32::MAX),
+        });
+        let served = shared.clone();
+        let task = tokio::spawn(async move {
+            while let Some(mut connection) = listener.accept().await {
+                served.connections.fetch_add(1, Ordering::SeqCst);
+                served.client.send_replace(Some(connection.to_client.clone()));
+                loop {
+                    let frame = tokio::select! {
+                        frame = connection.recv() => frame,
+                        _ = served.drop_connection.notified() => None,
+                    };
+                    let Some(frame) = frame else { break };
+                    match sso::parse_request(&frame, &served.keystore) {
+                        Ok(request) => served.answer(&connection, request),
+                        Err(e) => tracing::warn!(error = %e, "Mock server received an invalid frame"),
+                    }
+                }
+                served.client.send_replace(None);
+            }
+        });
+        Self { shared, task }
+    }
+
+    /// Register `expectation`; a request is handled by the first expectation for its command
+    /// that has not run out
+    pub fn expect(&self, expectation: Expectation) {
+        self.shared.expectations.lock().expect("Mutex poisoned").push(expectation);
+    }
+
+    /// Send an unsolicited packet to the bot once it is connected, `false` if it closed the
+    /// connection meanwhile
+    pub async fn push(&self, command: &str, body: impl Into<Bytes>) -> bool {
+        let sequence = self.shared.push_sequence.fetch_sub(1, Ordering::SeqCst);
+        let packet = SsoPacket::new(command.to_string(), body.into(), sequence);
+        let mut connected = self.shared.client.subscribe();
+        let client = match connected.wait_for(Option::is_some).await {
+            Ok(client) => client.clone(),
+            Err(_) => None,
+        };
+        client.is_some_and(|client| client.send(self.shared.frame(&packet)).is_ok())
+    }
+
+    /// Requests an expectation matched so far, in order, over all connections
+    pub fn received(&self) -> Vec<SsoPacket> {
+        self.shared.received.lock().expect("Mutex poisoned").clone()
+    }
+
+    /// Requests no expectation matched
+    pub fn unmatched(&self) -> Vec<SsoPacket> {
+        self.shared.unmatched.lock().expect("Mutex poisoned").clone()
+    }
+
+    /// Connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.shared.connections.load(Ordering::SeqCst)
+    }
+
+    /// Close the current connection from the server side, as a server restart would
+    pub fn drop_connection(&self) {
+        self.shared.drop_connection.notify_one();
+    }
+}
+
+impl Shared {
+    fn frame(&self, packet: &SsoPacket) -> Bytes {
+        sso::build_response(packet, &self.keystore, EncryptType::NoEncrypt, None)
+    }
+
+    fn answer(&self, connection: &ServerConnection, request: SsoPacket) {
+        let mut expectations = self.expectations.lock().expect("Mutex poisoned");
+        let found = expectations
+            .iter()
+            .position(|expectation| expectation.command == request.command && expectation.remaining != Some(0));
+        let Some(index) = found else {
+            let covered = expectations.iter().any(|expectation| expectation.command == request.command);
+            drop(expectations);
+            if request.command == HEARTBEAT_COMMAND && !covered {
+                connection.send(self.frame(&SsoPacket::new(request.command, Bytes::new(), request.sequence)));
+            } else {
+                tracing::warn!(command = %request.command, "Mock server received an unexpected request");
+                self.unmatched.lock().expect("Mutex poisoned").push(request);
+            }
+            return;
+        };
+
+        let expectation = &mut expectations[index];
+        if let Some(remaining) = &mut expectation.remaining {
+            *remaining -= 1;
+        }
+        if let Some(responder) = &expectation.responder {
+            let frame = self.frame(&responder(&request));
+            if expectation.delay.is_zero() {
+                connection.send(frame);
+            } else {
+                let (delay, client) = (expectation.delay, connection.to_client.clone());
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = client.send(frame);
+                });
+            }
+        }
+        drop(expectations);
+        self.received.lock().expect("Mutex poisoned").push(request);
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+
+        let unmatched = self.unmatched();
+        if !unmatched.is_empty() && !std::thread::panicking() {
+            let commands: Vec<_> = unmatched.iter().map(|packet| packet.command.as_str()).collect();
+            panic!("Mock server received {} unexpected request(s): {:?}", commands.len(), commands);
+        }
+    }
+}
+
+/// Decode the hex dump of a test fixture, panicking on anything that is not hex
+#[cfg(test)]
+pub(crate) fn unhex(hex: &str) -> Bytes {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
@@ -1,7 +1,15 @@
+pub mod audio;
 pub mod binary;
 pub mod common;
 pub mod crypto;
+pub mod image;
+pub mod redact;
+pub mod upload;
 
 pub use binary::{BinaryPacket, Prefix};
 pub use common::tlv_unpack;
+pub use redact::Redacted;
+#[cfg(feature = "debug-secrets")]
+pub use redact::Unredacted;
+pub use upload::{FileDigest, UploadSource};
 pub use crypto::{EcdhProvider, EllipticCurve, EllipticCurveType, EllipticPoint, Sha1Stream};
@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+/// Voice formats accepted for records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    /// SILK v3, as produced by QQ itself
+    Silk,
+    /// AMR narrowband
+    Amr,
+}
+
+impl AudioFormat {
+    /// `voice_format` of the format in rich media requests
+    pub fn voice_format(self) -> u32 {
+        match self {
+            AudioFormat::Silk => 1,
+            AudioFormat::Amr => 0,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioFormat::Silk => "silk",
+            AudioFormat::Amr => "amr",
+        }
+    }
+}
+
+/// Format and length of a voice clip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioInfo {
+    pub format: AudioFormat,
+    pub duration: Duration,
+}
+
+impl AudioInfo {
+    /// Duration in whole seconds as shown by clients, at least one
+    pub fn seconds(&self) -> u32 {
+        (self.duration.as_millis().div_ceil(1000) as u32).max(1)
+    }
+}
+
+const SILK_HEADER: &[u8] = b"#!SILK_V3";
+const AMR_HEADER: &[u8] = b"#!AMR\n";
+
+/// Both formats use 20 ms frames
+const FRAME_DURATION: Duration = Duration::from_millis(20);
+
+/// Sizes of AMR-NB frames including their header byte, indexed by frame type
+const AMR_FRAME_SIZES: [usize; 16] = [13, 14, 16, 18, 20, 21, 27, 32, 6, 1, 1, 1, 1, 1, 1, 1];
+
+/// Read format and duration of a SILK v3 or AMR file, `None` if it is neither
+pub fn probe(data: &[u8]) -> Option<AudioInfo> {
+    let mut probe = AudioProbe::new();
+    probe.feed(data);
+    probe.finish()
+}
+
+/// Bytes needed to tell the formats apart: QQ prefixes its SILK files with 0x02
+const HEAD_LENGTH: usize = 1 + SILK_HEADER.len();
+
+/// [`probe`] for a file read in pieces, which never holds more than the header of the file
+#[derive(Debug, Default)]
+pub struct AudioProbe {
+    /// Start of the file until the format is known
+    head: Vec<u8>,
+    format: Option<AudioFormat>,
+    /// The head matched neither format
+    unknown: bool,
+    /// A SILK stream reached its end marker
+    ended: bool,
+    frames: u32,
+    /// Bytes of the current frame still to come, `None` between frames
+    remaining: Option<usize>,
+    /// First byte of a SILK frame length split between two pieces
+    length_byte: Option<u8>,
+}
+
+impl AudioProbe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process the next piece of the file
+    pub fn feed(&mut self, mut data: &[u8]) {
+        if self.format.is_none() {
+            if self.unknown {
+                return;
+            }
+            let take = (HEAD_LENGTH - self.head.len()).min(data.len());
+            self.head.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.head.len() < HEAD_LENGTH || !self.detect() {
+                return;
+            }
+        }
+        self.feed_frames(data);
+    }
+
+    /// Format and duration of the file, once all of it was fed
+    pub fn finish(mut self) -> Option<AudioInfo> {
+        if self.format.is_none() && !self.unknown {
+            self.detect();
+        }
+        let format = self.format?;
+        // A truncated SILK frame makes the stream invalid, a truncated AMR frame is ignored
+        if format == AudioFormat::Silk && self.remaining.is_some() {
+            return None;
+        }
+        Some(AudioInfo { format, duration: FRAME_DURATION * self.frames })
+    }
+
+    /// Match the head against the file headers, the frames start right after
+    fn detect(&mut self) -> bool {
+        let head = std::mem::take(&mut self.head);
+        let silk = head.strip_prefix(&[0x02]).unwrap_or(&head);
+        let (format, frames) = if let Some(frames) = silk.strip_prefix(SILK_HEADER) {
+            (AudioFormat::Silk, frames)
+        } else if let Some(frames) = head.strip_prefix(AMR_HEADER) {
+            (AudioFormat::Amr, frames)
+        } else {
+            self.unknown = true;
+            return false;
+        };
+
+        self.format = Some(format);
+        self.feed_frames(frames);
+        true
+    }
+
+    /// Each SILK frame is prefixed with its length as u16 LE, a length of 0xFFFF ends the
+    /// stream. The header byte of an AMR frame gives its size.
+    fn feed_frames(&mut self, mut data: &[u8]) {
+        while !data.is_empty() && !self.ended {
+            if let Some(remaining) = self.remaining {
+                let skipped = remaining.min(data.len());
+                data = &data[skipped..];
+                self.remaining = None;
+                self.start_frame(remaining - skipped);
+                continue;
+            }
+
+            match self.format {
+                Some(AudioFormat::Silk) => {
+                    let length = match self.length_byte.take() {
+                        Some(low) => {
+                            let length = u16::from_le_bytes([low, data[0]]);
+                            data = &data[1..];
+                            length
+                        }
+                        None if data.len() >= 2 => {
+                            let length = u16::from_le_bytes([data[0], data[1]]);
+                            data = &data[2..];
+                            length
+                        }
+                        None => {
+                            self.length_byte = Some(data[0]);
+                            return;
+                        }
+                    };
+                    if length == 0xFFFF {
+                        self.ended = true;
+                    } else {
+                        self.start_frame(length as usize);
+                    }
+                }
+                Some(AudioFormat::Amr) => {
+                    let size = AMR_FRAME_SIZES[((data[0] >> 3) & 0x0F) as usize];
+                    data = &data[1..];
+                    self.start_frame(size - 1);
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Expect `length` more bytes of the current frame, counting it once they are there
+    fn start_frame(&mut self, length: usize) {
+        if length == 0 {
+            self.frames += 1;
+        } else {
+            self.remaining = Some(length);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Synthetic SILK v3 stream: the QQ header and 132 frames of filler payload
+    const SILK_FIXTURE: &[u8] = include_bytes!("../../tests/fixtures/voice.silk");
+
+    #[test]
+    fn test_silk_duration() {
+        let info = probe(SILK_FIXTURE).unwrap();
+        assert_eq!(info.format, AudioFormat::Silk);
+        assert_eq!(info.duration, Duration::from_millis(2640));
+        assert_eq!(info.seconds(), 3);
+    }
+
+    #[test]
+    fn test_silk_without_prefix() {
+        let info = probe(&SILK_FIXTURE[1..]).unwrap();
+        assert_eq!(info.duration, Duration::from_millis(2640));
+    }
+
+    #[test]
+    fn test_truncated_silk() {
+        assert_eq!(probe(&SILK_FIXTURE[..SILK_FIXTURE.len() - 1]), None);
+    }
+
+    #[test]
+    fn test_amr_duration() {
+        // Header and 10 frames of mode 7 (12.2 kbit/s, 32 bytes each)
+        let mut amr = AMR_HEADER.to_vec();
+        for _ in 0..10 {
+            amr.push(7 << 3 | 0x04);
+            amr.extend([0; 31]);
+        }
+
+        let info = probe(&amr).unwrap();
+        assert_eq!(info.format, AudioFormat::Amr);
+        assert_eq!(info.duration, Duration::from_millis(200));
+        assert_eq!(info.seconds(), 1);
+    }
+
+    #[test]
+    fn test_fed_in_pieces() {
+        for piece_size in [1, 2, 3, 7, 64, 1000] {
+            let mut probe = AudioProbe::new();
+            for piece in SILK_FIXTURE.chunks(piece_size) {
+                probe.feed(piece);
+            }
+            assert_eq!(probe.finish(), super::probe(SILK_FIXTURE), "pieces of {}", piece_size);
+        }
+
+        let mut probe = AudioProbe::new();
+        for piece in SILK_FIXTURE[..SILK_FIXTURE.len() - 1].chunks(5) {
+            probe.feed(piece);
+        }
+        assert_eq!(probe.finish(), None);
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert_eq!(probe(b"RIFF\0\0\0\0WAVE"), None);
+    }
+}
@@ -16,6 +16,8 @@ mod private {
     impl Sealed for u128 {}
     impl Sealed for isize {}
     impl Sealed for usize {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
 }
 
 impl EndianSwap for i8 {
@@ -102,6 +104,21 @@ impl EndianSwap for usize {
     }
 }
 
+// Floats swap the bytes of their bits; `to_bits`/`from_bits` keep NaN payloads and -0.0 intact
+impl EndianSwap for f32 {
+    #[inline(always)]
+    fn swap_bytes(self) -> Self {
+        f32::from_bits(self.to_bits().swap_bytes())
+    }
+}
+
+impl EndianSwap for f64 {
+    #[inline(always)]
+    fn swap_bytes(self) -> Self {
+        f64::from_bits(self.to_bits().swap_bytes())
+    }
+}
+
 #[inline(always)]
 pub fn reverse_endianness<T: EndianSwap>(value: T) -> T {
     value.swap_bytes()
@@ -154,4 +171,13 @@ mod tests {
         assert_eq!(reverse_endianness(0x1234i16), 0x3412i16);
         assert_eq!(reverse_endianness(-12345i32), reverse_endianness(-12345i32));
     }
+
+    #[test]
+    fn test_float_types() {
+        assert_eq!(reverse_endianness(1.0f32).to_bits(), 0x0000803F);
+        assert_eq!(reverse_endianness(1.0f64).to_bits(), 0x000000000000F03F);
+        for value in [0.0f64, -0.0, 1.5, f64::MIN_POSITIVE, f64::INFINITY] {
+            assert_eq!(from_be(to_be(value)).to_bits(), value.to_bits());
+        }
+    }
 }
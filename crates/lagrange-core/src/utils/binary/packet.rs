@@ -179,6 +179,18 @@ impl BinaryPacket {
         Ok(())
     }
 
+    /// Writes an IEEE 754 float in big-endian, keeping NaN payloads and the sign of zero
+    #[inline]
+    pub fn write_f32(&mut self, value: f32) -> &mut Self {
+        self.write(value.to_bits())
+    }
+
+    /// Writes an IEEE 754 double in big-endian, keeping NaN payloads and the sign of zero
+    #[inline]
+    pub fn write_f64(&mut self, value: f64) -> &mut Self {
+        self.write(value.to_bits())
+    }
+
     #[inline]
     fn calculate_length(&self, length: usize, prefix: Prefix, addition: i32) -> usize {
         let mut len = length as i32 + addition;
@@ -269,6 +281,18 @@ impl BinaryPacket {
         Ok(from_be(value))
     }
 
+    /// Reads a big-endian IEEE 754 float, see [`BinaryPacket::write_f32`]
+    #[inline]
+    pub fn read_f32(&mut self) -> Result<f32> {
+        self.read::<u32>().map(f32::from_bits)
+    }
+
+    /// Reads a big-endian IEEE 754 double, see [`BinaryPacket::write_f64`]
+    #[inline]
+    pub fn read_f64(&mut self) -> Result<f64> {
+        self.read::<u64>().map(f64::from_bits)
+    }
+
     #[inline]
     fn read_length(&mut self, prefix: Prefix) -> Result<usize> {
         let prefix_len = prefix.prefix_length();
@@ -461,6 +485,52 @@ mod tests {
         assert_eq!(read_packet.read::<u64>().unwrap(), 0x123456789ABCDEFu64);
     }
 
+    #[test]
+    fn test_write_read_floats() {
+        let mut packet = BinaryPacket::with_capacity(64);
+
+        packet
+            .write(1.5f32)
+            .write(-2.25f64)
+            .write_f32(39.9042)
+            .write_f64(116.407396);
+
+        assert_eq!(&packet.as_slice()[..4], &[0x3F, 0xC0, 0x00, 0x00]);
+        assert_eq!(&packet.as_slice()[4..12], &[0xC0, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut read_packet = BinaryPacket::from(packet.to_vec());
+
+        assert_eq!(read_packet.read::<f32>().unwrap(), 1.5f32);
+        assert_eq!(read_packet.read::<f64>().unwrap(), -2.25f64);
+        assert_eq!(read_packet.read_f32().unwrap(), 39.9042f32);
+        assert_eq!(read_packet.read_f64().unwrap(), 116.407396f64);
+    }
+
+    #[test]
+    fn test_write_read_special_floats() {
+        // A quiet NaN with a payload, and a signaling NaN
+        let nan32 = [f32::from_bits(0x7FC0_1234), f32::from_bits(0x7F80_0001), -0.0, f32::NEG_INFINITY];
+        let nan64 = [f64::from_bits(0x7FF8_0000_DEAD_BEEF), f64::from_bits(0xFFF0_0000_0000_0001), -0.0];
+
+        let mut packet = BinaryPacket::with_capacity(64);
+        for &value in &nan32 {
+            packet.write(value).write_f32(value);
+        }
+        for &value in &nan64 {
+            packet.write(value).write_f64(value);
+        }
+
+        let mut read_packet = BinaryPacket::from(packet.to_vec());
+        for &value in &nan32 {
+            assert_eq!(read_packet.read::<f32>().unwrap().to_bits(), value.to_bits());
+            assert_eq!(read_packet.read_f32().unwrap().to_bits(), value.to_bits());
+        }
+        for &value in &nan64 {
+            assert_eq!(read_packet.read::<f64>().unwrap().to_bits(), value.to_bits());
+            assert_eq!(read_packet.read_f64().unwrap().to_bits(), value.to_bits());
+        }
+    }
+
     #[test]
     fn test_write_read_bytes() {
         let mut packet = BinaryPacket::with_capacity(64);
@@ -16,78 +16,71 @@ pub struct EllipticCurve {
     pub n: BigInt,
 }
 
-impl EllipticCurve {
-    /// Creates the Secp192K1 curve (192-bit Koblitz curve)
-    pub fn secp192k1() -> Self {
-        let p =
-            BigInt::parse_bytes(b"FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFEE37", 16).unwrap();
-
-        let a = BigInt::from(0);
-        let b = BigInt::from(3);
-
-        let gx =
-            BigInt::parse_bytes(b"DB4FF10EC057E9AE26B07D0280B7F4341DA5D1B1EAE06C7D", 16).unwrap();
-
-        let gy =
-            BigInt::parse_bytes(b"9B2F2F6D9C5628A7844163D015BE86344082AA88D95E2F9D", 16).unwrap();
+/// Parameters of a short Weierstrass curve `y² = x³ + ax + b`, in hex
+struct CurveParams {
+    p: &'static str,
+    a: &'static str,
+    b: &'static str,
+    gx: &'static str,
+    gy: &'static str,
+    n: &'static str,
+}
 
-        let n =
-            BigInt::parse_bytes(b"FFFFFFFFFFFFFFFFFFFFFFFE26F2FC170F69466A74DEFD8D", 16).unwrap();
+/// Secp192K1, the 192-bit Koblitz curve of the wtlogin key exchange
+const SECP192K1: CurveParams = CurveParams {
+    p: "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFEE37",
+    a: "0",
+    b: "3",
+    gx: "DB4FF10EC057E9AE26B07D0280B7F4341DA5D1B1EAE06C7D",
+    gy: "9B2F2F6D9C5628A7844163D015BE86344082AA88D95E2F9D",
+    n: "FFFFFFFFFFFFFFFFFFFFFFFE26F2FC170F69466A74DEFD8D",
+};
+
+/// Prime256V1, NIST P-256
+const PRIME256V1: CurveParams = CurveParams {
+    p: "FFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFF",
+    a: "FFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFC",
+    b: "5AC635D8AA3A93E7B3EBBD55769886BC651D06B0CC53B0F63BCE3C3E27D2604B",
+    gx: "6B17D1F2E12C4247F8BCE6E563A440F277037D812DEB33A0F4A13945D898C296",
+    gy: "4FE342E2FE1A7F9B8EE7EB4A7C0F9E162BCE33576B315ECECBB6406837BF51F5",
+    n: "FFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551",
+};
+
+/// Secp256K1, the 256-bit Koblitz curve
+const SECP256K1: CurveParams = CurveParams {
+    p: "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F",
+    a: "0",
+    b: "7",
+    gx: "79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798",
+    gy: "483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8",
+    n: "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
+};
 
+impl EllipticCurve {
+    fn from_params(params: &CurveParams) -> Self {
+        let parse = |hex: &str| BigInt::parse_bytes(hex.as_bytes(), 16).unwrap();
         Self {
-            p,
-            a,
-            b,
-            g: EllipticPoint { x: gx, y: gy },
-            n,
+            p: parse(params.p),
+            a: parse(params.a),
+            b: parse(params.b),
+            g: EllipticPoint { x: parse(params.gx), y: parse(params.gy) },
+            n: parse(params.n),
         }
     }
 
+    /// Creates the Secp192K1 curve (192-bit Koblitz curve)
+    pub fn secp192k1() -> Self {
+        Self::from_params(&SECP192K1)
+    }
+
     /// Creates the Prime256V1 curve (NIST P-256)
     pub fn prime256v1() -> Self {
-        let p = BigInt::parse_bytes(
-            b"FFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFF",
-            16,
-        )
-        .unwrap();
-
-        let a = BigInt::parse_bytes(
-            b"FFFFFFFF00000001000000000000000000000000FFFFFFFFFFFFFFFFFFFFFFFC",
-            16,
-        )
-        .unwrap();
-
-        let b = BigInt::parse_bytes(
-            b"5AC635D8AA3A93E7B3EBBD55769886BC651D06B0CC53B0F63BCE3C3E27D2604B",
-            16,
-        )
-        .unwrap();
-
-        let gx = BigInt::parse_bytes(
-            b"6B17D1F2E12C4247F8BCE6E563A440F277037D812DEB33A0F4A13945D898C296",
-            16,
-        )
-        .unwrap();
-
-        let gy = BigInt::parse_bytes(
-            b"4FE342E2FE1A7F9B8EE7EB4A7C0F9E162BCE33576B315ECECBB6406837BF51F5",
-            16,
-        )
-        .unwrap();
-
-        let n = BigInt::parse_bytes(
-            b"FFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551",
-            16,
-        )
-        .unwrap();
+        Self::from_params(&PRIME256V1)
+    }
 
-        Self {
-            p,
-            a,
-            b,
-            g: EllipticPoint { x: gx, y: gy },
-            n,
-        }
+    /// Creates the Secp256K1 curve (256-bit Koblitz curve)
+    pub fn secp256k1() -> Self {
+        Self::from_params(&SECP256K1)
     }
 
     /// Modular reduction ensuring positive result
@@ -120,6 +113,13 @@ impl EllipticCurve {
             return Some(BigInt::from(0));
         }
 
+        // For primes p ≡ 3 (mod 4), as for all the curves here, y = n^((p+1)/4) mod p is the root
+        // if there is one, which squaring checks without a separate Euler's criterion
+        if &self.p % 4 == BigInt::from(3) {
+            let root = n.modpow(&((&self.p + 1) / 4), &self.p);
+            return (self.mod_positive(&(&root * &root)) == n).then_some(root);
+        }
+
         // Check if n is a quadratic residue using Euler's criterion: n^((p-1)/2) ≡ 1 (mod p)
         let exp = (&self.p - 1) / 2;
         let legendre = n.modpow(&exp, &self.p);
@@ -127,12 +127,6 @@ impl EllipticCurve {
             return None; // Not a quadratic residue
         }
 
-        // For primes p ≡ 3 (mod 4), we can use the simple formula: y = n^((p+1)/4) mod p
-        if &self.p % 4 == BigInt::from(3) {
-            let exp = (&self.p + 1) / 4;
-            return Some(n.modpow(&exp, &self.p));
-        }
-
         // General Tonelli-Shanks algorithm for p ≡ 1 (mod 4)
         // Express p - 1 = 2^s * q where q is odd
         let mut q = &self.p - 1;
@@ -382,10 +376,7 @@ pub struct EcdhProvider {
 impl EcdhProvider {
     /// Creates a new ECDH provider with the specified curve and generates a random key pair
     pub fn new(curve_type: EllipticCurveType) -> Self {
-        let (curve, coord_size) = match curve_type {
-            EllipticCurveType::Secp192K1 => (EllipticCurve::secp192k1(), 24), // 192 bits = 24 bytes
-            EllipticCurveType::Prime256V1 => (EllipticCurve::prime256v1(), 32), // 256 bits = 32 bytes
-        };
+        let (curve, coord_size) = (curve_type.curve(), curve_type.coord_size());
 
         let mut rng = rand::thread_rng();
         let mut secret_bytes = vec![0u8; coord_size];
@@ -412,12 +403,14 @@ impl EcdhProvider {
         Self::new(EllipticCurveType::Secp192K1)
     }
 
+    /// Creates a provider for Secp256K1 curve, with 32-byte coordinates
+    pub fn secp256k1() -> Self {
+        Self::new(EllipticCurveType::Secp256K1)
+    }
+
     /// Creates a new ECDH provider with a custom secret key
     pub fn with_secret(curve_type: EllipticCurveType, secret_bytes: &[u8]) -> Self {
-        let (curve, coord_size) = match curve_type {
-            EllipticCurveType::Secp192K1 => (EllipticCurve::secp192k1(), 24),
-            EllipticCurveType::Prime256V1 => (EllipticCurve::prime256v1(), 32),
-        };
+        let (curve, coord_size) = (curve_type.curve(), curve_type.coord_size());
 
         // Parse secret from bytes
         let secret = BigInt::from_bytes_be(Sign::Plus, secret_bytes);
@@ -526,6 +519,27 @@ impl EcdhProvider {
 pub enum EllipticCurveType {
     Secp192K1,
     Prime256V1,
+    Secp256K1,
+}
+
+impl EllipticCurveType {
+    fn params(self) -> &'static CurveParams {
+        match self {
+            EllipticCurveType::Secp192K1 => &SECP192K1,
+            EllipticCurveType::Prime256V1 => &PRIME256V1,
+            EllipticCurveType::Secp256K1 => &SECP256K1,
+        }
+    }
+
+    /// Parameters of the curve
+    pub fn curve(self) -> EllipticCurve {
+        EllipticCurve::from_params(self.params())
+    }
+
+    /// Bytes of a coordinate, and of a secret key, on the curve
+    pub fn coord_size(self) -> usize {
+        self.params().p.len() / 2
+    }
 }
 
 #[cfg(test)]
@@ -692,6 +706,64 @@ mod tests {
         assert_eq!(alice_shared, bob_shared);
     }
 
+    #[test]
+    fn test_secp256k1_generator() {
+        let curve = EllipticCurve::secp256k1();
+        assert!(curve.verify_point(&curve.g));
+        assert_eq!(&curve.p % 4, BigInt::from(3));
+        assert_eq!(EllipticCurveType::Secp256K1.coord_size(), 32);
+        assert_eq!(EllipticCurveType::Secp192K1.coord_size(), 24);
+
+        // 2G and nG from the SEC 2 parameters
+        let doubled = curve.scalar_multiply(&curve.g, &BigInt::from(2));
+        let expected_x = BigInt::parse_bytes(b"C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5", 16);
+        assert_eq!(Some(doubled.x), expected_x);
+        assert!(curve.scalar_multiply(&curve.g, &curve.n).is_identity());
+    }
+
+    #[test]
+    fn test_secp256k1_compressed_point_round_trip() {
+        let curve = EllipticCurve::secp256k1();
+
+        for scalar in [1, 2, 3, 7, 100] {
+            let point = curve.scalar_multiply(&curve.g, &BigInt::from(scalar));
+
+            // Compress and decompress with 32-byte coordinates
+            let compressed = point.to_compressed(32);
+            assert_eq!(compressed.len(), 33);
+            let decompressed = EllipticPoint::from_bytes(&compressed, &curve).unwrap();
+
+            assert_eq!(point, decompressed);
+            assert!(curve.verify_point(&decompressed));
+        }
+
+        // x = 5 has no point: 5³ + 7 = 132 is not a square modulo p
+        let mut invalid = vec![0x02; 1];
+        invalid.extend_from_slice(&[0; 31]);
+        invalid.push(5);
+        assert!(EllipticPoint::from_bytes(&invalid, &curve).is_err());
+    }
+
+    #[test]
+    fn test_secp256k1_key_exchange() {
+        let alice = EcdhProvider::secp256k1();
+        let bob = EcdhProvider::secp256k1();
+        assert_eq!(alice.secret_bytes().len(), 32);
+
+        for compressed in [false, true] {
+            let alice_public = alice.public_key_bytes(compressed);
+            let bob_public = bob.public_key_bytes(compressed);
+            assert_eq!(alice_public.len(), if compressed { 33 } else { 65 });
+
+            let alice_shared = alice.key_exchange(&bob_public, true).unwrap();
+            let bob_shared = bob.key_exchange(&alice_public, true).unwrap();
+            assert_eq!(alice_shared, bob_shared);
+        }
+
+        let with_secret = EcdhProvider::with_secret(EllipticCurveType::Secp256K1, &alice.secret_bytes());
+        assert_eq!(with_secret.public_key(), alice.public_key());
+    }
+
     #[test]
     fn test_secp192k1_compressed_points() {
         let curve = EllipticCurve::secp192k1();
@@ -1,5 +1,31 @@
 use rand::Rng;
 
+/// Reasons a TEA ciphertext or key is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TeaError {
+    #[error("Ciphertext is shorter than two blocks")]
+    InputTooShort,
+
+    #[error("Ciphertext length is not a multiple of the block size")]
+    NotBlockAligned,
+
+    #[error("Padding length does not fit in the ciphertext")]
+    BadPadding,
+
+    #[error("Trailing zero bytes do not match")]
+    TrailerMismatch,
+
+    #[error("Key is shorter than 16 bytes")]
+    KeyTooShort,
+}
+
+/// Converts the first 16 bytes of `key` into a TEA key
+pub fn key(key: &[u8]) -> Result<[u8; 16], TeaError> {
+    key.get(..16)
+        .and_then(|key| key.try_into().ok())
+        .ok_or(TeaError::KeyTooShort)
+}
+
 /// Encrypts data using TEA (Tiny Encryption Algorithm)
 pub fn encrypt(source: &[u8], key: &[u8; 16]) -> Vec<u8> {
         let k0 = u32::from_be_bytes(key[0..4].try_into().unwrap());
@@ -78,9 +104,15 @@ pub fn encrypt(source: &[u8], key: &[u8; 16]) -> Vec<u8> {
 }
 
 /// Decrypts data using TEA (Tiny Encryption Algorithm)
-pub fn decrypt(source: &[u8], key: &[u8; 16]) -> Result<Vec<u8>, &'static str> {
-        if source.len() < 16 || !source.len().is_multiple_of(8) {
-            return Err("Invalid ciphertext length");
+///
+/// The padding descriptor in the first block is checked before the rest is decrypted, and the
+/// 7-byte zero trailer afterwards, so corrupted input is reported instead of returned as garbage
+pub fn decrypt(source: &[u8], key: &[u8; 16]) -> Result<Vec<u8>, TeaError> {
+        if source.len() < 16 {
+            return Err(TeaError::InputTooShort);
+        }
+        if !source.len().is_multiple_of(8) {
+            return Err(TeaError::NotBlockAligned);
         }
 
         let k0 = u32::from_be_bytes(key[0..4].try_into().unwrap());
@@ -138,22 +170,20 @@ pub fn decrypt(source: &[u8], key: &[u8; 16]) -> Result<Vec<u8>, &'static str> {
 
             let bytes = output.to_be_bytes();
             decrypted[i..i + 8].copy_from_slice(&bytes);
-        }
 
-        let fill = ((decrypted[0] & 0x07) + 3) as usize;
-
-        if fill + 7 > decrypted.len() {
-            return Err("Invalid padding length");
+            if i == 0 && ((bytes[0] & 0x07) + 3) as usize + 7 > source.len() {
+                return Err(TeaError::BadPadding);
+            }
         }
 
-        let start = fill;
+        let fill = ((decrypted[0] & 0x07) + 3) as usize;
         let end = decrypted.len() - 7;
 
-        if start > end {
-            return Err("Invalid decrypted data");
+        if decrypted[end..].iter().any(|&b| b != 0) {
+            return Err(TeaError::TrailerMismatch);
         }
 
-        Ok(decrypted[start..end].to_vec())
+        Ok(decrypted[fill..end].to_vec())
 }
 
 #[cfg(test)]
@@ -208,4 +238,64 @@ mod tests {
 
         assert!(decrypt(&invalid, &key).is_err());
     }
+
+    #[test]
+    fn test_tea_truncated() {
+        let key = [0x42; 16];
+        let encrypted = encrypt(b"Hello, World!", &key);
+
+        assert_eq!(decrypt(&encrypted[..8], &key), Err(TeaError::InputTooShort));
+        assert_eq!(decrypt(&[], &key), Err(TeaError::InputTooShort));
+        // Dropping whole blocks keeps the alignment but loses the trailer
+        assert_eq!(decrypt(&encrypted[..16], &key), Err(TeaError::TrailerMismatch));
+    }
+
+    #[test]
+    fn test_tea_misaligned() {
+        let key = [0x42; 16];
+        let encrypted = encrypt(b"Hello, World!", &key);
+
+        assert_eq!(decrypt(&encrypted[..encrypted.len() - 1], &key), Err(TeaError::NotBlockAligned));
+        assert_eq!(decrypt(&[0u8; 17], &key), Err(TeaError::NotBlockAligned));
+    }
+
+    #[test]
+    fn test_tea_bit_flipped() {
+        let key = [0x42; 16];
+        let encrypted = encrypt(b"Hello, World!", &key);
+
+        // Every block feeds into the ones after it, so any flip garbles the trailer
+        for byte in 0..encrypted.len() {
+            let mut corrupted = encrypted.clone();
+            corrupted[byte] ^= 0x01;
+            assert_eq!(decrypt(&corrupted, &key), Err(TeaError::TrailerMismatch), "flip at byte {}", byte);
+        }
+    }
+
+    #[test]
+    fn test_tea_bad_padding() {
+        let key = [0x42; 16];
+        let encrypted = encrypt(b"", &key);
+        assert_eq!(encrypted.len(), 16);
+
+        // A garbled first block decodes to a random padding length, and the longest one
+        // does not fit in a two-block ciphertext
+        let mut bad_padding = 0;
+        for flip in 1..=255u8 {
+            let mut corrupted = encrypted.clone();
+            corrupted[0] ^= flip;
+            match decrypt(&corrupted, &key) {
+                Err(TeaError::BadPadding) => bad_padding += 1,
+                Err(TeaError::TrailerMismatch) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        assert!(bad_padding > 0);
+    }
+
+    #[test]
+    fn test_tea_key() {
+        assert_eq!(key(&[0x42; 20]), Ok([0x42; 16]));
+        assert_eq!(key(&[0x42; 15]), Err(TeaError::KeyTooShort));
+    }
 }
@@ -0,0 +1,151 @@
+/// Image formats recognized from their leading bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+}
+
+impl ImageFormat {
+    /// `pic_format` of the format in rich media requests
+    pub fn pic_format(self) -> u32 {
+        match self {
+            ImageFormat::Jpeg => 1000,
+            ImageFormat::Png => 1001,
+            ImageFormat::Gif => 2000,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
+        }
+    }
+}
+
+/// Format and dimensions of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Read format and dimensions from the header of `data`, `None` if it is not a PNG, JPEG or GIF
+pub fn probe(data: &[u8]) -> Option<ImageInfo> {
+    if data.starts_with(PNG_SIGNATURE) {
+        // The IHDR chunk always comes first
+        let ihdr = data.get(16..24)?;
+        return Some(ImageInfo {
+            format: ImageFormat::Png,
+            width: u32::from_be_bytes(ihdr[0..4].try_into().ok()?),
+            height: u32::from_be_bytes(ihdr[4..8].try_into().ok()?),
+        });
+    }
+
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        let screen = data.get(6..10)?;
+        return Some(ImageInfo {
+            format: ImageFormat::Gif,
+            width: u16::from_le_bytes([screen[0], screen[1]]) as u32,
+            height: u16::from_le_bytes([screen[2], screen[3]]) as u32,
+        });
+    }
+
+    if data.starts_with(&[0xFF, 0xD8]) {
+        return probe_jpeg(data);
+    }
+
+    None
+}
+
+/// Walk the JPEG segments up to the first start-of-frame
+fn probe_jpeg(data: &[u8]) -> Option<ImageInfo> {
+    let mut offset = 2;
+    loop {
+        // Markers may be padded with any number of 0xFF
+        while *data.get(offset)? == 0xFF && *data.get(offset + 1)? == 0xFF {
+            offset += 1;
+        }
+        if *data.get(offset)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(offset + 1)?;
+        let length = u16::from_be_bytes([*data.get(offset + 2)?, *data.get(offset + 3)?]) as usize;
+
+        // SOF0 to SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let frame = data.get(offset + 5..offset + 9)?;
+            return Some(ImageInfo {
+                format: ImageFormat::Jpeg,
+                height: u16::from_be_bytes([frame[0], frame[1]]) as u32,
+                width: u16::from_be_bytes([frame[2], frame[3]]) as u32,
+            });
+        }
+
+        offset += 2 + length;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png() {
+        // Signature and IHDR of a 640x480 image
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend([0, 0, 0, 13]);
+        png.extend(b"IHDR");
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+
+        assert_eq!(
+            probe(&png),
+            Some(ImageInfo { format: ImageFormat::Png, width: 640, height: 480 })
+        );
+    }
+
+    #[test]
+    fn test_gif() {
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend(300u16.to_le_bytes());
+        gif.extend(200u16.to_le_bytes());
+        gif.extend([0xF7, 0, 0]);
+
+        assert_eq!(
+            probe(&gif),
+            Some(ImageInfo { format: ImageFormat::Gif, width: 300, height: 200 })
+        );
+    }
+
+    #[test]
+    fn test_jpeg() {
+        // SOI, an APP0 segment, padding and a progressive SOF2 for 1024x768
+        let mut jpeg = vec![0xFF, 0xD8];
+        jpeg.extend([0xFF, 0xE0, 0x00, 0x10]);
+        jpeg.extend(b"JFIF\0");
+        jpeg.extend([0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+        jpeg.extend([0xFF, 0xFF, 0xC2, 0x00, 0x11, 0x08]);
+        jpeg.extend(768u16.to_be_bytes());
+        jpeg.extend(1024u16.to_be_bytes());
+        jpeg.extend([0x03, 0x01, 0x22, 0x00]);
+
+        assert_eq!(
+            probe(&jpeg),
+            Some(ImageInfo { format: ImageFormat::Jpeg, width: 1024, height: 768 })
+        );
+    }
+
+    #[test]
+    fn test_unknown_and_truncated() {
+        assert_eq!(probe(b"RIFF\0\0\0\0WEBP"), None);
+        assert_eq!(probe(PNG_SIGNATURE), None);
+        assert_eq!(probe(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]), None);
+    }
+}
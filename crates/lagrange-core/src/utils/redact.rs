@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Bytes shown only by their length and the hex of their first 4 bytes, for keys, tickets and
+/// other secrets that may end up in logs. Enough to tell two values apart, not to reuse them.
+///
+/// ```
+/// use lagrange_core::utils::Redacted;
+///
+/// let key = [0xde, 0xad, 0xbe, 0xef, 0x01, 0x02];
+/// assert_eq!(format!("{:?}", Redacted(&key)), "<6 bytes deadbeef..>");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Redacted<T>(pub T);
+
+/// Bytes of the value shown in the fingerprint
+const FINGERPRINT_LEN: usize = 4;
+
+impl<T: AsRef<[u8]>> fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.0.as_ref();
+        if bytes.is_empty() {
+            return f.write_str("<0 bytes>");
+        }
+        let shown = if unredacted() { bytes.len() } else { FINGERPRINT_LEN };
+        write!(f, "<{} bytes ", bytes.len())?;
+        for byte in bytes.iter().take(shown) {
+            write!(f, "{:02x}", byte)?;
+        }
+        if bytes.len() > shown {
+            f.write_str("..")?;
+        }
+        f.write_str(">")
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "debug-secrets")]
+thread_local! {
+    static UNREDACTED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+#[cfg(feature = "debug-secrets")]
+fn unredacted() -> bool {
+    UNREDACTED.with(|flag| flag.get())
+}
+
+#[cfg(not(feature = "debug-secrets"))]
+fn unredacted() -> bool {
+    false
+}
+
+/// [`Debug`](fmt::Debug) of the value with every [`Redacted`] inside shown in full, for local
+/// debugging only. Requires the `debug-secrets` feature, so that builds shipping logs cannot
+/// print secrets by accident.
+#[cfg(feature = "debug-secrets")]
+pub struct Unredacted<T>(pub T);
+
+#[cfg(feature = "debug-secrets")]
+impl<T: fmt::Debug> fmt::Debug for Unredacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Restores the previous state, also when formatting panics
+        struct Restore(bool);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                UNREDACTED.with(|flag| flag.set(self.0));
+            }
+        }
+
+        let _restore = Restore(UNREDACTED.with(|flag| flag.replace(true)));
+        self.0.fmt(f)
+    }
+}
+
+/// `map` with its values redacted, sorted by key for a stable output
+pub fn redact_values<K: Ord, V: AsRef<[u8]>>(map: &HashMap<K, V>) -> BTreeMap<&K, Redacted<&V>> {
+    map.iter().map(|(key, value)| (key, Redacted(value))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(Redacted([0u8; 0]).to_string(), "<0 bytes>");
+        assert_eq!(Redacted([0xab, 0xcd]).to_string(), "<2 bytes abcd>");
+        assert_eq!(Redacted(vec![0x01, 0x02, 0x03, 0x04]).to_string(), "<4 bytes 01020304>");
+        assert_eq!(Redacted("secret skey").to_string(), "<11 bytes 73656372..>");
+        assert_eq!(format!("{:?}", Some(Redacted(&[0xffu8; 16]))), "Some(<16 bytes ffffffff..>)");
+    }
+
+    #[cfg(feature = "debug-secrets")]
+    #[test]
+    fn test_unredacted() {
+        let key = Some(Redacted(vec![0xab; 6]));
+        assert_eq!(format!("{:?}", Unredacted(&key)), "Some(<6 bytes abababababab>)");
+        assert_eq!(format!("{:?}", key), "Some(<6 bytes abababab..>)");
+    }
+
+    #[test]
+    fn test_redact_values() {
+        let map = HashMap::from([(2u16, vec![0x22; 8]), (1u16, vec![0x11; 8])]);
+        assert_eq!(format!("{:?}", redact_values(&map)), "{1: <8 bytes 11111111..>, 2: <8 bytes 22222222..>}");
+    }
+}
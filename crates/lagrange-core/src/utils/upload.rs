@@ -0,0 +1,177 @@
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// Size and hashes of a file, as upload requests announce them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileDigest {
+    pub size: u64,
+    pub md5: [u8; 16],
+    pub sha1: [u8; 20],
+}
+
+impl FileDigest {
+    /// Digest of a file already in memory
+    pub fn of(data: &[u8]) -> Self {
+        let mut hasher = DigestHasher::new();
+        hasher.update(data);
+        hasher.finish()
+    }
+}
+
+struct DigestHasher {
+    size: u64,
+    md5: md5::Context,
+    sha1: sha1::Sha1,
+}
+
+impl DigestHasher {
+    fn new() -> Self {
+        use sha1::Digest;
+
+        Self { size: 0, md5: md5::Context::new(), sha1: sha1::Sha1::new() }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        use sha1::Digest;
+
+        self.size += data.len() as u64;
+        self.md5.consume(data);
+        self.sha1.update(data);
+    }
+
+    fn finish(self) -> FileDigest {
+        use sha1::Digest;
+
+        FileDigest { size: self.size, md5: self.md5.compute().0, sha1: self.sha1.finalize().into() }
+    }
+}
+
+/// A file to upload, read from a seekable reader instead of being held in memory.
+///
+/// Upload requests announce the size and hashes of the file before any of it is sent. Opening
+/// the source reads the file once, one buffer at a time, to compute them, then rewinds it for
+/// the upload, so memory use stays at the buffer size whatever the size of the file.
+pub struct UploadSource<R> {
+    reader: R,
+    digest: FileDigest,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> UploadSource<R> {
+    /// Hash `reader` from its start, reading at most `buffer_size` bytes at a time
+    pub async fn open(reader: R, buffer_size: usize) -> std::io::Result<Self> {
+        Self::open_inspecting(reader, buffer_size, |_| {}).await
+    }
+
+    /// Like [`UploadSource::open`], also passing every piece read to `inspect`, e.g. to find
+    /// the format of the file along the way
+    pub async fn open_inspecting(
+        mut reader: R,
+        buffer_size: usize,
+        mut inspect: impl FnMut(&[u8]),
+    ) -> std::io::Result<Self> {
+        reader.seek(SeekFrom::Start(0)).await?;
+        let mut hasher = DigestHasher::new();
+        let mut buffer = vec![0; buffer_size.max(1)];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            inspect(&buffer[..read]);
+        }
+        reader.seek(SeekFrom::Start(0)).await?;
+
+        Ok(Self { reader, digest: hasher.finish() })
+    }
+
+    pub fn digest(&self) -> FileDigest {
+        self.digest
+    }
+
+    /// The file, positioned at its start right after opening
+    pub fn reader_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::ReadBuf;
+
+    /// Records the largest read it was asked for
+    struct CountingReader<R> {
+        inner: R,
+        largest_read: usize,
+        reads: usize,
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncRead for CountingReader<R> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+            self.largest_read = self.largest_read.max(buf.remaining());
+            self.reads += 1;
+            Pin::new(&mut self.inner).poll_read(cx, buf)
+        }
+    }
+
+    impl<R: AsyncSeek + Unpin> AsyncSeek for CountingReader<R> {
+        fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+            Pin::new(&mut self.inner).start_seek(position)
+        }
+
+        fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+            Pin::new(&mut self.inner).poll_complete(cx)
+        }
+    }
+
+    fn content(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i * 31 % 253) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn test_digest_of_temp_file() {
+        let data = content(10 * 4096 + 77);
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+
+        let reader = CountingReader { inner: tokio::fs::File::open(file.path()).await.unwrap(), largest_read: 0, reads: 0 };
+        let mut pieces = 0;
+        let mut source = UploadSource::open_inspecting(reader, 4096, |piece| {
+            assert!(piece.len() <= 4096);
+            pieces += 1;
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(source.digest(), FileDigest::of(&data));
+        assert_eq!(source.digest().size, data.len() as u64);
+        assert_eq!(source.digest().md5, md5::compute(&data).0);
+        assert!(pieces >= 11);
+        // The file is never read more than a buffer at a time
+        assert_eq!(source.reader_mut().largest_read, 4096);
+
+        // Rewound for the upload
+        let mut uploaded = Vec::new();
+        source.reader_mut().read_to_end(&mut uploaded).await.unwrap();
+        assert_eq!(uploaded, data);
+    }
+
+    #[tokio::test]
+    async fn test_rewinds_before_hashing() {
+        let data = content(1000);
+        let mut reader = std::io::Cursor::new(data.clone());
+        reader.set_position(600);
+
+        let source = UploadSource::open(reader, 64).await.unwrap();
+        assert_eq!(source.digest(), FileDigest::of(&data));
+        assert_eq!(source.into_reader().position(), 0);
+    }
+}
@@ -1,4 +1,5 @@
 use lagrange_core::internal::services::{LoginCommand, LoginEventReq};
+use lagrange_core::common::{BotFriend, BotGender};
 use lagrange_core::{config::BotConfig, keystore::BotKeystore, BotContext, Protocols};
 
 #[tokio::test]
@@ -25,14 +26,21 @@ async fn test_cache_context() {
     let bot = BotContext::builder().build();
 
     bot.cache
-        .cache_friends(vec![lagrange_core::internal::context::cache::Friend {
+        .cache_friends(vec![BotFriend {
             uin: 123,
             uid: "user123".to_string(),
             nickname: "Test User".to_string(),
+            age: 0,
+            gender: BotGender::Unset,
+            remarks: String::new(),
+            personal_sign: String::new(),
+            qid: String::new(),
+            category: None,
         }]);
 
     assert_eq!(bot.cache.resolve_uid(123), Some("user123".to_string()));
     assert_eq!(bot.cache.resolve_uin("user123"), Some(123));
+    assert_eq!(bot.get_friend(123).unwrap().nickname, "Test User");
 }
 
 #[tokio::test]
@@ -102,8 +110,8 @@ fn test_protocol_matching() {
     assert!(Protocols::AndroidWatch.matches(Protocols::ANDROID));
     assert!(!Protocols::Linux.matches(Protocols::ANDROID));
 
-    assert!(Protocols::Linux.matches(Protocols::Linux as u8));
-    assert!(!Protocols::Linux.matches(Protocols::Windows as u8));
+    assert!(Protocols::Linux.matches(Protocols::Linux.bits()));
+    assert!(!Protocols::Linux.matches(Protocols::Windows.bits()));
 
     assert!(Protocols::Linux.is_desktop());
     assert!(Protocols::Windows.is_desktop());
@@ -0,0 +1,99 @@
+use bytes::Bytes;
+use lagrange_core::internal::services::system::{AliveEventReq, AliveService};
+use lagrange_core::keystore::BotKeystore;
+use lagrange_core::testing::{Expectation, MockServer};
+use lagrange_core::BotContext;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Collects everything the fmt subscriber writes, one line per event
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn lines(&self) -> Vec<String> {
+        let bytes = self.0.lock().unwrap();
+        String::from_utf8_lossy(&bytes).lines().map(str::to_string).collect()
+    }
+
+    fn find(&self, message: &str) -> Option<String> {
+        self.lines().into_iter().find(|line| line.contains(message))
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Capture {
+    type Writer = Capture;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Install a subscriber for the current thread, which runs every task of a `tokio::test`
+fn capture() -> (Capture, tracing::subscriber::DefaultGuard) {
+    let capture = Capture::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(capture.clone())
+        .with_ansi(false)
+        .with_max_level(tracing::Level::TRACE)
+        .with_span_events(FmtSpan::CLOSE)
+        .finish();
+    (capture, tracing::subscriber::set_default(subscriber))
+}
+
+#[tokio::test]
+async fn test_request_span() {
+    let (capture, _guard) = capture();
+    let (transport, server) = MockServer::start(BotKeystore::default());
+    server.expect(Expectation::new("Heartbeat.Alive").respond(Bytes::new()));
+    let context = BotContext::builder().transport(transport).build();
+    context.connect().await.unwrap();
+
+    context.event.send::<AliveService>(AliveEventReq {}, context.clone()).await.unwrap();
+
+    // The sequence is only known once the packet is built, but lines logged after that carry it
+    let sending = capture.find("Sending packet and registering pending task").unwrap();
+    assert!(sending.contains("packet{command=Heartbeat.Alive uin=None sequence=1}"), "{}", sending);
+
+    let closed = capture
+        .lines()
+        .into_iter()
+        .find(|line| line.contains("packet{command=Heartbeat.Alive") && line.contains("close"))
+        .unwrap();
+    assert!(closed.contains("latency_ms="), "{}", closed);
+}
+
+#[tokio::test]
+async fn test_push_span() {
+    let (capture, _guard) = capture();
+    let (transport, server) = MockServer::start(BotKeystore::default());
+    let context = BotContext::builder().transport(transport).build();
+    context.connect().await.unwrap();
+
+    assert!(server.push("Test.UnknownPush", Bytes::new()).await);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while capture.find("No service registered for push").is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("push not dispatched");
+
+    let line = capture.find("No service registered for push").unwrap();
+    assert!(line.contains("push{command=Test.UnknownPush sequence="), "{}", line);
+    assert!(line.contains("uin=None"), "{}", line);
+}
@@ -0,0 +1,107 @@
+use bytes::Bytes;
+use lagrange_core::common::{BotOfflineEvent, OfflineKind};
+use lagrange_core::internal::services::system::{AliveEventReq, AliveService};
+use lagrange_core::keystore::BotKeystore;
+use lagrange_core::testing::{Expectation, MemoryTransport, MockServer};
+use lagrange_core::BotContext;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn heartbeat_server() -> (Arc<BotContext>, MockServer) {
+    let (transport, server) = MockServer::start(BotKeystore::default());
+    server.expect(Expectation::new("Heartbeat.Alive").respond(Bytes::new()));
+    (BotContext::builder().transport(transport).build(), server)
+}
+
+/// Poll `condition` until it holds, failing after a minute of (possibly paused) time
+async fn wait_until(condition: impl Fn() -> bool) {
+    tokio::time::timeout(Duration::from_secs(60), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("condition not reached");
+}
+
+#[tokio::test]
+async fn test_heartbeat_round_trip() {
+    let (context, server) = heartbeat_server();
+    context.connect().await.unwrap();
+    assert!(context.socket.is_connected().await);
+
+    // The heartbeat job sends its first heartbeat right away
+    wait_until(|| !server.received().is_empty()).await;
+    let heartbeat = &server.received()[0];
+    assert_eq!(heartbeat.command, "Heartbeat.Alive");
+    assert_eq!(heartbeat.data.as_ref(), &[0, 0, 0, 4]);
+
+    context.event.send::<AliveService>(AliveEventReq {}, context.clone()).await.unwrap();
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_monitor_reconnects_after_server_drop() {
+    let (context, server) = heartbeat_server();
+    context.connect().await.unwrap();
+    context.start_connection_monitor().unwrap();
+    wait_until(|| !server.received().is_empty()).await;
+
+    server.drop_connection();
+    wait_until(|| server.connections() == 2).await;
+
+    assert!(context.socket.is_connected().await);
+    let sent = server.received().len();
+    context.event.send::<AliveService>(AliveEventReq {}, context.clone()).await.unwrap();
+    assert!(server.received().len() > sent);
+}
+
+#[tokio::test]
+async fn test_connect_without_server() {
+    let (transport, listener) = MemoryTransport::new();
+    drop(listener);
+    let context = BotContext::builder().transport(transport).build();
+
+    assert!(context.connect().await.is_err());
+    assert!(!context.socket.is_connected().await);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_delayed_response() {
+    let (transport, server) = MockServer::start(BotKeystore::default());
+    server.expect(Expectation::new("Heartbeat.Alive").respond(Bytes::new()).after(Duration::from_millis(20)));
+    let context = BotContext::builder().transport(transport).build();
+    context.connect().await.unwrap();
+
+    let started = tokio::time::Instant::now();
+    context.event.send::<AliveService>(AliveEventReq {}, context.clone()).await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(20));
+}
+
+#[tokio::test]
+async fn test_push_injection() {
+    let (transport, server) = MockServer::start(BotKeystore::default());
+    let context = BotContext::builder().transport(transport).build();
+    let mut offline = context.event.subscribe_to::<BotOfflineEvent>();
+    context.connect().await.unwrap();
+
+    assert!(server.push("MessageSvc.PushForceOffline", Bytes::new()).await);
+    let event = tokio::time::timeout(Duration::from_secs(5), offline.recv()).await.unwrap().unwrap();
+    assert_eq!(event.kind, OfflineKind::ServerKick);
+}
+
+#[tokio::test]
+#[should_panic(expected = "unexpected request(s)")]
+async fn test_unexpected_request_fails_at_drop() {
+    let (transport, server) = MockServer::start(BotKeystore::default());
+    server.expect(Expectation::new("Heartbeat.Alive").respond(Bytes::new()).times(1));
+    let context = BotContext::builder().transport(transport).build();
+    context.connect().await.unwrap();
+
+    // The heartbeat job and these two heartbeats share one answer, the others are unexpected
+    for _ in 0..2 {
+        let heartbeat = context.event.send::<AliveService>(AliveEventReq {}, context.clone());
+        let _ = tokio::time::timeout(Duration::from_millis(100), heartbeat).await;
+    }
+    assert!(!server.unmatched().is_empty());
+}
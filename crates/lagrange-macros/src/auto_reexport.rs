@@ -76,12 +76,14 @@ pub fn auto_reexport_impl(input: TokenStream) -> TokenStream {
 }
 
 fn find_public_items(manifest_dir: &str, module_name: &str) -> Vec<Ident> {
+    // Most specific directories first, so `internal/services/message.rs` wins over `src/message.rs`
     let search_patterns = vec![
-        ("src", format!("{}/src", manifest_dir)),
-        ("src/internal", format!("{}/src/internal", manifest_dir)),
-        ("src/internal/services", format!("{}/src/internal/services", manifest_dir)),
-        ("src/internal/services/login", format!("{}/src/internal/services/login", manifest_dir)),
+        ("src/internal/services/message", format!("{}/src/internal/services/message", manifest_dir)),
         ("src/internal/services/system", format!("{}/src/internal/services/system", manifest_dir)),
+        ("src/internal/services/login", format!("{}/src/internal/services/login", manifest_dir)),
+        ("src/internal/services", format!("{}/src/internal/services", manifest_dir)),
+        ("src/internal", format!("{}/src/internal", manifest_dir)),
+        ("src", format!("{}/src", manifest_dir)),
     ];
 
     for (_, base_path) in &search_patterns {
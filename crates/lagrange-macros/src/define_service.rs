@@ -133,7 +133,6 @@ struct EventDefinition {
     #[allow(dead_code)]
     name: Ident,
     protocol_expr: Expr,
-    #[allow(dead_code)]
     protocol_name: String,
     request_name: Ident,
     request_fields: Vec<ServiceField>,
@@ -141,6 +140,18 @@ struct EventDefinition {
     response_fields: Vec<ServiceField>,
 }
 
+impl EventDefinition {
+    /// The protocol as a mask: bit masks are used as they are, single protocols through
+    /// `Protocols::bits`
+    fn protocol_mask(&self) -> proc_macro2::TokenStream {
+        let protocol_expr = &self.protocol_expr;
+        match self.protocol_name.as_str() {
+            "PC" | "ANDROID" | "ALL" => quote! { #protocol_expr },
+            _ => quote! { #protocol_expr.bits() },
+        }
+    }
+}
+
 impl Parse for EventDefinition {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let name: Ident = input.parse()?;
@@ -228,6 +239,7 @@ struct UnifiedServiceArgs {
     request_type: Option<Path>,
     encrypt_type: Option<Path>,
     disable_log: bool,
+    max_length_delimited: Option<Expr>,
     events: Vec<EventDefinition>,
     parse_fn: ServiceFunction,
     build_fn: ServiceFunction,
@@ -244,6 +256,7 @@ impl Parse for UnifiedServiceArgs {
         let mut request_type = None;
         let mut encrypt_type = None;
         let mut disable_log = false;
+        let mut max_length_delimited = None;
         let mut events = Vec::new();
         let mut parse_fn = None;
         let mut build_fn = None;
@@ -276,6 +289,10 @@ impl Parse for UnifiedServiceArgs {
                     let value: LitBool = content.parse()?;
                     disable_log = value.value;
                     let _ = content.parse::<Token![,]>();
+                } else if key == "max_length_delimited" {
+                    content.parse::<Token![:]>()?;
+                    max_length_delimited = Some(content.parse::<Expr>()?);
+                    let _ = content.parse::<Token![,]>();
                 } else if key == "events" {
                     let events_content;
                     braced!(events_content in content);
@@ -286,7 +303,8 @@ impl Parse for UnifiedServiceArgs {
                 } else {
                     return Err(syn::Error::new(
                         key.span(),
-                        "Unknown key in service definition. Expected: command, request_type, encrypt_type, disable_log, events",
+                        "Unknown key in service definition. Expected: command, request_type, encrypt_type, \
+                         disable_log, max_length_delimited, events",
                     ));
                 }
             } else if lookahead.peek(Token![async]) {
@@ -336,6 +354,7 @@ impl Parse for UnifiedServiceArgs {
             request_type,
             encrypt_type,
             disable_log,
+            max_length_delimited,
             events,
             parse_fn,
             build_fn,
@@ -371,6 +390,12 @@ pub(crate) fn define_service_impl(input: TokenStream) -> TokenStream {
         };
     }
 
+    if let Some(ref max_length_delimited) = args.max_length_delimited {
+        metadata_init = quote! {
+            #metadata_init.with_max_length_delimited(#max_length_delimited)
+        };
+    }
+
     let event_structs = args.events.iter().map(|event| {
         let request_name = &event.request_name;
         let response_name = &event.response_name;
@@ -595,7 +620,7 @@ pub(crate) fn define_service_impl(input: TokenStream) -> TokenStream {
         let event = &args.events[0];
         let request_type = &event.request_name;
         let response_type = &event.response_name;
-        let protocol_expr = &event.protocol_expr;
+        let protocol_mask = event.protocol_mask();
 
         let typed_impl = quote! {
             #[async_trait::async_trait]
@@ -628,17 +653,13 @@ pub(crate) fn define_service_impl(input: TokenStream) -> TokenStream {
                     // Call helper parse method
                     let event_msg = self.__internal_parse(bytes, context).await?;
 
-                    // Downcast to the concrete response type
-                    event_msg
-                        .downcast::<Self::Response>()
-                        .map(|arc| {
-                            std::sync::Arc::try_unwrap(arc).unwrap_or_else(|arc| (*arc).clone())
-                        })
-                        .ok_or_else(|| {
-                            crate::error::Error::ParseError(
-                                "Failed to downcast response to expected type".to_string()
-                            )
-                        })
+                    // Unwrap as the concrete response type
+                    event_msg.try_unwrap::<Self::Response>().map_err(|event_msg| {
+                        crate::error::Error::ParseError(format!(
+                            "Failed to downcast response {} to expected type",
+                            event_msg
+                        ))
+                    })
                 }
             }
         };
@@ -646,7 +667,7 @@ pub(crate) fn define_service_impl(input: TokenStream) -> TokenStream {
         let typed_reg = quote! {
             registry.register_typed_service(
                 #service_name::default(),
-                #protocol_expr,
+                #protocol_mask,
             );
         };
 
@@ -682,21 +703,16 @@ pub(crate) fn define_service_impl(input: TokenStream) -> TokenStream {
             let variant_name = &event.name;
             let response_type = &event.response_name;
             quote! {
-                if let Some(resp) = event_msg.downcast::<#response_type>() {
-                    let owned = std::sync::Arc::try_unwrap(resp).unwrap_or_else(|arc| (*arc).clone());
-                    return Ok(#response_enum_name::#variant_name(owned));
-                }
+                let event_msg = match event_msg.try_unwrap::<#response_type>() {
+                    Ok(resp) => return Ok(#response_enum_name::#variant_name(resp)),
+                    Err(event_msg) => event_msg,
+                };
             }
         });
 
         // Get the combined protocol mask (OR all protocols together)
-        let protocol_mask = if args.events.len() > 1 {
-            let first_proto = &args.events[0].protocol_expr;
-            quote! { #first_proto }
-        } else {
-            let proto = &args.events[0].protocol_expr;
-            quote! { #proto }
-        };
+        let protocol_masks = args.events.iter().map(EventDefinition::protocol_mask);
+        let protocol_mask = quote! { 0u8 #(| #protocol_masks)* };
 
         let enum_defs = quote! {
             #[derive(Debug, Clone)]
@@ -747,9 +763,10 @@ pub(crate) fn define_service_impl(input: TokenStream) -> TokenStream {
                     // Try downcasting to each possible response type
                     #(#parse_downcast_attempts)*
 
-                    Err(crate::error::Error::ParseError(
-                        "Response did not match any expected type".to_string()
-                    ))
+                    Err(crate::error::Error::ParseError(format!(
+                        "Response {} did not match any expected type",
+                        event_msg
+                    )))
                 }
             }
         };
@@ -25,8 +25,8 @@ pub(crate) fn levenshtein_distance(a: &str, b: &str) -> usize {
     for (i, row) in matrix.iter_mut().enumerate().take(len_a + 1) {
         row[0] = i;
     }
-    for j in 0..=len_b {
-        matrix[0][j] = j;
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
     }
 
     for (i, ca) in a.chars().enumerate() {
@@ -125,6 +125,20 @@ enum ProtoAttr {
     WireType(String),
 }
 
+/// Largest field number a key can hold, `lagrange_proto::wire::MAX_TAG`
+const MAX_TAG: u32 = (1 << 29) - 1;
+
+/// Field number of a `tag = N` attribute, rejecting numbers a key cannot hold
+pub fn parse_tag(lit: &syn::LitInt) -> Result<u32> {
+    match lit.base10_parse::<u32>() {
+        Ok(tag) if (1..=MAX_TAG).contains(&tag) => Ok(tag),
+        _ => Err(syn::Error::new_spanned(
+            lit,
+            format!("Tag must be between 1 and {}", MAX_TAG),
+        )),
+    }
+}
+
 impl Parse for ProtoAttr {
     fn parse(input: ParseStream) -> Result<Self> {
         let ident: Ident = input.parse()?;
@@ -135,7 +149,7 @@ impl Parse for ProtoAttr {
                 input.parse::<Token![=]>()?;
                 let lit: Lit = input.parse()?;
                 if let Lit::Int(int_lit) = lit {
-                    Ok(ProtoAttr::Tag(int_lit.base10_parse()?))
+                    Ok(ProtoAttr::Tag(parse_tag(&int_lit)?))
                 } else {
                     Err(syn::Error::new_spanned(
                         lit,
@@ -199,6 +213,9 @@ pub struct ProtoMessageAttrs {
     pub syntax: Option<String>,
 
     pub preserve_unknown: bool,
+
+    /// Also derive `serde::Serialize` and `serde::Deserialize`, for messages, enums and oneofs
+    pub serde: bool,
 }
 
 impl ProtoMessageAttrs {
@@ -223,6 +240,9 @@ impl ProtoMessageAttrs {
                 ProtoMessageAttr::PreserveUnknown => {
                     self.preserve_unknown = true;
                 }
+                ProtoMessageAttr::Serde => {
+                    self.serde = true;
+                }
             }
         }
         Ok(())
@@ -246,6 +266,8 @@ enum ProtoMessageAttr {
     Syntax(String),
 
     PreserveUnknown,
+
+    Serde,
 }
 
 impl Parse for ProtoMessageAttr {
@@ -267,6 +289,7 @@ impl Parse for ProtoMessageAttr {
                 }
             }
             "preserve_unknown" => Ok(ProtoMessageAttr::PreserveUnknown),
+            "serde" => Ok(ProtoMessageAttr::Serde),
             _ => Err(syn::Error::new_spanned(
                 ident,
                 format!("Unknown message-level proto attribute: {}", name),
@@ -290,6 +313,23 @@ mod tests {
         assert_eq!(attrs.tag, Some(1));
     }
 
+    #[test]
+    fn test_parse_tag_out_of_range() {
+        for field in [
+            parse_quote! { #[proto(tag = 0)] field: u32 },
+            parse_quote! { #[proto(tag = 536870912)] field: u32 },
+        ] {
+            let field: Field = field;
+            assert!(ProtoFieldAttrs::from_field(&field).is_err());
+        }
+
+        let field: Field = parse_quote! {
+            #[proto(tag = 536870911)]
+            field: u32
+        };
+        assert_eq!(ProtoFieldAttrs::from_field(&field).unwrap().tag, Some(536870911));
+    }
+
     #[test]
     fn test_parse_multiple_attrs() {
         let field: Field = parse_quote! {
@@ -1,3 +1,4 @@
+use crate::attributes::ProtoMessageAttrs;
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Data, DeriveInput, Error, Fields, Meta, Result, Variant};
@@ -88,6 +89,93 @@ pub fn expand_derive_proto_enum(input: DeriveInput) -> Result<TokenStream> {
         }
     });
 
+    // Variants are written by name, and read back from their name or value
+    let json = if cfg!(feature = "json") {
+        let enum_str = enum_name.to_string();
+        let to_name_arms = variant_infos.iter().map(|(name, _)| {
+            let name_str = name.to_string();
+            quote! { #enum_name::#name => #name_str }
+        });
+        let from_name_arms = variant_infos.iter().map(|(name, _)| {
+            let name_str = name.to_string();
+            quote! { #name_str => Ok(#enum_name::#name) }
+        });
+        quote! {
+            impl ::lagrange_proto::json::ProtoJson for #enum_name {
+                fn to_json_value(&self) -> ::lagrange_proto::json::Value {
+                    let name = match self {
+                        #(#to_name_arms),*
+                    };
+                    ::lagrange_proto::json::Value::String(name.to_string())
+                }
+
+                fn from_json_value(
+                    value: &::lagrange_proto::json::Value,
+                ) -> Result<Self, ::lagrange_proto::DecodeError> {
+                    match value {
+                        ::lagrange_proto::json::Value::String(name) => match name.as_str() {
+                            #(#from_name_arms,)*
+                            _ => Err(::lagrange_proto::DecodeError::InvalidJson(
+                                format!("unknown variant {:?} of {}", name, #enum_str),
+                            )),
+                        },
+                        value => {
+                            let value: i32 = ::lagrange_proto::json::ProtoJson::from_json_value(value)?;
+                            Self::from_i32(value).map_err(::lagrange_proto::DecodeError::InvalidEnumValue)
+                        }
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Variants are written as unit variants by name, `#[proto(serde)]`
+    let serde = if ProtoMessageAttrs::from_derive_input(&input)?.serde {
+        let enum_str = enum_name.to_string();
+        let names: Vec<_> = variant_infos.iter().map(|(name, _)| *name).collect();
+        let serialize_arms = names.iter().enumerate().map(|(index, name)| {
+            let index = index as u32;
+            let name_str = name.to_string();
+            quote! {
+                #enum_name::#name => serializer.serialize_unit_variant(#enum_str, #index, #name_str)
+            }
+        });
+        quote! {
+            const _: () = {
+                #[derive(::lagrange_proto::serde::Deserialize)]
+                #[serde(crate = "::lagrange_proto::serde")]
+                enum Shadow {
+                    #(#names),*
+                }
+
+                impl ::lagrange_proto::serde::Serialize for #enum_name {
+                    fn serialize<S: ::lagrange_proto::serde::Serializer>(
+                        &self,
+                        serializer: S,
+                    ) -> Result<S::Ok, S::Error> {
+                        match self {
+                            #(#serialize_arms),*
+                        }
+                    }
+                }
+
+                impl<'de> ::lagrange_proto::serde::Deserialize<'de> for #enum_name {
+                    fn deserialize<D: ::lagrange_proto::serde::Deserializer<'de>>(
+                        deserializer: D,
+                    ) -> Result<Self, D::Error> {
+                        Ok(match <Shadow as ::lagrange_proto::serde::Deserialize>::deserialize(deserializer)? {
+                            #(Shadow::#names => #enum_name::#names),*
+                        })
+                    }
+                }
+            };
+        }
+    } else {
+        quote! {}
+    };
+
     let expanded = quote! {
         impl ::lagrange_proto::ProtoEncode for #enum_name {
             fn encode<B: ::bytes::BufMut>(&self, buf: &mut B) -> Result<(), ::lagrange_proto::EncodeError> {
@@ -133,6 +221,10 @@ pub fn expand_derive_proto_enum(input: DeriveInput) -> Result<TokenStream> {
                 }
             }
         }
+
+        #json
+
+        #serde
     };
 
     Ok(expanded)
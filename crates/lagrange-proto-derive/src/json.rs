@@ -0,0 +1,42 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Proto3 JSON name of a field or oneof variant: `sender_uin` and `SenderUin` are `senderUin`
+pub fn json_name(name: &str) -> String {
+    let mut json_name = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        if c == '_' {
+            upper = !json_name.is_empty();
+        } else if upper {
+            json_name.extend(c.to_uppercase());
+            upper = false;
+        } else if json_name.is_empty() {
+            json_name.extend(c.to_lowercase());
+        } else {
+            json_name.push(c);
+        }
+    }
+    json_name
+}
+
+/// `SenderUin` as the `sender_uin` its field would be named in the `.proto`
+pub fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Pattern matching the JSON name of a field and its name in the `.proto`, which parsers accept too
+pub fn name_pattern(json_name: &str, proto_name: &str) -> TokenStream {
+    if json_name == proto_name {
+        quote! { #json_name }
+    } else {
+        quote! { #json_name | #proto_name }
+    }
+}
@@ -4,6 +4,7 @@ use syn::{parse_macro_input, DeriveInput};
 mod attributes;
 mod builder_derive;
 mod enum_derive;
+mod json;
 mod message;
 mod oneof_derive;
 
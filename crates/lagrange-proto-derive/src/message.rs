@@ -1,6 +1,8 @@
 use crate::attributes::{ProtoFieldAttrs, ProtoMessageAttrs};
+use crate::json::{json_name, name_pattern};
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::ext::IdentExt;
 use syn::{
     Data, DeriveInput, Error, Field, Fields, FieldsNamed, GenericArgument, PathArguments, Result,
     Type,
@@ -64,6 +66,42 @@ fn is_vec(ty: &Type) -> bool {
     false
 }
 
+/// Whether `ty` is `Vec<u8>`, a single `bytes` field rather than a repeated one
+fn is_byte_vec(ty: &Type) -> bool {
+    is_vec(ty) && matches!(extract_inner_type(ty), Some(Type::Path(inner)) if inner.path.is_ident("u8"))
+}
+
+/// Whether `ty` is `Cow<'_, str>`, by that name or as [`lagrange_proto::CowStr`]
+fn is_cow_str(ty: &Type) -> bool {
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            if segment.ident == "CowStr" {
+                return true;
+            }
+            if segment.ident == "Cow" {
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    return args.args.iter().any(|arg| {
+                        matches!(arg, GenericArgument::Type(Type::Path(inner)) if inner.path.is_ident("str"))
+                    });
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Whether `ty` takes a lifetime, so it may borrow from the buffer it is decoded from
+fn has_lifetime(ty: &Type) -> bool {
+    if let Type::Path(type_path) = ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                return args.args.iter().any(|arg| matches!(arg, GenericArgument::Lifetime(_)));
+            }
+        }
+    }
+    false
+}
+
 fn is_map(ty: &Type) -> bool {
     if let Type::Path(type_path) = ty {
         if let Some(segment) = type_path.path.segments.last() {
@@ -122,14 +160,33 @@ fn can_be_packed(ty: &Type) -> bool {
     )
 }
 
+/// Whether the derive knows the wire type of `ty`; other types are nested messages or enums
+fn is_known_scalar(ty: &Type) -> bool {
+    let type_str = quote!(#ty).to_string();
+    is_cow_str(ty) || matches!(
+        type_str.trim(),
+        "u32" | "u64" | "i32" | "i64" | "bool" | "f32" | "f64" |
+        "String" | "Vec < u8 >" | "Vec<u8>" |
+        "Bytes" | "bytes :: Bytes" | ":: bytes :: Bytes" |
+        "BytesMut" | "bytes :: BytesMut" | ":: bytes :: BytesMut" |
+        "SInt32" | "SInt64" | "Fixed32" | "Fixed64" | "SFixed32" | "SFixed64" |
+        ":: lagrange_proto :: SInt32" | ":: lagrange_proto :: SInt64" |
+        ":: lagrange_proto :: Fixed32" | ":: lagrange_proto :: Fixed64" |
+        ":: lagrange_proto :: SFixed32" | ":: lagrange_proto :: SFixed64"
+    )
+}
+
 fn wire_type_for_type(ty: &Type) -> TokenStream {
-    let inner_type = if is_option(ty) || is_vec(ty) {
+    let inner_type = if is_option(ty) || (is_vec(ty) && !is_byte_vec(ty)) {
         extract_inner_type(ty)
     } else {
         None
     };
 
     let actual_type = inner_type.as_ref().unwrap_or(ty);
+    if is_cow_str(actual_type) {
+        return quote! { ::lagrange_proto::wire::WireType::LengthDelimited };
+    }
     let actual_type_str = quote!(#actual_type).to_string();
     let actual_type_str = actual_type_str.trim();
 
@@ -158,7 +215,8 @@ fn wire_type_for_type(ty: &Type) -> TokenStream {
             quote! { ::lagrange_proto::wire::WireType::Fixed64 }
         }
 
-        "String" => {
+        "String" | "Vec < u8 >" | "Vec<u8>" | "Bytes" | "bytes :: Bytes" | ":: bytes :: Bytes"
+        | "BytesMut" | "bytes :: BytesMut" | ":: bytes :: BytesMut" => {
             quote! { ::lagrange_proto::wire::WireType::LengthDelimited }
         }
         _ => {
@@ -171,15 +229,22 @@ fn wire_type_for_type(ty: &Type) -> TokenStream {
     }
 }
 
-fn generate_field_encode(field: &FieldInfo) -> TokenStream {
+/// Encode `field`; `canonical` encodes nested messages canonically and map entries sorted by key
+fn generate_field_encode(field: &FieldInfo, canonical: bool) -> TokenStream {
     let name = &field.name;
     let tag = field.tag;
     let wire_type = wire_type_for_type(&field.ty);
+    let encode = if canonical { quote!(encode_canonical) } else { quote!(encode) };
+    let encode_custom_field = if canonical {
+        quote!(encode_custom_field_canonical)
+    } else {
+        quote!(encode_custom_field)
+    };
 
     if field.is_oneof {
         return quote! {
             if let Some(ref value) = self.#name {
-                value.encode(buf)?;
+                ::lagrange_proto::ProtoEncode::#encode(value, buf)?;
             }
         };
     }
@@ -189,8 +254,21 @@ fn generate_field_encode(field: &FieldInfo) -> TokenStream {
             let key_wire_type = wire_type_for_type(&key_ty);
             let val_wire_type = wire_type_for_type(&val_ty);
 
+            // Sorting needs `Ord` keys, which canonical encoding alone asks for
+            let entries = if canonical {
+                quote! {
+                    {
+                        let mut entries: Vec<_> = self.#name.iter().collect();
+                        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+                        entries
+                    }
+                }
+            } else {
+                quote! { &self.#name }
+            };
+
             return quote! {
-                for (k, v) in &self.#name {
+                for (k, v) in #entries {
 
                     let mut entry_size = 0usize;
 
@@ -211,7 +289,8 @@ fn generate_field_encode(field: &FieldInfo) -> TokenStream {
 
                     {
                         let mut temp = [0u8; 5];
-                        let len = ::lagrange_proto::varint::encode_to_slice(entry_size as u32, &mut temp);
+                        let prefix = ::lagrange_proto::encoding::checked_len(entry_size)?;
+                        let len = ::lagrange_proto::varint::encode_to_slice(prefix, &mut temp);
                         buf.put_slice(&temp[..len]);
                     }
 
@@ -220,14 +299,14 @@ fn generate_field_encode(field: &FieldInfo) -> TokenStream {
                         let len = ::lagrange_proto::varint::encode_to_slice(key_field_key, &mut temp);
                         buf.put_slice(&temp[..len]);
                     }
-                    k.encode(buf)?;
+                    ::lagrange_proto::ProtoEncode::#encode(k, buf)?;
 
                     {
                         let mut temp = [0u8; 5];
                         let len = ::lagrange_proto::varint::encode_to_slice(val_field_key, &mut temp);
                         buf.put_slice(&temp[..len]);
                     }
-                    v.encode(buf)?;
+                    ::lagrange_proto::ProtoEncode::#encode(v, buf)?;
                 }
             };
         }
@@ -253,15 +332,22 @@ fn generate_field_encode(field: &FieldInfo) -> TokenStream {
 
                     {
                         let mut temp = [0u8; 5];
-                        let len = ::lagrange_proto::varint::encode_to_slice(packed_size as u32, &mut temp);
+                        let prefix = ::lagrange_proto::encoding::checked_len(packed_size)?;
+                        let len = ::lagrange_proto::varint::encode_to_slice(prefix, &mut temp);
                         buf.put_slice(&temp[..len]);
                     }
 
                     for item in &self.#name {
-                        item.encode(buf)?;
+                        ::lagrange_proto::ProtoEncode::#encode(item, buf)?;
                     }
                 }
             }
+        } else if !is_known_scalar(&inner_ty) {
+            quote! {
+                for item in &self.#name {
+                    ::lagrange_proto::encoding::#encode_custom_field(#tag, item, buf)?;
+                }
+            }
         } else {
             quote! {
                 for item in &self.#name {
@@ -271,10 +357,16 @@ fn generate_field_encode(field: &FieldInfo) -> TokenStream {
                         let len = ::lagrange_proto::varint::encode_to_slice(key, &mut temp);
                         buf.put_slice(&temp[..len]);
                     }
-                    item.encode(buf)?;
+                    ::lagrange_proto::ProtoEncode::#encode(item, buf)?;
                 }
             }
         }
+    } else if field.is_optional && !is_known_scalar(&extract_inner_type(&field.ty).unwrap_or_else(|| field.ty.clone())) {
+        quote! {
+            if let Some(ref value) = self.#name {
+                ::lagrange_proto::encoding::#encode_custom_field(#tag, value, buf)?;
+            }
+        }
     } else if field.is_optional {
         quote! {
             if let Some(ref value) = self.#name {
@@ -284,9 +376,13 @@ fn generate_field_encode(field: &FieldInfo) -> TokenStream {
                     let len = ::lagrange_proto::varint::encode_to_slice(key, &mut temp);
                     buf.put_slice(&temp[..len]);
                 }
-                value.encode(buf)?;
+                ::lagrange_proto::ProtoEncode::#encode(value, buf)?;
             }
         }
+    } else if !is_known_scalar(&field.ty) {
+        quote! {
+            ::lagrange_proto::encoding::#encode_custom_field(#tag, &self.#name, buf)?;
+        }
     } else {
         quote! {
             let key = ::lagrange_proto::wire::encode_key(#tag, #wire_type);
@@ -295,8 +391,71 @@ fn generate_field_encode(field: &FieldInfo) -> TokenStream {
                 let len = ::lagrange_proto::varint::encode_to_slice(key, &mut temp);
                 buf.put_slice(&temp[..len]);
             }
-            self.#name.encode(buf)?;
+            ::lagrange_proto::ProtoEncode::#encode(&self.#name, buf)?;
+        }
+    }
+}
+
+/// Encode the fields in ascending tag order; oneof fields only know their tag once set, so
+/// each one is written before the first field with a larger tag
+fn generate_canonical_encode(fields: &[FieldInfo]) -> TokenStream {
+    let (oneof_fields, mut regular_fields): (Vec<_>, Vec<_>) = fields.iter().partition(|f| f.is_oneof);
+    regular_fields.sort_by_key(|field| field.tag);
+
+    let oneof_arms = oneof_fields.iter().enumerate().map(|(index, field)| {
+        let name = &field.name;
+        quote! {
+            #index => {
+                if let Some(ref value) = self.#name {
+                    ::lagrange_proto::ProtoEncode::encode_canonical(value, buf)?;
+                }
+            }
+        }
+    });
+    let oneof_arms: Vec<_> = oneof_arms.collect();
+    let oneofs_below = |limit: TokenStream| {
+        if oneof_fields.is_empty() {
+            return quote! {};
+        }
+        quote! {
+            while let Some((index, _)) = oneof_tags
+                .iter()
+                .enumerate()
+                .filter_map(|(index, tag)| tag.filter(|tag| *tag < #limit).map(|tag| (index, tag)))
+                .min_by_key(|(_, tag)| *tag)
+            {
+                oneof_tags[index] = None;
+                match index {
+                    #(#oneof_arms)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+    };
+
+    let oneof_tags = if oneof_fields.is_empty() {
+        quote! {}
+    } else {
+        let names = oneof_fields.iter().map(|field| &field.name);
+        quote! {
+            let mut oneof_tags = [#(self.#names.as_ref().map(|value| value.tag())),*];
         }
+    };
+    let encodes = regular_fields.iter().map(|field| {
+        let tag = field.tag;
+        let oneofs = oneofs_below(quote! { #tag });
+        let encode = generate_field_encode(field, true);
+        quote! {
+            #oneofs
+            #encode
+        }
+    });
+    let remaining_oneofs = oneofs_below(quote! { u32::MAX });
+
+    quote! {
+        #oneof_tags
+        #(#encodes)*
+        #remaining_oneofs
     }
 }
 
@@ -334,7 +493,7 @@ fn generate_field_size(field: &FieldInfo) -> TokenStream {
                     entry_size += ::lagrange_proto::helpers::get_varint_length_u32(val_field_key);
                     entry_size += v.encoded_size();
 
-                    size += ::lagrange_proto::helpers::get_varint_length_u32(entry_size as u32);
+                    size += ::lagrange_proto::helpers::get_varint_length_u64(entry_size as u64);
                     size += entry_size;
                 }
             };
@@ -354,10 +513,16 @@ fn generate_field_size(field: &FieldInfo) -> TokenStream {
                         packed_size += item.encoded_size();
                     }
 
-                    size += ::lagrange_proto::helpers::get_varint_length_u32(packed_size as u32);
+                    size += ::lagrange_proto::helpers::get_varint_length_u64(packed_size as u64);
                     size += packed_size;
                 }
             }
+        } else if !is_known_scalar(&inner_ty) {
+            quote! {
+                for item in &self.#name {
+                    size += ::lagrange_proto::encoding::custom_field_size(#tag, item);
+                }
+            }
         } else {
             quote! {
                 for item in &self.#name {
@@ -367,6 +532,12 @@ fn generate_field_size(field: &FieldInfo) -> TokenStream {
                 }
             }
         }
+    } else if field.is_optional && !is_known_scalar(&extract_inner_type(&field.ty).unwrap_or_else(|| field.ty.clone())) {
+        quote! {
+            if let Some(ref value) = self.#name {
+                size += ::lagrange_proto::encoding::custom_field_size(#tag, value);
+            }
+        }
     } else if field.is_optional {
         quote! {
             if let Some(ref value) = self.#name {
@@ -375,6 +546,10 @@ fn generate_field_size(field: &FieldInfo) -> TokenStream {
                 size += value.encoded_size();
             }
         }
+    } else if !is_known_scalar(&field.ty) {
+        quote! {
+            size += ::lagrange_proto::encoding::custom_field_size(#tag, &self.#name);
+        }
     } else {
         quote! {
             let key = ::lagrange_proto::wire::encode_key(#tag, #wire_type);
@@ -384,7 +559,41 @@ fn generate_field_size(field: &FieldInfo) -> TokenStream {
     }
 }
 
-fn generate_decode_value(ty: &Type) -> TokenStream {
+/// Which input the generated decoding reads from
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DecodeMode {
+    /// `ProtoDecode::decode`, copying everything out of the input
+    Owned,
+    /// `ProtoDecodeBorrowed::decode_borrowed`, strings and nested messages taking a lifetime
+    /// borrow from the input
+    Borrowed,
+    /// `ProtoDecode::decode_shared`, `Bytes` and nested messages slice the shared input
+    Shared,
+}
+
+fn generate_decode_value(ty: &Type, mode: DecodeMode) -> TokenStream {
+    if is_cow_str(ty) {
+        return if mode == DecodeMode::Borrowed {
+            quote! { ::std::borrow::Cow::Borrowed(reader.read_str()?) }
+        } else {
+            quote! {
+                {
+                    let data = reader.read_length_delimited()?;
+                    let value = String::from_utf8(data).map_err(::lagrange_proto::DecodeError::InvalidUtf8)?;
+                    ::std::borrow::Cow::Owned(value)
+                }
+            }
+        };
+    }
+    if mode == DecodeMode::Borrowed && has_lifetime(ty) {
+        return quote! {
+            ::lagrange_proto::ProtoDecodeBorrowed::decode_borrowed_with_options(
+                reader.read_length_delimited_bytes()?,
+                reader.nested_options()?,
+            )?
+        };
+    }
+
     let type_str = quote!(#ty).to_string();
     let type_str = type_str.trim();
 
@@ -474,18 +683,21 @@ fn generate_decode_value(ty: &Type) -> TokenStream {
             quote! { reader.read_length_delimited()? }
         }
         "Bytes" | "bytes :: Bytes" | ":: bytes :: Bytes" => {
+            quote! { reader.read_bytes()? }
+        }
+        "BytesMut" | "bytes :: BytesMut" | ":: bytes :: BytesMut" => {
             quote! {
                 {
                     let data = reader.read_length_delimited()?;
-                    ::bytes::Bytes::from(data)
+                    ::bytes::BytesMut::from(data.as_slice())
                 }
             }
         }
-        "BytesMut" | "bytes :: BytesMut" | ":: bytes :: BytesMut" => {
+        _ if mode == DecodeMode::Shared => {
             quote! {
                 {
-                    let data = reader.read_length_delimited()?;
-                    ::bytes::BytesMut::from(data.as_slice())
+                    let data = reader.read_bytes()?;
+                    ::lagrange_proto::ProtoDecode::decode_shared_with_options(&data, reader.nested_options()?)?
                 }
             }
         }
@@ -493,17 +705,43 @@ fn generate_decode_value(ty: &Type) -> TokenStream {
             quote! {
                 {
                     let data = reader.read_length_delimited()?;
-                    ::lagrange_proto::ProtoDecode::decode(&data)?
+                    ::lagrange_proto::ProtoDecode::decode_with_options(&data, reader.nested_options()?)?
                 }
             }
         }
     }
 }
 
+/// Merge a length-delimited occurrence of a nested message into `target`, as a later
+/// occurrence of a singular message field does instead of replacing the earlier one
+fn generate_merge_value(ty: &Type, mode: DecodeMode, target: TokenStream) -> TokenStream {
+    match mode {
+        DecodeMode::Borrowed if has_lifetime(ty) => quote! {
+            ::lagrange_proto::ProtoDecodeBorrowed::merge_borrowed_with_options(
+                #target,
+                reader.read_length_delimited_bytes()?,
+                reader.nested_options()?,
+            )?
+        },
+        DecodeMode::Shared => quote! {
+            {
+                let data = reader.read_bytes()?;
+                ::lagrange_proto::ProtoDecode::merge_shared_with_options(#target, &data, reader.nested_options()?)?
+            }
+        },
+        _ => quote! {
+            {
+                let data = reader.read_length_delimited()?;
+                ::lagrange_proto::ProtoDecode::merge_with_options(#target, &data, reader.nested_options()?)?
+            }
+        },
+    }
+}
+
 fn generate_varint_decode(ty: &Type) -> TokenStream {
     quote! {
         {
-            let value = #ty::decode(reader.remaining())?;
+            let value = <#ty>::decode(reader.remaining())?;
             let value_size = value.encoded_size();
             reader.advance(value_size);
             value
@@ -511,7 +749,7 @@ fn generate_varint_decode(ty: &Type) -> TokenStream {
     }
 }
 
-fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenStream {
+fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool, mode: DecodeMode) -> TokenStream {
     let (oneof_fields, regular_fields): (Vec<_>, Vec<_>) = fields.iter().partition(|f| f.is_oneof);
 
     let field_matches = regular_fields.iter().map(|field| {
@@ -520,14 +758,31 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
 
         if field.is_map {
             if let Some((key_ty, val_ty)) = extract_map_types(&field.ty) {
-                let key_decode = generate_decode_value(&key_ty);
-                let val_decode = generate_decode_value(&val_ty);
+                let key_decode = generate_decode_value(&key_ty, mode);
+                let val_decode = generate_decode_value(&val_ty, mode);
+                // Borrowed and shared keys and values must point into the input, not into a copy of the entry
+                let entry_reader = match mode {
+                    DecodeMode::Owned => quote! {
+                        let entry_data = reader.read_length_delimited()?;
+                        let mut entry_reader =
+                            ::lagrange_proto::decoding::FieldReader::new(&entry_data).with_options(reader.options());
+                    },
+                    DecodeMode::Borrowed => quote! {
+                        let entry_data = reader.read_length_delimited_bytes()?;
+                        let mut entry_reader =
+                            ::lagrange_proto::decoding::FieldReader::new(entry_data).with_options(reader.options());
+                    },
+                    DecodeMode::Shared => quote! {
+                        let entry_data = reader.read_bytes()?;
+                        let mut entry_reader = ::lagrange_proto::decoding::FieldReader::new_shared(&entry_data)
+                            .with_options(reader.options());
+                    },
+                };
 
                 return quote! {
                     #tag => {
 
-                        let entry_data = reader.read_length_delimited()?;
-                        let mut entry_reader = ::lagrange_proto::decoding::FieldReader::new(&entry_data);
+                        #entry_reader
 
                         let mut key: Option<#key_ty> = None;
                         let mut value: Option<#val_ty> = None;
@@ -552,9 +807,8 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
                             }
                         }
 
-                        if let (Some(k), Some(v)) = (key, value) {
-                            result.#name.insert(k, v);
-                        }
+                        // Writers omit a key or value equal to its default
+                        result.#name.insert(key.unwrap_or_default(), value.unwrap_or_default());
                     }
                 };
             }
@@ -566,11 +820,12 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
             field.ty.clone()
         };
 
-        let decode_value = generate_decode_value(&decode_ty);
+        let decode_value = generate_decode_value(&decode_ty, mode);
 
         if field.is_repeated {
 
-            if field.attrs.packed && can_be_packed(&decode_ty) {
+            // Parsers accept both encodings of a repeated scalar, whichever one the field is written with
+            if can_be_packed(&decode_ty) {
 
                 quote! {
                     #tag => {
@@ -606,7 +861,7 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
             let type_str = quote!(#decode_ty).to_string();
             let type_str_trimmed = type_str.trim();
 
-            let is_known_primitive = matches!(
+            let is_known_primitive = is_cow_str(&decode_ty) || matches!(
                 type_str_trimmed,
                 "u32" | "u64" | "i32" | "i64" | "bool" | "f32" | "f64" |
                 "String" | "Vec < u8 >" | "Vec<u8>" |
@@ -621,10 +876,13 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
             if !is_known_primitive {
 
                 let varint_decode = generate_varint_decode(&decode_ty);
+                let merge_value = generate_merge_value(&decode_ty, mode, quote! { existing });
                 quote! {
                     #tag => {
                         if wire_type == ::lagrange_proto::wire::WireType::Varint {
                             result.#name = Some(#varint_decode);
+                        } else if let Some(existing) = result.#name.as_mut() {
+                            #merge_value;
                         } else {
                             let value = #decode_value;
                             result.#name = Some(value);
@@ -644,7 +902,7 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
             let type_str = quote!(#decode_ty).to_string();
             let type_str_trimmed = type_str.trim();
 
-            let is_known_primitive = matches!(
+            let is_known_primitive = is_cow_str(&decode_ty) || matches!(
                 type_str_trimmed,
                 "u32" | "u64" | "i32" | "i64" | "bool" | "f32" | "f64" |
                 "String" | "Vec < u8 >" | "Vec<u8>" |
@@ -659,13 +917,13 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
             if !is_known_primitive {
 
                 let varint_decode = generate_varint_decode(&decode_ty);
+                let merge_value = generate_merge_value(&decode_ty, mode, quote! { &mut result.#name });
                 quote! {
                     #tag => {
                         if wire_type == ::lagrange_proto::wire::WireType::Varint {
                             result.#name = #varint_decode;
                         } else {
-
-                            result.#name = #decode_value;
+                            #merge_value;
                         }
                     }
                 }
@@ -689,7 +947,7 @@ fn generate_field_decode(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
         };
 
         quote! {
-            if let Ok(value) = #oneof_ty::decode_with_tag(tag, wire_type, &mut reader) {
+            if let Ok(value) = <#oneof_ty>::decode_with_tag(tag, wire_type, &mut reader) {
                 result.#name = Some(value);
                 oneof_handled = true;
             }
@@ -756,6 +1014,10 @@ fn generate_default_init(fields: &[FieldInfo], preserve_unknown: bool) -> TokenS
 }
 
 fn parse_default_value(ty: &Type, default_str: &str) -> TokenStream {
+    if is_cow_str(ty) {
+        return quote! { ::std::borrow::Cow::Borrowed(#default_str) };
+    }
+
     let type_str = quote!(#ty).to_string();
     let type_str = type_str.trim();
 
@@ -831,6 +1093,214 @@ fn parse_default_value(ty: &Type, default_str: &str) -> TokenStream {
     }
 }
 
+/// Proto3 JSON mapping of the message, and `to_json` and `from_json` on it
+fn generate_json(input: &DeriveInput, fields: &[FieldInfo], default_init: &TokenStream) -> TokenStream {
+    let name = &input.ident;
+    let message = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (oneof_fields, regular_fields): (Vec<_>, Vec<_>) = fields.iter().partition(|f| f.is_oneof);
+
+    let writes = fields.iter().map(|field| {
+        let name = &field.name;
+        let json_name = json_name(&name.unraw().to_string());
+        if field.is_oneof {
+            quote! {
+                if let Some(ref value) = self.#name {
+                    let (name, value) = ::lagrange_proto::json::ProtoJsonOneof::to_json_field(value);
+                    object.insert(name.to_string(), value);
+                }
+            }
+        } else if field.is_map {
+            quote! { object.insert(#json_name.to_string(), ::lagrange_proto::json::map_to_json(&self.#name)); }
+        } else if field.is_repeated {
+            quote! { object.insert(#json_name.to_string(), ::lagrange_proto::json::repeated_to_json(&self.#name)); }
+        } else if field.is_optional {
+            quote! {
+                if let Some(ref value) = self.#name {
+                    object.insert(#json_name.to_string(), ::lagrange_proto::json::ProtoJson::to_json_value(value));
+                }
+            }
+        } else {
+            quote! {
+                object.insert(#json_name.to_string(), ::lagrange_proto::json::ProtoJson::to_json_value(&self.#name));
+            }
+        }
+    });
+
+    let read_arms = regular_fields.iter().map(|field| {
+        let name = &field.name;
+        let proto_name = name.unraw().to_string();
+        let pattern = name_pattern(&json_name(&proto_name), &proto_name);
+        let value = if field.is_map {
+            quote! { ::lagrange_proto::json::map_from_json(value)? }
+        } else if field.is_repeated {
+            quote! { ::lagrange_proto::json::repeated_from_json(value)? }
+        } else if field.is_optional {
+            quote! { Some(::lagrange_proto::json::ProtoJson::from_json_value(value)?) }
+        } else {
+            quote! { ::lagrange_proto::json::ProtoJson::from_json_value(value)? }
+        };
+        quote! {
+            #pattern => {
+                if !value.is_null() {
+                    result.#name = #value;
+                }
+            }
+        }
+    });
+
+    let read_oneofs = oneof_fields.iter().map(|field| {
+        let name = &field.name;
+        let oneof_ty = extract_inner_type(&field.ty).unwrap_or_else(|| field.ty.clone());
+        quote! {
+            if <#oneof_ty as ::lagrange_proto::json::ProtoJsonOneof>::has_json_field(key) {
+                if !value.is_null() {
+                    result.#name = Some(::lagrange_proto::json::ProtoJsonOneof::from_json_field(key, value)?);
+                }
+                continue;
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::lagrange_proto::json::ProtoJson for #name #ty_generics #where_clause {
+            fn to_json_value(&self) -> ::lagrange_proto::json::Value {
+                let mut object = ::lagrange_proto::json::Map::new();
+                #(#writes)*
+                ::lagrange_proto::json::Value::Object(object)
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn from_json_value(value: &::lagrange_proto::json::Value) -> Result<Self, ::lagrange_proto::DecodeError> {
+                let object = ::lagrange_proto::json::expect_object(value, #message)?;
+                let mut result = Self {
+                    #default_init
+                };
+
+                // A null field keeps its default
+                for (key, value) in object {
+                    match key.as_str() {
+                        #(#read_arms)*
+                        _ => {
+                            #(#read_oneofs)*
+                            return Err(::lagrange_proto::json::unknown_field(#message, key));
+                        }
+                    }
+                }
+
+                Ok(result)
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// This message in the proto3 JSON mapping
+            #[allow(dead_code)]
+            pub fn to_json(&self) -> String {
+                ::lagrange_proto::json::to_string(self)
+            }
+
+            /// Read this message from its proto3 JSON mapping
+            #[allow(dead_code)]
+            pub fn from_json(json: &str) -> Result<Self, ::lagrange_proto::DecodeError> {
+                ::lagrange_proto::json::from_str(json)
+            }
+        }
+    }
+}
+
+/// `serde` impls writing the fields under their proto names, for `#[proto(serde)]`
+///
+/// Deserializing goes through a copy of the message with every field optional and derived
+/// `Deserialize`, fields it leaves out keep their defaults.
+fn generate_serde(input: &DeriveInput, fields: &[FieldInfo], default_init: &TokenStream) -> TokenStream {
+    let name = &input.ident;
+    let message = name.to_string();
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let field_count = fields.len();
+
+    let serialize_fields = fields.iter().map(|field| {
+        let name = &field.name;
+        let proto_name = name.unraw().to_string();
+        if field.is_optional || field.is_oneof {
+            quote! {
+                match self.#name {
+                    Some(ref value) => state.serialize_field(#proto_name, value)?,
+                    None => state.skip_field(#proto_name)?,
+                }
+            }
+        } else {
+            quote! { state.serialize_field(#proto_name, &self.#name)?; }
+        }
+    });
+
+    let shadow_fields = fields.iter().map(|field| {
+        let name = &field.name;
+        let ty = &field.ty;
+        if field.is_optional || field.is_oneof {
+            quote! { #name: #ty }
+        } else {
+            quote! { #name: Option<#ty> }
+        }
+    });
+    let assign_fields = fields.iter().map(|field| {
+        let name = &field.name;
+        if field.is_optional || field.is_oneof {
+            quote! { result.#name = shadow.#name; }
+        } else {
+            quote! {
+                if let Some(value) = shadow.#name {
+                    result.#name = value;
+                }
+            }
+        }
+    });
+
+    let mut de_generics = generics.clone();
+    de_generics.params.insert(0, syn::parse_quote!('de));
+    let (de_impl_generics, _, _) = de_generics.split_for_impl();
+
+    quote! {
+        const _: () = {
+            #[derive(::lagrange_proto::serde::Deserialize)]
+            #[serde(crate = "::lagrange_proto::serde", deny_unknown_fields)]
+            struct Shadow #generics #where_clause {
+                #(
+                    #[serde(default)]
+                    #shadow_fields,
+                )*
+            }
+
+            impl #impl_generics ::lagrange_proto::serde::Serialize for #name #ty_generics #where_clause {
+                fn serialize<S: ::lagrange_proto::serde::Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    use ::lagrange_proto::serde::ser::SerializeStruct;
+                    let mut state = serializer.serialize_struct(#message, #field_count)?;
+                    #(#serialize_fields)*
+                    state.end()
+                }
+            }
+
+            impl #de_impl_generics ::lagrange_proto::serde::Deserialize<'de> for #name #ty_generics #where_clause {
+                fn deserialize<D: ::lagrange_proto::serde::Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Self, D::Error> {
+                    let shadow =
+                        <Shadow #ty_generics as ::lagrange_proto::serde::Deserialize>::deserialize(deserializer)?;
+                    let mut result = Self {
+                        #default_init
+                    };
+                    #(#assign_fields)*
+                    Ok(result)
+                }
+            }
+        };
+    }
+}
+
 pub fn expand_derive_proto_message(input: DeriveInput) -> Result<TokenStream> {
     let name = &input.ident;
 
@@ -881,7 +1351,7 @@ pub fn expand_derive_proto_message(input: DeriveInput) -> Result<TokenStream> {
         let tag = if is_oneof { 0 } else { attrs.tag.unwrap() };
         let ty = field.ty.clone();
         let is_optional = is_option(&ty);
-        let is_repeated = is_vec(&ty);
+        let is_repeated = is_vec(&ty) && !is_byte_vec(&ty);
         let is_map = is_map(&ty);
 
         field_infos.push(FieldInfo {
@@ -896,7 +1366,8 @@ pub fn expand_derive_proto_message(input: DeriveInput) -> Result<TokenStream> {
         });
     }
 
-    let encode_fields = field_infos.iter().map(generate_field_encode);
+    let encode_fields = field_infos.iter().map(|field| generate_field_encode(field, false));
+    let encode_canonical_fields = generate_canonical_encode(&field_infos);
 
     let size_fields = field_infos.iter().map(generate_field_size);
 
@@ -912,17 +1383,73 @@ pub fn expand_derive_proto_message(input: DeriveInput) -> Result<TokenStream> {
         quote! {}
     };
 
-    let decode_match = generate_field_decode(&field_infos, msg_attrs.preserve_unknown);
+    let decode_match = generate_field_decode(&field_infos, msg_attrs.preserve_unknown, DecodeMode::Owned);
+    let decode_shared_match = generate_field_decode(&field_infos, msg_attrs.preserve_unknown, DecodeMode::Shared);
     let default_init = generate_default_init(&field_infos, msg_attrs.preserve_unknown);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let json = if cfg!(feature = "json") {
+        generate_json(&input, &field_infos, &default_init)
+    } else {
+        quote! {}
+    };
+    let serde = if msg_attrs.serde {
+        generate_serde(&input, &field_infos, &default_init)
+    } else {
+        quote! {}
+    };
+
+    // A message taking a lifetime can also be decoded borrowing from its input
+    let decode_borrowed = input.generics.lifetimes().next().map(|param| {
+        let lifetime = &param.lifetime;
+        let decode_match = generate_field_decode(&field_infos, msg_attrs.preserve_unknown, DecodeMode::Borrowed);
+        quote! {
+            impl #impl_generics ::lagrange_proto::ProtoDecodeBorrowed<#lifetime> for #name #ty_generics #where_clause {
+                fn decode_borrowed_with_options(
+                    buf: &#lifetime [u8],
+                    options: ::lagrange_proto::DecodeOptions,
+                ) -> Result<Self, ::lagrange_proto::DecodeError> {
+                    let mut result = Self {
+                        #default_init
+                    };
+                    result.merge_borrowed_with_options(buf, options)?;
+                    Ok(result)
+                }
+
+                fn merge_borrowed_with_options(
+                    &mut self,
+                    buf: &#lifetime [u8],
+                    options: ::lagrange_proto::DecodeOptions,
+                ) -> Result<(), ::lagrange_proto::DecodeError> {
+                    let mut reader = ::lagrange_proto::decoding::FieldReader::new(buf).with_options(options);
+                    let result = self;
+
+                    while reader.has_remaining() {
+                        let (tag, wire_type) = reader.read_field_key()?;
+                        #decode_match
+                    }
+
+                    Ok(())
+                }
+            }
+        }
+    });
 
     let expanded = quote! {
-        impl ::lagrange_proto::ProtoEncode for #name {
+        impl #impl_generics ::lagrange_proto::ProtoEncode for #name #ty_generics #where_clause {
+            const IS_MESSAGE: bool = true;
+
             fn encode<B: ::bytes::BufMut>(&self, buf: &mut B) -> Result<(), ::lagrange_proto::EncodeError> {
                 #(#encode_fields)*
                 #unknown_encode
                 Ok(())
             }
 
+            fn encode_canonical<B: ::bytes::BufMut>(&self, buf: &mut B) -> Result<(), ::lagrange_proto::EncodeError> {
+                #encode_canonical_fields
+                #unknown_encode
+                Ok(())
+            }
+
             fn encoded_size(&self) -> usize {
                 let mut size = 0;
                 #(#size_fields)*
@@ -931,21 +1458,71 @@ pub fn expand_derive_proto_message(input: DeriveInput) -> Result<TokenStream> {
             }
         }
 
-        impl ::lagrange_proto::ProtoDecode for #name {
+        impl #impl_generics ::lagrange_proto::ProtoDecode for #name #ty_generics #where_clause {
             fn decode(buf: &[u8]) -> Result<Self, ::lagrange_proto::DecodeError> {
-                let mut reader = ::lagrange_proto::decoding::FieldReader::new(buf);
+                Self::decode_with_options(buf, ::lagrange_proto::DecodeOptions::default())
+            }
+
+            fn decode_with_options(
+                buf: &[u8],
+                options: ::lagrange_proto::DecodeOptions,
+            ) -> Result<Self, ::lagrange_proto::DecodeError> {
                 let mut result = Self {
                     #default_init
                 };
+                result.merge_with_options(buf, options)?;
+                Ok(result)
+            }
+
+            fn merge_with_options(
+                &mut self,
+                buf: &[u8],
+                options: ::lagrange_proto::DecodeOptions,
+            ) -> Result<(), ::lagrange_proto::DecodeError> {
+                let mut reader = ::lagrange_proto::decoding::FieldReader::new(buf).with_options(options);
+                let result = self;
 
                 while reader.has_remaining() {
                     let (tag, wire_type) = reader.read_field_key()?;
                     #decode_match
                 }
 
+                Ok(())
+            }
+
+            fn decode_shared_with_options(
+                buf: &::bytes::Bytes,
+                options: ::lagrange_proto::DecodeOptions,
+            ) -> Result<Self, ::lagrange_proto::DecodeError> {
+                let mut result = Self {
+                    #default_init
+                };
+                result.merge_shared_with_options(buf, options)?;
                 Ok(result)
             }
+
+            fn merge_shared_with_options(
+                &mut self,
+                buf: &::bytes::Bytes,
+                options: ::lagrange_proto::DecodeOptions,
+            ) -> Result<(), ::lagrange_proto::DecodeError> {
+                let mut reader = ::lagrange_proto::decoding::FieldReader::new_shared(buf).with_options(options);
+                let result = self;
+
+                while reader.has_remaining() {
+                    let (tag, wire_type) = reader.read_field_key()?;
+                    #decode_shared_match
+                }
+
+                Ok(())
+            }
         }
+
+        #decode_borrowed
+
+        #json
+
+        #serde
     };
 
     Ok(expanded)
@@ -1,3 +1,5 @@
+use crate::attributes::ProtoMessageAttrs;
+use crate::json::{json_name, name_pattern, snake_case};
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Data, DeriveInput, Error, Fields, Meta, Result, Variant};
@@ -9,7 +11,7 @@ fn extract_tag(variant: &Variant) -> Result<u32> {
                 if nv.path.is_ident("tag") {
                     if let syn::Expr::Lit(expr_lit) = &nv.value {
                         if let syn::Lit::Int(lit_int) = &expr_lit.lit {
-                            return lit_int.base10_parse();
+                            return crate::attributes::parse_tag(lit_int);
                         }
                     }
                 }
@@ -82,6 +84,10 @@ pub fn expand_derive_proto_oneof(input: DeriveInput) -> Result<TokenStream> {
         }
     });
 
+    let tag_arms = variant_infos.iter().map(|(name, tag, _)| {
+        quote! { #enum_name::#name(_) => #tag }
+    });
+
     let decode_arms: Vec<_> = variant_infos
         .iter()
         .map(|(name, tag, field_ty)| {
@@ -95,6 +101,108 @@ pub fn expand_derive_proto_oneof(input: DeriveInput) -> Result<TokenStream> {
         })
         .collect();
 
+    // Each variant is a field of the message holding the oneof, named after the variant
+    let json = if cfg!(feature = "json") {
+        let enum_str = enum_name.to_string();
+        let patterns: Vec<_> = variant_infos
+            .iter()
+            .map(|(name, _, _)| {
+                let name = name.to_string();
+                name_pattern(&json_name(&name), &snake_case(&name))
+            })
+            .collect();
+        let to_field_arms = variant_infos.iter().map(|(name, _, _)| {
+            let json_name = json_name(&name.to_string());
+            quote! {
+                #enum_name::#name(ref value) => (#json_name, ::lagrange_proto::json::ProtoJson::to_json_value(value))
+            }
+        });
+        let from_field_arms = variant_infos.iter().zip(&patterns).map(|((name, _, _), pattern)| {
+            quote! {
+                #pattern => ::lagrange_proto::json::ProtoJson::from_json_value(value).map(#enum_name::#name)
+            }
+        });
+        quote! {
+            impl ::lagrange_proto::json::ProtoJsonOneof for #enum_name {
+                fn to_json_field(&self) -> (&'static str, ::lagrange_proto::json::Value) {
+                    match self {
+                        #(#to_field_arms),*
+                    }
+                }
+
+                fn has_json_field(name: &str) -> bool {
+                    matches!(name, #(#patterns)|*)
+                }
+
+                fn from_json_field(
+                    name: &str,
+                    value: &::lagrange_proto::json::Value,
+                ) -> Result<Self, ::lagrange_proto::DecodeError> {
+                    match name {
+                        #(#from_field_arms,)*
+                        _ => Err(::lagrange_proto::json::unknown_field(#enum_str, name)),
+                    }
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Externally tagged by the variant's field name, `#[proto(serde)]`
+    let serde = if ProtoMessageAttrs::from_derive_input(&input)?.serde {
+        let enum_str = enum_name.to_string();
+        let serialize_arms = variant_infos.iter().enumerate().map(|(index, (name, _, _))| {
+            let index = index as u32;
+            let field_name = snake_case(&name.to_string());
+            quote! {
+                #enum_name::#name(ref value) => {
+                    serializer.serialize_newtype_variant(#enum_str, #index, #field_name, value)
+                }
+            }
+        });
+        let shadow_variants = variant_infos.iter().map(|(name, _, field_ty)| {
+            let field_name = snake_case(&name.to_string());
+            quote! {
+                #[serde(rename = #field_name)]
+                #name(#field_ty)
+            }
+        });
+        let names: Vec<_> = variant_infos.iter().map(|(name, _, _)| *name).collect();
+        quote! {
+            const _: () = {
+                #[derive(::lagrange_proto::serde::Deserialize)]
+                #[serde(crate = "::lagrange_proto::serde")]
+                enum Shadow {
+                    #(#shadow_variants),*
+                }
+
+                impl ::lagrange_proto::serde::Serialize for #enum_name {
+                    fn serialize<S: ::lagrange_proto::serde::Serializer>(
+                        &self,
+                        serializer: S,
+                    ) -> Result<S::Ok, S::Error> {
+                        match self {
+                            #(#serialize_arms),*
+                        }
+                    }
+                }
+
+                impl<'de> ::lagrange_proto::serde::Deserialize<'de> for #enum_name {
+                    fn deserialize<D: ::lagrange_proto::serde::Deserializer<'de>>(
+                        deserializer: D,
+                    ) -> Result<Self, D::Error> {
+                        Ok(match <Shadow as ::lagrange_proto::serde::Deserialize>::deserialize(deserializer)? {
+                            #(Shadow::#names(value) => #enum_name::#names(value)),*
+                        })
+                    }
+                }
+            };
+        }
+    } else {
+        quote! {}
+    };
+
     let expanded = quote! {
         impl ::lagrange_proto::ProtoEncode for #enum_name {
             fn encode<B: ::bytes::BufMut>(&self, buf: &mut B) -> Result<(), ::lagrange_proto::EncodeError> {
@@ -112,6 +220,14 @@ pub fn expand_derive_proto_oneof(input: DeriveInput) -> Result<TokenStream> {
         }
 
         impl #enum_name {
+            /// Tag of the field the set variant is written as
+            #[allow(dead_code)]
+            pub fn tag(&self) -> u32 {
+                match self {
+                    #(#tag_arms),*
+                }
+            }
+
             #[allow(dead_code)]
             pub fn decode_with_tag(tag: u32, wire_type: ::lagrange_proto::wire::WireType, reader: &mut ::lagrange_proto::decoding::FieldReader<'_>) -> Result<Self, ::lagrange_proto::DecodeError> {
                 match tag {
@@ -120,6 +236,10 @@ pub fn expand_derive_proto_oneof(input: DeriveInput) -> Result<TokenStream> {
                 }
             }
         }
+
+        #json
+
+        #serde
     };
 
     Ok(expanded)
@@ -263,7 +383,10 @@ fn generate_decode_value(ty: &syn::Type) -> TokenStream {
             quote! {
                 {
 
-                    let value = ::lagrange_proto::ProtoDecode::decode(reader.remaining())?;
+                    let value = ::lagrange_proto::ProtoDecode::decode_with_options(
+                        reader.remaining(),
+                        reader.nested_options()?,
+                    )?;
                     let value_size = ::lagrange_proto::ProtoEncode::encoded_size(&value);
                     reader.advance(value_size);
                     value
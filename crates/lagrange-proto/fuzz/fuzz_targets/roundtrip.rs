@@ -0,0 +1,48 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use lagrange_proto::{Fixed32, ProtoEncode, ProtoMessage, SFixed64, SInt32, SInt64};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, PartialEq, ProtoMessage, Arbitrary)]
+struct Inner {
+    #[proto(tag = 1)]
+    value: u32,
+    #[proto(tag = 2)]
+    label: Option<String>,
+}
+
+/// Covers the field kinds of the messages in `tests/`, floats aside as NaN is not equal to itself
+#[derive(Debug, PartialEq, ProtoMessage, Arbitrary)]
+struct Sample {
+    #[proto(tag = 1)]
+    id: u64,
+    #[proto(tag = 2)]
+    name: String,
+    #[proto(tag = 3)]
+    active: bool,
+    #[proto(tag = 4)]
+    value: Option<i32>,
+    #[proto(tag = 5)]
+    tags: Vec<String>,
+    #[proto(tag = 6, packed)]
+    numbers: Vec<u32>,
+    #[proto(tag = 7)]
+    scores: Vec<i64>,
+    #[proto(tag = 8)]
+    single: Option<Inner>,
+    #[proto(tag = 9)]
+    many: Vec<Inner>,
+    #[proto(tag = 10)]
+    sint32_value: SInt32,
+    #[proto(tag = 11)]
+    sint64_value: SInt64,
+    #[proto(tag = 12)]
+    fixed32_value: Fixed32,
+    #[proto(tag = 13)]
+    sfixed64_value: SFixed64,
+}
+
+fuzz_target!(|data: &[u8]| {
+    lagrange_proto::fuzz::roundtrip_check::<Sample>(data);
+});
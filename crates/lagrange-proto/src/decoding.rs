@@ -1,14 +1,102 @@
 use crate::error::DecodeError;
 use crate::varint;
 use crate::wire::{decode_key, WireType};
-use bytes::Bytes;
+use bytes::{Buf, Bytes, BytesMut};
+use std::marker::PhantomData;
+
+/// Limits applied while decoding input that cannot be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// How many levels of nested messages may be decoded below the outermost one, beyond
+    /// which decoding fails with [`DecodeError::RecursionLimitExceeded`]
+    pub max_depth: usize,
+    /// Longest length-delimited field accepted, longer ones fail with
+    /// [`DecodeError::LengthLimitExceeded`] before anything is read or allocated for them
+    pub max_length_delimited: usize,
+}
+
+impl DecodeOptions {
+    pub const DEFAULT_MAX_DEPTH: usize = 100;
+    pub const DEFAULT_MAX_LENGTH_DELIMITED: usize = 64 * 1024 * 1024;
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_max_length_delimited(mut self, max_length_delimited: usize) -> Self {
+        self.max_length_delimited = max_length_delimited;
+        self
+    }
+
+    /// Options for a message nested one level deeper, failing if that is too deep
+    #[inline]
+    pub fn nested(self) -> Result<Self, DecodeError> {
+        match self.max_depth.checked_sub(1) {
+            Some(max_depth) => Ok(Self { max_depth, ..self }),
+            None => Err(DecodeError::RecursionLimitExceeded),
+        }
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            max_length_delimited: Self::DEFAULT_MAX_LENGTH_DELIMITED,
+        }
+    }
+}
 
 pub trait ProtoDecode: Sized {
     fn decode(buf: &[u8]) -> Result<Self, DecodeError>;
 
+    /// Decode under `options`; only messages nest, so other types ignore them.
+    fn decode_with_options(buf: &[u8], _options: DecodeOptions) -> Result<Self, DecodeError> {
+        Self::decode(buf)
+    }
+
+    /// Merge another encoding into `self`: fields it sets overwrite singular ones, append to
+    /// repeated ones and merge into nested messages. Types other than messages are replaced.
     fn merge(&mut self, buf: &[u8]) -> Result<(), DecodeError> {
-        let decoded = Self::decode(buf)?;
-        *self = decoded;
+        self.merge_with_options(buf, DecodeOptions::default())
+    }
+
+    fn merge_with_options(&mut self, buf: &[u8], options: DecodeOptions) -> Result<(), DecodeError> {
+        *self = Self::decode_with_options(buf, options)?;
+        Ok(())
+    }
+
+    /// Decode from a shared buffer, letting `Bytes` fields point into it instead of copying
+    /// them out; types that cannot share fall back to [`ProtoDecode::decode`].
+    fn decode_shared(buf: &Bytes) -> Result<Self, DecodeError> {
+        Self::decode_shared_with_options(buf, DecodeOptions::default())
+    }
+
+    fn decode_shared_with_options(buf: &Bytes, options: DecodeOptions) -> Result<Self, DecodeError> {
+        Self::decode_with_options(buf, options)
+    }
+
+    fn merge_shared_with_options(&mut self, buf: &Bytes, options: DecodeOptions) -> Result<(), DecodeError> {
+        *self = Self::decode_shared_with_options(buf, options)?;
+        Ok(())
+    }
+}
+
+/// Decoding that may borrow from the input, for types holding references into it such as
+/// [`CowStr`](crate::types::CowStr)
+///
+/// [`ProtoDecode::decode`] cannot tie its output to the input buffer, so types implementing both
+/// come out owned from it and borrowed from this one.
+pub trait ProtoDecodeBorrowed<'a>: Sized {
+    fn decode_borrowed(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        Self::decode_borrowed_with_options(buf, DecodeOptions::default())
+    }
+
+    fn decode_borrowed_with_options(buf: &'a [u8], options: DecodeOptions) -> Result<Self, DecodeError>;
+
+    fn merge_borrowed_with_options(&mut self, buf: &'a [u8], options: DecodeOptions) -> Result<(), DecodeError> {
+        *self = Self::decode_borrowed_with_options(buf, options)?;
         Ok(())
     }
 }
@@ -50,12 +138,45 @@ pub fn skip_field(wire_type: WireType, buf: &[u8]) -> Result<usize, DecodeError>
                 Ok(total_len)
             }
         }
-        WireType::StartGroup | WireType::EndGroup => {
-            Err(DecodeError::Custom("Groups are not supported".to_string()))
+        WireType::StartGroup => group_len(buf, None, DecodeOptions::DEFAULT_MAX_DEPTH),
+        WireType::EndGroup => Err(unmatched_end_group()),
+    }
+}
+
+/// Length of a proto2 group's fields and its end key, `buf` starting right after its start key
+///
+/// The end key must have the group's `tag` when it is known, and so must the end keys of groups
+/// nested in it, at most `max_depth` deep.
+fn group_len(buf: &[u8], tag: Option<u32>, max_depth: usize) -> Result<usize, DecodeError> {
+    let mut open = vec![tag];
+    let mut pos = 0;
+    loop {
+        let (field_tag, wire_type, key_len) = decode_field_key(&buf[pos..])?;
+        pos += key_len;
+        match wire_type {
+            WireType::StartGroup => {
+                if open.len() >= max_depth {
+                    return Err(DecodeError::RecursionLimitExceeded);
+                }
+                open.push(Some(field_tag));
+            }
+            WireType::EndGroup => {
+                if open.pop().flatten().is_some_and(|start_tag| start_tag != field_tag) {
+                    return Err(DecodeError::InvalidTag(field_tag));
+                }
+                if open.is_empty() {
+                    return Ok(pos);
+                }
+            }
+            _ => pos += skip_field(wire_type, &buf[pos..])?,
         }
     }
 }
 
+fn unmatched_end_group() -> DecodeError {
+    DecodeError::Custom("End of a group that was not started".to_string())
+}
+
 impl ProtoDecode for u32 {
     #[inline]
     fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
@@ -167,6 +288,12 @@ impl ProtoDecode for Bytes {
         let bytes = &buf[varint_len..varint_len + len];
         Ok(Bytes::copy_from_slice(bytes))
     }
+
+    #[inline]
+    fn decode_shared_with_options(buf: &Bytes, _options: DecodeOptions) -> Result<Self, DecodeError> {
+        let (data, _) = decode_length_delimited(buf)?;
+        Ok(buf.slice_ref(data))
+    }
 }
 
 impl ProtoDecode for bytes::BytesMut {
@@ -184,6 +311,34 @@ impl ProtoDecode for bytes::BytesMut {
     }
 }
 
+/// Recursive messages hold their nested self boxed
+impl<T: ProtoDecode> ProtoDecode for Box<T> {
+    #[inline]
+    fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        T::decode(buf).map(Box::new)
+    }
+
+    #[inline]
+    fn decode_with_options(buf: &[u8], options: DecodeOptions) -> Result<Self, DecodeError> {
+        T::decode_with_options(buf, options).map(Box::new)
+    }
+
+    #[inline]
+    fn decode_shared_with_options(buf: &Bytes, options: DecodeOptions) -> Result<Self, DecodeError> {
+        T::decode_shared_with_options(buf, options).map(Box::new)
+    }
+
+    #[inline]
+    fn merge_with_options(&mut self, buf: &[u8], options: DecodeOptions) -> Result<(), DecodeError> {
+        (**self).merge_with_options(buf, options)
+    }
+
+    #[inline]
+    fn merge_shared_with_options(&mut self, buf: &Bytes, options: DecodeOptions) -> Result<(), DecodeError> {
+        (**self).merge_shared_with_options(buf, options)
+    }
+}
+
 #[inline]
 pub fn decode_length_delimited(buf: &[u8]) -> Result<(&[u8], usize), DecodeError> {
     let (len, varint_len) = varint::decode::<u32>(buf)?;
@@ -225,12 +380,47 @@ pub fn decode_fixed64_field(buf: &[u8]) -> Result<(u64, usize), DecodeError> {
 pub struct FieldReader<'a> {
     buf: &'a [u8],
     pos: usize,
+    /// Owner of `buf` when reading from [`FieldReader::new_shared`]
+    shared: Option<&'a Bytes>,
+    options: DecodeOptions,
+    /// Tag of the last key read, which the end of a group must repeat
+    tag: Option<u32>,
 }
 
 impl<'a> FieldReader<'a> {
     #[inline]
     pub fn new(buf: &'a [u8]) -> Self {
-        Self { buf, pos: 0 }
+        Self { buf, pos: 0, shared: None, options: DecodeOptions::default(), tag: None }
+    }
+
+    /// Read `buf` under `options` rather than the defaults
+    #[inline]
+    pub fn new_with_limits(buf: &'a [u8], options: DecodeOptions) -> Self {
+        Self::new(buf).with_options(options)
+    }
+
+    /// Read from a shared buffer, which [`FieldReader::read_bytes`] slices instead of copying
+    #[inline]
+    pub fn new_shared(buf: &'a Bytes) -> Self {
+        Self { buf, pos: 0, shared: Some(buf), options: DecodeOptions::default(), tag: None }
+    }
+
+    /// Decode the message being read under `options`
+    #[inline]
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[inline]
+    pub fn options(&self) -> DecodeOptions {
+        self.options
+    }
+
+    /// Options to decode a message nested in the one being read with
+    #[inline]
+    pub fn nested_options(&self) -> Result<DecodeOptions, DecodeError> {
+        self.options.nested()
     }
 
     /// Check if there's more data to read.
@@ -253,12 +443,20 @@ impl<'a> FieldReader<'a> {
     pub fn read_field_key(&mut self) -> Result<(u32, WireType), DecodeError> {
         let (tag, wire_type, len) = decode_field_key(self.remaining())?;
         self.advance(len);
+        self.tag = Some(tag);
         Ok((tag, wire_type))
     }
 
     #[inline]
     pub fn skip_field(&mut self, wire_type: WireType) -> Result<(), DecodeError> {
-        let len = skip_field(wire_type, self.remaining())?;
+        let len = match wire_type {
+            WireType::LengthDelimited => {
+                self.check_length_delimited()?;
+                skip_field(wire_type, self.remaining())?
+            }
+            WireType::StartGroup => self.group_len()?,
+            _ => skip_field(wire_type, self.remaining())?,
+        };
         self.advance(len);
         Ok(())
     }
@@ -289,8 +487,8 @@ impl<'a> FieldReader<'a> {
                 data
             }
             WireType::LengthDelimited => {
-                let (len, varint_len) = varint::decode::<u32>(self.remaining())?;
-                let total_len = varint_len + len as usize;
+                let (len, varint_len) = self.check_length_delimited()?;
+                let total_len = varint_len + len;
                 if self.remaining().len() < total_len {
                     return Err(DecodeError::UnexpectedEof);
                 }
@@ -298,9 +496,14 @@ impl<'a> FieldReader<'a> {
                 self.advance(total_len);
                 data
             }
-            WireType::StartGroup | WireType::EndGroup => {
-                return Err(DecodeError::Custom("Groups are not supported".to_string()))
+            // The group's fields and end key, written back after its start key
+            WireType::StartGroup => {
+                let len = self.group_len()?;
+                let data = self.remaining()[..len].to_vec();
+                self.advance(len);
+                data
             }
+            WireType::EndGroup => return Err(unmatched_end_group()),
         };
         Ok(data)
     }
@@ -314,15 +517,49 @@ impl<'a> FieldReader<'a> {
 
     #[inline]
     pub fn read_length_delimited(&mut self) -> Result<Vec<u8>, DecodeError> {
+        self.check_length_delimited()?;
         let (data, len) = decode_length_delimited(self.remaining())?;
         let result = data.to_vec();
         self.advance(len);
         Ok(result)
     }
 
+    /// Read a length-delimited field as `Bytes`, sharing the input of [`FieldReader::new_shared`]
+    /// and copied out of any other input
+    #[inline]
+    pub fn read_bytes(&mut self) -> Result<Bytes, DecodeError> {
+        self.check_length_delimited()?;
+        let (data, len) = decode_length_delimited(self.remaining())?;
+        let bytes = match self.shared {
+            Some(shared) => shared.slice_ref(data),
+            None => Bytes::copy_from_slice(data),
+        };
+        self.advance(len);
+        Ok(bytes)
+    }
+
+    /// Like [`FieldReader::read_length_delimited`], borrowing the payload from the input
+    #[inline]
+    pub fn read_length_delimited_bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        self.check_length_delimited()?;
+        let buf = self.buf;
+        let (data, len) = decode_length_delimited(&buf[self.pos..])?;
+        self.advance(len);
+        Ok(data)
+    }
+
+    /// Read a length-delimited UTF-8 string borrowed from the input
+    #[inline]
+    pub fn read_str(&mut self) -> Result<&'a str, DecodeError> {
+        let data = self.read_length_delimited_bytes()?;
+        // `FromUtf8Error` can only be built by a failed conversion, which only happens here on invalid input
+        std::str::from_utf8(data).map_err(|_| DecodeError::InvalidUtf8(String::from_utf8(data.to_vec()).unwrap_err()))
+    }
+
     #[inline]
     pub fn read_length_delimited_slice(&mut self) -> Result<(usize, usize), DecodeError> {
         let start = self.pos;
+        self.check_length_delimited()?;
         let (_, len) = decode_length_delimited(self.remaining())?;
         self.advance(len);
         Ok((start, len))
@@ -341,13 +578,118 @@ impl<'a> FieldReader<'a> {
         self.advance(len);
         Ok(value)
     }
+
+    /// Length of the group at the cursor, started by the last key read
+    #[inline]
+    fn group_len(&self) -> Result<usize, DecodeError> {
+        group_len(self.remaining(), self.tag, self.options.max_depth)
+    }
+
+    /// Length and prefix size of the length-delimited field at the cursor, failing if it is
+    /// longer than [`DecodeOptions::max_length_delimited`]
+    #[inline]
+    fn check_length_delimited(&self) -> Result<(usize, usize), DecodeError> {
+        let (len, varint_len) = varint::decode::<u32>(self.remaining())?;
+        let len = len as usize;
+        if len > self.options.max_length_delimited {
+            return Err(DecodeError::LengthLimitExceeded(len));
+        }
+        Ok((len, varint_len))
+    }
+}
+
+/// Decodes messages prefixed with their varint length from input arriving in chunks
+///
+/// Chunks are accumulated until a whole message is buffered, which is then decoded with
+/// [`ProtoDecode::decode_shared_with_options`] so its `Bytes` fields share the accumulator.
+/// A prefix longer than [`DecodeOptions::max_length_delimited`] fails as soon as it is read,
+/// before its message is buffered.
+pub struct StreamingDecoder<T> {
+    buf: BytesMut,
+    /// Length of the message being buffered, once its prefix has been read
+    len: Option<usize>,
+    options: DecodeOptions,
+    _message: PhantomData<fn() -> T>,
+}
+
+impl<T: ProtoDecode> StreamingDecoder<T> {
+    pub fn new() -> Self {
+        Self { buf: BytesMut::new(), len: None, options: DecodeOptions::default(), _message: PhantomData }
+    }
+
+    /// Decode messages under `options`
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Bytes received and not yet decoded, including a partially read prefix
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Append `chunk` to the input, returning the next message if it is complete
+    ///
+    /// A chunk may complete several messages, the ones after the first are returned by
+    /// [`StreamingDecoder::next_message`].
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Option<T>, DecodeError> {
+        self.buf.extend_from_slice(chunk);
+        self.next_message()
+    }
+
+    /// Decode the next message if it has been received in full
+    ///
+    /// A message that fails to decode is dropped and the following ones can still be read,
+    /// an invalid or oversized prefix leaves the decoder failing on every call.
+    pub fn next_message(&mut self) -> Result<Option<T>, DecodeError> {
+        let len = match self.len {
+            Some(len) => len,
+            None => match self.read_prefix()? {
+                Some(len) => len,
+                None => return Ok(None),
+            },
+        };
+        if self.buf.len() < len {
+            self.buf.reserve(len - self.buf.len());
+            return Ok(None);
+        }
+
+        self.len = None;
+        let message = self.buf.split_to(len).freeze();
+        T::decode_shared_with_options(&message, self.options).map(Some)
+    }
+
+    /// Consume the length prefix at the front of the input once all its bytes have arrived
+    fn read_prefix(&mut self) -> Result<Option<usize>, DecodeError> {
+        let prefix = &self.buf[..self.buf.len().min(varint::MAX_VARINT_LEN_U32)];
+        if prefix.iter().all(|byte| byte & 0x80 != 0) {
+            return match prefix.len() {
+                varint::MAX_VARINT_LEN_U32 => Err(DecodeError::InvalidVarint),
+                _ => Ok(None),
+            };
+        }
+
+        let (len, varint_len) = varint::decode::<u32>(prefix)?;
+        let len = len as usize;
+        if len > self.options.max_length_delimited {
+            return Err(DecodeError::LengthLimitExceeded(len));
+        }
+        self.buf.advance(varint_len);
+        self.len = Some(len);
+        Ok(Some(len))
+    }
+}
+
+impl<T: ProtoDecode> Default for StreamingDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::encoding::ProtoEncode;
-    use bytes::BytesMut;
 
     #[test]
     fn test_decode_primitives() {
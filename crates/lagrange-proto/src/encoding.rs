@@ -1,11 +1,21 @@
 use crate::error::EncodeError;
 use crate::varint;
-use crate::wire::{encode_key, WireType};
+use crate::wire::{checked_encode_key, encode_key, WireType};
 use bytes::{BufMut, Bytes, BytesMut};
 
 pub trait ProtoEncode {
+    /// `true` for messages, whose `encode` writes bare fields: nested in another message
+    /// they must be written as length-delimited fields
+    const IS_MESSAGE: bool = false;
+
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError>;
 
+    /// Encode so that equal values always produce the same bytes: message fields in ascending
+    /// tag order and map entries sorted by key. Only messages differ from [`ProtoEncode::encode`].
+    fn encode_canonical<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
+        self.encode(buf)
+    }
+
     fn encoded_size(&self) -> usize;
 }
 
@@ -16,12 +26,74 @@ pub fn encode_field<B: BufMut, T: ProtoEncode>(
     value: &T,
     buf: &mut B,
 ) -> Result<(), EncodeError> {
-    let key = encode_key(tag, wire_type);
+    let key = checked_encode_key(tag, wire_type)?;
     let (arr, len) = varint::encode(key);
     buf.put_slice(&arr[..len]);
     value.encode(buf)
 }
 
+/// Length prefix of a length-delimited field of `size` bytes, failing instead of truncating it
+#[inline]
+pub fn checked_len(size: usize) -> Result<u32, EncodeError> {
+    u32::try_from(size).map_err(|_| EncodeError::MessageTooLarge { size })
+}
+
+/// Encodes a field whose type is not a scalar known to the derive: a nested message or an enum
+#[doc(hidden)]
+#[inline]
+pub fn encode_custom_field<B: BufMut, T: ProtoEncode>(
+    tag: u32,
+    value: &T,
+    buf: &mut B,
+) -> Result<(), EncodeError> {
+    encode_custom_field_key(tag, value, buf)?;
+    value.encode(buf)
+}
+
+/// Like [`encode_custom_field`], encoding `value` with [`ProtoEncode::encode_canonical`]
+#[doc(hidden)]
+#[inline]
+pub fn encode_custom_field_canonical<B: BufMut, T: ProtoEncode>(
+    tag: u32,
+    value: &T,
+    buf: &mut B,
+) -> Result<(), EncodeError> {
+    encode_custom_field_key(tag, value, buf)?;
+    value.encode_canonical(buf)
+}
+
+#[inline]
+fn encode_custom_field_key<B: BufMut, T: ProtoEncode>(tag: u32, value: &T, buf: &mut B) -> Result<(), EncodeError> {
+    if T::IS_MESSAGE {
+        let key = checked_encode_key(tag, WireType::LengthDelimited)?;
+        let (arr, len) = varint::encode(key);
+        buf.put_slice(&arr[..len]);
+        let (arr, len) = varint::encode(checked_len(value.encoded_size())?);
+        buf.put_slice(&arr[..len]);
+    } else {
+        let key = checked_encode_key(tag, WireType::Varint)?;
+        let (arr, len) = varint::encode(key);
+        buf.put_slice(&arr[..len]);
+    }
+    Ok(())
+}
+
+/// Size of a field written by [`encode_custom_field`]
+#[doc(hidden)]
+#[inline]
+pub fn custom_field_size<T: ProtoEncode>(tag: u32, value: &T) -> usize {
+    let size = value.encoded_size();
+    if T::IS_MESSAGE {
+        let key = encode_key(tag, WireType::LengthDelimited);
+        crate::helpers::get_varint_length_u32(key)
+            + crate::helpers::get_varint_length_u64(size as u64)
+            + size
+    } else {
+        let key = encode_key(tag, WireType::Varint);
+        crate::helpers::get_varint_length_u32(key) + size
+    }
+}
+
 impl ProtoEncode for u32 {
     #[inline]
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
@@ -120,11 +192,30 @@ impl ProtoEncode for f64 {
     }
 }
 
+impl<T: ProtoEncode> ProtoEncode for Box<T> {
+    const IS_MESSAGE: bool = T::IS_MESSAGE;
+
+    #[inline]
+    fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
+        (**self).encode(buf)
+    }
+
+    #[inline]
+    fn encode_canonical<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
+        (**self).encode_canonical(buf)
+    }
+
+    #[inline]
+    fn encoded_size(&self) -> usize {
+        (**self).encoded_size()
+    }
+}
+
 impl ProtoEncode for String {
     #[inline]
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
         let bytes = self.as_bytes();
-        let (arr, len) = varint::encode(bytes.len() as u32);
+        let (arr, len) = varint::encode(checked_len(bytes.len())?);
         buf.put_slice(&arr[..len]);
         buf.put_slice(bytes);
         Ok(())
@@ -133,7 +224,7 @@ impl ProtoEncode for String {
     #[inline]
     fn encoded_size(&self) -> usize {
         let len = self.len();
-        crate::helpers::get_varint_length_u32(len as u32) + len
+        crate::helpers::get_varint_length_u64(len as u64) + len
     }
 }
 
@@ -141,7 +232,7 @@ impl ProtoEncode for str {
     #[inline]
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
         let bytes = self.as_bytes();
-        let (arr, len) = varint::encode(bytes.len() as u32);
+        let (arr, len) = varint::encode(checked_len(bytes.len())?);
         buf.put_slice(&arr[..len]);
         buf.put_slice(bytes);
         Ok(())
@@ -150,14 +241,14 @@ impl ProtoEncode for str {
     #[inline]
     fn encoded_size(&self) -> usize {
         let len = self.len();
-        crate::helpers::get_varint_length_u32(len as u32) + len
+        crate::helpers::get_varint_length_u64(len as u64) + len
     }
 }
 
 impl ProtoEncode for Vec<u8> {
     #[inline]
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
-        let (arr, len) = varint::encode(self.len() as u32);
+        let (arr, len) = varint::encode(checked_len(self.len())?);
         buf.put_slice(&arr[..len]);
         buf.put_slice(self);
         Ok(())
@@ -165,14 +256,14 @@ impl ProtoEncode for Vec<u8> {
 
     #[inline]
     fn encoded_size(&self) -> usize {
-        crate::helpers::get_varint_length_u32(self.len() as u32) + self.len()
+        crate::helpers::get_varint_length_u64(self.len() as u64) + self.len()
     }
 }
 
 impl ProtoEncode for [u8] {
     #[inline]
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
-        let (arr, len) = varint::encode(self.len() as u32);
+        let (arr, len) = varint::encode(checked_len(self.len())?);
         buf.put_slice(&arr[..len]);
         buf.put_slice(self);
         Ok(())
@@ -180,14 +271,14 @@ impl ProtoEncode for [u8] {
 
     #[inline]
     fn encoded_size(&self) -> usize {
-        crate::helpers::get_varint_length_u32(self.len() as u32) + self.len()
+        crate::helpers::get_varint_length_u64(self.len() as u64) + self.len()
     }
 }
 
 impl ProtoEncode for Bytes {
     #[inline]
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
-        let (arr, len) = varint::encode(self.len() as u32);
+        let (arr, len) = varint::encode(checked_len(self.len())?);
         buf.put_slice(&arr[..len]);
         buf.put_slice(self);
         Ok(())
@@ -195,14 +286,14 @@ impl ProtoEncode for Bytes {
 
     #[inline]
     fn encoded_size(&self) -> usize {
-        crate::helpers::get_varint_length_u32(self.len() as u32) + self.len()
+        crate::helpers::get_varint_length_u64(self.len() as u64) + self.len()
     }
 }
 
 impl ProtoEncode for BytesMut {
     #[inline]
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
-        let (arr, len) = varint::encode(self.len() as u32);
+        let (arr, len) = varint::encode(checked_len(self.len())?);
         buf.put_slice(&arr[..len]);
         buf.put_slice(self);
         Ok(())
@@ -210,7 +301,7 @@ impl ProtoEncode for BytesMut {
 
     #[inline]
     fn encoded_size(&self) -> usize {
-        crate::helpers::get_varint_length_u32(self.len() as u32) + self.len()
+        crate::helpers::get_varint_length_u64(self.len() as u64) + self.len()
     }
 }
 
@@ -253,10 +344,10 @@ pub fn encode_length_delimited<B: BufMut>(
     data: &[u8],
     buf: &mut B,
 ) -> Result<(), EncodeError> {
-    let key = encode_key(tag, WireType::LengthDelimited);
+    let key = checked_encode_key(tag, WireType::LengthDelimited)?;
     let (arr_key, len_key) = varint::encode(key);
     buf.put_slice(&arr_key[..len_key]);
-    let (arr_len, len_len) = varint::encode(data.len() as u32);
+    let (arr_len, len_len) = varint::encode(checked_len(data.len())?);
     buf.put_slice(&arr_len[..len_len]);
     buf.put_slice(data);
     Ok(())
@@ -268,7 +359,7 @@ pub fn encode_varint_field<B: BufMut>(
     value: u64,
     buf: &mut B,
 ) -> Result<(), EncodeError> {
-    let key = encode_key(tag, WireType::Varint);
+    let key = checked_encode_key(tag, WireType::Varint)?;
     let (arr_key, len_key) = varint::encode(key);
     buf.put_slice(&arr_key[..len_key]);
     let (arr_val, len_val) = varint::encode(value);
@@ -282,7 +373,7 @@ pub fn encode_fixed32_field<B: BufMut>(
     value: u32,
     buf: &mut B,
 ) -> Result<(), EncodeError> {
-    let key = encode_key(tag, WireType::Fixed32);
+    let key = checked_encode_key(tag, WireType::Fixed32)?;
     let (arr, len) = varint::encode(key);
     buf.put_slice(&arr[..len]);
     buf.put_u32_le(value);
@@ -295,7 +386,7 @@ pub fn encode_fixed64_field<B: BufMut>(
     value: u64,
     buf: &mut B,
 ) -> Result<(), EncodeError> {
-    let key = encode_key(tag, WireType::Fixed64);
+    let key = checked_encode_key(tag, WireType::Fixed64)?;
     let (arr, len) = varint::encode(key);
     buf.put_slice(&arr[..len]);
     buf.put_u64_le(value);
@@ -11,6 +11,10 @@ pub enum EncodeError {
     #[error("Buffer too small")]
     BufferTooSmall,
 
+    /// A length-delimited field or a whole message is larger than a length prefix can hold
+    #[error("Message too large: {size} bytes")]
+    MessageTooLarge { size: usize },
+
     #[error("{0}")]
     Custom(String),
 }
@@ -41,6 +45,19 @@ pub enum DecodeError {
     #[error("Invalid enum value: {0}")]
     InvalidEnumValue(i32),
 
+    /// Messages are nested deeper than [`DecodeOptions::max_depth`](crate::decoding::DecodeOptions::max_depth)
+    #[error("Recursion limit exceeded")]
+    RecursionLimitExceeded,
+
+    /// A length-delimited field claims to be longer than
+    /// [`DecodeOptions::max_length_delimited`](crate::decoding::DecodeOptions::max_length_delimited)
+    #[error("Length-delimited field of {0} bytes exceeds the limit")]
+    LengthLimitExceeded(usize),
+
+    /// Input to [`json::from_str`](crate::json::from_str) is not JSON, or not the JSON of the message
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(String),
+
     #[error("Required field missing: {0}")]
     MissingField(&'static str),
 
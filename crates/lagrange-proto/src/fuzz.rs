@@ -0,0 +1,146 @@
+//! Checks run by the cargo-fuzz targets in `fuzz/`.
+//!
+//! Every check takes the raw input of the fuzzer and asserts that decoding it does not panic,
+//! and that whatever decodes successfully encodes again to bytes that decode to an equal value.
+//! A failed assertion is a finding, like a panic.
+//!
+//! ```sh
+//! cd crates/lagrange-proto
+//! cargo +nightly fuzz run roundtrip fuzz/corpus/roundtrip fuzz/seeds/roundtrip
+//! ```
+
+use crate::decoding::{skip_field, FieldReader};
+use crate::error::DecodeError;
+use crate::message::ProtoMessage;
+use crate::unknown_fields::UnknownFields;
+use crate::varint;
+use crate::wire::WireType;
+use crate::ProtoEncode;
+use arbitrary::{Arbitrary, Unstructured};
+use std::fmt::Debug;
+
+pub use arbitrary;
+
+/// Decode `data` as a `T`, then build a `T` from `data` with [`Arbitrary`] and check that it
+/// survives an encode and a decode.
+///
+/// Fields whose values are not equal to themselves, like floats holding NaN, fail the check.
+pub fn roundtrip_check<T>(data: &[u8])
+where
+    T: ProtoMessage + PartialEq + Debug + for<'a> Arbitrary<'a>,
+{
+    decode_check::<T>(data);
+
+    if let Ok(value) = T::arbitrary_take_rest(Unstructured::new(data)) {
+        let encoded = value.encode_to_vec().expect("an arbitrary message encodes");
+        assert_eq!(encoded.len(), value.encoded_size());
+        let decoded = T::decode_from_slice(&encoded).expect("an encoded message decodes");
+        assert_eq!(decoded, value);
+    }
+}
+
+/// Decode `data` as a `T`; if it decodes, its encoding must decode to an equal value
+pub fn decode_check<T>(data: &[u8])
+where
+    T: ProtoMessage + PartialEq + Debug,
+{
+    if let Ok(value) = T::decode_from_slice(data) {
+        let encoded = value.encode_to_vec().expect("a decoded message encodes");
+        let decoded = T::decode_from_slice(&encoded).expect("a re-encoded message decodes");
+        assert_eq!(decoded, value);
+    }
+}
+
+/// Walk `data` field by field with a [`FieldReader`], checking that skipping a field, reading
+/// its raw data and reading its typed value all consume the same bytes
+pub fn field_reader_check(data: &[u8]) {
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut reader = FieldReader::new(&data[pos..]);
+        let Ok((_, wire_type)) = reader.read_field_key() else {
+            return;
+        };
+        let value = reader.remaining();
+        let skipped = skip_field(wire_type, value);
+
+        let mut raw = FieldReader::new(value);
+        let read = raw.read_field_data(wire_type);
+        let mut typed = FieldReader::new(value);
+        let typed_read = match wire_type {
+            WireType::Varint => typed.read_varint().map(|_| ()),
+            WireType::Fixed32 => typed.read_fixed32().map(|_| ()),
+            WireType::Fixed64 => typed.read_fixed64().map(|_| ()),
+            WireType::LengthDelimited => typed.read_length_delimited().map(|_| ()),
+            WireType::StartGroup => typed.skip_field(wire_type),
+            WireType::EndGroup => Err(DecodeError::Custom(String::new())),
+        };
+
+        match (skipped, read, typed_read) {
+            (Ok(len), Ok(field), Ok(())) => {
+                assert_eq!(field.len(), len);
+                assert_eq!(raw.remaining().len(), value.len() - len);
+                assert_eq!(typed.remaining().len(), value.len() - len);
+                pos = data.len() - value.len() + len;
+            }
+            (Err(_), Err(_), Err(_)) => return,
+            (skipped, read, typed_read) => {
+                panic!("readers disagree on {:?}: {:?}, {:?}, {:?}", wire_type, skipped, read, typed_read)
+            }
+        }
+    }
+}
+
+/// Decode a varint as every width, and check that the value encodes to at most as many bytes
+/// and decodes back
+pub fn varint_check(data: &[u8]) {
+    if let Ok((value, len)) = varint::decode::<u64>(data) {
+        assert!(len > 0 && len <= data.len().min(varint::MAX_VARINT_LEN_U64));
+        assert_eq!(varint::decode_len::<u64>(data).ok(), Some(len));
+        let (arr, encoded_len) = varint::encode(value);
+        assert!(encoded_len <= len);
+        assert_eq!(varint::decode::<u64>(&arr[..encoded_len]).ok(), Some((value, encoded_len)));
+    }
+
+    if let Ok((value, len)) = varint::decode::<u32>(data) {
+        assert!(len > 0 && len <= data.len().min(varint::MAX_VARINT_LEN_U64));
+        let (arr, encoded_len) = varint::encode(value);
+        assert!(encoded_len <= len);
+        assert_eq!(varint::decode::<u32>(&arr[..encoded_len]).ok(), Some((value, encoded_len)));
+    }
+
+    if let Ok((value, len)) = varint::decode_zigzag::<u64>(data) {
+        let (arr, encoded_len) = varint::encode_zigzag::<u64>(value);
+        assert!(encoded_len <= len);
+        assert_eq!(varint::decode_zigzag::<u64>(&arr[..encoded_len]).ok(), Some((value, encoded_len)));
+    }
+}
+
+/// Read `data` as fields none of which are known, and build [`UnknownFields`] from `data` with
+/// [`Arbitrary`]; either must encode to bytes that read back as the same fields
+pub fn unknown_fields_check(data: &[u8]) {
+    if let Ok(fields) = read_unknown_fields(data) {
+        assert_unknown_fields_roundtrip(&fields);
+    }
+    if let Ok(fields) = UnknownFields::arbitrary_take_rest(Unstructured::new(data)) {
+        assert_unknown_fields_roundtrip(&fields);
+    }
+}
+
+fn assert_unknown_fields_roundtrip(fields: &UnknownFields) {
+    let mut encoded = Vec::with_capacity(fields.encoded_size());
+    fields.encode(&mut encoded).expect("unknown fields encode");
+    assert_eq!(encoded.len(), fields.encoded_size());
+    let read = read_unknown_fields(&encoded).expect("encoded unknown fields read back");
+    assert_eq!(&read, fields);
+}
+
+/// Every field of `buf`, the way messages that preserve unknown fields keep them
+fn read_unknown_fields(buf: &[u8]) -> Result<UnknownFields, DecodeError> {
+    let mut reader = FieldReader::new(buf);
+    let mut fields = UnknownFields::new();
+    while reader.has_remaining() {
+        let (tag, wire_type) = reader.read_field_key()?;
+        fields.add(tag, wire_type, reader.read_field_data(wire_type)?);
+    }
+    Ok(fields)
+}
@@ -41,19 +41,19 @@ pub fn get_varint_length_u64(value: u64) -> usize {
 #[inline(always)]
 pub fn count_string(s: &str) -> usize {
     let byte_len = s.len();
-    get_varint_length_u32(byte_len as u32) + byte_len
+    get_varint_length_u64(byte_len as u64) + byte_len
 }
 
 #[inline(always)]
 pub fn count_bytes(bytes: &[u8]) -> usize {
     let byte_len = bytes.len();
-    get_varint_length_u32(byte_len as u32) + byte_len
+    get_varint_length_u64(byte_len as u64) + byte_len
 }
 
 #[inline(always)]
 pub fn count_message<T: ProtoEncode>(message: &T) -> usize {
     let message_size = message.encoded_size();
-    get_varint_length_u32(message_size as u32) + message_size
+    get_varint_length_u64(message_size as u64) + message_size
 }
 
 #[inline(always)]
@@ -0,0 +1,78 @@
+//! Messages framed by their varint length over tokio's [`AsyncRead`] and [`AsyncWrite`], as
+//! exchanged with sign-provider sidecars
+
+use crate::decoding::{DecodeOptions, ProtoDecode};
+use crate::encoding::{checked_len, ProtoEncode};
+use crate::error::{DecodeError, EncodeError};
+use crate::varint;
+use bytes::{BufMut, Bytes, BytesMut};
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Write `message` after its varint length
+///
+/// The frame is written in one go but not flushed, flush `writer` when it buffers.
+pub async fn write_delimited<T, W>(writer: &mut W, message: &T) -> Result<(), EncodeError>
+where
+    T: ProtoEncode,
+    W: AsyncWrite + Unpin,
+{
+    let size = message.encoded_size();
+    let (prefix, prefix_len) = varint::encode(checked_len(size)?);
+    let mut buf = BytesMut::with_capacity(prefix_len + size);
+    buf.put_slice(&prefix[..prefix_len]);
+    message.encode(&mut buf)?;
+    writer.write_all(&buf).await?;
+    Ok(())
+}
+
+/// Read the next message written by [`write_delimited`], or `None` if the stream ended before it
+///
+/// A stream ending within a frame fails with [`DecodeError::UnexpectedEof`].
+pub async fn read_delimited<T, R>(reader: &mut R) -> Result<Option<T>, DecodeError>
+where
+    T: ProtoDecode,
+    R: AsyncRead + Unpin,
+{
+    read_delimited_with_options(reader, DecodeOptions::default()).await
+}
+
+/// Like [`read_delimited`], decoding under `options`
+///
+/// A length above [`DecodeOptions::max_length_delimited`] fails before the message is read.
+pub async fn read_delimited_with_options<T, R>(reader: &mut R, options: DecodeOptions) -> Result<Option<T>, DecodeError>
+where
+    T: ProtoDecode,
+    R: AsyncRead + Unpin,
+{
+    let mut prefix = [0u8; varint::MAX_VARINT_LEN_U32];
+    let mut prefix_len = 0;
+    loop {
+        if prefix_len == prefix.len() {
+            return Err(DecodeError::InvalidVarint);
+        }
+        if reader.read(&mut prefix[prefix_len..=prefix_len]).await? == 0 {
+            return match prefix_len {
+                0 => Ok(None),
+                _ => Err(DecodeError::UnexpectedEof),
+            };
+        }
+        prefix_len += 1;
+        if prefix[prefix_len - 1] & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let (len, _) = varint::decode::<u32>(&prefix[..prefix_len])?;
+    let len = len as usize;
+    if len > options.max_length_delimited {
+        return Err(DecodeError::LengthLimitExceeded(len));
+    }
+
+    let mut buf = vec![0; len];
+    reader.read_exact(&mut buf).await.map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => DecodeError::UnexpectedEof,
+        _ => DecodeError::Io(err),
+    })?;
+    T::decode_shared_with_options(&Bytes::from(buf), options).map(Some)
+}
@@ -0,0 +1,347 @@
+//! Proto3 JSON mapping of messages
+//!
+//! Fields are named in lowerCamelCase, `bytes` are base64, enums are their variant names, maps
+//! are objects and 64-bit integers are strings. Every field is written, including those at their
+//! default value, so a message reads back the same even when its defaults are not proto3's.
+//! Reading accepts the original field names and the alternative forms the mapping allows:
+//! numbers as strings, enums as numbers, URL-safe or unpadded base64 and `null` for defaults.
+//!
+//! With the `json` feature on, every field type of a derived message needs a [`ProtoJson`] impl,
+//! including types implementing the wire traits by hand.
+
+use crate::error::DecodeError;
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use bytes::{Bytes, BytesMut};
+use std::borrow::Cow;
+
+pub use serde_json::{Map, Value};
+
+/// Reads standard base64 with or without padding, URL-safe input is translated to it first
+const BASE64_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// A value with a proto3 JSON representation, derived along with `ProtoMessage` and `ProtoEnum`
+pub trait ProtoJson: Sized {
+    fn to_json_value(&self) -> Value;
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError>;
+
+    /// This value as the key of a map entry, which is always a string
+    fn to_json_key(&self) -> String {
+        match self.to_json_value() {
+            Value::String(key) => key,
+            value => value.to_string(),
+        }
+    }
+
+    fn from_json_key(key: &str) -> Result<Self, DecodeError> {
+        Self::from_json_value(&Value::String(key.to_string()))
+    }
+}
+
+/// A oneof, whose set variant is written as a field of the message holding it
+pub trait ProtoJsonOneof: Sized {
+    /// JSON name of the set variant's field, and its value
+    fn to_json_field(&self) -> (&'static str, Value);
+
+    /// Whether `name` is the JSON or `.proto` name of one of the variants' fields
+    fn has_json_field(name: &str) -> bool;
+
+    /// The variant whose field is named `name`, read from `value`
+    fn from_json_field(name: &str, value: &Value) -> Result<Self, DecodeError>;
+}
+
+/// `value` as a JSON document
+pub fn to_string<T: ProtoJson>(value: &T) -> String {
+    value.to_json_value().to_string()
+}
+
+/// Read a `T` from a JSON document
+pub fn from_str<T: ProtoJson>(json: &str) -> Result<T, DecodeError> {
+    let value: Value = serde_json::from_str(json).map_err(|err| DecodeError::InvalidJson(err.to_string()))?;
+    T::from_json_value(&value)
+}
+
+fn invalid(expected: &str, value: &Value) -> DecodeError {
+    DecodeError::InvalidJson(format!("expected {}, found {}", expected, value))
+}
+
+#[doc(hidden)]
+pub fn expect_object<'a>(value: &'a Value, message: &str) -> Result<&'a Map<String, Value>, DecodeError> {
+    value.as_object().ok_or_else(|| invalid(message, value))
+}
+
+#[doc(hidden)]
+pub fn unknown_field(message: &str, name: &str) -> DecodeError {
+    DecodeError::InvalidJson(format!("unknown field {:?} of {}", name, message))
+}
+
+#[doc(hidden)]
+pub fn repeated_to_json<T: ProtoJson>(items: &[T]) -> Value {
+    Value::Array(items.iter().map(ProtoJson::to_json_value).collect())
+}
+
+#[doc(hidden)]
+pub fn repeated_from_json<T: ProtoJson>(value: &Value) -> Result<Vec<T>, DecodeError> {
+    match value {
+        Value::Array(items) => items.iter().map(T::from_json_value).collect(),
+        value => Err(invalid("an array", value)),
+    }
+}
+
+#[doc(hidden)]
+pub fn map_to_json<'a, K, V>(entries: impl IntoIterator<Item = (&'a K, &'a V)>) -> Value
+where
+    K: ProtoJson + 'a,
+    V: ProtoJson + 'a,
+{
+    Value::Object(entries.into_iter().map(|(key, value)| (key.to_json_key(), value.to_json_value())).collect())
+}
+
+#[doc(hidden)]
+pub fn map_from_json<K, V, M>(value: &Value) -> Result<M, DecodeError>
+where
+    K: ProtoJson,
+    V: ProtoJson,
+    M: FromIterator<(K, V)>,
+{
+    match value {
+        Value::Object(entries) => {
+            entries.iter().map(|(key, value)| Ok((K::from_json_key(key)?, V::from_json_value(value)?))).collect()
+        }
+        value => Err(invalid("an object", value)),
+    }
+}
+
+/// An integer written as a number or a string, in exponent notation as long as it is whole
+fn integer<T: TryFrom<i128>>(value: &Value) -> Result<T, DecodeError> {
+    let whole = |float: f64| (float.fract() == 0.0 && float.abs() < 2f64.powi(127)).then_some(float as i128);
+    let parsed = match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from))
+            .or_else(|| number.as_f64().and_then(whole)),
+        Value::String(text) => text.parse::<i128>().ok().or_else(|| text.parse::<f64>().ok().and_then(whole)),
+        _ => None,
+    };
+    parsed.and_then(|parsed| T::try_from(parsed).ok()).ok_or_else(|| invalid("an integer in range", value))
+}
+
+fn float(value: &Value) -> Result<f64, DecodeError> {
+    match value {
+        Value::Number(number) => number.as_f64().ok_or_else(|| invalid("a number", value)),
+        Value::String(text) => match text.as_str() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            text => text.parse().map_err(|_| invalid("a number", value)),
+        },
+        value => Err(invalid("a number", value)),
+    }
+}
+
+fn float_to_json(value: f64) -> Value {
+    match serde_json::Number::from_f64(value) {
+        Some(number) => Value::Number(number),
+        None if value.is_nan() => Value::String("NaN".to_string()),
+        None if value > 0.0 => Value::String("Infinity".to_string()),
+        None => Value::String("-Infinity".to_string()),
+    }
+}
+
+fn bytes(value: &Value) -> Result<Vec<u8>, DecodeError> {
+    let text = value.as_str().ok_or_else(|| invalid("a base64 string", value))?;
+    let standard = text.replace('-', "+").replace('_', "/");
+    BASE64_LENIENT.decode(standard).map_err(|_| invalid("a base64 string", value))
+}
+
+/// 32-bit integers are numbers, 64-bit ones are strings so they survive JavaScript's doubles
+macro_rules! impl_integer_json {
+    ($($ty:ty => $write:expr),* $(,)?) => {
+        $(
+            impl ProtoJson for $ty {
+                fn to_json_value(&self) -> Value {
+                    $write(*self)
+                }
+
+                fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+                    integer(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_json! {
+    u32 => Value::from,
+    i32 => Value::from,
+    u64 => |value: u64| Value::String(value.to_string()),
+    i64 => |value: i64| Value::String(value.to_string()),
+}
+
+/// The wrappers only change the wire encoding, their JSON is the wrapped integer's
+macro_rules! impl_wrapper_json {
+    ($($wrapper:ident($ty:ty)),* $(,)?) => {
+        $(
+            impl ProtoJson for crate::types::$wrapper {
+                fn to_json_value(&self) -> Value {
+                    self.0.to_json_value()
+                }
+
+                fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+                    <$ty>::from_json_value(value).map(Self)
+                }
+            }
+        )*
+    };
+}
+
+impl_wrapper_json! {
+    SInt32(i32),
+    SInt64(i64),
+    Fixed32(u32),
+    Fixed64(u64),
+    SFixed32(i32),
+    SFixed64(i64),
+}
+
+impl ProtoJson for bool {
+    fn to_json_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        value.as_bool().ok_or_else(|| invalid("a boolean", value))
+    }
+
+    fn from_json_key(key: &str) -> Result<Self, DecodeError> {
+        key.parse().map_err(|_| invalid("a boolean", &Value::String(key.to_string())))
+    }
+}
+
+impl ProtoJson for f32 {
+    fn to_json_value(&self) -> Value {
+        float_to_json(*self as f64)
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        float(value).map(|value| value as f32)
+    }
+}
+
+impl ProtoJson for f64 {
+    fn to_json_value(&self) -> Value {
+        float_to_json(*self)
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        float(value)
+    }
+}
+
+impl ProtoJson for String {
+    fn to_json_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        value.as_str().map(str::to_string).ok_or_else(|| invalid("a string", value))
+    }
+}
+
+impl ProtoJson for Cow<'_, str> {
+    fn to_json_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        String::from_json_value(value).map(Cow::Owned)
+    }
+}
+
+impl ProtoJson for Vec<u8> {
+    fn to_json_value(&self) -> Value {
+        Value::String(STANDARD.encode(self))
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        bytes(value)
+    }
+}
+
+impl ProtoJson for Bytes {
+    fn to_json_value(&self) -> Value {
+        Value::String(STANDARD.encode(self))
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        bytes(value).map(Bytes::from)
+    }
+}
+
+impl ProtoJson for BytesMut {
+    fn to_json_value(&self) -> Value {
+        Value::String(STANDARD.encode(self))
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        bytes(value).map(|bytes| BytesMut::from(&bytes[..]))
+    }
+}
+
+impl<T: ProtoJson> ProtoJson for Box<T> {
+    fn to_json_value(&self) -> Value {
+        (**self).to_json_value()
+    }
+
+    fn from_json_value(value: &Value) -> Result<Self, DecodeError> {
+        T::from_json_value(value).map(Box::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(u64::MAX.to_json_value(), Value::String("18446744073709551615".to_string()));
+        assert_eq!((-5i32).to_json_value(), Value::from(-5));
+        assert_eq!(from_str::<i64>(r#""-9007199254740993""#).unwrap(), -9007199254740993);
+        assert_eq!(from_str::<u32>("1e3").unwrap(), 1000);
+        assert!(from_str::<u32>("-1").is_err());
+        assert!(from_str::<i32>("1.5").is_err());
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(f64::NAN.to_json_value(), Value::String("NaN".to_string()));
+        assert_eq!(f32::NEG_INFINITY.to_json_value(), Value::String("-Infinity".to_string()));
+        assert_eq!(from_str::<f64>(r#""Infinity""#).unwrap(), f64::INFINITY);
+        assert_eq!(from_str::<f32>("0.5").unwrap(), 0.5);
+    }
+
+    #[test]
+    fn test_bytes_base64() {
+        let bytes = Bytes::from_static(&[0xfb, 0xff, 0x01]);
+        assert_eq!(bytes.to_json_value(), Value::String("+/8B".to_string()));
+        assert_eq!(from_str::<Bytes>(r#""-_8B""#).unwrap(), bytes);
+        assert_eq!(from_str::<Vec<u8>>(r#""AQ""#).unwrap(), vec![1]);
+        assert_eq!(from_str::<Vec<u8>>(r#""AQ==""#).unwrap(), vec![1]);
+        assert!(from_str::<Vec<u8>>(r#""!!""#).is_err());
+    }
+
+    #[test]
+    fn test_map_keys() {
+        assert_eq!(true.to_json_key(), "true");
+        assert!(bool::from_json_key("false").is_ok_and(|key| !key));
+        assert_eq!(u64::from_json_key("7").unwrap(), 7);
+        assert_eq!(17u32.to_json_key(), "17");
+    }
+}
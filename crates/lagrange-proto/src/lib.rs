@@ -1,22 +1,32 @@
 pub mod decoding;
 pub mod encoding;
 pub mod error;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
 pub mod helpers;
+#[cfg(feature = "tokio")]
+pub mod io;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod message;
 pub mod types;
 pub mod unknown_fields;
 pub mod varint;
 pub mod wire;
 
-pub use decoding::ProtoDecode;
+pub use decoding::{DecodeOptions, ProtoDecode, ProtoDecodeBorrowed, StreamingDecoder};
 pub use encoding::ProtoEncode;
 pub use error::{DecodeError, EncodeError, ProtoError};
 pub use message::ProtoMessage;
 
-pub use types::{Fixed32, Fixed64, SFixed32, SFixed64, SInt32, SInt64};
+pub use types::{CowStr, Fixed32, Fixed64, SFixed32, SFixed64, SInt32, SInt64};
 
 pub use unknown_fields::{UnknownField, UnknownFields};
 
+// For the impls `#[proto(serde)]` derives
+#[doc(hidden)]
+pub use serde;
+
 #[cfg(feature = "derive")]
 pub use lagrange_proto_derive::{ProtoBuilder, ProtoEnum, ProtoMessage, ProtoOneof};
 
@@ -31,3 +41,11 @@ pub fn to_bytes<T: ProtoEncode>(value: &T) -> Result<Bytes, EncodeError> {
 pub fn from_bytes<T: ProtoDecode>(bytes: &[u8]) -> Result<T, DecodeError> {
     T::decode(bytes)
 }
+
+/// Like [`from_bytes`], with `Bytes` fields of the result sharing `bytes` instead of copying out of it
+///
+/// The shared fields keep all of `bytes` alive, copy them out of the result when they need to
+/// outlive it on their own.
+pub fn from_shared<T: ProtoDecode>(bytes: Bytes) -> Result<T, DecodeError> {
+    T::decode_shared(&bytes)
+}
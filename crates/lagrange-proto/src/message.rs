@@ -1,21 +1,28 @@
 use crate::decoding::ProtoDecode;
-use crate::encoding::ProtoEncode;
+use crate::encoding::{checked_len, ProtoEncode};
 use crate::error::{DecodeError, EncodeError};
 use bytes::{Bytes, BytesMut};
 
 pub trait ProtoMessage: ProtoEncode + ProtoDecode {
     fn encode_to_vec(&self) -> Result<Vec<u8>, EncodeError> {
-        let mut buf = BytesMut::with_capacity(self.encoded_size());
+        let mut buf = BytesMut::with_capacity(checked_len(self.encoded_size())? as usize);
         self.encode(&mut buf)?;
         Ok(buf.to_vec())
     }
 
     fn encode_to_bytes(&self) -> Result<Bytes, EncodeError> {
-        let mut buf = BytesMut::with_capacity(self.encoded_size());
+        let mut buf = BytesMut::with_capacity(checked_len(self.encoded_size())? as usize);
         self.encode(&mut buf)?;
         Ok(buf.freeze())
     }
 
+    /// Like [`ProtoMessage::encode_to_vec`], with [`ProtoEncode::encode_canonical`]
+    fn encode_canonical_to_vec(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut buf = BytesMut::with_capacity(checked_len(self.encoded_size())? as usize);
+        self.encode_canonical(&mut buf)?;
+        Ok(buf.to_vec())
+    }
+
     fn decode_from_slice(buf: &[u8]) -> Result<Self, DecodeError>
     where
         Self: Sized,
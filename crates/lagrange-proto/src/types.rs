@@ -1,10 +1,14 @@
-use crate::decoding::ProtoDecode;
+use crate::decoding::{decode_length_delimited, DecodeOptions, ProtoDecode, ProtoDecodeBorrowed};
 use crate::encoding::ProtoEncode;
 use crate::error::{DecodeError, EncodeError};
 use crate::varint;
 use bytes::BufMut;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
 pub struct SInt32(pub i32);
 
 impl From<i32> for SInt32 {
@@ -42,7 +46,9 @@ impl ProtoDecode for SInt32 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
 pub struct SInt64(pub i64);
 
 impl From<i64> for SInt64 {
@@ -80,7 +86,9 @@ impl ProtoDecode for SInt64 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
 pub struct Fixed32(pub u32);
 
 impl From<u32> for Fixed32 {
@@ -119,7 +127,9 @@ impl ProtoDecode for Fixed32 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
 pub struct Fixed64(pub u64);
 
 impl From<u64> for Fixed64 {
@@ -160,7 +170,9 @@ impl ProtoDecode for Fixed64 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
 pub struct SFixed32(pub i32);
 
 impl From<i32> for SFixed32 {
@@ -199,7 +211,9 @@ impl ProtoDecode for SFixed32 {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
+#[serde(transparent)]
 pub struct SFixed64(pub i64);
 
 impl From<i64> for SFixed64 {
@@ -240,11 +254,81 @@ impl ProtoDecode for SFixed64 {
     }
 }
 
+/// A string field that borrows from the buffer it is decoded from
+///
+/// [`ProtoDecodeBorrowed`] borrows the string when the input outlives the value, [`ProtoDecode`]
+/// copies it for input that is about to be dropped. Both reject invalid UTF-8.
+pub type CowStr<'a> = Cow<'a, str>;
+
+impl ProtoEncode for Cow<'_, str> {
+    #[inline]
+    fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
+        self.as_ref().encode(buf)
+    }
+
+    #[inline]
+    fn encoded_size(&self) -> usize {
+        self.as_ref().encoded_size()
+    }
+}
+
+impl ProtoDecode for Cow<'_, str> {
+    #[inline]
+    fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        String::decode(buf).map(Cow::Owned)
+    }
+}
+
+impl<'a> ProtoDecodeBorrowed<'a> for Cow<'a, str> {
+    #[inline]
+    fn decode_borrowed_with_options(buf: &'a [u8], _options: DecodeOptions) -> Result<Self, DecodeError> {
+        let (bytes, _) = decode_length_delimited(buf)?;
+        match std::str::from_utf8(bytes) {
+            Ok(value) => Ok(Cow::Borrowed(value)),
+            Err(_) => String::from_utf8(bytes.to_vec()).map(Cow::Owned).map_err(DecodeError::InvalidUtf8),
+        }
+    }
+}
+
+/// Conversions between the wrappers of the same value, which only differ in their wire encoding
+macro_rules! impl_reencode {
+    ($($from:ident => $to:ident),* $(,)?) => {
+        $(
+            impl From<$from> for $to {
+                fn from(value: $from) -> Self {
+                    Self(value.0.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_reencode! {
+    SInt32 => SFixed32,
+    SFixed32 => SInt32,
+    SInt64 => SFixed64,
+    SFixed64 => SInt64,
+    SInt32 => SInt64,
+    SInt32 => SFixed64,
+    SFixed32 => SFixed64,
+    SFixed32 => SInt64,
+    Fixed32 => Fixed64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use bytes::BytesMut;
 
+    #[test]
+    fn test_reencode_conversions() {
+        assert_eq!(SFixed32::from(SInt32(-7)), SFixed32(-7));
+        assert_eq!(SInt64::from(SFixed64(i64::MIN)), SInt64(i64::MIN));
+        assert_eq!(SInt64::from(SInt32(i32::MIN)), SInt64(i32::MIN as i64));
+        assert_eq!(SFixed64::from(SFixed32(-1)), SFixed64(-1));
+        assert_eq!(Fixed64::from(Fixed32(u32::MAX)), Fixed64(u32::MAX as u64));
+    }
+
     #[test]
     fn test_sint32_roundtrip() {
         let values = [i32::MIN, -1000, -1, 0, 1, 1000, i32::MAX];
@@ -8,6 +8,7 @@ pub struct UnknownField {
 
     pub wire_type: WireType,
 
+    /// Encoded value following the key; for a group, its fields and end key
     pub data: Vec<u8>,
 }
 
@@ -65,7 +66,8 @@ impl UnknownFields {
 impl ProtoEncode for UnknownFields {
     fn encode<B: BufMut>(&self, buf: &mut B) -> Result<(), EncodeError> {
         for field in &self.fields {
-            let key = crate::wire::encode_key(field.tag, field.wire_type);
+            // Tags may come from hostile input or be set by hand
+            let key = crate::wire::checked_encode_key(field.tag, field.wire_type)?;
             let (arr, len) = crate::varint::encode(key as u64);
             buf.put_slice(&arr[..len]);
 
@@ -86,6 +88,46 @@ impl ProtoEncode for UnknownFields {
     }
 }
 
+/// Fields that encode to valid wire data: a tag the key can hold, no groups, and data that
+/// is a complete value of the wire type
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for UnknownField {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let tag = u.int_in_range(crate::wire::MIN_TAG..=crate::wire::MAX_TAG)?;
+        let wire_type = *u.choose(&[
+            WireType::Varint,
+            WireType::Fixed64,
+            WireType::LengthDelimited,
+            WireType::Fixed32,
+        ])?;
+        let data = match wire_type {
+            WireType::Varint => {
+                let (arr, len) = crate::varint::encode(u.arbitrary::<u64>()?);
+                arr[..len].to_vec()
+            }
+            WireType::Fixed64 => u.bytes(8)?.to_vec(),
+            WireType::Fixed32 => u.bytes(4)?.to_vec(),
+            _ => {
+                let payload: &[u8] = u.arbitrary()?;
+                let (arr, len) = crate::varint::encode(payload.len() as u32);
+                [&arr[..len], payload].concat()
+            }
+        };
+        Ok(UnknownField { tag, wire_type, data })
+    }
+}
+
+#[cfg(feature = "fuzzing")]
+impl<'a> arbitrary::Arbitrary<'a> for UnknownFields {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(UnknownFields { fields: u.arbitrary()? })
+    }
+
+    fn arbitrary_take_rest(u: arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(UnknownFields { fields: arbitrary::Arbitrary::arbitrary_take_rest(u)? })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -122,6 +164,18 @@ mod tests {
         assert_eq!(tag2_fields[0].data, vec![0x14]);
     }
 
+    #[test]
+    fn test_unknown_fields_invalid_tag() {
+        for tag in [0, 1 << 29] {
+            let mut fields = UnknownFields::new();
+            fields.add(1, WireType::Varint, vec![0x0A]);
+            fields.add(tag, WireType::Varint, vec![0x14]);
+
+            let mut buf = Vec::new();
+            assert!(matches!(fields.encode(&mut buf), Err(EncodeError::InvalidTag(t)) if t == tag));
+        }
+    }
+
     #[test]
     fn test_unknown_fields_has() {
         let mut fields = UnknownFields::new();
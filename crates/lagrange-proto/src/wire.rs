@@ -1,6 +1,7 @@
-use crate::error::DecodeError;
+use crate::error::{DecodeError, EncodeError};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "fuzzing", derive(arbitrary::Arbitrary))]
 #[repr(u8)]
 pub enum WireType {
     Varint = 0,
@@ -67,11 +68,29 @@ impl Key {
     }
 }
 
+/// Smallest field number a key can hold
+pub const MIN_TAG: u32 = 1;
+
+/// Largest field number a key can hold, `2^29 - 1`
+pub const MAX_TAG: u32 = (1 << 29) - 1;
+
+/// Key of a field, for tags known to be in range such as the literal tags of the derive.
+/// Out of range tags produce keys peers reject or misread, see [`checked_encode_key`]
 #[inline]
 pub const fn encode_key(tag: u32, wire_type: WireType) -> u32 {
     Key::new(tag, wire_type).encode()
 }
 
+/// Key of a field, failing with [`EncodeError::InvalidTag`] for tags outside
+/// [`MIN_TAG`]`..=`[`MAX_TAG`]
+#[inline]
+pub fn checked_encode_key(tag: u32, wire_type: WireType) -> Result<u32, EncodeError> {
+    if !(MIN_TAG..=MAX_TAG).contains(&tag) {
+        return Err(EncodeError::InvalidTag(tag));
+    }
+    Ok(encode_key(tag, wire_type))
+}
+
 #[inline]
 pub fn decode_key(value: u32) -> Result<(u32, WireType), DecodeError> {
     let key = Key::decode(value)?;
@@ -121,4 +140,16 @@ mod tests {
         assert_eq!(tag, 1);
         assert_eq!(wire_type, WireType::Varint);
     }
+
+    #[test]
+    fn test_checked_encode_key() {
+        assert!(matches!(checked_encode_key(0, WireType::Varint), Err(EncodeError::InvalidTag(0))));
+        assert!(matches!(checked_encode_key(1 << 29, WireType::Varint), Err(EncodeError::InvalidTag(0x2000_0000))));
+        assert!(matches!(checked_encode_key(u32::MAX, WireType::Fixed32), Err(EncodeError::InvalidTag(u32::MAX))));
+
+        let key = checked_encode_key(MAX_TAG, WireType::Fixed32).unwrap();
+        assert_eq!(key, 0xFFFF_FFFD);
+        assert_eq!(decode_key(key).unwrap(), (MAX_TAG, WireType::Fixed32));
+        assert_eq!(checked_encode_key(MIN_TAG, WireType::Varint).unwrap(), 8);
+    }
 }
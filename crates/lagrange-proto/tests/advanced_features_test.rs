@@ -1,8 +1,8 @@
 use lagrange_proto::{
-    Fixed32, Fixed64, ProtoEncode, ProtoEnum, ProtoMessage, ProtoOneof, SFixed32, SFixed64, SInt32,
-    SInt64,
+    Fixed32, Fixed64, ProtoDecode, ProtoEncode, ProtoEnum, ProtoMessage, ProtoOneof, SFixed32, SFixed64,
+    SInt32, SInt64,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, PartialEq, ProtoEnum, Clone, Copy, Default)]
 enum Status {
@@ -291,7 +291,7 @@ fn test_mixed_types_message() {
     assert_eq!(msg, decoded);
 }
 
-#[derive(Debug, PartialEq, ProtoMessage)]
+#[derive(Debug, Clone, PartialEq, ProtoMessage)]
 struct MessageWithMaps {
     #[proto(tag = 1)]
     id: u64,
@@ -663,3 +663,281 @@ fn test_complex_oneof_enum() {
 
     assert_eq!(msg, decoded);
 }
+
+// Merging
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct MergeInner {
+    #[proto(tag = 1)]
+    id: u32,
+    #[proto(tag = 2)]
+    label: Option<String>,
+    #[proto(tag = 3)]
+    values: Vec<u32>,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct MergeOuter {
+    #[proto(tag = 1)]
+    name: String,
+    #[proto(tag = 2)]
+    count: Option<u32>,
+    #[proto(tag = 3)]
+    tags: Vec<String>,
+    #[proto(tag = 4)]
+    inner: MergeInner,
+    #[proto(tag = 5)]
+    extra: Option<MergeInner>,
+    #[proto(tag = 6)]
+    history: Vec<MergeInner>,
+}
+
+fn merge_outer(name: &str, count: Option<u32>, tags: &[&str], inner: MergeInner) -> MergeOuter {
+    MergeOuter {
+        name: name.to_string(),
+        count,
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        inner,
+        extra: None,
+        history: Vec::new(),
+    }
+}
+
+#[test]
+fn test_merge_overwrites_scalars() {
+    let mut message = merge_outer("first", Some(1), &[], MergeInner::default());
+    let later = merge_outer("second", None, &[], MergeInner::default());
+    message.merge(&later.encode_to_vec().unwrap()).unwrap();
+
+    assert_eq!(message.name, "second");
+    // Fields the later encoding leaves out keep their value
+    assert_eq!(message.count, Some(1));
+
+    let later = merge_outer("", Some(7), &[], MergeInner::default());
+    message.merge(&later.encode_to_vec().unwrap()).unwrap();
+    assert_eq!(message.count, Some(7));
+}
+
+#[test]
+fn test_merge_appends_repeated() {
+    let mut message = merge_outer("tags", None, &["a", "b"], MergeInner { values: vec![1], ..Default::default() });
+    message.history.push(MergeInner { id: 1, ..Default::default() });
+
+    let mut later = merge_outer("tags", None, &["c"], MergeInner { values: vec![2, 3], ..Default::default() });
+    later.history.push(MergeInner { id: 2, ..Default::default() });
+    message.merge(&later.encode_to_vec().unwrap()).unwrap();
+
+    assert_eq!(message.tags, vec!["a", "b", "c"]);
+    assert_eq!(message.inner.values, vec![1, 2, 3]);
+    // Repeated messages are appended, not merged with each other
+    assert_eq!(message.history.iter().map(|item| item.id).collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn test_merge_nested_messages() {
+    let inner = MergeInner { id: 1, label: Some("one".to_string()), values: vec![1] };
+    let mut message = merge_outer("nested", None, &[], inner);
+    message.extra = Some(MergeInner { id: 5, label: Some("extra".to_string()), values: vec![] });
+
+    let mut later = merge_outer("nested", None, &[], MergeInner { id: 2, label: None, values: vec![2] });
+    later.extra = Some(MergeInner { id: 6, label: None, values: vec![9] });
+    message.merge(&later.encode_to_vec().unwrap()).unwrap();
+
+    assert_eq!(message.inner, MergeInner { id: 2, label: Some("one".to_string()), values: vec![1, 2] });
+    assert_eq!(message.extra, Some(MergeInner { id: 6, label: Some("extra".to_string()), values: vec![9] }));
+}
+
+#[test]
+fn test_duplicate_nested_field_merges_within_buffer() {
+    let first = merge_outer("dup", None, &[], MergeInner { id: 1, label: Some("kept".to_string()), values: vec![1] });
+    let second = merge_outer("dup", None, &[], MergeInner { id: 2, label: None, values: vec![2] });
+
+    // Concatenated encodings are one encoding with every field written twice
+    let mut buf = first.encode_to_vec().unwrap();
+    buf.extend(second.encode_to_vec().unwrap());
+
+    let decoded = MergeOuter::decode(&buf).unwrap();
+    assert_eq!(decoded.inner, MergeInner { id: 2, label: Some("kept".to_string()), values: vec![1, 2] });
+
+    let shared = MergeOuter::decode_shared(&bytes::Bytes::from(buf)).unwrap();
+    assert_eq!(shared, decoded);
+}
+
+// Canonical encoding
+
+#[derive(Debug, Clone, PartialEq, ProtoMessage)]
+struct MessageWithSortedMaps {
+    #[proto(tag = 1)]
+    id: u64,
+    #[proto(tag = 2)]
+    string_map: BTreeMap<String, String>,
+    #[proto(tag = 3)]
+    int_map: BTreeMap<u32, u64>,
+    #[proto(tag = 4)]
+    name: String,
+}
+
+#[test]
+fn test_canonical_map_encoding() {
+    let keys: Vec<u32> = (0..64).map(|i| (i * 37) % 64).collect();
+    let build = |keys: &mut dyn Iterator<Item = &u32>| {
+        let mut message =
+            MessageWithMaps { id: 7, string_map: HashMap::new(), int_map: HashMap::new(), name: "maps".to_string() };
+        for &key in keys {
+            message.string_map.insert(format!("key{key}"), format!("value{key}"));
+            message.int_map.insert(key, key as u64 * 1000);
+        }
+        message
+    };
+    let forward = build(&mut keys.iter());
+    let backward = build(&mut keys.iter().rev());
+
+    let canonical = forward.encode_canonical_to_vec().unwrap();
+    assert_eq!(canonical, backward.encode_canonical_to_vec().unwrap());
+    assert_eq!(canonical.len(), forward.encoded_size());
+    assert_eq!(MessageWithMaps::decode_from_slice(&canonical).unwrap(), forward);
+
+    // Entries come out sorted by key, as an ordered map writes them
+    let sorted = MessageWithSortedMaps {
+        id: 7,
+        string_map: forward.string_map.clone().into_iter().collect(),
+        int_map: forward.int_map.clone().into_iter().collect(),
+        name: "maps".to_string(),
+    };
+    assert_eq!(canonical, sorted.encode_to_vec().unwrap());
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct ShuffledTags {
+    #[proto(tag = 7)]
+    tail: u32,
+    #[proto(oneof)]
+    data: Option<TestOneof>,
+    #[proto(tag = 1)]
+    head: String,
+    #[proto(tag = 3)]
+    inner: Option<MessageWithMaps>,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct OrderedTags {
+    #[proto(tag = 1)]
+    head: String,
+    #[proto(tag = 3)]
+    inner: Option<MessageWithSortedMaps>,
+    #[proto(oneof)]
+    data: Option<TestOneof>,
+    #[proto(tag = 7)]
+    tail: u32,
+}
+
+#[test]
+fn test_canonical_field_order() {
+    let int_map: HashMap<u32, u64> = (0..16).map(|key| (key, key as u64)).collect();
+    let inner = MessageWithMaps { id: 1, string_map: HashMap::new(), int_map, name: String::new() };
+    let sorted_inner = MessageWithSortedMaps {
+        id: 1,
+        string_map: BTreeMap::new(),
+        int_map: inner.int_map.clone().into_iter().collect(),
+        name: String::new(),
+    };
+
+    for data in [Some(TestOneof::Id(9)), None] {
+        let shuffled =
+            ShuffledTags { tail: 2, data: data.clone(), head: "head".to_string(), inner: Some(inner.clone()) };
+        let ordered = OrderedTags { head: "head".to_string(), inner: Some(sorted_inner.clone()), data, tail: 2 };
+
+        let canonical = shuffled.encode_canonical_to_vec().unwrap();
+        assert_eq!(canonical, ordered.encode_to_vec().unwrap());
+        assert_eq!(ShuffledTags::decode_from_slice(&canonical).unwrap(), shuffled);
+    }
+}
+
+// Proto3 JSON mapping
+
+#[test]
+fn test_json_map_roundtrip() {
+    let msg = MessageWithMaps {
+        id: 7,
+        string_map: HashMap::from([("b".to_string(), "two".to_string()), ("a".to_string(), "one".to_string())]),
+        int_map: HashMap::from([(2, 2000), (1, u64::MAX)]),
+        name: "maps".to_string(),
+    };
+
+    let json = msg.to_json();
+    assert_eq!(
+        json,
+        r#"{"id":"7","intMap":{"1":"18446744073709551615","2":"2000"},"name":"maps","stringMap":{"a":"one","b":"two"}}"#
+    );
+    assert_eq!(MessageWithMaps::from_json(&json).unwrap(), msg);
+
+    // Field names from the .proto and numbers for 64-bit integers are read too
+    let relaxed = concat!(
+        r#"{"id":7,"int_map":{"1":"18446744073709551615","2":2000},"#,
+        r#""name":"maps","string_map":{"a":"one","b":"two"}}"#,
+    );
+    assert_eq!(MessageWithMaps::from_json(relaxed).unwrap(), msg);
+}
+
+#[test]
+fn test_json_oneof_roundtrip() {
+    let variants = [Some(TestOneof::Name("alice".to_string())), Some(TestOneof::Id(42)), Some(TestOneof::Score(-3))];
+    for data in variants.into_iter().chain([None]) {
+        let msg = MessageWithOneof { version: 1, data };
+        assert_eq!(MessageWithOneof::from_json(&msg.to_json()).unwrap(), msg);
+    }
+
+    let msg = MessageWithOneof { version: 1, data: Some(TestOneof::Name("alice".to_string())) };
+    assert_eq!(msg.to_json(), r#"{"name":"alice","version":1}"#);
+    assert_eq!(MessageWithOneof { version: 1, data: None }.to_json(), r#"{"version":1}"#);
+
+    let msg = MessageWithComplexOneof { id: 789, data: Some(ComplexOneof::EnumValue(Status::Active)) };
+    assert_eq!(msg.to_json(), r#"{"enumValue":"Active","id":"789"}"#);
+    assert_eq!(MessageWithComplexOneof::from_json(&msg.to_json()).unwrap(), msg);
+
+    // A oneof variant read by its snake_case name, an enum by its value
+    let decoded = MessageWithComplexOneof::from_json(r#"{"id":"789","enum_value":1}"#).unwrap();
+    assert_eq!(decoded, msg);
+
+    let msg = MessageWithComplexOneof { id: 1, data: Some(ComplexOneof::FixedValue(Fixed64(u64::MAX))) };
+    assert_eq!(msg.to_json(), r#"{"fixedValue":"18446744073709551615","id":"1"}"#);
+    assert_eq!(MessageWithComplexOneof::from_json(&msg.to_json()).unwrap(), msg);
+}
+
+#[test]
+fn test_json_defaults() {
+    let msg = MessageWithDefaults::from_json("{}").unwrap();
+    assert_eq!(msg, MessageWithDefaults::decode_from_slice(&[]).unwrap());
+
+    // Null keeps the default too
+    let msg = MessageWithDefaults::from_json(r#"{"number":null,"optionalField":null}"#).unwrap();
+    assert_eq!(msg.number, 42);
+    assert_eq!(msg.optional_field, None);
+
+    // Fields at proto3's defaults are written, or they would read back as the message's own
+    let msg = MessageWithDefaults {
+        number: 0,
+        greeting: String::new(),
+        flag: false,
+        status: Status::Unknown,
+        optional_field: None,
+    };
+    let json = msg.to_json();
+    assert_eq!(json, r#"{"flag":false,"greeting":"","number":0,"status":"Unknown"}"#);
+    assert_eq!(MessageWithDefaults::from_json(&json).unwrap(), msg);
+}
+
+#[test]
+fn test_json_errors() {
+    use lagrange_proto::DecodeError;
+
+    let invalid = |json: &str| matches!(MessageWithDefaults::from_json(json), Err(DecodeError::InvalidJson(_)));
+    assert!(invalid("not json"));
+    assert!(invalid("[]"));
+    assert!(invalid(r#"{"unknown":1}"#));
+    assert!(invalid(r#"{"number":"forty-two"}"#));
+    assert!(invalid(r#"{"number":4294967296}"#));
+    assert!(invalid(r#"{"status":"Missing"}"#));
+    assert!(matches!(MessageWithDefaults::from_json(r#"{"status":9}"#), Err(DecodeError::InvalidEnumValue(9))));
+    assert!(matches!(MessageWithOneof::from_json(r#"{"id":"x"}"#), Err(DecodeError::InvalidJson(_))));
+}
@@ -0,0 +1,76 @@
+use bytes::Bytes;
+use lagrange_proto::io::{read_delimited, read_delimited_with_options, write_delimited};
+use lagrange_proto::{DecodeError, DecodeOptions, ProtoMessage};
+use tokio::io::AsyncWriteExt;
+
+#[derive(ProtoMessage, Debug, Clone, PartialEq, Default)]
+struct SignRequest {
+    #[proto(tag = 1)]
+    sequence: u32,
+
+    #[proto(tag = 2)]
+    command: String,
+
+    #[proto(tag = 3)]
+    body: Bytes,
+}
+
+fn request(sequence: u32, body_len: usize) -> SignRequest {
+    SignRequest { sequence, command: "wtlogin.login".to_string(), body: Bytes::from(vec![0x5a; body_len]) }
+}
+
+#[tokio::test]
+async fn test_delimited_through_duplex() {
+    let requests = vec![request(1, 3), SignRequest::default(), request(2, 300), request(3, 1000)];
+
+    // A buffer smaller than most frames, so they are written and read in parts
+    let (mut client, mut server) = tokio::io::duplex(16);
+    let sent = requests.clone();
+    let writer = tokio::spawn(async move {
+        for request in &sent {
+            write_delimited(&mut client, request).await.unwrap();
+        }
+    });
+
+    let mut received = Vec::new();
+    while let Some(request) = read_delimited::<SignRequest, _>(&mut server).await.unwrap() {
+        received.push(request);
+    }
+    writer.await.unwrap();
+    assert_eq!(received, requests);
+}
+
+#[tokio::test]
+async fn test_delimited_truncated_stream() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+    let encoded = request(1, 10).encode_to_vec().unwrap();
+    client.write_all(&[encoded.len() as u8]).await.unwrap();
+    client.write_all(&encoded[..5]).await.unwrap();
+    drop(client);
+
+    let result = read_delimited::<SignRequest, _>(&mut server).await;
+    assert!(matches!(result, Err(DecodeError::UnexpectedEof)));
+}
+
+#[tokio::test]
+async fn test_delimited_truncated_prefix() {
+    let (mut client, mut server) = tokio::io::duplex(64);
+    client.write_all(&[0x80]).await.unwrap();
+    drop(client);
+
+    let result = read_delimited::<SignRequest, _>(&mut server).await;
+    assert!(matches!(result, Err(DecodeError::UnexpectedEof)));
+}
+
+#[tokio::test]
+async fn test_delimited_max_size() {
+    let (mut client, mut server) = tokio::io::duplex(4096);
+    write_delimited(&mut client, &request(1, 16)).await.unwrap();
+    write_delimited(&mut client, &request(2, 1024)).await.unwrap();
+
+    let options = DecodeOptions::default().with_max_length_delimited(256);
+    let first = read_delimited_with_options::<SignRequest, _>(&mut server, options).await.unwrap();
+    assert_eq!(first, Some(request(1, 16)));
+    let second = read_delimited_with_options::<SignRequest, _>(&mut server, options).await;
+    assert!(matches!(second, Err(DecodeError::LengthLimitExceeded(len)) if len > 1024));
+}
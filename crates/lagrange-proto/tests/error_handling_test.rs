@@ -218,19 +218,14 @@ fn test_skip_field_length_delimited_truncated_data() {
 }
 
 #[test]
-fn test_skip_field_start_group_unsupported() {
+fn test_skip_field_start_group_unterminated() {
     use lagrange_proto::decoding::skip_field;
 
-    let data = &[1, 2, 3];
+    // Field 1 = 1, with no end-group key after it
+    let data = &[0x08, 0x01];
 
     let result = skip_field(WireType::StartGroup, data);
-    assert!(result.is_err());
-    match result {
-        Err(DecodeError::Custom(msg)) => {
-            assert!(msg.contains("Groups are not supported") || msg.contains("not supported"));
-        }
-        _ => panic!("Expected Custom error for unsupported groups"),
-    }
+    assert!(matches!(result, Err(DecodeError::UnexpectedEof)));
 }
 
 #[test]
@@ -326,15 +321,14 @@ fn test_field_reader_truncated_length_delimited() {
 }
 
 #[test]
-fn test_field_reader_read_field_data_groups_unsupported() {
+fn test_field_reader_read_field_data_group_errors() {
     use lagrange_proto::decoding::FieldReader;
 
-    let data = &[1, 2, 3];
+    let data = &[0x08, 0x01];
     let mut reader = FieldReader::new(data);
 
     let result = reader.read_field_data(WireType::StartGroup);
-    assert!(result.is_err());
-    assert!(matches!(result, Err(DecodeError::Custom(_))));
+    assert!(matches!(result, Err(DecodeError::UnexpectedEof)));
 
     let result = reader.read_field_data(WireType::EndGroup);
     assert!(result.is_err());
@@ -558,3 +552,84 @@ fn test_varint_decode_len_truncated() {
     let result = decode_len::<u32>(truncated);
     assert!(result.is_err());
 }
+
+/// A message reporting more bytes than a length prefix can hold, without holding them
+#[derive(Debug, PartialEq)]
+struct Oversized;
+
+const OVERSIZED: usize = u32::MAX as usize + 1;
+
+impl ProtoEncode for Oversized {
+    const IS_MESSAGE: bool = true;
+
+    fn encode<B: bytes::BufMut>(&self, _buf: &mut B) -> Result<(), EncodeError> {
+        panic!("an oversized message must not be written");
+    }
+
+    fn encoded_size(&self) -> usize {
+        OVERSIZED
+    }
+}
+
+impl ProtoDecode for Oversized {
+    fn decode(_buf: &[u8]) -> Result<Self, DecodeError> {
+        Ok(Oversized)
+    }
+}
+
+// Field types of derived messages need a JSON mapping as well
+impl lagrange_proto::json::ProtoJson for Oversized {
+    fn to_json_value(&self) -> lagrange_proto::json::Value {
+        lagrange_proto::json::Value::Null
+    }
+
+    fn from_json_value(_value: &lagrange_proto::json::Value) -> Result<Self, DecodeError> {
+        Ok(Oversized)
+    }
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Outer {
+    #[proto(tag = 1)]
+    id: u32,
+    #[proto(tag = 2)]
+    nested: Option<Oversized>,
+}
+
+#[test]
+fn test_oversized_nested_field_fails_to_encode() {
+    let mut buf = Vec::new();
+    let result = encoding::encode_custom_field(1, &Oversized, &mut buf);
+    assert!(matches!(result, Err(EncodeError::MessageTooLarge { size: OVERSIZED })));
+    assert!(buf.len() < 16, "the field data must not be written");
+
+    // The length prefix is counted at its full width, not truncated to u32
+    assert_eq!(encoding::custom_field_size(1, &Oversized), 1 + 5 + OVERSIZED);
+}
+
+#[test]
+fn test_oversized_message_fails_to_encode() {
+    let outer = Outer { id: 1, nested: Some(Oversized) };
+    // The total is checked before the buffer is allocated
+    assert!(matches!(outer.encode_to_vec(), Err(EncodeError::MessageTooLarge { .. })));
+    assert!(matches!(outer.encode_to_bytes(), Err(EncodeError::MessageTooLarge { .. })));
+    assert!(matches!(outer.encode(&mut Vec::new()), Err(EncodeError::MessageTooLarge { size: OVERSIZED })));
+}
+
+#[test]
+fn test_checked_len() {
+    assert_eq!(encoding::checked_len(0).unwrap(), 0);
+    assert_eq!(encoding::checked_len(u32::MAX as usize).unwrap(), u32::MAX);
+    assert!(matches!(encoding::checked_len(OVERSIZED), Err(EncodeError::MessageTooLarge { size: OVERSIZED })));
+}
+
+#[test]
+fn test_encode_field_with_out_of_range_tag() {
+    let mut buf = Vec::new();
+    assert!(matches!(encoding::encode_field(0, WireType::Varint, &1u32, &mut buf), Err(EncodeError::InvalidTag(0))));
+    assert!(matches!(encoding::encode_length_delimited(1 << 29, b"data", &mut buf), Err(EncodeError::InvalidTag(_))));
+    assert!(buf.is_empty());
+
+    encoding::encode_varint_field(wire::MAX_TAG, 1, &mut buf).unwrap();
+    assert_eq!(buf, [0xF8, 0xFF, 0xFF, 0xFF, 0x0F, 0x01]);
+}
@@ -1,9 +1,11 @@
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use lagrange_proto::decoding::FieldReader;
 use lagrange_proto::encoding::{
     encode_fixed32_field, encode_fixed64_field, encode_length_delimited, encode_varint_field,
 };
 use lagrange_proto::wire::WireType;
+use lagrange_proto::{ProtoEncode, ProtoMessage};
+use std::collections::HashMap;
 
 #[test]
 fn test_field_reader_basic_usage() {
@@ -469,3 +471,85 @@ fn test_field_reader_sequential_processing() {
 
     assert_eq!(field_count, 10);
 }
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct SharedBlob {
+    #[proto(tag = 1)]
+    name: String,
+    #[proto(tag = 2)]
+    payload: Bytes,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct SharedUpload {
+    #[proto(tag = 1)]
+    blob: Option<SharedBlob>,
+    #[proto(tag = 2)]
+    chunks: Vec<Bytes>,
+    #[proto(tag = 3)]
+    extra: HashMap<u32, Bytes>,
+    #[proto(tag = 4)]
+    digest: Option<Bytes>,
+}
+
+fn shared_upload() -> SharedUpload {
+    SharedUpload {
+        blob: Some(SharedBlob { name: "image.png".to_string(), payload: Bytes::from(vec![0x89; 4096]) }),
+        chunks: vec![Bytes::from_static(b"first"), Bytes::from_static(b"second")],
+        extra: HashMap::from([(7, Bytes::from_static(b"seven"))]),
+        digest: Some(Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef])),
+    }
+}
+
+/// Whether `part` lies within the allocation backing `whole`
+fn shares(whole: &Bytes, part: &Bytes) -> bool {
+    let range = whole.as_ptr_range();
+    range.contains(&part.as_ptr()) && part.as_ptr_range().end <= range.end
+}
+
+#[test]
+fn test_field_reader_shared_read_bytes() {
+    let mut buf = BytesMut::new();
+    encode_length_delimited(1, b"shared", &mut buf).unwrap();
+    encode_varint_field(2, 42, &mut buf).unwrap();
+    let input = buf.freeze();
+
+    let mut reader = FieldReader::new_shared(&input);
+    assert_eq!(reader.read_field_key().unwrap(), (1, WireType::LengthDelimited));
+    let shared = reader.read_bytes().unwrap();
+    assert_eq!(shared, "shared");
+    assert_eq!(shared.as_ptr(), input[2..].as_ptr());
+    assert_eq!(reader.read_field_key().unwrap(), (2, WireType::Varint));
+    assert_eq!(reader.read_varint().unwrap(), 42);
+
+    let mut reader = FieldReader::new(&input);
+    reader.read_field_key().unwrap();
+    let copied = reader.read_bytes().unwrap();
+    assert_eq!(copied, "shared");
+    assert!(!shares(&input, &copied));
+}
+
+#[test]
+fn test_from_shared_slices_input() {
+    let message = shared_upload();
+    let input = message.encode_to_bytes().unwrap();
+
+    let decoded: SharedUpload = lagrange_proto::from_shared(input.clone()).unwrap();
+    assert_eq!(decoded, message);
+    assert!(shares(&input, &decoded.blob.as_ref().unwrap().payload));
+    assert!(decoded.chunks.iter().all(|chunk| shares(&input, chunk)));
+    assert!(shares(&input, &decoded.extra[&7]));
+    assert!(shares(&input, decoded.digest.as_ref().unwrap()));
+}
+
+#[test]
+fn test_from_bytes_still_copies() {
+    let message = shared_upload();
+    let input = message.encode_to_bytes().unwrap();
+
+    let decoded: SharedUpload = lagrange_proto::from_bytes(&input).unwrap();
+    assert_eq!(decoded, message);
+    assert!(!shares(&input, &decoded.blob.as_ref().unwrap().payload));
+    assert!(!decoded.chunks.iter().any(|chunk| shares(&input, chunk)));
+    assert!(!shares(&input, decoded.digest.as_ref().unwrap()));
+}
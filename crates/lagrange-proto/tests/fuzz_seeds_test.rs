@@ -0,0 +1,198 @@
+//! Seed corpus of the cargo-fuzz targets in `fuzz/`, built from the messages of the other
+//! tests. Run with `WRITE_FUZZ_SEEDS=1` to rewrite `fuzz/seeds` after changing them.
+
+use arbitrary::Arbitrary;
+use lagrange_proto::fuzz;
+use lagrange_proto::{Fixed32, Fixed64, ProtoEncode, ProtoMessage, SFixed32, SFixed64, SInt32, SInt64};
+use std::path::Path;
+
+#[derive(Debug, PartialEq, ProtoMessage, Arbitrary)]
+struct Inner {
+    #[proto(tag = 1)]
+    value: u32,
+    #[proto(tag = 2)]
+    label: Option<String>,
+}
+
+/// Same as the message of `fuzz/fuzz_targets/roundtrip.rs`
+#[derive(Debug, PartialEq, ProtoMessage, Arbitrary)]
+struct Sample {
+    #[proto(tag = 1)]
+    id: u64,
+    #[proto(tag = 2)]
+    name: String,
+    #[proto(tag = 3)]
+    active: bool,
+    #[proto(tag = 4)]
+    value: Option<i32>,
+    #[proto(tag = 5)]
+    tags: Vec<String>,
+    #[proto(tag = 6, packed)]
+    numbers: Vec<u32>,
+    #[proto(tag = 7)]
+    scores: Vec<i64>,
+    #[proto(tag = 8)]
+    single: Option<Inner>,
+    #[proto(tag = 9)]
+    many: Vec<Inner>,
+    #[proto(tag = 10)]
+    sint32_value: SInt32,
+    #[proto(tag = 11)]
+    sint64_value: SInt64,
+    #[proto(tag = 12)]
+    fixed32_value: Fixed32,
+    #[proto(tag = 13)]
+    sfixed64_value: SFixed64,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct SimpleMessage {
+    #[proto(tag = 1)]
+    id: u32,
+    #[proto(tag = 2)]
+    name: String,
+    #[proto(tag = 3)]
+    active: bool,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct MessageWithOptional {
+    #[proto(tag = 1)]
+    id: u64,
+    #[proto(tag = 2)]
+    name: Option<String>,
+    #[proto(tag = 3)]
+    value: Option<i32>,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct MessageWithProtoTypes {
+    #[proto(tag = 1)]
+    sint32_value: SInt32,
+    #[proto(tag = 2)]
+    sint64_value: SInt64,
+    #[proto(tag = 3)]
+    fixed32_value: Fixed32,
+    #[proto(tag = 4)]
+    fixed64_value: Fixed64,
+    #[proto(tag = 5)]
+    sfixed32_value: SFixed32,
+    #[proto(tag = 6)]
+    sfixed64_value: SFixed64,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct MessageWithPackedFields {
+    #[proto(tag = 1)]
+    id: u64,
+    #[proto(tag = 2, packed)]
+    numbers: Vec<u32>,
+    #[proto(tag = 3, packed)]
+    flags: Vec<bool>,
+    #[proto(tag = 4, packed)]
+    scores: Vec<i32>,
+}
+
+/// Encoded messages, seeds of every target reading messages
+fn message_seeds() -> Vec<(&'static str, Vec<u8>)> {
+    let simple = SimpleMessage { id: 42, name: "test".to_string(), active: true };
+    let optional = MessageWithOptional { id: 12345, name: Some("optional".to_string()), value: Some(-42) };
+    let proto_types = MessageWithProtoTypes {
+        sint32_value: SInt32(-42),
+        sint64_value: SInt64(-123456789),
+        fixed32_value: Fixed32(0xDEADBEEF),
+        fixed64_value: Fixed64(0xCAFEBABEDEADBEEF),
+        sfixed32_value: SFixed32(-100),
+        sfixed64_value: SFixed64(-9876543210),
+    };
+    let packed = MessageWithPackedFields {
+        id: 1,
+        numbers: vec![1, 2, 3, 300, 70000],
+        flags: vec![true, false, true],
+        scores: vec![-1, 0, 1],
+    };
+    let sample = Sample {
+        id: u64::MAX,
+        name: "样本".to_string(),
+        active: true,
+        value: Some(0),
+        tags: vec!["tag1".to_string(), String::new()],
+        numbers: vec![0, 127, 128],
+        scores: vec![i64::MIN, -1],
+        single: Some(Inner { value: 7, label: Some("inner".to_string()) }),
+        many: vec![Inner { value: 1, label: None }, Inner { value: 2, label: None }],
+        sint32_value: SInt32(i32::MIN),
+        sint64_value: SInt64(-1),
+        fixed32_value: Fixed32(1),
+        sfixed64_value: SFixed64(i64::MAX),
+    };
+
+    vec![
+        ("empty", Vec::new()),
+        ("simple", simple.encode_to_vec().unwrap()),
+        ("optional", optional.encode_to_vec().unwrap()),
+        ("proto_types", proto_types.encode_to_vec().unwrap()),
+        ("packed", packed.encode_to_vec().unwrap()),
+        ("sample", sample.encode_to_vec().unwrap()),
+    ]
+}
+
+fn varint_seeds() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("zero", vec![0x00]),
+        ("one_byte_max", vec![0x7F]),
+        ("two_bytes", vec![0xAC, 0x02]),
+        ("u32_max", vec![0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+        ("u64_max", vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]),
+        ("non_minimal", vec![0x80, 0x80, 0x00]),
+        ("truncated", vec![0x80, 0x80]),
+    ]
+}
+
+fn seeds() -> Vec<(&'static str, &'static str, Vec<u8>)> {
+    let mut seeds = Vec::new();
+    for target in ["roundtrip", "field_reader", "unknown_fields"] {
+        seeds.extend(message_seeds().into_iter().map(|(name, data)| (target, name, data)));
+    }
+    seeds.extend(varint_seeds().into_iter().map(|(name, data)| ("varint", name, data)));
+    seeds
+}
+
+#[test]
+fn test_seed_corpus_is_current() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds");
+    let write = std::env::var_os("WRITE_FUZZ_SEEDS").is_some();
+
+    for (target, name, data) in seeds() {
+        let path = root.join(target).join(name);
+        if write {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &data).unwrap();
+        }
+        let stored = std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert_eq!(stored, data, "{} is outdated, run with WRITE_FUZZ_SEEDS=1", path.display());
+    }
+}
+
+#[test]
+fn test_checks_pass_on_seeds() {
+    for (target, _, data) in seeds() {
+        match target {
+            "roundtrip" => fuzz::roundtrip_check::<Sample>(&data),
+            "field_reader" => fuzz::field_reader_check(&data),
+            "unknown_fields" => fuzz::unknown_fields_check(&data),
+            "varint" => fuzz::varint_check(&data),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn test_arbitrary_unknown_fields_are_valid() {
+    // Any input builds fields that encode to well-formed wire data
+    for data in [&[0u8; 64][..], &[0xFF; 64], b"arbitrary unknown fields, read back field by field"] {
+        let fields = lagrange_proto::UnknownFields::arbitrary_take_rest(arbitrary::Unstructured::new(data)).unwrap();
+        fuzz::unknown_fields_check(data);
+        assert!(fields.iter().all(|field| field.tag > 0));
+    }
+}
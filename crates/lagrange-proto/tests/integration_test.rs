@@ -145,3 +145,43 @@ fn test_zigzag_encoding() {
 
     assert_eq!(msg, decoded);
 }
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Inner {
+    #[proto(tag = 1)]
+    id: u32,
+    #[proto(tag = 2)]
+    payload: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Outer {
+    #[proto(tag = 1)]
+    single: Option<Inner>,
+    #[proto(tag = 2)]
+    many: Vec<Inner>,
+    #[proto(tag = 3)]
+    required: Inner,
+}
+
+#[test]
+fn test_nested_messages_are_length_delimited() {
+    let msg = Outer {
+        single: Some(Inner { id: 1, payload: Some(vec![0xAA]) }),
+        many: vec![Inner { id: 2, payload: None }, Inner { id: 3, payload: None }],
+        required: Inner { id: 4, payload: None },
+    };
+
+    let encoded = msg.encode_to_vec().unwrap();
+    assert_eq!(
+        encoded,
+        vec![
+            0x0A, 0x05, 0x08, 0x01, 0x12, 0x01, 0xAA, // single
+            0x12, 0x02, 0x08, 0x02, // many[0]
+            0x12, 0x02, 0x08, 0x03, // many[1]
+            0x1A, 0x02, 0x08, 0x04, // required
+        ]
+    );
+    assert_eq!(msg.encoded_size(), encoded.len());
+    assert_eq!(Outer::decode_from_slice(&encoded).unwrap(), msg);
+}
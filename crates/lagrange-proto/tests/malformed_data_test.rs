@@ -397,3 +397,97 @@ fn test_decode_past_buffer_end() {
     let result = f32::decode(&buf);
     assert!(result.is_err());
 }
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Nested {
+    #[proto(tag = 1)]
+    child: Option<Box<Nested>>,
+    #[proto(tag = 2)]
+    depth: u32,
+}
+
+/// `levels` messages nested in each other, written out field by field
+fn nested_buffer(levels: usize) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for _ in 0..levels {
+        let mut outer = vec![0x0a];
+        let mut len = buf.len();
+        while len >= 0x80 {
+            outer.push((len as u8 & 0x7f) | 0x80);
+            len >>= 7;
+        }
+        outer.push(len as u8);
+        outer.extend_from_slice(&buf);
+        buf = outer;
+    }
+    buf
+}
+
+fn nesting(message: &Nested) -> usize {
+    message.child.as_deref().map_or(0, |child| nesting(child) + 1)
+}
+
+#[test]
+fn test_deep_nesting_hits_recursion_limit() {
+    let buf = nested_buffer(1000);
+    assert!(matches!(Nested::decode(&buf), Err(DecodeError::RecursionLimitExceeded)));
+    assert!(matches!(
+        Nested::decode_shared(&bytes::Bytes::from(buf)),
+        Err(DecodeError::RecursionLimitExceeded)
+    ));
+
+    let buf = nested_buffer(DecodeOptions::DEFAULT_MAX_DEPTH);
+    assert_eq!(nesting(&Nested::decode(&buf).unwrap()), DecodeOptions::DEFAULT_MAX_DEPTH);
+    let buf = nested_buffer(DecodeOptions::DEFAULT_MAX_DEPTH + 1);
+    assert!(matches!(Nested::decode(&buf), Err(DecodeError::RecursionLimitExceeded)));
+}
+
+#[test]
+fn test_decode_with_custom_depth() {
+    let options = DecodeOptions::default().with_max_depth(10);
+    let buf = nested_buffer(10);
+    assert_eq!(nesting(&Nested::decode_with_options(&buf, options).unwrap()), 10);
+    let buf = nested_buffer(11);
+    assert!(matches!(Nested::decode_with_options(&buf, options), Err(DecodeError::RecursionLimitExceeded)));
+
+    let flat = DecodeOptions::default().with_max_depth(0);
+    assert_eq!(Nested::decode_with_options(&[0x10, 0x05], flat).unwrap().depth, 5);
+    assert!(Nested::decode_with_options(&nested_buffer(1), flat).is_err());
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Labelled {
+    #[proto(tag = 1)]
+    label: String,
+    #[proto(tag = 2)]
+    payload: bytes::Bytes,
+}
+
+#[test]
+fn test_oversized_length_prefix_rejected() {
+    // Field 1 claiming 2 GiB, followed by nothing
+    let buf = [0x0a, 0x80, 0x80, 0x80, 0x80, 0x08];
+    assert!(matches!(Labelled::decode(&buf), Err(DecodeError::LengthLimitExceeded(0x8000_0000))));
+
+    let mut reader = decoding::FieldReader::new(&buf);
+    reader.read_field_key().unwrap();
+    assert!(matches!(reader.read_length_delimited(), Err(DecodeError::LengthLimitExceeded(0x8000_0000))));
+}
+
+#[test]
+fn test_custom_length_limit() {
+    let message = Labelled { label: "label".to_string(), payload: bytes::Bytes::from(vec![0u8; 64]) };
+    let buf = message.encode_to_vec().unwrap();
+    assert_eq!(Labelled::decode(&buf).unwrap(), message);
+
+    let options = DecodeOptions::default().with_max_length_delimited(16);
+    assert!(matches!(Labelled::decode_with_options(&buf, options), Err(DecodeError::LengthLimitExceeded(64))));
+    let options = DecodeOptions::default().with_max_length_delimited(64);
+    assert_eq!(Labelled::decode_with_options(&buf, options).unwrap(), message);
+
+    // Fields are checked even when skipped
+    let options = DecodeOptions::default().with_max_length_delimited(4);
+    let mut reader = decoding::FieldReader::new_with_limits(&buf, options);
+    let (_, wire_type) = reader.read_field_key().unwrap();
+    assert!(matches!(reader.skip_field(wire_type), Err(DecodeError::LengthLimitExceeded(5))));
+}
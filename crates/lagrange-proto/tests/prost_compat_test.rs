@@ -0,0 +1,466 @@
+//! Wire compatibility with prost, the reference implementation.
+//!
+//! Every lagrange-proto message here has a prost mirror, written the way prost-build generates
+//! it, and must encode to the same bytes and decode what the mirror encodes. Deviations from
+//! protobuf that are kept on purpose have a test of their own:
+//!
+//! - `i32` and `i64` fields are zigzag encoded, they are `sint32` and `sint64` on the wire.
+//!   There is no type for `int32` and `int64`, use `u32` and `u64` for non-negative values.
+//! - Repeated scalars are not packed unless marked `packed`, as in proto2; prost packs them
+//!   unless marked `packed = "false"`, as in proto3. Both accept either on decode.
+//! - Fields that are not `Option` are always written, even when they hold their default value,
+//!   as proto2 does for required fields; prost omits them, as proto3 does. Both read a missing
+//!   field as its default.
+
+use lagrange_proto::{
+    Fixed32, Fixed64, ProtoEncode, ProtoEnum, ProtoMessage, ProtoOneof, SFixed32, SFixed64, SInt32, SInt64,
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// Both messages encode to the same bytes, and each decodes what the other encodes
+fn assert_compatible<L, P>(lagrange: &L, prost: &P)
+where
+    L: ProtoMessage + PartialEq + Debug,
+    P: prost::Message + Default + PartialEq,
+{
+    let lagrange_bytes = lagrange.encode_to_vec().unwrap();
+    let prost_bytes = prost::Message::encode_to_vec(prost);
+    assert_eq!(lagrange_bytes, prost_bytes);
+    assert_eq!(lagrange.encoded_size(), prost.encoded_len());
+    assert_cross_decode(lagrange, prost);
+}
+
+/// Each message decodes what the other encodes, for messages whose field order may differ
+fn assert_cross_decode<L, P>(lagrange: &L, prost: &P)
+where
+    L: ProtoMessage + PartialEq + Debug,
+    P: prost::Message + Default + PartialEq,
+{
+    let lagrange_bytes = lagrange.encode_to_vec().unwrap();
+    let prost_bytes = prost::Message::encode_to_vec(prost);
+    assert_eq!(&P::decode(lagrange_bytes.as_slice()).unwrap(), prost);
+    assert_eq!(&L::decode_from_slice(&prost_bytes).unwrap(), lagrange);
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Scalars {
+    #[proto(tag = 1)]
+    id: u32,
+    #[proto(tag = 2)]
+    big: u64,
+    #[proto(tag = 3)]
+    active: bool,
+    #[proto(tag = 4)]
+    name: String,
+    #[proto(tag = 5)]
+    payload: Bytes,
+    #[proto(tag = 6)]
+    ratio: f32,
+    #[proto(tag = 7)]
+    precise: f64,
+    #[proto(tag = 8)]
+    maybe: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstScalars {
+    #[prost(uint32, tag = "1")]
+    id: u32,
+    #[prost(uint64, tag = "2")]
+    big: u64,
+    #[prost(bool, tag = "3")]
+    active: bool,
+    #[prost(string, tag = "4")]
+    name: String,
+    #[prost(bytes = "bytes", tag = "5")]
+    payload: Bytes,
+    #[prost(float, tag = "6")]
+    ratio: f32,
+    #[prost(double, tag = "7")]
+    precise: f64,
+    #[prost(uint32, optional, tag = "8")]
+    maybe: Option<u32>,
+}
+
+#[test]
+fn test_scalars() {
+    let lagrange = Scalars {
+        id: 42,
+        big: u64::MAX,
+        active: true,
+        name: "名字".to_string(),
+        payload: Bytes::from_static(&[0x00, 0xFF]),
+        ratio: 1.5,
+        precise: -0.25,
+        maybe: Some(0),
+    };
+    let prost = ProstScalars {
+        id: 42,
+        big: u64::MAX,
+        active: true,
+        name: "名字".to_string(),
+        payload: Bytes::from_static(&[0x00, 0xFF]),
+        ratio: 1.5,
+        precise: -0.25,
+        maybe: Some(0),
+    };
+    assert_compatible(&lagrange, &prost);
+}
+
+#[test]
+fn test_default_scalars_are_written() {
+    let lagrange = Scalars {
+        id: 0,
+        big: 0,
+        active: false,
+        name: String::new(),
+        payload: Bytes::new(),
+        ratio: 0.0,
+        precise: 0.0,
+        maybe: None,
+    };
+    assert_cross_decode(&lagrange, &ProstScalars::default());
+    assert!(prost::Message::encode_to_vec(&ProstScalars::default()).is_empty());
+
+    let expected = [
+        0x08, 0x00, 0x10, 0x00, 0x18, 0x00, 0x22, 0x00, 0x2A, 0x00, 0x35, 0x00, 0x00, 0x00, 0x00, 0x39, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    assert_eq!(lagrange.encode_to_vec().unwrap(), expected);
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Numbers {
+    #[proto(tag = 1)]
+    plain32: i32,
+    #[proto(tag = 2)]
+    plain64: i64,
+    #[proto(tag = 3)]
+    sint32: SInt32,
+    #[proto(tag = 4)]
+    sint64: SInt64,
+    #[proto(tag = 5)]
+    fixed32: Fixed32,
+    #[proto(tag = 6)]
+    fixed64: Fixed64,
+    #[proto(tag = 7)]
+    sfixed32: SFixed32,
+    #[proto(tag = 8)]
+    sfixed64: SFixed64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstNumbers {
+    // `i32` and `i64` are zigzag encoded, see the module docs
+    #[prost(sint32, tag = "1")]
+    plain32: i32,
+    #[prost(sint64, tag = "2")]
+    plain64: i64,
+    #[prost(sint32, tag = "3")]
+    sint32: i32,
+    #[prost(sint64, tag = "4")]
+    sint64: i64,
+    #[prost(fixed32, tag = "5")]
+    fixed32: u32,
+    #[prost(fixed64, tag = "6")]
+    fixed64: u64,
+    #[prost(sfixed32, tag = "7")]
+    sfixed32: i32,
+    #[prost(sfixed64, tag = "8")]
+    sfixed64: i64,
+}
+
+#[test]
+fn test_signed_and_fixed() {
+    for (small, large) in [(-1, -1), (i32::MIN, i64::MIN), (i32::MAX, i64::MAX), (300, -300)] {
+        let lagrange = Numbers {
+            plain32: small,
+            plain64: large,
+            sint32: small.into(),
+            sint64: large.into(),
+            fixed32: (small as u32).into(),
+            fixed64: (large as u64).into(),
+            sfixed32: small.into(),
+            sfixed64: large.into(),
+        };
+        let prost = ProstNumbers {
+            plain32: small,
+            plain64: large,
+            sint32: small,
+            sint64: large,
+            fixed32: small as u32,
+            fixed64: large as u64,
+            sfixed32: small,
+            sfixed64: large,
+        };
+        assert_compatible(&lagrange, &prost);
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstInt32 {
+    #[prost(int32, tag = "1")]
+    value: i32,
+    #[prost(int64, tag = "2")]
+    wide: i64,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Plain {
+    #[proto(tag = 1)]
+    value: i32,
+    #[proto(tag = 2)]
+    wide: i64,
+}
+
+#[test]
+fn test_plain_signed_integers_are_zigzag() {
+    // Deviation: protobuf int32 -1 is ten 0xFF-ish bytes, lagrange-proto writes sint32 -1
+    let lagrange = Plain { value: -1, wide: -1 }.encode_to_vec().unwrap();
+    let prost = prost::Message::encode_to_vec(&ProstInt32 { value: -1, wide: -1 });
+    assert_eq!(lagrange, [0x08, 0x01, 0x10, 0x01]);
+    assert_eq!(prost.len(), 22);
+    assert_ne!(lagrange, prost);
+
+    // The same bytes mean another value to an int32 reader
+    let misread = <ProstInt32 as prost::Message>::decode(lagrange.as_slice()).unwrap();
+    assert_eq!((misread.value, misread.wide), (1, 1));
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Repeated {
+    #[proto(tag = 1, packed)]
+    packed_numbers: Vec<u32>,
+    #[proto(tag = 2, packed)]
+    packed_flags: Vec<bool>,
+    #[proto(tag = 3, packed)]
+    packed_signed: Vec<i32>,
+    #[proto(tag = 4)]
+    numbers: Vec<u64>,
+    #[proto(tag = 5)]
+    names: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstRepeated {
+    #[prost(uint32, repeated, tag = "1")]
+    packed_numbers: Vec<u32>,
+    #[prost(bool, repeated, tag = "2")]
+    packed_flags: Vec<bool>,
+    #[prost(sint32, repeated, tag = "3")]
+    packed_signed: Vec<i32>,
+    #[prost(uint64, repeated, packed = "false", tag = "4")]
+    numbers: Vec<u64>,
+    #[prost(string, repeated, tag = "5")]
+    names: Vec<String>,
+}
+
+#[test]
+fn test_repeated() {
+    let lagrange = Repeated {
+        packed_numbers: vec![1, 300, u32::MAX],
+        packed_flags: vec![true, false],
+        packed_signed: vec![-1, 0, 1],
+        numbers: vec![0, 128],
+        names: vec!["a".to_string(), String::new()],
+    };
+    let prost = ProstRepeated {
+        packed_numbers: vec![1, 300, u32::MAX],
+        packed_flags: vec![true, false],
+        packed_signed: vec![-1, 0, 1],
+        numbers: vec![0, 128],
+        names: vec!["a".to_string(), String::new()],
+    };
+    assert_compatible(&lagrange, &prost);
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstPackedNumbers {
+    #[prost(uint64, repeated, tag = "4")]
+    numbers: Vec<u64>,
+}
+
+#[test]
+fn test_unpacked_fields_accept_packed() {
+    // Deviation: unmarked repeated scalars are written unpacked, packed ones are still read
+    let prost = prost::Message::encode_to_vec(&ProstPackedNumbers { numbers: vec![1, 2, 3] });
+    assert_eq!(prost, [0x22, 0x03, 0x01, 0x02, 0x03]);
+    let lagrange = Repeated::decode_from_slice(&prost).unwrap();
+    assert_eq!(lagrange.numbers, [1, 2, 3]);
+    assert_eq!(lagrange.encode_to_vec().unwrap(), [0x20, 0x01, 0x20, 0x02, 0x20, 0x03]);
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Maps {
+    #[proto(tag = 1)]
+    strings: HashMap<String, String>,
+    #[proto(tag = 2)]
+    numbers: HashMap<u32, u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstMaps {
+    #[prost(map = "string, string", tag = "1")]
+    strings: HashMap<String, String>,
+    #[prost(map = "uint32, uint64", tag = "2")]
+    numbers: HashMap<u32, u64>,
+}
+
+#[test]
+fn test_maps() {
+    let strings = HashMap::from([("key".to_string(), "value".to_string())]);
+    let numbers = HashMap::from([(7, 70)]);
+    let lagrange = Maps { strings: strings.clone(), numbers: numbers.clone() };
+    let prost = ProstMaps { strings, numbers };
+    assert_compatible(&lagrange, &prost);
+
+    // Entries are written in the iteration order of the map
+    let strings: HashMap<_, _> = (0..16).map(|i| (i.to_string(), "v".repeat(i))).collect();
+    let numbers: HashMap<_, _> = (0..16).map(|i| (i, u64::from(i) << 40)).collect();
+    let lagrange = Maps { strings: strings.clone(), numbers: numbers.clone() };
+    assert_cross_decode(&lagrange, &ProstMaps { strings, numbers });
+}
+
+#[test]
+fn test_map_entries_with_default_values() {
+    let strings = HashMap::from([(String::new(), String::new())]);
+    let numbers = HashMap::from([(0, 0)]);
+    let lagrange = Maps { strings: strings.clone(), numbers: numbers.clone() };
+    assert_cross_decode(&lagrange, &ProstMaps { strings, numbers });
+}
+
+#[derive(Debug, PartialEq, Clone, ProtoOneof)]
+enum Choice {
+    #[proto(tag = 2)]
+    Name(String),
+    #[proto(tag = 3)]
+    Id(u32),
+    #[proto(tag = 4)]
+    Score(i32),
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct WithOneof {
+    #[proto(tag = 1)]
+    version: u32,
+    #[proto(oneof)]
+    choice: Option<Choice>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum ProstChoice {
+    #[prost(string, tag = "2")]
+    Name(String),
+    #[prost(uint32, tag = "3")]
+    Id(u32),
+    #[prost(sint32, tag = "4")]
+    Score(i32),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstWithOneof {
+    #[prost(uint32, tag = "1")]
+    version: u32,
+    #[prost(oneof = "ProstChoice", tags = "2, 3, 4")]
+    choice: Option<ProstChoice>,
+}
+
+#[test]
+fn test_oneof() {
+    let cases = [
+        (Some(Choice::Name("alice".to_string())), Some(ProstChoice::Name("alice".to_string()))),
+        (Some(Choice::Id(0)), Some(ProstChoice::Id(0))),
+        (Some(Choice::Score(-5)), Some(ProstChoice::Score(-5))),
+        (None, None),
+    ];
+    for (choice, prost_choice) in cases {
+        let lagrange = WithOneof { version: 1, choice };
+        let prost = ProstWithOneof { version: 1, choice: prost_choice };
+        assert_compatible(&lagrange, &prost);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ProtoEnum)]
+enum Status {
+    #[default]
+    #[proto(value = 0)]
+    Unknown,
+    #[proto(value = 1)]
+    Active,
+    #[proto(value = 2)]
+    Inactive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, prost::Enumeration)]
+#[repr(i32)]
+enum ProstStatus {
+    Unknown = 0,
+    Active = 1,
+    Inactive = 2,
+}
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Inner {
+    #[proto(tag = 1)]
+    id: u32,
+    #[proto(tag = 2)]
+    label: Option<String>,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct Nested {
+    #[proto(tag = 1)]
+    single: Option<Inner>,
+    #[proto(tag = 2)]
+    many: Vec<Inner>,
+    #[proto(tag = 3)]
+    required: Inner,
+    #[proto(tag = 4)]
+    status: Status,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstInner {
+    #[prost(uint32, tag = "1")]
+    id: u32,
+    #[prost(string, optional, tag = "2")]
+    label: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct ProstNested {
+    #[prost(message, optional, tag = "1")]
+    single: Option<ProstInner>,
+    #[prost(message, repeated, tag = "2")]
+    many: Vec<ProstInner>,
+    #[prost(message, optional, tag = "3")]
+    required: Option<ProstInner>,
+    #[prost(enumeration = "ProstStatus", tag = "4")]
+    status: i32,
+}
+
+#[test]
+fn test_nested_messages_and_enums() {
+    let lagrange = Nested {
+        single: Some(Inner { id: 1, label: Some("one".to_string()) }),
+        many: vec![Inner { id: 2, label: None }, Inner { id: 3, label: None }],
+        required: Inner { id: 4, label: None },
+        status: Status::Inactive,
+    };
+    let prost = ProstNested {
+        single: Some(ProstInner { id: 1, label: Some("one".to_string()) }),
+        many: vec![ProstInner { id: 2, label: None }, ProstInner { id: 3, label: None }],
+        required: Some(ProstInner { id: 4, label: None }),
+        status: ProstStatus::Inactive as i32,
+    };
+    assert_compatible(&lagrange, &prost);
+
+    // A default message is written with its fields, prost writes it empty
+    let lagrange =
+        Nested { single: None, many: vec![Inner::default()], required: Inner::default(), status: Status::Unknown };
+    let prost =
+        ProstNested { many: vec![ProstInner::default()], required: Some(ProstInner::default()), ..Default::default() };
+    assert_cross_decode(&lagrange, &prost);
+}
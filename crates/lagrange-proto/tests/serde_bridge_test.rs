@@ -0,0 +1,144 @@
+use bytes::Bytes;
+use lagrange_proto::{
+    CowStr, Fixed64, ProtoEncode, ProtoEnum, ProtoMessage, ProtoOneof, SFixed32, SInt32, SInt64,
+};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, ProtoEnum)]
+#[proto(serde)]
+enum Role {
+    #[default]
+    #[proto(value = 0)]
+    Member,
+    #[proto(value = 1)]
+    Admin,
+    #[proto(value = 2)]
+    Owner,
+}
+
+#[derive(Debug, Clone, PartialEq, ProtoOneof)]
+#[proto(serde)]
+enum Contact {
+    #[proto(tag = 10)]
+    Email(String),
+    #[proto(tag = 11)]
+    PhoneNumber(Fixed64),
+}
+
+#[derive(Debug, Clone, PartialEq, Default, ProtoMessage)]
+#[proto(serde)]
+struct Profile {
+    #[proto(tag = 1)]
+    nickname: String,
+    #[proto(tag = 2)]
+    avatar: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq, ProtoMessage)]
+#[proto(serde)]
+struct Member {
+    #[proto(tag = 1)]
+    uin: u64,
+    #[proto(tag = 2)]
+    offset: SInt32,
+    #[proto(tag = 3)]
+    balance: SInt64,
+    #[proto(tag = 4)]
+    level: SFixed32,
+    #[proto(tag = 5, default = "Member")]
+    role: Role,
+    #[proto(tag = 6)]
+    profile: Option<Profile>,
+    #[proto(tag = 7)]
+    tags: Vec<String>,
+    #[proto(tag = 8)]
+    titles: HashMap<u32, String>,
+    #[proto(tag = 9)]
+    groups: BTreeMap<String, Profile>,
+    #[proto(oneof)]
+    contact: Option<Contact>,
+    #[proto(tag = 12, default = "30")]
+    timeout: u32,
+    #[proto(tag = 13)]
+    r#type: Option<u32>,
+}
+
+fn member() -> Member {
+    Member {
+        uin: 10001,
+        offset: SInt32(-8),
+        balance: SInt64(i64::MIN),
+        level: SFixed32(-3),
+        role: Role::Admin,
+        profile: Some(Profile { nickname: "alice".to_string(), avatar: Bytes::from_static(&[0, 1, 255]) }),
+        tags: vec!["early".to_string(), "active".to_string()],
+        titles: HashMap::from([(1, "first".to_string()), (2, "second".to_string())]),
+        groups: BTreeMap::from([("home".to_string(), Profile::default())]),
+        contact: Some(Contact::PhoneNumber(Fixed64(13800138000))),
+        timeout: 60,
+        r#type: Some(4),
+    }
+}
+
+#[test]
+fn test_serde_json_roundtrip() {
+    let member = member();
+    let json = serde_json::to_string(&member).unwrap();
+    let decoded: Member = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, member);
+}
+
+#[test]
+fn test_serde_representation() {
+    let value = serde_json::to_value(member()).unwrap();
+
+    // Fields are named as in the .proto, and the wrappers are their plain values
+    assert_eq!(value["uin"], 10001);
+    assert_eq!(value["offset"], -8);
+    assert_eq!(value["balance"], i64::MIN);
+    assert_eq!(value["level"], -3);
+    assert_eq!(value["role"], "Admin");
+    assert_eq!(value["profile"]["avatar"], serde_json::json!([0, 1, 255]));
+    assert_eq!(value["titles"]["2"], "second");
+    assert_eq!(value["contact"], serde_json::json!({ "phone_number": 13800138000u64 }));
+    assert_eq!(value["type"], 4);
+}
+
+#[test]
+fn test_serde_missing_fields_keep_defaults() {
+    let member: Member = serde_json::from_str(r#"{"uin": 7, "role": "Owner"}"#).unwrap();
+    assert_eq!(member.uin, 7);
+    assert_eq!(member.role, Role::Owner);
+    assert_eq!(member.timeout, 30);
+    assert_eq!(member.profile, None);
+    assert_eq!(member.contact, None);
+    assert!(member.tags.is_empty());
+
+    // Unset optional fields are left out rather than written as null
+    let json = serde_json::to_value(&member).unwrap();
+    assert!(json.get("profile").is_none());
+    assert!(json.get("contact").is_none());
+    assert_eq!(serde_json::from_value::<Member>(json).unwrap(), member);
+}
+
+#[test]
+fn test_serde_rejects_unknown_fields() {
+    assert!(serde_json::from_str::<Member>(r#"{"uin": 7, "nick": "bob"}"#).is_err());
+    assert!(serde_json::from_str::<Member>(r#"{"role": "Guest"}"#).is_err());
+    assert!(serde_json::from_str::<Member>(r#"{"contact": {"fax": "1"}}"#).is_err());
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+#[proto(serde)]
+struct Borrowed<'a> {
+    #[proto(tag = 1)]
+    name: CowStr<'a>,
+}
+
+#[test]
+fn test_serde_message_with_lifetime() {
+    let message = Borrowed { name: CowStr::Borrowed("bob") };
+    let json = serde_json::to_string(&message).unwrap();
+    assert_eq!(json, r#"{"name":"bob"}"#);
+    assert_eq!(serde_json::from_str::<Borrowed>(&json).unwrap(), message);
+}
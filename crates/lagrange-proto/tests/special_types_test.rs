@@ -1,6 +1,8 @@
 use bytes::BytesMut;
 use lagrange_proto::*;
-use lagrange_proto::{Fixed32, Fixed64, SFixed32, SFixed64, SInt32, SInt64};
+use lagrange_proto::{CowStr, Fixed32, Fixed64, SFixed32, SFixed64, SInt32, SInt64};
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 // SInt32 Tests
 
@@ -597,3 +599,108 @@ fn test_type_copy() {
     let copied = fixed;
     assert_eq!(fixed, copied);
 }
+
+// CowStr Tests
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct CowNick<'a> {
+    #[proto(tag = 1)]
+    uid: CowStr<'a>,
+    #[proto(tag = 2)]
+    nickname: Option<Cow<'a, str>>,
+}
+
+#[derive(Debug, PartialEq, ProtoMessage)]
+struct CowFriends<'a> {
+    #[proto(tag = 1)]
+    owner: CowStr<'a>,
+    #[proto(tag = 2)]
+    friends: Vec<CowNick<'a>>,
+    #[proto(tag = 3)]
+    tags: Vec<CowStr<'a>>,
+    #[proto(tag = 4)]
+    remarks: HashMap<CowStr<'a>, CowStr<'a>>,
+    #[proto(tag = 5)]
+    level: u32,
+}
+
+fn cow_friends() -> CowFriends<'static> {
+    CowFriends {
+        owner: Cow::Borrowed("u_owner"),
+        friends: vec![
+            CowNick { uid: Cow::Borrowed("u_alice"), nickname: Some(Cow::Borrowed("alice")) },
+            CowNick { uid: Cow::Owned("u_bob".to_string()), nickname: None },
+        ],
+        tags: vec![Cow::Borrowed("work"), Cow::Borrowed("")],
+        remarks: HashMap::from([(Cow::Borrowed("u_alice"), Cow::Borrowed("ali"))]),
+        level: 64,
+    }
+}
+
+// Asks about the `Cow` itself, which `&str` would hide
+#[allow(clippy::ptr_arg)]
+fn is_borrowed(value: &Cow<'_, str>) -> bool {
+    matches!(value, Cow::Borrowed(_))
+}
+
+#[test]
+fn test_cow_str_borrowed_roundtrip() {
+    let message = cow_friends();
+    let bytes = message.encode_to_vec().unwrap();
+
+    let decoded = CowFriends::decode_borrowed(&bytes).unwrap();
+    assert_eq!(decoded, message);
+    assert!(is_borrowed(&decoded.owner));
+    assert!(decoded.friends.iter().all(|friend| is_borrowed(&friend.uid)));
+    assert!(is_borrowed(decoded.friends[0].nickname.as_ref().unwrap()));
+    assert!(decoded.tags.iter().all(is_borrowed));
+    assert!(decoded.remarks.iter().all(|(key, value)| is_borrowed(key) && is_borrowed(value)));
+
+    // The strings point into the input instead of copies of it
+    let range = bytes.as_ptr_range();
+    assert!(range.contains(&decoded.owner.as_ptr()));
+}
+
+#[test]
+fn test_cow_str_owned_roundtrip() {
+    let message = cow_friends();
+
+    // Decoding from a buffer dropped right after, as `ProtoDecode` allows
+    let decoded: CowFriends<'static> = {
+        let bytes = message.encode_to_vec().unwrap();
+        CowFriends::decode_from_slice(&bytes).unwrap()
+    };
+    assert_eq!(decoded, message);
+    assert!(!is_borrowed(&decoded.owner));
+    assert!(decoded.friends.iter().all(|friend| !is_borrowed(&friend.uid)));
+    assert!(decoded.tags.iter().all(|tag| !is_borrowed(tag)));
+}
+
+#[test]
+fn test_cow_str_matches_string_encoding() {
+    #[derive(Debug, PartialEq, ProtoMessage)]
+    struct Owned {
+        #[proto(tag = 1)]
+        uid: String,
+        #[proto(tag = 2)]
+        nickname: Option<String>,
+    }
+
+    let owned = Owned { uid: "u_alice".to_string(), nickname: Some("alice".to_string()) };
+    let cow = CowNick { uid: Cow::Borrowed("u_alice"), nickname: Some(Cow::Borrowed("alice")) };
+    assert_eq!(cow.encode_to_vec().unwrap(), owned.encode_to_vec().unwrap());
+    assert_eq!(cow.encoded_size(), owned.encoded_size());
+
+    let mut buf = BytesMut::new();
+    CowStr::Borrowed("ünïcode").encode(&mut buf).unwrap();
+    assert_eq!(String::decode(&buf).unwrap(), "ünïcode");
+    assert!(is_borrowed(&CowStr::decode_borrowed(&buf).unwrap()));
+    assert!(!is_borrowed(&CowStr::decode(&buf).unwrap()));
+}
+
+#[test]
+fn test_cow_str_rejects_invalid_utf8() {
+    let invalid = [0x0a, 0x02, 0xc3, 0x28];
+    assert!(matches!(CowNick::decode_borrowed(&invalid), Err(DecodeError::InvalidUtf8(_))));
+    assert!(matches!(CowNick::decode_from_slice(&invalid), Err(DecodeError::InvalidUtf8(_))));
+}
@@ -0,0 +1,93 @@
+use bytes::Bytes;
+use lagrange_proto::{varint, DecodeError, DecodeOptions, ProtoMessage, StreamingDecoder};
+
+#[derive(ProtoMessage, Debug, Clone, PartialEq, Default)]
+struct Frame {
+    #[proto(tag = 1)]
+    sequence: u32,
+
+    #[proto(tag = 2)]
+    command: String,
+
+    #[proto(tag = 3)]
+    body: Bytes,
+}
+
+fn frame(sequence: u32, body: &[u8]) -> Frame {
+    Frame { sequence, command: "trpc.msg.olpush".to_string(), body: Bytes::copy_from_slice(body) }
+}
+
+/// `message` encoded after its varint length
+fn prefixed(message: &Frame) -> Vec<u8> {
+    let encoded = message.encode_to_vec().unwrap();
+    let (prefix, prefix_len) = varint::encode(encoded.len() as u32);
+    [&prefix[..prefix_len], &encoded[..]].concat()
+}
+
+#[test]
+fn test_streaming_one_byte_at_a_time() {
+    // A body long enough for a two-byte prefix
+    let message = frame(1, &[0xab; 200]);
+    let data = prefixed(&message);
+    assert!(data[0] & 0x80 != 0);
+
+    let mut decoder = StreamingDecoder::<Frame>::new();
+    let (last, head) = data.split_last().unwrap();
+    for byte in head {
+        assert_eq!(decoder.push(&[*byte]).unwrap(), None);
+    }
+    assert_eq!(decoder.push(&[*last]).unwrap(), Some(message));
+    assert_eq!(decoder.buffered(), 0);
+}
+
+#[test]
+fn test_streaming_several_messages_per_chunk() {
+    let first = frame(1, b"first");
+    let second = frame(2, b"second");
+    let third = frame(3, b"");
+    let data = [prefixed(&first), prefixed(&second), prefixed(&third)].concat();
+
+    // Split inside the third message
+    let (chunk, rest) = data.split_at(data.len() - 3);
+    let mut decoder = StreamingDecoder::<Frame>::default();
+    assert_eq!(decoder.push(chunk).unwrap(), Some(first));
+    assert_eq!(decoder.next_message().unwrap(), Some(second));
+    assert_eq!(decoder.next_message().unwrap(), None);
+    assert_eq!(decoder.push(rest).unwrap(), Some(third));
+    assert_eq!(decoder.next_message().unwrap(), None);
+}
+
+#[test]
+fn test_streaming_empty_message() {
+    let mut decoder = StreamingDecoder::<Frame>::new();
+    assert_eq!(decoder.push(&[0x00]).unwrap(), Some(Frame::default()));
+    assert_eq!(decoder.buffered(), 0);
+}
+
+#[test]
+fn test_streaming_oversized_prefix() {
+    let options = DecodeOptions::default().with_max_length_delimited(64);
+    let mut decoder = StreamingDecoder::<Frame>::new().with_options(options);
+
+    // A 128 byte message is rejected once its prefix is read, before its bytes arrive
+    assert_eq!(decoder.push(&[0x80]).unwrap(), None);
+    assert!(matches!(decoder.push(&[0x01]), Err(DecodeError::LengthLimitExceeded(128))));
+}
+
+#[test]
+fn test_streaming_invalid_prefix() {
+    let mut decoder = StreamingDecoder::<Frame>::new();
+    assert_eq!(decoder.push(&[0xff; 4]).unwrap(), None);
+    assert!(matches!(decoder.push(&[0xff]), Err(DecodeError::InvalidVarint)));
+}
+
+#[test]
+fn test_streaming_continues_after_invalid_message() {
+    let message = frame(7, b"after");
+    // A one byte message holding only the key of a truncated varint field
+    let data = [&[0x01, 0x08][..], &prefixed(&message)].concat();
+
+    let mut decoder = StreamingDecoder::<Frame>::new();
+    assert!(decoder.push(&data).is_err());
+    assert_eq!(decoder.next_message().unwrap(), Some(message));
+}
@@ -271,3 +271,56 @@ fn test_message_without_preserve() {
 
     assert!(re_encoded.len() < encoded.len());
 }
+
+// A MessageV1 (id = 7, name = "x") followed by group 5, which holds field 1 = 42 and a nested
+// group 2 with field 1 = "hi"
+const WITH_GROUP: &[u8] = &[
+    0x08, 0x07, 0x12, 0x01, b'x', 0x2b, 0x08, 0x2a, 0x13, 0x0a, 0x02, b'h', b'i', 0x14, 0x2c,
+];
+
+#[test]
+fn test_skip_group_field() {
+    let decoded = MessageWithoutPreserve::decode(WITH_GROUP).unwrap();
+    assert_eq!(decoded, MessageWithoutPreserve { id: 7, name: "x".to_string() });
+}
+
+#[test]
+fn test_preserve_group_field() {
+    let decoded = MessageV1::decode(WITH_GROUP).unwrap();
+    assert_eq!(decoded.id, 7);
+    assert_eq!(decoded.name, "x");
+
+    let fields: Vec<_> = decoded._unknown_fields.iter().collect();
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0].tag, 5);
+    assert_eq!(fields[0].wire_type, lagrange_proto::wire::WireType::StartGroup);
+    assert_eq!(fields[0].data, &WITH_GROUP[6..]);
+
+    assert_eq!(decoded.encoded_size(), WITH_GROUP.len());
+    assert_eq!(decoded.encode_to_vec().unwrap(), WITH_GROUP);
+}
+
+#[test]
+fn test_group_end_tag_mismatch() {
+    // Group 5 closed by the end key of group 6
+    let data = &[0x08, 0x07, 0x2b, 0x08, 0x01, 0x34];
+    assert!(matches!(MessageV1::decode(data), Err(lagrange_proto::DecodeError::InvalidTag(6))));
+    assert!(MessageWithoutPreserve::decode(data).is_err());
+
+    // End of group 5 without its start
+    assert!(MessageV1::decode(&[0x08, 0x07, 0x2c]).is_err());
+}
+
+#[test]
+fn test_group_nesting_limit() {
+    use lagrange_proto::DecodeOptions;
+
+    // Groups 3 to 5 nested in each other
+    let data = &[0x1b, 0x23, 0x2b, 0x2c, 0x24, 0x1c];
+    let options = DecodeOptions::default().with_max_depth(3);
+    assert!(MessageWithoutPreserve::decode_with_options(data, options).is_ok());
+
+    let options = DecodeOptions::default().with_max_depth(2);
+    let result = MessageWithoutPreserve::decode_with_options(data, options);
+    assert!(matches!(result, Err(lagrange_proto::DecodeError::RecursionLimitExceeded)));
+}
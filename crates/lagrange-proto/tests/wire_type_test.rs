@@ -0,0 +1,113 @@
+use bytes::Bytes;
+use lagrange_proto::{ProtoEncode, ProtoMessage};
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Attachment {
+    #[proto(tag = 1)]
+    data: Bytes,
+    #[proto(tag = 2)]
+    thumbnail: Option<Vec<u8>>,
+    #[proto(tag = 3)]
+    digest: Option<Bytes>,
+}
+
+#[test]
+fn test_byte_fields_are_length_delimited() {
+    let msg = Attachment {
+        data: Bytes::from_static(&[0x01, 0x02]),
+        thumbnail: Some(vec![0x04, 0x05, 0x06]),
+        digest: Some(Bytes::new()),
+    };
+
+    let encoded = msg.encode_to_vec().unwrap();
+    assert_eq!(
+        encoded,
+        vec![
+            0x0A, 0x02, 0x01, 0x02, // data
+            0x12, 0x03, 0x04, 0x05, 0x06, // thumbnail
+            0x1A, 0x00, // digest
+        ]
+    );
+    assert_eq!(msg.encoded_size(), encoded.len());
+    assert_eq!(Attachment::decode_from_slice(&encoded).unwrap(), msg);
+}
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Blob {
+    #[proto(tag = 1)]
+    data: Vec<u8>,
+    #[proto(tag = 2)]
+    chunks: Vec<Vec<u8>>,
+}
+
+#[test]
+fn test_vec_u8_is_a_single_bytes_field() {
+    let msg = Blob { data: vec![0x01, 0x02, 0x03], chunks: vec![vec![0x04], vec![]] };
+
+    let encoded = msg.encode_to_vec().unwrap();
+    assert_eq!(
+        encoded,
+        vec![
+            0x0A, 0x03, 0x01, 0x02, 0x03, // data
+            0x12, 0x01, 0x04, // chunks[0]
+            0x12, 0x00, // chunks[1]
+        ]
+    );
+    assert_eq!(msg.encoded_size(), encoded.len());
+    assert_eq!(Blob::decode_from_slice(&encoded).unwrap(), msg);
+    assert_eq!(Blob::default().encode_to_vec().unwrap(), vec![0x0A, 0x00]);
+}
+
+#[test]
+fn test_unset_byte_fields() {
+    let encoded = Attachment::default().encode_to_vec().unwrap();
+    assert_eq!(encoded, vec![0x0A, 0x00]);
+    assert_eq!(Attachment::decode_from_slice(&encoded).unwrap(), Attachment::default());
+}
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Empty {}
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Element {
+    #[proto(tag = 1)]
+    attachment: Option<Attachment>,
+    #[proto(tag = 2)]
+    marker: Empty,
+}
+
+#[derive(Debug, Default, PartialEq, ProtoMessage)]
+struct Body {
+    #[proto(tag = 1)]
+    elements: Vec<Element>,
+    #[proto(tag = 2)]
+    sequence: u32,
+}
+
+#[test]
+fn test_messages_nested_two_levels() {
+    let msg = Body {
+        elements: vec![
+            Element {
+                attachment: Some(Attachment { data: Bytes::from_static(&[0xFF]), ..Default::default() }),
+                marker: Empty {},
+            },
+            Element::default(),
+        ],
+        sequence: 7,
+    };
+
+    let encoded = msg.encode_to_vec().unwrap();
+    assert_eq!(
+        encoded,
+        vec![
+            0x0A, 0x07, // elements[0]
+            0x0A, 0x03, 0x0A, 0x01, 0xFF, // attachment
+            0x12, 0x00, // marker, empty but still written
+            0x0A, 0x02, 0x12, 0x00, // elements[1], only its marker
+            0x10, 0x07, // sequence
+        ]
+    );
+    assert_eq!(msg.encoded_size(), encoded.len());
+    assert_eq!(Body::decode_from_slice(&encoded).unwrap(), msg);
+}